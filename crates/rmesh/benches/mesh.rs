@@ -0,0 +1,89 @@
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+
+use rmesh::creation::create_box;
+use rmesh::exchange::{Compression, MeshFormat, PackOptions, load_mesh, pack_mesh, unpack_mesh};
+use rmesh::mesh::Trimesh;
+
+/// Create a fresh mesh with an empty cache so every
+/// iteration measures the computation rather than a cache hit.
+fn fresh(mesh: &Trimesh) -> Trimesh {
+    Trimesh::new(mesh.vertices().to_vec(), mesh.faces().to_vec(), None, None).unwrap()
+}
+
+fn bench_cached(c: &mut Criterion) {
+    let fuze = load_mesh(
+        include_bytes!("../../../test/data/fuze.obj"),
+        MeshFormat::OBJ,
+    )
+    .unwrap();
+    let cube = create_box(&[1.0, 1.0, 1.0]);
+
+    for (name, mesh) in [("box", &cube), ("fuze", &fuze)] {
+        c.bench_function(&format!("{name}/edges"), |b| {
+            b.iter_batched(
+                || fresh(mesh),
+                |m| black_box(m.edges()),
+                BatchSize::SmallInput,
+            )
+        });
+        c.bench_function(&format!("{name}/face_adjacency"), |b| {
+            b.iter_batched(
+                || fresh(mesh),
+                |m| black_box(m.face_adjacency()),
+                BatchSize::SmallInput,
+            )
+        });
+        c.bench_function(&format!("{name}/face_normals"), |b| {
+            b.iter_batched(
+                || fresh(mesh),
+                |m| black_box(m.face_normals()),
+                BatchSize::SmallInput,
+            )
+        });
+        c.bench_function(&format!("{name}/face_adjacency_angles"), |b| {
+            b.iter_batched(
+                || fresh(mesh),
+                |m| black_box(m.face_adjacency_angles()),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+}
+
+fn bench_packed(c: &mut Criterion) {
+    let fuze = load_mesh(
+        include_bytes!("../../../test/data/fuze.obj"),
+        MeshFormat::OBJ,
+    )
+    .unwrap();
+
+    for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+        for quantize in [None, Some(1e-4)] {
+            let options = PackOptions {
+                quantize,
+                compression,
+            };
+            let packed = pack_mesh(&fuze, &options).unwrap();
+            let name = format!(
+                "fuze/pack_{compression:?}_{}",
+                if quantize.is_some() {
+                    "quantized"
+                } else {
+                    "exact"
+                }
+            );
+            // the size is what this is trading speed for
+            println!("{name}: {} bytes", packed.len());
+            c.bench_function(&name, |b| {
+                b.iter(|| black_box(pack_mesh(&fuze, &options).unwrap()))
+            });
+            c.bench_function(&name.replace("pack", "unpack"), |b| {
+                b.iter(|| black_box(unpack_mesh(&packed).unwrap()))
+            });
+        }
+    }
+}
+
+criterion_group!(benches, bench_cached, bench_packed);
+criterion_main!(benches);
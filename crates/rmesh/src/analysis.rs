@@ -0,0 +1,383 @@
+use std::f64::consts::{PI, TAU};
+
+use ahash::AHashMap;
+use nalgebra::{Unit, Vector3};
+use rayon::prelude::*;
+use rmesh_macro::cache_access;
+
+use crate::mesh::Trimesh;
+
+impl Trimesh {
+    /// The draft angle of every face relative to a mold pull direction,
+    /// which is the angle between the face and the pull direction. Faces
+    /// facing along the pull direction have a positive draft, vertical
+    /// walls have zero draft and faces facing away from the pull
+    /// direction have a negative draft.
+    ///
+    /// Parameters
+    /// ------------
+    /// pull
+    ///   The direction the part is pulled out of the mold.
+    ///
+    /// Returns
+    /// ------------
+    /// angles
+    ///   The draft angle of each face in radians between -PI/2 and PI/2.
+    pub fn draft_angles(&self, pull: &Unit<Vector3<f64>>) -> Vec<f64> {
+        self.face_normals()
+            .par_iter()
+            .map(|normal| normal.dot(pull).clamp(-1.0, 1.0).asin())
+            .collect()
+    }
+
+    /// A mask of faces that would prevent the part from being pulled from
+    /// a single-sided mold, which is every face with less than the
+    /// required draft angle. Degenerate faces have zero draft.
+    ///
+    /// Parameters
+    /// ------------
+    /// pull
+    ///   The direction the part is pulled out of the mold.
+    /// min_draft
+    ///   The minimum acceptable draft angle in radians, often
+    ///   between 0.5 and 3.0 degrees depending on the material.
+    ///
+    /// Returns
+    /// ------------
+    /// undercut
+    ///   If each face has insufficient draft.
+    pub fn undercut_faces(&self, pull: &Unit<Vector3<f64>>, min_draft: f64) -> Vec<bool> {
+        self.draft_angles(pull)
+            .par_iter()
+            .map(|angle| *angle < min_draft)
+            .collect()
+    }
+
+    /// The area of the mesh projected onto a plane perpendicular to a
+    /// direction, computed as the summed projected area of every face
+    /// that faces along the direction. This is exact for closed meshes
+    /// whose projection doesn't overlap itself (i.e. convex meshes) and
+    /// otherwise counts overlapping regions once per layer.
+    ///
+    /// Parameters
+    /// ------------
+    /// direction
+    ///   The direction the mesh is being viewed from.
+    ///
+    /// Returns
+    /// ------------
+    /// area
+    ///   The projected area of the front-facing faces.
+    pub fn projected_area(&self, direction: &Unit<Vector3<f64>>) -> f64 {
+        self.faces_cross()
+            .par_iter()
+            .map(|cross| (cross.dot(direction) / 2.0).max(0.0))
+            .sum()
+    }
+
+    /// Find the silhouette edges of the mesh when viewed along a direction,
+    /// which are edges shared between a front-facing and a back-facing face
+    /// or boundary edges of a front-facing face.
+    ///
+    /// Parameters
+    /// ------------
+    /// direction
+    ///   The direction the mesh is being viewed from.
+    ///
+    /// Returns
+    /// ------------
+    /// edges
+    ///   The silhouette edges as pairs of vertex indices.
+    pub fn silhouette_edges(&self, direction: &Unit<Vector3<f64>>) -> Vec<[usize; 2]> {
+        let front: Vec<bool> = self
+            .faces_cross()
+            .par_iter()
+            .map(|cross| cross.dot(direction) > 0.0)
+            .collect();
+
+        // collect which faces reference each sorted edge in a stable order
+        let mut edge_faces: AHashMap<[usize; 2], Vec<usize>> = AHashMap::new();
+        let mut order = Vec::new();
+        for (i, edge) in self.edges().iter().enumerate() {
+            let key = [edge[0].min(edge[1]), edge[0].max(edge[1])];
+            edge_faces
+                .entry(key)
+                .or_insert_with(|| {
+                    order.push(key);
+                    Vec::new()
+                })
+                .push(i / 3);
+        }
+
+        order
+            .into_iter()
+            .filter(|key| {
+                let faces = &edge_faces[key];
+                match faces.as_slice() {
+                    [face] => front[*face],
+                    [a, b] => front[*a] != front[*b],
+                    // a non-manifold edge is on the silhouette if it
+                    // borders both front and back facing faces
+                    _ => faces.iter().any(|f| front[*f]) && faces.iter().any(|f| !front[*f]),
+                }
+            })
+            .collect()
+    }
+
+    /// The total length of the silhouette edges projected onto the
+    /// plane perpendicular to the view direction.
+    ///
+    /// Parameters
+    /// ------------
+    /// direction
+    ///   The direction the mesh is being viewed from.
+    ///
+    /// Returns
+    /// ------------
+    /// length
+    ///   The projected length of the silhouette.
+    pub fn silhouette_length(&self, direction: &Unit<Vector3<f64>>) -> f64 {
+        self.silhouette_edges(direction)
+            .iter()
+            .map(|[a, b]| {
+                let vector = self.vertices[*b] - self.vertices[*a];
+                (vector - direction.into_inner() * vector.dot(direction)).norm()
+            })
+            .sum()
+    }
+
+    /// The interior angle at each corner of every face in radians,
+    /// in the same order as the vertices of the face.
+    #[cache_access]
+    pub fn face_angles(&self) -> Vec<Vector3<f64>> {
+        self.par_triangles()
+            .map(|[a, b, c]| {
+                Vector3::new(
+                    (b - a).angle(&(c - a)),
+                    (c - b).angle(&(a - b)),
+                    (a - c).angle(&(b - c)),
+                )
+            })
+            .collect()
+    }
+
+    /// The angle defect at every vertex, which is the discrete Gaussian
+    /// curvature: a full turn minus the sum of the face angles around the
+    /// vertex. Vertices on the boundary of an open mesh subtract from a
+    /// half turn instead so that a flat boundary has no defect. Convex
+    /// corners are positive, saddles are negative and flat regions are
+    /// zero, as are vertices which aren't referenced by any face.
+    ///
+    /// Returns
+    /// ------------
+    /// defects
+    ///   The angle defect of each vertex in radians.
+    #[cache_access]
+    pub fn vertex_defects(&self) -> Vec<f64> {
+        let mut angles = vec![0.0; self.vertices.len()];
+        let mut referenced = vec![false; self.vertices.len()];
+        for (face, angle) in self.faces.iter().zip(self.face_angles().iter()) {
+            for (index, value) in [face.0, face.1, face.2].into_iter().zip(angle.iter()) {
+                angles[index] += value;
+                referenced[index] = true;
+            }
+        }
+
+        // a vertex is on the boundary if any edge touching it is only used once
+        let mut counts: AHashMap<[usize; 2], usize> = AHashMap::new();
+        for [a, b] in self.edges() {
+            *counts.entry([a.min(b), a.max(b)]).or_default() += 1;
+        }
+        let mut boundary = vec![false; self.vertices.len()];
+        for ([a, b], count) in counts {
+            if count == 1 {
+                boundary[a] = true;
+                boundary[b] = true;
+            }
+        }
+
+        angles
+            .iter()
+            .zip(referenced.iter().zip(boundary.iter()))
+            .map(
+                |(angle, (referenced, boundary))| match (referenced, boundary) {
+                    (false, _) => 0.0,
+                    (true, true) => PI - angle,
+                    (true, false) => TAU - angle,
+                },
+            )
+            .collect()
+    }
+
+    /// Is every edge of the mesh shared by exactly two faces, so the
+    /// surface is closed without holes or non-manifold edges. A mesh
+    /// without faces isn't watertight.
+    pub fn is_watertight(&self) -> bool {
+        if self.faces.is_empty() {
+            return false;
+        }
+        let mut counts: AHashMap<[usize; 2], usize> = AHashMap::new();
+        for [a, b] in self.edges() {
+            *counts.entry([a.min(b), a.max(b)]).or_default() += 1;
+        }
+        counts.values().all(|count| *count == 2)
+    }
+
+    /// Is every edge shared by two faces traversed in opposite directions
+    /// by them, which is required for the face normals to all point to
+    /// the same side of the surface.
+    pub fn is_winding_consistent(&self) -> bool {
+        // each directed edge may only appear once
+        let mut directed: AHashMap<[usize; 2], usize> = AHashMap::new();
+        for edge in self.edges() {
+            *directed.entry(edge).or_default() += 1;
+        }
+        directed.values().all(|count| *count == 1)
+    }
+
+    /// Find corner-like vertices whose angle defect exceeds a threshold,
+    /// which are useful as landmarks for registration or as vertices
+    /// that decimation should avoid moving.
+    ///
+    /// Parameters
+    /// ------------
+    /// angle_defect_threshold
+    ///   The minimum absolute angle defect in radians, so both sharp
+    ///   convex corners and sharp saddles are returned.
+    ///
+    /// Returns
+    /// ------------
+    /// vertices
+    ///   The indices of the feature vertices in ascending order.
+    pub fn feature_vertices(&self, angle_defect_threshold: f64) -> Vec<usize> {
+        self.vertex_defects()
+            .iter()
+            .enumerate()
+            .filter(|(_, defect)| defect.abs() > angle_defect_threshold)
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn test_draft_angles() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        let pull = Vector3::z_axis();
+        let angles = mesh.draft_angles(&pull);
+        assert_eq!(angles.len(), mesh.faces.len());
+
+        let normals = mesh.face_normals();
+        for (angle, normal) in angles.iter().zip(normals.iter()) {
+            if normal.z > 0.5 {
+                assert_relative_eq!(*angle, FRAC_PI_2, epsilon = 1e-10);
+            } else if normal.z < -0.5 {
+                assert_relative_eq!(*angle, -FRAC_PI_2, epsilon = 1e-10);
+            } else {
+                assert_relative_eq!(*angle, 0.0, epsilon = 1e-10);
+            }
+        }
+
+        // the walls and the bottom don't have enough draft
+        let undercut = mesh.undercut_faces(&pull, 1.0f64.to_radians());
+        assert_eq!(undercut.iter().filter(|u| **u).count(), 10);
+        // with zero required draft the walls are acceptable
+        let undercut = mesh.undercut_faces(&pull, -1e-6);
+        assert_eq!(undercut.iter().filter(|u| **u).count(), 2);
+
+        assert!(Trimesh::default().draft_angles(&pull).is_empty());
+    }
+
+    #[test]
+    fn test_projected_area() {
+        let mesh = create_box(&[1.0, 2.0, 3.0]);
+        assert_relative_eq!(
+            mesh.projected_area(&Vector3::z_axis()),
+            2.0,
+            epsilon = 1e-10
+        );
+        assert_relative_eq!(
+            mesh.projected_area(&Vector3::x_axis()),
+            6.0,
+            epsilon = 1e-10
+        );
+        assert_relative_eq!(
+            mesh.projected_area(&-Vector3::y_axis()),
+            3.0,
+            epsilon = 1e-10
+        );
+
+        // the silhouette along Z is the 1x2 rectangle around the box
+        let edges = mesh.silhouette_edges(&Vector3::z_axis());
+        assert_eq!(edges.len(), 4);
+        assert_relative_eq!(
+            mesh.silhouette_length(&Vector3::z_axis()),
+            6.0,
+            epsilon = 1e-10
+        );
+
+        let empty = Trimesh::default();
+        assert_eq!(empty.projected_area(&Vector3::z_axis()), 0.0);
+        assert!(empty.silhouette_edges(&Vector3::z_axis()).is_empty());
+    }
+
+    #[test]
+    fn test_feature_vertices() {
+        // every corner of a box is a right-angle corner
+        let mesh = create_box(&[1.0, 2.0, 3.0]);
+        let defects = mesh.vertex_defects();
+        for defect in defects.iter() {
+            assert_relative_eq!(*defect, FRAC_PI_2, epsilon = 1e-10);
+        }
+        // by Gauss-Bonnet a closed genus-0 mesh has a total defect of 4 PI
+        assert_relative_eq!(defects.iter().sum::<f64>(), 4.0 * PI, epsilon = 1e-10);
+        assert_eq!(mesh.feature_vertices(0.1), (0..8).collect::<Vec<_>>());
+        assert!(mesh.feature_vertices(2.0).is_empty());
+
+        // a flat square split through a center vertex only has
+        // features at the four corners of its boundary
+        let mesh = Trimesh::from_slice(
+            &[
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.5, 0.5, 0.0, 0.5,
+                0.0, 0.0,
+            ],
+            &[0, 5, 4, 5, 1, 4, 1, 2, 4, 2, 3, 4, 3, 0, 4],
+        )
+        .unwrap();
+        assert_eq!(mesh.feature_vertices(0.1), vec![0, 1, 2, 3]);
+        assert_relative_eq!(mesh.vertex_defects()[4], 0.0, epsilon = 1e-10);
+        assert_relative_eq!(mesh.vertex_defects()[5], 0.0, epsilon = 1e-10);
+
+        assert!(Trimesh::default().feature_vertices(0.1).is_empty());
+    }
+
+    #[test]
+    fn test_watertight() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        assert!(mesh.is_watertight());
+        assert!(mesh.is_winding_consistent());
+
+        // removing a face opens a hole but keeps the winding
+        let open = mesh
+            .submesh(&(1..mesh.faces.len()).collect::<Vec<_>>())
+            .unwrap();
+        assert!(!open.is_watertight());
+        assert!(open.is_winding_consistent());
+
+        // flipping a face keeps it closed but breaks the winding
+        let mut faces = mesh.faces.clone();
+        faces[0] = (faces[0].0, faces[0].2, faces[0].1);
+        let flipped = Trimesh::new(mesh.vertices.clone(), faces, None, None).unwrap();
+        assert!(flipped.is_watertight());
+        assert!(!flipped.is_winding_consistent());
+
+        assert!(!Trimesh::default().is_watertight());
+    }
+}
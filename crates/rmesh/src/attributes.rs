@@ -1,15 +1,24 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use anyhow::{Result, anyhow};
 use image::DynamicImage;
 use nalgebra::{Vector2, Vector3, Vector4};
 
 use crate::exchange::MeshFormat;
+use crate::mesh::Trimesh;
+use crate::scene::Unit;
+use crate::util::{join_attributes, join_groupings};
 
 pub type UV = Vec<Vector2<f64>>;
 pub type MaterialIndices = Vec<usize>;
 pub type GroupingIndices = Vec<usize>;
 pub type Color = Vec<Vector4<u8>>;
 pub type Normal = Vec<Vector3<f64>>;
+// the index of each group and the contiguous range of elements in it
+pub type GroupRanges = Vec<(usize, Range<usize>)>;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 
 pub enum GroupingKind {
     #[default]
@@ -19,19 +28,723 @@ pub enum GroupingKind {
     SmoothingIndex,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Grouping {
     pub name: String,
     pub kind: GroupingKind,
+    // the index of the group for every face or vertex
     pub indices: Vec<usize>,
+    // the name of each group by index if they have names, like
+    // the solids of an STL, and otherwise empty
+    pub labels: Vec<String>,
 }
 
-#[derive(Debug, Clone, Default)]
+impl Grouping {
+    /// Concatenate the indices of a grouping from several sets of
+    /// elements. Labeled groups are matched by label so a group with
+    /// the same name on both sides ends up as one group, while the
+    /// indices of unlabeled groupings are kept as they are.
+    ///
+    /// Parameters
+    /// ------------
+    /// parts
+    ///   The indices and labels of each set of elements in order.
+    ///
+    /// Returns
+    /// ------------
+    /// indices
+    ///   The group of every element of every part.
+    /// labels
+    ///   The labels of the groups, empty if no part had any.
+    pub fn join(parts: &[(&[usize], &[String])]) -> (Vec<usize>, Vec<String>) {
+        let mut labels: Vec<String> = Vec::new();
+        let mut indices = Vec::with_capacity(parts.iter().map(|(i, _)| i.len()).sum());
+        for (values, names) in parts.iter() {
+            let map: Vec<usize> = names
+                .iter()
+                .map(|name| match labels.iter().position(|l| l == name) {
+                    Some(index) => index,
+                    None => {
+                        labels.push(name.clone());
+                        labels.len() - 1
+                    }
+                })
+                .collect();
+            indices.extend(values.iter().map(|i| map.get(*i).copied().unwrap_or(*i)));
+        }
+        (indices, labels)
+    }
+}
+
+/// Which elements of a mesh an attribute has a value for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeDomain {
+    Vertex,
+    Face,
+}
+
+/// The kinds of attribute stored in `Attributes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeKind {
+    UV,
+    Normal,
+    Color,
+    Grouping,
+}
+
+/// The values of a single attribute of any kind.
+#[derive(Debug, Clone)]
+pub enum AttributeData {
+    UV(UV),
+    Normal(Normal),
+    Color(Color),
+    Grouping(Grouping),
+}
+
+impl AttributeData {
+    pub fn kind(&self) -> AttributeKind {
+        match self {
+            AttributeData::UV(_) => AttributeKind::UV,
+            AttributeData::Normal(_) => AttributeKind::Normal,
+            AttributeData::Color(_) => AttributeKind::Color,
+            AttributeData::Grouping(_) => AttributeKind::Grouping,
+        }
+    }
+
+    /// The number of values in the attribute.
+    pub fn len(&self) -> usize {
+        match self {
+            AttributeData::UV(v) => v.len(),
+            AttributeData::Normal(v) => v.len(),
+            AttributeData::Color(v) => v.len(),
+            AttributeData::Grouping(g) => g.indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The values of a named attribute with one value per element, for data
+/// beyond UV coordinates, normals, colors and groupings such as the extra
+/// properties of a PLY or values attached by the caller.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeArray {
+    F64(Vec<f64>),
+    Vec2(Vec<Vector2<f64>>),
+    Vec3(Vec<Vector3<f64>>),
+    Vec4(Vec<Vector4<f64>>),
+    U8(Vec<u8>),
+    U32(Vec<u32>),
+}
+
+/// A type which can be stored in an `AttributeArray`.
+pub trait AttributeValue: Clone + Sized {
+    /// The values of an array if it holds this type.
+    fn slice(array: &AttributeArray) -> Option<&[Self]>;
+
+    /// Wrap values of this type in an array.
+    fn array(values: Vec<Self>) -> AttributeArray;
+
+    /// The value given to elements which didn't have one.
+    fn zero() -> Self;
+
+    /// The value of a new element `t` of the way from `self` to `other`.
+    fn mix(&self, other: &Self, t: f64) -> Self;
+}
+
+macro_rules! attribute_value {
+    ($type:ty, $variant:ident, $zero:expr, |$a:ident, $b:ident, $t:ident| $mix:expr) => {
+        impl AttributeValue for $type {
+            fn slice(array: &AttributeArray) -> Option<&[Self]> {
+                match array {
+                    AttributeArray::$variant(values) => Some(values),
+                    _ => None,
+                }
+            }
+
+            fn array(values: Vec<Self>) -> AttributeArray {
+                AttributeArray::$variant(values)
+            }
+
+            fn zero() -> Self {
+                $zero
+            }
+
+            fn mix(&self, other: &Self, t: f64) -> Self {
+                let ($a, $b, $t) = (self, other, t);
+                $mix
+            }
+        }
+
+        impl From<Vec<$type>> for AttributeArray {
+            fn from(values: Vec<$type>) -> Self {
+                AttributeArray::$variant(values)
+            }
+        }
+    };
+}
+
+attribute_value!(f64, F64, 0.0, |a, b, t| a + (b - a) * t);
+attribute_value!(Vector2<f64>, Vec2, Vector2::zeros(), |a, b, t| a.lerp(b, t));
+attribute_value!(Vector3<f64>, Vec3, Vector3::zeros(), |a, b, t| a.lerp(b, t));
+attribute_value!(Vector4<f64>, Vec4, Vector4::zeros(), |a, b, t| a.lerp(b, t));
+// integers are usually labels or counts so take the nearer value
+attribute_value!(u8, U8, 0, |a, b, t| if t <= 0.5 { *a } else { *b });
+attribute_value!(u32, U32, 0, |a, b, t| if t <= 0.5 { *a } else { *b });
+
+/// Call a function generic over `AttributeValue` with the values of an array.
+macro_rules! with_values {
+    ($array:expr, $function:ident($($arg:expr),*)) => {
+        match $array {
+            AttributeArray::F64(v) => $function(v, $($arg),*),
+            AttributeArray::Vec2(v) => $function(v, $($arg),*),
+            AttributeArray::Vec3(v) => $function(v, $($arg),*),
+            AttributeArray::Vec4(v) => $function(v, $($arg),*),
+            AttributeArray::U8(v) => $function(v, $($arg),*),
+            AttributeArray::U32(v) => $function(v, $($arg),*),
+        }
+    };
+}
+
+impl AttributeArray {
+    /// The number of values in the array.
+    pub fn len(&self) -> usize {
+        fn len<T>(values: &[T]) -> usize {
+            values.len()
+        }
+        with_values!(self, len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The values at a list of indices.
+    pub fn select(&self, index: &[usize]) -> Self {
+        fn select<T: AttributeValue>(values: &[T], index: &[usize]) -> AttributeArray {
+            T::array(index.iter().map(|i| values[*i].clone()).collect())
+        }
+        with_values!(self, select(index))
+    }
+
+    /// The values with new ones appended which are blended between
+    /// two existing values, as in `Attributes::interpolate`.
+    pub fn interpolate(&self, blends: &[(usize, usize, f64)]) -> Self {
+        fn extend<T: AttributeValue>(
+            values: &[T],
+            blends: &[(usize, usize, f64)],
+        ) -> AttributeArray {
+            let mut result = values.to_vec();
+            result.extend(
+                blends
+                    .iter()
+                    .map(|(a, b, t)| values[*a].mix(&values[*b], *t)),
+            );
+            T::array(result)
+        }
+        with_values!(self, extend(blends))
+    }
+
+    /// Join the values of several sets of elements, where a set without
+    /// the attribute contributes zeros for each of its elements.
+    ///
+    /// Parameters
+    /// ------------
+    /// parts
+    ///   The values of each set if it has them and the number of elements.
+    ///
+    /// Returns
+    /// ------------
+    /// joined
+    ///   The values of every element in order, or `None` if no set has
+    ///   the attribute or the sets hold values of different types.
+    pub fn join(parts: &[(Option<&AttributeArray>, usize)]) -> Option<Self> {
+        fn join<T: AttributeValue>(
+            _: &[T],
+            parts: &[(Option<&AttributeArray>, usize)],
+        ) -> Option<AttributeArray> {
+            let mut result = Vec::with_capacity(parts.iter().map(|(_, n)| n).sum());
+            for (values, count) in parts.iter() {
+                match values {
+                    Some(values) => result.extend_from_slice(T::slice(values)?),
+                    None => result.extend(std::iter::repeat_n(T::zero(), *count)),
+                }
+            }
+            Some(T::array(result))
+        }
+        let first = parts.iter().find_map(|(values, _)| *values)?;
+        with_values!(first, join(parts))
+    }
+}
+
+/// Attributes of the vertices or faces of a mesh, where each attribute
+/// should have one value per element.
+///
+/// Face attributes follow their faces through every operation: a face
+/// kept by simplification, `submesh` or a repair keeps its values, each
+/// piece of a face split by subdivision or a boolean takes the values of
+/// the face it was split from, and a face filling a hole takes the values
+/// of a face next to it. Vertex attributes are interpolated onto new
+/// vertices, with groupings taking the value of one of the neighbors.
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct Attributes {
     pub uv: Vec<UV>,
     pub normals: Vec<Normal>,
     pub colors: Vec<Color>,
     pub groupings: Vec<Grouping>,
+    // any other attributes by name
+    pub named: HashMap<String, AttributeArray>,
+}
+
+impl Attributes {
+    /// The number of attributes of a kind.
+    pub fn count(&self, kind: AttributeKind) -> usize {
+        match kind {
+            AttributeKind::UV => self.uv.len(),
+            AttributeKind::Normal => self.normals.len(),
+            AttributeKind::Color => self.colors.len(),
+            AttributeKind::Grouping => self.groupings.len(),
+        }
+    }
+
+    /// Find a grouping by name.
+    pub fn grouping(&self, name: &str) -> Option<&Grouping> {
+        self.groupings.iter().find(|g| g.name == name)
+    }
+
+    /// The values of a named attribute if it exists and holds values of
+    /// type `T`, such as `attributes.get::<f64>("quality")`.
+    pub fn get<T: AttributeValue>(&self, name: &str) -> Option<&[T]> {
+        T::slice(self.named.get(name)?)
+    }
+
+    /// Add an attribute returning its index among attributes of its kind.
+    fn push(&mut self, data: AttributeData) -> usize {
+        let kind = data.kind();
+        match data {
+            AttributeData::UV(v) => self.uv.push(v),
+            AttributeData::Normal(v) => self.normals.push(v),
+            AttributeData::Color(v) => self.colors.push(v),
+            AttributeData::Grouping(g) => self.groupings.push(g),
+        }
+        self.count(kind) - 1
+    }
+
+    /// Remove an attribute by its index among attributes of its kind.
+    ///
+    /// Parameters
+    /// ------------
+    /// kind
+    ///   The kind of attribute to remove.
+    /// index
+    ///   The index of the attribute among attributes of `kind`.
+    ///
+    /// Returns
+    /// ------------
+    /// removed
+    ///   The values of the removed attribute or an error if it doesn't exist.
+    pub fn remove(&mut self, kind: AttributeKind, index: usize) -> Result<AttributeData> {
+        if index >= self.count(kind) {
+            return Err(anyhow!(
+                "No {kind:?} attribute at index {index}, there are {}",
+                self.count(kind)
+            ));
+        }
+        Ok(match kind {
+            AttributeKind::UV => AttributeData::UV(self.uv.remove(index)),
+            AttributeKind::Normal => AttributeData::Normal(self.normals.remove(index)),
+            AttributeKind::Color => AttributeData::Color(self.colors.remove(index)),
+            AttributeKind::Grouping => AttributeData::Grouping(self.groupings.remove(index)),
+        })
+    }
+
+    /// Remove a named attribute.
+    ///
+    /// Parameters
+    /// ------------
+    /// name
+    ///   The name the attribute is stored under.
+    ///
+    /// Returns
+    /// ------------
+    /// removed
+    ///   The values of the removed attribute or an error if it doesn't exist.
+    pub fn remove_named(&mut self, name: &str) -> Result<AttributeArray> {
+        self.named
+            .remove(name)
+            .ok_or_else(|| anyhow!("No attribute named `{name}`"))
+    }
+
+    /// Rename a grouping or a named attribute.
+    ///
+    /// Parameters
+    /// ------------
+    /// old
+    ///   The current name of the grouping or named attribute.
+    /// new
+    ///   The new name which must not already be used.
+    ///
+    /// Returns
+    /// ------------
+    /// result
+    ///   An error if there's nothing named `old` or something named `new`.
+    pub fn rename(&mut self, old: &str, new: &str) -> Result<()> {
+        let exists = |name: &str| self.grouping(name).is_some() || self.named.contains_key(name);
+        if !exists(old) {
+            return Err(anyhow!("No attribute named `{old}`"));
+        }
+        if old != new && exists(new) {
+            return Err(anyhow!("An attribute named `{new}` already exists"));
+        }
+        if let Some(grouping) = self.groupings.iter_mut().find(|g| g.name == old) {
+            grouping.name = new.to_string();
+            return Ok(());
+        }
+        let values = self.remove_named(old)?;
+        self.named.insert(new.to_string(), values);
+        Ok(())
+    }
+
+    /// Select the values of every attribute at a list of indices,
+    /// such as the faces or vertices that survive an operation.
+    /// Attributes which don't have one value per element can't be
+    /// selected from and are dropped.
+    ///
+    /// Parameters
+    /// ------------
+    /// index
+    ///   Indices of the elements to keep in the order to keep them.
+    /// count
+    ///   The number of elements before the selection.
+    ///
+    /// Returns
+    /// ------------
+    /// selected
+    ///   Attributes containing only the values of the indexed elements.
+    pub fn select(&self, index: &[usize], count: usize) -> Self {
+        fn take<T: Clone>(values: &[Vec<T>], index: &[usize], count: usize) -> Vec<Vec<T>> {
+            values
+                .iter()
+                .filter(|v| v.len() == count)
+                .map(|v| index.iter().map(|i| v[*i].clone()).collect())
+                .collect()
+        }
+        Self {
+            uv: take(&self.uv, index, count),
+            normals: take(&self.normals, index, count),
+            colors: take(&self.colors, index, count),
+            groupings: self
+                .groupings
+                .iter()
+                .filter(|g| g.indices.len() == count)
+                .map(|g| Grouping {
+                    name: g.name.clone(),
+                    kind: g.kind.clone(),
+                    indices: index.iter().map(|i| g.indices[*i]).collect(),
+                    labels: g.labels.clone(),
+                })
+                .collect(),
+            named: self
+                .named
+                .iter()
+                .filter(|(_, v)| v.len() == count)
+                .map(|(name, v)| (name.clone(), v.select(index)))
+                .collect(),
+        }
+    }
+
+    /// Keep every attribute with a value for each element and add
+    /// values for new elements blended between two existing ones, such
+    /// as vertices added along edges. UV coordinates and colors are
+    /// interpolated, normals are interpolated and renormalized, and
+    /// groupings take the value of the nearer element.
+    ///
+    /// Parameters
+    /// ------------
+    /// count
+    ///   The number of elements of these attributes.
+    /// blends
+    ///   For every new element the two elements it's between and how
+    ///   far it is from the first to the second between 0.0 and 1.0.
+    ///
+    /// Returns
+    /// ------------
+    /// extended
+    ///   Attributes with a value for `count + blends.len()` elements.
+    pub fn interpolate(&self, count: usize, blends: &[(usize, usize, f64)]) -> Self {
+        fn extend<T: Clone>(
+            values: &[Vec<T>],
+            count: usize,
+            blends: &[(usize, usize, f64)],
+            mix: impl Fn(&T, &T, f64) -> T,
+        ) -> Vec<Vec<T>> {
+            values
+                .iter()
+                .filter(|v| v.len() == count)
+                .map(|v| {
+                    let mut result = v.clone();
+                    result.extend(blends.iter().map(|(a, b, t)| mix(&v[*a], &v[*b], *t)));
+                    result
+                })
+                .collect()
+        }
+        Self {
+            uv: extend(&self.uv, count, blends, |a, b, t| a.lerp(b, t)),
+            normals: extend(&self.normals, count, blends, |a, b, t| {
+                a.lerp(b, t).try_normalize(f64::EPSILON).unwrap_or(*a)
+            }),
+            colors: extend(&self.colors, count, blends, |a, b, t| {
+                Vector4::from_fn(|i, _| {
+                    (a[i] as f64 + (b[i] as f64 - a[i] as f64) * t).round() as u8
+                })
+            }),
+            // an element between two groups can only belong to one
+            groupings: self
+                .groupings
+                .iter()
+                .filter(|g| g.indices.len() == count)
+                .map(|g| Grouping {
+                    name: g.name.clone(),
+                    kind: g.kind.clone(),
+                    indices: g
+                        .indices
+                        .iter()
+                        .copied()
+                        .chain(blends.iter().map(|(a, b, t)| {
+                            if *t <= 0.5 {
+                                g.indices[*a]
+                            } else {
+                                g.indices[*b]
+                            }
+                        }))
+                        .collect(),
+                    labels: g.labels.clone(),
+                })
+                .collect(),
+            named: self
+                .named
+                .iter()
+                .filter(|(_, v)| v.len() == count)
+                .map(|(name, v)| (name.clone(), v.interpolate(blends)))
+                .collect(),
+        }
+    }
+
+    /// Join the attributes of two sets of elements, such as the faces of
+    /// two meshes combined into one, like `util::concatenate` does. UV
+    /// coordinates, normals and colors are matched by their position
+    /// among attributes of their kind and groupings by name. A side
+    /// without an attribute the other has fills in zero UV coordinates,
+    /// zero normals as there's no geometry to compute them from, the
+    /// default color, group zero or zeros for a named attribute.
+    ///
+    /// Parameters
+    /// ------------
+    /// count
+    ///   The number of elements of these attributes.
+    /// other
+    ///   The attributes of the elements which follow.
+    /// other_count
+    ///   The number of elements of `other`.
+    ///
+    /// Returns
+    /// ------------
+    /// joined
+    ///   Attributes with a value for `count + other_count` elements.
+    pub fn concatenate(&self, count: usize, other: &Attributes, other_count: usize) -> Self {
+        let parts = [(self, count), (other, other_count)];
+        let mut joined = join_attributes(&parts, |side| vec![Vector3::zeros(); parts[side].1]);
+        joined.groupings = join_groupings(&parts.map(|(a, n)| (&a.groupings[..], n)), |_, _| 0);
+        joined
+    }
+}
+
+impl Trimesh {
+    /// The attributes of one domain of the mesh and the number of
+    /// elements each of them needs a value for.
+    fn attributes_mut(&mut self, domain: AttributeDomain) -> (&mut Attributes, usize) {
+        match domain {
+            AttributeDomain::Vertex => (&mut self.attributes_vertex, self.vertices.len()),
+            AttributeDomain::Face => (&mut self.attributes_face, self.faces.len()),
+        }
+    }
+
+    /// Add an attribute after checking it has a value for every element,
+    /// that a grouping's name is unique and that material indices
+    /// reference a material of the mesh.
+    ///
+    /// Parameters
+    /// ------------
+    /// domain
+    ///   Whether the attribute is for the vertices or faces.
+    /// data
+    ///   The values of the attribute.
+    ///
+    /// Returns
+    /// ------------
+    /// index
+    ///   The index of the new attribute among attributes of its kind,
+    ///   or an error if it isn't valid for the mesh.
+    pub fn add_attribute(&mut self, domain: AttributeDomain, data: AttributeData) -> Result<usize> {
+        let materials = self.materials.len();
+        let (attributes, count) = self.attributes_mut(domain);
+        if data.len() != count {
+            return Err(anyhow!(
+                "{:?} attribute has {} values but the mesh has {count} {domain:?} elements",
+                data.kind(),
+                data.len()
+            ));
+        }
+        if let AttributeData::Grouping(grouping) = &data {
+            if attributes.grouping(&grouping.name).is_some() {
+                return Err(anyhow!(
+                    "A grouping named `{}` already exists",
+                    grouping.name
+                ));
+            }
+            if matches!(grouping.kind, GroupingKind::MaterialIndex)
+                && grouping.indices.iter().any(|i| *i >= materials)
+            {
+                return Err(anyhow!(
+                    "Material grouping references a material that doesn't exist"
+                ));
+            }
+        }
+        let index = attributes.push(data);
+        self.invalidate_cache();
+        Ok(index)
+    }
+
+    /// Add a named attribute after checking it has a value for every
+    /// element and the name isn't already used.
+    ///
+    /// Parameters
+    /// ------------
+    /// domain
+    ///   Whether the attribute is for the vertices or faces.
+    /// name
+    ///   The name to store the attribute under.
+    /// values
+    ///   The values of the attribute, such as a `Vec<f64>`.
+    ///
+    /// Returns
+    /// ------------
+    /// result
+    ///   An error if the attribute isn't valid for the mesh.
+    pub fn add_named_attribute(
+        &mut self,
+        domain: AttributeDomain,
+        name: &str,
+        values: impl Into<AttributeArray>,
+    ) -> Result<()> {
+        let values = values.into();
+        let (attributes, count) = self.attributes_mut(domain);
+        if values.len() != count {
+            return Err(anyhow!(
+                "Attribute `{name}` has {} values but the mesh has {count} {domain:?} elements",
+                values.len()
+            ));
+        }
+        if attributes.named.contains_key(name) {
+            return Err(anyhow!("An attribute named `{name}` already exists"));
+        }
+        attributes.named.insert(name.to_string(), values);
+        self.invalidate_cache();
+        Ok(())
+    }
+
+    /// Add an attribute with a value for every vertex.
+    pub fn add_vertex_attribute(&mut self, data: AttributeData) -> Result<usize> {
+        self.add_attribute(AttributeDomain::Vertex, data)
+    }
+
+    /// Add an attribute with a value for every face.
+    pub fn add_face_attribute(&mut self, data: AttributeData) -> Result<usize> {
+        self.add_attribute(AttributeDomain::Face, data)
+    }
+
+    /// Remove an attribute by its index among attributes of its kind.
+    pub fn remove_attribute(
+        &mut self,
+        domain: AttributeDomain,
+        kind: AttributeKind,
+        index: usize,
+    ) -> Result<AttributeData> {
+        let removed = self.attributes_mut(domain).0.remove(kind, index)?;
+        self.invalidate_cache();
+        Ok(removed)
+    }
+
+    /// Remove a named attribute of the vertices or faces.
+    pub fn remove_named_attribute(
+        &mut self,
+        domain: AttributeDomain,
+        name: &str,
+    ) -> Result<AttributeArray> {
+        let removed = self.attributes_mut(domain).0.remove_named(name)?;
+        self.invalidate_cache();
+        Ok(removed)
+    }
+
+    /// Rename a grouping or named attribute of the vertices or faces.
+    pub fn rename_attribute(
+        &mut self,
+        domain: AttributeDomain,
+        old: &str,
+        new: &str,
+    ) -> Result<()> {
+        self.attributes_mut(domain).0.rename(old, new)
+    }
+
+    /// Reorder the faces so every group of a face grouping occupies
+    /// a contiguous range of face indices, so an exporter can emit one
+    /// primitive per material by slicing the index buffer rather than
+    /// duplicating indices. Faces keep their order within each group.
+    ///
+    /// Parameters
+    /// ------------
+    /// kind
+    ///   The kind of the face grouping to sort by, using the first
+    ///   grouping of that kind with a value for every face.
+    ///
+    /// Returns
+    /// ------------
+    /// sorted
+    ///   A new mesh with reordered faces and face attributes, and the
+    ///   range of faces of each group in ascending order of group, or
+    ///   an error if the mesh has no grouping of that kind.
+    pub fn sort_faces_by_group(&self, kind: GroupingKind) -> Result<(Self, GroupRanges)> {
+        let count = self.faces.len();
+        let groups = &self
+            .attributes_face
+            .groupings
+            .iter()
+            .find(|g| {
+                std::mem::discriminant(&g.kind) == std::mem::discriminant(&kind)
+                    && g.indices.len() == count
+            })
+            .ok_or(anyhow!("Mesh has no {kind:?} grouping of its faces"))?
+            .indices;
+
+        // a stable sort keeps the original order within each group
+        let mut order: Vec<usize> = (0..count).collect();
+        order.sort_by_key(|f| groups[*f]);
+
+        let mut ranges: GroupRanges = Vec::new();
+        for (index, face) in order.iter().enumerate() {
+            match ranges.last_mut() {
+                Some((group, range)) if *group == groups[*face] => range.end = index + 1,
+                _ => ranges.push((groups[*face], index..index + 1)),
+            }
+        }
+
+        let mut sorted = self.clone();
+        *sorted.faces_mut() = order.iter().map(|f| self.faces[*f]).collect();
+        sorted.attributes_face = self.attributes_face.select(&order, count);
+        Ok((sorted, ranges))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -41,9 +754,13 @@ pub struct LoadSource {
 
     // many formats have a header which would otherwise be discarded
     pub header: Option<String>,
+
+    // the unit of the coordinates for formats which record one,
+    // or `None` if the coordinates are unitless
+    pub units: Option<Unit>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SimpleMaterial {
     pub name: String,
     pub diffuse: Option<Vector3<f64>>,
@@ -53,13 +770,27 @@ pub struct SimpleMaterial {
     pub image: Option<DynamicImage>,
 }
 
-#[derive(Debug, Clone)]
+impl SimpleMaterial {
+    /// Create a material with a name and no other properties set.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            diffuse: None,
+            specular: None,
+            shininess: None,
+            alpha: None,
+            image: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct PBRMaterial {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct EmptyMaterial {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Material {
     Empty(EmptyMaterial),
     Simple(SimpleMaterial),
@@ -67,3 +798,368 @@ pub enum Material {
 }
 
 pub const DEFAULT_COLOR: Vector4<u8> = Vector4::new(100, 100, 100, 255);
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_attribute_mutation() {
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        let faces = mesh.faces.len();
+
+        // sizes need to match the number of elements
+        let uv = AttributeData::UV(vec![Vector2::zeros(); mesh.vertices.len()]);
+        assert!(mesh.add_face_attribute(uv.clone()).is_err());
+        assert_eq!(mesh.add_vertex_attribute(uv.clone()).unwrap(), 0);
+        assert_eq!(mesh.add_vertex_attribute(uv).unwrap(), 1);
+        assert_eq!(mesh.attributes_vertex.count(AttributeKind::UV), 2);
+
+        let grouping = |name: &str, kind: GroupingKind| {
+            AttributeData::Grouping(Grouping {
+                name: name.to_string(),
+                kind,
+                indices: vec![0; faces],
+                labels: Vec::new(),
+            })
+        };
+        mesh.add_face_attribute(grouping("parts", GroupingKind::GroupingIndex))
+            .unwrap();
+        // names are unique and materials need to exist
+        assert!(
+            mesh.add_face_attribute(grouping("parts", GroupingKind::GroupingIndex))
+                .is_err()
+        );
+        assert!(
+            mesh.add_face_attribute(grouping("material", GroupingKind::MaterialIndex))
+                .is_err()
+        );
+        mesh.materials
+            .push(Material::Simple(SimpleMaterial::new("red")));
+        mesh.add_face_attribute(grouping("material", GroupingKind::MaterialIndex))
+            .unwrap();
+
+        mesh.rename_attribute(AttributeDomain::Face, "parts", "bodies")
+            .unwrap();
+        assert!(mesh.attributes_face.grouping("bodies").is_some());
+        assert!(
+            mesh.rename_attribute(AttributeDomain::Face, "bodies", "material")
+                .is_err()
+        );
+        assert!(
+            mesh.rename_attribute(AttributeDomain::Vertex, "bodies", "parts")
+                .is_err()
+        );
+
+        // changing attributes clears cached values
+        mesh.face_normals();
+        assert!(mesh._cache.read().unwrap().face_normals.is_some());
+        let removed = mesh
+            .remove_attribute(AttributeDomain::Vertex, AttributeKind::UV, 0)
+            .unwrap();
+        assert_eq!(removed.kind(), AttributeKind::UV);
+        assert_eq!(removed.len(), mesh.vertices.len());
+        assert!(mesh._cache.read().unwrap().face_normals.is_none());
+        assert!(
+            mesh.remove_attribute(AttributeDomain::Vertex, AttributeKind::UV, 1)
+                .is_err()
+        );
+        assert_eq!(mesh.attributes_vertex.count(AttributeKind::UV), 1);
+    }
+
+    #[test]
+    fn test_grouping_consistency() {
+        use crate::util::concatenate;
+
+        // every grouping has a value per face, a unique name, labels
+        // for every group and material indices of existing materials
+        let consistent = |mesh: &Trimesh| {
+            let groupings = &mesh.attributes_face.groupings;
+            for (i, g) in groupings.iter().enumerate() {
+                assert_eq!(g.indices.len(), mesh.faces.len(), "{}", g.name);
+                assert!(groupings[..i].iter().all(|o| o.name != g.name));
+                assert!(g.labels.is_empty() || g.indices.iter().all(|i| *i < g.labels.len()));
+                if matches!(g.kind, GroupingKind::MaterialIndex) {
+                    assert!(g.indices.iter().all(|i| *i < mesh.materials.len()));
+                }
+            }
+        };
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        mesh.materials = vec![
+            Material::Simple(SimpleMaterial::new("a")),
+            Material::Simple(SimpleMaterial::new("b")),
+        ];
+        let faces = mesh.faces.len();
+        mesh.add_face_attribute(AttributeData::Grouping(Grouping {
+            name: "material".to_string(),
+            kind: GroupingKind::MaterialIndex,
+            indices: (0..faces).map(|f| f % 2).collect(),
+            labels: Vec::new(),
+        }))
+        .unwrap();
+        mesh.add_face_attribute(AttributeData::Grouping(Grouping {
+            name: "solid".to_string(),
+            kind: GroupingKind::GroupingIndex,
+            indices: (0..faces).map(|f| f / 6).collect(),
+            labels: vec!["lower".to_string(), "upper".to_string()],
+        }))
+        .unwrap();
+        consistent(&mesh);
+
+        // a submesh keeps the group of each face it selects
+        let sub = mesh.submesh(&[11, 1, 6]).unwrap();
+        consistent(&sub);
+        let solid = sub.attributes_face.grouping("solid").unwrap();
+        assert_eq!(solid.indices, vec![1, 0, 1]);
+        assert_eq!(solid.labels, vec!["lower", "upper"]);
+        let material = sub.attributes_face.grouping("material").unwrap();
+        assert_eq!(material.indices, vec![1, 1, 0]);
+
+        // a mesh without the labeled grouping doesn't join a labeled group
+        let mut other = create_box(&[1.0, 1.0, 1.0]);
+        other.materials = vec![Material::Simple(SimpleMaterial::new("c"))];
+        other
+            .add_face_attribute(AttributeData::Grouping(Grouping {
+                name: "material".to_string(),
+                kind: GroupingKind::MaterialIndex,
+                indices: vec![0; faces],
+                labels: Vec::new(),
+            }))
+            .unwrap();
+        let mut joined = concatenate(&[&sub, &other]);
+        consistent(&joined);
+        let solid = joined.attributes_face.grouping("solid").unwrap();
+        assert_eq!(&solid.indices[..3], &[1, 0, 1]);
+        assert!(
+            solid.indices[3..]
+                .iter()
+                .all(|i| solid.labels[*i].is_empty())
+        );
+        let material = &joined.attributes_face.grouping("material").unwrap().indices;
+        assert_eq!(&material[..3], &[1, 1, 0]);
+        assert!(material[3..].iter().all(|i| *i == 2));
+        // and the joined names are still checked for duplicates
+        assert!(
+            joined
+                .add_face_attribute(AttributeData::Grouping(Grouping {
+                    name: "solid".to_string(),
+                    indices: vec![0; joined.faces.len()],
+                    ..Default::default()
+                }))
+                .is_err()
+        );
+        consistent(&joined.submesh(&[0, 14]).unwrap());
+    }
+
+    #[test]
+    fn test_sort_faces_by_group() {
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        mesh.materials = vec![
+            Material::Simple(SimpleMaterial::new("a")),
+            Material::Simple(SimpleMaterial::new("b")),
+        ];
+        assert!(
+            mesh.sort_faces_by_group(GroupingKind::MaterialIndex)
+                .is_err()
+        );
+
+        // alternate materials so neither group is contiguous
+        let indices: Vec<usize> = (0..mesh.faces.len()).map(|f| f % 2).collect();
+        mesh.add_face_attribute(AttributeData::Grouping(Grouping {
+            name: "material".to_string(),
+            kind: GroupingKind::MaterialIndex,
+            indices,
+            labels: Vec::new(),
+        }))
+        .unwrap();
+        let original: Vec<usize> = (0..mesh.faces.len()).collect();
+        mesh.add_face_attribute(AttributeData::Grouping(Grouping {
+            name: "original".to_string(),
+            kind: GroupingKind::GroupingIndex,
+            indices: original,
+            labels: Vec::new(),
+        }))
+        .unwrap();
+
+        let (sorted, ranges) = mesh
+            .sort_faces_by_group(GroupingKind::MaterialIndex)
+            .unwrap();
+        assert_eq!(ranges, vec![(0, 0..6), (1, 6..12)]);
+        let material = &sorted.attributes_face.grouping("material").unwrap().indices;
+        for (group, range) in ranges {
+            assert!(material[range].iter().all(|m| *m == group));
+        }
+        // faces move with their attributes and keep their order in a group
+        let original = &sorted.attributes_face.grouping("original").unwrap().indices;
+        assert_eq!(original, &vec![0, 2, 4, 6, 8, 10, 1, 3, 5, 7, 9, 11]);
+        for (face, source) in sorted.faces.iter().zip(original) {
+            assert_eq!(*face, mesh.faces[*source]);
+        }
+        assert_relative_eq!(sorted.area(), mesh.area());
+    }
+
+    #[test]
+    fn test_face_attribute_propagation() {
+        use crate::boolean::union;
+        use nalgebra::Vector3;
+
+        // label each face by the side of the box it's on so every
+        // face can be checked against its normal after an operation
+        let side = |n: &Vector3<f64>| n.iamax() * 2 + usize::from(n[n.iamax()] > 0.0);
+        let labeled = |mesh: &mut Trimesh, material: &str| {
+            let labels: Vec<usize> = mesh.face_normals().iter().map(side).collect();
+            mesh.attributes_face.colors.push(
+                labels
+                    .iter()
+                    .map(|l| Vector4::new(*l as u8, 0, 0, 255))
+                    .collect(),
+            );
+            mesh.attributes_face.groupings.push(Grouping {
+                name: "side".to_string(),
+                kind: GroupingKind::GroupingIndex,
+                indices: labels,
+                labels: Vec::new(),
+            });
+            mesh.attributes_face.groupings.push(Grouping {
+                name: "material".to_string(),
+                kind: GroupingKind::MaterialIndex,
+                indices: vec![0; mesh.faces.len()],
+                labels: Vec::new(),
+            });
+            mesh.materials = vec![Material::Simple(SimpleMaterial::new(material))];
+        };
+        let check = |mesh: &Trimesh| {
+            let labels = &mesh.attributes_face.grouping("side").unwrap().indices;
+            let colors = &mesh.attributes_face.colors[0];
+            assert_eq!(labels.len(), mesh.faces.len());
+            assert_eq!(colors.len(), mesh.faces.len());
+            for ((n, l), c) in mesh.face_normals().iter().zip(labels).zip(colors) {
+                assert_eq!(side(n), *l);
+                assert_eq!(c.x as usize, *l);
+            }
+        };
+
+        let mut mesh = create_box(&[1.0, 2.0, 3.0]).subdivide_to_size(0.5).unwrap();
+        labeled(&mut mesh, "a");
+        check(&mesh.subdivide_to_size(0.2).unwrap());
+        check(&mesh.simplify(mesh.faces.len() / 4, 7.0));
+        check(&mesh.simplify_lossless());
+
+        let top: Vec<usize> = (0..mesh.faces.len())
+            .filter(|f| mesh.face_normals()[*f].z > 0.5)
+            .collect();
+        let sub = mesh.submesh(&top).unwrap();
+        check(&sub);
+        assert_eq!(sub.faces.len(), top.len());
+        assert!(sub.vertices.len() < mesh.vertices.len());
+        assert_relative_eq!(sub.area(), 2.0, epsilon = 1e-10);
+        assert!(mesh.submesh(&[mesh.faces.len()]).is_err());
+
+        let mut other = create_box(&[1.0, 1.0, 1.0])
+            .apply_translation(&Vector3::new(0.0, 0.0, 1.5))
+            .unwrap();
+        labeled(&mut other, "b");
+        let joined = union(&mesh, &other).unwrap();
+        check(&joined);
+        // faces from the second mesh use its material after the first's
+        assert_eq!(joined.materials.len(), 2);
+        let materials = &joined.attributes_face.grouping("material").unwrap().indices;
+        assert!(materials.contains(&0) && materials.contains(&1));
+    }
+
+    #[test]
+    fn test_named_attributes() {
+        use crate::util::concatenate;
+
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        let height: Vec<f64> = mesh.vertices.iter().map(|v| v.z).collect();
+        mesh.add_named_attribute(AttributeDomain::Vertex, "height", height)
+            .unwrap();
+        let ids: Vec<u32> = (0..12).collect();
+        mesh.add_named_attribute(AttributeDomain::Face, "id", ids.clone())
+            .unwrap();
+        // the wrong length, an existing name and a typed lookup of the wrong type
+        assert!(
+            mesh.add_named_attribute(AttributeDomain::Face, "short", vec![1.0_f64])
+                .is_err()
+        );
+        assert!(
+            mesh.add_named_attribute(AttributeDomain::Face, "id", ids.clone())
+                .is_err()
+        );
+        assert_eq!(mesh.attributes_face.get::<u32>("id").unwrap(), ids);
+        assert!(mesh.attributes_face.get::<f64>("id").is_none());
+
+        // interpolated values follow the vertices they were attached to
+        let fine = mesh.subdivide_to_size(0.3).unwrap();
+        let height = fine.attributes_vertex.get::<f64>("height").unwrap();
+        assert_eq!(height.len(), fine.vertices.len());
+        for (v, h) in fine.vertices.iter().zip(height) {
+            assert_relative_eq!(v.z, *h, epsilon = 1e-12);
+        }
+        let sub = mesh.submesh(&[3, 7]).unwrap();
+        assert_eq!(sub.attributes_face.get::<u32>("id").unwrap(), [3, 7]);
+
+        // a mesh without the attribute contributes zeros
+        let joined = concatenate(&[&mesh, &create_box(&[1.0, 1.0, 1.0]), &mesh]);
+        let id = joined.attributes_face.get::<u32>("id").unwrap();
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[12..24], &[0; 12]);
+        assert_eq!(&id[24..], ids.as_slice());
+        let both = mesh
+            .attributes_face
+            .concatenate(12, &mesh.attributes_face, 12);
+        assert_eq!(both.get::<u32>("id").unwrap().len(), 24);
+        // attributes only one side has are filled with defaults
+        let mut colored = create_box(&[1.0, 1.0, 1.0]).attributes_face;
+        colored.colors.push(vec![Vector4::new(255, 0, 0, 255); 12]);
+        let mixed = mesh.attributes_face.concatenate(12, &colored, 12);
+        assert_eq!(&mixed.colors[0][..12], &[DEFAULT_COLOR; 12]);
+        assert_eq!(mixed.colors[0][12], Vector4::new(255, 0, 0, 255));
+        assert_eq!(&mixed.get::<u32>("id").unwrap()[12..], &[0; 12]);
+        let reversed = colored.concatenate(12, &mesh.attributes_face, 12);
+        assert_eq!(&reversed.get::<u32>("id").unwrap()[..12], &[0; 12]);
+        assert_eq!(&reversed.get::<u32>("id").unwrap()[12..], ids.as_slice());
+        assert!(
+            AttributeArray::join(&[
+                (Some(&AttributeArray::U8(vec![1])), 1),
+                (Some(&AttributeArray::F64(vec![1.0])), 1)
+            ])
+            .is_none()
+        );
+
+        // named attributes are renamed and removed like groupings
+        mesh.rename_attribute(AttributeDomain::Face, "id", "label")
+            .unwrap();
+        assert_eq!(mesh.attributes_face.get::<u32>("label").unwrap(), ids);
+        assert!(mesh.attributes_face.get::<u32>("id").is_none());
+        assert!(
+            mesh.rename_attribute(AttributeDomain::Face, "id", "other")
+                .is_err()
+        );
+        assert!(
+            mesh.rename_attribute(AttributeDomain::Vertex, "height", "height")
+                .is_ok()
+        );
+        mesh.add_named_attribute(AttributeDomain::Face, "id", ids.clone())
+            .unwrap();
+        assert!(
+            mesh.rename_attribute(AttributeDomain::Face, "id", "label")
+                .is_err()
+        );
+        mesh.face_normals();
+        let removed = mesh
+            .remove_named_attribute(AttributeDomain::Face, "label")
+            .unwrap();
+        assert_eq!(removed, AttributeArray::U32(ids));
+        assert!(mesh._cache.read().unwrap().face_normals.is_none());
+        assert!(mesh.attributes_face.get::<u32>("label").is_none());
+        assert!(
+            mesh.remove_named_attribute(AttributeDomain::Face, "label")
+                .is_err()
+        );
+    }
+}
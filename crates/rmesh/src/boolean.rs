@@ -0,0 +1,682 @@
+use ahash::AHashMap;
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector3};
+use rayon::prelude::*;
+
+use crate::attributes::GroupingKind;
+use crate::bvh::Bvh;
+use crate::clip::clip_triangle_by_plane;
+use crate::mesh::Trimesh;
+
+/// Does every directed edge of the mesh have a matching reversed
+/// edge, which is true for closed, consistently wound meshes.
+fn is_closed(mesh: &Trimesh) -> bool {
+    let mut edges: AHashMap<(usize, usize), i64> = AHashMap::with_capacity(mesh.faces.len() * 3);
+    for f in mesh.faces.iter() {
+        for (a, b) in [(f.0, f.1), (f.1, f.2), (f.2, f.0)] {
+            *edges.entry((a.min(b), a.max(b))).or_default() += if a < b { 1 } else { -1 };
+        }
+    }
+    edges.values().all(|v| *v == 0)
+}
+
+/// A boolean operation between the volumes enclosed by two meshes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BooleanOperation {
+    // the volume inside either mesh
+    Union,
+    // the volume inside both meshes
+    Intersection,
+    // the volume inside the first mesh but not the second
+    Difference,
+}
+
+/// A line as a point on it and its direction.
+type Line = (Point3<f64>, Vector3<f64>);
+
+/// Where a piece of the surface of one mesh is relative to the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Inside,
+    Outside,
+    // on a face of the other mesh facing the same way
+    Same,
+    // on a face of the other mesh facing the opposite way
+    Opposite,
+}
+
+/// The faces of a mesh that another face is cut by.
+#[derive(Debug, Clone, Default)]
+struct Cuts {
+    // lines in the plane of the face
+    lines: Vec<Line>,
+    // faces of the other mesh in the same plane as this face
+    coplanar: Vec<usize>,
+}
+
+/// The corners of a face of a mesh.
+fn triangle(mesh: &Trimesh, face: usize) -> [Point3<f64>; 3] {
+    let f = mesh.faces[face];
+    [mesh.vertices[f.0], mesh.vertices[f.1], mesh.vertices[f.2]]
+}
+
+/// The interval where a triangle crosses a plane projected onto a
+/// direction, from the signed distance of each corner to the plane.
+fn crossing(
+    t: &[Point3<f64>; 3],
+    distance: &[f64; 3],
+    direction: &Vector3<f64>,
+    tolerance: f64,
+) -> Option<(f64, f64)> {
+    let mut points = Vec::with_capacity(3);
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        if distance[i].abs() <= tolerance {
+            points.push(t[i]);
+        }
+        if (distance[i] > tolerance && distance[j] < -tolerance)
+            || (distance[i] < -tolerance && distance[j] > tolerance)
+        {
+            let s = distance[i] / (distance[i] - distance[j]);
+            points.push(t[i] + (t[j] - t[i]) * s);
+        }
+    }
+    let projected = points.iter().map(|p| direction.dot(&p.coords));
+    let lower = projected.clone().fold(f64::INFINITY, f64::min);
+    let upper = projected.fold(f64::NEG_INFINITY, f64::max);
+    (lower <= upper).then_some((lower, upper))
+}
+
+/// Find how two triangles cut each other, returning the line that each
+/// should be split along or `None` if they don't cross. Coplanar pairs
+/// are reported with a `None` line so each can be cut by the other's edges.
+fn cut_triangles(
+    t: &[Point3<f64>; 3],
+    s: &[Point3<f64>; 3],
+    tolerance: f64,
+) -> Option<Option<Line>> {
+    let nt = (t[1] - t[0]).cross(&(t[2] - t[0])).try_normalize(0.0)?;
+    let ns = (s[1] - s[0]).cross(&(s[2] - s[0])).try_normalize(0.0)?;
+
+    let dt = t.map(|p| ns.dot(&(p - s[0])));
+    if dt.iter().all(|d| *d > tolerance) || dt.iter().all(|d| *d < -tolerance) {
+        return None;
+    }
+    if dt.iter().all(|d| d.abs() <= tolerance) {
+        return Some(None);
+    }
+    let ds = s.map(|p| nt.dot(&(p - t[0])));
+    if ds.iter().all(|d| *d > tolerance) || ds.iter().all(|d| *d < -tolerance) {
+        return None;
+    }
+
+    // both triangles cross the line where their planes meet
+    let direction = nt.cross(&ns).try_normalize(0.0)?;
+    let a = crossing(t, &dt, &direction, tolerance)?;
+    let b = crossing(s, &ds, &direction, tolerance)?;
+    let (lower, upper) = (a.0.max(b.0), a.1.min(b.1));
+    if upper - lower <= tolerance {
+        return None;
+    }
+
+    // a point on the line of intersection from the plane equations
+    let (ht, hs) = (nt.dot(&t[0].coords), ns.dot(&s[0].coords));
+    let dot = nt.dot(&ns);
+    let scale = 1.0 - dot * dot;
+    let origin = nt * ((ht - hs * dot) / scale) + ns * ((hs - ht * dot) / scale);
+    Some(Some((Point3::from(origin), direction)))
+}
+
+/// Split triangles in the plane with a normal along a line.
+fn split_triangles(
+    triangles: Vec<[Point3<f64>; 3]>,
+    line: &Line,
+    normal: &Vector3<f64>,
+    tolerance: f64,
+) -> Vec<[Point3<f64>; 3]> {
+    // the in-plane direction perpendicular to the line
+    let Some(side) = line.1.cross(normal).try_normalize(0.0) else {
+        return triangles;
+    };
+    let mut result = Vec::with_capacity(triangles.len() * 2);
+    for t in triangles {
+        let clipped = clip_triangle_by_plane(&t.map(|p| side.dot(&(p - line.0))), tolerance);
+        result.extend(
+            clipped
+                .above
+                .iter()
+                .chain(clipped.below.iter())
+                .map(|piece| piece.map(|blend| blend.point(&t))),
+        );
+    }
+    result
+}
+
+/// Is a point inside a triangle it is coplanar with.
+fn in_triangle(p: &Point3<f64>, t: &[Point3<f64>; 3], tolerance: f64) -> bool {
+    let normal = (t[1] - t[0]).cross(&(t[2] - t[0]));
+    (0..3).all(|i| {
+        let edge = t[(i + 1) % 3] - t[i];
+        // the distance of the point inside of the edge
+        normal.cross(&edge).normalize().dot(&(t[i] - p)) <= tolerance
+    })
+}
+
+/// Split every face of `mesh` along where it crosses `other` and find
+/// which side of `other` each resulting triangle is on, along with the
+/// index of the face each triangle was split from.
+fn split_mesh(mesh: &Trimesh, other: &Trimesh, cuts: &[Cuts], tolerance: f64) -> Vec<Piece> {
+    let normals = mesh.face_normals();
+    let other_normals = other.face_normals();
+    let pieces: Vec<([Point3<f64>; 3], Option<Side>, usize)> = cuts
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(face, cut)| {
+            let t = triangle(mesh, face);
+            let mut pieces = vec![t];
+            for line in cut.lines.iter() {
+                pieces = split_triangles(pieces, line, &normals[face], tolerance);
+            }
+            // faces in the same plane are cut by each others' edges
+            for c in cut.coplanar.iter() {
+                let s = triangle(other, *c);
+                for i in 0..3 {
+                    let line = (s[i], s[(i + 1) % 3] - s[i]);
+                    pieces = split_triangles(pieces, &line, &normals[face], tolerance);
+                }
+            }
+
+            pieces
+                .into_iter()
+                .map(|piece| {
+                    let center =
+                        Point3::from((piece[0].coords + piece[1].coords + piece[2].coords) / 3.0);
+                    // pieces lying on a face of the other mesh can't be
+                    // classified by winding number which is unstable there
+                    let side = cut.coplanar.iter().find_map(|c| {
+                        in_triangle(&center, &triangle(other, *c), tolerance).then(|| {
+                            if normals[face].dot(&other_normals[*c]) > 0.0 {
+                                Side::Same
+                            } else {
+                                Side::Opposite
+                            }
+                        })
+                    });
+                    (piece, side, face)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    // everything else is inside or outside by winding number
+    let centers: Vec<Point3<f64>> = pieces
+        .iter()
+        .map(|(p, _, _)| Point3::from((p[0].coords + p[1].coords + p[2].coords) / 3.0))
+        .collect();
+    let winding = other.winding_number(&centers);
+    pieces
+        .into_iter()
+        .zip(winding)
+        .map(|((piece, side, face), w)| {
+            let side = side.unwrap_or(if w > 0.5 { Side::Inside } else { Side::Outside });
+            (piece, side, face)
+        })
+        .collect()
+}
+
+/// Split faces where a vertex lies on one of their edges, which
+/// connects surfaces that were split at different points along
+/// the same edge into a closed mesh. Returns the new faces and the
+/// index of the face each of them was split from.
+fn repair_t_junctions(
+    vertices: &[Point3<f64>],
+    mut faces: Vec<(usize, usize, usize)>,
+    tolerance: f64,
+) -> (Vec<(usize, usize, usize)>, Vec<usize>) {
+    let mut parents: Vec<usize> = (0..faces.len()).collect();
+    // every split adds a face so this terminates well before the limit
+    for _ in 0..64 {
+        let mut edges: AHashMap<(usize, usize), usize> = AHashMap::with_capacity(faces.len() * 3);
+        for (index, f) in faces.iter().enumerate() {
+            for edge in [(f.0, f.1), (f.1, f.2), (f.2, f.0)] {
+                edges.insert(edge, index);
+            }
+        }
+        let boundary: Vec<((usize, usize), usize)> = edges
+            .iter()
+            .filter(|(edge, _)| !edges.contains_key(&(edge.1, edge.0)))
+            .map(|(edge, face)| (*edge, *face))
+            .collect();
+        if boundary.is_empty() {
+            break;
+        }
+        let mut candidates: Vec<usize> = boundary.iter().flat_map(|(e, _)| [e.0, e.1]).collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        // the vertices on each boundary edge ordered along the edge
+        let mut splits = AHashMap::new();
+        for ((a, b), face) in boundary {
+            if splits.contains_key(&face) {
+                continue;
+            }
+            let (start, end) = (vertices[a], vertices[b]);
+            let vector = end - start;
+            let length = vector.norm();
+            let mut on: Vec<(f64, usize)> = candidates
+                .iter()
+                .filter(|c| **c != a && **c != b)
+                .filter_map(|c| {
+                    let t = vector.dot(&(vertices[*c] - start)) / length;
+                    let distance = (start + vector * (t / length) - vertices[*c]).norm();
+                    (t > tolerance && t < length - tolerance && distance <= tolerance)
+                        .then_some((t, *c))
+                })
+                .collect();
+            if !on.is_empty() {
+                on.sort_by(|x, y| x.0.total_cmp(&y.0));
+                splits.insert(face, ((a, b), on));
+            }
+        }
+        if splits.is_empty() {
+            break;
+        }
+
+        let mut result = Vec::with_capacity(faces.len() + splits.len() * 2);
+        let mut split_parents = Vec::with_capacity(result.capacity());
+        for (index, f) in faces.iter().enumerate() {
+            let Some(((a, b), on)) = splits.get(&index) else {
+                result.push(*f);
+                split_parents.push(parents[index]);
+                continue;
+            };
+            let opposite = [f.0, f.1, f.2]
+                .into_iter()
+                .find(|v| v != a && v != b)
+                .unwrap_or(f.0);
+            let chain: Vec<usize> = std::iter::once(*a)
+                .chain(on.iter().map(|(_, v)| *v))
+                .chain(std::iter::once(*b))
+                .collect();
+            for pair in chain.windows(2) {
+                result.push((pair[0], pair[1], opposite));
+                split_parents.push(parents[index]);
+            }
+        }
+        faces = result;
+        parents = split_parents;
+    }
+    (faces, parents)
+}
+
+/// The tolerance for cutting two meshes, relative to their size.
+fn tolerance(a: &Trimesh, b: &Trimesh) -> f64 {
+    let scale = [a.bounds(), b.bounds()]
+        .iter()
+        .flatten()
+        .map(|(lower, upper)| (upper - lower).amax())
+        .fold(0.0, f64::max);
+    scale * 1e-10
+}
+
+/// A triangle split from a face of a mesh, which side of the other
+/// mesh it is on and the index of the face it was split from.
+type Piece = ([Point3<f64>; 3], Side, usize);
+
+/// Split the faces of two meshes where they cross each other and find
+/// which side of the other mesh every resulting piece is on.
+fn classify(a: &Trimesh, b: &Trimesh, tolerance: f64) -> (Vec<Piece>, Vec<Piece>) {
+    // find every pair of faces which cut each other
+    let bvh: std::sync::Arc<Bvh> = b.bvh();
+    let pairs: Vec<(usize, usize, Option<Line>)> = (0..a.faces.len())
+        .into_par_iter()
+        .flat_map_iter(|face| {
+            let t = triangle(a, face);
+            let padding = Vector3::repeat(tolerance);
+            let bounds = (
+                t[0].inf(&t[1]).inf(&t[2]) - padding,
+                t[0].sup(&t[1]).sup(&t[2]) + padding,
+            );
+            let mut found = Vec::new();
+            bvh.query(&bounds, |other| {
+                if let Some(cut) = cut_triangles(&t, &triangle(b, other), tolerance) {
+                    found.push((face, other, cut));
+                }
+            });
+            found
+        })
+        .collect();
+
+    let mut cuts_a = vec![Cuts::default(); a.faces.len()];
+    let mut cuts_b = vec![Cuts::default(); b.faces.len()];
+    for (fa, fb, cut) in pairs {
+        match cut {
+            Some(line) => {
+                cuts_a[fa].lines.push(line);
+                cuts_b[fb].lines.push(line);
+            }
+            None => {
+                cuts_a[fa].coplanar.push(fb);
+                cuts_b[fb].coplanar.push(fa);
+            }
+        }
+    }
+    (
+        split_mesh(a, b, &cuts_a, tolerance),
+        split_mesh(b, a, &cuts_b, tolerance),
+    )
+}
+
+/// Compute the volume of the overlap between two closed meshes without
+/// constructing the boolean intersection surface. The faces of each
+/// mesh are split and classified exactly as `boolean` does, and the
+/// signed volumes of the pieces of either surface inside the other are
+/// summed directly, which skips welding the pieces into a mesh.
+///
+/// Parameters
+/// ------------
+/// a
+///   A closed, consistently wound mesh.
+/// b
+///   Another closed, consistently wound mesh.
+///
+/// Returns
+/// ------------
+/// volume
+///   The volume inside both meshes, or an error if either isn't closed.
+pub fn intersection_volume(a: &Trimesh, b: &Trimesh) -> Result<f64> {
+    if !is_closed(a) || !is_closed(b) {
+        return Err(anyhow!(
+            "Mesh must be closed and consistently wound to compute an intersection volume"
+        ));
+    }
+    let (Some(bounds_a), Some(bounds_b)) = (a.bounds(), b.bounds()) else {
+        return Ok(0.0);
+    };
+    if (0..3).any(|i| bounds_a.0[i] > bounds_b.1[i] || bounds_b.0[i] > bounds_a.1[i]) {
+        return Ok(0.0);
+    }
+
+    let (pieces_a, pieces_b) = classify(a, b, tolerance(a, b));
+    // measure from the middle of the overlap to keep the products small
+    let origin = nalgebra::center(&bounds_a.0.sup(&bounds_b.0), &bounds_a.1.inf(&bounds_b.1));
+    let signed = |t: &[Point3<f64>; 3]| {
+        let (p, q, r) = (t[0] - origin, t[1] - origin, t[2] - origin);
+        p.dot(&q.cross(&r)) / 6.0
+    };
+    // coplanar pieces facing the same way are counted once from `a`
+    let volume: f64 = pieces_a
+        .par_iter()
+        .filter(|(_, side, _)| matches!(side, Side::Inside | Side::Same))
+        .map(|(t, _, _)| signed(t))
+        .sum::<f64>()
+        + pieces_b
+            .par_iter()
+            .filter(|(_, side, _)| *side == Side::Inside)
+            .map(|(t, _, _)| signed(t))
+            .sum::<f64>();
+
+    // cancellation between signed pieces can leave a tiny negative value
+    Ok(volume.max(0.0))
+}
+
+/// Compute a boolean operation between the volumes enclosed by two
+/// closed meshes. Faces of each mesh are split where they cross the
+/// other mesh using a bounding volume hierarchy to find candidate
+/// pairs, the resulting pieces are kept or discarded depending on
+/// whether they are inside the other mesh, and the kept pieces are
+/// welded back together into a closed mesh.
+///
+/// Every piece keeps the face attributes of the face it was split from
+/// for the attributes both meshes have, and the materials of `b` follow
+/// the materials of `a` with its material indices offset to match.
+///
+/// Parameters
+/// ------------
+/// a
+///   A closed, consistently wound mesh.
+/// b
+///   Another closed, consistently wound mesh.
+/// operation
+///   Which boolean operation to compute.
+///
+/// Returns
+/// ------------
+/// result
+///   A new closed mesh, or an error if either mesh isn't closed.
+pub fn boolean(a: &Trimesh, b: &Trimesh, operation: BooleanOperation) -> Result<Trimesh> {
+    if !is_closed(a) || !is_closed(b) {
+        return Err(anyhow!(
+            "Meshes must be closed and consistently wound for boolean operations"
+        ));
+    }
+    let tolerance = tolerance(a, b);
+    let (pieces_a, pieces_b) = classify(a, b, tolerance);
+
+    use BooleanOperation::*;
+    use Side::*;
+    let keep_a = |side: Side| match operation {
+        Union => matches!(side, Outside | Same),
+        Intersection => matches!(side, Inside | Same),
+        Difference => matches!(side, Outside | Opposite),
+    };
+    // coplanar faces are kept from the first mesh so there is only one copy
+    let keep_b = |side: Side| match operation {
+        Union => side == Outside,
+        Intersection | Difference => side == Inside,
+    };
+    // the second mesh is inside out when subtracted
+    let flip_b = operation == Difference;
+
+    let (mut triangles, source_a): (Vec<[Point3<f64>; 3]>, Vec<usize>) = pieces_a
+        .into_iter()
+        .filter(|(_, side, _)| keep_a(*side))
+        .map(|(t, _, face)| (t, face))
+        .unzip();
+    let (pieces_b, source_b): (Vec<[Point3<f64>; 3]>, Vec<usize>) = pieces_b
+        .into_iter()
+        .filter(|(_, side, _)| keep_b(*side))
+        .map(|(t, _, face)| (if flip_b { [t[0], t[2], t[1]] } else { t }, face))
+        .unzip();
+    triangles.extend(pieces_b);
+
+    // the second mesh's materials come after the first's
+    let mut attributes_b = b.attributes_face.clone();
+    for grouping in attributes_b.groupings.iter_mut() {
+        if matches!(grouping.kind, GroupingKind::MaterialIndex) {
+            grouping
+                .indices
+                .iter_mut()
+                .for_each(|i| *i += a.materials.len());
+        }
+    }
+    let attributes_face = a
+        .attributes_face
+        .select(&source_a, a.faces.len())
+        .concatenate(
+            source_a.len(),
+            &attributes_b.select(&source_b, b.faces.len()),
+            source_b.len(),
+        );
+
+    // weld the triangle soup and connect any split edges
+    let soup = Trimesh::new(
+        triangles.iter().flatten().copied().collect(),
+        (0..triangles.len())
+            .map(|i| (i * 3, i * 3 + 1, i * 3 + 2))
+            .collect(),
+        None,
+        Some(attributes_face),
+    )?
+    .merge_vertices(tolerance * 10.0)?;
+    let count = soup.faces.len();
+    let (faces, parents) = repair_t_junctions(&soup.vertices, soup.faces, tolerance * 10.0);
+    let mut result = Trimesh::new(
+        soup.vertices,
+        faces,
+        None,
+        Some(soup.attributes_face.select(&parents, count)),
+    )?;
+    result.materials = a
+        .materials
+        .iter()
+        .chain(b.materials.iter())
+        .cloned()
+        .collect();
+    Ok(result)
+}
+
+/// The volume inside either of two closed meshes.
+pub fn union(a: &Trimesh, b: &Trimesh) -> Result<Trimesh> {
+    boolean(a, b, BooleanOperation::Union)
+}
+
+/// The volume inside both of two closed meshes.
+pub fn intersection(a: &Trimesh, b: &Trimesh) -> Result<Trimesh> {
+    boolean(a, b, BooleanOperation::Intersection)
+}
+
+/// The volume inside the first closed mesh but not the second.
+pub fn difference(a: &Trimesh, b: &Trimesh) -> Result<Trimesh> {
+    boolean(a, b, BooleanOperation::Difference)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::{create_box, create_sphere};
+    use approx::assert_relative_eq;
+
+    /// Move every vertex of a mesh by an offset.
+    fn shifted(mesh: &Trimesh, x: f64, y: f64, z: f64) -> Trimesh {
+        let offset = Vector3::new(x, y, z);
+        Trimesh::new(
+            mesh.vertices.iter().map(|v| v + offset).collect(),
+            mesh.faces.clone(),
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    /// The volume enclosed by a closed mesh.
+    fn volume(mesh: &Trimesh) -> f64 {
+        mesh.faces
+            .iter()
+            .map(|f| {
+                let (a, b, c) = (mesh.vertices[f.0], mesh.vertices[f.1], mesh.vertices[f.2]);
+                a.coords.dot(&b.coords.cross(&c.coords)) / 6.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_boolean() {
+        let a = create_box(&[2.0, 2.0, 2.0]);
+
+        // overlapping by a corner
+        let b = shifted(&a, 1.0, 1.0, 1.0);
+        for (operation, expected) in [
+            (BooleanOperation::Union, 15.0),
+            (BooleanOperation::Intersection, 1.0),
+            (BooleanOperation::Difference, 7.0),
+        ] {
+            let result = boolean(&a, &b, operation).unwrap();
+            assert!(is_closed(&result), "{operation:?} isn't closed");
+            assert_relative_eq!(volume(&result), expected, epsilon = 1e-8);
+        }
+
+        // sharing the planes of four sides
+        let c = shifted(&a, 1.0, 0.0, 0.0);
+        for (operation, expected) in [
+            (BooleanOperation::Union, 12.0),
+            (BooleanOperation::Intersection, 4.0),
+            (BooleanOperation::Difference, 4.0),
+        ] {
+            let result = boolean(&a, &c, operation).unwrap();
+            assert!(is_closed(&result), "{operation:?} isn't closed");
+            assert_relative_eq!(volume(&result), expected, epsilon = 1e-8);
+        }
+
+        // a box entirely inside another leaves a cavity
+        let small = create_box(&[1.0, 1.0, 1.0]);
+        let hollow = difference(&a, &small).unwrap();
+        assert!(is_closed(&hollow));
+        assert_eq!(hollow.faces.len(), 24);
+        assert_relative_eq!(volume(&hollow), 7.0, epsilon = 1e-8);
+        assert_relative_eq!(
+            volume(&intersection(&a, &small).unwrap()),
+            1.0,
+            epsilon = 1e-8
+        );
+
+        // disjoint meshes
+        let far = shifted(&a, 5.0, 0.0, 0.0);
+        assert_relative_eq!(volume(&union(&a, &far).unwrap()), 16.0, epsilon = 1e-8);
+        assert!(intersection(&a, &far).unwrap().is_empty());
+
+        let mut open = a.clone();
+        open.faces.pop();
+        assert!(union(&open, &b).is_err());
+    }
+
+    #[test]
+    fn test_intersection_volume() {
+        let a = create_box(&[2.0, 2.0, 2.0]);
+        assert_relative_eq!(intersection_volume(&a, &a).unwrap(), 8.0, epsilon = 1e-10);
+
+        // shifted by half a box along X overlaps by half
+        let b = shifted(&a, 1.0, 0.0, 0.0);
+        assert_relative_eq!(intersection_volume(&a, &b).unwrap(), 4.0, epsilon = 1e-10);
+
+        // shifted along every axis overlaps by an eighth
+        let c = shifted(&a, 1.0, 1.0, 1.0);
+        assert_relative_eq!(intersection_volume(&a, &c).unwrap(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(intersection_volume(&c, &a).unwrap(), 1.0, epsilon = 1e-10);
+
+        // a box inside another is entirely overlapped
+        let small = create_box(&[0.5, 0.5, 0.5]);
+        assert_relative_eq!(
+            intersection_volume(&a, &small).unwrap(),
+            0.125,
+            epsilon = 1e-10
+        );
+
+        // disjoint meshes don't overlap
+        let far = shifted(&a, 5.0, 0.0, 0.0);
+        assert_eq!(intersection_volume(&a, &far).unwrap(), 0.0);
+
+        // an open mesh has no inside
+        let mut open = a.clone();
+        open.faces.pop();
+        assert!(intersection_volume(&open, &b).is_err());
+    }
+
+    #[test]
+    fn test_intersection_volume_cost() {
+        let a = create_sphere(1.0, [16, 16]).unwrap();
+        let b = shifted(&a, 0.7, 0.3, 0.1);
+
+        // the fastest of a few runs is least affected by other tests
+        let fastest = |f: &dyn Fn() -> f64| {
+            (0..3)
+                .map(|_| {
+                    let start = std::time::Instant::now();
+                    let volume = f();
+                    (start.elapsed(), volume)
+                })
+                .min_by_key(|(elapsed, _)| *elapsed)
+                .unwrap()
+        };
+        let (direct, expected) = fastest(&|| intersection_volume(&a, &b).unwrap());
+        let (surface, measured) = fastest(&|| volume(&intersection(&a, &b).unwrap()));
+        assert_relative_eq!(expected, measured, epsilon = 1e-8);
+        // both share the classification which dominates, so only check
+        // that skipping the welding never makes the volume slower
+        assert!(
+            direct.as_secs_f64() < surface.as_secs_f64() * 1.5,
+            "intersection_volume took {direct:?} but intersection took {surface:?}"
+        );
+    }
+}
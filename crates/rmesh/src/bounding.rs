@@ -0,0 +1,469 @@
+use nalgebra::{
+    DMatrix, DVector, Matrix3, Matrix4, Point2, Point3, SymmetricEigen, Vector2, Vector3,
+};
+use rayon::prelude::*;
+use rmesh_macro::cache_access;
+
+use crate::creation::{Primitive, PrimitiveKind};
+use crate::mesh::Trimesh;
+use crate::pose::convex_hull_2d;
+
+/// The principal axes of a cloud of points, which are the
+/// eigenvectors of the covariance of the points.
+fn principal_axes(points: &[Point3<f64>]) -> [Vector3<f64>; 3] {
+    let count = points.len().max(1) as f64;
+    let mean = points.iter().fold(Vector3::zeros(), |s, p| s + p.coords) / count;
+    let covariance = points.iter().fold(Matrix3::zeros(), |s, p| {
+        let d = p.coords - mean;
+        s + d * d.transpose()
+    });
+    let eigen = SymmetricEigen::new(covariance);
+    [0, 1, 2].map(|i| eigen.eigenvectors.column(i).into())
+}
+
+/// The bounds of points along a direction and the direction
+/// a quarter turn counter-clockwise from it.
+fn rectangle_bounds(points: &[Point2<f64>], side: &Vector2<f64>) -> (Point2<f64>, Point2<f64>) {
+    let normal = Vector2::new(-side.y, side.x);
+    points.iter().fold(
+        (
+            Point2::new(f64::INFINITY, f64::INFINITY),
+            Point2::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+        ),
+        |(lower, upper), p| {
+            let q = Point2::new(p.coords.dot(side), p.coords.dot(&normal));
+            (lower.inf(&q), upper.sup(&q))
+        },
+    )
+}
+
+/// The smallest rectangle containing a convex polygon, which has a
+/// side flush with one of the polygon's edges.
+///
+/// Returns
+/// ------------
+/// rectangle
+///   The area, the direction of the first side and the lower
+///   and upper bounds of the polygon along both sides.
+fn minimum_rectangle(hull: &[Point2<f64>]) -> (f64, Vector2<f64>, Point2<f64>, Point2<f64>) {
+    let mut best = (
+        f64::INFINITY,
+        Vector2::x(),
+        Point2::origin(),
+        Point2::origin(),
+    );
+    for (i, a) in hull.iter().enumerate() {
+        let Some(side) = (hull[(i + 1) % hull.len()] - a).try_normalize(0.0) else {
+            continue;
+        };
+        let (lower, upper) = rectangle_bounds(hull, &side);
+        let area = (upper.x - lower.x) * (upper.y - lower.y);
+        if area < best.0 {
+            best = (area, side, lower, upper);
+        }
+    }
+    best
+}
+
+/// The smallest sphere with every point of a support set on it, or
+/// the sphere around their centroid if the points are degenerate.
+fn circumsphere(support: &[Point3<f64>]) -> (Point3<f64>, f64) {
+    let Some(first) = support.first() else {
+        return (Point3::origin(), -1.0);
+    };
+    if support.len() == 1 {
+        return (*first, 0.0);
+    }
+    // the center is the first point plus a combination of the
+    // vectors to the others equidistant from all of them
+    let vectors: Vec<Vector3<f64>> = support[1..].iter().map(|p| p - first).collect();
+    let count = vectors.len();
+    let gram = DMatrix::from_fn(count, count, |i, j| 2.0 * vectors[i].dot(&vectors[j]));
+    let lengths = DVector::from_fn(count, |i, _| vectors[i].norm_squared());
+    let center = match gram.lu().solve(&lengths) {
+        Some(weights) if weights.iter().all(|w| w.is_finite()) => {
+            first
+                + vectors
+                    .iter()
+                    .zip(weights.iter())
+                    .map(|(v, w)| v * *w)
+                    .sum::<Vector3<f64>>()
+        }
+        _ => Point3::from(
+            support.iter().map(|p| p.coords).sum::<Vector3<f64>>() / support.len() as f64,
+        ),
+    };
+    let radius = support
+        .iter()
+        .map(|p| (p - center).norm())
+        .fold(0.0, f64::max);
+    (center, radius)
+}
+
+/// The smallest sphere containing the first `count` points with the
+/// support points on its surface, using the move-to-front variant of
+/// Welzl's algorithm so the recursion is only as deep as the support.
+fn miniball(
+    points: &mut [Point3<f64>],
+    count: usize,
+    support: &mut Vec<Point3<f64>>,
+    limit: usize,
+) -> (Point3<f64>, f64) {
+    let mut ball = circumsphere(support);
+    if support.len() == limit {
+        return ball;
+    }
+    for i in 0..count {
+        let (center, radius) = ball;
+        // allow for the rounding of points already on the sphere
+        if (points[i] - center).norm() <= radius * (1.0 + 1e-12) + f64::EPSILON {
+            continue;
+        }
+        support.push(points[i]);
+        ball = miniball(points, i, support, limit);
+        support.pop();
+        // points which were outside are likely to be outside later
+        points[..=i].rotate_right(1);
+    }
+    ball
+}
+
+/// The smallest cylinder containing points with its axis along one of
+/// several candidate directions, which for the axes of the faces of the
+/// convex hull includes the smallest cylinder of most shapes since a
+/// cap is usually flush with a face.
+///
+/// Parameters
+/// ------------
+/// points
+///   The points the cylinder should contain.
+/// axes
+///   The candidate directions of the axis of the cylinder.
+///
+/// Returns
+/// ------------
+/// cylinder
+///   A cylinder primitive with the transform placing its center and
+///   its axis on Z, or `None` if there are no points or valid axes.
+pub fn minimum_cylinder(points: &[Point3<f64>], axes: &[Vector3<f64>]) -> Option<Primitive> {
+    if points.is_empty() {
+        return None;
+    }
+    let cylinders = axes.par_iter().filter_map(|axis| {
+        let w = axis.try_normalize(f64::EPSILON)?;
+        let seed = if w.x.abs() < 0.9 {
+            Vector3::x()
+        } else {
+            Vector3::y()
+        };
+        let u = w.cross(&seed).normalize();
+        let v = w.cross(&u);
+        // the smallest circle around the points flattened along the axis
+        let mut flat: Vec<Point3<f64>> = points
+            .iter()
+            .map(|p| Point3::new(p.coords.dot(&u), p.coords.dot(&v), 0.0))
+            .collect();
+        let count = flat.len();
+        let (center, radius) = miniball(&mut flat, count, &mut Vec::new(), 3);
+        let (near, far) = points
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(l, h), p| {
+                let d = p.coords.dot(&w);
+                (l.min(d), h.max(d))
+            });
+        let height = far - near;
+        let origin = u * center.x + v * center.y + w * (near + far) / 2.0;
+        let volume = radius * radius * height;
+        Some((volume, radius, height, [u, v, w], origin))
+    });
+    let (_, radius, height, axes, origin) = cylinders.min_by(|a, b| a.0.total_cmp(&b.0))?;
+    let mut transform = Matrix3::from_columns(&axes).to_homogeneous();
+    transform.fixed_view_mut::<3, 1>(0, 3).copy_from(&origin);
+    Some(Primitive {
+        kind: PrimitiveKind::Cylinder { radius, height },
+        transform,
+    })
+}
+
+impl Trimesh {
+    /// An oriented box containing the mesh with close to the smallest
+    /// volume. Every face of the convex hull and the principal axes of
+    /// the vertices are tried as the direction of one side of the box,
+    /// and for each the smallest rectangle around the hull projected
+    /// along it is found with rotating calipers. The smallest box is
+    /// always flush with a hull face or nearly so, which makes this
+    /// exact for most shapes and close for the rest.
+    ///
+    /// Returns
+    /// ------------
+    /// box
+    ///   A box primitive with the extents of the box and the transform
+    ///   placing its center and axes in the frame of the mesh, or `None`
+    ///   for a mesh without vertices. A flat mesh has a zero extent.
+    #[cache_access]
+    pub fn bounding_box_oriented(&self) -> Option<Primitive> {
+        if self.vertices.is_empty() {
+            return None;
+        }
+        // only the vertices of the hull can touch the box
+        let (points, mut normals) = match self.convex_hull() {
+            Ok(hull) => (hull.vertices.clone(), hull.face_normals()),
+            Err(_) => (self.vertices.clone(), Vec::new()),
+        };
+        normals.extend(principal_axes(&points));
+
+        // the volume, axes and bounds along them of the box around each normal
+        let boxes = normals.par_iter().filter_map(|normal| {
+            let w = normal.try_normalize(f64::EPSILON)?;
+            let seed = if w.x.abs() < 0.9 {
+                Vector3::x()
+            } else {
+                Vector3::y()
+            };
+            let u = w.cross(&seed).normalize();
+            let v = w.cross(&u);
+            let projected: Vec<Point2<f64>> = points
+                .iter()
+                .map(|p| Point2::new(p.coords.dot(&u), p.coords.dot(&v)))
+                .collect();
+            let hull = convex_hull_2d(projected);
+            let (area, side, lower, upper) = match hull.len() {
+                0 => return None,
+                // a line or a point has no edges to be flush with
+                1 | 2 => {
+                    let side = (hull[hull.len() - 1] - hull[0])
+                        .try_normalize(0.0)
+                        .unwrap_or_else(Vector2::x);
+                    let (lower, upper) = rectangle_bounds(&hull, &side);
+                    (0.0, side, lower, upper)
+                }
+                _ => minimum_rectangle(&hull),
+            };
+            let x = u * side.x + v * side.y;
+            let y = w.cross(&x);
+            let (near, far) =
+                points
+                    .iter()
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(l, h), p| {
+                        let d = p.coords.dot(&w);
+                        (l.min(d), h.max(d))
+                    });
+            let lower = Vector3::new(lower.x, lower.y, near);
+            let upper = Vector3::new(upper.x, upper.y, far);
+            Some((area * (far - near), [x, y, w], lower, upper))
+        });
+        // boxes around flat meshes all have no volume so
+        // prefer the one with the smallest surface
+        let surface = |lower: &Vector3<f64>, upper: &Vector3<f64>| {
+            let e = upper - lower;
+            e.x * e.y + e.y * e.z + e.z * e.x
+        };
+        let (_, axes, lower, upper) = boxes.min_by(|a, b| {
+            a.0.total_cmp(&b.0)
+                .then(surface(&a.2, &a.3).total_cmp(&surface(&b.2, &b.3)))
+        })?;
+
+        let rotation = Matrix3::from_columns(&axes);
+        let center = rotation * (lower + upper) / 2.0;
+        let mut transform = rotation.to_homogeneous();
+        transform.fixed_view_mut::<3, 1>(0, 3).copy_from(&center);
+        let extents = upper - lower;
+        Some(Primitive {
+            kind: PrimitiveKind::Box {
+                extents: [extents.x, extents.y, extents.z],
+            },
+            transform,
+        })
+    }
+
+    /// The smallest sphere containing every vertex of the mesh, found
+    /// exactly from the vertices of the convex hull with Welzl's algorithm.
+    ///
+    /// Returns
+    /// ------------
+    /// sphere
+    ///   A sphere primitive with the transform moving it to its center,
+    ///   or `None` for a mesh without vertices.
+    pub fn bounding_sphere(&self) -> Option<Primitive> {
+        if self.vertices.is_empty() {
+            return None;
+        }
+        let mut points = match self.convex_hull() {
+            Ok(hull) => hull.vertices,
+            Err(_) => self.vertices.clone(),
+        };
+        let count = points.len();
+        let (center, radius) = miniball(&mut points, count, &mut Vec::new(), 4);
+        Some(Primitive {
+            kind: PrimitiveKind::Sphere { radius },
+            transform: Matrix4::new_translation(&center.coords),
+        })
+    }
+
+    /// A cylinder containing every vertex of the mesh with close to the
+    /// smallest volume, trying the normal of every face of the convex hull,
+    /// the principal axes of the vertices and the axes of the oriented
+    /// bounding box as the axis with `minimum_cylinder`.
+    ///
+    /// Returns
+    /// ------------
+    /// cylinder
+    ///   A cylinder primitive with the transform placing its center and
+    ///   its axis on Z, or `None` for a mesh without vertices.
+    pub fn bounding_cylinder(&self) -> Option<Primitive> {
+        if self.vertices.is_empty() {
+            return None;
+        }
+        let (points, mut axes) = match self.convex_hull() {
+            Ok(hull) => (hull.vertices.clone(), hull.face_normals()),
+            Err(_) => (self.vertices.clone(), Vec::new()),
+        };
+        // opposite and repeated normals give the same cylinder
+        let mut seen = ahash::AHashSet::new();
+        axes.retain(|a| {
+            let a = if a[a.iamax()] < 0.0 { -a } else { *a };
+            seen.insert(a.map(|v| (v * 1e9).round() as i64))
+        });
+        axes.extend(principal_axes(&points));
+        if let Some(obb) = self.bounding_box_oriented() {
+            axes.extend((0..3).map(|i| obb.transform.fixed_view::<3, 1>(0, i).into_owned()));
+        }
+        minimum_cylinder(&points, &axes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::{create_box, create_cylinder, create_sphere};
+    use approx::assert_relative_eq;
+    use nalgebra::Rotation3;
+
+    /// The extents of a box primitive from smallest to largest.
+    fn sorted(primitive: &Primitive) -> Vector3<f64> {
+        let PrimitiveKind::Box { extents } = primitive.kind else {
+            panic!("Expected a box");
+        };
+        let mut extents = extents;
+        extents.sort_by(f64::total_cmp);
+        Vector3::from(extents)
+    }
+
+    #[test]
+    fn test_bounding_box_oriented() {
+        let rotation = Rotation3::from_euler_angles(0.3, -1.1, 2.0).to_homogeneous();
+        let placed = create_box(&[1.0, 2.0, 3.0])
+            .apply_transform(&rotation)
+            .unwrap()
+            .apply_translation(&Vector3::new(4.0, -2.0, 7.0))
+            .unwrap();
+        let obb = placed.bounding_box_oriented().unwrap();
+        assert_relative_eq!(sorted(&obb), Vector3::new(1.0, 2.0, 3.0), epsilon = 1e-8);
+        assert_relative_eq!(
+            obb.transform.fixed_view::<3, 1>(0, 3).into_owned(),
+            Vector3::new(4.0, -2.0, 7.0),
+            epsilon = 1e-8
+        );
+        assert_relative_eq!(
+            obb.transform.fixed_view::<3, 3>(0, 0).determinant(),
+            1.0,
+            epsilon = 1e-12
+        );
+
+        // every vertex is inside the box
+        let inverse = obb.transform.try_inverse().unwrap();
+        let PrimitiveKind::Box { extents } = obb.kind else {
+            unreachable!()
+        };
+        for vertex in placed.vertices.iter() {
+            let local = inverse.transform_point(vertex);
+            for i in 0..3 {
+                assert!(local[i].abs() <= extents[i] / 2.0 + 1e-8);
+            }
+        }
+
+        // a sphere's box is about its diameter on every side
+        let sphere = create_sphere(1.0, [16, 16])
+            .unwrap()
+            .bounding_box_oriented()
+            .unwrap();
+        assert!(sorted(&sphere).iter().all(|e| *e > 1.9 && *e <= 2.0 + 1e-8));
+
+        // a flat square has no thickness
+        let flat = Trimesh::new(
+            vec![
+                Point3::new(0.0, 0.0, 0.0),
+                Point3::new(1.0, 1.0, 0.0),
+                Point3::new(0.0, 2.0, 0.0),
+                Point3::new(-1.0, 1.0, 0.0),
+            ],
+            vec![(0, 1, 2), (0, 2, 3)],
+            None,
+            None,
+        )
+        .unwrap();
+        let obb = flat.bounding_box_oriented().unwrap();
+        assert_relative_eq!(
+            sorted(&obb),
+            Vector3::new(0.0, 2.0_f64.sqrt(), 2.0_f64.sqrt()),
+            epsilon = 1e-8
+        );
+        assert!(Trimesh::default().bounding_box_oriented().is_none());
+    }
+
+    #[test]
+    fn test_bounding_sphere_cylinder() {
+        let offset = Vector3::new(1.0, -3.0, 2.0);
+        let sphere = create_sphere(1.0, [16, 16])
+            .unwrap()
+            .apply_translation(&offset)
+            .unwrap();
+        let ball = sphere.bounding_sphere().unwrap();
+        let PrimitiveKind::Sphere { radius } = ball.kind else {
+            panic!("Expected a sphere");
+        };
+        assert_relative_eq!(radius, 1.0, epsilon = 1e-8);
+        assert_relative_eq!(
+            ball.transform.fixed_view::<3, 1>(0, 3).into_owned(),
+            offset,
+            epsilon = 1e-8
+        );
+
+        // the corners of a box are all on its smallest sphere
+        let ball = create_box(&[1.0, 2.0, 3.0]).bounding_sphere().unwrap();
+        let PrimitiveKind::Sphere { radius } = ball.kind else {
+            panic!("Expected a sphere");
+        };
+        assert_relative_eq!(radius, 14.0_f64.sqrt() / 2.0, epsilon = 1e-8);
+
+        // a rotated cylinder is its own bounding cylinder
+        let rotation = Rotation3::from_euler_angles(0.7, 0.2, -1.3).to_homogeneous();
+        let placed = create_cylinder(1.0, 3.0, 64)
+            .unwrap()
+            .apply_transform(&rotation)
+            .unwrap()
+            .apply_translation(&offset)
+            .unwrap();
+        let fit = placed.bounding_cylinder().unwrap();
+        let PrimitiveKind::Cylinder { radius, height } = fit.kind else {
+            panic!("Expected a cylinder");
+        };
+        assert_relative_eq!(radius, 1.0, epsilon = 1e-8);
+        assert_relative_eq!(height, 3.0, epsilon = 1e-8);
+        // the axis is the rotated Z axis in either direction
+        let axis = fit.transform.fixed_view::<3, 1>(0, 2).into_owned();
+        let expected = rotation.fixed_view::<3, 1>(0, 2).into_owned();
+        assert_relative_eq!(axis.dot(&expected).abs(), 1.0, epsilon = 1e-8);
+        // every vertex is inside the cylinder
+        let inverse = fit.transform.try_inverse().unwrap();
+        for vertex in placed.vertices.iter() {
+            let local = inverse.transform_point(vertex);
+            assert!(local.xy().coords.norm() <= radius + 1e-8);
+            assert!(local.z.abs() <= height / 2.0 + 1e-8);
+        }
+
+        assert!(Trimesh::default().bounding_sphere().is_none());
+        assert!(Trimesh::default().bounding_cylinder().is_none());
+    }
+}
@@ -0,0 +1,502 @@
+use nalgebra::Point3;
+
+use crate::ray::Ray;
+
+/// The most items stored in a single leaf of the tree.
+const LEAF_SIZE: usize = 4;
+
+/// Subtrees with more items than this are built on separate threads,
+/// as below it the overhead of splitting work dominates.
+const PARALLEL_MIN_ITEMS: usize = 4096;
+
+/// An axis aligned bounding box as its lower and upper corners.
+pub type Bounds = (Point3<f64>, Point3<f64>);
+
+/// A node of a flattened tree, where the left child of an inner node
+/// always immediately follows it.
+#[derive(Debug, Clone)]
+struct BvhNode {
+    bounds: Bounds,
+    // for leaves the first item in `Bvh::items`, for inner
+    // nodes the index of the right child in `Bvh::nodes`
+    offset: usize,
+    // the number of items in a leaf or zero for inner nodes
+    count: usize,
+}
+
+/// A node of the tree while it's being built in parallel.
+enum BuildNode {
+    Leaf(Bounds, Vec<usize>),
+    Inner(Bounds, Box<BuildNode>, Box<BuildNode>),
+}
+
+/// A bounding volume hierarchy over a list of axis aligned boxes,
+/// which accelerates finding the items a ray might hit from linear
+/// to roughly logarithmic in the number of items.
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    // the item indices ordered so every leaf references a contiguous run
+    items: Vec<usize>,
+}
+
+/// The box containing two boxes.
+fn union(a: &Bounds, b: &Bounds) -> Bounds {
+    (a.0.inf(&b.0), a.1.sup(&b.1))
+}
+
+/// Recursively split items along the longest axis of their box centers.
+fn build(bounds: &[Bounds], mut items: Vec<usize>) -> BuildNode {
+    let total = items
+        .iter()
+        .map(|i| bounds[*i])
+        .reduce(|a, b| union(&a, &b))
+        .unwrap_or((Point3::origin(), Point3::origin()));
+    if items.len() <= LEAF_SIZE {
+        return BuildNode::Leaf(total, items);
+    }
+
+    let center = |i: &usize| nalgebra::center(&bounds[*i].0, &bounds[*i].1);
+    let (lower, upper) = items
+        .iter()
+        .map(center)
+        .fold((total.1, total.0), |(l, u), c| (l.inf(&c), u.sup(&c)));
+    let axis = (upper - lower).imax();
+    // every center is the same so splitting won't separate anything
+    if upper[axis] <= lower[axis] {
+        return BuildNode::Leaf(total, items);
+    }
+
+    let half = items.len() / 2;
+    items.select_nth_unstable_by(half, |a, b| center(a)[axis].total_cmp(&center(b)[axis]));
+    let right = items.split_off(half);
+    let (left, right) = if right.len() + items.len() > PARALLEL_MIN_ITEMS {
+        rayon::join(|| build(bounds, items), || build(bounds, right))
+    } else {
+        (build(bounds, items), build(bounds, right))
+    };
+    BuildNode::Inner(total, Box::new(left), Box::new(right))
+}
+
+/// The range of the ray parameter inside a box, or `None` if it
+/// misses the box or only hits it beyond `limit`.
+fn ray_box(origin: &Point3<f64>, inverse: &[f64; 3], bounds: &Bounds, limit: f64) -> Option<f64> {
+    let mut near = 0.0_f64;
+    let mut far = limit;
+    for i in 0..3 {
+        let a = (bounds.0[i] - origin[i]) * inverse[i];
+        let b = (bounds.1[i] - origin[i]) * inverse[i];
+        // `min` and `max` skip the NaN of a ray lying in a slab plane
+        near = near.max(a.min(b));
+        far = far.min(a.max(b));
+    }
+    (near <= far).then_some(near)
+}
+
+/// The distance from a point to the closest point of a box,
+/// which is zero for points inside of it.
+fn point_box(point: &Point3<f64>, bounds: &Bounds) -> f64 {
+    (bounds.0 - point)
+        .sup(&(point - bounds.1))
+        .sup(&nalgebra::Vector3::zeros())
+        .norm()
+}
+
+impl Bvh {
+    /// The approximate number of bytes the tree uses.
+    pub(crate) fn memory(&self) -> usize {
+        self.nodes.len() * std::mem::size_of::<BvhNode>()
+            + self.items.len() * std::mem::size_of::<usize>()
+    }
+
+    /// Build a tree over a list of boxes, using multiple
+    /// threads for the upper levels of large trees.
+    ///
+    /// Parameters
+    /// ------------
+    /// bounds
+    ///   The box of every item.
+    ///
+    /// Returns
+    /// ------------
+    /// bvh
+    ///   A tree referencing items by their index in `bounds`.
+    pub fn new(bounds: &[Bounds]) -> Self {
+        if bounds.is_empty() {
+            return Self::default();
+        }
+        let root = build(bounds, (0..bounds.len()).collect());
+
+        // flatten the tree depth first so the left child follows its parent
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(bounds.len() * 2 / LEAF_SIZE + 1),
+            items: Vec::with_capacity(bounds.len()),
+        };
+        let mut stack = vec![(root, None)];
+        while let Some((node, parent)) = stack.pop() {
+            // a right child finally knows where it is
+            if let Some(parent) = parent {
+                let index = bvh.nodes.len();
+                let parent: &mut BvhNode = &mut bvh.nodes[parent];
+                parent.offset = index;
+            }
+            match node {
+                BuildNode::Leaf(bounds, items) => {
+                    bvh.nodes.push(BvhNode {
+                        bounds,
+                        offset: bvh.items.len(),
+                        count: items.len(),
+                    });
+                    bvh.items.extend(items);
+                }
+                BuildNode::Inner(bounds, left, right) => {
+                    stack.push((*right, Some(bvh.nodes.len())));
+                    bvh.nodes.push(BvhNode {
+                        bounds,
+                        offset: 0,
+                        count: 0,
+                    });
+                    stack.push((*left, None));
+                }
+            }
+        }
+        bvh
+    }
+
+    /// Update the boxes of the tree for items which have moved without
+    /// rebuilding it, which keeps the same grouping of items so queries
+    /// slow down as items move far from where the tree was built.
+    ///
+    /// Parameters
+    /// ------------
+    /// bounds
+    ///   The new box of every item the tree was built with.
+    pub fn refit(&mut self, bounds: &[Bounds]) {
+        // children always come after their parent so every child
+        // is updated before the node containing it
+        for index in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[index];
+            let refit = if node.count > 0 {
+                self.items[node.offset..node.offset + node.count]
+                    .iter()
+                    .map(|i| bounds[*i])
+                    .reduce(|a, b| union(&a, &b))
+                    .unwrap_or(node.bounds)
+            } else {
+                union(
+                    &self.nodes[index + 1].bounds,
+                    &self.nodes[node.offset].bounds,
+                )
+            };
+            self.nodes[index].bounds = refit;
+        }
+    }
+
+    /// Combine a value for every node of the tree from the items below
+    /// it, such as the total area of the faces in each subtree, so a
+    /// query with `descend` can use a whole subtree at once.
+    ///
+    /// Parameters
+    /// ------------
+    /// leaf
+    ///   The value of the items of a leaf.
+    /// combine
+    ///   The value of an inner node from the values of its children.
+    ///
+    /// Returns
+    /// ------------
+    /// values
+    ///   The value of every node by the index `descend` passes.
+    pub fn fold_nodes<T>(
+        &self,
+        leaf: impl Fn(&[usize]) -> T,
+        combine: impl Fn(&T, &T) -> T,
+    ) -> Vec<T> {
+        // children always come after their parent so both
+        // children are combined before the node containing them
+        let mut values: Vec<Option<T>> = Vec::with_capacity(self.nodes.len());
+        values.resize_with(self.nodes.len(), || None);
+        for index in (0..self.nodes.len()).rev() {
+            let node = &self.nodes[index];
+            values[index] = Some(if node.count > 0 {
+                leaf(&self.items[node.offset..node.offset + node.count])
+            } else {
+                match (&values[index + 1], &values[node.offset]) {
+                    (Some(left), Some(right)) => combine(left, right),
+                    _ => unreachable!("children are folded before their parent"),
+                }
+            });
+        }
+        values.into_iter().flatten().collect()
+    }
+
+    /// Walk the tree from the root and decide at every node whether to
+    /// look inside it, so a query can use a value from `fold_nodes` for
+    /// a subtree which is far away rather than visiting its items.
+    ///
+    /// Parameters
+    /// ------------
+    /// visit
+    ///   Called with the index of a node, its box and its items if it's
+    ///   a leaf. Returning `true` visits the children of an inner node.
+    pub fn descend(&self, mut visit: impl FnMut(usize, &Bounds, Option<&[usize]>) -> bool) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let items =
+                (node.count > 0).then(|| &self.items[node.offset..node.offset + node.count]);
+            if visit(index, &node.bounds, items) && items.is_none() {
+                stack.extend([node.offset, index + 1]);
+            }
+        }
+    }
+
+    /// Visit every item whose box overlaps a query box.
+    ///
+    /// Parameters
+    /// ------------
+    /// bounds
+    ///   The box to query.
+    /// visit
+    ///   Called with the index of each item whose box overlaps.
+    pub fn query(&self, bounds: &Bounds, mut visit: impl FnMut(usize)) {
+        let overlaps =
+            |other: &Bounds| (0..3).all(|i| bounds.0[i] <= other.1[i] && other.0[i] <= bounds.1[i]);
+        if self.nodes.is_empty() || !overlaps(&self.nodes[0].bounds) {
+            return;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if node.count > 0 {
+                for item in &self.items[node.offset..node.offset + node.count] {
+                    visit(*item);
+                }
+                continue;
+            }
+            for child in [index + 1, node.offset] {
+                if overlaps(&self.nodes[child].bounds) {
+                    stack.push(child);
+                }
+            }
+        }
+    }
+
+    /// Visit every pair of items from two trees whose boxes might overlap,
+    /// descending both trees together so pairs of distant subtrees are
+    /// skipped without looking at their items. Like `query` the pairs
+    /// are every combination of items in overlapping leaves.
+    ///
+    /// Parameters
+    /// ------------
+    /// other
+    ///   The tree to pair items with, which must be in the same frame.
+    /// visit
+    ///   Called with an item of this tree and an item of the other tree.
+    ///   Returning `true` stops the traversal.
+    pub fn query_tree(&self, other: &Bvh, mut visit: impl FnMut(usize, usize) -> bool) {
+        let overlaps =
+            |a: &Bounds, b: &Bounds| (0..3).all(|i| a.0[i] <= b.1[i] && b.0[i] <= a.1[i]);
+        let volume = |b: &Bounds| (b.1 - b.0).product();
+        if self.nodes.is_empty() || other.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![(0, 0)];
+        while let Some((a, b)) = stack.pop() {
+            let (node_a, node_b) = (&self.nodes[a], &other.nodes[b]);
+            if !overlaps(&node_a.bounds, &node_b.bounds) {
+                continue;
+            }
+            match (node_a.count > 0, node_b.count > 0) {
+                (true, true) => {
+                    for i in &self.items[node_a.offset..node_a.offset + node_a.count] {
+                        for j in &other.items[node_b.offset..node_b.offset + node_b.count] {
+                            if visit(*i, *j) {
+                                return;
+                            }
+                        }
+                    }
+                }
+                // split the larger of two inner nodes
+                (false, false) if volume(&node_a.bounds) < volume(&node_b.bounds) => {
+                    stack.extend([(a, b + 1), (a, node_b.offset)]);
+                }
+                (false, _) => stack.extend([(a + 1, b), (node_a.offset, b)]),
+                (true, false) => stack.extend([(a, b + 1), (a, node_b.offset)]),
+            }
+        }
+    }
+
+    /// Visit every item whose box is hit by a ray closer than a limit,
+    /// visiting nearer boxes first so a search for the closest hit can
+    /// shrink the limit and skip most of the tree.
+    ///
+    /// Parameters
+    /// ------------
+    /// ray
+    ///   The ray to traverse the tree with.
+    /// visit
+    ///   Called with each candidate item and the limit on the ray
+    ///   parameter which starts at infinity and can be reduced. Returning
+    ///   `true` stops the traversal.
+    pub fn traverse(&self, ray: &Ray, mut visit: impl FnMut(usize, &mut f64) -> bool) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let inverse = [
+            1.0 / ray.direction.x,
+            1.0 / ray.direction.y,
+            1.0 / ray.direction.z,
+        ];
+        let mut limit = f64::INFINITY;
+        if ray_box(&ray.origin, &inverse, &self.nodes[0].bounds, limit).is_none() {
+            return;
+        }
+
+        let mut stack = vec![(0, 0.0)];
+        while let Some((index, near)) = stack.pop() {
+            // the limit may have shrunk since the node was pushed
+            if near > limit {
+                continue;
+            }
+            let node = &self.nodes[index];
+            if node.count > 0 {
+                for item in &self.items[node.offset..node.offset + node.count] {
+                    if visit(*item, &mut limit) {
+                        return;
+                    }
+                }
+                continue;
+            }
+            let hits = [index + 1, node.offset].map(|child| {
+                ray_box(&ray.origin, &inverse, &self.nodes[child].bounds, limit)
+                    .map(|near| (near, child))
+            });
+            match hits {
+                [Some(a), Some(b)] => {
+                    // push the farther child first so the nearer pops first
+                    let (near, far) = if a.0 <= b.0 { (a, b) } else { (b, a) };
+                    stack.push((far.1, far.0));
+                    stack.push((near.1, near.0));
+                }
+                [Some(a), None] | [None, Some(a)] => stack.push((a.1, a.0)),
+                [None, None] => {}
+            }
+        }
+    }
+
+    /// Visit every item whose box is closer to a point than a limit,
+    /// visiting nearer boxes first so a search for the closest item
+    /// can shrink the limit and skip most of the tree.
+    ///
+    /// Parameters
+    /// ------------
+    /// point
+    ///   The point to search around.
+    /// visit
+    ///   Called with each candidate item and the limit on the distance
+    ///   which starts at infinity and can be reduced.
+    pub fn nearest(&self, point: &Point3<f64>, mut visit: impl FnMut(usize, &mut f64)) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut limit = f64::INFINITY;
+        let mut stack = vec![(0, point_box(point, &self.nodes[0].bounds))];
+        while let Some((index, near)) = stack.pop() {
+            // the limit may have shrunk since the node was pushed
+            if near > limit {
+                continue;
+            }
+            let node = &self.nodes[index];
+            if node.count > 0 {
+                for item in &self.items[node.offset..node.offset + node.count] {
+                    visit(*item, &mut limit);
+                }
+                continue;
+            }
+            let [a, b] = [index + 1, node.offset]
+                .map(|child| (child, point_box(point, &self.nodes[child].bounds)));
+            // push the farther child first so the nearer pops first
+            let (near, far) = if a.1 <= b.1 { (a, b) } else { (b, a) };
+            stack.push(far);
+            stack.push(near);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use nalgebra::Vector3;
+
+    #[test]
+    fn test_bvh_traverse() {
+        // a row of unit boxes along X
+        let bounds: Vec<Bounds> = (0..100)
+            .map(|i| {
+                let x = i as f64 * 2.0;
+                (Point3::new(x, 0.0, 0.0), Point3::new(x + 1.0, 1.0, 1.0))
+            })
+            .collect();
+        let bvh = Bvh::new(&bounds);
+        let mut items = bvh.items.clone();
+        items.sort();
+        assert_eq!(items, (0..100).collect::<Vec<_>>());
+
+        // a ray down through a single box
+        let ray = Ray::new(Point3::new(20.5, 0.5, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let mut visited = Vec::new();
+        bvh.traverse(&ray, |i, _| {
+            visited.push(i);
+            false
+        });
+        assert!(visited.contains(&10));
+        assert!(visited.len() <= LEAF_SIZE);
+
+        // a ray along the row visits the nearest box first
+        let ray = Ray::new(Point3::new(-5.0, 0.5, 0.5), Vector3::new(1.0, 0.0, 0.0));
+        let mut visited = Vec::new();
+        bvh.traverse(&ray, |i, limit| {
+            visited.push(i);
+            *limit = limit.min(bounds[i].0.x + 5.0);
+            false
+        });
+        assert!(visited.contains(&0));
+        assert!(visited.len() <= LEAF_SIZE);
+
+        // a box overlapping the first three boxes
+        let mut found = Vec::new();
+        bvh.query(
+            &(Point3::new(-1.0, 0.5, 0.5), Point3::new(4.5, 2.0, 2.0)),
+            |i| found.push(i),
+        );
+        found.sort();
+        assert_eq!(found, vec![0, 1, 2]);
+
+        // moving the last box to the start is found after a refit
+        let mut moved = bounds.clone();
+        moved[99] = (Point3::new(0.0, 0.0, 3.0), Point3::new(1.0, 1.0, 4.0));
+        let mut refit = bvh.clone();
+        refit.refit(&moved);
+        let mut found = Vec::new();
+        refit.query(
+            &(Point3::new(0.5, 0.5, 0.5), Point3::new(0.5, 0.5, 3.5)),
+            |i| found.push(i),
+        );
+        // leaves may hold other items but never miss an overlapping one
+        assert!(found.contains(&0) && found.contains(&99));
+        assert!(found.len() <= LEAF_SIZE * 2);
+        assert_eq!(refit.nodes[0].bounds.1.z, 4.0);
+
+        let mut count = 0;
+        Bvh::default().traverse(&ray, |_, _| {
+            count += 1;
+            false
+        });
+        assert_eq!(count, 0);
+    }
+}
@@ -0,0 +1,274 @@
+use std::fmt;
+use std::mem::size_of;
+
+use rayon::prelude::*;
+
+use crate::mesh::{InnerCache, Trimesh};
+
+/// A value `Trimesh` computes on first use and caches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheField {
+    FaceAdjacency,
+    FaceNormals,
+    Edges,
+    FacesCross,
+    FacesArea,
+    Area,
+    FaceAngles,
+    VertexDefects,
+    Bvh,
+    VertexTree,
+    VertexAdjacency,
+    VertexNormals,
+    PrincipalInertiaTransform,
+    BoundingBoxOriented,
+    HalfEdges,
+    FacesFlat,
+}
+
+impl CacheField {
+    /// Every cached value.
+    pub const ALL: [CacheField; 16] = [
+        CacheField::FaceAdjacency,
+        CacheField::FaceNormals,
+        CacheField::Edges,
+        CacheField::FacesCross,
+        CacheField::FacesArea,
+        CacheField::Area,
+        CacheField::FaceAngles,
+        CacheField::VertexDefects,
+        CacheField::Bvh,
+        CacheField::VertexTree,
+        CacheField::VertexAdjacency,
+        CacheField::VertexNormals,
+        CacheField::PrincipalInertiaTransform,
+        CacheField::BoundingBoxOriented,
+        CacheField::HalfEdges,
+        CacheField::FacesFlat,
+    ];
+
+    /// The name of the method which computes the value.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CacheField::FaceAdjacency => "face_adjacency",
+            CacheField::FaceNormals => "face_normals",
+            CacheField::Edges => "edges",
+            CacheField::FacesCross => "faces_cross",
+            CacheField::FacesArea => "faces_area",
+            CacheField::Area => "area",
+            CacheField::FaceAngles => "face_angles",
+            CacheField::VertexDefects => "vertex_defects",
+            CacheField::Bvh => "bvh",
+            CacheField::VertexTree => "vertex_tree",
+            CacheField::VertexAdjacency => "vertex_adjacency",
+            CacheField::VertexNormals => "vertex_normals",
+            CacheField::PrincipalInertiaTransform => "principal_inertia_transform",
+            CacheField::BoundingBoxOriented => "bounding_box_oriented",
+            CacheField::HalfEdges => "half_edges",
+            CacheField::FacesFlat => "faces_flat",
+        }
+    }
+
+    /// The cached values this one is computed from.
+    pub fn dependencies(&self) -> &'static [CacheField] {
+        match self {
+            CacheField::FaceNormals | CacheField::FacesArea => &[CacheField::FacesCross],
+            CacheField::Area => &[CacheField::FacesArea],
+            CacheField::VertexDefects => &[CacheField::FaceAngles, CacheField::Edges],
+            CacheField::VertexAdjacency => &[CacheField::Edges],
+            CacheField::VertexNormals => &[CacheField::FaceNormals, CacheField::FaceAngles],
+            _ => &[],
+        }
+    }
+
+    /// The approximate number of bytes of the cached value,
+    /// or `None` if it hasn't been computed.
+    fn memory(&self, cache: &InnerCache) -> Option<usize> {
+        fn vec<T>(value: &Option<Vec<T>>) -> Option<usize> {
+            value.as_ref().map(|v| v.len() * size_of::<T>())
+        }
+        match self {
+            CacheField::FaceAdjacency => vec(&cache.face_adjacency),
+            CacheField::FaceNormals => vec(&cache.face_normals),
+            CacheField::Edges => vec(&cache.edges),
+            CacheField::FacesCross => vec(&cache.faces_cross),
+            CacheField::FacesArea => vec(&cache.faces_area),
+            CacheField::Area => cache.area.map(|_| size_of::<f64>()),
+            CacheField::FaceAngles => vec(&cache.face_angles),
+            CacheField::VertexDefects => vec(&cache.vertex_defects),
+            CacheField::Bvh => cache.bvh.as_ref().map(|b| b.memory()),
+            CacheField::VertexTree => cache.vertex_tree.as_ref().map(|t| t.memory()),
+            CacheField::VertexAdjacency => cache.vertex_adjacency.as_ref().map(|a| {
+                a.iter()
+                    .map(|n| size_of::<Vec<usize>>() + n.len() * size_of::<usize>())
+                    .sum()
+            }),
+            CacheField::VertexNormals => vec(&cache.vertex_normals),
+            CacheField::PrincipalInertiaTransform => {
+                cache.principal_inertia_transform.map(|t| size_of_val(&t))
+            }
+            CacheField::BoundingBoxOriented => {
+                cache.bounding_box_oriented.as_ref().map(size_of_val)
+            }
+            CacheField::HalfEdges => cache.half_edges.as_ref().map(|h| h.memory()),
+            CacheField::FacesFlat => cache
+                .faces_flat
+                .as_ref()
+                .map(|f| f.as_ref().map_or(0, |f| size_of_val(f.as_ref()))),
+        }
+    }
+}
+
+/// Which cached values of a mesh have been computed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheStats {
+    // every computed value and the approximate bytes it uses
+    pub populated: Vec<(CacheField, usize)>,
+    // the revision of the mesh from `Trimesh::revision`
+    pub revision: u64,
+}
+
+impl CacheStats {
+    /// Has a value been computed.
+    pub fn contains(&self, field: CacheField) -> bool {
+        self.populated.iter().any(|(f, _)| *f == field)
+    }
+
+    /// The approximate bytes used by every computed value.
+    pub fn bytes(&self) -> usize {
+        self.populated.iter().map(|(_, b)| b).sum()
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} cached values using {} bytes at revision {}",
+            self.populated.len(),
+            self.bytes(),
+            self.revision
+        )?;
+        for (field, bytes) in self.populated.iter() {
+            write!(f, "\n  {}: {bytes} bytes", field.name())?;
+        }
+        Ok(())
+    }
+}
+
+impl Trimesh {
+    /// Compute a value and store it in the cache.
+    fn compute(&self, field: CacheField) {
+        match field {
+            CacheField::FaceAdjacency => _ = self.face_adjacency(),
+            CacheField::FaceNormals => _ = self.face_normals(),
+            CacheField::Edges => _ = self.edges(),
+            CacheField::FacesCross => _ = self.faces_cross(),
+            CacheField::FacesArea => _ = self.faces_area(),
+            CacheField::Area => _ = self.area(),
+            CacheField::FaceAngles => _ = self.face_angles(),
+            CacheField::VertexDefects => _ = self.vertex_defects(),
+            CacheField::Bvh => _ = self.bvh(),
+            CacheField::VertexTree => _ = self.vertex_tree(),
+            CacheField::VertexAdjacency => _ = self.vertex_adjacency(),
+            CacheField::VertexNormals => _ = self.vertex_normals(),
+            CacheField::PrincipalInertiaTransform => _ = self.principal_inertia_transform(),
+            CacheField::BoundingBoxOriented => _ = self.bounding_box_oriented(),
+            CacheField::HalfEdges => _ = self.half_edges(),
+            CacheField::FacesFlat => _ = self.faces_flat(),
+        }
+    }
+
+    /// Compute cached values ahead of time so later calls, such as
+    /// from worker threads sharing the mesh, don't have to. Values
+    /// are computed in parallel in rounds, where each round computes
+    /// every value whose dependencies were computed by earlier rounds.
+    ///
+    /// Parameters
+    /// ------------
+    /// fields
+    ///   The values to compute, along with everything they depend on.
+    pub fn warm_cache(&self, fields: &[CacheField]) {
+        let mut pending: Vec<CacheField> = Vec::new();
+        let mut stack = fields.to_vec();
+        while let Some(field) = stack.pop() {
+            if !pending.contains(&field) {
+                pending.push(field);
+                stack.extend_from_slice(field.dependencies());
+            }
+        }
+        // values which are already cached are free
+        let stats = self.cache_stats();
+        pending.retain(|f| !stats.contains(*f));
+
+        while !pending.is_empty() {
+            let (ready, waiting): (Vec<CacheField>, Vec<CacheField>) = pending
+                .iter()
+                .partition(|f| f.dependencies().iter().all(|d| !pending.contains(d)));
+            ready.par_iter().for_each(|f| self.compute(*f));
+            pending = waiting;
+        }
+    }
+
+    /// Which cached values have been computed and roughly how much
+    /// memory they use, such as to check what a mesh carries before
+    /// sending it to another thread.
+    pub fn cache_stats(&self) -> CacheStats {
+        let cache = self._cache.read().unwrap();
+        CacheStats {
+            populated: CacheField::ALL
+                .iter()
+                .filter_map(|f| Some((*f, f.memory(&cache)?)))
+                .collect(),
+            revision: cache.revision,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_sphere;
+
+    #[test]
+    fn test_warm_cache() {
+        let mut mesh = create_sphere(1.0, [32, 16]).unwrap();
+        assert!(mesh.cache_stats().populated.is_empty());
+
+        // dependencies are computed along with what was asked for
+        mesh.warm_cache(&[CacheField::VertexNormals, CacheField::Area]);
+        let stats = mesh.cache_stats();
+        for field in [
+            CacheField::VertexNormals,
+            CacheField::FaceNormals,
+            CacheField::FaceAngles,
+            CacheField::FacesCross,
+            CacheField::FacesArea,
+            CacheField::Area,
+        ] {
+            assert!(stats.contains(field), "{field:?} should be cached");
+        }
+        assert!(!stats.contains(CacheField::Bvh));
+        assert_eq!(stats.populated.len(), 6);
+        assert_eq!(
+            stats.bytes(),
+            mesh.faces.len() * (3 * 24 + 8) + mesh.vertices.len() * 24 + 8
+        );
+
+        mesh.warm_cache(&CacheField::ALL);
+        let stats = mesh.cache_stats();
+        assert_eq!(stats.populated.len(), CacheField::ALL.len());
+        assert!(stats.to_string().contains("bvh"));
+
+        // the values are the same as computing them on demand
+        let fresh = create_sphere(1.0, [32, 16]).unwrap();
+        assert_eq!(mesh.vertex_defects(), fresh.vertex_defects());
+        assert_eq!(mesh.area(), fresh.area());
+
+        mesh.invalidate_cache();
+        let stats = mesh.cache_stats();
+        assert!(stats.populated.is_empty());
+        assert!(stats.revision > 0);
+    }
+}
@@ -0,0 +1,456 @@
+use ahash::AHashMap;
+use anyhow::{Result, anyhow};
+use nalgebra::{Point2, Point3, Vector3};
+use rayon::prelude::*;
+
+use crate::bvh::Bounds;
+use crate::creation::{Plane, Triangulator};
+use crate::facets::boundary_loops;
+use crate::mesh::Trimesh;
+use crate::path::nest_loops;
+
+/// A corner of a piece of a clipped triangle, which is a point on the
+/// edge between two corners of the original triangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Blend {
+    // the corners of the original triangle from 0 to 2, which
+    // are the same if the point is an original corner
+    pub a: usize,
+    pub b: usize,
+    // how far along the edge the point is from `a` to `b`
+    pub t: f64,
+}
+
+impl Blend {
+    fn corner(index: usize) -> Self {
+        Self {
+            a: index,
+            b: index,
+            t: 0.0,
+        }
+    }
+
+    /// Is this an original corner of the triangle rather than a new point.
+    pub fn is_corner(&self) -> bool {
+        self.a == self.b
+    }
+
+    /// The position of the point from the corners of the original triangle.
+    pub fn point(&self, corners: &[Point3<f64>; 3]) -> Point3<f64> {
+        corners[self.a] + (corners[self.b] - corners[self.a]) * self.t
+    }
+}
+
+/// The pieces of a triangle on either side of a plane, wound the
+/// same way as the original triangle.
+#[derive(Debug, Clone, Default)]
+pub struct ClippedTriangle {
+    // the pieces on the side the normal of the plane points to
+    pub above: Vec<[Blend; 3]>,
+    pub below: Vec<[Blend; 3]>,
+}
+
+/// Clip a triangle by a plane from the signed distance of its corners.
+/// Corners within the tolerance of the plane are on it and start no
+/// new edge, so a triangle touching the plane isn't cut into slivers,
+/// and a triangle lying in the plane is entirely above it. Each new
+/// point is described by the corners it's between so any value on the
+/// corners such as a position or UV coordinate can be interpolated.
+///
+/// Parameters
+/// ------------
+/// distance
+///   The signed distance of each corner to the plane.
+/// tolerance
+///   How far from the plane a corner is still on it.
+///
+/// Returns
+/// ------------
+/// clipped
+///   The triangles on each side of the plane.
+pub fn clip_triangle_by_plane(distance: &[f64; 3], tolerance: f64) -> ClippedTriangle {
+    let distance = distance.map(|d| if d.abs() <= tolerance { 0.0 } else { d });
+    if distance.iter().all(|d| *d >= 0.0) {
+        return ClippedTriangle {
+            above: vec![[0, 1, 2].map(Blend::corner)],
+            below: Vec::new(),
+        };
+    }
+    if distance.iter().all(|d| *d <= 0.0) {
+        return ClippedTriangle {
+            above: Vec::new(),
+            below: vec![[0, 1, 2].map(Blend::corner)],
+        };
+    }
+
+    // walk the edges collecting the corners of the polygon on each side
+    let (mut above, mut below) = (Vec::with_capacity(4), Vec::with_capacity(4));
+    for a in 0..3 {
+        let b = (a + 1) % 3;
+        if distance[a] >= 0.0 {
+            above.push(Blend::corner(a));
+        }
+        if distance[a] <= 0.0 {
+            below.push(Blend::corner(a));
+        }
+        if distance[a] * distance[b] < 0.0 {
+            let t = distance[a] / (distance[a] - distance[b]);
+            above.push(Blend { a, b, t });
+            below.push(Blend { a, b, t });
+        }
+    }
+    // each side is a triangle or a convex quad
+    let fan = |polygon: Vec<Blend>| {
+        (1..polygon.len().saturating_sub(1))
+            .map(|i| [polygon[0], polygon[i], polygon[i + 1]])
+            .collect()
+    };
+    ClippedTriangle {
+        above: fan(above),
+        below: fan(below),
+    }
+}
+
+impl Trimesh {
+    /// Cut the mesh with a plane and keep the part on the side the normal
+    /// of the plane points to. Faces crossing the plane are clipped with
+    /// `clip_triangle_by_plane`, and the new vertices where they cross are
+    /// shared by the faces on either side of an edge and interpolate the
+    /// vertex attributes of its ends. The pieces of a face keep its face
+    /// attributes. Without capping the result of cutting a closed mesh
+    /// is open.
+    ///
+    /// Parameters
+    /// ------------
+    /// plane
+    ///   The plane to cut with.
+    /// cap
+    ///   Close the cut with `cap_plane` so a closed mesh stays closed.
+    ///
+    /// Returns
+    /// ------------
+    /// sliced
+    ///   The part of the mesh above the plane, or an error if the
+    ///   normal of the plane has no length or the cut can't be capped.
+    pub fn slice_plane(&self, plane: &Plane, cap: bool) -> Result<Self> {
+        let normal = plane
+            .normal
+            .try_normalize(0.0)
+            .ok_or_else(|| anyhow!("Plane normal must be non-zero"))?;
+        let distance: Vec<f64> = self
+            .vertices
+            .par_iter()
+            .map(|v| normal.dot(&(v - plane.origin)))
+            .collect();
+
+        let tolerance = self.tolerances().merge;
+        let mut vertices = self.vertices.clone();
+        let mut blends: Vec<(usize, usize, f64)> = Vec::new();
+        let mut index: AHashMap<(usize, usize), usize> = AHashMap::new();
+        let mut faces = Vec::with_capacity(self.faces.len());
+        // the face each new face was clipped from
+        let mut parents = Vec::with_capacity(self.faces.len());
+        for (parent, f) in self.faces.iter().enumerate() {
+            let corners = [f.0, f.1, f.2];
+            let clipped = clip_triangle_by_plane(&corners.map(|v| distance[v]), tolerance);
+            for piece in clipped.above {
+                let [a, b, c] = piece.map(|blend| {
+                    let (a, b) = (corners[blend.a], corners[blend.b]);
+                    if blend.is_corner() {
+                        return a;
+                    }
+                    // key the edge by its lower index so both faces share the vertex
+                    let (a, b, t) = if a < b {
+                        (a, b, blend.t)
+                    } else {
+                        (b, a, 1.0 - blend.t)
+                    };
+                    *index.entry((a, b)).or_insert_with(|| {
+                        vertices.push(self.vertices[a] + (self.vertices[b] - self.vertices[a]) * t);
+                        blends.push((a, b, t));
+                        vertices.len() - 1
+                    })
+                });
+                faces.push((a, b, c));
+                parents.push(parent);
+            }
+        }
+
+        let mut result = Trimesh::new(
+            vertices,
+            faces,
+            Some(
+                self.attributes_vertex
+                    .interpolate(self.vertices.len(), &blends),
+            ),
+            Some(self.attributes_face.select(&parents, self.faces.len())),
+        )?;
+        result.materials = self.materials.clone();
+        result.source = self.source.clone();
+        let result = result.remove_unreferenced_vertices();
+        if cap {
+            return result.cap_plane(plane);
+        }
+        Ok(result)
+    }
+
+    /// Close the boundary loops of the mesh which lie in a plane with
+    /// faces pointing against the plane normal, which is what closes the
+    /// part `slice_plane` keeps. The loops are triangulated together so
+    /// a loop inside another is a hole, and each new face takes its face
+    /// attributes from a face next to its boundary. The mesh should have
+    /// merged vertices so the boundary of the cut is connected.
+    ///
+    /// Parameters
+    /// ------------
+    /// plane
+    ///   The plane the loops to close lie in.
+    ///
+    /// Returns
+    /// ------------
+    /// capped
+    ///   A new mesh with the caps added, or an error if the normal of the
+    ///   plane has no length or the boundary isn't a set of simple loops.
+    pub fn cap_plane(&self, plane: &Plane) -> Result<Self> {
+        let normal = plane
+            .normal
+            .try_normalize(0.0)
+            .ok_or_else(|| anyhow!("Plane normal must be non-zero"))?;
+        let plane = Plane::new(normal, plane.origin);
+        let tolerance = self.tolerances().merge;
+        let loops: Vec<Vec<usize>> = boundary_loops(&self.faces)
+            .ok_or_else(|| anyhow!("Mesh boundary isn't a set of simple loops"))?
+            .into_iter()
+            .filter(|chain| {
+                chain.len() > 2
+                    && chain
+                        .iter()
+                        .all(|v| normal.dot(&(self.vertices[*v] - plane.origin)).abs() <= tolerance)
+            })
+            .collect();
+        let flat: Vec<Vec<Point2<f64>>> = loops
+            .iter()
+            .map(|chain| {
+                let points: Vec<Point3<f64>> = chain.iter().map(|v| self.vertices[*v]).collect();
+                plane.to_2d(&points)
+            })
+            .collect();
+
+        let mut owner: AHashMap<(usize, usize), usize> = AHashMap::new();
+        for (index, f) in self.faces.iter().enumerate() {
+            for edge in [(f.0, f.1), (f.1, f.2), (f.2, f.0)] {
+                owner.insert(edge, index);
+            }
+        }
+        let mut faces = self.faces.clone();
+        let mut source: Vec<usize> = (0..faces.len()).collect();
+        let mut triangulator = Triangulator::new();
+        for rings in nest_loops(&flat) {
+            let mut points = Vec::new();
+            let mut index = Vec::new();
+            let mut local: Vec<Vec<usize>> = Vec::with_capacity(rings.len());
+            for ring in rings.iter() {
+                local.push((points.len()..points.len() + flat[*ring].len()).collect());
+                points.extend(flat[*ring].iter().copied());
+                index.extend(loops[*ring].iter().copied());
+            }
+            let neighbor = owner[&(loops[rings[0]][0], loops[rings[0]][1])];
+            let triangles: Vec<(usize, usize, usize)> = triangulator
+                .trianglate_2d(&local[0], &local[1..], &points)
+                .into_iter()
+                .map(|(a, b, c)| (index[a], index[b], index[c]))
+                .collect();
+            // the triangles are wound alike so flip them together, which
+            // keeps any sliver between collinear points consistent
+            let area: Vector3<f64> = triangles
+                .iter()
+                .map(|f| {
+                    let (a, b, c) = (self.vertices[f.0], self.vertices[f.1], self.vertices[f.2]);
+                    (b - a).cross(&(c - a))
+                })
+                .sum();
+            let flip = area.dot(&normal) > 0.0;
+            source.extend(std::iter::repeat_n(neighbor, triangles.len()));
+            faces.extend(
+                triangles
+                    .into_iter()
+                    .map(|(a, b, c)| if flip { (a, c, b) } else { (a, b, c) }),
+            );
+        }
+        Ok(self.with_faces(faces, &source))
+    }
+
+    /// Keep the part of the mesh inside an axis aligned box by slicing
+    /// it with each side of the box.
+    ///
+    /// Parameters
+    /// ------------
+    /// bounds
+    ///   The lower and upper corners of the box.
+    /// cap
+    ///   Close the cut of every side so a closed mesh stays closed.
+    ///
+    /// Returns
+    /// ------------
+    /// cropped
+    ///   The part of the mesh inside the box.
+    pub fn crop(&self, bounds: &Bounds, cap: bool) -> Result<Self> {
+        let mut planes = (0..3).flat_map(|axis| {
+            let normal = Vector3::ith(axis, 1.0);
+            [Plane::new(normal, bounds.0), Plane::new(-normal, bounds.1)]
+        });
+        // there is always a first plane to start from
+        let first = planes.next().unwrap();
+        planes.try_fold(self.slice_plane(&first, cap)?, |mesh, plane| {
+            mesh.slice_plane(&plane, cap)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::{create_box, create_cylinder, create_sphere};
+    use approx::assert_relative_eq;
+    use nalgebra::{Vector2, Vector4};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_clip_triangle() {
+        // one corner above
+        let clipped = clip_triangle_by_plane(&[1.0, -1.0, -3.0], 0.0);
+        assert_eq!(clipped.above.len(), 1);
+        assert_eq!(clipped.below.len(), 2);
+        let corners = [
+            Point3::new(0.0, 1.0, 0.0),
+            Point3::new(0.0, -1.0, 0.0),
+            Point3::new(2.0, -3.0, 0.0),
+        ];
+        let above = clipped.above[0].map(|b| b.point(&corners));
+        assert_eq!(above[0], corners[0]);
+        assert_relative_eq!(above[1], Point3::new(0.0, 0.0, 0.0));
+        assert_relative_eq!(above[2], Point3::new(0.5, 0.0, 0.0));
+
+        // a corner within the tolerance of the plane makes no sliver
+        let clipped = clip_triangle_by_plane(&[1e-12, -1.0, 1.0], 1e-10);
+        assert_eq!(clipped.above.len(), 1);
+        assert_eq!(clipped.below.len(), 1);
+        assert!(clipped.above[0][0].is_corner());
+        // touching or lying in the plane doesn't split
+        let clipped = clip_triangle_by_plane(&[0.0, 1.0, 1.0], 0.0);
+        assert_eq!((clipped.above.len(), clipped.below.len()), (1, 0));
+        let clipped = clip_triangle_by_plane(&[0.0, 0.0, 0.0], 0.0);
+        assert_eq!((clipped.above.len(), clipped.below.len()), (1, 0));
+    }
+
+    #[test]
+    fn test_slice_plane() {
+        let mut mesh = create_box(&[2.0, 2.0, 2.0]);
+        // UV coordinates and colors which vary linearly with position
+        mesh.attributes_vertex.uv.push(
+            mesh.vertices
+                .iter()
+                .map(|v| Vector2::new(v.x + 1.0, v.z + 1.0) / 2.0)
+                .collect(),
+        );
+        mesh.attributes_vertex.colors.push(
+            mesh.vertices
+                .iter()
+                .map(|v| Vector4::new(((v.z + 1.0) * 100.0) as u8, 0, 0, 255))
+                .collect(),
+        );
+        mesh.attributes_face
+            .colors
+            .push((0..12).map(|i| Vector4::new(i, 0, 0, 255)).collect());
+
+        let sliced = mesh
+            .slice_plane(&Plane::new(Vector3::z(), Point3::new(0.0, 0.0, 0.5)), false)
+            .unwrap();
+        let (lower, upper) = sliced.bounds().unwrap();
+        assert_relative_eq!(lower, Point3::new(-1.0, -1.0, 0.5));
+        assert_relative_eq!(upper, Point3::new(1.0, 1.0, 1.0));
+        // the top and the remaining quarter of each side
+        assert_relative_eq!(sliced.area(), 4.0 + 4.0 * 2.0 * 0.5, epsilon = 1e-10);
+        // each new vertex on a vertical edge or side diagonal is
+        // shared by the faces on both sides of its edge
+        assert_eq!(sliced.vertices.len(), 12);
+        for (v, (uv, color)) in sliced.vertices.iter().zip(
+            sliced.attributes_vertex.uv[0]
+                .iter()
+                .zip(sliced.attributes_vertex.colors[0].iter()),
+        ) {
+            assert_relative_eq!(*uv, Vector2::new(v.x + 1.0, v.z + 1.0) / 2.0);
+            assert_eq!(color.x, ((v.z + 1.0) * 100.0) as u8);
+        }
+        // every piece keeps the color of the face it was cut from
+        let parents: Vec<u8> = sliced.attributes_face.colors[0]
+            .iter()
+            .map(|c| c.x)
+            .collect();
+        assert_eq!(parents.len(), sliced.faces.len());
+        assert!(parents.iter().all(|p| *p >= 2));
+
+        // a sphere cropped to a box which cuts off its ends
+        let sphere = create_sphere(1.0, [32, 16]).unwrap();
+        let cropped = sphere
+            .crop(
+                &(Point3::new(-0.5, -2.0, -2.0), Point3::new(2.0, 2.0, 0.5)),
+                false,
+            )
+            .unwrap();
+        let (lower, upper) = cropped.bounds().unwrap();
+        assert_relative_eq!(lower.x, -0.5, epsilon = 1e-12);
+        assert_relative_eq!(upper.z, 0.5, epsilon = 1e-12);
+        assert!(cropped.faces.len() < sphere.faces.len());
+        assert!(mesh.crop(&(lower, upper), false).unwrap().area() > 0.0);
+        // a box around everything keeps everything
+        let kept = sphere
+            .crop(
+                &(Point3::new(-2.0, -2.0, -2.0), Point3::new(2.0, 2.0, 2.0)),
+                false,
+            )
+            .unwrap();
+        assert_eq!(kept.faces, sphere.faces);
+
+        assert!(
+            mesh.slice_plane(&Plane::new(Vector3::zeros(), Point3::origin()), false)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_cap_plane() {
+        let mesh = create_box(&[2.0, 2.0, 2.0]);
+        let plane = Plane::new(Vector3::new(0.0, 0.0, 2.0), Point3::new(0.0, 0.0, 0.5));
+        let capped = mesh.slice_plane(&plane, true).unwrap();
+        assert!(boundary_loops(&capped.faces).unwrap().is_empty());
+        assert_relative_eq!(capped.volume(), 2.0, epsilon = 1e-10);
+        // the top, the cap and the remaining quarter of each side
+        assert_relative_eq!(capped.area(), 4.0 + 4.0 + 4.0 * 2.0 * 0.5, epsilon = 1e-10);
+        // the cap faces down out of the part which was kept
+        let open = mesh.slice_plane(&plane, false).unwrap().faces.len();
+        let cap: Vector3<f64> = capped.faces_cross()[open..].iter().sum();
+        assert_relative_eq!(cap, Vector3::new(0.0, 0.0, -8.0), epsilon = 1e-10);
+
+        // a cylinder cropped on every side stays closed
+        let cylinder = create_cylinder(1.0, 4.0, 64).unwrap();
+        let cropped = cylinder
+            .crop(
+                &(Point3::new(-2.0, -2.0, -1.0), Point3::new(0.5, 2.0, 1.0)),
+                true,
+            )
+            .unwrap();
+        assert!(boundary_loops(&cropped.faces).unwrap().is_empty());
+        let (lower, upper) = cropped.bounds().unwrap();
+        assert_relative_eq!(lower.z, -1.0, epsilon = 1e-12);
+        assert_relative_eq!(upper.x, 0.5, epsilon = 1e-12);
+        // the area of the circular segment kept times the height
+        let segment = (PI - (0.5_f64).acos()) + 0.5 * (1.0 - 0.25_f64).sqrt();
+        assert_relative_eq!(cropped.volume(), segment * 2.0, epsilon = 0.01);
+
+        // nothing on the plane leaves the mesh alone
+        let same = mesh.cap_plane(&plane).unwrap();
+        assert_eq!(same.faces, mesh.faces);
+    }
+}
@@ -0,0 +1,236 @@
+use nalgebra::{Point2, Point3, Vector3};
+
+use crate::mesh::Trimesh;
+
+/// The range a triangle covers along a direction where it meets a plane,
+/// from the signed distance of each corner to the plane.
+fn plane_interval(
+    t: &[Point3<f64>; 3],
+    distance: &[f64; 3],
+    direction: &Vector3<f64>,
+    tolerance: f64,
+) -> (f64, f64) {
+    let mut lower = f64::INFINITY;
+    let mut upper = f64::NEG_INFINITY;
+    let mut include = |p: Point3<f64>| {
+        let d = direction.dot(&p.coords);
+        lower = lower.min(d);
+        upper = upper.max(d);
+    };
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        if distance[i].abs() <= tolerance {
+            include(t[i]);
+        }
+        if (distance[i] > tolerance && distance[j] < -tolerance)
+            || (distance[i] < -tolerance && distance[j] > tolerance)
+        {
+            let s = distance[i] / (distance[i] - distance[j]);
+            include(t[i] + (t[j] - t[i]) * s);
+        }
+    }
+    (lower, upper)
+}
+
+/// Twice the signed area of the 2D triangle `a`, `b`, `c`.
+fn cross_2d(a: &Point2<f64>, b: &Point2<f64>, c: &Point2<f64>) -> f64 {
+    (b - a).perp(&(c - a))
+}
+
+/// Do two 2D segments touch or cross.
+fn segments_touch(a: [Point2<f64>; 2], b: [Point2<f64>; 2], tolerance: f64) -> bool {
+    let d = [
+        cross_2d(&a[0], &a[1], &b[0]),
+        cross_2d(&a[0], &a[1], &b[1]),
+        cross_2d(&b[0], &b[1], &a[0]),
+        cross_2d(&b[0], &b[1], &a[1]),
+    ];
+    let opposite =
+        |x: f64, y: f64| (x <= tolerance && y >= -tolerance) || (x >= -tolerance && y <= tolerance);
+    if !(opposite(d[0], d[1]) && opposite(d[2], d[3])) {
+        return false;
+    }
+    // collinear segments also need their ranges to overlap
+    let within = |s: &[Point2<f64>; 2], p: &Point2<f64>| {
+        p.x >= s[0].x.min(s[1].x) - tolerance
+            && p.x <= s[0].x.max(s[1].x) + tolerance
+            && p.y >= s[0].y.min(s[1].y) - tolerance
+            && p.y <= s[0].y.max(s[1].y) + tolerance
+    };
+    if d.iter().all(|v| v.abs() <= tolerance) {
+        return within(&a, &b[0]) || within(&a, &b[1]) || within(&b, &a[0]) || within(&b, &a[1]);
+    }
+    true
+}
+
+/// Is a 2D point inside or on a triangle of either winding.
+fn in_triangle_2d(p: &Point2<f64>, t: &[Point2<f64>; 3], tolerance: f64) -> bool {
+    let d = [0, 1, 2].map(|i| cross_2d(&t[i], &t[(i + 1) % 3], p));
+    d.iter().all(|v| *v >= -tolerance) || d.iter().all(|v| *v <= tolerance)
+}
+
+/// Do two triangles in the same plane overlap, checked in 2D by
+/// dropping the axis the normal of the plane points along the most.
+fn coplanar_intersect(
+    t: &[Point3<f64>; 3],
+    s: &[Point3<f64>; 3],
+    normal: &Vector3<f64>,
+    tolerance: f64,
+) -> bool {
+    let drop = normal.iamax();
+    let (x, y) = ((drop + 1) % 3, (drop + 2) % 3);
+    let flat = |p: &[Point3<f64>; 3]| p.map(|v| Point2::new(v[x], v[y]));
+    let (t, s) = (flat(t), flat(s));
+    // the 2D tolerance is an area
+    let area = tolerance * tolerance;
+    (0..3).any(|i| {
+        (0..3).any(|j| segments_touch([t[i], t[(i + 1) % 3]], [s[j], s[(j + 1) % 3]], area))
+    }) || in_triangle_2d(&t[0], &s, area)
+        || in_triangle_2d(&s[0], &t, area)
+}
+
+/// Do two triangles touch or cross each other, using the interval
+/// overlap test of Möller (1997) along the line their planes meet on,
+/// and a 2D overlap test for triangles in the same plane. Degenerate
+/// triangles without a normal never intersect anything.
+///
+/// Parameters
+/// ------------
+/// t, s
+///   The corners of the triangles.
+/// tolerance
+///   How far apart corners can be and still touch.
+///
+/// Returns
+/// ------------
+/// intersects
+///   If the triangles share any point.
+pub fn triangles_intersect(t: &[Point3<f64>; 3], s: &[Point3<f64>; 3], tolerance: f64) -> bool {
+    let Some(ns) = (s[1] - s[0]).cross(&(s[2] - s[0])).try_normalize(0.0) else {
+        return false;
+    };
+    let Some(nt) = (t[1] - t[0]).cross(&(t[2] - t[0])).try_normalize(0.0) else {
+        return false;
+    };
+    // every corner of one triangle on the same side of the other's plane
+    let separated =
+        |d: &[f64; 3]| d.iter().all(|v| *v > tolerance) || d.iter().all(|v| *v < -tolerance);
+    let dt = t.map(|p| ns.dot(&(p - s[0])));
+    if separated(&dt) {
+        return false;
+    }
+    if dt.iter().all(|d| d.abs() <= tolerance) {
+        return coplanar_intersect(t, s, &ns, tolerance);
+    }
+    let ds = s.map(|p| nt.dot(&(p - t[0])));
+    if separated(&ds) {
+        return false;
+    }
+    // both triangles meet the line where their planes cross
+    let Some(direction) = nt.cross(&ns).try_normalize(0.0) else {
+        return coplanar_intersect(t, s, &ns, tolerance);
+    };
+    let a = plane_interval(t, &dt, &direction, tolerance);
+    let b = plane_interval(s, &ds, &direction, tolerance);
+    a.0.max(b.0) <= a.1.min(b.1) + tolerance
+}
+
+impl Trimesh {
+    /// Check whether the surfaces of two meshes touch or cross, by
+    /// descending both face trees together with `Bvh::query_tree` and
+    /// testing pairs of faces with `triangles_intersect` until the first
+    /// hit. A mesh entirely inside another doesn't touch its surface,
+    /// which `contains` can check for.
+    ///
+    /// Parameters
+    /// ------------
+    /// other
+    ///   The mesh to check against, in the same frame.
+    ///
+    /// Returns
+    /// ------------
+    /// intersects
+    ///   If any face of one mesh touches a face of the other.
+    pub fn intersects(&self, other: &Trimesh) -> bool {
+        if self.is_empty() || other.is_empty() {
+            return false;
+        }
+        let tolerance = self.tolerances().merge.max(other.tolerances().merge);
+        let triangle = |mesh: &Trimesh, face: usize| {
+            let f = mesh.faces[face];
+            [mesh.vertices[f.0], mesh.vertices[f.1], mesh.vertices[f.2]]
+        };
+        let mut found = false;
+        self.bvh().query_tree(&other.bvh(), |a, b| {
+            found = triangles_intersect(&triangle(self, a), &triangle(other, b), tolerance);
+            found
+        });
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::{create_box, create_sphere};
+
+    #[test]
+    fn test_triangles_intersect() {
+        let t = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(0.0, 2.0, 0.0),
+        ];
+        // a triangle piercing the first one
+        let s = [
+            Point3::new(0.5, 0.5, -1.0),
+            Point3::new(0.5, 0.5, 1.0),
+            Point3::new(0.6, 0.4, 1.0),
+        ];
+        assert!(triangles_intersect(&t, &s, 0.0));
+        // the same triangle moved clear of the first
+        let moved = s.map(|p| p + Vector3::new(3.0, 0.0, 0.0));
+        assert!(!triangles_intersect(&t, &moved, 0.0));
+        // crossing the plane but not the triangle
+        let beside = s.map(|p| p + Vector3::new(1.5, 1.5, 0.0));
+        assert!(!triangles_intersect(&t, &beside, 0.0));
+        // touching at a corner
+        let corner = [
+            Point3::new(2.0, 0.0, 0.0),
+            Point3::new(3.0, 0.0, 1.0),
+            Point3::new(3.0, 1.0, 1.0),
+        ];
+        assert!(triangles_intersect(&t, &corner, 1e-12));
+
+        // coplanar triangles overlapping, nested and apart
+        let shifted = t.map(|p| p + Vector3::new(1.0, 1.0, 0.0));
+        assert!(triangles_intersect(&t, &shifted, 1e-12));
+        let inner = t.map(|p| Point3::new(0.1 + p.x * 0.1, 0.1 + p.y * 0.1, 0.0));
+        assert!(triangles_intersect(&t, &inner, 1e-12));
+        assert!(triangles_intersect(&inner, &t, 1e-12));
+        let apart = t.map(|p| p + Vector3::new(5.0, 0.0, 0.0));
+        assert!(!triangles_intersect(&t, &apart, 1e-12));
+    }
+
+    #[test]
+    fn test_mesh_intersects() {
+        let a = create_box(&[2.0, 2.0, 2.0]);
+        let overlapping = a.apply_translation(&Vector3::new(1.0, 0.5, 0.2)).unwrap();
+        assert!(a.intersects(&overlapping));
+        let apart = a.apply_translation(&Vector3::new(3.0, 0.0, 0.0)).unwrap();
+        assert!(!a.intersects(&apart));
+        // sharing a face exactly
+        let touching = a.apply_translation(&Vector3::new(2.0, 0.0, 0.0)).unwrap();
+        assert!(a.intersects(&touching));
+
+        // a sphere inside the box doesn't touch its surface
+        let sphere = create_sphere(0.5, [16, 8]).unwrap();
+        assert!(!a.intersects(&sphere));
+        let poking = sphere
+            .apply_translation(&Vector3::new(0.8, 0.0, 0.0))
+            .unwrap();
+        assert!(a.intersects(&poking));
+        assert!(!a.intersects(&Trimesh::default()));
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::{Result, anyhow};
+use nalgebra::Point3;
+
+use crate::mesh::Trimesh;
+
+/// The number of points sampled on each surface by `mean_surface_distance`.
+pub const DEFAULT_SAMPLES: usize = 10_000;
+
+// the constants of the R2 low discrepancy sequence
+const R2: [f64; 2] = [0.754_877_666_246_692_7, 0.569_840_290_998_053_2];
+
+/// Points spread over the surface of a mesh with a density proportional
+/// to area. The samples are deterministic rather than random so metrics
+/// computed from them are repeatable: faces are picked at even steps
+/// along the cumulative area and the position on each face comes from
+/// a low discrepancy sequence.
+///
+/// Parameters
+/// ------------
+/// mesh
+///   The mesh to sample the surface of.
+/// count
+///   The number of points to sample.
+///
+/// Returns
+/// ------------
+/// samples
+///   The sampled points, which are empty if the mesh has no area.
+pub fn sample_surface(mesh: &Trimesh, count: usize) -> Vec<Point3<f64>> {
+    let mut cumulative = Vec::with_capacity(mesh.faces.len());
+    let mut total = 0.0;
+    for area in mesh.faces_area().iter() {
+        total += area;
+        cumulative.push(total);
+    }
+    if count == 0 || total.is_nan() || total <= 0.0 {
+        return Vec::new();
+    }
+    let triangles: Vec<[Point3<f64>; 3]> = mesh.triangles().collect();
+    (0..count)
+        .map(|i| {
+            let target = (i as f64 + 0.5) / count as f64 * total;
+            let face = cumulative
+                .partition_point(|c| *c < target)
+                .min(cumulative.len() - 1);
+            let (mut u, mut v) = (
+                (0.5 + i as f64 * R2[0]).fract(),
+                (0.5 + i as f64 * R2[1]).fract(),
+            );
+            // fold the far half of the square back onto the triangle
+            if u + v > 1.0 {
+                (u, v) = (1.0 - u, 1.0 - v);
+            }
+            let [a, b, c] = triangles[face];
+            a + (b - a) * u + (c - a) * v
+        })
+        .collect()
+}
+
+/// The distance from points sampled on the surface of one mesh to the
+/// closest point on the surface of another, which is one direction of
+/// the comparison used by `mean_surface_distance`.
+///
+/// Parameters
+/// ------------
+/// from
+///   The mesh to sample points on.
+/// to
+///   The mesh to measure the distance to.
+/// samples
+///   The number of points to sample on `from`.
+///
+/// Returns
+/// ------------
+/// distances
+///   The distance of every sample, or an error if either mesh is empty.
+pub fn surface_distances(from: &Trimesh, to: &Trimesh, samples: usize) -> Result<Vec<f64>> {
+    if from.is_empty() || to.is_empty() {
+        return Err(anyhow!("Both meshes must have faces to compare"));
+    }
+    Ok(to
+        .closest_point(&sample_surface(from, samples))
+        .into_iter()
+        .flatten()
+        .map(|(_, distance, _)| distance)
+        .collect())
+}
+
+/// The Hausdorff distance between the surfaces of two meshes as measured
+/// by `Trimesh::hausdorff`, returning an error for an empty mesh like
+/// the other metrics here.
+///
+/// Parameters
+/// ------------
+/// a
+///   The first mesh.
+/// b
+///   The second mesh.
+/// resolution
+///   The number of steps to divide the edges of every face into
+///   for the grid of points, where one is only the vertices.
+///
+/// Returns
+/// ------------
+/// distance
+///   The largest distance found in either direction,
+///   or an error if either mesh is empty.
+pub fn hausdorff_distance(a: &Trimesh, b: &Trimesh, resolution: usize) -> Result<f64> {
+    a.hausdorff(b, resolution)
+        .ok_or_else(|| anyhow!("Both meshes must have faces to compare"))
+}
+
+/// The mean distance between the surfaces of two meshes, averaged over
+/// `DEFAULT_SAMPLES` points sampled on each surface so it's symmetric.
+/// Unlike the Hausdorff distance it measures how far the surfaces are
+/// apart overall rather than at their worst point.
+///
+/// Parameters
+/// ------------
+/// a
+///   The first mesh.
+/// b
+///   The second mesh.
+///
+/// Returns
+/// ------------
+/// distance
+///   The mean distance of the samples of both surfaces,
+///   or an error if either mesh is empty.
+pub fn mean_surface_distance(a: &Trimesh, b: &Trimesh) -> Result<f64> {
+    let forward = surface_distances(a, b, DEFAULT_SAMPLES)?;
+    let backward = surface_distances(b, a, DEFAULT_SAMPLES)?;
+    let count = forward.len() + backward.len();
+    if count == 0 {
+        return Ok(0.0);
+    }
+    Ok(forward.iter().chain(backward.iter()).sum::<f64>() / count as f64)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_compare() {
+        let inner = create_box(&[2.0, 2.0, 2.0]);
+        let outer = create_box(&[2.2, 2.2, 2.2]);
+
+        // samples cover every face in proportion to its area
+        let samples = sample_surface(&inner, 600);
+        assert_eq!(samples.len(), 600);
+        for axis in 0..3 {
+            for side in [-1.0, 1.0] {
+                let count = samples
+                    .iter()
+                    .filter(|p| (p[axis] - side).abs() < 1e-12)
+                    .count();
+                assert!((95..=105).contains(&count));
+            }
+        }
+
+        // every point of the inner box is a tenth from the outer box
+        let distances = surface_distances(&inner, &outer, 500).unwrap();
+        assert_eq!(distances.len(), 500);
+        assert!(distances.iter().all(|d| (d - 0.1).abs() < 1e-12));
+
+        // the corners of the outer box are the furthest from the inner box
+        let hausdorff = hausdorff_distance(&inner, &outer, 4).unwrap();
+        assert_relative_eq!(hausdorff, 3.0_f64.sqrt() / 10.0, epsilon = 1e-12);
+        let mean = mean_surface_distance(&inner, &outer).unwrap();
+        assert!(mean > 0.1 && mean < hausdorff);
+
+        assert_relative_eq!(hausdorff_distance(&inner, &inner, 4).unwrap(), 0.0);
+        assert!(mean_surface_distance(&inner, &inner).unwrap() < 1e-12);
+        assert!(hausdorff_distance(&inner, &Trimesh::default(), 4).is_err());
+        assert!(sample_surface(&Trimesh::default(), 10).is_empty());
+    }
+}
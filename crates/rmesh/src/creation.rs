@@ -4,6 +4,7 @@ use nalgebra::{Matrix3, Matrix4, Point2, Point3, Rotation3, Transform3, Unit, Ve
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 
 use crate::mesh::Trimesh;
+use crate::tolerance::Tolerances;
 
 /// Create a mesh of a box centered at the origin with the
 /// specified axis aligned bounding box size.
@@ -38,8 +39,8 @@ pub fn create_box(extents: &[f64; 3]) -> Trimesh {
 
     // Faces as Vec<(usize, usize, usize)>
     let faces = vec![
-        (0, 1, 2),
-        (0, 2, 3),
+        (0, 2, 1),
+        (0, 3, 2),
         (4, 5, 6),
         (4, 6, 7),
         (0, 1, 5),
@@ -56,10 +57,149 @@ pub fn create_box(extents: &[f64; 3]) -> Trimesh {
     Trimesh {
         vertices,
         faces,
+        primitive: Some(Primitive::new(PrimitiveKind::Box { extents: *extents })),
         ..Default::default()
     }
 }
 
+/// The exact shape a mesh was tessellated from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrimitiveKind {
+    // an axis aligned box centered at the origin
+    Box { extents: [f64; 3] },
+    // a sphere centered at the origin
+    Sphere { radius: f64 },
+    // a cylinder centered at the origin along the Z axis
+    Cylinder { radius: f64, height: f64 },
+}
+
+/// The parametric description of a mesh created from a primitive, which
+/// lets exporters and physics engines use the exact shape rather than
+/// the tessellated approximation of it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Primitive {
+    pub kind: PrimitiveKind,
+    // places the primitive from its own frame into the frame of the mesh
+    pub transform: Matrix4<f64>,
+}
+
+impl Primitive {
+    /// A primitive in its own frame with an identity transform.
+    pub fn new(kind: PrimitiveKind) -> Self {
+        Self {
+            kind,
+            transform: Matrix4::identity(),
+        }
+    }
+}
+
+/// Create a mesh of a sphere centered at the origin out of rings
+/// of latitude and longitude, with a single vertex at each pole.
+///
+/// Parameters
+/// -------------
+/// radius
+///   The radius of the sphere.
+/// count
+///   The number of segments around the equator and from pole to pole.
+///
+/// Returns
+/// -------------
+///  A Trimesh representing the sphere or an error if it would be degenerate.
+pub fn create_sphere(radius: f64, count: [usize; 2]) -> Result<Trimesh> {
+    let [around, rings] = count;
+    if around < 3 || rings < 2 || !(radius.is_finite() && radius > 0.0) {
+        return Err(anyhow::anyhow!(
+            "Sphere needs a positive radius, 3 segments around and 2 rings: {radius} {count:?}"
+        ));
+    }
+
+    let mut vertices = vec![Point3::new(0.0, 0.0, radius)];
+    for ring in 1..rings {
+        let theta = std::f64::consts::PI * ring as f64 / rings as f64;
+        for segment in 0..around {
+            let phi = std::f64::consts::TAU * segment as f64 / around as f64;
+            vertices.push(Point3::new(
+                radius * theta.sin() * phi.cos(),
+                radius * theta.sin() * phi.sin(),
+                radius * theta.cos(),
+            ));
+        }
+    }
+    vertices.push(Point3::new(0.0, 0.0, -radius));
+
+    // the first vertex of each ring and the south pole
+    let ring = |r: usize| 1 + r * around;
+    let south = vertices.len() - 1;
+    let mut faces = Vec::with_capacity(around * 2 * (rings - 1));
+    for s in 0..around {
+        let n = (s + 1) % around;
+        faces.push((0, ring(0) + s, ring(0) + n));
+        for r in 0..rings - 2 {
+            let (top, bottom) = (ring(r), ring(r + 1));
+            faces.push((top + s, bottom + s, bottom + n));
+            faces.push((top + s, bottom + n, top + n));
+        }
+        faces.push((south, ring(rings - 2) + n, ring(rings - 2) + s));
+    }
+
+    Ok(Trimesh {
+        vertices,
+        faces,
+        primitive: Some(Primitive::new(PrimitiveKind::Sphere { radius })),
+        ..Default::default()
+    })
+}
+
+/// Create a mesh of a closed cylinder centered at the origin along Z.
+///
+/// Parameters
+/// -------------
+/// radius
+///   The radius of the cylinder.
+/// height
+///   The length of the cylinder along Z.
+/// sections
+///   The number of segments around the cylinder.
+///
+/// Returns
+/// -------------
+///  A Trimesh representing the cylinder or an error if it would be degenerate.
+pub fn create_cylinder(radius: f64, height: f64, sections: usize) -> Result<Trimesh> {
+    if sections < 3 || !(radius.is_finite() && radius > 0.0 && height.is_finite() && height > 0.0) {
+        return Err(anyhow::anyhow!(
+            "Cylinder needs a positive size and 3 sections: {radius} {height} {sections}"
+        ));
+    }
+    let half = height / 2.0;
+
+    // the center of each cap followed by interleaved bottom and top rims
+    let mut vertices = vec![Point3::new(0.0, 0.0, -half), Point3::new(0.0, 0.0, half)];
+    for segment in 0..sections {
+        let phi = std::f64::consts::TAU * segment as f64 / sections as f64;
+        let (x, y) = (radius * phi.cos(), radius * phi.sin());
+        vertices.push(Point3::new(x, y, -half));
+        vertices.push(Point3::new(x, y, half));
+    }
+
+    let mut faces = Vec::with_capacity(sections * 4);
+    for s in 0..sections {
+        let (b0, t0) = (2 + 2 * s, 3 + 2 * s);
+        let (b1, t1) = (2 + 2 * ((s + 1) % sections), 3 + 2 * ((s + 1) % sections));
+        faces.push((0, b1, b0));
+        faces.push((1, t0, t1));
+        faces.push((b0, b1, t1));
+        faces.push((b0, t1, t0));
+    }
+
+    Ok(Trimesh {
+        vertices,
+        faces,
+        primitive: Some(Primitive::new(PrimitiveKind::Cylinder { radius, height })),
+        ..Default::default()
+    })
+}
+
 use earcut::Earcut;
 
 /// A wrapper object for a triangulator
@@ -130,10 +270,18 @@ impl Triangulator {
         let mut result: Vec<usize> = vec![];
         earcut.earcut(flat, &holes, &mut result);
 
+        // earcut returns positions in the flat array so map
+        // them back to the indices of the original vertices
+        let index: Vec<usize> = exterior
+            .iter()
+            .chain(interiors.iter().flatten())
+            .copied()
+            .collect();
+
         // convert the flat result into a list of triangles
         result
             .chunks_exact(3)
-            .map(|chunk| (chunk[0], chunk[1], chunk[2]))
+            .map(|chunk| (index[chunk[0]], index[chunk[1]], index[chunk[2]]))
             .collect()
     }
 
@@ -232,6 +380,17 @@ impl Plane {
     /// plane
     ///   The plane that best fits the points using the specified method.
     pub fn from_points(points: &[Point3<f64>], method_cross: bool) -> Result<Self> {
+        Self::fit(points, method_cross, &Tolerances::default())
+    }
+
+    /// Fit a plane to points like `from_points` with explicit tolerances,
+    /// where the cross product method only accepts three points whose
+    /// cross product is longer than `tolerances.zero`.
+    pub fn fit(
+        points: &[Point3<f64>],
+        method_cross: bool,
+        tolerances: &Tolerances,
+    ) -> Result<Self> {
         if points.len() < 3 {
             return Err(anyhow::anyhow!(
                 "At least 3 points are required to define a plane."
@@ -259,7 +418,7 @@ impl Plane {
                 // run the cross product
                 let normal = v1.cross(&v2);
                 // this should only be zero if the points are colinear or identical
-                if normal.norm() > 1e-10 {
+                if normal.norm() > tolerances.zero {
                     // we have a nonzero norm so return a plane
                     return Ok(Plane::new(normal.normalize(), p0));
                 }
@@ -430,6 +589,61 @@ mod tests {
         (0..count).map(|i| start + i as f64 * step).collect()
     }
 
+    #[test]
+    fn test_primitive() {
+        let sphere = create_sphere(2.0, [64, 32]).unwrap();
+        assert_eq!(sphere.vertices.len(), 64 * 31 + 2);
+        assert!(
+            sphere
+                .vertices
+                .iter()
+                .all(|v| (v.coords.norm() - 2.0).abs() < 1e-12)
+        );
+        // a closed outward facing mesh slightly smaller than the sphere
+        let exact = 4.0 / 3.0 * std::f64::consts::PI * 8.0;
+        assert!(sphere.volume() < exact && sphere.volume() > exact * 0.98);
+        assert!(create_sphere(1.0, [2, 8]).is_err());
+
+        let cylinder = create_cylinder(1.0, 3.0, 128).unwrap();
+        let exact = std::f64::consts::PI * 3.0;
+        assert!(cylinder.volume() < exact && cylinder.volume() > exact * 0.99);
+        assert_eq!(
+            cylinder.primitive.as_ref().unwrap().kind,
+            PrimitiveKind::Cylinder {
+                radius: 1.0,
+                height: 3.0
+            }
+        );
+        assert!(create_cylinder(1.0, 0.0, 8).is_err());
+
+        // transforms are kept alongside the exact parameters
+        let mesh = create_box(&[1.0, 2.0, 3.0])
+            .apply_translation(&Vector3::new(1.0, 0.0, 0.0))
+            .unwrap()
+            .apply_scale(2.0)
+            .unwrap();
+        let primitive = mesh.primitive.as_ref().unwrap();
+        assert_eq!(
+            primitive.kind,
+            PrimitiveKind::Box {
+                extents: [1.0, 2.0, 3.0]
+            }
+        );
+        assert_relative_eq!(
+            primitive.transform,
+            Matrix4::new_scaling(2.0) * Matrix4::new_translation(&Vector3::new(1.0, 0.0, 0.0))
+        );
+        let placed = create_box(&[1.0, 2.0, 3.0]).apply_transform(&primitive.transform);
+        assert_relative_eq!(
+            placed.unwrap().bounds().unwrap().0,
+            mesh.bounds().unwrap().0
+        );
+
+        // anything which changes the geometry drops the primitive
+        assert!(mesh.submesh(&[0, 1]).unwrap().primitive.is_none());
+        assert!(mesh.process(false).unwrap().primitive.is_none());
+    }
+
     #[test]
     fn test_mesh_normals() {
         let m = Trimesh::from_slice(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], &[0, 1, 2])
@@ -439,6 +653,29 @@ mod tests {
         assert_relative_eq!(normals[0], Vector3::new(0.0, 0.0, 1.0), epsilon = 1e-6);
     }
 
+    #[test]
+    fn test_triangulate_indices() {
+        // a square referencing vertices that aren't at the start of the list
+        let vertices = vec![
+            Point3::new(9.0, 9.0, 9.0),
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(9.0, 9.0, 9.0),
+            Point3::new(1.0, 0.0, 0.0),
+            Point3::new(1.0, 1.0, 0.0),
+            Point3::new(0.0, 1.0, 0.0),
+        ];
+        let exterior = vec![1, 3, 4, 5];
+        let triangles = Triangulator::new()
+            .triangulate_3d(&exterior, &[], &vertices)
+            .unwrap();
+        assert_eq!(triangles.len(), 2);
+        for tri in triangles {
+            for i in [tri.0, tri.1, tri.2] {
+                assert!(exterior.contains(&i));
+            }
+        }
+    }
+
     #[test]
     fn test_align_vectors() {
         for theta in linspace(0.0, 360.0, 10000) {
@@ -0,0 +1,173 @@
+use anyhow::{Result, anyhow};
+use nalgebra::Vector4;
+
+use crate::mesh::Trimesh;
+use crate::stats::percentiles;
+
+/// Summary statistics of the deviation of one mesh from another.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviationSummary {
+    // the mean of the absolute deviation of every vertex
+    pub mean: f64,
+    // the root mean square of the deviation of every vertex
+    pub rms: f64,
+    // the largest absolute deviation of any vertex
+    pub max: f64,
+    // the median absolute deviation
+    pub p50: f64,
+    // the 95th percentile of the absolute deviation
+    pub p95: f64,
+    // the Hausdorff distance between the vertices of either
+    // mesh and the other, from `Trimesh::hausdorff` at resolution one
+    pub hausdorff: f64,
+}
+
+/// A mesh colored by its deviation from a reference mesh.
+#[derive(Debug)]
+pub struct MeshDiff {
+    // the compared mesh with the deviation as its first vertex colors
+    pub mesh: Trimesh,
+    // the signed deviation of every vertex which is positive outside
+    // of the reference and negative inside of it
+    pub deviation: Vec<f64>,
+    pub summary: DeviationSummary,
+}
+
+/// Map a value to a diverging blue, white and red colormap.
+fn diverging_color(value: f64, scale: f64) -> Vector4<u8> {
+    let t = if scale > 0.0 {
+        (value / scale).clamp(-1.0, 1.0)
+    } else {
+        0.0
+    };
+    // fade the opposite channels out from white at zero
+    let fade = (255.0 * (1.0 - t.abs())).round() as u8;
+    if t >= 0.0 {
+        Vector4::new(255, fade, fade, 255)
+    } else {
+        Vector4::new(fade, fade, 255, 255)
+    }
+}
+
+/// Compare a mesh against a reference such as a golden result in a
+/// test, producing a copy colored by how far every vertex deviates
+/// from the reference along with summary statistics in one call.
+///
+/// Vertices outside of the reference are colored red and vertices
+/// inside of it blue, fading to white where the meshes agree with
+/// the full color at the largest deviation. The sign comes from the
+/// ray parity of `Trimesh::contains` and relies on the reference
+/// being closed, while the distance is to the closest point.
+///
+/// Parameters
+/// ------------
+/// mesh
+///   The mesh to compare, such as the result of processing.
+/// reference
+///   The mesh to compare against.
+///
+/// Returns
+/// ------------
+/// diff
+///   The colored mesh, the deviation of every vertex and the summary
+///   or an error if either mesh is empty.
+pub fn diff_visualize(mesh: &Trimesh, reference: &Trimesh) -> Result<MeshDiff> {
+    if mesh.is_empty() || reference.is_empty() {
+        return Err(anyhow!("Both meshes must have faces to compare"));
+    }
+    // both queries use the cached tree of the reference faces
+    let inside = reference.contains(&mesh.vertices);
+    let deviation: Vec<f64> = reference
+        .closest_point(&mesh.vertices)
+        .into_iter()
+        .zip(inside)
+        .map(|(closest, inside)| {
+            let distance = closest.map_or(0.0, |(_, distance, _)| distance);
+            if inside { -distance } else { distance }
+        })
+        .collect();
+    let absolute: Vec<f64> = deviation.iter().map(|d| d.abs()).collect();
+    let max = absolute.iter().copied().fold(0.0, f64::max);
+
+    let count = absolute.len() as f64;
+    let ranks = percentiles(&absolute, &[50.0, 95.0])?;
+    let summary = DeviationSummary {
+        mean: absolute.iter().sum::<f64>() / count,
+        rms: (absolute.iter().map(|d| d * d).sum::<f64>() / count).sqrt(),
+        max,
+        p50: ranks[0],
+        p95: ranks[1],
+        hausdorff: mesh.hausdorff(reference, 1).unwrap_or(max),
+    };
+
+    let mut colored = mesh.clone();
+    colored.attributes_vertex.colors.insert(
+        0,
+        deviation.iter().map(|d| diverging_color(*d, max)).collect(),
+    );
+
+    Ok(MeshDiff {
+        mesh: colored,
+        deviation,
+        summary,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_diff_visualize() {
+        let reference = create_box(&[2.0, 2.0, 2.0]);
+
+        // identical meshes have no deviation and are white
+        let same = diff_visualize(&reference, &reference).unwrap();
+        assert_relative_eq!(same.summary.max, 0.0);
+        assert_relative_eq!(same.summary.hausdorff, 0.0);
+        assert!(
+            same.mesh.attributes_vertex.colors[0]
+                .iter()
+                .all(|c| *c == Vector4::new(255, 255, 255, 255))
+        );
+
+        // push one corner out and another in
+        let mut mesh = create_box(&[2.0, 2.0, 2.0]);
+        let outer = mesh
+            .vertices
+            .iter()
+            .position(|v| v.coords.sum() > 2.9)
+            .unwrap();
+        let inner = mesh
+            .vertices
+            .iter()
+            .position(|v| v.coords.sum() < -2.9)
+            .unwrap();
+        mesh.vertices[outer] *= 1.5;
+        mesh.vertices[inner] *= 0.5;
+
+        let diff = diff_visualize(&mesh, &reference).unwrap();
+        assert_eq!(diff.deviation.len(), mesh.vertices.len());
+        // the closest point to a pushed out corner is the corner
+        assert_relative_eq!(diff.deviation[outer], 0.5 * 3.0_f64.sqrt(), epsilon = 1e-10);
+        // a pulled in corner is closest to the three faces it was on
+        assert_relative_eq!(diff.deviation[inner], -0.5, epsilon = 1e-10);
+        assert_relative_eq!(diff.summary.max, diff.deviation[outer]);
+        assert_relative_eq!(
+            diff.summary.mean,
+            (diff.deviation[outer] + 0.5) / 8.0,
+            epsilon = 1e-10
+        );
+        assert_eq!(diff.summary.p50, 0.0);
+        assert!(diff.summary.hausdorff >= diff.summary.max);
+
+        let colors = &diff.mesh.attributes_vertex.colors[0];
+        assert_eq!(colors[outer], Vector4::new(255, 0, 0, 255));
+        assert!(colors[inner].z == 255 && colors[inner].x < 255);
+
+        assert!(diff_visualize(&Trimesh::default(), &reference).is_err());
+    }
+}
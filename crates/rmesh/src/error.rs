@@ -0,0 +1,144 @@
+use thiserror::Error;
+
+use crate::exchange::MeshFormat;
+
+/// The errors returned by the loaders and mesh constructors, which
+/// callers can match on rather than inspecting the message. Everything
+/// else in the crate returns `anyhow` errors which these convert into.
+#[derive(Debug, Error)]
+pub enum RmeshError {
+    // reading a file or stream failed
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    // the contents of a file don't match its format
+    #[error("Invalid {} file{}: {message}", .format.extension(), on_line(.line))]
+    Parse {
+        format: MeshFormat,
+        // the line of a text format the problem is on counting from one
+        line: Option<usize>,
+        message: String,
+    },
+
+    // the format isn't supported or couldn't be detected
+    #[error("Unsupported format: {0}")]
+    UnsupportedFormat(String),
+
+    // the geometry is inconsistent, like a face referencing a missing vertex
+    #[error("Invalid geometry: {0}")]
+    InvalidGeometry(String),
+
+    // any other error
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Describe the line an error is on if known.
+fn on_line(line: &Option<usize>) -> String {
+    line.map(|l| format!(" on line {l}")).unwrap_or_default()
+}
+
+impl RmeshError {
+    /// The name of the kind of error, for bindings which
+    /// can only pass errors across as strings.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RmeshError::Io(_) => "IoError",
+            RmeshError::Parse { .. } => "ParseError",
+            RmeshError::UnsupportedFormat(_) => "UnsupportedFormat",
+            RmeshError::InvalidGeometry(_) => "InvalidGeometry",
+            RmeshError::Other(_) => "Error",
+        }
+    }
+
+    /// Classify an error from a loader: errors which are already
+    /// structured or from I/O keep their kind and anything else
+    /// is a problem parsing a file of the format.
+    pub(crate) fn from_load(format: &MeshFormat, error: anyhow::Error) -> Self {
+        let error = match error.downcast::<RmeshError>() {
+            Ok(error) => return error,
+            Err(error) => error,
+        };
+        match error.downcast::<std::io::Error>() {
+            Ok(error) => RmeshError::Io(error),
+            Err(error) => RmeshError::Parse {
+                format: format.clone(),
+                line: None,
+                message: format!("{error:#}"),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::exchange::{ExportOptions, export_mesh, load_mesh, load_mesh_strict, load_scene};
+    use crate::mesh::Trimesh;
+    use nalgebra::Point3;
+
+    #[test]
+    fn test_error_kinds() {
+        assert!(matches!(
+            MeshFormat::from_string("step"),
+            Err(RmeshError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            MeshFormat::detect(b"glTF"),
+            Err(RmeshError::UnsupportedFormat(_))
+        ));
+        let error = load_mesh(b"OFF\n3 1 0\n0 0 0\n", MeshFormat::OFF).unwrap_err();
+        assert!(matches!(
+            error,
+            RmeshError::Parse {
+                format: MeshFormat::OFF,
+                ..
+            }
+        ));
+        assert_eq!(error.kind(), "ParseError");
+        // loading a scene and exporting are structured the same way
+        assert!(matches!(
+            load_scene(b"not a zip", MeshFormat::THREEMF),
+            Err(RmeshError::Parse {
+                format: MeshFormat::THREEMF,
+                ..
+            })
+        ));
+        assert!(matches!(
+            load_scene(b"OFF\n3 1 0\n0 0 0\n", MeshFormat::OFF),
+            Err(RmeshError::Parse { .. })
+        ));
+        let mesh = load_mesh(
+            b"OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n",
+            MeshFormat::OFF,
+        )
+        .unwrap();
+        assert!(matches!(
+            export_mesh(&mesh, MeshFormat::THREEMF, &ExportOptions::default()),
+            Err(RmeshError::UnsupportedFormat(_))
+        ));
+
+        // validation knows the line the problem is on
+        let error = load_mesh_strict(b"v 0 0 0\nf 1 2 3\n", MeshFormat::OBJ).unwrap_err();
+        let RmeshError::Parse { line, .. } = &error else {
+            panic!("expected a parse error: {error}");
+        };
+        assert_eq!(*line, Some(2));
+        assert!(
+            error
+                .to_string()
+                .starts_with("Invalid obj file on line 2: ")
+        );
+
+        let error = Trimesh::new(vec![Point3::origin()], vec![(0, 1, 2)], None, None);
+        assert!(matches!(error, Err(RmeshError::InvalidGeometry(_))));
+
+        // structured errors pass through `anyhow` unchanged
+        let wrapped: anyhow::Error = RmeshError::InvalidGeometry("bad".into()).into();
+        assert!(matches!(
+            RmeshError::from_load(&MeshFormat::STL, wrapped),
+            RmeshError::InvalidGeometry(_)
+        ));
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+use rayon::prelude::*;
+
+use crate::exchange::{ExportOptions, MeshFormat, export_mesh, load_mesh};
+use crate::mesh::Trimesh;
+
+/// Options for converting many meshes with `batch_convert`.
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    // how to write every converted mesh
+    pub export: ExportOptions,
+    // merge vertices before export as `Trimesh::process` does
+    pub process: bool,
+}
+
+/// The outcome of converting every input of `batch_convert`.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    // the converted file or the error for each input in order
+    pub results: Vec<Result<Vec<u8>>>,
+}
+
+impl BatchReport {
+    /// The number of inputs which converted successfully.
+    pub fn succeeded(&self) -> usize {
+        self.results.iter().filter(|r| r.is_ok()).count()
+    }
+
+    /// The index and error of every input which failed.
+    pub fn failures(&self) -> Vec<(usize, &anyhow::Error)> {
+        self.results
+            .iter()
+            .enumerate()
+            .filter_map(|(i, r)| r.as_ref().err().map(|e| (i, e)))
+            .collect()
+    }
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "converted {} of {}",
+            self.succeeded(),
+            self.results.len()
+        )?;
+        for (index, error) in self.failures() {
+            write!(f, "\n  {index}: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// How long loading one file with `load_many_timed` took and what it held.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoadStats {
+    // the size of the file
+    pub bytes: usize,
+    // the time spent in the loader
+    pub duration: Duration,
+    // the number of vertices loaded, which is zero on failure
+    pub vertices: usize,
+    // the number of faces loaded, which is zero on failure
+    pub faces: usize,
+}
+
+impl LoadStats {
+    /// The rate the file was loaded at in megabytes per second.
+    pub fn throughput(&self) -> f64 {
+        let seconds = self.duration.as_secs_f64();
+        if seconds > 0.0 {
+            self.bytes as f64 / 1e6 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Run a loader or converter, turning a panic into an error so
+/// one bad file can't take down the rest of a batch.
+fn guarded<T>(action: &str, format: &MeshFormat, run: impl FnOnce() -> Result<T>) -> Result<T> {
+    catch_unwind(AssertUnwindSafe(run)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(anyhow!("{action} {format:?} panicked: {message}"))
+    })
+}
+
+/// Load and export a single file.
+fn convert(
+    data: &[u8],
+    format: MeshFormat,
+    target: MeshFormat,
+    options: &ConvertOptions,
+) -> Result<Vec<u8>> {
+    let mut mesh = load_mesh(data, format)?;
+    if options.process {
+        mesh = mesh.process(false)?;
+    }
+    Ok(export_mesh(&mesh, target, &options.export)?.into_bytes())
+}
+
+/// Convert many files to one format in parallel, which is the bulk of
+/// preprocessing a dataset. Every input is converted independently so
+/// a file which fails to load, or even panics a loader, only fails its
+/// own result rather than the whole batch.
+///
+/// Parameters
+/// ------------
+/// inputs
+///   The bytes and format of every file.
+/// target
+///   The format to convert every file to.
+/// options
+///   How to process and write the converted meshes.
+///
+/// Returns
+/// ------------
+/// report
+///   The converted file or error for every input in order.
+pub fn batch_convert<B: AsRef<[u8]> + Sync>(
+    inputs: &[(B, MeshFormat)],
+    target: MeshFormat,
+    options: &ConvertOptions,
+) -> BatchReport {
+    let results = inputs
+        .par_iter()
+        .map(|(data, format)| {
+            guarded("Converting", format, || {
+                convert(data.as_ref(), format.clone(), target.clone(), options)
+            })
+        })
+        .collect();
+    BatchReport { results }
+}
+
+/// Load many files in parallel with `load_many_timed`
+/// and discard the statistics.
+///
+/// Parameters
+/// ------------
+/// items
+///   The bytes and format of every file.
+///
+/// Returns
+/// ------------
+/// meshes
+///   The loaded mesh or error for every file in order.
+pub fn load_many(items: Vec<(Vec<u8>, MeshFormat)>) -> Vec<Result<Trimesh>> {
+    load_many_timed(&items)
+        .into_iter()
+        .map(|(result, _)| result)
+        .collect()
+}
+
+/// Load many files in parallel across threads, timing each one, which is
+/// how a corpus of files is benchmarked. As with `batch_convert` a file
+/// which fails to load, or even panics a loader, only fails its own result.
+///
+/// Parameters
+/// ------------
+/// items
+///   The bytes and format of every file.
+///
+/// Returns
+/// ------------
+/// loaded
+///   The loaded mesh or error for every file in order, along with
+///   the time it took to load and the size of what was loaded.
+pub fn load_many_timed<B: AsRef<[u8]> + Sync>(
+    items: &[(B, MeshFormat)],
+) -> Vec<(Result<Trimesh>, LoadStats)> {
+    items
+        .par_iter()
+        .map(|(data, format)| {
+            let data = data.as_ref();
+            let start = Instant::now();
+            let result = guarded("Loading", format, || Ok(load_mesh(data, format.clone())?));
+            let duration = start.elapsed();
+            let (vertices, faces) = result
+                .as_ref()
+                .map_or((0, 0), |mesh| (mesh.vertices.len(), mesh.faces.len()));
+            let stats = LoadStats {
+                bytes: data.len(),
+                duration,
+                vertices,
+                faces,
+            };
+            (result, stats)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_batch_convert() {
+        let inputs: Vec<(Vec<u8>, MeshFormat)> = vec![
+            (
+                include_bytes!("../../../../test/data/unit_cube.STL").to_vec(),
+                MeshFormat::STL,
+            ),
+            (b"garbage".to_vec(), MeshFormat::OFF),
+            (
+                include_bytes!("../../../../test/data/fuze.obj").to_vec(),
+                MeshFormat::OBJ,
+            ),
+            // the PLY loader panics rather than returning an error
+            (b"ply\n".to_vec(), MeshFormat::PLY),
+        ];
+        let options = ConvertOptions {
+            process: true,
+            ..Default::default()
+        };
+        let report = batch_convert(&inputs, MeshFormat::OBJ, &options);
+        assert_eq!(report.results.len(), 4);
+        assert_eq!(report.succeeded(), 2);
+        let failures: Vec<usize> = report.failures().iter().map(|(i, _)| *i).collect();
+        assert_eq!(failures, vec![1, 3]);
+        assert!(report.to_string().starts_with("converted 2 of 4\n  1: "));
+
+        // the converted files load back as the same meshes
+        let cube = load_mesh(report.results[0].as_ref().unwrap(), MeshFormat::OBJ).unwrap();
+        assert_eq!(cube.vertices.len(), 8);
+        assert_eq!(cube.faces.len(), 12);
+
+        // an unsupported target fails every input on its own
+        let report = batch_convert(&inputs, MeshFormat::XYZ, &options);
+        assert_eq!(report.succeeded(), 0);
+        assert_eq!(report.failures().len(), 4);
+    }
+
+    #[test]
+    fn test_load_many() {
+        let inputs: Vec<(Vec<u8>, MeshFormat)> = vec![
+            (
+                include_bytes!("../../../../test/data/unit_cube.STL").to_vec(),
+                MeshFormat::STL,
+            ),
+            (b"ply\n".to_vec(), MeshFormat::PLY),
+            (
+                include_bytes!("../../../../test/data/fuze.obj").to_vec(),
+                MeshFormat::OBJ,
+            ),
+        ];
+        let timed = load_many_timed(&inputs);
+        assert_eq!(timed.len(), 3);
+        let (cube, stats) = &timed[0];
+        assert_eq!(cube.as_ref().unwrap().faces.len(), 12);
+        assert_eq!((stats.vertices, stats.faces), (36, 12));
+        assert_eq!(stats.bytes, inputs[0].0.len());
+        // the panicking loader only fails its own file
+        assert!(timed[1].0.is_err());
+        assert_eq!((timed[1].1.vertices, timed[1].1.faces), (0, 0));
+
+        let loaded = load_many(inputs);
+        let ok: Vec<bool> = loaded.iter().map(|r| r.is_ok()).collect();
+        assert_eq!(ok, vec![true, false, true]);
+    }
+}
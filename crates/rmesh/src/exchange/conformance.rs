@@ -0,0 +1,233 @@
+//! Loads small hand-written files from `test/data/conformance` which
+//! each exercise an edge case of a format, and checks the exact
+//! vertices, faces and attributes so a change to any loader that
+//! alters what it produces for them is caught.
+
+use approx::assert_relative_eq;
+use nalgebra::{Point3, Vector2, Vector3, Vector4};
+
+use crate::attributes::DEFAULT_COLOR;
+use crate::exchange::{MeshFormat, load_mesh, load_mesh_auto, load_mesh_reader, validate};
+use crate::mesh::Trimesh;
+
+macro_rules! fixture {
+    ($name:literal) => {
+        (
+            $name,
+            include_bytes!(concat!("../../../../test/data/conformance/", $name)).as_slice(),
+        )
+    };
+}
+
+/// Every fixture with the format it's written in.
+fn fixtures() -> Vec<(&'static str, &'static [u8], MeshFormat)> {
+    vec![
+        (fixture!("empty_header.stl"), MeshFormat::STL),
+        (fixture!("nameless.stl"), MeshFormat::STL),
+        (fixture!("negative_indices.obj"), MeshFormat::OBJ),
+        (fixture!("list_properties.ply"), MeshFormat::PLY),
+        (fixture!("list_properties_be.ply"), MeshFormat::PLY),
+        (fixture!("counts_in_header.off"), MeshFormat::OFF),
+        (fixture!("comma.xyz"), MeshFormat::XYZ),
+    ]
+    .into_iter()
+    .map(|((name, bytes), format)| (name, bytes, format))
+    .collect()
+}
+
+fn load(name: &str) -> Trimesh {
+    let (_, bytes, format) = fixtures()
+        .into_iter()
+        .find(|f| f.0 == name)
+        .expect("fixture should exist");
+    load_mesh(bytes, format).unwrap()
+}
+
+fn points(values: &[[f64; 3]]) -> Vec<Point3<f64>> {
+    values.iter().map(|v| Point3::from(*v)).collect()
+}
+
+#[test]
+fn test_conformance_detect() {
+    for (name, bytes, format) in fixtures() {
+        assert_eq!(MeshFormat::detect(bytes).unwrap(), format, "{name}");
+        // every way of loading a file produces the same mesh
+        let mesh = load_mesh(bytes, format.clone()).unwrap();
+        for other in [
+            load_mesh_auto(bytes).unwrap(),
+            load_mesh_reader(bytes, format).unwrap(),
+        ] {
+            assert_eq!(other.vertices, mesh.vertices, "{name}");
+            assert_eq!(other.faces, mesh.faces, "{name}");
+        }
+    }
+}
+
+#[test]
+fn test_conformance_stl() {
+    // a binary header of zeros isn't a name and nothing is merged
+    let mesh = load("empty_header.stl");
+    assert_eq!(
+        mesh.vertices,
+        points(&[
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+        ])
+    );
+    assert_eq!(mesh.faces, vec![(0, 1, 2), (3, 4, 5)]);
+    assert_eq!(mesh.source.format, Some(MeshFormat::STL));
+    assert_eq!(mesh.source.header, None);
+
+    // an ASCII solid without a name
+    let mesh = load("nameless.stl");
+    assert_eq!(
+        mesh.vertices,
+        points(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]])
+    );
+    assert_eq!(mesh.faces, vec![(0, 1, 2)]);
+}
+
+#[test]
+fn test_conformance_obj() {
+    // negative indices count back from the last vertex read before the
+    // face, and a face with the invalid index zero is skipped
+    let mesh = load("negative_indices.obj");
+    let triangles: Vec<Vec<Point3<f64>>> = mesh
+        .faces
+        .iter()
+        .map(|f| vec![mesh.vertices[f.0], mesh.vertices[f.1], mesh.vertices[f.2]])
+        .collect();
+    assert_eq!(
+        triangles,
+        vec![
+            points(&[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]]),
+            points(&[[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 0.0, 1.0]]),
+        ]
+    );
+    // corners with and without a UV are separate vertices
+    assert_eq!(mesh.vertices.len(), 6);
+    assert_eq!(mesh.uv().unwrap()[2], Vector2::new(1.0, 1.0));
+    assert!(mesh.attributes_face.groupings.is_empty());
+}
+
+#[test]
+fn test_conformance_ply() {
+    // the quad is triangulated and the list of texture coordinates
+    // on each face and the edge element are read past and ignored
+    let mesh = load("list_properties.ply");
+    assert_eq!(
+        mesh.vertices,
+        points(&[
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.5, 0.5, 1.0],
+        ])
+    );
+    assert_eq!(mesh.faces.len(), 3);
+    assert_eq!(mesh.faces[2], (0, 1, 4));
+    assert!(
+        mesh.faces[..2]
+            .iter()
+            .all(|f| f.0 < 4 && f.1 < 4 && f.2 < 4)
+    );
+    assert_relative_eq!(mesh.area(), 1.0 + 1.25_f64.sqrt() / 2.0, epsilon = 1e-12);
+    assert_eq!(
+        mesh.attributes_vertex.colors,
+        vec![vec![
+            Vector4::new(255, 0, 0, 255),
+            Vector4::new(0, 255, 0, 255),
+            Vector4::new(0, 0, 255, 255),
+            Vector4::new(255, 255, 255, 255),
+            Vector4::new(0, 0, 0, 255),
+        ]]
+    );
+    // each triangle takes the color of its polygon
+    assert_eq!(
+        mesh.attributes_face.colors,
+        vec![vec![
+            Vector4::new(10, 20, 30, 255),
+            Vector4::new(10, 20, 30, 255),
+            Vector4::new(40, 50, 60, 255),
+        ]]
+    );
+    assert!(mesh.attributes_vertex.normals.is_empty());
+    assert!(mesh.attributes_vertex.uv.is_empty());
+    assert_eq!(mesh.source.format, Some(MeshFormat::PLY));
+    assert!(mesh.source.header.as_ref().unwrap().ends_with("end_header"));
+
+    // the same file in big endian binary loads identically
+    let binary = load("list_properties_be.ply");
+    assert_eq!(binary.vertices, mesh.vertices);
+    assert_eq!(binary.faces, mesh.faces);
+    assert_eq!(
+        binary.attributes_vertex.colors,
+        mesh.attributes_vertex.colors
+    );
+    assert_eq!(binary.attributes_face.colors, mesh.attributes_face.colors);
+
+    // a body shorter than the header declares is an error
+    let (_, bytes) = fixture!("list_properties_be.ply");
+    assert!(load_mesh(&bytes[..bytes.len() - 1], MeshFormat::PLY).is_err());
+    assert!(load_mesh_reader(&bytes[..bytes.len() - 1], MeshFormat::PLY).is_err());
+    // including a count far too large to allocate for
+    let huge = "ply\nformat ascii 1.0\nelement vertex 1000000000000000000\n\
+                property float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n";
+    assert!(load_mesh(huge.as_bytes(), MeshFormat::PLY).is_err());
+    assert!(load_mesh_reader(huge.as_bytes(), MeshFormat::PLY).is_err());
+    let empty = "ply\nformat ascii 1.0\nelement nothing 1000000000000000000\nend_header\n";
+    assert!(load_mesh(empty.as_bytes(), MeshFormat::PLY).is_ok());
+
+    // negative and fractional vertex indices are errors rather than zero
+    for index in ["-1", "0.5", "nan"] {
+        let face = format!(
+            "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+             property float z\nelement face 1\nproperty list uchar int vertex_indices\n\
+             end_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 {index}\n"
+        );
+        assert!(
+            load_mesh(face.as_bytes(), MeshFormat::PLY).is_err(),
+            "{index}"
+        );
+        assert!(
+            !validate(face.as_bytes(), &MeshFormat::PLY).is_valid(),
+            "{index}"
+        );
+    }
+}
+
+#[test]
+fn test_conformance_off() {
+    // the counts run into the keyword and only the quad has a color
+    let mesh = load("counts_in_header.off");
+    assert_eq!(mesh.vertices.len(), 4);
+    assert_eq!(mesh.faces.len(), 3);
+    assert_eq!(mesh.faces[2], (0, 2, 3));
+    assert_relative_eq!(mesh.area(), 1.5, epsilon = 1e-12);
+    let red = Vector4::new(255, 0, 0, 255);
+    assert_eq!(
+        mesh.attributes_face.colors,
+        vec![vec![red, red, DEFAULT_COLOR]]
+    );
+}
+
+#[test]
+fn test_conformance_xyz() {
+    // comma separated columns with float normals
+    let mesh = load("comma.xyz");
+    assert_eq!(mesh.vertices, points(&[[0.0, 0.0, 0.0], [1.5, 2.0, 3.0]]));
+    assert!(mesh.faces.is_empty());
+    assert_eq!(
+        mesh.attributes_vertex.normals,
+        vec![vec![
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 1.0, 0.0)
+        ]]
+    );
+    assert!(mesh.attributes_vertex.colors.is_empty());
+}
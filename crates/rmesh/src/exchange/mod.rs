@@ -1,12 +1,51 @@
+mod batch;
+mod mtl;
 mod obj;
+mod off;
+mod packed;
+mod ply;
 mod stl;
+mod stream;
+mod threemf;
+mod validate;
+mod xyz;
+
+#[cfg(test)]
+mod conformance;
+
+use std::io::{BufReader, Read};
+use std::path::Path;
 
 use anyhow::Result;
 
+use crate::error::RmeshError;
+use crate::geometry::Geometry;
 use crate::mesh::Trimesh;
+use crate::mesh32::Trimesh32;
+use crate::process::quantize;
+use crate::scene::{Scene, SceneNode, SceneNodeKind};
 
 use crate::exchange::obj::ObjMesh;
-use crate::exchange::stl::BinaryStl;
+use crate::exchange::off::OffMesh;
+use crate::exchange::ply::PlyMesh;
+use crate::exchange::threemf::ThreeMf;
+use crate::exchange::xyz::XyzPoints;
+
+pub use crate::exchange::batch::{
+    BatchReport, ConvertOptions, LoadStats, batch_convert, load_many, load_many_timed,
+};
+pub use crate::exchange::packed::{Compression, PackOptions, pack_mesh, unpack_mesh};
+pub use crate::exchange::ply::{
+    PlyEncoding, PlyExport, PlyProperty, Scalar as PlyScalar, export as export_ply,
+};
+pub use crate::exchange::stl::{
+    ATTRIBUTE_GROUPING as STL_ATTRIBUTE_GROUPING, BinaryStl, SOLID_GROUPING as STL_SOLID_GROUPING,
+    StlColors, export_binary as export_stl_binary,
+};
+pub use crate::exchange::stream::MeshStream;
+pub use crate::exchange::validate::{
+    FormatValidator, Severity, ValidationIssue, ValidationReport, load_mesh_strict, validate,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 // An enum to represent the different mesh file formats.
@@ -17,11 +56,17 @@ pub enum MeshFormat {
     OBJ,
     // the PLY format is a binary format with an ASCII header
     PLY,
+    // the 3MF format is a zip container of XML with multiple objects
+    THREEMF,
+    // the OFF format is an ASCII format of polygons common in academic datasets
+    OFF,
+    // the XYZ format is an ASCII point cloud with one point per line
+    XYZ,
 }
 
 impl MeshFormat {
     /// Convert a string to a MeshFormat enum.
-    pub fn from_string(s: &str) -> Result<Self> {
+    pub fn from_string(s: &str) -> Result<Self, RmeshError> {
         // clean up to match 'stl', '.stl', ' .STL ', etc
         let binding = s.to_ascii_lowercase();
         let clean = binding.trim().trim_start_matches('.').trim();
@@ -29,19 +74,437 @@ impl MeshFormat {
             "stl" => Ok(MeshFormat::STL),
             "obj" => Ok(MeshFormat::OBJ),
             "ply" => Ok(MeshFormat::PLY),
-            _ => Err(anyhow::anyhow!("Unsupported file type: `{}`", clean)),
+            "3mf" => Ok(MeshFormat::THREEMF),
+            "off" => Ok(MeshFormat::OFF),
+            "xyz" => Ok(MeshFormat::XYZ),
+            _ => Err(RmeshError::UnsupportedFormat(format!(
+                "unknown file type `{clean}`"
+            ))),
+        }
+    }
+
+    /// Can the format reference texture images through its materials.
+    pub fn supports_textures(&self) -> bool {
+        matches!(self, MeshFormat::OBJ | MeshFormat::THREEMF)
+    }
+
+    /// The conventional file extension for the format without a period.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MeshFormat::STL => "stl",
+            MeshFormat::OBJ => "obj",
+            MeshFormat::PLY => "ply",
+            MeshFormat::THREEMF => "3mf",
+            MeshFormat::OFF => "off",
+            MeshFormat::XYZ => "xyz",
+        }
+    }
+
+    /// Detect the format of a file from its contents by checking
+    /// magic bytes and headers, falling back to heuristics for the
+    /// ASCII formats which have no header.
+    ///
+    /// Parameters
+    /// ------------
+    /// bytes
+    ///   Raw bytes of the file, of which only the start is
+    ///   examined except for the binary STL size check.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<MeshFormat>
+    ///   The detected format or an error if it couldn't be determined.
+    pub fn detect(bytes: &[u8]) -> Result<Self, RmeshError> {
+        // the zip container used by 3MF
+        if bytes.starts_with(b"PK\x03\x04") {
+            return Ok(MeshFormat::THREEMF);
+        }
+        if bytes.starts_with(b"glTF") {
+            return Err(RmeshError::UnsupportedFormat(
+                "GLB files are not supported".to_string(),
+            ));
         }
+        // binary STL files often start with `solid` so check the size first
+        if bytes.len() >= 84 {
+            let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+            // the size can overflow where usize is 32 bits like WASM
+            if count.checked_mul(50).and_then(|n| n.checked_add(84)) == Some(bytes.len()) {
+                return Ok(MeshFormat::STL);
+            }
+        }
+
+        // everything else is text, so only look at the start of the file
+        let text = String::from_utf8_lossy(&bytes[..bytes.len().min(DETECT_LENGTH)]);
+        let start = text.trim_start();
+        if start.starts_with("solid") {
+            return Ok(MeshFormat::STL);
+        }
+        if start.starts_with("ply") {
+            return Ok(MeshFormat::PLY);
+        }
+        let keyword: String = start
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect();
+        if keyword.ends_with("OFF") {
+            return Ok(MeshFormat::OFF);
+        }
+
+        // drop the last line as it may have been truncated
+        let mut lines: Vec<&str> = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .collect();
+        if bytes.len() > DETECT_LENGTH {
+            lines.pop();
+        }
+        let lines: Vec<&str> = lines.into_iter().filter(|l| !l.is_empty()).collect();
+
+        // OBJ files are made of lines that start with a keyword
+        if lines.iter().any(|line| {
+            matches!(
+                line.split_whitespace().next(),
+                Some("v" | "vn" | "vt" | "f" | "o" | "g" | "mtllib" | "usemtl")
+            )
+        }) {
+            return Ok(MeshFormat::OBJ);
+        }
+
+        // XYZ files are rows of at least 3 numbers
+        if !lines.is_empty()
+            && lines.iter().all(|line| {
+                let values: Vec<&str> = line
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|v| !v.is_empty())
+                    .collect();
+                values.len() >= 3 && values.iter().all(|v| v.parse::<f64>().is_ok())
+            })
+        {
+            return Ok(MeshFormat::XYZ);
+        }
+
+        Err(RmeshError::UnsupportedFormat(
+            "unable to detect the file format".to_string(),
+        ))
     }
 }
 
-pub fn load_mesh(file_data: &[u8], file_type: MeshFormat) -> Result<Trimesh> {
+// how many bytes from the start of a file to check for text formats
+const DETECT_LENGTH: usize = 4096;
+
+/// Options for how the exporters write a mesh, which mostly
+/// trade file size against fidelity.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportOptions {
+    // the number of digits after the decimal point with trailing zeros
+    // removed, or `None` for the shortest text that round-trips exactly
+    pub digits: Option<usize>,
+
+    // snap vertex positions to a grid with this spacing before writing
+    pub quantize: Option<f64>,
+
+    // for formats without textures bake them into vertex colors, first
+    // subdividing so no edge is longer than this or not at all if infinite
+    pub bake_textures: Option<f64>,
+}
+
+impl ExportOptions {
+    /// Write a vertex coordinate, quantizing it if requested.
+    pub fn write_coordinate(&self, value: f64, out: &mut String) {
+        let value = match self.quantize {
+            Some(step) if step > 0.0 => quantize(value, step),
+            _ => value,
+        };
+        self.write_float(value, out);
+    }
+
+    /// Write a float like a normal or UV coordinate with the configured precision.
+    pub fn write_float(&self, value: f64, out: &mut String) {
+        // values that aren't finite are written the way the loaders parse
+        // them rather than as a number which would silently move a vertex
+        if !value.is_finite() {
+            out.push_str(if value.is_nan() {
+                "nan"
+            } else if value > 0.0 {
+                "inf"
+            } else {
+                "-inf"
+            });
+            return;
+        }
+        // don't write negative zero
+        let value = if value == 0.0 { 0.0 } else { value };
+        match self.digits {
+            Some(digits) => {
+                let start = out.len();
+                out.push_str(&format!("{value:.digits$}"));
+                if out[start..].contains('.') {
+                    let trimmed = out.trim_end_matches('0').trim_end_matches('.').len();
+                    out.truncate(trimmed);
+                }
+                if &out[start..] == "-0" {
+                    out.replace_range(start.., "0");
+                }
+            }
+            None => out.push_str(ryu::Buffer::new().format_finite(value)),
+        }
+    }
+}
+
+/// Export a mesh to the text of an ASCII file format.
+///
+/// Parameters
+/// ------------
+/// mesh
+///   The mesh to export.
+/// file_type
+///   The format to export, currently STL, OBJ or PLY.
+/// options
+///   How numbers should be formatted and whether to bake textures.
+///
+/// Returns
+/// ------------
+/// Result<String, RmeshError>
+///   The text of the file, or an `UnsupportedFormat` error for a
+///   format which can't be exported.
+pub fn export_mesh(
+    mesh: &Trimesh,
+    file_type: MeshFormat,
+    options: &ExportOptions,
+) -> Result<String, RmeshError> {
+    let baked;
+    let mesh = match options.bake_textures {
+        Some(max_edge) if !file_type.supports_textures() && mesh.has_textures() => {
+            baked = mesh.bake_textures(max_edge)?;
+            &baked
+        }
+        _ => mesh,
+    };
     match file_type {
+        MeshFormat::STL => Ok(stl::export_ascii(mesh, options)),
+        MeshFormat::OBJ => Ok(obj::export(mesh, options)),
+        MeshFormat::PLY => Ok(ply::export_ascii(mesh, options)),
+        _ => Err(RmeshError::UnsupportedFormat(format!(
+            "exporting {} files isn't supported",
+            file_type.extension()
+        ))),
+    }
+}
+
+/// A callback which returns the bytes of a file referenced by name
+/// from inside another file, such as the MTL library and textures of
+/// an OBJ, or `None` if it isn't available. This lets callers without
+/// a filesystem like WASM supply the referenced files themselves.
+pub type Resolver<'a> = dyn Fn(&str) -> Option<Vec<u8>> + 'a;
+
+/// A resolver which loads files relative to a directory.
+fn path_resolver(root: &Path) -> impl Fn(&str) -> Option<Vec<u8>> + '_ {
+    move |name| std::fs::read(root.join(name)).ok()
+}
+
+pub fn load_mesh(file_data: &[u8], file_type: MeshFormat) -> Result<Trimesh, RmeshError> {
+    load_mesh_resolved(file_data, file_type, &|_| None)
+}
+
+/// Load a mesh using a resolver for any files it references.
+///
+/// Parameters
+/// ------------
+/// file_data
+///   Raw bytes of the file.
+/// file_type
+///   The format of the file.
+/// resolver
+///   Returns the bytes of a referenced file by name.
+///
+/// Returns
+/// ------------
+/// Result<Trimesh, RmeshError>
+///   The loaded mesh, or an error which is a `Parse` error
+///   unless the file couldn't be read or its geometry is invalid.
+pub fn load_mesh_resolved(
+    file_data: &[u8],
+    file_type: MeshFormat,
+    resolver: &Resolver,
+) -> Result<Trimesh, RmeshError> {
+    let load = || match file_type {
         MeshFormat::STL => BinaryStl::from_bytes(file_data)?.to_mesh(),
-        MeshFormat::OBJ => ObjMesh::from_string(&String::from_utf8_lossy(file_data))?.into_mesh(),
-        MeshFormat::PLY => todo!(),
+        MeshFormat::OBJ => {
+            ObjMesh::from_string(&String::from_utf8_lossy(file_data), resolver)?.into_mesh()
+        }
+        MeshFormat::PLY => PlyMesh::from_bytes(file_data)?.into_mesh(),
+        MeshFormat::THREEMF => ThreeMf::from_bytes(file_data)?.to_mesh(),
+        MeshFormat::OFF => OffMesh::from_string(&String::from_utf8_lossy(file_data))?.into_mesh(),
+        MeshFormat::XYZ => XyzPoints::from_string(&String::from_utf8_lossy(file_data))?.into_mesh(),
+    };
+    load().map_err(|e| RmeshError::from_load(&file_type, e))
+}
+
+/// Load a mesh in single precision to halve the memory it uses. An STL
+/// is read straight into single precision as that's how it's stored,
+/// while other formats are loaded as a `Trimesh` and converted.
+///
+/// Parameters
+/// ------------
+/// file_data
+///   Raw bytes of the file.
+/// file_type
+///   The format of the file.
+///
+/// Returns
+/// ------------
+/// Result<Trimesh32, RmeshError>
+///   The loaded mesh or an error as for `load_mesh`.
+pub fn load_mesh32(file_data: &[u8], file_type: MeshFormat) -> Result<Trimesh32, RmeshError> {
+    match file_type {
+        MeshFormat::STL => BinaryStl::from_bytes(file_data)
+            .map(|stl| stl.to_mesh32())
+            .map_err(|e| RmeshError::from_load(&file_type, e)),
+        _ => Ok(load_mesh(file_data, file_type)?.to_f32()?),
+    }
+}
+
+/// Load a mesh from a file, detecting the format from its contents.
+pub fn load_mesh_auto(file_data: &[u8]) -> Result<Trimesh, RmeshError> {
+    load_mesh(file_data, MeshFormat::detect(file_data)?)
+}
+
+/// Load a mesh from a stream, parsing the formats that support it
+/// incrementally so the raw file never has to be held in memory, which
+/// matters for multi-gigabyte STL and PLY files. STL, OBJ and PLY are
+/// parsed as they are read, while other formats like 3MF are read to
+/// the end and passed to `load_mesh`.
+///
+/// Parameters
+/// ------------
+/// reader
+///   The source of the file data, which will be buffered internally.
+/// file_type
+///   The format of the file.
+///
+/// Returns
+/// ------------
+/// Result<Trimesh, RmeshError>
+///   The loaded mesh or an error.
+pub fn load_mesh_reader<R: Read>(reader: R, file_type: MeshFormat) -> Result<Trimesh, RmeshError> {
+    read_mesh(reader, file_type, &|_| None)
+}
+
+fn read_mesh<R: Read>(
+    reader: R,
+    file_type: MeshFormat,
+    resolver: &Resolver,
+) -> Result<Trimesh, RmeshError> {
+    match file_type {
+        MeshFormat::STL => {
+            BinaryStl::mesh_from_reader(reader).map_err(|e| RmeshError::from_load(&file_type, e))
+        }
+        MeshFormat::OBJ => ObjMesh::from_reader(BufReader::new(reader), resolver)
+            .and_then(|obj| obj.into_mesh())
+            .map_err(|e| RmeshError::from_load(&file_type, e)),
+        MeshFormat::PLY => PlyMesh::from_reader(BufReader::new(reader))
+            .and_then(|ply| ply.into_mesh())
+            .map_err(|e| RmeshError::from_load(&file_type, e)),
+        _ => {
+            let mut file_data = Vec::new();
+            BufReader::new(reader).read_to_end(&mut file_data)?;
+            load_mesh_resolved(&file_data, file_type, resolver)
+        }
+    }
+}
+
+/// Load a mesh from a file on disk, using the extension for the format
+/// if it is recognized and detecting it from the contents otherwise.
+/// Referenced files like OBJ materials are loaded relative to the file.
+pub fn load_mesh_path(path: impl AsRef<Path>) -> Result<Trimesh, RmeshError> {
+    let path = path.as_ref();
+    let format = path
+        .extension()
+        .and_then(|e| MeshFormat::from_string(&e.to_string_lossy()).ok());
+    let resolver = path_resolver(path.parent().unwrap_or(Path::new(".")));
+    match format {
+        Some(format) => read_mesh(std::fs::File::open(path)?, format, &resolver),
+        None => {
+            let file_data = std::fs::read(path)?;
+            load_mesh_resolved(&file_data, MeshFormat::detect(&file_data)?, &resolver)
+        }
     }
 }
 
+/// Load a file into a scene, preserving multiple objects and their
+/// transforms for formats that support them. Every solid of an STL and
+/// every object of an OBJ, or every group if it has no objects, is a
+/// separate geometry under a node with its name. Files which only
+/// contain a single mesh produce a scene with a single node.
+///
+/// Parameters
+/// ------------
+/// file_data
+///   Raw bytes of the file.
+/// file_type
+///   The format of the file.
+///
+/// Returns
+/// ------------
+/// Result<Scene, RmeshError>
+///   The loaded scene or an error as for `load_mesh`.
+pub fn load_scene(file_data: &[u8], file_type: MeshFormat) -> Result<Scene, RmeshError> {
+    let load = || match file_type {
+        MeshFormat::THREEMF => ThreeMf::from_bytes(file_data)?.to_scene(),
+        MeshFormat::STL => Ok(scene_from_bodies(BinaryStl::solids_from_bytes(file_data)?)),
+        MeshFormat::OBJ => Ok(scene_from_bodies(
+            ObjMesh::from_string(&String::from_utf8_lossy(file_data), &|_| None)?.into_bodies()?,
+        )),
+        _ => Ok(scene_from_bodies(vec![(
+            String::new(),
+            load_mesh(file_data, file_type.clone())?,
+        )])),
+    };
+    load().map_err(|e| RmeshError::from_load(&file_type, e))
+}
+
+/// A scene with a node for each of several named meshes under a root,
+/// or with the only mesh on the root itself.
+fn scene_from_bodies(bodies: Vec<(String, Trimesh)>) -> Scene {
+    let mut scene = Scene::new();
+    if bodies.len() == 1 {
+        let (_, mesh) = bodies.into_iter().next().unwrap();
+        let index = scene.add_geometry(Geometry::Mesh(Box::new(mesh)));
+        let root = scene.graph.add_node(SceneNode {
+            name: "world".to_string(),
+            kind: SceneNodeKind::GEOMETRY,
+            index: vec![index],
+            ..Default::default()
+        });
+        scene.graph.set_root(root);
+        return scene;
+    }
+
+    let root = scene.graph.add_node(SceneNode {
+        name: "world".to_string(),
+        kind: SceneNodeKind::CUSTOM,
+        ..Default::default()
+    });
+    scene.graph.set_root(root);
+    for (position, (name, mesh)) in bodies.into_iter().enumerate() {
+        let index = scene.add_geometry(Geometry::Mesh(Box::new(mesh)));
+        let node = scene.graph.add_node(SceneNode {
+            name: if name.is_empty() {
+                format!("geometry_{position}")
+            } else {
+                name
+            },
+            kind: SceneNodeKind::GEOMETRY,
+            index: vec![index],
+            ..Default::default()
+        });
+        // the root was just added so the edge can't make a cycle
+        let root = scene.graph.root();
+        scene.graph.add_edge(root, node).unwrap();
+    }
+    scene
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -62,7 +525,238 @@ mod tests {
         assert_eq!(MeshFormat::from_string(".ply").unwrap(), MeshFormat::PLY);
         assert_eq!(MeshFormat::from_string(".PLY").unwrap(), MeshFormat::PLY);
         assert_eq!(MeshFormat::from_string("  .pLy ").unwrap(), MeshFormat::PLY);
+        assert_eq!(MeshFormat::from_string("3MF").unwrap(), MeshFormat::THREEMF);
+        assert_eq!(
+            MeshFormat::from_string(".3mf").unwrap(),
+            MeshFormat::THREEMF
+        );
 
         assert!(MeshFormat::from_string("foo").is_err());
     }
+
+    #[test]
+    fn test_mesh_format_detect() {
+        let cases: Vec<(&[u8], MeshFormat)> = vec![
+            (
+                include_bytes!("../../../../test/data/unit_cube.STL"),
+                MeshFormat::STL,
+            ),
+            (
+                include_bytes!("../../../../test/data/two_objects_mixed_case_names.stl"),
+                MeshFormat::STL,
+            ),
+            (
+                include_bytes!("../../../../test/data/basic.obj"),
+                MeshFormat::OBJ,
+            ),
+            (
+                include_bytes!("../../../../test/data/fuze.obj"),
+                MeshFormat::OBJ,
+            ),
+            (
+                include_bytes!("../../../../test/data/multi_object.3mf"),
+                MeshFormat::THREEMF,
+            ),
+            (
+                b"OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n",
+                MeshFormat::OFF,
+            ),
+            (b"ply\nformat ascii 1.0\n", MeshFormat::PLY),
+            (b"0 0 0\n1.0, 2.0, 3.0\n", MeshFormat::XYZ),
+        ];
+        for (data, expected) in cases {
+            let detected = MeshFormat::detect(data).unwrap();
+            assert_eq!(detected, expected);
+            // the extension should round trip through the string parser
+            assert_eq!(
+                MeshFormat::from_string(detected.extension()).unwrap(),
+                expected
+            );
+        }
+
+        // a binary STL whose header starts with `solid` is still binary
+        let mut binary = include_bytes!("../../../../test/data/unit_cube.STL").to_vec();
+        binary[..5].copy_from_slice(b"solid");
+        assert_eq!(MeshFormat::detect(&binary).unwrap(), MeshFormat::STL);
+        assert_eq!(load_mesh_auto(&binary).unwrap().faces.len(), 12);
+
+        assert!(MeshFormat::detect(b"glTF\x02\x00\x00\x00").is_err());
+        assert!(MeshFormat::detect(b"hello there").is_err());
+        assert!(MeshFormat::detect(b"").is_err());
+    }
+
+    #[test]
+    fn test_load_mesh_path() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test/data");
+        for (name, faces) in [
+            ("unit_cube.STL", 12),
+            ("two_objects_mixed_case_names.stl", 24),
+            ("multi_object.3mf", 28),
+        ] {
+            let mesh = load_mesh_path(root.join(name)).unwrap();
+            assert_eq!(mesh.faces.len(), faces, "{name}");
+        }
+
+        // the reader should match loading from memory exactly
+        let data = include_bytes!("../../../../test/data/fuze.obj");
+        let expected = load_mesh(data, MeshFormat::OBJ).unwrap();
+        let mesh = load_mesh_reader(data.as_slice(), MeshFormat::OBJ).unwrap();
+        assert_eq!(mesh.vertices, expected.vertices);
+        assert_eq!(mesh.faces, expected.faces);
+        assert_eq!(mesh.uv(), expected.uv());
+
+        let off = b"OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2\n";
+        let mesh = load_mesh_reader(off.as_slice(), MeshFormat::OFF).unwrap();
+        assert_eq!(mesh.faces.len(), 1);
+
+        // the material library should be loaded from next to the OBJ
+        let mesh = load_mesh_path(root.join("fuze.obj")).unwrap();
+        assert_eq!(mesh.materials.len(), 1);
+
+        assert!(load_mesh_path(root.join("does_not_exist.stl")).is_err());
+    }
+
+    #[test]
+    fn test_export_options() {
+        let write = |options: &ExportOptions, value: f64| {
+            let mut out = String::new();
+            options.write_coordinate(value, &mut out);
+            out
+        };
+        let shortest = ExportOptions::default();
+        assert_eq!(write(&shortest, 0.1), "0.1");
+        assert_eq!(write(&shortest, 1.0 / 3.0), "0.3333333333333333");
+        assert_eq!(write(&shortest, -0.0), "0.0");
+        // values that aren't finite are written so they parse back
+        assert_eq!(write(&shortest, f64::NAN), "nan");
+        assert_eq!(write(&shortest, f64::INFINITY), "inf");
+        assert_eq!(write(&shortest, f64::NEG_INFINITY), "-inf");
+        assert!(write(&shortest, f64::NAN).parse::<f64>().unwrap().is_nan());
+
+        let digits = ExportOptions {
+            digits: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(write(&digits, 1.0 / 3.0), "0.333");
+        assert_eq!(write(&digits, 2.0), "2");
+        assert_eq!(write(&digits, 2.5), "2.5");
+        assert_eq!(write(&digits, -0.0001), "0");
+        assert_eq!(write(&digits, f64::NAN), "nan");
+
+        let quantized = ExportOptions {
+            quantize: Some(0.001),
+            ..Default::default()
+        };
+        assert_eq!(write(&quantized, 0.30004), "0.3");
+        assert_eq!(write(&quantized, 1.23456), "1.235");
+        let quantized = ExportOptions {
+            quantize: Some(0.25),
+            ..Default::default()
+        };
+        assert_eq!(write(&quantized, 0.3), "0.25");
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let data = include_bytes!("../../../../test/data/unit_cube.STL");
+        let mesh = load_mesh(data, MeshFormat::STL)
+            .unwrap()
+            .process(false)
+            .unwrap();
+
+        for format in [MeshFormat::STL, MeshFormat::OBJ, MeshFormat::PLY] {
+            let text = export_mesh(&mesh, format.clone(), &ExportOptions::default()).unwrap();
+            assert_eq!(MeshFormat::detect(text.as_bytes()).unwrap(), format);
+            let loaded = load_mesh(text.as_bytes(), format.clone()).unwrap();
+            assert_eq!(loaded.faces.len(), mesh.faces.len());
+            // shortest round-trip formatting should be exact
+            let soup: Vec<_> = mesh.faces.iter().flat_map(|f| [f.0, f.1, f.2]).collect();
+            let loaded_soup: Vec<_> = loaded.faces.iter().flat_map(|f| [f.0, f.1, f.2]).collect();
+            for (a, b) in soup.iter().zip(loaded_soup.iter()) {
+                assert_eq!(mesh.vertices[*a], loaded.vertices[*b]);
+            }
+        }
+
+        // fewer digits should make a smaller file
+        let full = export_mesh(&mesh, MeshFormat::OBJ, &ExportOptions::default()).unwrap();
+        let short = export_mesh(
+            &mesh,
+            MeshFormat::OBJ,
+            &ExportOptions {
+                digits: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(short.len() <= full.len());
+        assert!(export_mesh(&mesh, MeshFormat::THREEMF, &ExportOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_load_scene_bodies() {
+        let data = include_bytes!("../../../../test/data/two_objects_mixed_case_names.stl");
+        let scene = load_scene(data, MeshFormat::STL).unwrap();
+        assert_eq!(scene.geometry.len(), 2);
+        let names: Vec<&str> = scene
+            .graph
+            .nodes()
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "world",
+                "CubeExportedFromCAD",
+                "TranslatedCubeExportedFromCAD"
+            ]
+        );
+        assert_eq!(scene.flatten().len(), 2);
+        // the solids together are the merged mesh
+        let merged = load_mesh(data, MeshFormat::STL).unwrap();
+        let faces: usize = scene
+            .geometry
+            .iter()
+            .map(|g| match g {
+                Geometry::Mesh(mesh) => mesh.faces.len(),
+                _ => 0,
+            })
+            .sum();
+        assert_eq!(faces, merged.faces.len());
+
+        // objects split an OBJ and win over groups
+        let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\n\
+                   o first\ng a\nf 1 2 3\ng b\nf 1 2 4\n\
+                   o second\nf 1 3 4\nf 2 3 4\n";
+        let scene = load_scene(obj.as_bytes(), MeshFormat::OBJ).unwrap();
+        let names: Vec<&str> = scene
+            .graph
+            .nodes()
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["world", "first", "second"]);
+        for geometry in scene.geometry.iter() {
+            let Geometry::Mesh(mesh) = geometry else {
+                panic!("Expected a mesh");
+            };
+            assert_eq!((mesh.faces.len(), mesh.vertices.len()), (2, 4));
+        }
+
+        // groups split an OBJ without objects and a file with
+        // neither is a single mesh on the root
+        let grouped = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 2 3\ng top\nf 1 2 4\n";
+        let scene = load_scene(grouped.as_bytes(), MeshFormat::OBJ).unwrap();
+        let names: Vec<&str> = scene
+            .graph
+            .nodes()
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["world", "geometry_0", "top"]);
+        let plain = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+        let scene = load_scene(plain.as_bytes(), MeshFormat::OBJ).unwrap();
+        assert_eq!(scene.graph.nodes().len(), 1);
+        assert_eq!(scene.geometry.len(), 1);
+    }
 }
@@ -0,0 +1,112 @@
+use nalgebra::Vector3;
+
+use crate::attributes::SimpleMaterial;
+use crate::exchange::Resolver;
+
+/// Parse the text of an MTL material library into materials, using
+/// the resolver to fetch any texture images it references. Textures
+/// which can't be resolved or decoded are skipped rather than failing
+/// the whole load, as a missing texture is very common in the wild.
+///
+/// Parameters
+/// ------------
+/// data
+///   The text of the MTL file.
+/// resolver
+///   Returns the bytes of a file referenced by name.
+///
+/// Returns
+/// ------------
+/// materials
+///   Every material defined by a `newmtl` line in order.
+pub fn parse_mtl(data: &str, resolver: &Resolver) -> Vec<SimpleMaterial> {
+    let mut materials: Vec<SimpleMaterial> = Vec::new();
+    for line in data.lines() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let Some((keyword, rest)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let rest = rest.trim();
+        if keyword == "newmtl" {
+            materials.push(SimpleMaterial::new(rest));
+            continue;
+        }
+        // everything else modifies the most recent material
+        let Some(material) = materials.last_mut() else {
+            continue;
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_color(rest),
+            "Ks" => material.specular = parse_color(rest),
+            "Ns" => material.shininess = rest.parse().ok(),
+            "d" => material.alpha = rest.parse().ok(),
+            "map_Kd" => {
+                material.image = resolver(texture_name(rest))
+                    .and_then(|bytes| image::load_from_memory(&bytes).ok());
+            }
+            _ => {}
+        }
+    }
+    materials
+}
+
+/// Parse an RGB color from an MTL line like `Kd 1.0 0.5 0.5`.
+fn parse_color(raw: &str) -> Option<Vector3<f64>> {
+    let values = raw
+        .split_whitespace()
+        .map(|v| v.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    match values.as_slice() {
+        [r, g, b, ..] => Some(Vector3::new(*r, *g, *b)),
+        // a single value is a gray
+        [v] => Some(Vector3::repeat(*v)),
+        _ => None,
+    }
+}
+
+/// Get the file name from a texture map line. File names may contain
+/// spaces, so only split on whitespace if there are options like
+/// `-s 1 1 1` before the name in which case it's the last value.
+fn texture_name(raw: &str) -> &str {
+    if raw.starts_with('-') {
+        raw.split_whitespace().last().unwrap_or_default()
+    } else {
+        raw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_parse_mtl() {
+        let data = "# two materials
+newmtl red
+Kd 1.0 0.0 0.0
+Ks 0.5 0.5 0.5
+Ns 10.0
+d 0.5
+map_Kd missing.png
+
+newmtl gray
+Kd 0.25
+";
+        let materials = parse_mtl(data, &|_| None);
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials[0].name, "red");
+        assert_eq!(materials[0].diffuse, Some(Vector3::new(1.0, 0.0, 0.0)));
+        assert_eq!(materials[0].specular, Some(Vector3::repeat(0.5)));
+        assert_eq!(materials[0].shininess, Some(10.0));
+        assert_eq!(materials[0].alpha, Some(0.5));
+        // the texture couldn't be resolved
+        assert!(materials[0].image.is_none());
+        assert_eq!(materials[1].diffuse, Some(Vector3::repeat(0.25)));
+        assert!(materials[1].alpha.is_none());
+
+        assert_eq!(texture_name("fuze uv.jpg"), "fuze uv.jpg");
+        assert_eq!(texture_name("-s 1 1 1 texture.png"), "texture.png");
+    }
+}
@@ -1,9 +1,19 @@
-use anyhow::Result;
+use std::borrow::Cow;
+use std::io::BufRead;
+
+use ahash::AHashMap;
+use anyhow::{Result, anyhow};
 use nalgebra::{Point3, Vector2, Vector3, Vector4};
 use rayon::prelude::*;
 
-use crate::attributes::{Attributes, DEFAULT_COLOR, Material};
+use crate::attributes::{
+    Attributes, DEFAULT_COLOR, Grouping, GroupingKind, Material, SimpleMaterial,
+};
 use crate::creation::{Triangulator, triangulate_fan};
+use crate::error::RmeshError;
+use crate::exchange::mtl::parse_mtl;
+use crate::exchange::validate::{FormatValidator, ValidationReport, numbers};
+use crate::exchange::{ExportOptions, MeshFormat, Resolver};
 use crate::mesh::Trimesh;
 
 /// The intermediate representation of a single line from an OBJ file,
@@ -18,8 +28,9 @@ enum ObjLine {
     Vn(Vector3<f64>),
     // A vertex UV texture coordinate
     Vt(Vector2<f64>),
-    // An OBJ face
-    F(Vec<Vec<Option<usize>>>),
+    // An OBJ face whose one-based indices are negative if they
+    // are relative to the end of the vertices read so far
+    F(Vec<Vec<Option<i64>>>),
     // A new-object command
     O(String),
     // A group command
@@ -35,9 +46,29 @@ enum ObjLine {
     Ignore(String),
 }
 
+/// Parse a number from an OBJ line.
+fn parse_float(value: &str) -> Result<f64> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("invalid number `{value}`"))
+}
+
+/// Parse one `/` separated component of a face reference, which
+/// may be empty like the texture index of `1//3`.
+fn parse_index(value: &str) -> Result<Option<i64>> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| anyhow!("invalid face index `{value}`"))
+}
+
 impl ObjLine {
-    /// Parse a single raw OBJ line into native types
-    fn from_line(line: &str) -> Self {
+    /// Parse a single raw OBJ line into native types, returning an
+    /// error if a number in a vertex or face can't be parsed.
+    fn from_line(line: &str) -> Result<Self> {
         // clean up a raw OBJ line: ignore anything after a comment then cleanly split it
         let parts: Vec<&str> = line
             .split('#')
@@ -46,25 +77,27 @@ impl ObjLine {
             .split_whitespace()
             .collect();
 
-        match parts.as_slice() {
-            ["v", x, y, z] => ObjLine::V(
-                Point3::new(x.parse().unwrap(), y.parse().unwrap(), z.parse().unwrap()),
-                None,
-            ),
+        let point = |x: &str, y: &str, z: &str| -> Result<Point3<f64>> {
+            Ok(Point3::new(
+                parse_float(x)?,
+                parse_float(y)?,
+                parse_float(z)?,
+            ))
+        };
+        Ok(match parts.as_slice() {
+            ["v", x, y, z] => ObjLine::V(point(x, y, z)?, None),
             ["v", x, y, z, color @ ..] => {
                 // they've encoded some other color data after the vertex
-                ObjLine::V(
-                    Point3::new(x.parse().unwrap(), y.parse().unwrap(), z.parse().unwrap()),
-                    str_to_rgba(color),
-                )
+                ObjLine::V(point(x, y, z)?, str_to_rgba(color))
             }
-            ["vn", x, y, z] => ObjLine::Vn(Vector3::new(
-                x.parse().unwrap(),
-                y.parse().unwrap(),
-                z.parse().unwrap(),
-            )),
+            ["vn", x, y, z, ..] => ObjLine::Vn(point(x, y, z)?.coords),
+            // the V and W coordinates are optional
+            ["vt", u] => ObjLine::Vt(Vector2::new(parse_float(u)?, 0.0)),
             ["vt", u, v, _garbage @ ..] => {
-                ObjLine::Vt(Vector2::new(u.parse().unwrap(), v.parse().unwrap()))
+                ObjLine::Vt(Vector2::new(parse_float(u)?, parse_float(v)?))
+            }
+            ["v" | "vn", ..] => {
+                return Err(anyhow!("`{}` needs 3 numbers", parts[0]));
             }
             ["o", name @ ..] => ObjLine::O(name.join(" ")),
             ["s", name @ ..] => ObjLine::S(name.join(" ")),
@@ -76,21 +109,26 @@ impl ObjLine {
                 // 1/2/3, 1//3, 1/2, 1
                 // and will return None for any missing values which can be analyzed later
                 blob.iter()
-                    .map(|f| f.split('/').map(|s| s.parse::<usize>().ok()).collect())
-                    .collect(),
+                    .map(|f| f.split('/').map(parse_index).collect())
+                    .collect::<Result<_>>()?,
             ),
 
             _ => ObjLine::Ignore(line.to_string()),
-        }
+        })
     }
 
-    fn load_materials(&self) -> Option<Vec<Material>> {
+    /// Load the materials from an `mtllib` line using the resolver
+    /// to get the contents of the MTL file and any textures.
+    fn load_materials(&self, resolver: &Resolver) -> Option<Vec<Material>> {
         match self {
-            ObjLine::MtlLib(_name) => {
-                // TODO : load the materials from the file
-                // and return them as a vector of Materials
-                // for now just return an empty vector
-                Some(vec![])
+            ObjLine::MtlLib(name) => {
+                let data = resolver(name)?;
+                Some(
+                    parse_mtl(&String::from_utf8_lossy(&data), resolver)
+                        .into_iter()
+                        .map(Material::Simple)
+                        .collect(),
+                )
             }
             _ => None,
         }
@@ -112,6 +150,20 @@ fn upsert(name: &str, values: &mut Vec<String>) -> usize {
     }
 }
 
+/// Resolve a one-based OBJ index, or a negative one relative to the
+/// end of the values read so far, to a zero-based index which is
+/// `None` if it's missing or out of range.
+fn resolve_index(index: Option<i64>, count: usize) -> Option<usize> {
+    let index = match index? {
+        index if index < 0 => count.checked_sub(index.unsigned_abs() as usize)?,
+        index => (index as usize).checked_sub(1)?,
+    };
+    (index < count).then_some(index)
+}
+
+// the indexes of the position, UV and normal used by a corner of a face
+type Corner = (usize, Option<usize>, Option<usize>);
+
 // keep a bunch of mutable arrays as we go
 #[derive(Default, Clone)]
 struct ObjVertices {
@@ -132,43 +184,112 @@ struct ObjVertices {
 }
 
 impl ObjVertices {
-    /// Convert the vertex data into a vector of attributes
+    /// Convert the vertex data into vertices, faces and vertex attributes
     /// for the Trimesh.
-    pub fn to_attributes(&self) -> Option<Attributes> {
-        let mut attributes = Attributes::default();
-
-        // Add vertex colors only if they exist
+    ///
+    /// OBJ faces index positions, texture coordinates and normals
+    /// separately. If any face references texture coordinates or normals,
+    /// every unique combination used by a corner becomes its own vertex
+    /// and vertices no face references are dropped, with normals only
+    /// kept if every corner has one. Otherwise the vertices are kept as
+    /// they are, along with the texture coordinates or normals if there is
+    /// exactly one for every vertex.
+    ///
+    /// Parameters
+    /// ------------
+    /// faces
+    ///   The faces with the indices of every corner.
+    ///
+    /// Returns
+    /// ------------
+    /// mesh
+    ///   The vertices and faces with the colors,
+    ///   normals and UV of every vertex.
+    pub fn flatten(self, faces: &ObjFaces) -> Trimesh {
+        let has_tex = faces.faces_tex.iter().any(|t| t.is_some());
+        let has_normal = faces.face_normal.iter().any(|n| n.is_some());
+
+        // the colors are a tuple of (vertex index, color) pairs since they
+        // may be sparse, so start with every vertex the default color
+        let mut color = Vec::new();
         if !self.color.is_empty() {
-            // the colors are a tuple of (vertex index, color) pairs
-            // since they may be  sparse and not all vertices have a color.
-            // thus, start with a fully populated vector of the default color
-            let mut color = vec![DEFAULT_COLOR; self.vertices.len()];
+            color = vec![DEFAULT_COLOR; self.vertices.len()];
             for (i, c) in self.color.iter() {
-                // replace just the color at the index
                 color[*i] = *c;
             }
-            // push our vertex-matching colors into the attributes
-            attributes.colors.push(color);
         }
 
-        // Add normals if any were populated.
-        if !self.normal.is_empty() {
-            attributes.normals.push(self.normal.clone());
+        let mut attributes = Attributes::default();
+        if !(has_tex || has_normal) {
+            if !color.is_empty() {
+                attributes.colors.push(color);
+            }
+            if self.normal.len() == self.vertices.len() && !self.normal.is_empty() {
+                attributes.normals.push(self.normal);
+            }
+            if self.uv.len() == self.vertices.len() && !self.uv.is_empty() {
+                attributes.uv.push(self.uv);
+            }
+            return Trimesh {
+                vertices: self.vertices,
+                faces: faces.faces.clone(),
+                attributes_vertex: attributes,
+                ..Default::default()
+            };
         }
 
-        // Add UVs
-        if !self.uv.is_empty() {
-            attributes.uv.push(self.uv.clone());
-        }
+        // a new vertex for every unique combination of indices in face order
+        let mut unique: AHashMap<Corner, usize> = AHashMap::new();
+        let mut source: Vec<Corner> = Vec::new();
+        let mut corner = |key| {
+            *unique.entry(key).or_insert_with(|| {
+                source.push(key);
+                source.len() - 1
+            })
+        };
+        let flat: Vec<(usize, usize, usize)> = faces
+            .faces
+            .iter()
+            .zip(faces.faces_tex.iter().zip(faces.face_normal.iter()))
+            .map(|(f, (t, n))| {
+                let t = t.map_or([None; 3], |t| [Some(t.0), Some(t.1), Some(t.2)]);
+                let n = n.map_or([None; 3], |n| [Some(n.0), Some(n.1), Some(n.2)]);
+                (
+                    corner((f.0, t[0], n[0])),
+                    corner((f.1, t[1], n[1])),
+                    corner((f.2, t[2], n[2])),
+                )
+            })
+            .collect();
 
-        if attributes.colors.is_empty()
-            && attributes.normals.is_empty()
-            && attributes.uv.is_empty()
-            && attributes.groupings.is_empty()
+        let vertices = source.iter().map(|(v, _, _)| self.vertices[*v]).collect();
+        if !color.is_empty() {
+            attributes
+                .colors
+                .push(source.iter().map(|(v, _, _)| color[*v]).collect());
+        }
+        // normals are only kept if every vertex has one so they can be used
+        // directly, while corners of faces without a UV get zeros
+        if let Some(normals) = source
+            .iter()
+            .map(|(_, _, n)| n.map(|n| self.normal[n]))
+            .collect::<Option<Vec<_>>>()
         {
-            None
-        } else {
-            Some(attributes)
+            attributes.normals.push(normals);
+        }
+        if has_tex {
+            attributes.uv.push(
+                source
+                    .iter()
+                    .map(|(_, t, _)| t.map_or(Vector2::zeros(), |t| self.uv[t]))
+                    .collect(),
+            );
+        }
+        Trimesh {
+            vertices,
+            faces: flat,
+            attributes_vertex: attributes,
+            ..Default::default()
         }
     }
 }
@@ -191,7 +312,9 @@ struct ObjFaces {
 
     // the indexes of `vertices.vertices`
     pub faces: Vec<(usize, usize, usize)>,
+    // the indexes of `vertices.uv` for each corner of a face if every corner has one
     pub faces_tex: Vec<Option<(usize, usize, usize)>>,
+    // the indexes of `vertices.normal` for each corner of a face if every corner has one
     pub face_normal: Vec<Option<(usize, usize, usize)>>,
     pub faces_material: Vec<usize>,
     pub faces_group: Vec<usize>,
@@ -233,61 +356,203 @@ impl ObjFaces {
     ///   -- vertex indices and normals.
     pub fn extend(
         &mut self,
-        raw: &[Vec<Option<usize>>],
-        vertices: &[Point3<f64>],
+        raw: &[Vec<Option<i64>>],
+        vertices: &ObjVertices,
         triangulator: &mut Triangulator,
     ) {
-        // take just the vertex points from the raw data
-        let f: Vec<usize> = raw.iter().map(|v| v[0].unwrap_or(0) - 1).collect();
+        // take just the vertex points from the raw data, resolving
+        // relative indices and skipping faces with invalid ones
+        let Some(f) = raw
+            .iter()
+            .map(|v| resolve_index(v.first().copied().flatten(), vertices.vertices.len()))
+            .collect::<Option<Vec<usize>>>()
+        else {
+            return;
+        };
+        // the texture and normal indices are optional for every corner
+        let tex: Vec<Option<usize>> = raw
+            .iter()
+            .map(|v| resolve_index(v.get(1).copied().flatten(), vertices.uv.len()))
+            .collect();
+        let normal: Vec<Option<usize>> = raw
+            .iter()
+            .map(|v| resolve_index(v.get(2).copied().flatten(), vertices.normal.len()))
+            .collect();
 
-        // get the triangles as indexes in our current face
+        // get the triangles as positions in our current face
         let tri = {
+            let n = f.len();
             // if we have a triangle this is easy
-            if f.len() == 3 {
-                vec![(f[0], f[1], f[2])]
-            } else if f.len() == 4 {
+            if n == 3 {
+                vec![(0, 1, 2)]
+            } else if n == 4 {
                 // if we have a quad split it into two triangles
-                vec![(f[0], f[1], f[2]), (f[0], f[2], f[3])]
-            } else if f.len() > 4 {
-                // if we have a polygon triangulate it
-                // TODO : do we have to do this in a second pass to avoid
-                // referencing vertices that haven't been added yet?
+                vec![(0, 1, 2), (0, 2, 3)]
+            } else if n > 4 {
+                // if we have a polygon triangulate only its own corners
+                let corners: Vec<usize> = (0..n).collect();
+                let points: Vec<Point3<f64>> = f.iter().map(|i| vertices.vertices[*i]).collect();
                 triangulator
-                    .triangulate_3d(&f, &[], vertices)
-                    .unwrap_or_else(|_| triangulate_fan(&f))
+                    .triangulate_3d(&corners, &[], &points)
+                    .unwrap_or_else(|_| triangulate_fan(&corners))
             } else {
                 vec![]
             }
         };
+        // look up the same corners in the optional indices
+        let corners = |values: &[Option<usize>], (a, b, c): (usize, usize, usize)| {
+            Some((values[a]?, values[b]?, values[c]?))
+        };
+        self.faces_tex.extend(tri.iter().map(|t| corners(&tex, *t)));
+        self.face_normal
+            .extend(tri.iter().map(|t| corners(&normal, *t)));
+        let tri: Vec<(usize, usize, usize)> = tri
+            .into_iter()
+            .map(|(a, b, c)| (f[a], f[b], f[c]))
+            .collect();
 
+        // faces before any `o` or `g` line belong to an unnamed one
+        if self.objects.is_empty() {
+            self.upsert_object("");
+        }
+        if self.groups.is_empty() {
+            self.upsert_group("");
+        }
+        // every triangle from the polygon uses the current material,
+        // group and object
+        self.faces_material
+            .extend(std::iter::repeat_n(self.material, tri.len()));
+        self.faces_group
+            .extend(std::iter::repeat_n(self.group, tri.len()));
+        self.faces_object
+            .extend(std::iter::repeat_n(self.object, tri.len()));
         // add the actual triangles
         self.faces.extend(tri);
     }
 }
 
+#[derive(Default)]
 pub struct ObjMesh {
     // the original indexed vertices from the OBJ file
     vertices: ObjVertices,
 
     // the indexed faces from the OBJ file
     faces: ObjFaces,
+
+    // how many raw lines have been read so far for numbering errors
+    lines: usize,
+}
+
+/// Join every line ending with a backslash onto the line after it,
+/// as OBJ allows long lines to be continued that way.
+///
+/// Parameters
+/// ------------
+/// raw
+///   The lines of the file.
+/// start
+///   How many lines came before these ones.
+///
+/// Returns
+/// ------------
+/// lines
+///   The joined lines with the number of the first raw line
+///   each one started on counting from one.
+fn join_continued<'a>(raw: &[&'a str], start: usize) -> Vec<(usize, Cow<'a, str>)> {
+    let mut joined = Vec::with_capacity(raw.len());
+    let mut pending: Option<(usize, String)> = None;
+    for (index, line) in raw.iter().enumerate() {
+        match (line.trim_end().strip_suffix('\\'), pending.as_mut()) {
+            (Some(head), Some((_, text))) => {
+                text.push_str(head);
+                text.push(' ');
+            }
+            (Some(head), None) => pending = Some((start + index + 1, format!("{head} "))),
+            (None, Some(_)) => {
+                let (number, mut text) = pending.take().unwrap();
+                text.push_str(line);
+                joined.push((number, Cow::Owned(text)));
+            }
+            (None, None) => joined.push((start + index + 1, Cow::Borrowed(*line))),
+        }
+    }
+    // a continuation on the last line continues into nothing
+    if let Some((number, text)) = pending {
+        joined.push((number, Cow::Owned(text)));
+    }
+    joined
 }
 
+// how many lines to parse at once when reading an OBJ incrementally
+const READ_CHUNK_LINES: usize = 1 << 16;
+
 impl ObjMesh {
-    /// Parse a string into an ObjMesh.
-    pub fn from_string(data: &str) -> Result<Self> {
+    /// Parse a string into an ObjMesh, using the resolver to load
+    /// any material libraries and textures referenced by the file.
+    pub fn from_string(data: &str, resolver: &Resolver) -> Result<Self> {
+        let mut mesh = ObjMesh::default();
+        mesh.extend_lines(data.lines().collect(), resolver)?;
+        Ok(mesh)
+    }
+
+    /// Parse an OBJ file from a reader a chunk of lines at a time, so
+    /// the raw text of the whole file is never held in memory at once.
+    ///
+    /// Parameters
+    /// ------------
+    /// reader
+    ///   The source of the OBJ text.
+    /// resolver
+    ///   Returns the bytes of material libraries and textures by name.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Self>
+    ///   The parsed OBJ or an error if the reader failed
+    ///   or a line couldn't be parsed.
+    pub fn from_reader<R: BufRead>(mut reader: R, resolver: &Resolver) -> Result<Self> {
+        let mut mesh = ObjMesh::default();
+        let mut chunk: Vec<u8> = Vec::new();
+        // keep reading past the end of a chunk in the middle of a continued line
+        let continued = |chunk: &[u8]| chunk.trim_ascii_end().ends_with(b"\\");
+        loop {
+            chunk.clear();
+            let mut count = 0;
+            while (count < READ_CHUNK_LINES || continued(&chunk))
+                && reader.read_until(b'\n', &mut chunk)? > 0
+            {
+                count += 1;
+            }
+            if count == 0 {
+                break;
+            }
+            mesh.extend_lines(String::from_utf8_lossy(&chunk).lines().collect(), resolver)?;
+        }
+        Ok(mesh)
+    }
+
+    /// Parse a batch of lines and add them to the mesh, where faces
+    /// may reference vertices from any previous batch. A line which
+    /// can't be parsed is an `RmeshError::Parse` with its line number.
+    pub(crate) fn extend_lines(&mut self, raw: Vec<&str>, resolver: &Resolver) -> Result<()> {
+        let joined = join_continued(&raw, self.lines);
+        self.lines += raw.len();
         // parse the strings in parallel
-        let lines: Vec<ObjLine> = data
-            .lines()
-            .collect::<Vec<_>>()
+        let lines: Vec<ObjLine> = joined
             .into_par_iter() // TODO : check performance of par_iter vs iter ;)
-            .map(ObjLine::from_line)
-            .collect();
+            .map(|(number, line)| {
+                ObjLine::from_line(&line).map_err(|e| RmeshError::Parse {
+                    format: MeshFormat::OBJ,
+                    line: Some(number),
+                    message: e.to_string(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
 
         // the `vn``, `vt``, `v`` lines which are independent of each other
-        let mut vertex = ObjVertices::default();
+        let vertex = &mut self.vertices;
         // the `f` lines which may reference any of the `v`, `vn`, `vt` lines
-        let mut faces = ObjFaces::default();
+        let faces = &mut self.faces;
 
         // we may have to triangulate 3D polygon faces as we go
         // OBJ supports arbitrary polygons but we need triangles
@@ -296,15 +561,15 @@ impl ObjMesh {
         for line in lines.iter() {
             match line {
                 ObjLine::V(p, color) => {
-                    vertex.vertices.push(*p);
                     if let Some(c) = color {
                         vertex.color.push((vertex.vertices.len(), *c));
                     }
+                    vertex.vertices.push(*p);
                 }
                 ObjLine::Vn(n) => vertex.normal.push(*n),
                 ObjLine::Vt(t) => vertex.uv.push(*t),
                 ObjLine::F(raw) => {
-                    faces.extend(raw, &vertex.vertices, &mut triangulator);
+                    faces.extend(raw, vertex, &mut triangulator);
                 }
                 ObjLine::O(name) => faces.upsert_object(name),
                 ObjLine::G(name) => faces.upsert_group(name),
@@ -312,33 +577,141 @@ impl ObjMesh {
                 ObjLine::UseMtl(name) => faces.upsert_material(name),
                 ObjLine::MtlLib(_) => {
                     // try to load the materials from the `mtl` file specified
-                    if let Some(materials) = line.load_materials() {
+                    if let Some(materials) = line.load_materials(resolver) {
                         faces.materials_obj.extend(materials);
                     }
                 }
                 ObjLine::Ignore(_) => (),
             }
         }
-
-        Ok(ObjMesh {
-            vertices: vertex,
-            faces,
-        })
+        Ok(())
     }
 
     pub fn into_mesh(self) -> Result<Trimesh> {
         // "flatten" the mesh to ensure each vertex matches
-        let attributes_vertex = self.vertices.to_attributes().unwrap_or_default();
+        let mesh = self.vertices.flatten(&self.faces);
+
+        // order the loaded materials to match the names from `usemtl`
+        // keeping an empty material for any that weren't in a library
+        let mut attributes_face = Attributes::default();
+        let mut materials = Vec::new();
+        if !self.faces.materials.is_empty() {
+            materials = self
+                .faces
+                .materials
+                .iter()
+                .map(|name| {
+                    self.faces
+                        .materials_obj
+                        .iter()
+                        .find(|m| matches!(m, Material::Simple(s) if s.name == *name))
+                        .cloned()
+                        .unwrap_or_else(|| Material::Simple(SimpleMaterial::new(name)))
+                })
+                .collect();
+            attributes_face.groupings.push(Grouping {
+                name: "material".to_string(),
+                kind: GroupingKind::MaterialIndex,
+                indices: self.faces.faces_material,
+                labels: Vec::new(),
+            });
+        }
 
         Ok(Trimesh {
-            vertices: self.vertices.vertices,
-            faces: self.faces.faces,
-            attributes_vertex,
-            ..Default::default()
+            attributes_face,
+            materials,
+            ..mesh
         })
     }
 }
 
+impl ObjMesh {
+    /// Split the mesh into a separate mesh for every object from the `o`
+    /// lines, or for every group from the `g` lines if there are no
+    /// objects, each only keeping the vertices its faces reference.
+    ///
+    /// Returns
+    /// ------------
+    /// bodies
+    ///   The name and mesh of every object or group in the order they
+    ///   first appear, which is a single unnamed mesh if there are none.
+    pub fn into_bodies(self) -> Result<Vec<(String, Trimesh)>> {
+        let named = |names: &[String]| names.iter().any(|n| !n.is_empty());
+        let (names, keys) = if named(&self.faces.objects) {
+            (self.faces.objects.clone(), self.faces.faces_object.clone())
+        } else if named(&self.faces.groups) {
+            (self.faces.groups.clone(), self.faces.faces_group.clone())
+        } else {
+            return Ok(vec![(String::new(), self.into_mesh()?)]);
+        };
+        let mesh = self.into_mesh()?;
+
+        // the faces of each body in the order the bodies first appear
+        let mut order: Vec<usize> = Vec::new();
+        let mut faces: Vec<Vec<usize>> = vec![Vec::new(); names.len()];
+        for (face, key) in keys.iter().enumerate() {
+            if faces[*key].is_empty() {
+                order.push(*key);
+            }
+            faces[*key].push(face);
+        }
+        order
+            .into_iter()
+            .map(|key| Ok((names[key].clone(), mesh.submesh(&faces[key])?)))
+            .collect()
+    }
+}
+
+/// Export a mesh as an OBJ, including the vertex normals and
+/// UV coordinates if there is one for every vertex.
+pub fn export(mesh: &Trimesh, options: &ExportOptions) -> String {
+    let count = mesh.vertices.len();
+    let normals = mesh
+        .attributes_vertex
+        .normals
+        .first()
+        .filter(|n| n.len() == count);
+    let uv = mesh.uv().filter(|uv| uv.len() == count);
+
+    let mut out = String::with_capacity(count * 64 + mesh.faces.len() * 32);
+    let mut write_row = |keyword: &str, values: &[f64], coordinate: bool| {
+        out.push_str(keyword);
+        for value in values {
+            out.push(' ');
+            if coordinate {
+                options.write_coordinate(*value, &mut out);
+            } else {
+                options.write_float(*value, &mut out);
+            }
+        }
+        out.push('\n');
+    };
+    for vertex in mesh.vertices.iter() {
+        write_row("v", vertex.coords.as_slice(), true);
+    }
+    for normal in normals.into_iter().flatten() {
+        write_row("vn", normal.as_slice(), false);
+    }
+    for coordinate in uv.into_iter().flatten() {
+        write_row("vt", coordinate.as_slice(), false);
+    }
+
+    for face in mesh.faces.iter() {
+        out.push('f');
+        for index in [face.0 + 1, face.1 + 1, face.2 + 1] {
+            // every attribute uses the same index as the vertex
+            match (uv.is_some(), normals.is_some()) {
+                (true, true) => out.push_str(&format!(" {index}/{index}/{index}")),
+                (true, false) => out.push_str(&format!(" {index}/{index}")),
+                (false, true) => out.push_str(&format!(" {index}//{index}")),
+                (false, false) => out.push_str(&format!(" {index}")),
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
 /// Convert a string slice containing 0.0 to 1.0 float colors
 /// to a vector color.
 ///
@@ -368,10 +741,79 @@ fn str_to_rgba(raw: &[&str]) -> Option<Vector4<u8>> {
     Some(color)
 }
 
+/// Checks every OBJ vertex has enough numeric values and every face
+/// references vertices, texture coordinates and normals which exist,
+/// including relative negative indices.
+pub struct ObjValidator;
+
+impl FormatValidator for ObjValidator {
+    fn validate(&self, bytes: &[u8]) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        // the number of vertices, texture coordinates and normals so far
+        let mut counts = [0_usize; 3];
+        let mut faces = 0;
+        let text = String::from_utf8_lossy(bytes);
+        let raw: Vec<&str> = text.lines().collect();
+        for (number, line) in join_continued(&raw, 0) {
+            let line_number = Some(number);
+            let line = line.split('#').next().unwrap_or_default();
+            let mut words = line.split_whitespace();
+            let Some(keyword) = words.next() else {
+                continue;
+            };
+            let (slot, minimum) = match keyword {
+                "v" => (0, 3),
+                "vt" => (1, 1),
+                "vn" => (2, 3),
+                "f" => {
+                    let references: Vec<&str> = words.collect();
+                    if references.len() < 3 {
+                        report.error(line_number, "Face must reference at least 3 vertices");
+                    }
+                    for reference in references {
+                        for (slot, value) in reference.split('/').enumerate().take(3) {
+                            // texture and normal indices may be left empty
+                            if value.is_empty() && slot > 0 {
+                                continue;
+                            }
+                            let count = counts[slot] as i64;
+                            match value.parse::<i64>() {
+                                Ok(i) if (1..=count).contains(&i) || (-count..=-1).contains(&i) => {
+                                }
+                                Ok(i) => report.error(
+                                    line_number,
+                                    format!("Face index {i} is out of range for {count} elements"),
+                                ),
+                                Err(_) => report.error(
+                                    line_number,
+                                    format!("Face has an invalid index `{reference}`"),
+                                ),
+                            }
+                        }
+                    }
+                    faces += 1;
+                    continue;
+                }
+                _ => continue,
+            };
+            if numbers(words).is_none_or(|v| v.len() < minimum) {
+                report.error(
+                    line_number,
+                    format!("`{keyword}` must have at least {minimum} numbers"),
+                );
+            }
+            counts[slot] += 1;
+        }
+        report.vertices = Some(counts[0]);
+        report.faces = Some(faces);
+        report
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
-    use crate::exchange::{MeshFormat, load_mesh};
+    use crate::exchange::load_mesh;
 
     use super::*;
 
@@ -399,18 +841,41 @@ mod tests {
         // make sure the OBJ file was loadable into a mesh
         let mesh = load_mesh(data.as_bytes(), crate::exchange::MeshFormat::OBJ).unwrap();
 
-        // should have loaded a vertex for every occurrence of 'v '
-        assert_eq!(mesh.vertices.len(), data.matches("\nv ").count());
-        // todo : implement faces
         // should have loaded a face for every occurrence of 'f '
         assert_eq!(mesh.faces.len(), data.matches("\nf ").count());
 
+        // vertices on a UV seam are split so every vertex has one UV
         assert!(mesh.uv().is_some());
         let uv = mesh.uv().unwrap();
-        assert_eq!(uv.len(), data.matches("\nvt ").count());
+        assert_eq!(uv.len(), mesh.vertices.len());
+        assert!(mesh.vertices.len() > data.matches("\nv ").count());
+        assert_eq!(mesh.attributes_vertex.normals[0].len(), mesh.vertices.len());
+    }
 
-        // here's the big tricky TODO
-        // assert_eq!(uv.len(),mesh.vertices.len());
+    #[test]
+    fn test_obj_unmerge() {
+        // a quad whose two triangles use different UVs along their shared edge
+        let data = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+                    vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\nvt 0.5 0.5\n\
+                    vn 0 0 1\n\
+                    f 1/1/1 2/2/1 3/3/1\nf 1/5/1 3/3/1 4/4/1\n";
+        let mesh = load_mesh(data.as_bytes(), crate::exchange::MeshFormat::OBJ).unwrap();
+        // the first vertex is used with two different UVs
+        assert_eq!(mesh.vertices.len(), 5);
+        assert_eq!(mesh.faces, vec![(0, 1, 2), (3, 2, 4)]);
+        let uv = mesh.uv().unwrap();
+        assert_eq!(mesh.vertices[3], Point3::origin());
+        assert_eq!(
+            (uv[0], uv[3]),
+            (Vector2::new(0.0, 0.0), Vector2::new(0.5, 0.5))
+        );
+
+        // relative indices resolve and normals are dropped unless every corner has one
+        let data = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 0 0 1\nf -3/-1 -2/-1/-1 -1/1\n";
+        let mesh = load_mesh(data.as_bytes(), crate::exchange::MeshFormat::OBJ).unwrap();
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.uv().unwrap().len(), 3);
+        assert!(mesh.attributes_vertex.normals.is_empty());
     }
 
     #[test]
@@ -422,7 +887,7 @@ mod tests {
             .lines()
             .collect::<Vec<_>>()
             .into_par_iter()
-            .map(ObjLine::from_line)
+            .map(|line| ObjLine::from_line(line).unwrap())
             .collect();
 
         // check a few parse results of more difficult lines
@@ -439,12 +904,92 @@ mod tests {
         // make sure the OBJ file was loadable into a mesh
         let mesh = load_mesh(data.as_bytes(), MeshFormat::OBJ).unwrap();
 
-        // should have loaded a vertex for every occurrence of 'v '
-        assert_eq!(mesh.vertices.len(), data.matches("\nv ").count());
-        // todo : implement faces
         // should have loaded a face for every occurrence of 'f '
         assert_eq!(mesh.faces.len(), data.matches("\nf ").count());
+        // vertices are split where faces use them with different UVs
+        assert!(mesh.vertices.len() >= data.matches("\nv ").count());
+        assert_eq!(mesh.uv().unwrap().len(), mesh.vertices.len());
 
         println!("mesh: {mesh:?}");
     }
+
+    #[test]
+    fn test_obj_continuation() {
+        // a face continued over two lines with relative indices
+        let data = "v 0 0 0\nv 1 0 \\\n 0\nv 1 1 0\nv 0 1 0\nvt 0 0\nvt 1 1\n\
+                    f -4/-2 -3/-2 \\\n  -2/-1 -1/-1\n";
+        let mesh = load_mesh(data.as_bytes(), MeshFormat::OBJ).unwrap();
+        assert_eq!(mesh.faces.len(), 2);
+        assert_eq!(mesh.vertices[1], Point3::new(1.0, 0.0, 0.0));
+        assert_eq!(mesh.uv().unwrap()[3], Vector2::new(1.0, 1.0));
+
+        // the streaming reader never splits a continued line between chunks
+        let read = crate::exchange::load_mesh_reader(data.as_bytes(), MeshFormat::OBJ).unwrap();
+        assert_eq!(read.faces, mesh.faces);
+
+        // malformed numbers are errors on the line they start on
+        for (data, line) in [
+            ("v 0 0 0\nv 1 0 nan0\n", 2),
+            ("v 0 0 0\nv 1 0 \\\n x\n", 2),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 three\n", 4),
+            ("v 0 0\n", 1),
+        ] {
+            let error = load_mesh(data.as_bytes(), MeshFormat::OBJ).unwrap_err();
+            assert!(
+                matches!(error, RmeshError::Parse { line: Some(l), .. } if l == line),
+                "{error}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_obj_chunked() {
+        // faces should be able to reference vertices from earlier chunks
+        let data = include_str!("../../../../test/data/basic.obj");
+        let expected = ObjMesh::from_string(data, &|_| None)
+            .unwrap()
+            .into_mesh()
+            .unwrap();
+        let mut chunked = ObjMesh::default();
+        for line in data.lines() {
+            chunked.extend_lines(vec![line], &|_| None).unwrap();
+        }
+        let mesh = chunked.into_mesh().unwrap();
+        assert_eq!(mesh.vertices, expected.vertices);
+        assert_eq!(mesh.faces, expected.faces);
+    }
+
+    #[test]
+    fn test_obj_materials() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../test/data");
+        let data = include_str!("../../../../test/data/fuze.obj");
+        let resolver = |name: &str| std::fs::read(root.join(name)).ok();
+        let mesh = ObjMesh::from_string(data, &resolver)
+            .unwrap()
+            .into_mesh()
+            .unwrap();
+
+        assert_eq!(mesh.materials.len(), 1);
+        let Material::Simple(material) = &mesh.materials[0] else {
+            panic!("expected a simple material");
+        };
+        assert_eq!(material.name, "material_0");
+        assert_eq!(material.diffuse, Some(Vector3::new(1.0, 1.0, 1.0)));
+        assert_eq!(material.shininess, Some(0.0));
+        // the texture has a space in the file name
+        assert!(material.image.is_some());
+
+        let grouping = &mesh.attributes_face.groupings[0];
+        assert!(matches!(grouping.kind, GroupingKind::MaterialIndex));
+        assert_eq!(grouping.indices.len(), mesh.faces.len());
+        assert!(grouping.indices.iter().all(|i| *i == 0));
+
+        // without a resolver the material is known only by name
+        let mesh = load_mesh(data.as_bytes(), MeshFormat::OBJ).unwrap();
+        let Material::Simple(material) = &mesh.materials[0] else {
+            panic!("expected a simple material");
+        };
+        assert_eq!(material.name, "material_0");
+        assert!(material.diffuse.is_none() && material.image.is_none());
+    }
 }
@@ -0,0 +1,357 @@
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector4};
+
+use crate::attributes::{Attributes, DEFAULT_COLOR, LoadSource};
+use crate::creation::{Triangulator, triangulate_fan};
+use crate::exchange::MeshFormat;
+use crate::exchange::validate::{FormatValidator, ValidationReport, numbers};
+use crate::mesh::Trimesh;
+
+/// The parsed contents of an ASCII OFF file.
+pub struct OffMesh {
+    vertices: Vec<Point3<f64>>,
+    // the polygon faces as indexes of `vertices`
+    polygons: Vec<Vec<usize>>,
+    // the color of each face if the file specified any
+    colors: Vec<Option<Vector4<u8>>>,
+    // the first line of the file such as `OFF` or `COFF`
+    header: String,
+}
+
+impl OffMesh {
+    /// Parse the text of an ASCII OFF file.
+    ///
+    /// Parameters
+    /// ------------
+    /// data
+    ///   The text of the OFF file.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Self>
+    ///   The parsed vertices and polygons or an error.
+    pub fn from_string(data: &str) -> Result<Self> {
+        // strip comments and blank lines
+        let mut lines = data
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty());
+
+        let first = lines.next().ok_or_else(|| anyhow!("OFF file is empty"))?;
+        let keyword_end = first
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(first.len());
+        let (keyword, rest) = first.split_at(keyword_end);
+        if !keyword.ends_with("OFF") {
+            return Err(anyhow!("OFF file must start with `OFF`: `{first}`"));
+        }
+        if keyword.contains("4") || keyword.starts_with("n") {
+            return Err(anyhow!("Unsupported OFF variant: `{keyword}`"));
+        }
+
+        // the counts are usually on the next line but some datasets
+        // (notably ModelNet) run them into the header like `OFF490 518 0`
+        let counts_line = if rest.trim().is_empty() {
+            lines
+                .next()
+                .ok_or_else(|| anyhow!("OFF file has no element counts"))?
+        } else {
+            rest.trim()
+        };
+        let counts = counts_line
+            .split_whitespace()
+            .map(|c| c.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| anyhow!("OFF file has invalid element counts: `{counts_line}`"))?;
+        let [vertex_count, face_count, ..] = counts.as_slice() else {
+            return Err(anyhow!(
+                "OFF file has invalid element counts: `{counts_line}`"
+            ));
+        };
+
+        let vertices = lines
+            .by_ref()
+            .take(*vertex_count)
+            .map(|line| {
+                let values = line
+                    .split_whitespace()
+                    .take(3)
+                    .map(|v| v.parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()?;
+                match values.as_slice() {
+                    [x, y, z] => Ok(Point3::new(*x, *y, *z)),
+                    _ => Err(anyhow!("OFF vertex has too few values: `{line}`")),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if vertices.len() != *vertex_count {
+            return Err(anyhow!(
+                "OFF file has {} vertices but header specified {vertex_count}",
+                vertices.len()
+            ));
+        }
+
+        // every face takes at least a byte which bounds a hostile count
+        let capacity = (*face_count).min(data.len());
+        let mut polygons = Vec::with_capacity(capacity);
+        let mut colors = Vec::with_capacity(capacity);
+        for line in lines.take(*face_count) {
+            let mut values = line.split_whitespace();
+            let count: usize = values
+                .next()
+                .and_then(|c| c.parse().ok())
+                .ok_or_else(|| anyhow!("OFF face is missing a vertex count: `{line}`"))?;
+            let polygon = values
+                .by_ref()
+                .take(count)
+                .map(|v| v.parse::<usize>())
+                .collect::<Result<Vec<_>, _>>()?;
+            if polygon.len() != count {
+                return Err(anyhow!("OFF face has too few indices: `{line}`"));
+            }
+            if let Some(bad) = polygon.iter().find(|i| **i >= vertices.len()) {
+                return Err(anyhow!(
+                    "OFF face references vertex {bad} of {}",
+                    vertices.len()
+                ));
+            }
+            // anything after the indices is an optional color
+            colors.push(parse_color(&values.collect::<Vec<_>>()));
+            polygons.push(polygon);
+        }
+        if polygons.len() != *face_count {
+            return Err(anyhow!(
+                "OFF file has {} faces but header specified {face_count}",
+                polygons.len()
+            ));
+        }
+
+        Ok(Self {
+            vertices,
+            polygons,
+            colors,
+            header: first.to_string(),
+        })
+    }
+
+    /// Triangulate the polygons into a mesh.
+    pub fn into_mesh(self) -> Result<Trimesh> {
+        let mut triangulator = Triangulator::new();
+        let mut faces = Vec::with_capacity(self.polygons.len());
+        let mut colors = Vec::with_capacity(self.polygons.len());
+
+        for (polygon, color) in self.polygons.iter().zip(self.colors.iter()) {
+            let triangles = match polygon.len() {
+                0..=2 => vec![],
+                3 => vec![(polygon[0], polygon[1], polygon[2])],
+                _ => triangulator
+                    .triangulate_3d(polygon, &[], &self.vertices)
+                    .unwrap_or_else(|_| triangulate_fan(polygon)),
+            };
+            colors.extend(std::iter::repeat_n(
+                color.unwrap_or(DEFAULT_COLOR),
+                triangles.len(),
+            ));
+            faces.extend(triangles);
+        }
+
+        let mut attributes_face = Attributes::default();
+        if self.colors.iter().any(|c| c.is_some()) {
+            attributes_face.colors.push(colors);
+        }
+
+        let mut mesh = Trimesh::new(self.vertices, faces, None, Some(attributes_face))?;
+        mesh.source = LoadSource {
+            format: Some(MeshFormat::OFF),
+            header: Some(self.header),
+
+            ..Default::default()
+        };
+        Ok(mesh)
+    }
+}
+
+/// Parse an OFF color which is either integers from 0-255 or floats
+/// from 0.0-1.0, with an optional alpha.
+fn parse_color(raw: &[&str]) -> Option<Vector4<u8>> {
+    if raw.len() < 3 {
+        return None;
+    }
+    let is_float = raw.iter().any(|v| v.contains('.'));
+    let mut color = Vector4::new(0, 0, 0, 255);
+    for (i, value) in raw.iter().take(4).enumerate() {
+        let value: f64 = value.parse().ok()?;
+        color[i] = if is_float {
+            (value * 255.0).round().clamp(0.0, 255.0) as u8
+        } else {
+            value.clamp(0.0, 255.0) as u8
+        };
+    }
+    Some(color)
+}
+
+/// Checks an OFF file has the header and counts `OffMesh` supports, as
+/// many vertices and faces as the counts declare, and that every face
+/// index refers to a vertex.
+pub struct OffValidator;
+
+impl FormatValidator for OffValidator {
+    fn validate(&self, bytes: &[u8]) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let text = String::from_utf8_lossy(bytes);
+        // keep the line numbers of the lines left after stripping comments
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.split('#').next().unwrap_or_default().trim()))
+            .filter(|(_, line)| !line.is_empty());
+
+        let Some((first_number, first)) = lines.next() else {
+            report.error(None, "OFF file is empty");
+            return report;
+        };
+        let keyword_end = first
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(first.len());
+        let (keyword, rest) = first.split_at(keyword_end);
+        if !keyword.ends_with("OFF") || keyword.contains('4') || keyword.starts_with('n') {
+            report.error(
+                Some(first_number),
+                format!("Unsupported OFF header `{keyword}`"),
+            );
+            return report;
+        }
+        let counts = if rest.trim().is_empty() {
+            lines.next()
+        } else {
+            Some((first_number, rest.trim()))
+        };
+        let parsed = counts.and_then(|(_, line)| {
+            line.split_whitespace()
+                .map(|c| c.parse::<usize>().ok())
+                .collect::<Option<Vec<_>>>()
+        });
+        let Some([vertex_count, face_count, ..]) = parsed.as_deref() else {
+            report.error(counts.map(|c| c.0), "OFF file has invalid element counts");
+            return report;
+        };
+        let (vertex_count, face_count) = (*vertex_count, *face_count);
+        report.vertices = Some(vertex_count);
+        report.faces = Some(face_count);
+
+        let mut vertices = 0;
+        for (number, line) in lines.by_ref().take(vertex_count) {
+            if numbers(line.split_whitespace().take(3)).is_none_or(|v| v.len() != 3) {
+                report.error(Some(number), "OFF vertex must start with three numbers");
+            }
+            vertices += 1;
+        }
+        if vertices != vertex_count {
+            report.error(
+                None,
+                format!("OFF file has {vertices} vertices but declares {vertex_count}"),
+            );
+            return report;
+        }
+
+        let mut faces = 0;
+        for (number, line) in lines.by_ref().take(face_count) {
+            let mut values = line.split_whitespace();
+            let count = values.next().and_then(|c| c.parse::<usize>().ok());
+            let indices: Option<Vec<usize>> = count.and_then(|count| {
+                let indices: Option<Vec<usize>> =
+                    values.take(count).map(|v| v.parse().ok()).collect();
+                indices.filter(|i| i.len() == count)
+            });
+            match indices {
+                Some(indices) => {
+                    if let Some(bad) = indices.iter().find(|i| **i >= vertex_count) {
+                        report.error(
+                            Some(number),
+                            format!("OFF face references vertex {bad} of {vertex_count}"),
+                        );
+                    }
+                }
+                None => report.error(Some(number), "OFF face has an invalid vertex list"),
+            }
+            faces += 1;
+        }
+        if faces != face_count {
+            report.error(
+                None,
+                format!("OFF file has {faces} faces but declares {face_count}"),
+            );
+        }
+        if lines.next().is_some() {
+            report.warning(None, "OFF file has lines after the declared faces");
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::exchange::load_mesh;
+
+    #[test]
+    fn test_off_cube() {
+        let data = "OFF
+# a unit cube with quad faces
+8 6 12
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+0 0 1
+1 0 1
+1 1 1
+0 1 1
+4 0 3 2 1
+4 4 5 6 7
+4 0 1 5 4 255 0 0
+4 2 3 7 6
+4 1 2 6 5
+4 3 0 4 7
+";
+        let mesh = load_mesh(data.as_bytes(), MeshFormat::OFF).unwrap();
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.faces.len(), 12);
+        assert_eq!(mesh.source.format, Some(MeshFormat::OFF));
+        approx::assert_relative_eq!(mesh.area(), 6.0, epsilon = 1e-10);
+
+        // only one face had a color
+        let colors = &mesh.attributes_face.colors[0];
+        assert_eq!(colors.len(), 12);
+        assert_eq!(colors.iter().filter(|c| **c == DEFAULT_COLOR).count(), 10);
+    }
+
+    #[test]
+    fn test_off_modelnet_header() {
+        // ModelNet files have the counts on the header line
+        let data = "OFF3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 2 0.5 0.5 0.5\n";
+        let mesh = load_mesh(data.as_bytes(), MeshFormat::OFF).unwrap();
+        assert_eq!(mesh.faces, vec![(0, 1, 2)]);
+        assert_eq!(
+            mesh.attributes_face.colors[0][0],
+            Vector4::new(128, 128, 128, 255)
+        );
+    }
+
+    #[test]
+    fn test_off_invalid() {
+        assert!(OffMesh::from_string("").is_err());
+        assert!(OffMesh::from_string("PLY\n").is_err());
+        // too few vertices for the header
+        assert!(OffMesh::from_string("OFF\n3 1 0\n0 0 0\n").is_err());
+        // a face referencing a vertex that doesn't exist
+        let data = "OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 7\n";
+        assert!(load_mesh(data.as_bytes(), MeshFormat::OFF).is_err());
+        // including a polygon which would be triangulated
+        let data = "OFF\n4 1 0\n0 0 0\n1 0 0\n1 1 0\n0 1 0\n4 0 1 2 9\n";
+        assert!(OffMesh::from_string(data).is_err());
+        // a face count far too large to allocate for
+        assert!(OffMesh::from_string("OFF\n0 1000000000000000000 0\n").is_err());
+    }
+}
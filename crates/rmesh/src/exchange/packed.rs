@@ -0,0 +1,563 @@
+use std::io::Read;
+
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector2, Vector3, Vector4};
+
+use crate::attributes::Attributes;
+use crate::mesh::Trimesh;
+
+/// The bytes every packed mesh starts with.
+const MAGIC: &[u8; 4] = b"RMPK";
+/// The version of the layout written by `pack_mesh`.
+const VERSION: u8 = 1;
+/// The most LZ4 can expand its input, as a byte can at most add
+/// 255 to the length of a match, which caps the size a corrupt
+/// header can make `unpack_mesh` allocate.
+const LZ4_MAX_RATIO: usize = 255;
+
+// which optional vertex attribute streams are present
+const HAS_COLORS: u8 = 1;
+const HAS_NORMALS: u8 = 2;
+const HAS_UV: u8 = 4;
+// which attribute streams are stored as 16 bit fixed point
+const FIXED_NORMALS: u8 = 8;
+const FIXED_UV: u8 = 16;
+
+/// How the streams of a packed mesh are compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    // store the encoded streams as they are
+    None,
+    // fast to compress and decompress, which suits passing meshes
+    // between threads where the copy is the bottleneck
+    #[default]
+    Lz4,
+    // smaller than LZ4 but slower, which suits sending over a network
+    Zstd,
+}
+
+impl Compression {
+    /// Parse a compression from a name like `lz4`, `zstd` or `none`.
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "none" | "" => Ok(Compression::None),
+            "lz4" => Ok(Compression::Lz4),
+            "zstd" => Ok(Compression::Zstd),
+            other => Err(anyhow!("Unsupported compression: `{other}`")),
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Lz4 => 1,
+            Compression::Zstd => 2,
+        }
+    }
+
+    fn from_byte(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Lz4),
+            2 => Ok(Compression::Zstd),
+            _ => Err(anyhow!("Unknown packed mesh compression: {value}")),
+        }
+    }
+}
+
+/// Options for packing a mesh into a compact binary buffer.
+#[derive(Debug, Clone, Default)]
+pub struct PackOptions {
+    // snap vertices to a grid of this spacing and store them as
+    // differences between grid indices, which is lossy but makes
+    // coherent meshes much smaller, or store exact values if `None`
+    pub quantize: Option<f64>,
+    pub compression: Compression,
+}
+
+/// Map a signed integer to an unsigned one so small values of either
+/// sign become small values which take few bytes as a varint.
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    ((value >> 1) as i64) ^ -((value & 1) as i64)
+}
+
+fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Write a sequence of integers as zigzag varints of the difference
+/// from the previous value, which is tiny for coherent sequences.
+fn write_deltas(values: impl Iterator<Item = i64>, out: &mut Vec<u8>) {
+    let mut previous = 0i64;
+    for value in values {
+        write_varint(zigzag(value.wrapping_sub(previous)), out);
+        previous = value;
+    }
+}
+
+/// Read through the payload of a packed mesh, erroring on truncation
+/// rather than panicking as the data may come from anywhere.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        if count > self.data.len() {
+            return Err(anyhow!("Packed mesh is truncated"));
+        }
+        let (head, tail) = self.data.split_at(count);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(anyhow!("Packed mesh has an invalid varint"))
+    }
+
+    fn deltas(&mut self, count: usize) -> Result<Vec<i64>> {
+        let mut previous = 0i64;
+        (0..count)
+            .map(|_| {
+                previous = previous.wrapping_add(unzigzag(self.varint()?));
+                Ok(previous)
+            })
+            .collect()
+    }
+
+    /// Read `count` values of `width` bytes each, checking the length
+    /// first so a corrupt count can't allocate a huge buffer.
+    fn values<T>(
+        &mut self,
+        count: usize,
+        width: usize,
+        parse: impl Fn(&[u8]) -> T,
+    ) -> Result<Vec<T>> {
+        let bytes = self.take(
+            count
+                .checked_mul(width)
+                .ok_or(anyhow!("Packed mesh is truncated"))?,
+        )?;
+        Ok(bytes.chunks_exact(width).map(parse).collect())
+    }
+}
+
+/// Write every component of a sequence of vectors as a separate plane
+/// so each stream holds similar values for the compressor.
+fn write_planes<const D: usize>(
+    values: &[[f64; D]],
+    quantized: bool,
+    scale: f64,
+    out: &mut Vec<u8>,
+) {
+    for axis in 0..D {
+        for v in values {
+            if quantized {
+                out.extend(((v[axis] * scale).round() as i16).to_le_bytes());
+            } else {
+                out.extend(v[axis].to_le_bytes());
+            }
+        }
+    }
+}
+
+fn read_planes<const D: usize>(
+    reader: &mut Reader,
+    count: usize,
+    quantized: bool,
+    scale: f64,
+) -> Result<Vec<[f64; D]>> {
+    let mut result = vec![[0.0; D]; count];
+    for axis in 0..D {
+        let plane = if quantized {
+            reader.values(count, 2, |b| {
+                i16::from_le_bytes([b[0], b[1]]) as f64 / scale
+            })?
+        } else {
+            reader.values(count, 8, |b| f64::from_le_bytes(b.try_into().unwrap()))?
+        };
+        for (r, value) in result.iter_mut().zip(plane) {
+            r[axis] = value;
+        }
+    }
+    Ok(result)
+}
+
+/// Pack a mesh with its first vertex colors, normals and UV coordinates
+/// into a compact binary buffer, such as for transferring between a
+/// web worker and the main thread or sending over a network.
+///
+/// With `quantize` set vertices are stored as varint differences between
+/// grid indices, unit normals and UV coordinates in the unit square as
+/// 16 bit fixed point, with faces always stored as varint differences
+/// between consecutive indices before compression.
+///
+/// Parameters
+/// ------------
+/// mesh
+///   The mesh to pack.
+/// options
+///   The quantization step and compression to use.
+///
+/// Returns
+/// ------------
+/// packed
+///   The packed bytes or an error if the quantization step isn't
+///   positive and finite or a vertex is too far from the origin for it.
+pub fn pack_mesh(mesh: &Trimesh, options: &PackOptions) -> Result<Vec<u8>> {
+    let count = mesh.vertices.len();
+    let step = match options.quantize {
+        Some(step) if step <= 0.0 || !step.is_finite() => {
+            return Err(anyhow!("Quantize step must be positive and finite: {step}"));
+        }
+        Some(step) => step,
+        None => 0.0,
+    };
+    let quantized = step > 0.0;
+    fn first<T>(values: &[Vec<T>], count: usize) -> Option<&Vec<T>> {
+        values.first().filter(|v| v.len() == count)
+    }
+    let colors = first(&mesh.attributes_vertex.colors, count);
+    let normals = first(&mesh.attributes_vertex.normals, count);
+    let uv = first(&mesh.attributes_vertex.uv, count);
+    // values outside of the range of fixed point are stored exactly
+    // such as normals which weren't unit vectors in the source file
+    let fixed_normals =
+        quantized && normals.is_some_and(|n| n.iter().all(|n| n.iter().all(|n| n.abs() <= 1.0)));
+    let fixed_uv = quantized
+        && uv.is_some_and(|uv| uv.iter().all(|c| c.iter().all(|c| (0.0..=1.0).contains(c))));
+
+    let mut payload = Vec::with_capacity(count * 12 + mesh.faces.len() * 6);
+    payload.extend((count as u64).to_le_bytes());
+    payload.extend((mesh.faces.len() as u64).to_le_bytes());
+    payload.extend(step.to_le_bytes());
+    let mut flags = 0;
+    for (present, flag) in [
+        (colors.is_some(), HAS_COLORS),
+        (normals.is_some(), HAS_NORMALS),
+        (uv.is_some(), HAS_UV),
+        (fixed_normals, FIXED_NORMALS),
+        (fixed_uv, FIXED_UV),
+    ] {
+        if present {
+            flags |= flag;
+        }
+    }
+    payload.push(flags);
+
+    let vertices: Vec<[f64; 3]> = mesh.vertices.iter().map(|v| [v.x, v.y, v.z]).collect();
+    if quantized {
+        // rounding saturates so check the grid index actually fits
+        let limit = (1u64 << 52) as f64;
+        if let Some(v) = vertices
+            .iter()
+            .flatten()
+            .find(|v| !v.is_finite() || (*v / step).abs() >= limit)
+        {
+            return Err(anyhow!("Vertex value {v} can't be quantized to {step}"));
+        }
+        for axis in 0..3 {
+            write_deltas(
+                vertices.iter().map(|v| (v[axis] / step).round() as i64),
+                &mut payload,
+            );
+        }
+    } else {
+        write_planes(&vertices, false, 1.0, &mut payload);
+    }
+    write_deltas(
+        mesh.faces
+            .iter()
+            .flat_map(|f| [f.0 as i64, f.1 as i64, f.2 as i64]),
+        &mut payload,
+    );
+
+    if let Some(colors) = colors {
+        for channel in 0..4 {
+            payload.extend(colors.iter().map(|c| c[channel]));
+        }
+    }
+    if let Some(normals) = normals {
+        let normals: Vec<[f64; 3]> = normals.iter().map(|n| [n.x, n.y, n.z]).collect();
+        write_planes(&normals, fixed_normals, i16::MAX as f64, &mut payload);
+    }
+    if let Some(uv) = uv {
+        // map the unit square onto the full range of a 16 bit integer
+        let offset = if fixed_uv { 0.5 } else { 0.0 };
+        let uv: Vec<[f64; 2]> = uv.iter().map(|c| [c.x - offset, c.y - offset]).collect();
+        write_planes(&uv, fixed_uv, u16::MAX as f64, &mut payload);
+    }
+
+    let mut result = Vec::with_capacity(payload.len() / 2 + 6);
+    result.extend(MAGIC);
+    result.push(VERSION);
+    result.push(options.compression.to_byte());
+    match options.compression {
+        Compression::None => result.extend(payload),
+        Compression::Lz4 => result.extend(lz4_flex::compress_prepend_size(&payload)),
+        Compression::Zstd => result.extend(ruzstd::encoding::compress_to_vec(
+            payload.as_slice(),
+            ruzstd::encoding::CompressionLevel::Fastest,
+        )),
+    }
+    Ok(result)
+}
+
+/// Unpack a mesh packed by `pack_mesh`.
+///
+/// Parameters
+/// ------------
+/// data
+///   The bytes from `pack_mesh`.
+///
+/// Returns
+/// ------------
+/// mesh
+///   The unpacked mesh, which has vertices snapped to the grid if it
+///   was packed with quantization, or an error if the data is invalid.
+pub fn unpack_mesh(data: &[u8]) -> Result<Trimesh> {
+    if data.len() < 6 || &data[..4] != MAGIC {
+        return Err(anyhow!("Data is not a packed mesh"));
+    }
+    if data[4] != VERSION {
+        return Err(anyhow!("Unsupported packed mesh version: {}", data[4]));
+    }
+    let payload = match Compression::from_byte(data[5])? {
+        Compression::None => data[6..].to_vec(),
+        Compression::Lz4 => {
+            let (size, compressed) = lz4_flex::block::uncompressed_size(&data[6..])
+                .map_err(|e| anyhow!("Packed mesh is corrupt: {e}"))?;
+            if size > compressed.len().saturating_mul(LZ4_MAX_RATIO) {
+                return Err(anyhow!(
+                    "Packed mesh is corrupt: {} bytes can't decompress to {size}",
+                    compressed.len()
+                ));
+            }
+            lz4_flex::decompress(compressed, size)
+                .map_err(|e| anyhow!("Packed mesh is corrupt: {e}"))?
+        }
+        Compression::Zstd => {
+            let mut payload = Vec::new();
+            ruzstd::decoding::StreamingDecoder::new(&data[6..])
+                .map_err(|e| anyhow!("Packed mesh is corrupt: {e}"))?
+                .read_to_end(&mut payload)?;
+            payload
+        }
+    };
+
+    let mut reader = Reader { data: &payload };
+    let count = reader.u64()? as usize;
+    let face_count = reader.u64()? as usize;
+    let step = reader.f64()?;
+    let flags = reader.byte()?;
+    let fixed_uv = flags & FIXED_UV != 0;
+    let quantized = step > 0.0;
+
+    let vertices: Vec<Point3<f64>> = if quantized {
+        // every value takes at least one byte so check before allocating
+        if count.saturating_mul(3) > reader.data.len() {
+            return Err(anyhow!("Packed mesh is truncated"));
+        }
+        let axes: Vec<Vec<f64>> = (0..3)
+            .map(|_| {
+                Ok(reader
+                    .deltas(count)?
+                    .into_iter()
+                    .map(|v| v as f64)
+                    .collect())
+            })
+            .collect::<Result<_>>()?;
+        (0..count)
+            .map(|i| Point3::new(axes[0][i], axes[1][i], axes[2][i]) * step)
+            .collect()
+    } else {
+        read_planes::<3>(&mut reader, count, false, 1.0)?
+            .into_iter()
+            .map(Point3::from)
+            .collect()
+    };
+
+    if face_count.saturating_mul(3) > reader.data.len() {
+        return Err(anyhow!("Packed mesh is truncated"));
+    }
+    let indices = reader.deltas(face_count * 3)?;
+    if let Some(index) = indices.iter().find(|i| **i < 0 || **i as usize >= count) {
+        return Err(anyhow!("Packed mesh references missing vertex {index}"));
+    }
+    let faces = indices
+        .chunks_exact(3)
+        .map(|f| (f[0] as usize, f[1] as usize, f[2] as usize))
+        .collect();
+
+    let mut attributes = Attributes::default();
+    if flags & HAS_COLORS != 0 {
+        let channels = reader.take(
+            count
+                .checked_mul(4)
+                .ok_or(anyhow!("Packed mesh is truncated"))?,
+        )?;
+        attributes.colors.push(
+            (0..count)
+                .map(|i| Vector4::from_fn(|c, _| channels[c * count + i]))
+                .collect(),
+        );
+    }
+    if flags & HAS_NORMALS != 0 {
+        attributes.normals.push(
+            read_planes::<3>(
+                &mut reader,
+                count,
+                flags & FIXED_NORMALS != 0,
+                i16::MAX as f64,
+            )?
+            .into_iter()
+            .map(Vector3::from)
+            .collect(),
+        );
+    }
+    if flags & HAS_UV != 0 {
+        let offset = if fixed_uv { 0.5 } else { 0.0 };
+        attributes.uv.push(
+            read_planes::<2>(&mut reader, count, fixed_uv, u16::MAX as f64)?
+                .into_iter()
+                .map(|c| Vector2::new(c[0] + offset, c[1] + offset))
+                .collect(),
+        );
+    }
+
+    Ok(Trimesh::new(vertices, faces, Some(attributes), None)?)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::exchange::{MeshFormat, load_mesh};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_pack_mesh() {
+        let mut mesh = load_mesh(
+            include_bytes!("../../../../test/data/fuze.obj"),
+            MeshFormat::OBJ,
+        )
+        .unwrap();
+        let count = mesh.vertices.len();
+        // the normals in the file aren't unit vectors so use computed ones
+        mesh.attributes_vertex.normals.clear();
+        let normals = mesh.vertex_normals();
+        mesh.attributes_vertex.normals = vec![normals.clone()];
+        mesh.attributes_vertex.uv = vec![
+            mesh.vertices
+                .iter()
+                .map(|v| Vector2::new(v.x.fract().abs(), v.y.fract().abs()))
+                .collect(),
+        ];
+        mesh.attributes_vertex.colors = vec![vec![Vector4::new(10, 20, 30, 255); count]];
+
+        // exact packing round trips bit for bit and the
+        // same vertex, normal and UV values as flat doubles
+        let raw = count * 8 * 8 + count * 4 + mesh.faces.len() * 3 * 4;
+        for compression in [Compression::None, Compression::Lz4, Compression::Zstd] {
+            let options = PackOptions {
+                quantize: None,
+                compression,
+            };
+            let packed = pack_mesh(&mesh, &options).unwrap();
+            let unpacked = unpack_mesh(&packed).unwrap();
+            assert_eq!(unpacked.vertices, mesh.vertices);
+            assert_eq!(unpacked.faces, mesh.faces);
+            assert_eq!(unpacked.attributes_vertex.normals[0], normals);
+            assert_eq!(unpacked.attributes_vertex.uv, mesh.attributes_vertex.uv);
+            assert_eq!(
+                unpacked.attributes_vertex.colors,
+                mesh.attributes_vertex.colors
+            );
+            assert!(packed.len() < raw);
+        }
+
+        // quantized packing is within half a step and much smaller
+        let step = 1e-4;
+        let exact = pack_mesh(&mesh, &PackOptions::default()).unwrap();
+        let options = PackOptions {
+            quantize: Some(step),
+            compression: Compression::Zstd,
+        };
+        let packed = pack_mesh(&mesh, &options).unwrap();
+        assert!(packed.len() * 2 < exact.len());
+        let unpacked = unpack_mesh(&packed).unwrap();
+        assert_eq!(unpacked.faces, mesh.faces);
+        for (a, b) in unpacked.vertices.iter().zip(mesh.vertices.iter()) {
+            assert!((a - b).amax() <= step / 2.0 + 1e-12);
+        }
+        for (a, b) in unpacked.attributes_vertex.normals[0]
+            .iter()
+            .zip(normals.iter())
+        {
+            assert_relative_eq!(*a, b, epsilon = 1e-4);
+        }
+        for (a, b) in unpacked.attributes_vertex.uv[0]
+            .iter()
+            .zip(mesh.attributes_vertex.uv[0].iter())
+        {
+            assert_relative_eq!(*a, b, epsilon = 1e-4);
+        }
+
+        // corrupt or truncated data is an error rather than a panic
+        assert!(unpack_mesh(b"not a mesh").is_err());
+        assert!(unpack_mesh(&exact[..exact.len() / 2]).is_err());
+        let raw_packed = pack_mesh(
+            &mesh,
+            &PackOptions {
+                quantize: Some(step),
+                compression: Compression::None,
+            },
+        )
+        .unwrap();
+        assert!(unpack_mesh(&raw_packed[..raw_packed.len() - 1]).is_err());
+        // a size which no data that short could decompress to
+        let mut huge = exact[..6].to_vec();
+        huge.extend(u32::MAX.to_le_bytes());
+        huge.extend([0; 16]);
+        let error = unpack_mesh(&huge).unwrap_err();
+        assert!(error.to_string().contains("can't decompress"), "{error}");
+        assert!(
+            pack_mesh(
+                &mesh,
+                &PackOptions {
+                    quantize: Some(0.0),
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,1035 @@
+use std::io::BufRead;
+
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector2, Vector3, Vector4};
+
+use crate::attributes::{AttributeArray, Attributes, LoadSource};
+use crate::creation::{Triangulator, triangulate_fan};
+use crate::exchange::validate::{FormatValidator, ValidationReport};
+use crate::exchange::{ExportOptions, MeshFormat};
+use crate::mesh::Trimesh;
+
+/// The type of a value in a PLY file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn from_name(name: &str) -> Result<Self> {
+        match name {
+            "char" | "int8" => Ok(Scalar::I8),
+            "uchar" | "uint8" => Ok(Scalar::U8),
+            "short" | "int16" => Ok(Scalar::I16),
+            "ushort" | "uint16" => Ok(Scalar::U16),
+            "int" | "int32" => Ok(Scalar::I32),
+            "uint" | "uint32" => Ok(Scalar::U32),
+            "float" | "float32" => Ok(Scalar::F32),
+            "double" | "float64" => Ok(Scalar::F64),
+            _ => Err(anyhow!("Unknown PLY property type: `{name}`")),
+        }
+    }
+
+    /// The name of the type written in a header.
+    fn name(&self) -> &'static str {
+        match self {
+            Scalar::I8 => "char",
+            Scalar::U8 => "uchar",
+            Scalar::I16 => "short",
+            Scalar::U16 => "ushort",
+            Scalar::I32 => "int",
+            Scalar::U32 => "uint",
+            Scalar::F32 => "float",
+            Scalar::F64 => "double",
+        }
+    }
+
+    /// The number of bytes of the type in a binary file.
+    fn size(&self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    /// Write a value as this type, where integer types round
+    /// and saturate values that don't fit.
+    fn write(&self, value: f64, encoding: PlyEncoding, options: &ExportOptions, out: &mut Vec<u8>) {
+        match encoding {
+            PlyEncoding::Ascii => {
+                let mut text = String::new();
+                match self {
+                    Scalar::F32 | Scalar::F64 => options.write_float(value, &mut text),
+                    _ => text.push_str(&self.integer(value).to_string()),
+                }
+                out.extend_from_slice(text.as_bytes());
+            }
+            PlyEncoding::BinaryLittleEndian => {
+                let integer = self.integer(value);
+                match self {
+                    Scalar::I8 => out.extend((integer as i8).to_le_bytes()),
+                    Scalar::U8 => out.extend((integer as u8).to_le_bytes()),
+                    Scalar::I16 => out.extend((integer as i16).to_le_bytes()),
+                    Scalar::U16 => out.extend((integer as u16).to_le_bytes()),
+                    Scalar::I32 => out.extend((integer as i32).to_le_bytes()),
+                    Scalar::U32 => out.extend((integer as u32).to_le_bytes()),
+                    Scalar::F32 => out.extend((value as f32).to_le_bytes()),
+                    Scalar::F64 => out.extend(value.to_le_bytes()),
+                }
+            }
+        }
+    }
+
+    /// A value rounded and clamped to the range of an integer type.
+    fn integer(&self, value: f64) -> i64 {
+        let (low, high) = match self {
+            Scalar::I8 => (i8::MIN as f64, i8::MAX as f64),
+            Scalar::U8 => (0.0, u8::MAX as f64),
+            Scalar::I16 => (i16::MIN as f64, i16::MAX as f64),
+            Scalar::U16 => (0.0, u16::MAX as f64),
+            Scalar::I32 => (i32::MIN as f64, i32::MAX as f64),
+            Scalar::U32 => (0.0, u32::MAX as f64),
+            Scalar::F32 | Scalar::F64 => (f64::MIN, f64::MAX),
+        };
+        // `as` maps NaN to zero
+        value.round().clamp(low, high) as i64
+    }
+}
+
+/// A property of an element, where lists store the
+/// type of their length and then of their values.
+struct Property {
+    name: String,
+    kind: Scalar,
+    list: Option<Scalar>,
+}
+
+// how many rows to reserve space for before reading an element
+const PLY_READ_ROWS: usize = 1 << 14;
+
+/// An element declared by the header with the values of
+/// every property for every row, where lists are flattened.
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+    // for each property the values of every row and
+    // for lists the number of values in each row
+    values: Vec<(Vec<f64>, Vec<usize>)>,
+}
+
+// the properties read into vertices, normals, UV coordinates and colors
+const KNOWN_PROPERTIES: [&str; 25] = [
+    "x",
+    "y",
+    "z",
+    "nx",
+    "ny",
+    "nz",
+    "u",
+    "s",
+    "texture_u",
+    "texture_s",
+    "v",
+    "t",
+    "texture_v",
+    "texture_t",
+    "red",
+    "r",
+    "diffuse_red",
+    "green",
+    "g",
+    "diffuse_green",
+    "blue",
+    "b",
+    "diffuse_blue",
+    "alpha",
+    "a",
+];
+
+impl Element {
+    /// The values of a scalar property by name.
+    fn scalar(&self, name: &str) -> Option<&[f64]> {
+        self.properties
+            .iter()
+            .position(|p| p.name == name && p.list.is_none())
+            .map(|i| self.values[i].0.as_slice())
+    }
+
+    /// The values of the first of several scalar properties which exists.
+    fn any_scalar(&self, names: &[&str]) -> Option<&[f64]> {
+        names.iter().find_map(|name| self.scalar(name))
+    }
+
+    /// Append a row read by `Body::read_row` to the values.
+    fn push_row(&mut self, row: &[f64]) {
+        let mut rest = row;
+        for (property, (values, lengths)) in self.properties.iter().zip(self.values.iter_mut()) {
+            match property.list {
+                Some(_) => {
+                    let length = rest[0] as usize;
+                    values.extend_from_slice(&rest[1..=length]);
+                    lengths.push(length);
+                    rest = &rest[length + 1..];
+                }
+                None => {
+                    values.push(rest[0]);
+                    rest = &rest[1..];
+                }
+            }
+        }
+    }
+
+    /// The scalar properties which aren't read into anything else
+    /// with the value of every row.
+    fn custom(&self) -> impl Iterator<Item = (&str, &[f64])> {
+        self.properties
+            .iter()
+            .zip(self.values.iter())
+            .filter(|(p, _)| p.list.is_none() && !KNOWN_PROPERTIES.contains(&p.name.as_str()))
+            .map(|(p, (values, _))| (p.name.as_str(), values.as_slice()))
+    }
+}
+
+/// How the body of a PLY file being read is encoded.
+#[derive(Debug, Clone, Copy)]
+enum BodyFormat {
+    Ascii,
+    Binary { big_endian: bool },
+}
+
+/// Reads values from some of the bytes of the body of a PLY file.
+struct Body<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    format: BodyFormat,
+    // if the bytes run to the end of the file, so an
+    // ASCII number at the very end is whole rather than cut off
+    last: bool,
+}
+
+impl Body<'_> {
+    /// Is there anything besides whitespace left in the bytes.
+    fn has_remaining(&self) -> bool {
+        let rest = &self.bytes[self.position..];
+        match self.format {
+            BodyFormat::Ascii => rest.iter().any(|b| !b.is_ascii_whitespace()),
+            BodyFormat::Binary { .. } => !rest.is_empty(),
+        }
+    }
+
+    /// Read the next value, or `None` if the bytes end before it does.
+    fn read(&mut self, kind: Scalar) -> Result<Option<f64>> {
+        match self.format {
+            BodyFormat::Ascii => {
+                let rest = &self.bytes[self.position..];
+                let Some(start) = rest.iter().position(|b| !b.is_ascii_whitespace()) else {
+                    return Ok(None);
+                };
+                let end = match rest[start..].iter().position(|b| b.is_ascii_whitespace()) {
+                    Some(length) => start + length,
+                    None if self.last => rest.len(),
+                    None => return Ok(None),
+                };
+                let token = String::from_utf8_lossy(&rest[start..end]);
+                let value = token
+                    .parse::<f64>()
+                    .map_err(|_| anyhow!("Invalid number in PLY body: `{token}`"))?;
+                self.position += end;
+                Ok(Some(value))
+            }
+            BodyFormat::Binary { big_endian } => {
+                let size = kind.size();
+                let Some(bytes) = self.bytes.get(self.position..self.position + size) else {
+                    return Ok(None);
+                };
+                let mut raw: [u8; 8] = [0; 8];
+                raw[..size].copy_from_slice(bytes);
+                self.position += size;
+                if big_endian {
+                    raw[..size].reverse();
+                }
+                let [a, b, c, d, ..] = raw;
+                Ok(Some(match kind {
+                    Scalar::I8 => a as i8 as f64,
+                    Scalar::U8 => a as f64,
+                    Scalar::I16 => i16::from_le_bytes([a, b]) as f64,
+                    Scalar::U16 => u16::from_le_bytes([a, b]) as f64,
+                    Scalar::I32 => i32::from_le_bytes([a, b, c, d]) as f64,
+                    Scalar::U32 => u32::from_le_bytes([a, b, c, d]) as f64,
+                    Scalar::F32 => f32::from_le_bytes([a, b, c, d]) as f64,
+                    Scalar::F64 => f64::from_le_bytes(raw),
+                }))
+            }
+        }
+    }
+
+    /// Read every property of the next row, where a list is its length
+    /// followed by its values, returning false if the bytes end first.
+    fn read_row(&mut self, properties: &[Property], row: &mut Vec<f64>) -> Result<bool> {
+        row.clear();
+        for property in properties {
+            let count = match property.list {
+                Some(length) => {
+                    let Some(length) = self.read(length)? else {
+                        return Ok(false);
+                    };
+                    row.push(length);
+                    length as usize
+                }
+                None => 1,
+            };
+            for _ in 0..count {
+                let Some(value) = self.read(property.kind)? else {
+                    return Ok(false);
+                };
+                row.push(value);
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Where the header of a PLY file ends and where its body starts after
+/// the line ending of `end_header`, or `None` if more bytes are needed.
+fn header_end(bytes: &[u8], last: bool) -> Option<(usize, usize)> {
+    const END: &[u8] = b"end_header";
+    let end = bytes.windows(END.len()).position(|w| w == END)? + END.len();
+    let mut start = end;
+    for ending in [b'\r', b'\n'] {
+        match bytes.get(start) {
+            Some(byte) if *byte == ending => start += 1,
+            // the line ending may be in the bytes still to arrive
+            None if !last => return None,
+            _ => {}
+        }
+    }
+    Some((end, start))
+}
+
+/// Parse the header of a PLY file into its elements without any
+/// values and the encoding of its body.
+fn read_header(header: &str) -> Result<(Vec<Element>, BodyFormat)> {
+    let mut lines = header.lines().map(|l| l.trim());
+    if lines.next() != Some("ply") {
+        return Err(anyhow!("PLY file must start with `ply`"));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", kind, ..] => format = Some(kind.to_string()),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid PLY element count: `{line}`"))?,
+                properties: Vec::new(),
+                values: Vec::new(),
+            }),
+            ["property", "list", length, kind, name] => elements
+                .last_mut()
+                .ok_or_else(|| anyhow!("PLY property before any element: `{line}`"))?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    kind: Scalar::from_name(kind)?,
+                    list: Some(Scalar::from_name(length)?),
+                }),
+            ["property", kind, name] => elements
+                .last_mut()
+                .ok_or_else(|| anyhow!("PLY property before any element: `{line}`"))?
+                .properties
+                .push(Property {
+                    name: name.to_string(),
+                    kind: Scalar::from_name(kind)?,
+                    list: None,
+                }),
+            ["comment", ..] | ["obj_info", ..] | ["end_header"] | [] => {}
+            _ => return Err(anyhow!("Invalid PLY header line: `{line}`")),
+        }
+    }
+
+    let format = match format.as_deref() {
+        Some("ascii") => BodyFormat::Ascii,
+        Some(kind @ ("binary_little_endian" | "binary_big_endian")) => BodyFormat::Binary {
+            big_endian: kind == "binary_big_endian",
+        },
+        _ => return Err(anyhow!("Unsupported PLY format: `{format:?}`")),
+    };
+
+    for element in elements.iter_mut() {
+        // don't trust the count in the header for the allocation
+        let reserve = element.count.min(PLY_READ_ROWS);
+        element.values = element
+            .properties
+            .iter()
+            .map(|p| match p.list {
+                Some(_) => (Vec::new(), Vec::with_capacity(reserve)),
+                None => (Vec::with_capacity(reserve), Vec::new()),
+            })
+            .collect();
+    }
+    Ok((elements, format))
+}
+
+/// The elements of a PLY file decoded a row at a time as its
+/// bytes arrive rather than once the whole file is available.
+#[derive(Default)]
+pub(crate) struct PlyStream {
+    // the header up to and including `end_header` once it has arrived
+    header: Option<(String, BodyFormat)>,
+    elements: Vec<Element>,
+    // the element and row the next bytes start
+    element: usize,
+    row: usize,
+    // the values of the row being read
+    scratch: Vec<f64>,
+    // if there was anything after the last declared element
+    trailing: bool,
+}
+
+impl PlyStream {
+    /// Decode the header and every whole row at the start of some
+    /// bytes which follow the ones already decoded, ignoring anything
+    /// after the last element the header declares.
+    ///
+    /// Parameters
+    /// ------------
+    /// bytes
+    ///   The bytes following the ones already consumed.
+    /// last
+    ///   If the bytes run to the end of the file.
+    ///
+    /// Returns
+    /// ------------
+    /// consumed
+    ///   How many bytes were decoded, with the rest of a partial header
+    ///   or row left to be passed again with the bytes after it.
+    pub(crate) fn push(&mut self, bytes: &[u8], last: bool) -> Result<usize> {
+        let mut start = 0;
+        let format = match &self.header {
+            Some((_, format)) => *format,
+            None => {
+                let Some((end, body)) = header_end(bytes, last) else {
+                    if last {
+                        return Err(anyhow!("PLY file has no `end_header`"));
+                    }
+                    return Ok(0);
+                };
+                let header = String::from_utf8_lossy(&bytes[..end]).to_string();
+                let (elements, format) = read_header(&header)?;
+                self.elements = elements;
+                self.header = Some((header, format));
+                start = body;
+                format
+            }
+        };
+
+        let mut body = Body {
+            bytes: &bytes[start..],
+            position: 0,
+            format,
+            last,
+        };
+        while let Some(element) = self.elements.get_mut(self.element) {
+            // an element without properties has nothing to read in any row
+            if self.row == element.count || element.properties.is_empty() {
+                self.element += 1;
+                self.row = 0;
+                continue;
+            }
+            let row = body.position;
+            if !body.read_row(&element.properties, &mut self.scratch)? {
+                return Ok(start + row);
+            }
+            element.push_row(&self.scratch);
+            self.row += 1;
+        }
+        self.trailing |= body.has_remaining();
+        Ok(bytes.len())
+    }
+
+    /// The parsed file, or an error if the header never ended or the
+    /// body ended before every element the header declares.
+    pub(crate) fn finish(self) -> Result<PlyMesh> {
+        let Some((header, _)) = self.header else {
+            return Err(anyhow!("PLY file has no `end_header`"));
+        };
+        if let Some(element) = self.elements.get(self.element) {
+            return Err(anyhow!(
+                "PLY body ended at row {} of {} `{}` elements",
+                self.row,
+                element.count,
+                element.name
+            ));
+        }
+        Ok(PlyMesh {
+            elements: self.elements,
+            header,
+        })
+    }
+}
+
+/// The parsed contents of an ASCII or binary PLY file.
+pub struct PlyMesh {
+    elements: Vec<Element>,
+    // the header up to and including `end_header`
+    header: String,
+}
+
+impl PlyMesh {
+    /// Parse a PLY file in any of its three encodings, reading every
+    /// element and property declared by the header.
+    ///
+    /// Parameters
+    /// ------------
+    /// bytes
+    ///   The contents of the PLY file.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Self>
+    ///   The parsed elements or an error if the header is invalid
+    ///   or the body is shorter than the header declares.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut stream = PlyStream::default();
+        stream.push(bytes, true)?;
+        stream.finish()
+    }
+
+    /// Parse a PLY file from a reader a row at a time, so only the
+    /// values and not the raw file are held in memory.
+    ///
+    /// Parameters
+    /// ------------
+    /// reader
+    ///   The source of the file data.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Self>
+    ///   The parsed elements or an error as for `from_bytes`.
+    pub fn from_reader<R: BufRead>(mut reader: R) -> Result<Self> {
+        let mut stream = PlyStream::default();
+        // the bytes of a header or row split between reads
+        let mut partial = Vec::new();
+        loop {
+            let chunk = reader.fill_buf()?;
+            let length = chunk.len();
+            partial.extend_from_slice(chunk);
+            reader.consume(length);
+            let consumed = stream.push(&partial, length == 0)?;
+            partial.drain(..consumed);
+            if length == 0 {
+                return stream.finish();
+            }
+        }
+    }
+
+    /// Convert the `vertex` and `face` elements into a mesh, triangulating
+    /// polygons and keeping colors, normals and texture coordinates. Any
+    /// other scalar properties of vertices and faces are kept as named
+    /// attributes and other elements are ignored.
+    pub fn into_mesh(self) -> Result<Trimesh> {
+        let element = |name: &str| self.elements.iter().find(|e| e.name == name);
+        let mut vertices = Vec::new();
+        let mut attributes_vertex = Attributes::default();
+        if let Some(vertex) = element("vertex") {
+            let (Some(x), Some(y), Some(z)) =
+                (vertex.scalar("x"), vertex.scalar("y"), vertex.scalar("z"))
+            else {
+                return Err(anyhow!("PLY vertices need `x`, `y` and `z` properties"));
+            };
+            vertices = (0..vertex.count)
+                .map(|i| Point3::new(x[i], y[i], z[i]))
+                .collect();
+            if let (Some(x), Some(y), Some(z)) = (
+                vertex.scalar("nx"),
+                vertex.scalar("ny"),
+                vertex.scalar("nz"),
+            ) {
+                attributes_vertex.normals.push(
+                    (0..vertex.count)
+                        .map(|i| Vector3::new(x[i], y[i], z[i]))
+                        .collect(),
+                );
+            }
+            if let (Some(u), Some(v)) = (
+                vertex.any_scalar(&["u", "s", "texture_u", "texture_s"]),
+                vertex.any_scalar(&["v", "t", "texture_v", "texture_t"]),
+            ) {
+                attributes_vertex.uv.push(
+                    (0..vertex.count)
+                        .map(|i| Vector2::new(u[i], v[i]))
+                        .collect(),
+                );
+            }
+            if let Some(colors) = colors(vertex) {
+                attributes_vertex.colors.push(colors);
+            }
+            for (name, values) in vertex.custom() {
+                attributes_vertex
+                    .named
+                    .insert(name.to_string(), AttributeArray::F64(values.to_vec()));
+            }
+        }
+
+        let mut faces = Vec::new();
+        let mut attributes_face = Attributes::default();
+        if let Some(face) = element("face") {
+            let index = face
+                .properties
+                .iter()
+                .position(|p| {
+                    p.list.is_some() && (p.name == "vertex_indices" || p.name == "vertex_index")
+                })
+                .ok_or_else(|| anyhow!("PLY faces need a `vertex_indices` list"))?;
+            let (values, lengths) = &face.values[index];
+            let face_colors = colors(face);
+            let mut colors = Vec::new();
+            // the row each triangle came from
+            let mut rows = Vec::new();
+            let mut triangulator = Triangulator::new();
+            let mut offset = 0;
+            for (row, length) in lengths.iter().enumerate() {
+                let polygon: Vec<usize> = values[offset..offset + length]
+                    .iter()
+                    .map(|v| match *v {
+                        v if v >= 0.0 && v.fract() == 0.0 => Ok(v as usize),
+                        v => Err(anyhow!("PLY face has an invalid vertex index {v}")),
+                    })
+                    .collect::<Result<_>>()?;
+                offset += length;
+                if let Some(bad) = polygon.iter().find(|i| **i >= vertices.len()) {
+                    return Err(anyhow!(
+                        "PLY face references vertex {bad} of {}",
+                        vertices.len()
+                    ));
+                }
+                let triangles = match polygon.len() {
+                    0..=2 => vec![],
+                    3 => vec![(polygon[0], polygon[1], polygon[2])],
+                    _ => triangulator
+                        .triangulate_3d(&polygon, &[], &vertices)
+                        .unwrap_or_else(|_| triangulate_fan(&polygon)),
+                };
+                if let Some(face_colors) = &face_colors {
+                    colors.extend(std::iter::repeat_n(face_colors[row], triangles.len()));
+                }
+                rows.extend(std::iter::repeat_n(row, triangles.len()));
+                faces.extend(triangles);
+            }
+            if face_colors.is_some() {
+                attributes_face.colors.push(colors);
+            }
+            for (name, values) in face.custom() {
+                attributes_face.named.insert(
+                    name.to_string(),
+                    AttributeArray::F64(rows.iter().map(|r| values[*r]).collect()),
+                );
+            }
+        }
+
+        let mut mesh = Trimesh::new(
+            vertices,
+            faces,
+            Some(attributes_vertex),
+            Some(attributes_face),
+        )?;
+        mesh.source = LoadSource {
+            format: Some(MeshFormat::PLY),
+            header: Some(self.header),
+
+            ..Default::default()
+        };
+        Ok(mesh)
+    }
+}
+
+/// The colors of every row of an element, where integer channels are
+/// 0-255 and float channels are 0.0-1.0 and alpha defaults to opaque.
+fn colors(element: &Element) -> Option<Vec<Vector4<u8>>> {
+    let channels = [
+        element.any_scalar(&["red", "r", "diffuse_red"])?,
+        element.any_scalar(&["green", "g", "diffuse_green"])?,
+        element.any_scalar(&["blue", "b", "diffuse_blue"])?,
+    ];
+    let alpha = element.any_scalar(&["alpha", "a"]);
+    let float = ["red", "r", "diffuse_red"].iter().any(|name| {
+        element
+            .properties
+            .iter()
+            .any(|p| p.name == *name && matches!(p.kind, Scalar::F32 | Scalar::F64))
+    });
+    let convert = |value: f64| {
+        if float {
+            (value * 255.0).round().clamp(0.0, 255.0) as u8
+        } else {
+            value.clamp(0.0, 255.0) as u8
+        }
+    };
+    Some(
+        (0..element.count)
+            .map(|i| {
+                Vector4::new(
+                    convert(channels[0][i]),
+                    convert(channels[1][i]),
+                    convert(channels[2][i]),
+                    alpha.map(|a| convert(a[i])).unwrap_or(255),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// How the body of an exported PLY is encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum PlyEncoding {
+    #[default]
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// A custom property written for every vertex or face of a PLY,
+/// like the quality of each vertex or a scalar field such as curvature.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyProperty {
+    pub name: String,
+    // the type the values are written as
+    pub kind: Scalar,
+    // the value of every vertex or face in order
+    pub values: Vec<f64>,
+}
+
+impl PlyProperty {
+    pub fn new(name: &str, kind: Scalar, values: Vec<f64>) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            values,
+        }
+    }
+}
+
+/// What to write to a PLY besides the vertices, faces and colors.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlyExport {
+    pub encoding: PlyEncoding,
+    // properties written after the position and color of each vertex
+    pub vertex: Vec<PlyProperty>,
+    // properties written after the indices of each face
+    pub face: Vec<PlyProperty>,
+}
+
+// the names of the properties written for every mesh
+const VERTEX_NAMES: [&str; 7] = ["x", "y", "z", "red", "green", "blue", "alpha"];
+const FACE_NAMES: [&str; 1] = ["vertex_indices"];
+
+/// Export a mesh as a PLY with custom vertex and face properties in
+/// either the ASCII or binary little endian encoding, including vertex
+/// colors if there is one for every vertex.
+///
+/// Parameters
+/// ------------
+/// mesh
+///   The mesh to export.
+/// options
+///   How numbers are formatted in the ASCII encoding and if vertices
+///   are quantized.
+/// properties
+///   The encoding and the custom properties to write.
+///
+/// Returns
+/// ------------
+/// Result<Vec<u8>>
+///   The contents of the file, or an error if a property doesn't have
+///   a value for every element or reuses the name of another property.
+pub fn export(mesh: &Trimesh, options: &ExportOptions, properties: &PlyExport) -> Result<Vec<u8>> {
+    for (element, custom, count, reserved) in [
+        (
+            "vertex",
+            &properties.vertex,
+            mesh.vertices.len(),
+            VERTEX_NAMES.as_slice(),
+        ),
+        (
+            "face",
+            &properties.face,
+            mesh.faces.len(),
+            FACE_NAMES.as_slice(),
+        ),
+    ] {
+        for (i, property) in custom.iter().enumerate() {
+            if property.values.len() != count {
+                return Err(anyhow!(
+                    "PLY {element} property `{}` has {} values for {count} elements",
+                    property.name,
+                    property.values.len()
+                ));
+            }
+            if property.name.is_empty() || property.name.contains(char::is_whitespace) {
+                return Err(anyhow!("Invalid PLY property name: `{}`", property.name));
+            }
+            if reserved.contains(&property.name.as_str())
+                || custom[..i].iter().any(|p| p.name == property.name)
+            {
+                return Err(anyhow!(
+                    "PLY {element} property `{}` is already written",
+                    property.name
+                ));
+            }
+        }
+    }
+    Ok(write(mesh, options, properties))
+}
+
+/// Write a PLY after its properties have been checked.
+fn write(mesh: &Trimesh, options: &ExportOptions, properties: &PlyExport) -> Vec<u8> {
+    let count = mesh.vertices.len();
+    let colors = mesh
+        .attributes_vertex
+        .colors
+        .first()
+        .filter(|c| c.len() == count);
+    let encoding = properties.encoding;
+    let binary = encoding == PlyEncoding::BinaryLittleEndian;
+
+    let mut header = String::from("ply\n");
+    header.push_str(if binary {
+        "format binary_little_endian 1.0\n"
+    } else {
+        "format ascii 1.0\n"
+    });
+    header.push_str(&format!("element vertex {count}\n"));
+    header.push_str("property double x\nproperty double y\nproperty double z\n");
+    if colors.is_some() {
+        header.push_str("property uchar red\nproperty uchar green\n");
+        header.push_str("property uchar blue\nproperty uchar alpha\n");
+    }
+    for property in properties.vertex.iter() {
+        header.push_str(&format!(
+            "property {} {}\n",
+            property.kind.name(),
+            property.name
+        ));
+    }
+    header.push_str(&format!("element face {}\n", mesh.faces.len()));
+    header.push_str("property list uchar int vertex_indices\n");
+    for property in properties.face.iter() {
+        header.push_str(&format!(
+            "property {} {}\n",
+            property.kind.name(),
+            property.name
+        ));
+    }
+    header.push_str("end_header\n");
+
+    let mut out = header.into_bytes();
+    out.reserve(count * 64 + mesh.faces.len() * 32);
+    // values in ASCII rows are separated by spaces
+    let separate = |out: &mut Vec<u8>| {
+        if !binary {
+            out.push(b' ');
+        }
+    };
+    let end_row = |out: &mut Vec<u8>| {
+        if !binary {
+            out.push(b'\n');
+        }
+    };
+
+    for (index, vertex) in mesh.vertices.iter().enumerate() {
+        for (i, value) in vertex.iter().enumerate() {
+            if binary {
+                out.extend(value.to_le_bytes());
+            } else {
+                if i > 0 {
+                    out.push(b' ');
+                }
+                let mut text = String::new();
+                options.write_coordinate(*value, &mut text);
+                out.extend_from_slice(text.as_bytes());
+            }
+        }
+        if let Some(colors) = colors {
+            for channel in colors[index].iter() {
+                separate(&mut out);
+                Scalar::U8.write(*channel as f64, encoding, options, &mut out);
+            }
+        }
+        for property in properties.vertex.iter() {
+            separate(&mut out);
+            property
+                .kind
+                .write(property.values[index], encoding, options, &mut out);
+        }
+        end_row(&mut out);
+    }
+    for (index, face) in mesh.faces.iter().enumerate() {
+        Scalar::U8.write(3.0, encoding, options, &mut out);
+        for vertex in [face.0, face.1, face.2] {
+            separate(&mut out);
+            Scalar::I32.write(vertex as f64, encoding, options, &mut out);
+        }
+        for property in properties.face.iter() {
+            separate(&mut out);
+            property
+                .kind
+                .write(property.values[index], encoding, options, &mut out);
+        }
+        end_row(&mut out);
+    }
+    out
+}
+
+/// Export a mesh as an ASCII PLY, including vertex
+/// colors if there is one for every vertex.
+pub fn export_ascii(mesh: &Trimesh, options: &ExportOptions) -> String {
+    // without custom properties the file is always valid text
+    String::from_utf8(write(mesh, options, &PlyExport::default())).unwrap_or_default()
+}
+
+/// Checks a PLY header declares a supported format and valid properties,
+/// the body holds exactly the elements declared, and every face index
+/// refers to a declared vertex.
+pub struct PlyValidator;
+
+impl FormatValidator for PlyValidator {
+    fn validate(&self, bytes: &[u8]) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let mut stream = PlyStream::default();
+        let pushed = stream.push(bytes, true);
+        if stream.header.is_some() {
+            let count = |name: &str| {
+                stream
+                    .elements
+                    .iter()
+                    .find(|e| e.name == name)
+                    .map(|e| e.count)
+            };
+            report.vertices = Some(count("vertex").unwrap_or(0));
+            report.faces = Some(count("face").unwrap_or(0));
+        }
+        let trailing = stream.trailing;
+        let ply = match pushed.and_then(|_| stream.finish()) {
+            Ok(ply) => ply,
+            Err(error) => {
+                report.error(None, error.to_string());
+                return report;
+            }
+        };
+
+        let vertex_count = report.vertices.unwrap_or(0);
+        for element in ply.elements.iter().filter(|e| e.name == "face") {
+            for (property, (values, lengths)) in
+                element.properties.iter().zip(element.values.iter())
+            {
+                if property.name != "vertex_indices" && property.name != "vertex_index" {
+                    continue;
+                }
+                let mut offset = 0;
+                for (row, length) in lengths.iter().enumerate() {
+                    if let Some(bad) = values[offset..offset + length]
+                        .iter()
+                        .find(|v| !(**v >= 0.0 && v.fract() == 0.0 && **v < vertex_count as f64))
+                    {
+                        report.error(
+                            None,
+                            format!("PLY face {row} references vertex {bad} of {vertex_count}"),
+                        );
+                    }
+                    offset += length;
+                }
+            }
+        }
+        if trailing {
+            report.warning(None, "PLY body has data after the declared elements");
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+
+    #[test]
+    fn test_export_properties() {
+        let mut mesh = create_box(&[1.0, 2.0, 3.0]);
+        mesh.attributes_vertex
+            .colors
+            .push(vec![Vector4::new(255, 0, 0, 255); 8]);
+        let quality: Vec<f64> = (0..8).map(|i| i as f64 * 0.25).collect();
+        let label: Vec<f64> = (0..12).map(|i| (i % 3) as f64).collect();
+        let options = ExportOptions::default();
+
+        for encoding in [PlyEncoding::Ascii, PlyEncoding::BinaryLittleEndian] {
+            let properties = PlyExport {
+                encoding,
+                vertex: vec![PlyProperty::new("quality", Scalar::F32, quality.clone())],
+                face: vec![
+                    PlyProperty::new("label", Scalar::U8, label.clone()),
+                    PlyProperty::new("area", Scalar::F64, mesh.faces_area()),
+                ],
+            };
+            let bytes = export(&mesh, &options, &properties).unwrap();
+            let ply = PlyMesh::from_bytes(&bytes).unwrap();
+            let element = |name: &str| ply.elements.iter().find(|e| e.name == name).unwrap();
+            assert_eq!(element("vertex").scalar("quality").unwrap(), quality);
+            assert_eq!(element("face").scalar("label").unwrap(), label);
+            assert_eq!(element("face").scalar("area").unwrap(), mesh.faces_area());
+            // reading a few bytes at a time splits the header and rows
+            let read = PlyMesh::from_reader(std::io::BufReader::with_capacity(7, bytes.as_slice()))
+                .unwrap();
+            assert_eq!(read.header, ply.header);
+            for (a, b) in read.elements.iter().zip(ply.elements.iter()) {
+                assert_eq!(a.values, b.values);
+            }
+
+            let loaded = ply.into_mesh().unwrap();
+            assert_eq!(
+                loaded.attributes_vertex.get::<f64>("quality").unwrap(),
+                quality
+            );
+            assert_eq!(loaded.attributes_face.get::<f64>("label").unwrap(), label);
+            assert_eq!(loaded.vertices, mesh.vertices);
+            assert_eq!(loaded.faces, mesh.faces);
+            assert_eq!(
+                loaded.attributes_vertex.colors,
+                mesh.attributes_vertex.colors
+            );
+        }
+
+        // properties need a value for every element and their own name
+        let bad = |vertex: Vec<PlyProperty>| {
+            let properties = PlyExport {
+                vertex,
+                ..Default::default()
+            };
+            assert!(export(&mesh, &options, &properties).is_err());
+        };
+        bad(vec![PlyProperty::new("quality", Scalar::F32, vec![1.0])]);
+        bad(vec![PlyProperty::new("x", Scalar::F32, quality.clone())]);
+        bad(vec![PlyProperty::new("a b", Scalar::F32, quality.clone())]);
+        bad(vec![
+            PlyProperty::new("q", Scalar::F32, quality.clone()),
+            PlyProperty::new("q", Scalar::U8, quality.clone()),
+        ]);
+        // integer types saturate
+        assert_eq!(Scalar::U8.integer(300.0), 255);
+        assert_eq!(Scalar::I8.integer(-300.0), -128);
+    }
+}
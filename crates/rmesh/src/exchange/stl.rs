@@ -1,11 +1,24 @@
+use std::io::{BufRead, BufReader, Cursor, Read};
+
 use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector4};
 use rayon::prelude::*;
 
-use crate::{attributes::LoadSource, mesh::Trimesh};
+use crate::attributes::{Attributes, DEFAULT_COLOR, Grouping, GroupingKind, LoadSource};
+use crate::exchange::ExportOptions;
+use crate::exchange::validate::{FormatValidator, ValidationReport, numbers};
+use crate::mesh::Trimesh;
+use crate::mesh32::Trimesh32;
 
 pub struct BinaryStl {
     header: String,
+    // the object color written to the header as `COLOR=` for Magics
+    color: Option<Vector4<u8>>,
     triangles: Vec<StlTriangle>,
+    // the name of every `solid` of an ASCII file
+    names: Vec<String>,
+    // the index into `names` of the solid each triangle is in
+    solids: Vec<usize>,
 }
 #[repr(C, packed)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
@@ -19,7 +32,266 @@ struct StlTriangle {
 const STL_TRIANGLE_SIZE: usize = std::mem::size_of::<StlTriangle>(); // the size of a triangle in bytes
 const STL_HEADER_SIZE: usize = 80; // The size of the header in bytes
 const STL_COUNT_SIZE: usize = 4; // The size of the triangle count in bytes
-const STL_DATA_START: usize = STL_HEADER_SIZE + STL_COUNT_SIZE; // the size of the header plus the triangle count
+pub(crate) const STL_DATA_START: usize = STL_HEADER_SIZE + STL_COUNT_SIZE; // the size of the header plus the triangle count
+const STL_READ_CHUNK: usize = 1 << 14; // how many triangles to read at once from a stream
+pub(crate) const STL_SNIFF_SIZE: usize = 512; // how many bytes to check when deciding if a stream is ASCII
+
+/// The length of a binary STL with a number of triangles, or `None` if
+/// it overflows, which a hostile count can where usize is 32 bits.
+fn binary_length(count: usize) -> Option<usize> {
+    count
+        .checked_mul(STL_TRIANGLE_SIZE)?
+        .checked_add(STL_DATA_START)
+}
+
+/// The face grouping of an ASCII STL with the index of the solid each face
+/// is in, labeled with the names of the solids.
+pub const SOLID_GROUPING: &str = "solid";
+/// The face grouping of a binary STL with the raw 16 bit attribute of each
+/// triangle, which some exporters use for colors.
+pub const ATTRIBUTE_GROUPING: &str = "stl_attribute";
+
+/// Does the start of an STL look like an ASCII file, which starts with
+/// `solid` followed by text containing `facet` or `endsolid`, as
+/// some binary files also start their header with `solid`.
+pub(crate) fn is_ascii_start(start: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(start);
+    text.trim_start().starts_with("solid")
+        && text
+            .lines()
+            .skip(1)
+            .any(|line| matches!(line.split_whitespace().next(), Some("facet" | "endsolid")))
+}
+
+/// The header text and triangle count from the start of a binary STL.
+fn binary_header(start: &[u8; STL_DATA_START]) -> (String, usize) {
+    let header = String::from_utf8_lossy(&start[0..STL_HEADER_SIZE])
+        // headers are often padded with zeros rather than spaces
+        .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+        .to_string();
+    let count = u32::from_le_bytes(start[STL_HEADER_SIZE..STL_DATA_START].try_into().unwrap());
+    (header, count as usize)
+}
+
+/// Decode whole binary triangles, adding their vertices and attribute words.
+fn decode_triangles(
+    bytes: &[u8],
+    vertices: &mut Vec<Point3<f64>>,
+    words: &mut Vec<u16>,
+) -> Result<()> {
+    let triangles: &[StlTriangle] = bytemuck::try_cast_slice(bytes)
+        .map_err(|_e| anyhow!("Could not interpret bytes as STL triangles!"))?;
+    for triangle in triangles {
+        let v = triangle.vertices;
+        vertices.extend(
+            v.chunks_exact(3)
+                .map(|c| Point3::new(c[0] as f64, c[1] as f64, c[2] as f64)),
+        );
+        words.push(triangle.attributes);
+    }
+    Ok(())
+}
+
+/// A mesh with a face for every three vertices of a triangle soup.
+fn soup_to_mesh(
+    vertices: Vec<Point3<f64>>,
+    header: String,
+    attributes: Attributes,
+) -> Result<Trimesh> {
+    let faces = (0..vertices.len() / 3)
+        .map(|i| (i * 3, i * 3 + 1, i * 3 + 2))
+        .collect();
+    let mut result = Trimesh::new(vertices, faces, None, Some(attributes))?;
+    result.source = LoadSource {
+        header: if header.is_empty() {
+            None
+        } else {
+            Some(header)
+        },
+        format: Some(super::MeshFormat::STL),
+        ..Default::default()
+    };
+    Ok(result)
+}
+
+/// The triangles of a binary STL decoded as its bytes arrive
+/// rather than once the whole file is available.
+pub(crate) struct BinaryStlStream {
+    header: String,
+    // the number of triangles the header says follow it
+    expected: usize,
+    vertices: Vec<Point3<f64>>,
+    words: Vec<u16>,
+}
+
+impl BinaryStlStream {
+    /// Start decoding a binary STL from its header and triangle count.
+    pub(crate) fn new(start: &[u8; STL_DATA_START]) -> Self {
+        let (header, expected) = binary_header(start);
+        Self {
+            header,
+            expected,
+            vertices: Vec::with_capacity(expected.min(STL_READ_CHUNK) * 3),
+            words: Vec::with_capacity(expected.min(STL_READ_CHUNK)),
+        }
+    }
+
+    /// Decode every whole triangle at the start of some bytes which
+    /// follow the ones already decoded, ignoring anything after the
+    /// last triangle the header promised.
+    ///
+    /// Returns
+    /// ------------
+    /// consumed
+    ///   How many bytes were decoded, with the rest of a partial
+    ///   triangle left to be passed again with the bytes after it.
+    pub(crate) fn push(&mut self, bytes: &[u8]) -> Result<usize> {
+        let remaining = self.expected - self.words.len();
+        let count = (bytes.len() / STL_TRIANGLE_SIZE).min(remaining);
+        decode_triangles(
+            &bytes[..count * STL_TRIANGLE_SIZE],
+            &mut self.vertices,
+            &mut self.words,
+        )?;
+        Ok(if count == remaining {
+            bytes.len()
+        } else {
+            count * STL_TRIANGLE_SIZE
+        })
+    }
+
+    /// The mesh of every triangle, or an error if fewer
+    /// triangles arrived than the header promised.
+    pub(crate) fn finish(self) -> Result<Trimesh> {
+        if self.words.len() < self.expected {
+            return Err(anyhow!(
+                "STL file is truncated: header specified {} triangles",
+                self.expected
+            ));
+        }
+        soup_to_mesh(
+            self.vertices,
+            self.header,
+            face_attributes(Vec::new(), Vec::new(), self.words),
+        )
+    }
+}
+
+/// The facets of an ASCII STL parsed a line at a time
+/// rather than once the whole file is available.
+#[derive(Default)]
+pub(crate) struct AsciiStlStream {
+    // the first line of the file
+    header: Option<String>,
+    vertices: Vec<Point3<f64>>,
+    // the name of every solid and the solid of every facet
+    names: Vec<String>,
+    solids: Vec<usize>,
+    // the vertices of the facet currently being read
+    facet: Vec<Point3<f64>>,
+}
+
+impl AsciiStlStream {
+    /// Parse the next line of the file, which only
+    /// keeps the `vertex`, `endfacet` and `solid` lines.
+    pub(crate) fn push_line(&mut self, line: &str) -> Result<()> {
+        if self.header.is_none() {
+            self.header = Some(line.to_string());
+        }
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("vertex") => {
+                let values = parts
+                    .take(3)
+                    .map(|v| v.parse::<f32>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| anyhow!("STL vertex is not numeric: `{line}`"))?;
+                if let [x, y, z] = values.as_slice() {
+                    self.facet
+                        .push(Point3::new(*x as f64, *y as f64, *z as f64));
+                }
+            }
+            Some("endfacet") => {
+                // only keep complete triangles like the in-memory parser
+                if self.facet.len() == 3 {
+                    self.vertices.append(&mut self.facet);
+                    self.solids.push(self.names.len().saturating_sub(1));
+                }
+                self.facet.clear();
+            }
+            Some("solid") => self.names.push(solid_name(line)),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The mesh of every complete facet.
+    pub(crate) fn finish(self) -> Result<Trimesh> {
+        soup_to_mesh(
+            self.vertices,
+            self.header.unwrap_or_default(),
+            face_attributes(self.names, self.solids, Vec::new()),
+        )
+    }
+}
+
+/// The face attributes of a loaded STL: the solids of an ASCII file and
+/// the attribute words of a binary file if any of them are set.
+fn face_attributes(names: Vec<String>, solids: Vec<usize>, words: Vec<u16>) -> Attributes {
+    let mut attributes = Attributes::default();
+    if !names.is_empty() {
+        attributes.groupings.push(Grouping {
+            name: SOLID_GROUPING.to_string(),
+            kind: GroupingKind::GroupingIndex,
+            indices: solids,
+            labels: names,
+        });
+    }
+    if words.iter().any(|w| *w != 0) {
+        attributes.groupings.push(Grouping {
+            name: ATTRIBUTE_GROUPING.to_string(),
+            kind: GroupingKind::Unspecified,
+            indices: words.into_iter().map(usize::from).collect(),
+            labels: Vec::new(),
+        });
+    }
+    attributes
+}
+
+/// The name of a solid from its `solid` line.
+fn solid_name(line: &str) -> String {
+    line.trim()
+        .strip_prefix("solid")
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// How a face color is packed into the 16 bit attribute of a binary STL
+/// triangle, as 5 bits for each of red, green and blue. The two common
+/// conventions order the channels in opposite directions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StlColors {
+    // VisCAM and SolidView: blue in the lowest bits and red in bits 10 to 14,
+    // with bit 15 set if the color is valid
+    VisCam,
+    // Materialise Magics: red in the lowest bits and blue in bits 10 to 14,
+    // with bit 15 clear if the face has its own color rather than the
+    // object color from `COLOR=` in the header
+    Magics,
+}
+
+impl StlColors {
+    /// Pack an RGBA color into a triangle attribute, dropping alpha.
+    pub fn encode(&self, color: &Vector4<u8>) -> u16 {
+        // scale each channel from 8 bits to 5 rounding to nearest
+        let [r, g, b] = [0, 1, 2].map(|i| ((color[i] as u32 * 31 + 127) / 255) as u16);
+        match self {
+            StlColors::VisCam => 0x8000 | (r << 10) | (g << 5) | b,
+            StlColors::Magics => (b << 10) | (g << 5) | r,
+        }
+    }
+}
 
 impl BinaryStl {
     /// Parse a binary or ASCII STL file from the raw bytes. Note that binary STL files
@@ -41,14 +313,15 @@ impl BinaryStl {
         }
 
         let header = String::from_utf8_lossy(&bytes[0..STL_HEADER_SIZE])
-            .trim()
+            // headers are often padded with zeros rather than spaces
+            .trim_matches(|c: char| c == '\0' || c.is_whitespace())
             .to_string();
         // the number of triangles is stored as a little-endian u32 at bytes 80-84
         let triangle_count =
             u32::from_le_bytes(bytes[STL_HEADER_SIZE..STL_DATA_START].try_into().unwrap());
 
         // if our passed bytes are not a
-        if bytes.len() != STL_DATA_START + (triangle_count as usize) * STL_TRIANGLE_SIZE {
+        if binary_length(triangle_count as usize) != Some(bytes.len()) {
             // this may be an ASCII STL file
             return Self::parse_ascii_stl(bytes);
             // return Err(anyhow::anyhow!("STL file size does not match header"));
@@ -60,7 +333,10 @@ impl BinaryStl {
 
         Ok(Self {
             header,
+            color: None,
             triangles: triangles.to_vec(),
+            names: Vec::new(),
+            solids: Vec::new(),
         })
     }
 
@@ -77,11 +353,26 @@ impl BinaryStl {
         // split the text into chunks between the `facet` and `endfacet` keywords
         let chunks = text.split("facet").collect::<Vec<_>>();
 
+        // the `solid` lines come between facets so count them in order
+        let mut names = Vec::new();
+        let chunk_solid: Vec<usize> = chunks
+            .iter()
+            .map(|chunk| {
+                for line in chunk.lines() {
+                    if line.split_whitespace().next() == Some("solid") {
+                        names.push(solid_name(line));
+                    }
+                }
+                names.len().saturating_sub(1)
+            })
+            .collect();
+
         //println!("chunks: {:?}", chunks.clone());
 
-        let triangles = chunks
+        let (triangles, solids): (Vec<StlTriangle>, Vec<usize>) = chunks
             .par_iter()
-            .map(|chunk| {
+            .zip(chunk_solid.par_iter())
+            .map(|(chunk, solid)| {
                 let mut normal = [0.0f32; 3];
                 let mut vertices = [0.0f32; 9];
                 let mut vertex_count = 0;
@@ -118,20 +409,253 @@ impl BinaryStl {
                 }
 
                 if vertex_count == 3 {
-                    Some(StlTriangle {
-                        normal,
-                        vertices,
-                        attributes: 0,
-                    })
+                    Some((
+                        StlTriangle {
+                            normal,
+                            vertices,
+                            attributes: 0,
+                        },
+                        *solid,
+                    ))
                 } else {
                     None
                 }
             })
             .filter_map(|t| t)
-            .collect::<Vec<_>>();
+            .unzip();
         //println!("triangles: {:?}", triangles.clone());
 
-        Ok(Self { header, triangles })
+        Ok(Self {
+            header,
+            color: None,
+            triangles,
+            names,
+            solids,
+        })
+    }
+
+    /// Read a binary or ASCII STL file from a stream directly into a mesh
+    /// without holding the raw file in memory, so peak memory is roughly
+    /// the size of the resulting mesh.
+    ///
+    /// Since the length of a stream isn't known ahead of time, a file is
+    /// parsed as ASCII if it starts with `solid` and is followed by text
+    /// containing `facet` or `endsolid`, and as binary otherwise.
+    ///
+    /// Parameters
+    /// ------------
+    /// reader
+    ///   The source of the STL data.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Trimesh>
+    ///   The loaded mesh or an error if the stream was invalid or truncated.
+    pub fn mesh_from_reader<R: Read>(mut reader: R) -> Result<Trimesh> {
+        // read the start of the file to decide if it is ASCII
+        let mut start = Vec::with_capacity(STL_SNIFF_SIZE);
+        reader
+            .by_ref()
+            .take(STL_SNIFF_SIZE as u64)
+            .read_to_end(&mut start)?;
+        let is_ascii = is_ascii_start(&start);
+
+        let reader = Cursor::new(start).chain(reader);
+        if is_ascii {
+            let mut stream = AsciiStlStream::default();
+            for line in BufReader::new(reader).lines() {
+                stream.push_line(&line?)?;
+            }
+            return stream.finish();
+        }
+        let (vertices, header, attributes) = Self::read_binary(reader)?;
+        soup_to_mesh(vertices, header, attributes)
+    }
+
+    /// Read the triangles of a binary STL a chunk at a time.
+    fn read_binary<R: Read>(mut reader: R) -> Result<(Vec<Point3<f64>>, String, Attributes)> {
+        let mut start = [0u8; STL_DATA_START];
+        reader
+            .read_exact(&mut start)
+            .map_err(|_| anyhow!("STL file too short"))?;
+        let (header, triangle_count) = binary_header(&start);
+
+        // don't trust the count in the header for the allocation
+        let mut vertices = Vec::with_capacity(triangle_count.min(STL_READ_CHUNK) * 3);
+        let mut words = Vec::with_capacity(triangle_count.min(STL_READ_CHUNK));
+        let mut buffer = vec![0u8; STL_READ_CHUNK * STL_TRIANGLE_SIZE];
+        let mut remaining = triangle_count;
+        while remaining > 0 {
+            let count = remaining.min(STL_READ_CHUNK);
+            let chunk = &mut buffer[..count * STL_TRIANGLE_SIZE];
+            reader.read_exact(chunk).map_err(|_| {
+                anyhow!("STL file is truncated: header specified {triangle_count} triangles")
+            })?;
+            decode_triangles(chunk, &mut vertices, &mut words)?;
+            remaining -= count;
+        }
+        Ok((
+            vertices,
+            header,
+            face_attributes(Vec::new(), Vec::new(), words),
+        ))
+    }
+
+    /// Parse every solid of an STL file into a separate mesh, since
+    /// ASCII files may contain several `solid` blocks which `to_mesh`
+    /// would merge. A binary file is always a single solid.
+    ///
+    /// Parameters
+    /// ------------
+    /// bytes
+    ///   Raw bytes of the STL file.
+    ///
+    /// Returns
+    /// ------------
+    /// solids
+    ///   The name from the `solid` line and the mesh of every
+    ///   solid in the order they appear, or an error.
+    pub fn solids_from_bytes(bytes: &[u8]) -> Result<Vec<(String, Trimesh)>> {
+        let stl = Self::from_bytes(bytes)?;
+        let text = String::from_utf8_lossy(bytes);
+        let is_binary = bytes.len() >= STL_DATA_START
+            && binary_length(
+                u32::from_le_bytes(bytes[STL_HEADER_SIZE..STL_DATA_START].try_into()?) as usize,
+            ) == Some(bytes.len());
+        if is_binary {
+            return Ok(vec![(String::new(), stl.to_mesh()?)]);
+        }
+
+        // the byte offset of every line starting a solid
+        let mut starts = Vec::new();
+        let mut offset = 0;
+        for line in text.split_inclusive('\n') {
+            if line.split_whitespace().next() == Some("solid") {
+                starts.push(offset);
+            }
+            offset += line.len();
+        }
+        if starts.len() < 2 {
+            let name = stl.header.trim().strip_prefix("solid").unwrap_or_default();
+            return Ok(vec![(name.trim().to_string(), stl.to_mesh()?)]);
+        }
+        starts.push(text.len());
+        starts
+            .windows(2)
+            .map(|range| {
+                let solid = Self::parse_ascii_stl(text[range[0]..range[1]].as_bytes())?;
+                let name = solid
+                    .header
+                    .trim()
+                    .strip_prefix("solid")
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string();
+                Ok((name, solid.to_mesh()?))
+            })
+            .collect()
+    }
+
+    /// Convert a mesh into binary STL triangles, with the normal of every
+    /// face and its color packed into the attribute if the mesh has a
+    /// color for every face, or otherwise the attribute loaded from an
+    /// STL so files with colors in a convention we don't decode round-trip.
+    ///
+    /// Parameters
+    /// ------------
+    /// mesh
+    ///   The mesh to convert.
+    /// colors
+    ///   How to pack face colors into the triangle attributes.
+    ///
+    /// Returns
+    /// ------------
+    /// stl
+    ///   The triangles with the header of the mesh if it was loaded from an STL.
+    pub fn from_mesh(mesh: &Trimesh, colors: StlColors) -> Self {
+        let face_colors = mesh
+            .attributes_face
+            .colors
+            .first()
+            .filter(|c| c.len() == mesh.faces.len());
+        let words = mesh
+            .attributes_face
+            .grouping(ATTRIBUTE_GROUPING)
+            .filter(|g| g.indices.len() == mesh.faces.len());
+        let triangles = mesh
+            .faces
+            .par_iter()
+            .zip(mesh.faces_cross().par_iter())
+            .enumerate()
+            .map(|(index, (face, cross))| {
+                let normal = cross.try_normalize(f64::EPSILON).unwrap_or_default();
+                let mut vertices = [0.0f32; 9];
+                for (corner, vertex) in [face.0, face.1, face.2].iter().enumerate() {
+                    for axis in 0..3 {
+                        vertices[corner * 3 + axis] = mesh.vertices[*vertex][axis] as f32;
+                    }
+                }
+                StlTriangle {
+                    normal: [normal.x as f32, normal.y as f32, normal.z as f32],
+                    vertices,
+                    attributes: match (face_colors, words) {
+                        (Some(c), _) => colors.encode(&c[index]),
+                        (None, Some(w)) => w.indices[index] as u16,
+                        (None, None) => 0,
+                    },
+                }
+            })
+            .collect();
+
+        Self {
+            header: mesh.source.header.clone().unwrap_or_default(),
+            // Magics only reads face colors if the header has an object color
+            color: (face_colors.is_some() && colors == StlColors::Magics).then_some(DEFAULT_COLOR),
+            triangles,
+            names: Vec::new(),
+            solids: Vec::new(),
+        }
+    }
+
+    /// Write the triangles as a binary STL file.
+    ///
+    /// Returns
+    /// ------------
+    /// bytes
+    ///   The 80 byte header padded with zeros, the little-endian
+    ///   triangle count and 50 bytes for every triangle.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(STL_DATA_START + self.triangles.len() * STL_TRIANGLE_SIZE);
+        if let Some(color) = self.color {
+            bytes.extend_from_slice(b"COLOR=");
+            bytes.extend_from_slice(color.as_slice());
+            bytes.push(b' ');
+        }
+        bytes.extend_from_slice(self.header.as_bytes());
+        bytes.resize(STL_HEADER_SIZE, 0);
+        bytes.extend_from_slice(&(self.triangles.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(bytemuck::cast_slice(&self.triangles));
+        bytes
+    }
+
+    /// Convert the triangles to a single precision mesh without
+    /// converting the vertices through double precision.
+    pub fn to_mesh32(&self) -> Trimesh32 {
+        Trimesh32 {
+            vertices: self
+                .triangles
+                .iter()
+                .flat_map(|t| {
+                    let v = t.vertices;
+                    [0, 3, 6].map(|i| Point3::new(v[i], v[i + 1], v[i + 2]))
+                })
+                .collect(),
+            faces: (0..self.triangles.len() as u32)
+                .map(|i| [i * 3, i * 3 + 1, i * 3 + 2])
+                .collect(),
+            ..Default::default()
+        }
     }
 
     pub fn to_mesh(&self) -> Result<Trimesh> {
@@ -157,17 +681,170 @@ impl BinaryStl {
         let source = LoadSource {
             header,
             format: Some(super::MeshFormat::STL),
+            ..Default::default()
         };
 
         let mut result = Trimesh::from_slice(&vertices, &faces)?;
         result.source = source;
+        result.attributes_face = face_attributes(
+            self.names.clone(),
+            self.solids.clone(),
+            self.triangles.iter().map(|t| t.attributes).collect(),
+        );
         Ok(result)
     }
 }
 
+/// Export a mesh as an ASCII STL, which is a triangle soup
+/// with a normal written for every face.
+pub fn export_ascii(mesh: &Trimesh, options: &ExportOptions) -> String {
+    let name = mesh
+        .source
+        .header
+        .as_deref()
+        .and_then(|h| h.strip_prefix("solid"))
+        .map(|h| h.trim())
+        .unwrap_or_default();
+
+    let mut out = String::with_capacity(mesh.faces.len() * 256);
+    out.push_str(&format!("solid {name}\n"));
+    for (face, normal) in mesh.faces.iter().zip(mesh.face_normals().iter()) {
+        out.push_str("facet normal");
+        for value in normal.iter() {
+            out.push(' ');
+            options.write_float(*value, &mut out);
+        }
+        out.push_str("\n  outer loop\n");
+        for index in [face.0, face.1, face.2] {
+            out.push_str("    vertex");
+            for value in mesh.vertices[index].iter() {
+                out.push(' ');
+                options.write_coordinate(*value, &mut out);
+            }
+            out.push('\n');
+        }
+        out.push_str("  endloop\nendfacet\n");
+    }
+    out.push_str(&format!("endsolid {name}\n"));
+    out
+}
+
+/// Export a mesh as a binary STL, which is much smaller and faster
+/// to read than ASCII and can carry a color for every face.
+///
+/// Parameters
+/// ------------
+/// mesh
+///   The mesh to export.
+/// colors
+///   How to pack face colors if the mesh has them.
+///
+/// Returns
+/// ------------
+/// bytes
+///   The binary STL file.
+pub fn export_binary(mesh: &Trimesh, colors: StlColors) -> Vec<u8> {
+    BinaryStl::from_mesh(mesh, colors).to_bytes()
+}
+
+/// Checks a binary STL is exactly as long as its triangle count requires
+/// and an ASCII STL has three numeric vertices in every facet.
+pub struct StlValidator;
+
+impl FormatValidator for StlValidator {
+    fn validate(&self, bytes: &[u8]) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let mut declared = None;
+        if bytes.len() >= STL_DATA_START {
+            let count =
+                u32::from_le_bytes(bytes[STL_HEADER_SIZE..STL_DATA_START].try_into().unwrap())
+                    as usize;
+            if binary_length(count) == Some(bytes.len()) {
+                report.vertices = Some(count * 3);
+                report.faces = Some(count);
+                if let Ok(triangles) =
+                    bytemuck::try_cast_slice::<u8, StlTriangle>(&bytes[STL_DATA_START..])
+                {
+                    let bad = triangles
+                        .iter()
+                        .filter(|t| {
+                            // copy the field out as the struct is packed
+                            let vertices = t.vertices;
+                            vertices.iter().any(|v| !v.is_finite())
+                        })
+                        .count();
+                    if bad > 0 {
+                        report.warning(None, format!("{bad} triangles have non-finite vertices"));
+                    }
+                }
+                return report;
+            }
+            declared = Some(count);
+        }
+
+        let text = String::from_utf8_lossy(bytes);
+        if !text.trim_start().starts_with("solid") {
+            report.error(
+                None,
+                match declared.map(|count| (count, binary_length(count))) {
+                    Some((count, Some(needed))) => format!(
+                        "Binary STL declares {count} triangles needing {needed} bytes but has {}",
+                        bytes.len()
+                    ),
+                    Some((count, None)) => {
+                        format!("Binary STL declares {count} triangles which is too many to load")
+                    }
+                    None => "STL file is too short to be binary and isn't ASCII".to_string(),
+                },
+            );
+            return report;
+        }
+
+        // the number of vertices in the current facet
+        let mut corners: Option<usize> = None;
+        let mut facets = 0;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = Some(index + 1);
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("facet") => {
+                    if corners.is_some() {
+                        report.error(line_number, "Facet starts before the last one ended");
+                    }
+                    corners = Some(0);
+                }
+                Some("vertex") => {
+                    if numbers(words).is_none_or(|v| v.len() != 3) {
+                        report.error(line_number, "Vertex must have three numbers");
+                    }
+                    match corners.as_mut() {
+                        Some(count) => *count += 1,
+                        None => report.error(line_number, "Vertex is outside of a facet"),
+                    }
+                }
+                Some("endfacet") => match corners.take() {
+                    Some(3) => facets += 1,
+                    Some(count) => {
+                        report.error(line_number, format!("Facet has {count} vertices not 3"))
+                    }
+                    None => report.error(line_number, "Facet ends without starting"),
+                },
+                _ => {}
+            }
+        }
+        if corners.is_some() {
+            report.error(None, "STL ends inside a facet");
+        }
+        report.vertices = Some(facets * 3);
+        report.faces = Some(facets);
+        report
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
+    use super::*;
     use crate::exchange::{MeshFormat, load_mesh};
 
     #[test]
@@ -178,6 +855,10 @@ mod tests {
 
         assert_eq!(mesh.vertices.len(), 36);
         assert_eq!(mesh.faces.len(), 12);
+
+        // the length of a file with any count is found without overflowing
+        assert_eq!(binary_length(12), Some(stl_data.len()));
+        assert_eq!(binary_length(usize::MAX / 10), None);
     }
 
     #[test]
@@ -188,4 +869,103 @@ mod tests {
         //assert_eq!(mesh.vertices.len(), 36);
         assert_eq!(mesh.faces.len(), 24);
     }
+
+    #[test]
+    fn test_export_binary() {
+        let data = include_bytes!("../../../../test/data/unit_cube.STL");
+        let mut mesh = load_mesh(data, MeshFormat::STL).unwrap();
+        let exported = export_binary(&mesh, StlColors::VisCam);
+        assert_eq!(exported.len(), 84 + 12 * 50);
+        let loaded = load_mesh(&exported, MeshFormat::STL).unwrap();
+        assert_eq!(loaded.vertices, mesh.vertices);
+        assert_eq!(loaded.faces, mesh.faces);
+        assert_eq!(loaded.source.header, mesh.source.header);
+
+        // the normals point out of the cube
+        let stl = BinaryStl::from_bytes(&exported).unwrap();
+        for (triangle, normal) in stl.triangles.iter().zip(mesh.face_normals()) {
+            let written = triangle.normal;
+            assert!((written[2] as f64 - normal.z).abs() < 1e-6);
+            assert_eq!({ triangle.attributes }, 0);
+        }
+
+        // pure red in each convention
+        let red = Vector4::new(255, 0, 0, 255);
+        assert_eq!(StlColors::VisCam.encode(&red), 0x8000 | (31 << 10));
+        assert_eq!(StlColors::Magics.encode(&red), 31);
+        mesh.attributes_face.colors.push(vec![red; 12]);
+        let stl = BinaryStl::from_mesh(&mesh, StlColors::VisCam);
+        assert!(stl.triangles.iter().all(|t| { t.attributes } == 0xfc00));
+        let magics = export_binary(&mesh, StlColors::Magics);
+        assert!(magics.starts_with(b"COLOR="));
+        assert_eq!(u16::from_le_bytes([magics[84 + 48], magics[84 + 49]]), 31);
+
+        assert_eq!(
+            export_binary(&Trimesh::default(), StlColors::VisCam).len(),
+            84
+        );
+    }
+
+    #[test]
+    fn test_stl_face_attributes() {
+        // each solid of an ASCII file is a labeled group of faces
+        let data = include_bytes!("../../../../test/data/two_objects_mixed_case_names.stl");
+        let mesh = load_mesh(data, MeshFormat::STL).unwrap();
+        let solids = mesh.attributes_face.grouping(SOLID_GROUPING).unwrap();
+        assert_eq!(solids.labels.len(), 2);
+        assert_eq!(solids.indices.len(), mesh.faces.len());
+        assert_eq!(solids.indices[0], 0);
+        assert_eq!(solids.indices[mesh.faces.len() - 1], 1);
+        assert!(mesh.attributes_face.grouping(ATTRIBUTE_GROUPING).is_none());
+
+        // attribute words of a binary file survive a round trip
+        let data = include_bytes!("../../../../test/data/unit_cube.STL");
+        let mut mesh = load_mesh(data, MeshFormat::STL).unwrap();
+        assert!(mesh.attributes_face.groupings.is_empty());
+        mesh.attributes_face.groupings.push(Grouping {
+            name: ATTRIBUTE_GROUPING.to_string(),
+            kind: GroupingKind::Unspecified,
+            indices: (0..12).map(|i| 0x8000 | i).collect(),
+            labels: Vec::new(),
+        });
+        let loaded = load_mesh(&export_binary(&mesh, StlColors::VisCam), MeshFormat::STL).unwrap();
+        assert_eq!(
+            loaded.attributes_face.groupings,
+            mesh.attributes_face.groupings
+        );
+    }
+
+    #[test]
+    fn test_stl_reader() {
+        for data in [
+            include_bytes!("../../../../test/data/unit_cube.STL").as_slice(),
+            include_bytes!("../../../../test/data/two_objects_mixed_case_names.stl").as_slice(),
+        ] {
+            let expected = load_mesh(data, MeshFormat::STL).unwrap();
+            let mesh = BinaryStl::mesh_from_reader(data).unwrap();
+            assert_eq!(mesh.vertices, expected.vertices);
+            assert_eq!(mesh.faces, expected.faces);
+            assert_eq!(mesh.source.header, expected.source.header);
+            assert_eq!(
+                mesh.attributes_face.groupings,
+                expected.attributes_face.groupings
+            );
+        }
+
+        // a binary STL with a `solid` header should still be read as binary
+        let mut binary = include_bytes!("../../../../test/data/unit_cube.STL").to_vec();
+        binary[..5].copy_from_slice(b"solid");
+        assert_eq!(
+            BinaryStl::mesh_from_reader(binary.as_slice())
+                .unwrap()
+                .faces
+                .len(),
+            12
+        );
+
+        // a truncated binary STL should error rather than return a partial mesh
+        binary.truncate(binary.len() - 10);
+        assert!(BinaryStl::mesh_from_reader(binary.as_slice()).is_err());
+        assert!(BinaryStl::mesh_from_reader(&b"short"[..]).is_err());
+    }
 }
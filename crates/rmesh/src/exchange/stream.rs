@@ -0,0 +1,322 @@
+use crate::error::RmeshError;
+use crate::exchange::obj::ObjMesh;
+use crate::exchange::ply::PlyStream;
+use crate::exchange::stl::{
+    AsciiStlStream, BinaryStlStream, STL_DATA_START, STL_SNIFF_SIZE, is_ascii_start,
+};
+use crate::exchange::{MeshFormat, load_mesh};
+use crate::mesh::Trimesh;
+
+/// What a `MeshStream` has parsed of the bytes pushed so far.
+enum StreamState {
+    // the start of an STL held until it can be told apart as ASCII or binary
+    Sniffing(Vec<u8>),
+    // an OBJ parsed a batch of whole lines at a time
+    Obj {
+        mesh: Box<ObjMesh>,
+        // the bytes after the last whole line
+        partial: Vec<u8>,
+    },
+    // an ASCII STL parsed a batch of whole lines at a time
+    AsciiStl {
+        stream: Box<AsciiStlStream>,
+        // the bytes after the last whole line
+        partial: Vec<u8>,
+    },
+    // a binary STL decoded a triangle at a time
+    BinaryStl {
+        stream: BinaryStlStream,
+        // the bytes of a triangle split between pushes
+        partial: Vec<u8>,
+    },
+    // a PLY decoded a row at a time once its header has arrived
+    Ply {
+        stream: Box<PlyStream>,
+        // the bytes of the header or a row split between pushes
+        partial: Vec<u8>,
+    },
+    // a format which can't be parsed incrementally, held until the end
+    Buffered(Vec<u8>),
+}
+
+/// Load a mesh from bytes pushed as they arrive, such as the chunks of a
+/// file which is still downloading, so the work of parsing is spread over
+/// the download rather than all happening once it's done.
+///
+/// OBJ files, STL files and PLY files are parsed as their lines,
+/// triangles and rows arrive, while other formats are held until `finish`
+/// and then loaded with `load_mesh`. Files referenced by an OBJ like its materials aren't
+/// loaded as there's nowhere to load them from.
+pub struct MeshStream {
+    format: MeshFormat,
+    state: StreamState,
+    // the number of bytes pushed so far
+    received: usize,
+}
+
+impl MeshStream {
+    /// Start loading a mesh in a format.
+    pub fn new(file_type: MeshFormat) -> Self {
+        let state = match file_type {
+            MeshFormat::STL => StreamState::Sniffing(Vec::new()),
+            MeshFormat::OBJ => StreamState::Obj {
+                mesh: Box::default(),
+                partial: Vec::new(),
+            },
+            MeshFormat::PLY => StreamState::Ply {
+                stream: Box::default(),
+                partial: Vec::new(),
+            },
+            _ => StreamState::Buffered(Vec::new()),
+        };
+        Self {
+            format: file_type,
+            state,
+            received: 0,
+        }
+    }
+
+    /// The format being loaded.
+    pub fn format(&self) -> &MeshFormat {
+        &self.format
+    }
+
+    /// How many bytes have been pushed so far.
+    pub fn received(&self) -> usize {
+        self.received
+    }
+
+    /// Parse the next bytes of the file as far as possible, holding on
+    /// to any which can't be parsed until more arrive. A stream which
+    /// has returned an error shouldn't be pushed to again.
+    ///
+    /// Parameters
+    /// ------------
+    /// chunk
+    ///   The bytes following the ones already pushed.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<(), RmeshError>
+    ///   An error if the bytes so far can't be the start of a valid file.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), RmeshError> {
+        self.received += chunk.len();
+        match &mut self.state {
+            StreamState::Sniffing(start) => {
+                start.extend_from_slice(chunk);
+                if start.len() >= STL_SNIFF_SIZE {
+                    let start = std::mem::take(start);
+                    self.state = stl_state(start);
+                    return self.drain();
+                }
+            }
+            StreamState::Obj { partial, .. }
+            | StreamState::AsciiStl { partial, .. }
+            | StreamState::BinaryStl { partial, .. }
+            | StreamState::Ply { partial, .. } => {
+                partial.extend_from_slice(chunk);
+                return self.drain();
+            }
+            StreamState::Buffered(data) => data.extend_from_slice(chunk),
+        }
+        Ok(())
+    }
+
+    /// Parse whatever is whole in the bytes held by the stream.
+    fn drain(&mut self) -> Result<(), RmeshError> {
+        let format = self.format.clone();
+        match &mut self.state {
+            StreamState::Obj { mesh, partial } => {
+                let Some(end) = partial.iter().rposition(|b| *b == b'\n') else {
+                    return Ok(());
+                };
+                // a continued line has to wait for the line it continues onto
+                if partial[..end].trim_ascii_end().ends_with(b"\\") {
+                    return Ok(());
+                }
+                let lines = String::from_utf8_lossy(&partial[..=end]).into_owned();
+                mesh.extend_lines(lines.lines().collect(), &|_| None)
+                    .map_err(|e| RmeshError::from_load(&format, e))?;
+                partial.drain(..=end);
+            }
+            StreamState::AsciiStl { stream, partial } => {
+                let Some(end) = partial.iter().rposition(|b| *b == b'\n') else {
+                    return Ok(());
+                };
+                for line in String::from_utf8_lossy(&partial[..=end]).lines() {
+                    stream
+                        .push_line(line)
+                        .map_err(|e| RmeshError::from_load(&format, e))?;
+                }
+                partial.drain(..=end);
+            }
+            StreamState::BinaryStl { stream, partial } => {
+                let consumed = stream
+                    .push(partial)
+                    .map_err(|e| RmeshError::from_load(&format, e))?;
+                partial.drain(..consumed);
+            }
+            StreamState::Ply { stream, partial } => {
+                let consumed = stream
+                    .push(partial, false)
+                    .map_err(|e| RmeshError::from_load(&format, e))?;
+                partial.drain(..consumed);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Parse the rest of the file once every byte has been pushed.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Trimesh, RmeshError>
+    ///   The loaded mesh, or an error as for `load_mesh` including
+    ///   if the file stopped before it was complete.
+    pub fn finish(self) -> Result<Trimesh, RmeshError> {
+        let state = match self.state {
+            // a file shorter than the sniffed start
+            StreamState::Sniffing(start) => stl_state(start),
+            state => state,
+        };
+        let loaded = match state {
+            StreamState::Obj { mut mesh, partial } => mesh
+                .extend_lines(String::from_utf8_lossy(&partial).lines().collect(), &|_| {
+                    None
+                })
+                .and_then(|_| (*mesh).into_mesh()),
+            StreamState::AsciiStl {
+                mut stream,
+                partial,
+            } => String::from_utf8_lossy(&partial)
+                .lines()
+                .try_for_each(|line| stream.push_line(line))
+                .and_then(|_| stream.finish()),
+            StreamState::BinaryStl {
+                mut stream,
+                partial,
+            } => stream.push(&partial).and_then(|_| stream.finish()),
+            StreamState::Ply {
+                mut stream,
+                partial,
+            } => stream
+                .push(&partial, true)
+                .and_then(|_| stream.finish())
+                .and_then(|ply| ply.into_mesh()),
+            StreamState::Buffered(data) => return load_mesh(&data, self.format),
+            StreamState::Sniffing(_) => unreachable!("an STL is sniffed by now"),
+        };
+        loaded.map_err(|e| RmeshError::from_load(&self.format, e))
+    }
+}
+
+/// The state for an STL once enough of its start has arrived to tell
+/// if it's ASCII, which is parsed by line, or binary, which is decoded.
+fn stl_state(start: Vec<u8>) -> StreamState {
+    // too short to be either, which loading reports
+    if start.len() < STL_DATA_START {
+        return StreamState::Buffered(start);
+    }
+    if is_ascii_start(&start) {
+        return StreamState::AsciiStl {
+            stream: Box::default(),
+            partial: start,
+        };
+    }
+    StreamState::BinaryStl {
+        stream: BinaryStlStream::new(start[..STL_DATA_START].try_into().unwrap()),
+        partial: start[STL_DATA_START..].to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Push a file to a stream in chunks of a size and finish it.
+    fn stream(data: &[u8], format: MeshFormat, size: usize) -> Result<Trimesh, RmeshError> {
+        let mut stream = MeshStream::new(format);
+        for chunk in data.chunks(size) {
+            stream.push(chunk)?;
+        }
+        assert_eq!(stream.received(), data.len());
+        stream.finish()
+    }
+
+    #[test]
+    fn test_mesh_stream() {
+        let models: [(&[u8], MeshFormat); 6] = [
+            (
+                include_bytes!("../../../../test/data/unit_cube.STL"),
+                MeshFormat::STL,
+            ),
+            (
+                include_bytes!("../../../../test/data/two_objects_mixed_case_names.stl"),
+                MeshFormat::STL,
+            ),
+            (
+                include_bytes!("../../../../test/data/fuze.obj"),
+                MeshFormat::OBJ,
+            ),
+            (
+                include_bytes!("../../../../test/data/conformance/list_properties.ply"),
+                MeshFormat::PLY,
+            ),
+            (
+                include_bytes!("../../../../test/data/conformance/list_properties_be.ply"),
+                MeshFormat::PLY,
+            ),
+            (
+                include_bytes!("../../../../test/data/multi_object.3mf"),
+                MeshFormat::THREEMF,
+            ),
+        ];
+        for (data, format) in models {
+            let expected = load_mesh(data, format.clone()).unwrap();
+            // chunks which split lines and triangles anywhere
+            for size in [1, 7, 50, 4096, data.len()] {
+                let mesh = stream(data, format.clone(), size).unwrap();
+                assert_eq!(mesh.vertices, expected.vertices, "{format:?} in {size}");
+                assert_eq!(mesh.faces, expected.faces);
+                assert_eq!(
+                    mesh.attributes_face.groupings,
+                    expected.attributes_face.groupings
+                );
+            }
+        }
+
+        // an ASCII STL is parsed as its lines arrive rather than held
+        let data = include_bytes!("../../../../test/data/two_objects_mixed_case_names.stl");
+        let mut ascii = MeshStream::new(MeshFormat::STL);
+        ascii.push(&data[..data.len() / 2]).unwrap();
+        match &ascii.state {
+            StreamState::AsciiStl { partial, .. } => assert!(partial.len() < 100),
+            _ => panic!("an ASCII STL should be parsed by line"),
+        }
+
+        // a binary STL missing its last byte is truncated
+        let data = include_bytes!("../../../../test/data/unit_cube.STL");
+        let error = stream(&data[..data.len() - 1], MeshFormat::STL, 100).unwrap_err();
+        assert_eq!(error.kind(), "ParseError");
+        assert!(stream(&data[..40], MeshFormat::STL, 10).is_err());
+        // as is a PLY missing its last row or its header
+        let data = include_bytes!("../../../../test/data/conformance/list_properties.ply");
+        let error = stream(&data[..data.len() - 12], MeshFormat::PLY, 5).unwrap_err();
+        assert_eq!(error.kind(), "ParseError");
+        assert!(stream(&data[..20], MeshFormat::PLY, 5).is_err());
+        // errors in a line are reported as soon as the line arrives
+        let mut obj = MeshStream::new(MeshFormat::OBJ);
+        obj.push(b"v 0 0 0\nv 1 \\\n").unwrap();
+        obj.push(b"0 0\nv 0 zero 0\n").unwrap_err();
+        // a continued line is joined across pushes
+        let mesh = stream(
+            b"v 0 0 0\nv 1 0 \\\n0\nv 0 1 0\nf 1 2 3",
+            MeshFormat::OBJ,
+            3,
+        )
+        .unwrap();
+        assert_eq!(mesh.faces.len(), 1);
+    }
+}
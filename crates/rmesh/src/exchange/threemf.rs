@@ -0,0 +1,640 @@
+use std::io::{Cursor, Read};
+
+use ahash::AHashMap;
+use anyhow::{Result, anyhow};
+use nalgebra::{Matrix4, Point3, Vector4};
+use roxmltree::{Document, Node};
+
+use crate::attributes::{Attributes, DEFAULT_COLOR, LoadSource};
+use crate::exchange::MeshFormat;
+use crate::exchange::validate::{FormatValidator, ValidationReport};
+use crate::geometry::Geometry;
+use crate::mesh::Trimesh;
+use crate::scene::{Scene, SceneNode, SceneNodeKind, Unit};
+
+// the relationship type of the root model part in `_rels/.rels`
+const MODEL_RELATIONSHIP: &str = "http://schemas.microsoft.com/3dmanufacturing/2013/01/3dmodel";
+// where the model usually lives if the relationships are missing
+const DEFAULT_MODEL_PATH: &str = "3D/3dmodel.model";
+
+/// A mesh object defined in the `resources` of a 3MF model.
+struct ThreeMfMesh {
+    name: String,
+    vertices: Vec<Point3<f64>>,
+    faces: Vec<(usize, usize, usize)>,
+    // the color of each face if any face referenced a color
+    colors: Option<Vec<Vector4<u8>>>,
+}
+
+/// A reference from one object to another with a transform.
+struct ThreeMfComponent {
+    object: usize,
+    transform: Option<Matrix4<f64>>,
+}
+
+/// A 3MF object is either a mesh or a list of other objects. Objects
+/// with no triangles have nothing to render but are used by some CAD
+/// exporters to place named coordinate systems, so they become frames.
+enum ThreeMfObject {
+    Mesh(ThreeMfMesh),
+    Components {
+        name: String,
+        components: Vec<ThreeMfComponent>,
+    },
+    Frame {
+        name: String,
+    },
+}
+
+/// The parsed contents of a 3MF package.
+pub struct ThreeMf {
+    // objects keyed by their resource ID
+    objects: AHashMap<usize, ThreeMfObject>,
+    // the `build` items which are the objects that are actually placed
+    build: Vec<ThreeMfComponent>,
+    // the `unit` attribute of the model
+    unit: Option<String>,
+}
+
+impl ThreeMf {
+    /// Parse a 3MF package from the raw bytes of the zip container.
+    ///
+    /// Parameters
+    /// ------------
+    /// bytes
+    ///   Raw bytes of the 3MF file.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Self>
+    ///   The parsed objects and build items or an error.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+
+        // find the path of the root model from the package relationships
+        let path = read_entry(&mut archive, "_rels/.rels")
+            .ok()
+            .and_then(|rels| model_path(&rels))
+            .unwrap_or_else(|| DEFAULT_MODEL_PATH.to_string());
+        let model = read_entry(&mut archive, path.trim_start_matches('/'))?;
+
+        Self::from_model(&model)
+    }
+
+    /// Parse the XML of a 3MF model part.
+    fn from_model(text: &str) -> Result<Self> {
+        let doc = Document::parse(text)?;
+        let root = doc.root_element();
+        if root.tag_name().name() != "model" {
+            return Err(anyhow!("3MF root element is not a `model`"));
+        }
+
+        let resources =
+            child(&root, "resources").ok_or_else(|| anyhow!("3MF model has no `resources`"))?;
+
+        // property groups which may be referenced by objects and triangles
+        let mut properties: AHashMap<usize, Vec<Vector4<u8>>> = AHashMap::new();
+        let mut objects = AHashMap::new();
+
+        for node in resources.children().filter(|n| n.is_element()) {
+            match node.tag_name().name() {
+                "basematerials" => {
+                    let colors = node
+                        .children()
+                        .filter(|n| n.is_element() && n.tag_name().name() == "base")
+                        .map(|n| {
+                            n.attribute("displaycolor")
+                                .and_then(parse_color)
+                                .unwrap_or(DEFAULT_COLOR)
+                        })
+                        .collect();
+                    properties.insert(attr(&node, "id")?, colors);
+                }
+                "colorgroup" => {
+                    let colors = node
+                        .children()
+                        .filter(|n| n.is_element() && n.tag_name().name() == "color")
+                        .map(|n| {
+                            n.attribute("color")
+                                .and_then(parse_color)
+                                .unwrap_or(DEFAULT_COLOR)
+                        })
+                        .collect();
+                    properties.insert(attr(&node, "id")?, colors);
+                }
+                "object" => {
+                    objects.insert(attr(&node, "id")?, parse_object(&node, &properties)?);
+                }
+                _ => {}
+            }
+        }
+
+        let build = child(&root, "build")
+            .map(|b| {
+                b.children()
+                    .filter(|n| n.is_element() && n.tag_name().name() == "item")
+                    .map(|n| parse_component(&n))
+                    .collect::<Result<Vec<_>>>()
+            })
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(Self {
+            objects,
+            build,
+            unit: root.attribute("unit").map(|u| u.to_string()),
+        })
+    }
+
+    /// Convert the package into a scene with one geometry per mesh object
+    /// and a node for every build item and component.
+    pub fn to_scene(&self) -> Result<Scene> {
+        let mut scene = Scene::new();
+
+        // add mesh objects to the scene geometry in ID order
+        let mut ids: Vec<&usize> = self.objects.keys().collect();
+        ids.sort();
+        let mut geometry = AHashMap::new();
+        for id in ids {
+            if let ThreeMfObject::Mesh(mesh) = &self.objects[id] {
+                let mut mesh = mesh.to_mesh()?;
+                mesh.source = self.source();
+                geometry.insert(*id, scene.add_geometry(Geometry::Mesh(Box::new(mesh))));
+            }
+        }
+
+        let root = scene.graph.add_node(SceneNode {
+            name: "world".to_string(),
+            kind: SceneNodeKind::CUSTOM,
+            ..Default::default()
+        });
+        scene.graph.set_root(root);
+        // the specification defaults to millimeters
+        scene.units = Some(Unit::from_name(
+            self.unit.as_deref().unwrap_or("millimeter"),
+        )?);
+
+        for item in self.build.iter() {
+            let child = self.add_node(&mut scene, &geometry, item, &mut vec![])?;
+            scene.graph.add_edge(root, child)?;
+        }
+
+        Ok(scene)
+    }
+
+    /// Recursively add a node for a reference to an object.
+    fn add_node(
+        &self,
+        scene: &mut Scene,
+        geometry: &AHashMap<usize, usize>,
+        component: &ThreeMfComponent,
+        path: &mut Vec<usize>,
+    ) -> Result<usize> {
+        if path.contains(&component.object) {
+            return Err(anyhow!("3MF object {} references itself", component.object));
+        }
+        let object = self
+            .objects
+            .get(&component.object)
+            .ok_or_else(|| anyhow!("3MF references missing object {}", component.object))?;
+
+        match object {
+            ThreeMfObject::Mesh(mesh) => Ok(scene.graph.add_node(SceneNode {
+                name: mesh.name.clone(),
+                transform: component.transform,
+                kind: SceneNodeKind::GEOMETRY,
+                index: vec![geometry[&component.object]],
+                ..Default::default()
+            })),
+            ThreeMfObject::Components { name, components } => {
+                path.push(component.object);
+                let children = components
+                    .iter()
+                    .map(|c| self.add_node(scene, geometry, c, path))
+                    .collect::<Result<Vec<_>>>()?;
+                path.pop();
+                Ok(scene.graph.add_node(SceneNode {
+                    name: name.clone(),
+                    children,
+                    transform: component.transform,
+                    kind: SceneNodeKind::CUSTOM,
+                    ..Default::default()
+                }))
+            }
+            ThreeMfObject::Frame { name } => Ok(scene.graph.add_node(SceneNode {
+                name: name.clone(),
+                transform: component.transform,
+                kind: SceneNodeKind::FRAME,
+                ..Default::default()
+            })),
+        }
+    }
+
+    /// Combine every placed mesh into a single mesh in the build frame.
+    pub fn to_mesh(&self) -> Result<Trimesh> {
+        let mut vertices = Vec::new();
+        let mut faces = Vec::new();
+        let mut colors: Vec<Vector4<u8>> = Vec::new();
+        let mut has_color = false;
+
+        let mut stack: Vec<(Matrix4<f64>, usize, usize)> = self
+            .build
+            .iter()
+            .rev()
+            .map(|b| (b.transform.unwrap_or_else(Matrix4::identity), b.object, 0))
+            .collect();
+        while let Some((transform, id, depth)) = stack.pop() {
+            if depth > self.objects.len() {
+                return Err(anyhow!("3MF object {id} references itself"));
+            }
+            match self.objects.get(&id) {
+                Some(ThreeMfObject::Mesh(mesh)) => {
+                    let offset = vertices.len();
+                    vertices.extend(mesh.vertices.iter().map(|v| transform.transform_point(v)));
+                    faces.extend(
+                        mesh.faces
+                            .iter()
+                            .map(|f| (f.0 + offset, f.1 + offset, f.2 + offset)),
+                    );
+                    match &mesh.colors {
+                        Some(c) => {
+                            has_color = true;
+                            colors.extend(c.iter());
+                        }
+                        None => colors.extend(std::iter::repeat_n(DEFAULT_COLOR, mesh.faces.len())),
+                    }
+                }
+                Some(ThreeMfObject::Components { components, .. }) => {
+                    for c in components.iter().rev() {
+                        let local = c.transform.unwrap_or_else(Matrix4::identity);
+                        stack.push((transform * local, c.object, depth + 1));
+                    }
+                }
+                Some(ThreeMfObject::Frame { .. }) => {}
+                None => return Err(anyhow!("3MF references missing object {id}")),
+            }
+        }
+
+        let mut mesh = Trimesh::new(vertices, faces, None, None)?;
+        if has_color {
+            mesh.attributes_face.colors.push(colors);
+        }
+        mesh.source = self.source();
+        Ok(mesh)
+    }
+
+    fn source(&self) -> LoadSource {
+        LoadSource {
+            format: Some(MeshFormat::THREEMF),
+            header: self.unit.as_ref().map(|u| format!("unit={u}")),
+            // the specification defaults to millimeters
+            units: Unit::from_name(self.unit.as_deref().unwrap_or("millimeter")).ok(),
+        }
+    }
+}
+
+impl ThreeMfMesh {
+    fn to_mesh(&self) -> Result<Trimesh> {
+        let mut attributes_face = Attributes::default();
+        if let Some(colors) = &self.colors {
+            attributes_face.colors.push(colors.clone());
+        }
+        let mut mesh = Trimesh::new(
+            self.vertices.clone(),
+            self.faces.clone(),
+            None,
+            Some(attributes_face),
+        )?;
+        mesh.source.format = Some(MeshFormat::THREEMF);
+        Ok(mesh)
+    }
+}
+
+/// Parse an `object` element which contains a mesh or components.
+fn parse_object(
+    node: &Node,
+    properties: &AHashMap<usize, Vec<Vector4<u8>>>,
+) -> Result<ThreeMfObject> {
+    let name = node.attribute("name").unwrap_or_default().to_string();
+
+    if let Some(components) = child(node, "components") {
+        return Ok(ThreeMfObject::Components {
+            name,
+            components: components
+                .children()
+                .filter(|n| n.is_element() && n.tag_name().name() == "component")
+                .map(|n| parse_component(&n))
+                .collect::<Result<Vec<_>>>()?,
+        });
+    }
+
+    let mesh = child(node, "mesh").ok_or_else(|| anyhow!("3MF object `{name}` has no mesh"))?;
+
+    let vertices = child(&mesh, "vertices")
+        .map(|v| {
+            v.children()
+                .filter(|n| n.is_element() && n.tag_name().name() == "vertex")
+                .map(|n| Ok(Point3::new(attr(&n, "x")?, attr(&n, "y")?, attr(&n, "z")?)))
+                .collect::<Result<Vec<_>>>()
+        })
+        .transpose()?
+        .unwrap_or_default();
+
+    // the object-level default property for triangles that don't specify one
+    let default_pid: Option<usize> = node.attribute("pid").and_then(|p| p.parse().ok());
+    let default_index: usize = node
+        .attribute("pindex")
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(0);
+
+    let mut faces = Vec::new();
+    let mut colors = Vec::new();
+    let mut has_color = false;
+    for n in child(&mesh, "triangles")
+        .into_iter()
+        .flat_map(|t| t.children())
+        .filter(|n| n.is_element() && n.tag_name().name() == "triangle")
+    {
+        faces.push((attr(&n, "v1")?, attr(&n, "v2")?, attr(&n, "v3")?));
+
+        // triangles can have per-vertex properties but we only keep one
+        // color per face so use the first vertex or the object default
+        let pid = n
+            .attribute("pid")
+            .and_then(|p| p.parse().ok())
+            .or(default_pid);
+        let index = n
+            .attribute("p1")
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(default_index);
+        match pid
+            .and_then(|p| properties.get(&p))
+            .and_then(|c| c.get(index))
+        {
+            Some(color) => {
+                has_color = true;
+                colors.push(*color);
+            }
+            None => colors.push(DEFAULT_COLOR),
+        }
+    }
+
+    if faces.is_empty() {
+        return Ok(ThreeMfObject::Frame { name });
+    }
+
+    Ok(ThreeMfObject::Mesh(ThreeMfMesh {
+        name,
+        vertices,
+        faces,
+        colors: if has_color { Some(colors) } else { None },
+    }))
+}
+
+/// Parse a `component` or build `item` which references an object.
+fn parse_component(node: &Node) -> Result<ThreeMfComponent> {
+    Ok(ThreeMfComponent {
+        object: attr(node, "objectid")?,
+        transform: node
+            .attribute("transform")
+            .map(parse_transform)
+            .transpose()?,
+    })
+}
+
+/// Parse a 3MF transform which is 12 values of a row-major 4x3 matrix
+/// that multiplies row vectors, into a homogeneous column-vector matrix.
+fn parse_transform(text: &str) -> Result<Matrix4<f64>> {
+    let values = text
+        .split_whitespace()
+        .map(|v| v.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()?;
+    if values.len() != 12 {
+        return Err(anyhow!("3MF transform must have 12 values: `{text}`"));
+    }
+    #[rustfmt::skip]
+    let matrix = Matrix4::new(
+        values[0], values[3], values[6], values[9],
+        values[1], values[4], values[7], values[10],
+        values[2], values[5], values[8], values[11],
+        0.0, 0.0, 0.0, 1.0,
+    );
+    Ok(matrix)
+}
+
+/// Parse a `#RRGGBB` or `#RRGGBBAA` color.
+fn parse_color(text: &str) -> Option<Vector4<u8>> {
+    let hex = text.trim().strip_prefix('#')?;
+    if hex.len() != 6 && hex.len() != 8 {
+        return None;
+    }
+    let mut color = Vector4::new(0, 0, 0, 255);
+    for i in 0..hex.len() / 2 {
+        color[i] = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(color)
+}
+
+/// Find the path of the root model in a package relationships file.
+fn model_path(rels: &str) -> Option<String> {
+    let doc = Document::parse(rels).ok()?;
+    doc.descendants()
+        .find(|n| n.attribute("Type") == Some(MODEL_RELATIONSHIP))
+        .and_then(|n| n.attribute("Target"))
+        .map(|t| t.to_string())
+}
+
+/// Read an entry from a zip archive into a string.
+fn read_entry(archive: &mut zip::ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<String> {
+    let mut text = String::new();
+    archive.by_name(name)?.read_to_string(&mut text)?;
+    Ok(text)
+}
+
+/// Find the first child element with a tag name ignoring namespaces.
+fn child<'a, 'input>(node: &Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children()
+        .find(|n| n.is_element() && n.tag_name().name() == name)
+}
+
+/// Every element below a node with a tag name ignoring namespaces.
+fn descendants<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.descendants()
+        .filter(move |n| n.is_element() && n.tag_name().name() == name)
+}
+
+/// Parse a required attribute of an element.
+fn attr<T: std::str::FromStr>(node: &Node, name: &str) -> Result<T> {
+    node.attribute(name)
+        .ok_or_else(|| anyhow!("3MF `{}` missing `{name}`", node.tag_name().name()))?
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("3MF `{}` has invalid `{name}`", node.tag_name().name()))
+}
+
+/// Checks a 3MF package opens and has a model part of valid XML, every
+/// triangle refers to vertices of its own mesh, and every component and
+/// build item refers to an object which exists.
+pub struct ThreeMfValidator;
+
+impl FormatValidator for ThreeMfValidator {
+    fn validate(&self, bytes: &[u8]) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let model = zip::ZipArchive::new(Cursor::new(bytes))
+            .map_err(anyhow::Error::from)
+            .and_then(|mut archive| {
+                let path = read_entry(&mut archive, "_rels/.rels")
+                    .ok()
+                    .and_then(|rels| model_path(&rels))
+                    .unwrap_or_else(|| DEFAULT_MODEL_PATH.to_string());
+                read_entry(&mut archive, path.trim_start_matches('/'))
+            });
+        let doc = match model.as_deref().map(Document::parse) {
+            Ok(Ok(doc)) => doc,
+            Ok(Err(error)) => {
+                report.error(None, format!("3MF model isn't valid XML: {error}"));
+                return report;
+            }
+            Err(error) => {
+                report.error(None, format!("3MF package can't be read: {error}"));
+                return report;
+            }
+        };
+
+        let ids: Vec<String> = descendants(doc.root(), "object")
+            .filter_map(|n| n.attribute("id").map(|id| id.trim().to_string()))
+            .collect();
+        let (mut vertices, mut faces) = (0, 0);
+        for object in descendants(doc.root(), "object") {
+            let count = descendants(object, "vertex").count();
+            vertices += count;
+            for triangle in descendants(object, "triangle") {
+                faces += 1;
+                for name in ["v1", "v2", "v3"] {
+                    match attr::<usize>(&triangle, name) {
+                        Ok(index) if index < count => {}
+                        Ok(index) => report.error(
+                            Some(doc.text_pos_at(triangle.range().start).row as usize),
+                            format!("3MF triangle references vertex {index} of {count}"),
+                        ),
+                        Err(error) => report.error(
+                            Some(doc.text_pos_at(triangle.range().start).row as usize),
+                            error.to_string(),
+                        ),
+                    }
+                }
+            }
+        }
+        for reference in descendants(doc.root(), "component").chain(descendants(doc.root(), "item"))
+        {
+            let id = reference.attribute("objectid").unwrap_or_default().trim();
+            if !ids.iter().any(|i| i == id) {
+                report.error(
+                    Some(doc.text_pos_at(reference.range().start).row as usize),
+                    format!(
+                        "3MF `{}` references missing object `{id}`",
+                        reference.tag_name().name()
+                    ),
+                );
+            }
+        }
+        report.vertices = Some(vertices);
+        report.faces = Some(faces);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::exchange::{load_mesh, load_scene};
+    use approx::assert_relative_eq;
+    use nalgebra::Vector3;
+
+    #[test]
+    fn test_threemf_transform() {
+        let m = parse_transform("1 0 0 0 1 0 0 0 1 10 20 30").unwrap();
+        assert_eq!(
+            m.transform_point(&Point3::new(1.0, 2.0, 3.0)),
+            Point3::new(11.0, 22.0, 33.0)
+        );
+        assert!(parse_transform("1 0 0").is_err());
+
+        assert_eq!(parse_color("#FF0000"), Some(Vector4::new(255, 0, 0, 255)));
+        assert_eq!(parse_color("#00ff0080"), Some(Vector4::new(0, 255, 0, 128)));
+        assert_eq!(parse_color("red"), None);
+    }
+
+    #[test]
+    fn test_threemf_scene() {
+        let data = include_bytes!("../../../../test/data/multi_object.3mf");
+        let scene = load_scene(data, MeshFormat::THREEMF).unwrap();
+
+        // a cube and a tetrahedron
+        assert_eq!(scene.geometry.len(), 2);
+
+        // the cube is placed twice: once directly and once via an assembly
+        let flat = scene.flatten();
+        assert_eq!(flat.len(), 3);
+
+        let mesh = load_mesh(data, MeshFormat::THREEMF).unwrap();
+        assert_eq!(mesh.faces.len(), 12 + 12 + 4);
+        assert_eq!(mesh.source.format, Some(MeshFormat::THREEMF));
+
+        // the red cube should have face colors
+        let colors = &mesh.attributes_face.colors[0];
+        assert_eq!(colors.len(), mesh.faces.len());
+        assert_eq!(colors[0], Vector4::new(255, 0, 0, 255));
+
+        // the assembly translates the second cube by 20 along X
+        let (lower, upper) = mesh.bounds().unwrap();
+        assert_relative_eq!(lower.coords, Vector3::new(0.0, 0.0, 0.0));
+        assert_relative_eq!(upper.coords, Vector3::new(30.0, 10.0, 10.0));
+    }
+
+    #[test]
+    fn test_threemf_frames() {
+        let model = r#"<?xml version="1.0" encoding="UTF-8"?>
+<model unit="millimeter" xmlns="http://schemas.microsoft.com/3dmanufacturing/core/2015/02">
+  <resources>
+    <object id="1" name="tool" type="model">
+      <mesh>
+        <vertices>
+          <vertex x="0" y="0" z="0" /><vertex x="1" y="0" z="0" /><vertex x="0" y="1" z="0" />
+        </vertices>
+        <triangles><triangle v1="0" v2="1" v3="2" /></triangles>
+      </mesh>
+    </object>
+    <object id="2" name="tcp" type="other">
+      <mesh><vertices /><triangles /></mesh>
+    </object>
+    <object id="3" name="assembly">
+      <components>
+        <component objectid="1" />
+        <component objectid="2" transform="1 0 0 0 1 0 0 0 1 0 0 5" />
+      </components>
+    </object>
+  </resources>
+  <build>
+    <item objectid="3" transform="1 0 0 0 1 0 0 0 1 10 0 0" />
+  </build>
+</model>"#;
+        let threemf = ThreeMf::from_model(model).unwrap();
+        let scene = threemf.to_scene().unwrap();
+        assert_eq!(scene.units, Some(Unit::Millimeter));
+        assert_eq!(scene.geometry.len(), 1);
+        let frame = scene.graph.frame("tcp").unwrap();
+        assert_relative_eq!(
+            frame.transform_point(&Point3::origin()),
+            Point3::new(10.0, 0.0, 5.0)
+        );
+        // the frame doesn't contribute any geometry
+        let mesh = threemf.to_mesh().unwrap();
+        assert_eq!(mesh.faces.len(), 1);
+        assert_eq!(mesh.source.units, Some(Unit::Millimeter));
+    }
+}
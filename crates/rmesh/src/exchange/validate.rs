@@ -0,0 +1,280 @@
+use std::fmt;
+
+use crate::error::RmeshError;
+use crate::exchange::obj::ObjValidator;
+use crate::exchange::off::OffValidator;
+use crate::exchange::ply::PlyValidator;
+use crate::exchange::stl::StlValidator;
+use crate::exchange::threemf::ThreeMfValidator;
+use crate::exchange::xyz::XyzValidator;
+use crate::exchange::{MeshFormat, load_mesh};
+use crate::mesh::Trimesh;
+
+/// How serious a problem found by a `FormatValidator` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    // the file loads but may not contain what the writer intended
+    Warning,
+    // the file can't be loaded as written
+    Error,
+}
+
+/// A single problem found in a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    // the line the problem is on counting from one for text formats
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.line {
+            Some(line) => write!(f, "{severity} on line {line}: {}", self.message),
+            None => write!(f, "{severity}: {}", self.message),
+        }
+    }
+}
+
+/// The structure of a file as checked by a `FormatValidator`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValidationReport {
+    // the number of vertices in the file if it got far enough to count them
+    pub vertices: Option<usize>,
+    // the number of faces as written, before polygons are triangulated
+    pub faces: Option<usize>,
+    // every problem found in the order it was found
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Is the file free of errors, although it may have warnings.
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// The issues which prevent the file from loading.
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    /// Record an error.
+    pub(crate) fn error(&mut self, line: Option<usize>, message: impl Into<String>) {
+        self.issues.push(ValidationIssue {
+            severity: Severity::Error,
+            line,
+            message: message.into(),
+        });
+    }
+
+    /// Record a warning.
+    pub(crate) fn warning(&mut self, line: Option<usize>, message: impl Into<String>) {
+        self.issues.push(ValidationIssue {
+            severity: Severity::Warning,
+            line,
+            message: message.into(),
+        });
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = |c: Option<usize>| c.map_or("?".to_string(), |c| c.to_string());
+        write!(
+            f,
+            "{} with {} vertices and {} faces",
+            if self.is_valid() { "valid" } else { "invalid" },
+            count(self.vertices),
+            count(self.faces)
+        )?;
+        for issue in self.issues.iter() {
+            write!(f, "\n  {issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the structure of a file in one format before it's loaded:
+/// the header, that the element counts match the data, and that every
+/// index refers to an element which exists. Validators only scan the
+/// file and don't build a mesh, so they're cheap enough to run on
+/// untrusted input before committing to a full parse.
+pub trait FormatValidator: Send + Sync {
+    /// Check the raw bytes of a file.
+    fn validate(&self, bytes: &[u8]) -> ValidationReport;
+}
+
+impl MeshFormat {
+    /// The validator for files of this format.
+    pub fn validator(&self) -> &'static dyn FormatValidator {
+        match self {
+            MeshFormat::STL => &StlValidator,
+            MeshFormat::OBJ => &ObjValidator,
+            MeshFormat::PLY => &PlyValidator,
+            MeshFormat::THREEMF => &ThreeMfValidator,
+            MeshFormat::OFF => &OffValidator,
+            MeshFormat::XYZ => &XyzValidator,
+        }
+    }
+}
+
+/// Check a file with the validator of its format.
+///
+/// Parameters
+/// ------------
+/// file_data
+///   Raw bytes of the file.
+/// file_type
+///   The format of the file.
+///
+/// Returns
+/// ------------
+/// report
+///   The counts and every problem found in the file.
+pub fn validate(file_data: &[u8], file_type: &MeshFormat) -> ValidationReport {
+    file_type.validator().validate(file_data)
+}
+
+/// Load a mesh only if it passes validation, rather than loading whatever
+/// the loader can make sense of as `load_mesh` does.
+///
+/// Parameters
+/// ------------
+/// file_data
+///   Raw bytes of the file.
+/// file_type
+///   The format of the file.
+///
+/// Returns
+/// ------------
+/// Result<Trimesh, RmeshError>
+///   The loaded mesh, or a `Parse` error on the line of the first
+///   validation error listing every one if there were any.
+pub fn load_mesh_strict(file_data: &[u8], file_type: MeshFormat) -> Result<Trimesh, RmeshError> {
+    let report = validate(file_data, &file_type);
+    let mut errors = report.errors();
+    if let Some(first) = errors.next() {
+        let mut message = first.message.clone();
+        for other in errors {
+            message.push_str(&format!("\n  {other}"));
+        }
+        return Err(RmeshError::Parse {
+            format: file_type,
+            line: first.line,
+            message,
+        });
+    }
+    load_mesh(file_data, file_type)
+}
+
+/// Parse every token as a number, or `None` if any isn't one.
+pub(crate) fn numbers<'a>(tokens: impl Iterator<Item = &'a str>) -> Option<Vec<f64>> {
+    tokens.map(|t| t.parse::<f64>().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_validate_models() {
+        // every model in the test data is valid
+        let models: [(&[u8], MeshFormat, usize, usize); 4] = [
+            (
+                include_bytes!("../../../../test/data/unit_cube.STL"),
+                MeshFormat::STL,
+                36,
+                12,
+            ),
+            (
+                include_bytes!("../../../../test/data/fuze.obj"),
+                MeshFormat::OBJ,
+                502,
+                1000,
+            ),
+            (
+                include_bytes!("../../../../test/data/two_objects_mixed_case_names.stl"),
+                MeshFormat::STL,
+                72,
+                24,
+            ),
+            (
+                include_bytes!("../../../../test/data/multi_object.3mf"),
+                MeshFormat::THREEMF,
+                12,
+                16,
+            ),
+        ];
+        for (data, format, vertices, faces) in models {
+            let report = validate(data, &format);
+            assert!(report.is_valid(), "{report}");
+            assert_eq!(report.vertices, Some(vertices));
+            assert_eq!(report.faces, Some(faces));
+            assert!(load_mesh_strict(data, format).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_validate_invalid() {
+        let check = |data: &str, format: MeshFormat, line: Option<usize>| {
+            let report = validate(data.as_bytes(), &format);
+            assert!(!report.is_valid(), "{format:?} should be invalid: {data}");
+            assert_eq!(report.errors().next().unwrap().line, line, "{report}");
+            assert!(load_mesh_strict(data.as_bytes(), format).is_err());
+        };
+
+        // an index past the last vertex
+        check(
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n",
+            MeshFormat::OBJ,
+            Some(4),
+        );
+        // a relative index before the first vertex
+        check("v 0 0 0\nv 1 0 0\nf -1 -2 -4\n", MeshFormat::OBJ, Some(3));
+        check("v 0 zero 0\n", MeshFormat::OBJ, Some(1));
+        // a facet with two vertices
+        check(
+            "solid a\nfacet normal 0 0 1\nouter loop\nvertex 0 0 0\nvertex 1 0 0\nendloop\nendfacet\nendsolid a\n",
+            MeshFormat::STL,
+            Some(7),
+        );
+        // a binary header promising more triangles than there are
+        let mut binary = vec![0_u8; 84 + 50];
+        binary[80] = 2;
+        assert!(!validate(&binary, &MeshFormat::STL).is_valid());
+        // fewer vertices than the header declares
+        check("OFF\n3 1 0\n0 0 0\n1 0 0\n", MeshFormat::OFF, None);
+        check(
+            "OFF\n3 1 0\n0 0 0\n1 0 0\n0 1 0\n3 0 1 3\n",
+            MeshFormat::OFF,
+            Some(6),
+        );
+        check(
+            "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n0 0 0\n1 0 0\n0 1 0\n3 0 1 5\n",
+            MeshFormat::PLY,
+            None,
+        );
+        check(
+            "ply\nformat ascii 1.0\nelement vertex 3\n",
+            MeshFormat::PLY,
+            None,
+        );
+        check("1 2 3\n4 5\n", MeshFormat::XYZ, Some(2));
+        check("not a zip", MeshFormat::THREEMF, None);
+
+        // extra data after the declared elements is only a warning
+        let report = validate(
+            b"ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\nend_header\n0 0 0\n1 1 1\n",
+            &MeshFormat::PLY,
+        );
+        assert!(report.is_valid());
+        assert_eq!(report.issues[0].severity, Severity::Warning);
+        assert_eq!(report.vertices, Some(1));
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector3, Vector4};
+use rayon::prelude::*;
+
+use crate::attributes::{Attributes, LoadSource};
+use crate::exchange::MeshFormat;
+use crate::exchange::validate::{FormatValidator, ValidationReport, numbers};
+use crate::mesh::Trimesh;
+
+/// A point cloud from an XYZ file, which is whitespace or comma
+/// delimited text with one point per line. Extra columns after the
+/// position are interpreted as an RGB color if they are all integers
+/// and as a normal otherwise.
+pub struct XyzPoints {
+    vertices: Vec<Point3<f64>>,
+    normals: Vec<Vector3<f64>>,
+    colors: Vec<Vector4<u8>>,
+}
+
+impl XyzPoints {
+    /// Parse the text of an XYZ file.
+    ///
+    /// Parameters
+    /// ------------
+    /// data
+    ///   The text of the XYZ file.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Self>
+    ///   The parsed points or an error if any line is invalid.
+    pub fn from_string(data: &str) -> Result<Self> {
+        let rows = data
+            .lines()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .filter_map(|line| {
+                let line = line.split('#').next().unwrap_or_default().trim();
+                if line.is_empty() {
+                    return None;
+                }
+                let tokens: Vec<&str> = line
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|v| !v.is_empty())
+                    .collect();
+                // are all the columns after the position integers
+                let integer = tokens.iter().skip(3).all(|v| !v.contains('.'));
+                Some(
+                    tokens
+                        .iter()
+                        .map(|v| v.parse::<f64>())
+                        .collect::<Result<Vec<_>, _>>()
+                        .map(|values| (values, integer))
+                        .map_err(|_| anyhow!("XYZ line is not numeric: `{line}`")),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // every line should have the same number of columns
+        let columns = rows.first().map(|r| r.0.len()).unwrap_or(3);
+        if columns < 3 || rows.iter().any(|r| r.0.len() != columns) {
+            return Err(anyhow!(
+                "XYZ rows must all have at least 3 and the same number of columns"
+            ));
+        }
+
+        let vertices = rows
+            .iter()
+            .map(|(r, _)| Point3::new(r[0], r[1], r[2]))
+            .collect();
+
+        let (mut normals, mut colors) = (Vec::new(), Vec::new());
+        if columns >= 6 {
+            if rows.iter().all(|(_, integer)| *integer) {
+                colors = rows
+                    .iter()
+                    .map(|(r, _)| {
+                        let mut color = Vector4::new(0, 0, 0, 255);
+                        for (i, v) in r.iter().skip(3).take(4).enumerate() {
+                            color[i] = v.clamp(0.0, 255.0) as u8;
+                        }
+                        color
+                    })
+                    .collect();
+            } else {
+                normals = rows
+                    .iter()
+                    .map(|(r, _)| Vector3::new(r[3], r[4], r[5]))
+                    .collect();
+            }
+        }
+
+        Ok(Self {
+            vertices,
+            normals,
+            colors,
+        })
+    }
+
+    /// Convert the points into a mesh with vertices and no faces.
+    pub fn into_mesh(self) -> Result<Trimesh> {
+        let mut attributes_vertex = Attributes::default();
+        if !self.normals.is_empty() {
+            attributes_vertex.normals.push(self.normals);
+        }
+        if !self.colors.is_empty() {
+            attributes_vertex.colors.push(self.colors);
+        }
+
+        let mut mesh = Trimesh::new(self.vertices, vec![], Some(attributes_vertex), None)?;
+        mesh.source = LoadSource {
+            format: Some(MeshFormat::XYZ),
+            header: None,
+
+            ..Default::default()
+        };
+        Ok(mesh)
+    }
+}
+
+/// Checks every line of an XYZ file has the same number of numeric
+/// columns and at least the three of a position.
+pub struct XyzValidator;
+
+impl FormatValidator for XyzValidator {
+    fn validate(&self, bytes: &[u8]) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        let mut columns = None;
+        let mut points = 0;
+        let text = String::from_utf8_lossy(bytes);
+        for (index, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let tokens = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|v| !v.is_empty());
+            match numbers(tokens) {
+                Some(values) => {
+                    let expected = *columns.get_or_insert(values.len());
+                    if values.len() < 3 {
+                        report.error(Some(index + 1), "XYZ line has fewer than 3 columns");
+                    } else if values.len() != expected {
+                        report.error(
+                            Some(index + 1),
+                            format!("XYZ line has {} columns not {expected}", values.len()),
+                        );
+                    }
+                }
+                None => report.error(Some(index + 1), "XYZ line is not numeric"),
+            }
+            points += 1;
+        }
+        report.vertices = Some(points);
+        report.faces = Some(0);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::exchange::load_mesh;
+
+    #[test]
+    fn test_xyz_points() {
+        let data = "# a comment\n0 0 0\n1.0 2.0 3.0\n\n4,5,6\n";
+        let mesh = load_mesh(data.as_bytes(), MeshFormat::XYZ).unwrap();
+        assert_eq!(mesh.vertices.len(), 3);
+        assert!(mesh.faces.is_empty());
+        assert_eq!(mesh.vertices[2], Point3::new(4.0, 5.0, 6.0));
+        assert!(mesh.attributes_vertex.colors.is_empty());
+    }
+
+    #[test]
+    fn test_xyz_attributes() {
+        let colored = "0 0 0 255 0 0\n1 1 1 0 255 0\n";
+        let mesh = load_mesh(colored.as_bytes(), MeshFormat::XYZ).unwrap();
+        assert_eq!(
+            mesh.attributes_vertex.colors[0][1],
+            Vector4::new(0, 255, 0, 255)
+        );
+
+        let normals = "0 0 0 0.0 0.0 1.0\n1 1 1 0.0 1.0 0.0\n";
+        let mesh = load_mesh(normals.as_bytes(), MeshFormat::XYZ).unwrap();
+        assert_eq!(
+            mesh.attributes_vertex.normals[0][1],
+            Vector3::new(0.0, 1.0, 0.0)
+        );
+
+        let normals = "0,0,0,0.0,0.0,1.0\n";
+        let mesh = load_mesh(normals.as_bytes(), MeshFormat::XYZ).unwrap();
+        assert_eq!(mesh.attributes_vertex.normals[0].len(), 1);
+
+        assert!(XyzPoints::from_string("0 0 0\n1 1\n").is_err());
+        assert!(XyzPoints::from_string("0 0 zero\n").is_err());
+    }
+}
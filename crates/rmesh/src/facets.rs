@@ -0,0 +1,367 @@
+use ahash::AHashMap;
+use anyhow::Result;
+use nalgebra::Point2;
+
+use crate::creation::{Plane, Triangulator};
+use crate::mesh::Trimesh;
+use crate::tolerance::Tolerances;
+
+/// The signed area of a closed 2D polygon which is positive
+/// if the polygon is wound counter-clockwise.
+fn signed_area(points: &[Point2<f64>]) -> f64 {
+    let mut area = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area / 2.0
+}
+
+/// Are three points in a row on a straight line, continuing in the
+/// same direction rather than doubling back, where `tolerance` is the
+/// sine of the angle below which they are on a line.
+fn is_collinear(a: &Point2<f64>, b: &Point2<f64>, c: &Point2<f64>, tolerance: f64) -> bool {
+    let (ab, bc) = (b - a, c - b);
+    let cross = ab.x * bc.y - ab.y * bc.x;
+    cross.abs() <= tolerance * ab.norm() * bc.norm() && ab.dot(&bc) > 0.0
+}
+
+/// Chain the boundary edges of a group of faces into closed loops of
+/// vertex indices. Boundary edges are the directed edges whose reverse
+/// isn't also in the group, so loops follow the winding of the faces.
+///
+/// Returns `None` if the boundary isn't a set of simple loops, for
+/// example if two loops touch at a single vertex.
+pub(crate) fn boundary_loops(faces: &[(usize, usize, usize)]) -> Option<Vec<Vec<usize>>> {
+    let directed: Vec<[usize; 2]> = faces
+        .iter()
+        .flat_map(|f| [[f.0, f.1], [f.1, f.2], [f.2, f.0]])
+        .collect();
+    let interior: ahash::AHashSet<[usize; 2]> = directed.iter().copied().collect();
+
+    // map the start of every boundary edge to its end
+    let mut next: AHashMap<usize, usize> = AHashMap::new();
+    for [a, b] in directed.iter() {
+        if interior.contains(&[*b, *a]) {
+            continue;
+        }
+        if next.insert(*a, *b).is_some() {
+            return None;
+        }
+    }
+
+    let mut starts: Vec<usize> = next.keys().copied().collect();
+    starts.sort_unstable();
+
+    let mut loops = Vec::new();
+    for start in starts {
+        if !next.contains_key(&start) {
+            continue;
+        }
+        let mut current = start;
+        let mut chain = Vec::new();
+        while let Some(end) = next.remove(&current) {
+            chain.push(current);
+            current = end;
+        }
+        if current != start {
+            return None;
+        }
+        loops.push(chain);
+    }
+    Some(loops)
+}
+
+impl Trimesh {
+    /// Find groups of adjacent faces which are coplanar. Faces are
+    /// grown outward from a seed face and a neighbor joins the group if
+    /// its normal is within `tolerance` of the seed's normal, which keeps
+    /// gently curved surfaces from being merged into a single facet.
+    ///
+    /// Faces are only considered adjacent if they share vertex indices,
+    /// so a triangle soup like an STL should be merged first.
+    ///
+    /// Parameters
+    /// ------------
+    /// tolerance
+    ///   The maximum angle between face normals in radians.
+    ///
+    /// Returns
+    /// ------------
+    /// facets
+    ///   Groups of face indices with more than one face each, sorted.
+    pub fn facets(&self, tolerance: f64) -> Vec<Vec<usize>> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        let normals = self.face_normals();
+
+        // an adjacency list for every face
+        let mut neighbors = vec![Vec::new(); self.faces.len()];
+        for (a, b) in self.face_adjacency() {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+
+        let mut visited = vec![false; self.faces.len()];
+        let mut facets = Vec::new();
+        for seed in 0..self.faces.len() {
+            if visited[seed] || normals[seed].norm_squared() == 0.0 {
+                continue;
+            }
+            visited[seed] = true;
+            let mut group = vec![seed];
+            let mut queue = vec![seed];
+            while let Some(current) = queue.pop() {
+                for &other in neighbors[current].iter() {
+                    if visited[other] || normals[other].norm_squared() == 0.0 {
+                        continue;
+                    }
+                    if normals[seed].angle(&normals[other]) <= tolerance {
+                        visited[other] = true;
+                        group.push(other);
+                        queue.push(other);
+                    }
+                }
+            }
+            if group.len() > 1 {
+                group.sort_unstable();
+                facets.push(group);
+            }
+        }
+        facets
+    }
+
+    /// Merge coplanar facets and re-triangulate their outlines, which on
+    /// CAD-tessellated meshes can remove most of the faces without
+    /// changing the shape at all. Vertices on the outline of a facet are
+    /// all kept so no cracks are opened against neighboring facets, but
+    /// vertices inside of a facet are removed.
+    ///
+    /// Facets whose outline can't be re-triangulated cleanly keep their
+    /// original faces. Face attributes are not carried over.
+    ///
+    /// Parameters
+    /// ------------
+    /// tolerance
+    ///   The maximum angle between face normals in radians for
+    ///   faces to be considered coplanar.
+    ///
+    /// Returns
+    /// ------------
+    /// simplified
+    ///   A new mesh with the facets merged.
+    pub fn simplify_planar(&self, tolerance: f64) -> Result<Self> {
+        self.simplify_planar_with(tolerance, &self.tolerances())
+    }
+
+    /// Merge coplanar facets like `simplify_planar` with explicit
+    /// tolerances for when a vertex on a facet outline can be removed.
+    pub fn simplify_planar_with(&self, tolerance: f64, tolerances: &Tolerances) -> Result<Self> {
+        let normals = self.face_normals();
+        let mut triangulator = Triangulator::new();
+
+        let mut keep = vec![true; self.faces.len()];
+        let mut faces = Vec::new();
+
+        for facet in self.facets(tolerance) {
+            let group: Vec<(usize, usize, usize)> = facet.iter().map(|i| self.faces[*i]).collect();
+            let Some(loops) = boundary_loops(&group) else {
+                continue;
+            };
+
+            // average the face normals for the plane
+            let normal = facet
+                .iter()
+                .map(|i| normals[*i])
+                .sum::<nalgebra::Vector3<f64>>()
+                .normalize();
+            let plane = Plane::new(normal, self.vertices[group[0].0]);
+
+            // project the loops into the plane where the exterior
+            // winds counter-clockwise and any holes wind clockwise
+            let flat: Vec<usize> = loops.iter().flatten().copied().collect();
+            let points = plane.to_2d(&flat.iter().map(|i| self.vertices[*i]).collect::<Vec<_>>());
+
+            // earcut silently drops collinear points which would open
+            // cracks against the neighboring facets, so remove them here
+            // and split the triangles along those edges afterwards
+            let mut splits: AHashMap<[usize; 2], Vec<usize>> = AHashMap::new();
+            let mut simple = Vec::with_capacity(loops.len());
+            let mut start = 0;
+            for chain in loops.iter() {
+                let range: Vec<usize> = (start..start + chain.len()).collect();
+                start += chain.len();
+                let corners: Vec<usize> = range
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, current)| {
+                        let prev = points[range[(i + range.len() - 1) % range.len()]];
+                        let next = points[range[(i + 1) % range.len()]];
+                        !is_collinear(&prev, &points[**current], &next, tolerances.collinear)
+                    })
+                    .map(|(_, current)| *current)
+                    .collect();
+                for (i, corner) in corners.iter().enumerate() {
+                    let end = corners[(i + 1) % corners.len()];
+                    let between: Vec<usize> = (1..)
+                        .map(|k| range[(corner - range[0] + k) % range.len()])
+                        .take_while(|v| *v != end)
+                        .collect();
+                    if !between.is_empty() {
+                        splits.insert([*corner, end], between);
+                    }
+                }
+                simple.push(corners);
+            }
+            if simple.iter().any(|c| c.len() < 3) {
+                continue;
+            }
+
+            let (exterior, interiors): (Vec<_>, Vec<_>) = simple.into_iter().partition(|r| {
+                signed_area(&r.iter().map(|i| points[*i]).collect::<Vec<_>>()) > 0.0
+            });
+            let [exterior] = exterior.as_slice() else {
+                continue;
+            };
+
+            // a polygon with holes triangulates into exactly this many
+            // triangles so anything else means the triangulation failed
+            let corners = exterior.len() + interiors.iter().map(|i| i.len()).sum::<usize>();
+            let expected = corners + 2 * interiors.len() - 2;
+            let triangles = triangulator.trianglate_2d(exterior, &interiors, &points);
+            if triangles.len() != expected {
+                continue;
+            }
+
+            // put back the collinear points by splitting triangles along
+            // the edge they were on, towards the opposite vertex
+            let mut stack = triangles;
+            while let Some((a, b, c)) = stack.pop() {
+                let split = [(a, b, c), (b, c, a), (c, a, b)]
+                    .into_iter()
+                    .find_map(|(a, b, c)| {
+                        if let Some(between) = splits.get(&[a, b]) {
+                            Some((a, b, c, between.clone()))
+                        } else {
+                            splits
+                                .get(&[b, a])
+                                .map(|between| (a, b, c, between.iter().rev().copied().collect()))
+                        }
+                    });
+                let Some((a, b, c, between)) = split else {
+                    // map back to the mesh vertices and wind them to match the facet
+                    let (a, b, c) = (flat[a], flat[b], flat[c]);
+                    let cross = (self.vertices[b] - self.vertices[a])
+                        .cross(&(self.vertices[c] - self.vertices[a]));
+                    if cross.dot(&normal) < 0.0 {
+                        faces.push((a, c, b));
+                    } else {
+                        faces.push((a, b, c));
+                    }
+                    continue;
+                };
+                splits.remove(&[a, b]);
+                splits.remove(&[b, a]);
+                let chain: Vec<usize> = std::iter::once(a)
+                    .chain(between)
+                    .chain(std::iter::once(b))
+                    .collect();
+                stack.extend(chain.windows(2).map(|w| (w[0], w[1], c)));
+            }
+            for i in facet {
+                keep[i] = false;
+            }
+        }
+
+        faces.extend(
+            self.faces
+                .iter()
+                .zip(keep.iter())
+                .filter(|(_, k)| **k)
+                .map(|(f, _)| *f),
+        );
+
+        // remove the vertices that were only referenced by merged faces
+        let mut mask = vec![usize::MAX; self.vertices.len()];
+        let mut vertices = Vec::new();
+        for face in faces.iter_mut() {
+            for index in [&mut face.0, &mut face.1, &mut face.2] {
+                if mask[*index] == usize::MAX {
+                    mask[*index] = vertices.len();
+                    vertices.push(self.vertices[*index]);
+                }
+                *index = mask[*index];
+            }
+        }
+
+        Ok(Trimesh::new(vertices, faces, None, None)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+    use nalgebra::Point3;
+
+    /// A unit square in the XY plane split into a grid of triangles.
+    fn grid(count: usize) -> Trimesh {
+        let mut vertices = Vec::new();
+        for j in 0..=count {
+            for i in 0..=count {
+                vertices.push(Point3::new(i as f64, j as f64, 0.0) / count as f64);
+            }
+        }
+        let row = count + 1;
+        let mut faces = Vec::new();
+        for j in 0..count {
+            for i in 0..count {
+                let v = j * row + i;
+                faces.push((v, v + 1, v + row + 1));
+                faces.push((v, v + row + 1, v + row));
+            }
+        }
+        Trimesh::new(vertices, faces, None, None).unwrap()
+    }
+
+    #[test]
+    fn test_facets() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        let facets = mesh.facets(1e-8);
+        assert_eq!(facets.len(), 6);
+        assert!(facets.iter().all(|f| f.len() == 2));
+
+        let facets = grid(4).facets(1e-8);
+        assert_eq!(facets, vec![(0..32).collect::<Vec<_>>()]);
+
+        assert!(Trimesh::default().facets(1e-8).is_empty());
+    }
+
+    #[test]
+    fn test_simplify_planar() {
+        let mesh = grid(4);
+        let simple = mesh.simplify_planar(1e-8).unwrap();
+
+        // the 9 interior vertices are removed and the 16 on the
+        // outline triangulate into 14 triangles
+        assert_eq!(simple.vertices.len(), 16);
+        assert_eq!(simple.faces.len(), 14);
+        assert_relative_eq!(simple.area(), mesh.area(), epsilon = 1e-10);
+        // every face should still point the same way
+        for normal in simple.face_normals() {
+            assert_relative_eq!(normal.z, 1.0, epsilon = 1e-10);
+        }
+
+        // a box is already minimal so nothing should change
+        let mesh = create_box(&[1.0, 2.0, 3.0]);
+        let simple = mesh.simplify_planar(1e-8).unwrap();
+        assert_eq!(simple.faces.len(), 12);
+        assert_relative_eq!(simple.area(), mesh.area(), epsilon = 1e-10);
+        assert!(simple.winding_number(&[Point3::origin()])[0] > 0.99);
+
+        assert!(Trimesh::default().simplify_planar(1e-8).unwrap().is_empty());
+    }
+}
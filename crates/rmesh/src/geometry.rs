@@ -1,3 +1,4 @@
+use crate::bvh::Bounds;
 use crate::mesh::Trimesh;
 use crate::path::Path;
 
@@ -5,3 +6,14 @@ pub enum Geometry {
     Mesh(Box<Trimesh>),
     Path(Path),
 }
+
+impl Geometry {
+    /// An axis aligned box containing the geometry in its own frame,
+    /// or `None` if the geometry is empty.
+    pub fn bounds(&self) -> Option<Bounds> {
+        match self {
+            Geometry::Mesh(mesh) => mesh.bounds(),
+            Geometry::Path(path) => path.bounds(),
+        }
+    }
+}
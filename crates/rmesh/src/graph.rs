@@ -0,0 +1,147 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use anyhow::{Result, anyhow};
+use rmesh_macro::cache_access;
+
+use crate::mesh::Trimesh;
+
+impl Trimesh {
+    /// The vertices sharing an edge with each vertex, sorted
+    /// by index, which is the mesh viewed as a graph.
+    #[cache_access]
+    pub fn vertex_adjacency(&self) -> Vec<Vec<usize>> {
+        let mut neighbors = vec![Vec::new(); self.vertices.len()];
+        for [a, b] in self.edges().iter() {
+            neighbors[*a].push(*b);
+            neighbors[*b].push(*a);
+        }
+        for list in neighbors.iter_mut() {
+            list.sort_unstable();
+            list.dedup();
+        }
+        neighbors
+    }
+
+    /// The number of vertices sharing an edge with each vertex.
+    pub fn vertex_degree(&self) -> Vec<usize> {
+        self.vertex_adjacency().iter().map(|n| n.len()).collect()
+    }
+
+    /// The length of the shortest path along the edges of the mesh from
+    /// any of a set of source vertices to each of a set of target vertices,
+    /// using Dijkstra's algorithm which stops once every target is reached.
+    /// This approximates the geodesic distance over the surface from above,
+    /// and gets closer as the mesh is refined.
+    ///
+    /// Parameters
+    /// ------------
+    /// sources
+    ///   The vertices to measure from.
+    /// targets
+    ///   The vertices to measure to.
+    ///
+    /// Returns
+    /// ------------
+    /// distance
+    ///   The distance to each target in order, which is infinity for a
+    ///   target not connected to any source, or an error if any vertex
+    ///   index is out of range.
+    pub fn geodesic_distance(&self, sources: &[usize], targets: &[usize]) -> Result<Vec<f64>> {
+        let count = self.vertices.len();
+        if let Some(bad) = sources.iter().chain(targets.iter()).find(|v| **v >= count) {
+            return Err(anyhow!(
+                "Vertex index {bad} is out of range for {count} vertices"
+            ));
+        }
+        let adjacency = self.vertex_adjacency();
+        let mut distance = vec![f64::INFINITY; count];
+        let mut remaining = vec![false; count];
+        for target in targets.iter() {
+            remaining[*target] = true;
+        }
+        let mut unreached = remaining.iter().filter(|r| **r).count();
+
+        // the bits of a non-negative float sort in the same order as its value
+        let mut heap = BinaryHeap::new();
+        for source in sources.iter() {
+            distance[*source] = 0.0;
+            heap.push(Reverse((0.0_f64.to_bits(), *source)));
+        }
+        while let Some(Reverse((bits, vertex))) = heap.pop() {
+            let current = f64::from_bits(bits);
+            if current > distance[vertex] {
+                continue;
+            }
+            if remaining[vertex] {
+                remaining[vertex] = false;
+                unreached -= 1;
+                if unreached == 0 {
+                    break;
+                }
+            }
+            for neighbor in adjacency[vertex].iter() {
+                let next = current + (self.vertices[*neighbor] - self.vertices[vertex]).norm();
+                if next < distance[*neighbor] {
+                    distance[*neighbor] = next;
+                    heap.push(Reverse((next.to_bits(), *neighbor)));
+                }
+            }
+        }
+        Ok(targets.iter().map(|t| distance[*t]).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use approx::assert_relative_eq;
+    use nalgebra::Point3;
+
+    #[test]
+    fn test_geodesic() {
+        // a flat grid of 5 by 5 vertices with unit spacing
+        let size = 5;
+        let mut vertices: Vec<Point3<f64>> = (0..size * size)
+            .map(|i| Point3::new((i % size) as f64, (i / size) as f64, 0.0))
+            .collect();
+        let mut faces = Vec::new();
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let a = y * size + x;
+                faces.push((a, a + 1, a + size + 1));
+                faces.push((a, a + size + 1, a + size));
+            }
+        }
+        // a separate triangle which nothing can reach
+        vertices.extend([
+            Point3::new(10.0, 0.0, 0.0),
+            Point3::new(11.0, 0.0, 0.0),
+            Point3::new(10.0, 1.0, 0.0),
+        ]);
+        faces.push((25, 26, 27));
+        let mesh = Trimesh::new(vertices, faces, None, None).unwrap();
+
+        let adjacency = mesh.vertex_adjacency();
+        assert_eq!(adjacency[0], vec![1, 5, 6]);
+        assert_eq!(adjacency[12], vec![6, 7, 11, 13, 17, 18]);
+        let degree = mesh.vertex_degree();
+        assert_eq!((degree[0], degree[4], degree[12], degree[25]), (3, 2, 6, 2));
+
+        let distance = mesh.geodesic_distance(&[0], &[0, 4, 24, 7, 25]).unwrap();
+        assert_relative_eq!(distance[0], 0.0);
+        // along the edge of the grid
+        assert_relative_eq!(distance[1], 4.0);
+        // along the diagonals
+        assert_relative_eq!(distance[2], 4.0 * 2.0_f64.sqrt());
+        // one diagonal and one edge
+        assert_relative_eq!(distance[3], 1.0 + 2.0_f64.sqrt());
+        assert!(distance[4].is_infinite());
+
+        // the nearest of several sources is used
+        let distance = mesh.geodesic_distance(&[0, 4], &[3]).unwrap();
+        assert_relative_eq!(distance[0], 1.0);
+        assert!(mesh.geodesic_distance(&[0], &[100]).is_err());
+    }
+}
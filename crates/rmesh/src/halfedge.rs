@@ -0,0 +1,281 @@
+use std::sync::Arc;
+
+use ahash::AHashMap;
+use rmesh_macro::cache_access;
+
+use crate::mesh::Trimesh;
+
+// the value stored for a half edge without a twin or a vertex without a face
+const NONE: usize = usize::MAX;
+
+/// The half edges of a mesh for walking around its faces and vertices.
+/// Half edge `3 * f + i` runs from corner `i` of face `f` to the next
+/// corner, so the next and previous half edges and the face of each are
+/// implied by their index and only the twins are stored.
+///
+/// An edge shared by more than two faces pairs the first two and leaves
+/// the rest without a twin, so they're treated as boundary edges.
+#[derive(Debug, Clone, Default)]
+pub struct HalfEdgeMesh {
+    // the vertex each half edge starts from
+    origin: Vec<usize>,
+    // the half edge running the other way along the same edge
+    twin: Vec<usize>,
+    // a half edge starting from each vertex, which is a boundary half
+    // edge for a vertex on the boundary so a walk around it starts there
+    outgoing: Vec<usize>,
+}
+
+impl HalfEdgeMesh {
+    /// Build the half edges of a mesh.
+    ///
+    /// Parameters
+    /// ------------
+    /// mesh
+    ///   The mesh whose faces to connect.
+    ///
+    /// Returns
+    /// ------------
+    /// half_edges
+    ///   Three half edges for every face.
+    pub fn new(mesh: &Trimesh) -> Self {
+        let origin: Vec<usize> = mesh.faces.iter().flat_map(|f| [f.0, f.1, f.2]).collect();
+        let mut twin = vec![NONE; origin.len()];
+        let mut open: AHashMap<[usize; 2], usize> = AHashMap::with_capacity(origin.len());
+        for h in 0..origin.len() {
+            let (a, b) = (origin[h], origin[next(h)]);
+            match open.remove(&[b, a]) {
+                Some(other) => {
+                    twin[h] = other;
+                    twin[other] = h;
+                }
+                None => {
+                    open.entry([a, b]).or_insert(h);
+                }
+            }
+        }
+        let mut outgoing = vec![NONE; mesh.vertices.len()];
+        for (h, v) in origin.iter().enumerate() {
+            if outgoing[*v] == NONE || twin[h] == NONE {
+                outgoing[*v] = h;
+            }
+        }
+        Self {
+            origin,
+            twin,
+            outgoing,
+        }
+    }
+
+    /// The number of half edges, which is three per face.
+    pub fn len(&self) -> usize {
+        self.origin.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.origin.is_empty()
+    }
+
+    /// The next half edge around the same face.
+    pub fn next(&self, h: usize) -> usize {
+        next(h)
+    }
+
+    /// The previous half edge around the same face.
+    pub fn prev(&self, h: usize) -> usize {
+        h - h % 3 + (h + 2) % 3
+    }
+
+    /// The half edge running the other way along the same edge,
+    /// or `None` for an edge on the boundary.
+    pub fn twin(&self, h: usize) -> Option<usize> {
+        Some(self.twin[h]).filter(|t| *t != NONE)
+    }
+
+    /// The face a half edge belongs to.
+    pub fn face(&self, h: usize) -> usize {
+        h / 3
+    }
+
+    /// The vertex a half edge starts from.
+    pub fn origin(&self, h: usize) -> usize {
+        self.origin[h]
+    }
+
+    /// The vertex a half edge ends at.
+    pub fn target(&self, h: usize) -> usize {
+        self.origin[next(h)]
+    }
+
+    /// Is a half edge on the boundary of the mesh.
+    pub fn is_boundary(&self, h: usize) -> bool {
+        self.twin[h] == NONE
+    }
+
+    /// The half edges starting from a vertex in order around it,
+    /// which starts on the boundary for a vertex on the boundary.
+    /// A vertex where separate fans of faces meet only returns the
+    /// fan containing its stored half edge.
+    pub fn outgoing(&self, vertex: usize) -> Vec<usize> {
+        let mut result = Vec::new();
+        let Some(start) = self.outgoing.get(vertex).copied().filter(|h| *h != NONE) else {
+            return result;
+        };
+        let mut h = start;
+        loop {
+            result.push(h);
+            match self.twin(self.prev(h)) {
+                Some(t) if t != start => h = t,
+                _ => break,
+            }
+        }
+        result
+    }
+
+    /// The vertices sharing an edge with a vertex in order around it,
+    /// as in `outgoing`.
+    pub fn vertex_ring(&self, vertex: usize) -> Vec<usize> {
+        let outgoing = self.outgoing(vertex);
+        let mut ring: Vec<usize> = outgoing.iter().map(|h| self.target(*h)).collect();
+        // the last neighbor of a boundary vertex is only on an incoming edge
+        if let Some(last) = outgoing.last()
+            && self.is_boundary(self.prev(*last))
+        {
+            ring.push(self.origin(self.prev(*last)));
+        }
+        ring
+    }
+
+    /// The faces around a vertex in order, as in `outgoing`.
+    pub fn vertex_faces(&self, vertex: usize) -> Vec<usize> {
+        self.outgoing(vertex)
+            .iter()
+            .map(|h| self.face(*h))
+            .collect()
+    }
+
+    /// Chain the boundary half edges into loops of vertex indices, which
+    /// follow the winding of the faces like `facets::boundary_loops`.
+    ///
+    /// Returns
+    /// ------------
+    /// loops
+    ///   The vertices of every boundary loop, each starting from the
+    ///   lowest numbered half edge on it.
+    pub fn boundary_loops(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.len()];
+        let mut loops = Vec::new();
+        for start in (0..self.len()).filter(|h| self.is_boundary(*h)) {
+            if visited[start] {
+                continue;
+            }
+            let mut chain = Vec::new();
+            let mut h = start;
+            while !visited[h] {
+                visited[h] = true;
+                chain.push(self.origin(h));
+                // rotate around the end vertex to the next boundary half edge
+                h = self.next(h);
+                let mut steps = 0;
+                while let Some(t) = self.twin(h) {
+                    h = self.next(t);
+                    steps += 1;
+                    if steps > self.len() {
+                        break;
+                    }
+                }
+            }
+            loops.push(chain);
+        }
+        loops
+    }
+}
+
+/// The next half edge around the same face.
+fn next(h: usize) -> usize {
+    h - h % 3 + (h + 1) % 3
+}
+
+impl Trimesh {
+    /// The half edges of the mesh, which are built on first use
+    /// and rebuilt after the faces change.
+    #[cache_access]
+    pub fn half_edges(&self) -> Arc<HalfEdgeMesh> {
+        Arc::new(HalfEdgeMesh::new(self))
+    }
+}
+
+impl HalfEdgeMesh {
+    /// The approximate number of bytes the structure uses.
+    pub(crate) fn memory(&self) -> usize {
+        (self.origin.len() + self.twin.len() + self.outgoing.len()) * size_of::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::creation::{create_box, create_sphere};
+    use crate::facets::boundary_loops;
+
+    #[test]
+    fn test_half_edges() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        let half = mesh.half_edges();
+        assert_eq!(half.len(), 36);
+        for h in 0..half.len() {
+            // a closed mesh has a twin for every half edge
+            let t = half.twin(h).unwrap();
+            assert_eq!(half.twin(t), Some(h));
+            assert_eq!(half.origin(t), half.target(h));
+            assert_eq!(half.next(half.prev(h)), h);
+            assert_eq!(half.face(half.next(h)), half.face(h));
+        }
+        assert!(half.boundary_loops().is_empty());
+
+        // the ring around each vertex is its neighbors in the graph
+        let adjacency = mesh.vertex_adjacency();
+        for (v, neighbors) in adjacency.iter().enumerate() {
+            let mut ring = half.vertex_ring(v);
+            assert_eq!(ring.len(), half.vertex_faces(v).len());
+            ring.sort_unstable();
+            assert_eq!(&ring, neighbors);
+        }
+
+        // removing a cap of faces opens a hole
+        let sphere = create_sphere(1.0, [16, 8]).unwrap();
+        let keep: Vec<usize> = (0..sphere.faces.len())
+            .filter(|f| sphere.triangles().nth(*f).unwrap()[0].z < 0.9)
+            .collect();
+        let open = sphere.submesh(&keep).unwrap();
+        let half = open.half_edges();
+        let loops = half.boundary_loops();
+        assert_eq!(loops.len(), 1);
+        let expected = boundary_loops(&open.faces).unwrap();
+        assert_eq!(loops[0].len(), expected[0].len());
+        let start = expected[0].iter().position(|v| *v == loops[0][0]).unwrap();
+        let mut rotated = expected[0].clone();
+        rotated.rotate_left(start);
+        assert_eq!(loops[0], rotated);
+
+        // a boundary vertex's ring is one longer than its faces
+        let v = loops[0][0];
+        assert!(half.is_boundary(half.outgoing(v)[0]));
+        assert_eq!(half.vertex_ring(v).len(), half.vertex_faces(v).len() + 1);
+        let mut ring = half.vertex_ring(v);
+        ring.sort_unstable();
+        assert_eq!(ring, open.vertex_adjacency()[v]);
+
+        // the cached structure is rebuilt when the faces change
+        let mut changed = open.clone();
+        changed.faces_mut().truncate(1);
+        assert_eq!(changed.half_edges().len(), 3);
+        assert_eq!(
+            changed.half_edges().boundary_loops(),
+            vec![{
+                let f = changed.faces[0];
+                vec![f.0, f.1, f.2]
+            }]
+        );
+    }
+}
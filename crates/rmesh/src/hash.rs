@@ -0,0 +1,143 @@
+use nalgebra::SymmetricEigen;
+
+use crate::mesh::Trimesh;
+
+/// The significant digits each value of `identifier` is rounded to
+/// before hashing, so the floating point noise of moving a mesh
+/// around doesn't change its hash.
+const IDENTIFIER_DIGITS: i32 = 6;
+
+/// Round a value to a number of significant digits.
+fn round_significant(value: f64, digits: i32) -> f64 {
+    if value == 0.0 || !value.is_finite() {
+        return value;
+    }
+    let scale = 10.0_f64.powi(digits - 1 - value.abs().log10().floor() as i32);
+    (value * scale).round() / scale
+}
+
+impl Trimesh {
+    /// Properties of the shape which don't change when it's moved,
+    /// rotated, scaled or has its faces and vertices reordered, so two
+    /// copies of a part from different sources can be matched. Every
+    /// value is zero for a mesh which doesn't enclose any volume.
+    ///
+    /// Returns
+    /// ------------
+    /// identifier
+    ///   The isoperimetric ratio of the area cubed to the volume squared
+    ///   which is one for a sphere, the ratio of the volume to the volume
+    ///   of the convex hull, and the smallest and middle principal moment
+    ///   of inertia each divided by the largest.
+    pub fn identifier(&self) -> Vec<f64> {
+        let volume = self.volume().abs();
+        let Some(inertia) = self.moment_inertia().filter(|_| volume > 0.0) else {
+            return vec![0.0; 4];
+        };
+        let mut moments: Vec<f64> = SymmetricEigen::new(inertia)
+            .eigenvalues
+            .iter()
+            .map(|m| m.abs())
+            .collect();
+        moments.sort_by(f64::total_cmp);
+        let hull = self
+            .convex_hull()
+            .map(|h| h.volume().abs())
+            .unwrap_or_default();
+        let ratio = |a: f64, b: f64| if b > 0.0 { a / b } else { 0.0 };
+        vec![
+            ratio(
+                self.area().powi(3),
+                36.0 * std::f64::consts::PI * volume * volume,
+            ),
+            ratio(volume, hull),
+            ratio(moments[0], moments[2]),
+            ratio(moments[1], moments[2]),
+        ]
+    }
+
+    /// A BLAKE3 hash of `identifier` with each value rounded to a few
+    /// significant digits, which is the same for copies of a part in
+    /// different places and sizes. Values which round differently
+    /// near the edge of a digit still hash differently.
+    pub fn identifier_hash(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        for value in self.identifier() {
+            hasher.update(&round_significant(value, IDENTIFIER_DIGITS).to_le_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    /// A BLAKE3 hash of the exact vertices and faces, which is the same
+    /// on every platform and changes if any coordinate or index changes.
+    /// It's collision resistant, so it can find duplicates across a
+    /// corpus and key caches of values computed from a mesh.
+    pub fn content_hash(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&(self.vertices.len() as u64).to_le_bytes());
+        for vertex in self.vertices.iter() {
+            for c in vertex.iter() {
+                hasher.update(&c.to_le_bytes());
+            }
+        }
+        for face in self.faces.iter() {
+            for i in [face.0, face.1, face.2] {
+                hasher.update(&(i as u64).to_le_bytes());
+            }
+        }
+        hasher.finalize().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::{create_box, create_sphere};
+    use approx::assert_relative_eq;
+    use nalgebra::{Matrix4, Vector3};
+
+    #[test]
+    fn test_hash() {
+        let mesh = create_box(&[1.0, 2.0, 3.0]);
+        let identifier = mesh.identifier();
+        // the box is convex and its moments are 13, 10 and 5 over 12
+        assert_relative_eq!(identifier[1], 1.0, epsilon = 1e-10);
+        assert_relative_eq!(identifier[2], 5.0 / 13.0, epsilon = 1e-10);
+        assert_relative_eq!(identifier[3], 10.0 / 13.0, epsilon = 1e-10);
+        let sphere = create_sphere(1.0, [64, 32]).unwrap();
+        assert_relative_eq!(sphere.identifier()[0], 1.0, epsilon = 1e-2);
+
+        // moving, turning and scaling keep the identifier
+        let moved = mesh
+            .apply_transform(
+                &(Matrix4::new_rotation(Vector3::new(0.3, 0.2, 0.1))
+                    * Matrix4::new_translation(&Vector3::new(5.0, -1.0, 2.0))
+                    * Matrix4::new_scaling(2.5)),
+            )
+            .unwrap();
+        for (a, b) in identifier.iter().zip(moved.identifier().iter()) {
+            assert_relative_eq!(a, b, epsilon = 1e-9);
+        }
+        assert_eq!(mesh.identifier_hash(), moved.identifier_hash());
+        assert_ne!(mesh.identifier_hash(), sphere.identifier_hash());
+        assert_eq!(Trimesh::default().identifier(), vec![0.0; 4]);
+
+        // the content hash only matches exactly the same buffers
+        assert_eq!(
+            mesh.content_hash(),
+            create_box(&[1.0, 2.0, 3.0]).content_hash()
+        );
+        assert_ne!(mesh.content_hash(), moved.content_hash());
+        let mut swapped = create_box(&[1.0, 2.0, 3.0]);
+        swapped.faces_mut().swap(0, 1);
+        assert_ne!(mesh.content_hash(), swapped.content_hash());
+        // an empty mesh only hashes its vertex count
+        assert_eq!(
+            Trimesh::default().content_hash(),
+            *blake3::hash(&0u64.to_le_bytes()).as_bytes()
+        );
+        assert_eq!(round_significant(123.4567, 3), 123.0);
+        assert_eq!(round_significant(0.0012345, 2), 0.0012);
+    }
+}
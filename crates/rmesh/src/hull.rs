@@ -0,0 +1,253 @@
+use ahash::{AHashMap, AHashSet};
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector3};
+
+use crate::mesh::Trimesh;
+
+/// A face of the hull while it's being built, with the points
+/// which are still outside of it.
+struct HullFace {
+    vertices: [usize; 3],
+    normal: Vector3<f64>,
+    offset: f64,
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+impl HullFace {
+    fn new(points: &[Point3<f64>], vertices: [usize; 3]) -> Self {
+        let [a, b, c] = vertices.map(|v| points[v]);
+        let normal = (b - a).cross(&(c - a)).normalize();
+        Self {
+            vertices,
+            normal,
+            offset: normal.dot(&a.coords),
+            outside: Vec::new(),
+            alive: true,
+        }
+    }
+
+    /// The signed distance of a point above the face.
+    fn distance(&self, point: &Point3<f64>) -> f64 {
+        self.normal.dot(&point.coords) - self.offset
+    }
+}
+
+/// Give each point to the first face it's outside of, dropping the
+/// points which are inside of every face.
+fn assign_outside(
+    points: &[Point3<f64>],
+    candidates: impl IntoIterator<Item = usize>,
+    faces: &mut [HullFace],
+    new_faces: &[usize],
+    tolerance: f64,
+) {
+    for p in candidates {
+        if let Some(f) = new_faces
+            .iter()
+            .find(|f| faces[**f].distance(&points[p]) > tolerance)
+        {
+            faces[*f].outside.push(p);
+        }
+    }
+}
+
+/// Compute the convex hull of a set of points using quickhull.
+///
+/// Parameters
+/// ------------
+/// points
+///   The points to wrap.
+///
+/// Returns
+/// ------------
+/// hull
+///   A closed mesh with outward facing faces which only references the
+///   points on the hull, or an error if the points are all coplanar.
+pub fn convex_hull(points: &[Point3<f64>]) -> Result<Trimesh> {
+    if points.len() < 4 {
+        return Err(anyhow!("A hull needs at least 4 points: {}", points.len()));
+    }
+    let (lower, upper) = points
+        .iter()
+        .fold((points[0], points[0]), |(l, u), p| (l.inf(p), u.sup(p)));
+    let scale = (upper - lower).norm();
+    let tolerance = scale * 1e-10;
+    if !tolerance.is_finite() {
+        return Err(anyhow!("Hull points must be finite"));
+    }
+
+    // start with the simplex of two extremes along the widest axis, the
+    // point furthest from their line and the point furthest from that plane
+    let axis = (upper - lower).imax();
+    let (i0, i1) = points.iter().enumerate().fold((0, 0), |(lo, hi), (i, p)| {
+        (
+            if p[axis] < points[lo][axis] { i } else { lo },
+            if p[axis] > points[hi][axis] { i } else { hi },
+        )
+    });
+    let line = (points[i1] - points[i0]).normalize();
+    let furthest = |distance: &dyn Fn(&Point3<f64>) -> f64| {
+        (0..points.len())
+            .map(|i| (i, distance(&points[i])))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap()
+    };
+    let (i2, d2) = furthest(&|p| {
+        let v = p - points[i0];
+        (v - line * v.dot(&line)).norm()
+    });
+    if d2 <= tolerance {
+        return Err(anyhow!("Hull points are collinear"));
+    }
+    let plane = (points[i1] - points[i0])
+        .cross(&(points[i2] - points[i0]))
+        .normalize();
+    let (i3, d3) = furthest(&|p| plane.dot(&(p - points[i0])).abs());
+    if d3 <= tolerance {
+        return Err(anyhow!("Hull points are coplanar"));
+    }
+
+    // wind the simplex so every face points away from its fourth vertex
+    let (i1, i2) = if plane.dot(&(points[i3] - points[i0])) > 0.0 {
+        (i2, i1)
+    } else {
+        (i1, i2)
+    };
+    let mut faces: Vec<HullFace> = [[i0, i1, i2], [i0, i3, i1], [i1, i3, i2], [i2, i3, i0]]
+        .into_iter()
+        .map(|v| HullFace::new(points, v))
+        .collect();
+    assign_outside(
+        points,
+        0..points.len(),
+        &mut faces,
+        &[0, 1, 2, 3],
+        tolerance,
+    );
+
+    while let Some(start) = faces.iter().position(|f| f.alive && !f.outside.is_empty()) {
+        // the point furthest outside of the face is always on the hull
+        let eye = *faces[start]
+            .outside
+            .iter()
+            .max_by(|a, b| {
+                let face = &faces[start];
+                face.distance(&points[**a])
+                    .total_cmp(&face.distance(&points[**b]))
+            })
+            .unwrap();
+
+        // every face the point can see gets replaced
+        let visible: Vec<usize> = (0..faces.len())
+            .filter(|f| faces[*f].alive && faces[*f].distance(&points[eye]) > tolerance)
+            .collect();
+        let edges: Vec<(usize, usize)> = visible
+            .iter()
+            .flat_map(|f| {
+                let [a, b, c] = faces[*f].vertices;
+                [(a, b), (b, c), (c, a)]
+            })
+            .collect();
+        let interior: AHashSet<(usize, usize)> = edges.iter().copied().collect();
+
+        // connect the eye to the horizon, i.e. the edges between visible
+        // and hidden faces, keeping the winding of the visible faces
+        let mut orphans = Vec::new();
+        for f in visible.iter() {
+            faces[*f].alive = false;
+            orphans.append(&mut faces[*f].outside);
+        }
+        let mut new_faces = Vec::new();
+        for (a, b) in edges.iter() {
+            if !interior.contains(&(*b, *a)) {
+                new_faces.push(faces.len());
+                faces.push(HullFace::new(points, [*a, *b, eye]));
+            }
+        }
+        assign_outside(
+            points,
+            orphans.into_iter().filter(|p| *p != eye),
+            &mut faces,
+            &new_faces,
+            tolerance,
+        );
+    }
+
+    // only keep the points which are referenced by the hull
+    let mut remap: AHashMap<usize, usize> = AHashMap::new();
+    let mut vertices = Vec::new();
+    let hull_faces = faces
+        .iter()
+        .filter(|f| f.alive)
+        .map(|f| {
+            let [a, b, c] = f.vertices.map(|v| {
+                *remap.entry(v).or_insert_with(|| {
+                    vertices.push(points[v]);
+                    vertices.len() - 1
+                })
+            });
+            (a, b, c)
+        })
+        .collect();
+    Ok(Trimesh::new(vertices, hull_faces, None, None)?)
+}
+
+impl Trimesh {
+    /// The convex hull of the vertices of this mesh.
+    ///
+    /// Returns
+    /// ------------
+    /// hull
+    ///   A closed mesh with outward facing faces, or an error if the
+    ///   mesh is flat or has fewer than 4 vertices.
+    pub fn convex_hull(&self) -> Result<Trimesh> {
+        convex_hull(&self.vertices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::exchange::{MeshFormat, load_mesh};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_convex_hull() {
+        // a cube with a point in the middle and extra points on its faces
+        let mut points: Vec<Point3<f64>> = (0..8)
+            .map(|i| Point3::new((i & 1) as f64, ((i >> 1) & 1) as f64, (i >> 2) as f64))
+            .collect();
+        points.push(Point3::new(0.5, 0.5, 0.5));
+        points.push(Point3::new(0.5, 0.5, 1.0));
+        points.push(Point3::new(0.0, 0.3, 0.2));
+        let hull = convex_hull(&points).unwrap();
+        assert_eq!(hull.vertices.len(), 8);
+        assert_eq!(hull.faces.len(), 12);
+        assert_relative_eq!(hull.volume(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(hull.area(), 6.0, epsilon = 1e-10);
+
+        let mesh = load_mesh(
+            include_bytes!("../../../test/data/fuze.obj"),
+            MeshFormat::OBJ,
+        )
+        .unwrap();
+        let hull = mesh.convex_hull().unwrap();
+        // every face of a convex mesh has every vertex behind it
+        let normals = hull.face_normals();
+        for (f, n) in hull.faces.iter().zip(normals.iter()) {
+            let origin = hull.vertices[f.0];
+            assert!(mesh.vertices.iter().all(|v| n.dot(&(v - origin)) < 1e-8));
+        }
+        // closed and consistently wound with every edge used once each way
+        let edges: AHashSet<[usize; 2]> = hull.edges().into_iter().collect();
+        assert_eq!(edges.len(), hull.faces.len() * 3);
+        assert!(edges.iter().all(|[a, b]| edges.contains(&[*b, *a])));
+        assert!(hull.volume() > 0.0);
+
+        let flat: Vec<Point3<f64>> = points.iter().map(|p| Point3::new(p.x, p.y, 0.0)).collect();
+        assert!(convex_hull(&flat).is_err());
+        assert!(convex_hull(&points[..3]).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use rmesh_macro::cache_access;
+
+use crate::error::RmeshError;
+use crate::mesh::Trimesh;
+
+/// An integer type vertex indices can be stored as outside of a mesh,
+/// such as the `u32` of a GPU index buffer or of parry, which is narrower
+/// than the `usize` of `Trimesh::faces` on 64 bit targets.
+pub trait FaceIndex: Copy + Send + Sync + 'static {
+    /// The largest index the type can hold.
+    const MAX: usize;
+
+    /// Convert an index known to be at most `MAX`.
+    fn from_index(index: usize) -> Self;
+
+    /// Convert the value back to an index.
+    fn index(self) -> usize;
+}
+
+macro_rules! face_index {
+    ($($type:ty),*) => {
+        $(impl FaceIndex for $type {
+            const MAX: usize = <$type>::MAX as usize;
+
+            fn from_index(index: usize) -> Self {
+                index as $type
+            }
+
+            fn index(self) -> usize {
+                self as usize
+            }
+        })*
+    };
+}
+
+face_index!(u16, u32, usize);
+
+/// Check every vertex of a mesh can be indexed by a type.
+///
+/// Parameters
+/// ------------
+/// count
+///   The number of vertices.
+///
+/// Returns
+/// ------------
+/// Result<(), RmeshError>
+///   An `InvalidGeometry` error if the last vertex index doesn't fit.
+pub fn check_index<I: FaceIndex>(count: usize) -> Result<(), RmeshError> {
+    if count > 0 && count - 1 > I::MAX {
+        return Err(RmeshError::InvalidGeometry(format!(
+            "{count} vertices can't be indexed by `{}` with a maximum of {}",
+            std::any::type_name::<I>(),
+            I::MAX
+        )));
+    }
+    Ok(())
+}
+
+impl Trimesh {
+    /// The faces as arrays of a narrower index type, such as the
+    /// `[u32; 3]` a GPU index buffer or parry's `TriMesh` expects.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Vec<[I; 3]>, RmeshError>
+    ///   The vertex indices of every face, or an `InvalidGeometry`
+    ///   error rather than wrapping if a vertex index doesn't fit.
+    pub fn faces_as<I: FaceIndex>(&self) -> Result<Vec<[I; 3]>, RmeshError> {
+        check_index::<I>(self.vertices.len())?;
+        Ok(self
+            .faces
+            .iter()
+            .map(|f| [f.0, f.1, f.2].map(I::from_index))
+            .collect())
+    }
+
+    /// The faces with `u32` indices, as in `faces_as`.
+    pub fn faces_u32(&self) -> Result<Vec<[u32; 3]>, RmeshError> {
+        self.faces_as()
+    }
+
+    /// The vertices as a flat slice of XYZ values, which is the same
+    /// memory as `vertices` so it's free to get.
+    pub fn vertices_flat(&self) -> &[f64] {
+        bytemuck::cast_slice(&self.vertices)
+    }
+
+    /// The faces as a flat buffer of `u32` vertex indices, three per face,
+    /// which is built once and cached so repeated uploads to a GPU or
+    /// conversions for other libraries share it rather than converting
+    /// the faces every time. It's shared rather than borrowed as the
+    /// cache can be replaced while it's in use.
+    ///
+    /// Returns
+    /// ------------
+    /// faces
+    ///   The indices, or `None` if a vertex index doesn't fit in a `u32`.
+    #[cache_access]
+    pub fn faces_flat(&self) -> Option<Arc<[u32]>> {
+        check_index::<u32>(self.vertices.len()).ok()?;
+        Some(
+            self.faces
+                .iter()
+                .flat_map(|f| [f.0 as u32, f.1 as u32, f.2 as u32])
+                .collect(),
+        )
+    }
+
+    /// Create a mesh from faces with indices of any `FaceIndex` type.
+    ///
+    /// Parameters
+    /// ------------
+    /// vertices
+    ///   The position of every vertex.
+    /// faces
+    ///   The vertex indices of every face.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Trimesh, RmeshError>
+    ///   The mesh or an error if a face references a missing vertex.
+    pub fn from_indexed<I: FaceIndex>(
+        vertices: Vec<nalgebra::Point3<f64>>,
+        faces: &[[I; 3]],
+    ) -> Result<Self, RmeshError> {
+        let faces = faces
+            .iter()
+            .map(|f| (f[0].index(), f[1].index(), f[2].index()))
+            .collect();
+        Trimesh::new(vertices, faces, None, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+
+    #[test]
+    fn test_face_index() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        let faces = mesh.faces_u32().unwrap();
+        assert_eq!(faces.len(), 12);
+        let rebuilt = Trimesh::from_indexed(mesh.vertices.clone(), &faces).unwrap();
+        assert_eq!(rebuilt.faces, mesh.faces);
+        let small: Vec<[u16; 3]> = mesh.faces_as().unwrap();
+        assert_eq!(
+            small[0].map(|i| i as usize),
+            [mesh.faces[0].0, mesh.faces[0].1, mesh.faces[0].2]
+        );
+
+        // the last index has to fit rather than the count
+        assert!(check_index::<u16>(65536).is_ok());
+        assert!(check_index::<u16>(65537).is_err());
+        assert!(check_index::<u32>(0).is_ok());
+        assert!(matches!(
+            check_index::<u16>(70000),
+            Err(RmeshError::InvalidGeometry(_))
+        ));
+        assert!(Trimesh::from_indexed(mesh.vertices.clone(), &[[0_u32, 1, 8]]).is_err());
+
+        // the flat buffers match the nested ones
+        assert_eq!(
+            mesh.vertices_flat()[3..6],
+            mesh.vertices[1].coords.as_slice()[..]
+        );
+        let flat = mesh.faces_flat().unwrap();
+        assert_eq!(flat.len(), 36);
+        assert_eq!(
+            flat.chunks(3)
+                .map(|f| [f[0], f[1], f[2]])
+                .collect::<Vec<_>>(),
+            faces
+        );
+        // the buffer is shared until the faces change
+        assert!(Arc::ptr_eq(&flat, &mesh.faces_flat().unwrap()));
+        let mut flipped = mesh.clone();
+        flipped.faces_mut()[0] = (0, 2, 1);
+        assert_eq!(flipped.faces_flat().unwrap()[..3], [0, 2, 1]);
+    }
+}
@@ -0,0 +1,278 @@
+use std::sync::Arc;
+
+use nalgebra::Point3;
+use rmesh_macro::cache_access;
+
+use crate::mesh::Trimesh;
+
+/// Ranges of points this short are searched linearly rather than split.
+const LEAF_SIZE: usize = 8;
+
+/// Subtrees with more points than this are built on separate threads.
+const PARALLEL_MIN_POINTS: usize = 4096;
+
+/// A kd-tree over a list of points which answers nearest neighbor and
+/// radius queries in roughly logarithmic rather than linear time.
+///
+/// The tree is implicit: the point indices are ordered so every range
+/// longer than a leaf has its splitting point in the middle with the
+/// points before it below the split and the points after it above.
+#[derive(Debug, Clone, Default)]
+pub struct KdTree {
+    points: Vec<Point3<f64>>,
+    // the point indices in tree order
+    order: Vec<usize>,
+    // the axis each range is split on, stored at the index of its middle
+    axes: Vec<u8>,
+}
+
+/// Order a range of point indices into a subtree.
+fn build(points: &[Point3<f64>], order: &mut [usize], axes: &mut [u8]) {
+    if order.len() <= LEAF_SIZE {
+        return;
+    }
+    // split along the axis the points are most spread out on
+    let (lower, upper) = order
+        .iter()
+        .fold((points[order[0]], points[order[0]]), |(l, u), i| {
+            (l.inf(&points[*i]), u.sup(&points[*i]))
+        });
+    let axis = (upper - lower).imax();
+    let middle = order.len() / 2;
+    order.select_nth_unstable_by(middle, |a, b| points[*a][axis].total_cmp(&points[*b][axis]));
+    axes[middle] = axis as u8;
+
+    let parallel = order.len() > PARALLEL_MIN_POINTS;
+    let (left, right) = order.split_at_mut(middle);
+    let (left_axes, right_axes) = axes.split_at_mut(middle);
+    let (right, right_axes) = (&mut right[1..], &mut right_axes[1..]);
+    if parallel {
+        rayon::join(
+            || build(points, left, left_axes),
+            || build(points, right, right_axes),
+        );
+    } else {
+        build(points, left, left_axes);
+        build(points, right, right_axes);
+    }
+}
+
+impl KdTree {
+    /// The approximate number of bytes the tree uses.
+    pub(crate) fn memory(&self) -> usize {
+        self.points.len() * std::mem::size_of::<Point3<f64>>()
+            + self.order.len() * std::mem::size_of::<usize>()
+            + self.axes.len()
+    }
+
+    /// Build a tree over a list of points, using multiple
+    /// threads for the upper levels of large trees.
+    ///
+    /// Parameters
+    /// ------------
+    /// points
+    ///   The points to index, which are copied into the tree.
+    ///
+    /// Returns
+    /// ------------
+    /// tree
+    ///   A tree referencing points by their index in `points`.
+    pub fn new(points: &[Point3<f64>]) -> Self {
+        let mut order: Vec<usize> = (0..points.len()).collect();
+        let mut axes = vec![0; points.len()];
+        build(points, &mut order, &mut axes);
+        Self {
+            points: points.to_vec(),
+            order,
+            axes,
+        }
+    }
+
+    /// The points the tree was built over.
+    pub fn points(&self) -> &[Point3<f64>] {
+        &self.points
+    }
+
+    /// The number of points in the tree.
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Does the tree contain no points.
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Visit the points of a range closer than a limit, descending into
+    /// the side of each split containing the query point first so the
+    /// limit shrinks before the other side is checked.
+    fn search(
+        &self,
+        range: (usize, usize),
+        point: &Point3<f64>,
+        limit: &mut f64,
+        visit: &mut impl FnMut(usize, f64, &mut f64),
+    ) {
+        let (start, end) = range;
+        if end - start <= LEAF_SIZE {
+            for index in &self.order[start..end] {
+                let distance = (self.points[*index] - point).norm();
+                if distance <= *limit {
+                    visit(*index, distance, limit);
+                }
+            }
+            return;
+        }
+        let middle = start + (end - start) / 2;
+        let index = self.order[middle];
+        let axis = self.axes[middle] as usize;
+        let offset = point[axis] - self.points[index][axis];
+        let (near, far) = if offset < 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+
+        self.search(near, point, limit, visit);
+        let distance = (self.points[index] - point).norm();
+        if distance <= *limit {
+            visit(index, distance, limit);
+        }
+        // the far side is at least as far as the splitting plane
+        if offset.abs() <= *limit {
+            self.search(far, point, limit, visit);
+        }
+    }
+
+    /// Find the points closest to a query point.
+    ///
+    /// Parameters
+    /// ------------
+    /// point
+    ///   The point to search around.
+    /// k
+    ///   The number of neighbors to find.
+    ///
+    /// Returns
+    /// ------------
+    /// nearest
+    ///   Up to `k` pairs of point index and distance from closest to
+    ///   farthest, with ties broken by the lower index.
+    pub fn nearest(&self, point: &Point3<f64>, k: usize) -> Vec<(usize, f64)> {
+        let mut found: Vec<(usize, f64)> = Vec::with_capacity(k + 1);
+        if k == 0 || self.is_empty() {
+            return found;
+        }
+        let mut limit = f64::INFINITY;
+        self.search(
+            (0, self.order.len()),
+            point,
+            &mut limit,
+            &mut |index, distance, limit| {
+                let position = found.partition_point(|(i, d)| (*d, *i) < (distance, index));
+                if position >= k {
+                    return;
+                }
+                found.insert(position, (index, distance));
+                found.truncate(k);
+                if found.len() == k {
+                    *limit = found[k - 1].1;
+                }
+            },
+        );
+        found
+    }
+
+    /// Find every point within a distance of a query point.
+    ///
+    /// Parameters
+    /// ------------
+    /// point
+    ///   The point to search around.
+    /// radius
+    ///   The largest distance of a point to include.
+    ///
+    /// Returns
+    /// ------------
+    /// within
+    ///   The index of every point within the radius in ascending order.
+    pub fn radius(&self, point: &Point3<f64>, radius: f64) -> Vec<usize> {
+        let mut found = Vec::new();
+        if self.is_empty() || radius.is_nan() || radius < 0.0 {
+            return found;
+        }
+        let mut limit = radius;
+        self.search(
+            (0, self.order.len()),
+            point,
+            &mut limit,
+            &mut |index, _, _| found.push(index),
+        );
+        found.sort_unstable();
+        found
+    }
+}
+
+impl Trimesh {
+    /// A kd-tree over the vertices of the mesh which is built on first
+    /// use and accelerates finding the vertices near a point.
+    #[cache_access]
+    pub fn vertex_tree(&self) -> Arc<KdTree> {
+        Arc::new(KdTree::new(&self.vertices))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_kdtree() {
+        // a jittered grid of points with a duplicate
+        let mut points: Vec<Point3<f64>> = (0..1000)
+            .map(|i| {
+                let (x, y, z) = (i % 10, (i / 10) % 10, i / 100);
+                let jitter = ((i * 7919) % 13) as f64 / 100.0;
+                Point3::new(x as f64 + jitter, y as f64 - jitter, z as f64)
+            })
+            .collect();
+        points.push(points[500]);
+        let tree = KdTree::new(&points);
+        assert_eq!(tree.len(), 1001);
+
+        // every query matches a brute force search
+        let queries = [
+            Point3::new(3.3, 4.1, 5.9),
+            Point3::new(-2.0, 0.0, 0.0),
+            Point3::new(9.0, 9.0, 9.0),
+            points[500],
+        ];
+        for query in queries.iter() {
+            let mut brute: Vec<(usize, f64)> = points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (i, (p - query).norm()))
+                .collect();
+            brute.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+            assert_eq!(tree.nearest(query, 5), brute[..5].to_vec());
+
+            let within: Vec<usize> = {
+                let mut w: Vec<usize> = brute
+                    .iter()
+                    .filter(|(_, d)| *d <= 1.5)
+                    .map(|(i, _)| *i)
+                    .collect();
+                w.sort();
+                w
+            };
+            assert_eq!(tree.radius(query, 1.5), within);
+        }
+        // the duplicate is found at zero distance along with the original
+        assert_eq!(tree.radius(&points[500], 0.0), vec![500, 1000]);
+
+        assert!(KdTree::default().nearest(&queries[0], 3).is_empty());
+        assert!(tree.nearest(&queries[0], 0).is_empty());
+        assert_eq!(tree.nearest(&queries[0], 2000).len(), 1001);
+    }
+}
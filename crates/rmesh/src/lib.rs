@@ -1,8 +1,51 @@
+pub mod analysis;
 pub mod attributes;
+pub mod boolean;
+pub mod bounding;
+pub mod bvh;
+pub mod cache;
+pub mod clip;
+pub mod collision;
+pub mod compare;
 pub mod creation;
+pub mod diff;
+pub mod error;
 pub mod exchange;
+pub mod facets;
 pub mod geometry;
+pub mod graph;
+pub mod halfedge;
+pub mod hash;
+pub mod hull;
+pub mod index;
+pub mod kdtree;
+pub mod mass;
 pub mod mesh;
+pub mod mesh32;
+pub mod morph;
+#[cfg(feature = "parry")]
+pub mod parry;
 pub mod path;
+pub mod pose;
+pub mod process;
+pub mod proximity;
+pub mod ray;
+pub mod registration;
+pub mod repair;
 pub mod scene;
+pub mod section;
 pub mod simplify;
+pub mod stats;
+pub mod strips;
+pub mod subdivide;
+pub mod texture;
+pub mod tolerance;
+pub mod transform;
+pub mod unwrap;
+pub mod util;
+#[cfg(feature = "view")]
+pub mod viewer;
+pub mod visual;
+pub mod voxel;
+
+pub use crate::exchange::batch_convert;
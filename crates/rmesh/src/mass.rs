@@ -0,0 +1,193 @@
+use nalgebra::{Matrix3, Matrix4, Point3, SymmetricEigen, Vector3};
+use rayon::prelude::*;
+use rmesh_macro::cache_access;
+
+use crate::mesh::Trimesh;
+
+impl Trimesh {
+    /// The signed volume and the volume weighted centroid of the tetrahedra
+    /// from every face to an apex, which sum to the solid for a closed mesh.
+    fn signed_volume_centroid(&self) -> (f64, Point3<f64>) {
+        let Some((lower, upper)) = self.bounds().filter(|_| !self.is_empty()) else {
+            return (0.0, Point3::origin());
+        };
+        // an apex near the mesh rather than the origin keeps precision
+        let apex = nalgebra::center(&lower, &upper);
+        let (volume, moment) = self
+            .par_triangles()
+            .map(|t| {
+                let [a, b, c] = t.map(|p| p - apex);
+                let volume = a.dot(&b.cross(&c)) / 6.0;
+                (volume, (a + b + c) * (volume / 4.0))
+            })
+            .reduce(|| (0.0, Vector3::zeros()), |x, y| (x.0 + y.0, x.1 + y.1));
+        if volume == 0.0 {
+            return (0.0, apex);
+        }
+        (volume, apex + moment / volume)
+    }
+
+    /// The volume enclosed by the mesh, which is only meaningful for a
+    /// closed mesh and is negative if its faces are wound inward.
+    pub fn volume(&self) -> f64 {
+        self.signed_volume_centroid().0
+    }
+
+    /// The center of mass of the solid enclosed by the mesh with a
+    /// uniform density, or `None` if it doesn't enclose any volume.
+    pub fn center_mass(&self) -> Option<Point3<f64>> {
+        let (volume, centroid) = self.signed_volume_centroid();
+        (volume != 0.0).then_some(centroid)
+    }
+
+    /// The moment of inertia tensor of the solid enclosed by the mesh
+    /// with a density of one, about its center of mass and along the
+    /// axes of the mesh frame.
+    ///
+    /// Returns
+    /// ------------
+    /// inertia
+    ///   The symmetric tensor, or `None` if the mesh doesn't
+    ///   enclose any volume.
+    pub fn moment_inertia(&self) -> Option<Matrix3<f64>> {
+        let (volume, centroid) = self.signed_volume_centroid();
+        if volume == 0.0 {
+            return None;
+        }
+        // the second moment of each tetrahedron from the centroid to a
+        // face is `det / 120 * A * S * A^T` for the corners in `A`
+        let canonical = Matrix3::new(2.0, 1.0, 1.0, 1.0, 2.0, 1.0, 1.0, 1.0, 2.0);
+        let covariance = self
+            .par_triangles()
+            .map(|t| {
+                let corners = Matrix3::from_columns(&t.map(|p| p - centroid));
+                corners * canonical * corners.transpose() * (corners.determinant() / 120.0)
+            })
+            .reduce(Matrix3::zeros, |a, b| a + b);
+        // a mesh wound inward has a negative volume and covariance
+        let covariance = covariance * volume.signum();
+        Some(Matrix3::identity() * covariance.trace() - covariance)
+    }
+
+    /// The transform which moves the center of mass to the origin and
+    /// rotates the principal axes of inertia onto X, Y and Z, in order
+    /// from the smallest moment so the axis the part is longest along
+    /// is usually X. Applying it aligns parts regardless of how they
+    /// were placed, with the direction of each axis chosen so its
+    /// largest component is positive and the frame is right handed.
+    ///
+    /// Returns
+    /// ------------
+    /// transform
+    ///   The homogeneous transform from the mesh frame to the principal
+    ///   frame, or `None` if the mesh doesn't enclose any volume.
+    #[cache_access]
+    pub fn principal_inertia_transform(&self) -> Option<Matrix4<f64>> {
+        let inertia = self.moment_inertia()?;
+        let center = self.center_mass()?;
+        let eigen = SymmetricEigen::new(inertia);
+        let mut order = [0, 1, 2];
+        order.sort_by(|a, b| eigen.eigenvalues[*a].total_cmp(&eigen.eigenvalues[*b]));
+        let mut axes = order.map(|i| {
+            let axis: Vector3<f64> = eigen.eigenvectors.column(i).into();
+            if axis[axis.iamax()] < 0.0 {
+                -axis
+            } else {
+                axis
+            }
+        });
+        axes[2] = axes[0].cross(&axes[1]);
+        // the rows of the rotation into the principal frame are the axes
+        let rotation = Matrix3::from_rows(&axes.map(|a| a.transpose()));
+        let mut transform = rotation.to_homogeneous();
+        transform
+            .fixed_view_mut::<3, 1>(0, 3)
+            .copy_from(&(-(rotation * center.coords)));
+        Some(transform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use approx::assert_relative_eq;
+    use nalgebra::{Matrix3, Point3, Rotation3, Vector3};
+
+    use crate::creation::create_box;
+    use crate::mesh::Trimesh;
+
+    #[test]
+    fn test_mass_properties() {
+        let mesh = create_box(&[1.0, 2.0, 3.0])
+            .apply_translation(&Vector3::new(5.0, -1.0, 2.0))
+            .unwrap();
+        assert_relative_eq!(mesh.volume(), 6.0, epsilon = 1e-10);
+        assert_relative_eq!(
+            mesh.center_mass().unwrap(),
+            Point3::new(5.0, -1.0, 2.0),
+            epsilon = 1e-10
+        );
+
+        // a flat triangle doesn't enclose anything
+        let open = Trimesh::new(
+            vec![
+                Point3::origin(),
+                Point3::new(1.0, 0.0, 0.0),
+                Point3::new(0.0, 1.0, 0.0),
+            ],
+            vec![(0, 1, 2)],
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(open.volume(), 0.0);
+        assert!(open.center_mass().is_none());
+        assert!(open.moment_inertia().is_none());
+        assert!(open.principal_inertia_transform().is_none());
+        assert!(Trimesh::default().center_mass().is_none());
+    }
+
+    #[test]
+    fn test_principal_inertia() {
+        let mesh = create_box(&[1.0, 2.0, 3.0]);
+        // the box has a mass of 6 and moments of m * (b^2 + c^2) / 12
+        assert_relative_eq!(
+            mesh.moment_inertia().unwrap(),
+            Matrix3::from_diagonal(&Vector3::new(6.5, 5.0, 2.5)),
+            epsilon = 1e-10
+        );
+
+        // a box placed anywhere is aligned with its longest side on X
+        let rotation = Rotation3::from_euler_angles(0.3, -1.1, 2.0).to_homogeneous();
+        let placed = mesh
+            .apply_transform(&rotation)
+            .unwrap()
+            .apply_translation(&Vector3::new(4.0, -2.0, 7.0))
+            .unwrap();
+        let transform = placed.principal_inertia_transform().unwrap();
+        assert_relative_eq!(
+            transform.fixed_view::<3, 3>(0, 0).determinant(),
+            1.0,
+            epsilon = 1e-12
+        );
+        let aligned = placed.apply_transform(&transform).unwrap();
+        let (lower, upper) = aligned.bounds().unwrap();
+        assert_relative_eq!(upper - lower, Vector3::new(3.0, 2.0, 1.0), epsilon = 1e-8);
+        assert_relative_eq!(
+            aligned.center_mass().unwrap(),
+            Point3::origin(),
+            epsilon = 1e-8
+        );
+        // turning a mesh inside out doesn't change its inertia
+        let mut inverted = create_box(&[1.0, 2.0, 3.0]);
+        inverted
+            .faces_mut()
+            .iter_mut()
+            .for_each(|f| *f = (f.0, f.2, f.1));
+        assert_relative_eq!(
+            inverted.moment_inertia().unwrap(),
+            mesh.moment_inertia().unwrap(),
+            epsilon = 1e-10
+        );
+    }
+}
@@ -1,19 +1,32 @@
-use std::sync::RwLock;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, RwLock};
 
 use ahash::AHashMap;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 
 use crate::{
-    attributes::{Attributes, LoadSource},
-    simplify::simplify_mesh,
+    attributes::{Attributes, Color, LoadSource, Material, Normal, UV},
+    bvh::Bvh,
+    creation::Primitive,
+    error::RmeshError,
+    halfedge::HalfEdgeMesh,
+    kdtree::KdTree,
+    simplify::simplify_mesh_values,
+    tolerance::Tolerances,
 };
-use nalgebra::{Point3, Vector2, Vector3};
+use nalgebra::{Matrix4, Point3, Vector2, Vector3, Vector4};
 use rayon::prelude::*;
 use rmesh_macro::cache_access;
 
+// The minimum number of items each thread should process in parallel
+// iterators over faces, as for small meshes the overhead of splitting
+// work across threads dominates the actual computation.
+const PARALLEL_MIN_LEN: usize = 4096;
+
 // The inner cache for the Trimesh struct. Any field that uses
-// the `#[cache_access]` macro will be stored here.
+// the `#[cache_access]` macro will be stored here and should
+// be listed in `cache::CacheField`.
 #[derive(Default, Debug, Clone)]
 pub struct InnerCache {
     pub face_adjacency: Option<Vec<(usize, usize)>>,
@@ -25,12 +38,50 @@ pub struct InnerCache {
     pub faces_cross: Option<Vec<Vector3<f64>>>,
     pub faces_area: Option<Vec<f64>>,
     pub area: Option<f64>,
+    pub face_angles: Option<Vec<Vector3<f64>>>,
+    pub vertex_defects: Option<Vec<f64>>,
+    pub bvh: Option<Arc<Bvh>>,
+    pub vertex_tree: Option<Arc<KdTree>>,
+    pub vertex_adjacency: Option<Vec<Vec<usize>>>,
+    pub vertex_normals: Option<Vec<Vector3<f64>>>,
+    pub principal_inertia_transform: Option<Option<Matrix4<f64>>>,
+    pub bounding_box_oriented: Option<Option<Primitive>>,
+    pub half_edges: Option<Arc<HalfEdgeMesh>>,
+    pub faces_flat: Option<Option<Arc<[u32]>>>,
+
+    // how many times the cache has been invalidated, which is kept
+    // when the cache is cleared so callers can tell the mesh changed
+    pub revision: u64,
+}
+
+impl InnerCache {
+    /// Discard every cached value and count a new revision.
+    fn invalidate(&mut self) {
+        *self = InnerCache {
+            revision: self.revision + 1,
+            ..Default::default()
+        };
+    }
+}
+
+/// How the normals of the faces around a vertex are
+/// weighted when averaging them into a vertex normal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NormalWeighting {
+    // weight by the interior angle of the face at the vertex
+    // which doesn't depend on how the surface is triangulated
+    #[default]
+    Angle,
+    // weight by the area of the face
+    Area,
 }
 
 #[derive(Default, Debug)]
 pub struct Trimesh {
-    pub vertices: Vec<Point3<f64>>,
-    pub faces: Vec<(usize, usize, usize)>,
+    // read through `vertices` and `faces` and edited through `vertices_mut`
+    // and `faces_mut` so the cached values computed from them are discarded
+    pub(crate) vertices: Vec<Point3<f64>>,
+    pub(crate) faces: Vec<(usize, usize, usize)>,
 
     // A flat list of attributes so we can define things like
     // multiple colors, normals, uv coordinates, etc and can pick
@@ -38,25 +89,111 @@ pub struct Trimesh {
     pub attributes_vertex: Attributes,
     pub attributes_face: Attributes,
 
+    // the materials referenced by face groupings of `GroupingKind::MaterialIndex`
+    pub materials: Vec<Material>,
+
     // information about where the mesh came from
     pub source: LoadSource,
 
+    // the exact shape the mesh was tessellated from, which is kept
+    // through transforms and dropped by anything changing the geometry
+    pub primitive: Option<Primitive>,
+
     // the cached values computed for the mesh
     pub _cache: RwLock<InnerCache>,
 }
 
+/// A face of a mesh with the positions of its corners, which looks up
+/// the vertex attributes of each corner so callers don't have to check
+/// an attribute has a value for every vertex themselves.
+#[derive(Clone, Copy)]
+pub struct Triangle<'a> {
+    // the index of the face in the mesh
+    pub index: usize,
+    // the index of the vertex at each corner
+    pub vertices: [usize; 3],
+    // the position of each corner
+    pub points: [Point3<f64>; 3],
+    mesh: &'a Trimesh,
+}
+
+impl Triangle<'_> {
+    /// The value of a vertex attribute at each corner, or `None`
+    /// if the attribute doesn't have a value for every vertex.
+    fn corners<T: Copy>(&self, values: Option<&Vec<T>>) -> Option<[T; 3]> {
+        let values = values.filter(|v| v.len() == self.mesh.vertices.len())?;
+        Some(self.vertices.map(|v| values[v]))
+    }
+
+    /// The UV coordinates of each corner from a layer of vertex UVs.
+    pub fn uv(&self, layer: usize) -> Option<[Vector2<f64>; 3]> {
+        self.corners(self.mesh.attributes_vertex.uv.get(layer))
+    }
+
+    /// The normal of each corner from a layer of vertex normals.
+    pub fn normals(&self, layer: usize) -> Option<[Vector3<f64>; 3]> {
+        self.corners(self.mesh.attributes_vertex.normals.get(layer))
+    }
+
+    /// The color of each corner from a layer of vertex colors.
+    pub fn colors(&self, layer: usize) -> Option<[Vector4<u8>; 3]> {
+        self.corners(self.mesh.attributes_vertex.colors.get(layer))
+    }
+
+    /// The group of each corner from a vertex grouping by name.
+    pub fn grouping(&self, name: &str) -> Option<[usize; 3]> {
+        self.corners(
+            self.mesh
+                .attributes_vertex
+                .grouping(name)
+                .map(|g| &g.indices),
+        )
+    }
+}
+
 impl Clone for Trimesh {
     fn clone(&self) -> Self {
-        let cache = self._cache.read().unwrap();
         Self {
             vertices: self.vertices.clone(),
             faces: self.faces.clone(),
-            _cache: RwLock::new(cache.clone()),
-            ..Default::default()
+            attributes_vertex: self.attributes_vertex.clone(),
+            attributes_face: self.attributes_face.clone(),
+            materials: self.materials.clone(),
+            source: self.source.clone(),
+            primitive: self.primitive.clone(),
+            _cache: RwLock::new(self._cache.read().unwrap().clone()),
         }
     }
 }
 
+/// Mutable access to the vertices or faces of a mesh from
+/// `Trimesh::vertices_mut` or `Trimesh::faces_mut`, which discards
+/// every cached value computed from them when it's dropped.
+pub struct GeometryMut<'a, T> {
+    value: &'a mut T,
+    cache: &'a mut RwLock<InnerCache>,
+}
+
+impl<T> Deref for GeometryMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value
+    }
+}
+
+impl<T> DerefMut for GeometryMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.value
+    }
+}
+
+impl<T> Drop for GeometryMut<'_, T> {
+    fn drop(&mut self) {
+        self.cache.get_mut().unwrap().invalidate();
+    }
+}
+
 impl Trimesh {
     /// Create a new trimesh from a vec of tuple values.
     pub fn new(
@@ -64,7 +201,8 @@ impl Trimesh {
         faces: Vec<(usize, usize, usize)>,
         attributes_vertex: Option<Attributes>,
         attributes_face: Option<Attributes>,
-    ) -> Result<Self> {
+    ) -> Result<Self, RmeshError> {
+        check_faces(&faces, vertices.len())?;
         Ok(Self {
             vertices,
             faces,
@@ -76,7 +214,7 @@ impl Trimesh {
     }
 
     /// Create a Trimesh from flat slices of vertices and faces.
-    pub fn from_slice(vertices: &[f64], faces: &[usize]) -> Result<Self> {
+    pub fn from_slice(vertices: &[f64], faces: &[usize]) -> Result<Self, RmeshError> {
         let vertices: Vec<Point3<f64>> = vertices
             .chunks_exact(3)
             .map(|chunk| Point3::new(chunk[0], chunk[1], chunk[2]))
@@ -87,6 +225,8 @@ impl Trimesh {
             .map(|chunk| (chunk[0], chunk[1], chunk[2]))
             .collect();
 
+        check_faces(&faces, vertices.len())?;
+
         Ok(Self {
             vertices,
             faces,
@@ -95,52 +235,417 @@ impl Trimesh {
         })
     }
 
+    /// The position of every vertex.
+    pub fn vertices(&self) -> &[Point3<f64>] {
+        &self.vertices
+    }
+
+    /// The indices of the three vertices of every face.
+    pub fn faces(&self) -> &[(usize, usize, usize)] {
+        &self.faces
+    }
+
+    /// Edit the vertices in place, discarding the cached values computed
+    /// from them once the returned guard is dropped. The primitive the
+    /// mesh was created from is dropped as it may no longer match.
+    pub fn vertices_mut(&mut self) -> GeometryMut<'_, Vec<Point3<f64>>> {
+        self.primitive = None;
+        GeometryMut {
+            value: &mut self.vertices,
+            cache: &mut self._cache,
+        }
+    }
+
+    /// Edit the faces in place, discarding the cached values computed
+    /// from them once the returned guard is dropped.
+    pub fn faces_mut(&mut self) -> GeometryMut<'_, Vec<(usize, usize, usize)>> {
+        self.primitive = None;
+        GeometryMut {
+            value: &mut self.faces,
+            cache: &mut self._cache,
+        }
+    }
+
+    /// Discard every cached value, such as after editing the attributes
+    /// in place, as cached values like normals may have been computed
+    /// from them.
+    pub fn invalidate_cache(&mut self) {
+        self._cache.get_mut().unwrap().invalidate();
+    }
+
+    /// How many times the cached values have been discarded because
+    /// the mesh changed, which callers holding values computed from the
+    /// mesh can compare to tell if they're still current.
+    pub fn revision(&self) -> u64 {
+        self._cache.read().unwrap().revision
+    }
+
+    /// Is this mesh empty, i.e. does it have no faces or no vertices?
+    ///
+    /// Every method on an empty mesh is expected to return an empty
+    /// result (an empty `Vec`, zero, or `None`) rather than panic.
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty() || self.vertices.is_empty()
+    }
+
+    /// The corners of every face in order.
+    pub fn triangles(&self) -> impl ExactSizeIterator<Item = [Point3<f64>; 3]> + '_ {
+        self.faces
+            .iter()
+            .map(|f| [self.vertices[f.0], self.vertices[f.1], self.vertices[f.2]])
+    }
+
+    /// The corners of every face in order as a parallel iterator.
+    pub fn par_triangles(&self) -> impl IndexedParallelIterator<Item = [Point3<f64>; 3]> + '_ {
+        self.faces
+            .par_iter()
+            .map(|f| [self.vertices[f.0], self.vertices[f.1], self.vertices[f.2]])
+    }
+
+    /// Every face in order as a view which can look up the vertex
+    /// attributes of each of its corners.
+    pub fn triangles_with_attributes(&self) -> impl ExactSizeIterator<Item = Triangle<'_>> + '_ {
+        self.faces.iter().enumerate().map(|(index, f)| Triangle {
+            index,
+            vertices: [f.0, f.1, f.2],
+            points: [self.vertices[f.0], self.vertices[f.1], self.vertices[f.2]],
+            mesh: self,
+        })
+    }
+
+    /// A copy of the mesh with new faces, where every face takes its
+    /// face attributes from the original face at the same position
+    /// in `source`.
+    pub(crate) fn with_faces(&self, faces: Vec<(usize, usize, usize)>, source: &[usize]) -> Self {
+        Trimesh {
+            vertices: self.vertices.clone(),
+            faces,
+            attributes_vertex: self.attributes_vertex.clone(),
+            attributes_face: self.attributes_face.select(source, self.faces.len()),
+            materials: self.materials.clone(),
+            source: self.source.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// A mesh of a subset of the faces which only keeps the vertices
+    /// they reference, carrying along the attributes of both.
+    ///
+    /// Parameters
+    /// ------------
+    /// faces
+    ///   The indices of the faces to keep in the order to keep them.
+    ///
+    /// Returns
+    /// ------------
+    /// submesh
+    ///   A new mesh or an error if a face index is out of range.
+    pub fn submesh(&self, faces: &[usize]) -> Result<Self> {
+        if let Some(bad) = faces.iter().find(|f| **f >= self.faces.len()) {
+            return Err(anyhow!(
+                "Face index {bad} is out of range for {} faces",
+                self.faces.len()
+            ));
+        }
+        // number the vertices in the order they are first referenced
+        let mut remap = vec![usize::MAX; self.vertices.len()];
+        let mut kept = Vec::new();
+        let mut index = |v: usize| {
+            if remap[v] == usize::MAX {
+                remap[v] = kept.len();
+                kept.push(v);
+            }
+            remap[v]
+        };
+        let selected: Vec<(usize, usize, usize)> = faces
+            .iter()
+            .map(|f| {
+                let (a, b, c) = self.faces[*f];
+                (index(a), index(b), index(c))
+            })
+            .collect();
+        Ok(Trimesh {
+            vertices: kept.iter().map(|v| self.vertices[*v]).collect(),
+            faces: selected,
+            attributes_vertex: self.attributes_vertex.select(&kept, self.vertices.len()),
+            attributes_face: self.attributes_face.select(faces, self.faces.len()),
+            materials: self.materials.clone(),
+            source: self.source.clone(),
+            ..Default::default()
+        })
+    }
+
+    /// Simplify the mesh with quadric edge collapses, carrying UV
+    /// coordinates, normals and colors of the vertices through each
+    /// collapse by interpolating them at the position the edge collapses
+    /// to. Groupings take the value of the nearest original vertex and
+    /// face attributes the value of the face each face came from.
+    ///
+    /// Parameters
+    /// ------------
+    /// target_count
+    ///   The number of faces to simplify down to.
+    /// aggressiveness
+    ///   How aggressively to collapse edges, typically between 5 and 8.
+    ///
+    /// Returns
+    /// ------------
+    /// simplified
+    ///   A new mesh with at most about `target_count` faces.
     pub fn simplify(&self, target_count: usize, aggressiveness: f64) -> Self {
-        let (vertices, faces) = simplify_mesh(
+        self.simplify_until(target_count, f64::INFINITY, aggressiveness)
+    }
+
+    /// Simplify the mesh to a fraction of its current face count.
+    ///
+    /// Parameters
+    /// ------------
+    /// ratio
+    ///   The fraction of faces to keep, between zero and one.
+    /// aggressiveness
+    ///   How aggressively to collapse edges, typically between 5 and 8.
+    ///
+    /// Returns
+    /// ------------
+    /// simplified
+    ///   A new mesh with at most about `ratio` of the faces or an
+    ///   error if the ratio isn't between zero and one.
+    pub fn simplify_to_ratio(&self, ratio: f64, aggressiveness: f64) -> Result<Self> {
+        if !(0.0..=1.0).contains(&ratio) {
+            return Err(anyhow!("Simplification ratio must be in [0, 1]: {ratio}"));
+        }
+        let target_count = (self.faces.len() as f64 * ratio).round() as usize;
+        Ok(self.simplify(target_count, aggressiveness))
+    }
+
+    /// Simplify the mesh as far as possible while every edge collapse
+    /// stays below an error, rather than to a face count. The error of a
+    /// collapse is the sum of squared distances from the collapsed vertex
+    /// to the planes of the original faces around it.
+    ///
+    /// Parameters
+    /// ------------
+    /// max_error
+    ///   The largest quadric error an edge collapse may have.
+    /// aggressiveness
+    ///   How quickly the threshold grows towards `max_error`, typically
+    ///   between 5 and 8, with lower values picking cheaper collapses first.
+    ///
+    /// Returns
+    /// ------------
+    /// simplified
+    ///   A new mesh or an error if `max_error` is negative or not finite.
+    pub fn simplify_to_error(&self, max_error: f64, aggressiveness: f64) -> Result<Self> {
+        if !(max_error >= 0.0 && max_error.is_finite()) {
+            return Err(anyhow!(
+                "Simplification error must be positive and finite: {max_error}"
+            ));
+        }
+        Ok(self.simplify_until(0, max_error, aggressiveness))
+    }
+
+    /// Simplify the mesh by only collapsing edges which don't change its
+    /// shape, such as the extra vertices of a tessellated flat region.
+    /// The quadric error of a collapse is a squared distance, so it's
+    /// lossless if it's below the `zero` area of `Trimesh::tolerances`,
+    /// which scales with the mesh.
+    ///
+    /// Returns
+    /// ------------
+    /// simplified
+    ///   A new mesh with the same shape and as few faces as the
+    ///   collapses can reach.
+    pub fn simplify_lossless(&self) -> Self {
+        let tolerances = self.tolerances();
+        self.simplify_with(0, tolerances.zero, 7.0, &tolerances)
+    }
+
+    /// Simplify the mesh to a face count and measure how far the result
+    /// deviates from the original with `hausdorff`, which is the error
+    /// the simplification actually introduced rather than the quadric
+    /// error it was guided by. This can certify a result is within a
+    /// manufacturing tolerance when checked with a fine resolution.
+    ///
+    /// Parameters
+    /// ------------
+    /// target_count
+    ///   The number of faces to simplify down to.
+    /// aggressiveness
+    ///   How aggressively to collapse edges, typically between 5 and 8.
+    /// resolution
+    ///   The number of steps to divide the edges of every face into
+    ///   when sampling the surfaces to compare.
+    ///
+    /// Returns
+    /// ------------
+    /// simplified
+    ///   A new mesh with at most `target_count` faces.
+    /// deviation
+    ///   The largest distance found between the surfaces, which is zero
+    ///   if either mesh is empty.
+    pub fn simplify_certified(
+        &self,
+        target_count: usize,
+        aggressiveness: f64,
+        resolution: usize,
+    ) -> (Self, f64) {
+        let simplified = self.simplify(target_count, aggressiveness);
+        let deviation = simplified.hausdorff(self, resolution).unwrap_or(0.0);
+        (simplified, deviation)
+    }
+
+    /// Simplify the mesh until it has at most `target_count` faces or
+    /// no collapse below `max_error` is left, carrying attributes along.
+    fn simplify_until(&self, target_count: usize, max_error: f64, aggressiveness: f64) -> Self {
+        self.simplify_with(target_count, max_error, aggressiveness, &self.tolerances())
+    }
+
+    /// Simplify the mesh with every stopping condition and explicit
+    /// tolerances, which the other simplification methods call.
+    ///
+    /// Parameters
+    /// ------------
+    /// target_count
+    ///   Stop once the mesh has at most this many faces.
+    /// max_error
+    ///   Stop once no collapse is below this quadric error, or
+    ///   infinity to only stop at `target_count`.
+    /// aggressiveness
+    ///   How aggressively to collapse edges, typically between 5 and 8.
+    /// tolerances
+    ///   When a collapse would make a face degenerate or flip it.
+    ///
+    /// Returns
+    /// ------------
+    /// simplified
+    ///   A new mesh with its attributes carried along.
+    pub fn simplify_with(
+        &self,
+        target_count: usize,
+        max_error: f64,
+        aggressiveness: f64,
+        tolerances: &Tolerances,
+    ) -> Self {
+        let count = self.vertices.len();
+        let attributes = &self.attributes_vertex;
+        let uv: Vec<&UV> = attributes.uv.iter().filter(|v| v.len() == count).collect();
+        let normals: Vec<&Normal> = attributes
+            .normals
+            .iter()
+            .filter(|v| v.len() == count)
+            .collect();
+        let colors: Vec<&Color> = attributes
+            .colors
+            .iter()
+            .filter(|v| v.len() == count)
+            .collect();
+
+        // pack every value into one row per vertex to interpolate together
+        let stride = uv.len() * 2 + normals.len() * 3 + colors.len() * 4;
+        let mut values = Vec::with_capacity(count * stride);
+        for index in 0..count {
+            uv.iter().for_each(|v| values.extend(v[index].iter()));
+            normals.iter().for_each(|v| values.extend(v[index].iter()));
+            colors
+                .iter()
+                .for_each(|v| values.extend(v[index].iter().map(|c| *c as f64)));
+        }
+
+        let result = simplify_mesh_values(
             &self.vertices,
             &self.faces,
+            &values,
+            stride,
             target_count,
+            max_error,
             aggressiveness,
             false,
+            tolerances,
         );
 
+        let mut attributes_vertex = attributes.select(&result.vertex_source, count);
+        let rows: Vec<&[f64]> = if stride > 0 {
+            result.values.chunks_exact(stride).collect()
+        } else {
+            Vec::new()
+        };
+        let mut offset = 0;
+        attributes_vertex.uv = (0..uv.len())
+            .map(|layer| {
+                let start = offset + layer * 2;
+                rows.iter()
+                    .map(|r| Vector2::new(r[start], r[start + 1]))
+                    .collect()
+            })
+            .collect();
+        offset += uv.len() * 2;
+        attributes_vertex.normals = (0..normals.len())
+            .map(|layer| {
+                let start = offset + layer * 3;
+                rows.iter()
+                    .map(|r| {
+                        let normal = Vector3::new(r[start], r[start + 1], r[start + 2]);
+                        normal.try_normalize(f64::EPSILON).unwrap_or(normal)
+                    })
+                    .collect()
+            })
+            .collect();
+        offset += normals.len() * 3;
+        attributes_vertex.colors = (0..colors.len())
+            .map(|layer| {
+                let start = offset + layer * 4;
+                rows.iter()
+                    .map(|r| Vector4::from_fn(|i, _| r[start + i].round().clamp(0.0, 255.0) as u8))
+                    .collect()
+            })
+            .collect();
+
         Self {
-            vertices,
-            faces,
+            vertices: result.vertices,
+            attributes_face: self
+                .attributes_face
+                .select(&result.face_source, self.faces.len()),
+            faces: result.faces,
+            attributes_vertex,
+            materials: self.materials.clone(),
+            source: self.source.clone(),
+            primitive: None,
             _cache: RwLock::new(InnerCache::default()),
-            ..Default::default()
         }
     }
 
-    /// Calculate the normals for each face of the mesh.
+    /// Calculate the normals for each face of the mesh. Degenerate
+    /// faces with zero area will have a zero-length normal.
     #[cache_access]
     pub fn face_normals(&self) -> Vec<Vector3<f64>> {
         self.faces_cross()
             .par_iter()
-            .map(|cross| cross.normalize())
+            .with_min_len(PARALLEL_MIN_LEN)
+            .map(|cross| cross.try_normalize(f64::EPSILON).unwrap_or_default())
             .collect()
     }
 
     // Get the edges calculated from the faces
     #[cache_access]
     pub fn edges(&self) -> Vec<[usize; 2]> {
-        self.faces
-            .par_iter()
-            .flat_map(|face| vec![[face.0, face.1], [face.1, face.2], [face.2, face.0]])
-            .collect()
+        // this is memory bound so a single preallocated pass
+        // is faster than splitting the work across threads
+        let mut edges = Vec::with_capacity(self.faces.len() * 3);
+        for face in self.faces.iter() {
+            edges.extend_from_slice(&[[face.0, face.1], [face.1, face.2], [face.2, face.0]]);
+        }
+        edges
     }
 
     /// The non-normalized cross product of every face.
     #[cache_access]
     pub fn faces_cross(&self) -> Vec<Vector3<f64>> {
-        self.faces
-            .par_iter()
-            .map(|face| {
-                let v0 = self.vertices[face.0];
-                let v1 = self.vertices[face.1];
-                let v2 = self.vertices[face.2];
-                (v1 - v0).cross(&(v2 - v0))
-            })
+        if self.is_empty() {
+            return Vec::new();
+        }
+        self.par_triangles()
+            .with_min_len(PARALLEL_MIN_LEN)
+            .map(|[v0, v1, v2]| (v1 - v0).cross(&(v2 - v0)))
             .collect()
     }
 
@@ -149,6 +654,7 @@ impl Trimesh {
     pub fn faces_area(&self) -> Vec<f64> {
         self.faces_cross()
             .par_iter()
+            .with_min_len(PARALLEL_MIN_LEN)
             .map(|cross| cross.norm() / 2.0)
             .collect()
     }
@@ -168,20 +674,22 @@ impl Trimesh {
     // What are the pairs of face indices that share an edge?
     #[cache_access]
     pub fn face_adjacency(&self) -> Vec<(usize, usize)> {
-        let mut edge_map = AHashMap::new();
-        let mut adjacency = Vec::new();
-
-        for (i, edge) in self.edges().iter().enumerate() {
-            // there are 3 edges per triangle
-            let face_index = i / 3;
-            // sorted edge for querying
-            let edge = [edge[0].min(edge[1]), edge[0].max(edge[1])];
-            if let Some(other) = edge_map.get(&edge) {
-                // add the face index to the adjacency list
-                adjacency.push((*other, face_index));
-            } else {
-                // add the edge to the map for checking later
-                edge_map.insert(edge, face_index);
+        // a closed manifold mesh has 1.5 edges per face
+        let mut edge_map = AHashMap::with_capacity(self.faces.len() * 3 / 2);
+        let mut adjacency = Vec::with_capacity(self.faces.len() * 3 / 2);
+
+        // iterate the faces directly rather than cloning `self.edges()`
+        for (face_index, face) in self.faces.iter().enumerate() {
+            for edge in [[face.0, face.1], [face.1, face.2], [face.2, face.0]] {
+                // sorted edge for querying
+                let edge = [edge[0].min(edge[1]), edge[0].max(edge[1])];
+                if let Some(other) = edge_map.get(&edge) {
+                    // add the face index to the adjacency list
+                    adjacency.push((*other, face_index));
+                } else {
+                    // add the edge to the map for checking later
+                    edge_map.insert(edge, face_index);
+                }
             }
         }
 
@@ -194,23 +702,157 @@ impl Trimesh {
         let normals = self.face_normals();
         adjacency
             .par_iter()
+            .with_min_len(PARALLEL_MIN_LEN)
             .map(|adj| normals[adj.0].angle(&normals[adj.1]))
             .collect()
     }
 
-    pub fn smooth_shaded(&self, threshold: f64) {
-        // get the angles between adjacent faces
+    /// The normal of every vertex, which is the normal loaded from the
+    /// file if it had one for every vertex and otherwise the average of
+    /// the normals of the faces around it weighted by angle. Vertices
+    /// not referenced by any face have a zero normal.
+    #[cache_access]
+    pub fn vertex_normals(&self) -> Vec<Vector3<f64>> {
+        match self.attributes_vertex.normals.first() {
+            Some(normals) if normals.len() == self.vertices.len() => normals.clone(),
+            _ => self.vertex_normals_weighted(NormalWeighting::Angle),
+        }
+    }
+
+    /// Compute the normal of every vertex from the faces around it,
+    /// ignoring any normals that were loaded from a file.
+    ///
+    /// Parameters
+    /// ------------
+    /// weighting
+    ///   How to weight the normals of the faces around each vertex.
+    ///
+    /// Returns
+    /// ------------
+    /// normals
+    ///   A unit normal for every vertex or zero if it isn't in any face.
+    pub fn vertex_normals_weighted(&self, weighting: NormalWeighting) -> Vec<Vector3<f64>> {
+        let mut sums = vec![Vector3::zeros(); self.vertices.len()];
+        match weighting {
+            NormalWeighting::Angle => {
+                let normals = self.face_normals();
+                for ((face, normal), angles) in self
+                    .faces
+                    .iter()
+                    .zip(normals.iter())
+                    .zip(self.face_angles().iter())
+                {
+                    sums[face.0] += normal * angles.x;
+                    sums[face.1] += normal * angles.y;
+                    sums[face.2] += normal * angles.z;
+                }
+            }
+            NormalWeighting::Area => {
+                // the cross product is already proportional to the area
+                for (face, cross) in self.faces.iter().zip(self.faces_cross().iter()) {
+                    sums[face.0] += cross;
+                    sums[face.1] += cross;
+                    sums[face.2] += cross;
+                }
+            }
+        }
+        sums.into_iter()
+            .map(|sum| sum.try_normalize(f64::EPSILON).unwrap_or_default())
+            .collect()
+    }
+
+    /// Split vertices along sharp edges so that a renderer interpolating
+    /// vertex normals shades the smooth regions smoothly while keeping
+    /// hard creases. Faces which share an edge with an angle between them
+    /// below the threshold share vertices, and every vertex gets an angle
+    /// weighted normal from only the faces that share it.
+    ///
+    /// Parameters
+    /// ------------
+    /// threshold
+    ///   The angle between adjacent faces in radians above which
+    ///   the edge between them is kept sharp.
+    ///
+    /// Returns
+    /// ------------
+    /// shaded
+    ///   A new mesh with split vertices and their normals in `attributes_vertex`.
+    pub fn smooth_shaded(&self, threshold: f64) -> Self {
+        // every corner of every face starts as its own vertex and corners
+        // across smooth edges are joined with a disjoint set
+        let mut parent: Vec<usize> = (0..self.faces.len() * 3).collect();
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        let corners = |face: usize| {
+            let f = self.faces[face];
+            [f.0, f.1, f.2]
+        };
         let angles = self.face_adjacency_angles();
-        let _index: Vec<usize> = (0..angles.len())
-            .into_par_iter()
-            .filter(|i| angles[*i] < threshold)
-            .collect();
+        for (pair, angle) in self.face_adjacency().iter().zip(angles.iter()) {
+            if *angle >= threshold {
+                continue;
+            }
+            let (a, b) = (corners(pair.0), corners(pair.1));
+            for (i, vertex) in a.iter().enumerate() {
+                if let Some(j) = b.iter().position(|v| v == vertex) {
+                    let (x, y) = (
+                        find(&mut parent, pair.0 * 3 + i),
+                        find(&mut parent, pair.1 * 3 + j),
+                    );
+                    parent[x] = y;
+                }
+            }
+        }
+
+        // create a vertex for every set of corners
+        let normals = self.face_normals();
+        let face_angles = self.face_angles();
+        let mut lookup: AHashMap<usize, usize> = AHashMap::new();
+        let mut index: Vec<usize> = Vec::new();
+        let mut sums: Vec<Vector3<f64>> = Vec::new();
+        let mut faces = Vec::with_capacity(self.faces.len());
+        for face in 0..self.faces.len() {
+            let mut new = [0; 3];
+            for (i, vertex) in corners(face).into_iter().enumerate() {
+                let root = find(&mut parent, face * 3 + i);
+                let current = *lookup.entry(root).or_insert_with(|| {
+                    index.push(vertex);
+                    sums.push(Vector3::zeros());
+                    index.len() - 1
+                });
+                sums[current] += normals[face] * face_angles[face][i];
+                new[i] = current;
+            }
+            faces.push((new[0], new[1], new[2]));
+        }
+
+        let mut attributes_vertex = self.attributes_vertex.select(&index, self.vertices.len());
+        // the new normals replace any that were loaded
+        attributes_vertex.normals = vec![
+            sums.into_iter()
+                .map(|sum| sum.try_normalize(f64::EPSILON).unwrap_or_default())
+                .collect(),
+        ];
 
-        let _adjacency = self.face_adjacency();
+        Trimesh {
+            vertices: index.iter().map(|i| self.vertices[*i]).collect(),
+            faces,
+            attributes_vertex,
+            attributes_face: self.attributes_face.clone(),
+            materials: self.materials.clone(),
+            source: self.source.clone(),
+            ..Default::default()
+        }
     }
 
     /// Calculate an axis-aligned bounding box (AABB) for the mesh,
-    /// or an error if the mesh is empty.
+    /// or `None` if the mesh has no vertices or zero extents.
     ///
     /// Returns
     /// ------------
@@ -236,14 +878,111 @@ impl Trimesh {
     }
 }
 
+/// Check that every face references a vertex that exists.
+///
+/// Parameters
+/// ------------
+/// faces
+///   The triangles as indexes of a vertex list.
+/// vertex_count
+///   The number of vertices the faces may reference.
+///
+/// Returns
+/// ------------
+/// Result<(), RmeshError>
+///   An `InvalidGeometry` error naming the first face with an out-of-range index.
+fn check_faces(faces: &[(usize, usize, usize)], vertex_count: usize) -> Result<(), RmeshError> {
+    if let Some((i, face)) = faces
+        .iter()
+        .enumerate()
+        .find(|(_, f)| f.0.max(f.1).max(f.2) >= vertex_count)
+    {
+        return Err(RmeshError::InvalidGeometry(format!(
+            "face {i} references a vertex out of range: {face:?} with {vertex_count} vertices"
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use crate::attributes::EmptyMaterial;
     use crate::creation::create_box;
     use crate::exchange::{MeshFormat, load_mesh};
     use approx::relative_eq;
 
+    #[test]
+    fn test_cache_invalidation() {
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        assert!(relative_eq!(mesh.area(), 6.0));
+        let revision = mesh.revision();
+
+        // editing through the guard discards the cached area
+        for v in mesh.vertices_mut().iter_mut() {
+            v.coords *= 2.0;
+        }
+        assert!(relative_eq!(mesh.area(), 24.0));
+        assert!(mesh.revision() > revision);
+
+        // flipping every face through the guard flips the normals
+        let normals = mesh.face_normals();
+        for f in mesh.faces_mut().iter_mut() {
+            *f = (f.0, f.2, f.1);
+        }
+        for (a, b) in normals.iter().zip(mesh.face_normals()) {
+            assert!(relative_eq!(*a, -b));
+        }
+
+        // replacing the vertices with the same count through the guard
+        let revision = mesh.revision();
+        {
+            let mut vertices = mesh.vertices_mut();
+            let scaled: Vec<Point3<f64>> = vertices.iter().map(|v| v * 0.5).collect();
+            vertices.clear();
+            vertices.extend(scaled);
+        }
+        assert!(relative_eq!(mesh.area(), 6.0));
+        assert!(mesh.revision() > revision);
+        let taken = std::mem::take(&mut *mesh.vertices_mut());
+        *mesh.vertices_mut() = taken.iter().map(|v| v * 3.0).collect();
+        assert!(relative_eq!(mesh.area(), 54.0));
+        mesh.invalidate_cache();
+        assert!(relative_eq!(mesh.area(), 54.0));
+
+        // a clone keeps the cache of the original
+        let copy = mesh.clone();
+        assert!(copy._cache.read().unwrap().area.is_some());
+        assert!(relative_eq!(copy.area(), 54.0));
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        mesh.attributes_face
+            .colors
+            .push(vec![Vector4::new(255, 0, 0, 255); 12]);
+        mesh.attributes_vertex.uv.push(vec![Vector2::zeros(); 8]);
+        mesh.materials.push(Material::Empty(EmptyMaterial {}));
+        mesh.source.format = Some(MeshFormat::STL);
+
+        // every field is copied
+        let copy = mesh.clone();
+        assert_eq!(copy.vertices(), mesh.vertices());
+        assert_eq!(copy.faces(), mesh.faces());
+        assert_eq!(copy.attributes_face.colors, mesh.attributes_face.colors);
+        assert_eq!(copy.attributes_vertex.uv, mesh.attributes_vertex.uv);
+        assert_eq!(copy.materials.len(), 1);
+        assert_eq!(copy.source.format, mesh.source.format);
+        assert!(copy.primitive.is_some());
+
+        // editing the geometry drops the primitive it was created from
+        let mut edited = mesh.clone();
+        edited.vertices_mut()[0].x = 5.0;
+        assert!(edited.primitive.is_none());
+    }
+
     #[test]
     fn test_mesh_normals() {
         let m = Trimesh::from_slice(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], &[0, 1, 2])
@@ -257,6 +996,36 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_triangles() {
+        let mut mesh = create_box(&[1.0, 2.0, 3.0]);
+        let triangles: Vec<[Point3<f64>; 3]> = mesh.triangles().collect();
+        assert_eq!(triangles.len(), 12);
+        assert_eq!(triangles[3][1], mesh.vertices[mesh.faces[3].1]);
+        let parallel: Vec<[Point3<f64>; 3]> = mesh.par_triangles().collect();
+        assert_eq!(parallel, triangles);
+
+        mesh.attributes_vertex
+            .uv
+            .push(mesh.vertices.iter().map(|v| v.xy().coords).collect());
+        // a layer which doesn't have a value for every vertex
+        mesh.attributes_vertex
+            .colors
+            .push(vec![Vector4::zeros(); 3]);
+        for (triangle, points) in mesh.triangles_with_attributes().zip(triangles.iter()) {
+            assert_eq!(triangle.points, *points);
+            let uv = triangle.uv(0).unwrap();
+            for (corner, point) in uv.iter().zip(points.iter()) {
+                assert_eq!(*corner, point.xy().coords);
+            }
+            assert!(triangle.uv(1).is_none());
+            assert!(triangle.colors(0).is_none());
+            assert!(triangle.normals(0).is_none());
+            assert!(triangle.grouping("missing").is_none());
+        }
+        assert_eq!(Trimesh::default().triangles().len(), 0);
+    }
+
     #[test]
     fn test_bounds() {
         let cube = create_box(&[1.0, 2.0, 3.0]);
@@ -289,6 +1058,122 @@ mod tests {
         assert_eq!(mesh.faces.len(), 12);
     }
 
+    #[test]
+    fn test_mesh_empty() {
+        // a mesh with nothing in it
+        let m = Trimesh::default();
+        assert!(m.is_empty());
+        assert!(m.face_normals().is_empty());
+        assert!(m.faces_cross().is_empty());
+        assert!(m.faces_area().is_empty());
+        assert!(m.edges().is_empty());
+        assert!(m.face_adjacency().is_empty());
+        assert!(m.face_adjacency_angles().is_empty());
+        assert_eq!(m.area(), 0.0);
+        assert!(m.bounds().is_none());
+        assert!(m.uv().is_none());
+        assert!(m.simplify(10, 7.0).is_empty());
+
+        // vertices but no faces is still empty
+        let m = Trimesh::from_slice(&[0.0, 0.0, 0.0, 1.0, 1.0, 1.0], &[]).unwrap();
+        assert!(m.is_empty());
+        assert!(m.face_normals().is_empty());
+        assert!(m.bounds().is_some());
+
+        // faces referencing vertices that don't exist are an error
+        assert!(Trimesh::from_slice(&[], &[0, 1, 2]).is_err());
+        assert!(Trimesh::new(vec![Point3::origin()], vec![(0, 0, 1)], None, None).is_err());
+
+        // a degenerate face should have a zero normal rather than NaN
+        let m = Trimesh::from_slice(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 2.0, 0.0, 0.0], &[0, 1, 2])
+            .unwrap();
+        assert_eq!(m.face_normals()[0], Vector3::zeros());
+    }
+
+    #[test]
+    fn test_vertex_normals() {
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        // angle weighting makes every corner point diagonally out
+        for (vertex, normal) in mesh.vertices.iter().zip(mesh.vertex_normals()) {
+            assert!(relative_eq!(
+                normal,
+                vertex.coords.normalize(),
+                epsilon = 1e-10
+            ));
+        }
+        let area = mesh.vertex_normals_weighted(NormalWeighting::Area);
+        assert!(
+            area.iter()
+                .all(|n| relative_eq!(n.norm(), 1.0, epsilon = 1e-10))
+        );
+
+        // normals from the file are used when present
+        let loaded = vec![Vector3::z(); mesh.vertices.len()];
+        mesh.attributes_vertex.normals.push(loaded.clone());
+        mesh.invalidate_cache();
+        assert_eq!(mesh.vertex_normals(), loaded);
+
+        // a loose vertex has no normal
+        let m = Trimesh::from_slice(
+            &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 5.0, 5.0, 5.0],
+            &[0, 1, 2],
+        )
+        .unwrap();
+        assert_eq!(m.vertex_normals()[3], Vector3::zeros());
+    }
+
+    #[test]
+    fn test_smooth_shaded() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+
+        // every edge of the box is sharp so each side has its own corners
+        let sharp = mesh.smooth_shaded(0.1);
+        assert_eq!(sharp.vertices.len(), 24);
+        assert_eq!(sharp.faces.len(), 12);
+        let normals = sharp.vertex_normals();
+        for (face, normal) in sharp.faces.iter().zip(sharp.face_normals()) {
+            for vertex in [face.0, face.1, face.2] {
+                assert!(relative_eq!(normals[vertex], normal, epsilon = 1e-10));
+            }
+        }
+
+        // nothing is sharp so no vertices are split
+        let smooth = mesh.smooth_shaded(std::f64::consts::PI);
+        assert_eq!(smooth.vertices.len(), 8);
+        assert!(relative_eq!(
+            smooth.vertex_normals()[0],
+            mesh.vertex_normals()[0],
+            epsilon = 1e-10
+        ));
+        assert!(Trimesh::default().smooth_shaded(0.5).is_empty());
+
+        // vertex attributes follow the vertices they were split from
+        // and face attributes are unchanged
+        let mut colored = create_box(&[1.0, 1.0, 1.0]);
+        colored.attributes_vertex.uv.push(
+            colored
+                .vertices
+                .iter()
+                .map(|v| Vector2::new(v.x, v.y))
+                .collect(),
+        );
+        colored
+            .attributes_face
+            .colors
+            .push(vec![nalgebra::Vector4::new(255, 0, 0, 255); 12]);
+        let shaded = colored.smooth_shaded(0.1);
+        let uv = shaded.uv().unwrap();
+        assert_eq!(uv.len(), 24);
+        for (vertex, uv) in shaded.vertices.iter().zip(uv) {
+            assert_eq!(*uv, Vector2::new(vertex.x, vertex.y));
+        }
+        assert_eq!(
+            shaded.attributes_face.colors,
+            colored.attributes_face.colors
+        );
+        assert_eq!(shaded.attributes_vertex.normals.len(), 1);
+    }
+
     #[test]
     fn test_mesh_adj() {
         let box_mesh = create_box(&[1.0, 1.0, 1.0]);
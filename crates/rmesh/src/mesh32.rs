@@ -0,0 +1,157 @@
+use anyhow::Result;
+use nalgebra::{Point3, Vector2, Vector3, Vector4};
+
+use crate::attributes::Attributes;
+use crate::mesh::Trimesh;
+
+/// A mesh stored in single precision with `u32` indices, which uses
+/// under half the memory of a `Trimesh` for graphics where the geometry
+/// goes straight to a GPU or across the WASM boundary. It only holds
+/// the data a renderer needs, so convert it with `to_trimesh` for any
+/// computation on the geometry.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Trimesh32 {
+    pub vertices: Vec<Point3<f32>>,
+    pub faces: Vec<[u32; 3]>,
+
+    // a normal, UV coordinate or color for every vertex, or empty
+    pub normals: Vec<Vector3<f32>>,
+    pub uv: Vec<Vector2<f32>>,
+    pub colors: Vec<Vector4<u8>>,
+}
+
+impl Trimesh32 {
+    /// Convert a mesh to single precision, keeping the first layer of
+    /// vertex normals, UV coordinates and colors which has a value for
+    /// every vertex and dropping every other attribute.
+    ///
+    /// Parameters
+    /// ------------
+    /// mesh
+    ///   The mesh to convert.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Trimesh32>
+    ///   The converted mesh, or an error if it has too many
+    ///   vertices to index with a `u32`.
+    pub fn from_trimesh(mesh: &Trimesh) -> Result<Self> {
+        let faces = mesh.faces_u32()?;
+        let count = mesh.vertices.len();
+        fn first<T: Clone>(values: &[Vec<T>], count: usize) -> Option<&Vec<T>> {
+            values.iter().find(|v| v.len() == count)
+        }
+        let attributes = &mesh.attributes_vertex;
+        Ok(Self {
+            vertices: mesh.vertices.iter().map(|v| v.cast()).collect(),
+            faces,
+            normals: first(&attributes.normals, count)
+                .map(|n| n.iter().map(|n| n.cast()).collect())
+                .unwrap_or_default(),
+            uv: first(&attributes.uv, count)
+                .map(|uv| uv.iter().map(|uv| uv.cast()).collect())
+                .unwrap_or_default(),
+            colors: first(&attributes.colors, count)
+                .cloned()
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Convert back to a double precision mesh with the normals,
+    /// UV coordinates and colors as vertex attributes.
+    pub fn to_trimesh(&self) -> Trimesh {
+        let mut attributes = Attributes::default();
+        if !self.normals.is_empty() {
+            attributes
+                .normals
+                .push(self.normals.iter().map(|n| n.cast()).collect());
+        }
+        if !self.uv.is_empty() {
+            attributes
+                .uv
+                .push(self.uv.iter().map(|uv| uv.cast()).collect());
+        }
+        if !self.colors.is_empty() {
+            attributes.colors.push(self.colors.clone());
+        }
+        Trimesh {
+            vertices: self.vertices.iter().map(|v| v.cast()).collect(),
+            faces: self
+                .faces
+                .iter()
+                .map(|f| (f[0] as usize, f[1] as usize, f[2] as usize))
+                .collect(),
+            attributes_vertex: attributes,
+            ..Default::default()
+        }
+    }
+
+    /// The vertices as flat XYZ values without copying, in the layout
+    /// of a vertex buffer.
+    pub fn positions(&self) -> &[f32] {
+        bytemuck::cast_slice(&self.vertices)
+    }
+
+    /// The vertex indices of every triangle without copying,
+    /// in the layout of an index buffer.
+    pub fn indices(&self) -> &[u32] {
+        bytemuck::cast_slice(&self.faces)
+    }
+
+    /// The number of bytes used by the geometry and attributes.
+    pub fn memory(&self) -> usize {
+        std::mem::size_of_val(self.vertices.as_slice())
+            + std::mem::size_of_val(self.faces.as_slice())
+            + std::mem::size_of_val(self.normals.as_slice())
+            + std::mem::size_of_val(self.uv.as_slice())
+            + std::mem::size_of_val(self.colors.as_slice())
+    }
+}
+
+impl Trimesh {
+    /// Convert the mesh to single precision, as in `Trimesh32::from_trimesh`.
+    pub fn to_f32(&self) -> Result<Trimesh32> {
+        Trimesh32::from_trimesh(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::creation::create_sphere;
+    use crate::exchange::{MeshFormat, load_mesh, load_mesh32};
+
+    #[test]
+    fn test_trimesh32() {
+        let mut mesh = create_sphere(1.0, [16, 8]).unwrap();
+        mesh.attributes_vertex.normals.push(mesh.vertex_normals());
+        let small = mesh.to_f32().unwrap();
+        assert_eq!(small.vertices.len(), mesh.vertices.len());
+        assert_eq!(small.normals.len(), mesh.vertices.len());
+        assert!(small.uv.is_empty());
+        assert_eq!(small.positions().len(), mesh.vertices.len() * 3);
+        assert_eq!(small.indices()[..3], small.faces[0]);
+
+        // converting back only loses precision
+        let back = small.to_trimesh();
+        assert_eq!(back.faces, mesh.faces);
+        for (a, b) in back.vertices.iter().zip(mesh.vertices.iter()) {
+            assert!((a - b).norm() < 1e-6);
+        }
+        assert!((back.area() - mesh.area()).abs() < 1e-5);
+
+        // an STL is already single precision so loading it directly is exact
+        let data = include_bytes!("../../../test/data/unit_cube.STL");
+        let direct = load_mesh32(data, MeshFormat::STL).unwrap();
+        let converted = load_mesh(data, MeshFormat::STL).unwrap().to_f32().unwrap();
+        assert_eq!(direct.vertices, converted.vertices);
+        assert_eq!(direct.faces, converted.faces);
+        // half the 24 bytes of each vertex and face of a `Trimesh` on 64 bit
+        assert_eq!(direct.memory(), 36 * 12 + 12 * 12);
+        let data = include_bytes!("../../../test/data/fuze.obj");
+        assert_eq!(
+            load_mesh32(data, MeshFormat::OBJ).unwrap().faces.len(),
+            load_mesh(data, MeshFormat::OBJ).unwrap().faces.len()
+        );
+    }
+}
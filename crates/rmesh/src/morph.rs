@@ -0,0 +1,220 @@
+use anyhow::{Result, anyhow};
+use rayon::prelude::*;
+
+use crate::mesh::Trimesh;
+use crate::visual::{color_from_linear, color_to_linear};
+
+/// Which vertex attributes `Trimesh::morph_with` blends along with the
+/// positions, with the rest copied from the mesh being morphed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MorphOptions {
+    // blend the vertex normals and normalize the result
+    pub normals: bool,
+    // blend the vertex colors in linear light
+    pub colors: bool,
+}
+
+impl Default for MorphOptions {
+    fn default() -> Self {
+        Self {
+            normals: true,
+            colors: true,
+        }
+    }
+}
+
+impl Trimesh {
+    /// Check that another mesh has the same faces over the same number
+    /// of vertices, so each vertex can be blended with its counterpart.
+    fn check_morph_target(&self, other: &Trimesh) -> Result<()> {
+        if self.vertices.len() != other.vertices.len() {
+            return Err(anyhow!(
+                "Can't morph between {} and {} vertices",
+                self.vertices.len(),
+                other.vertices.len()
+            ));
+        }
+        if self.faces != other.faces {
+            return Err(anyhow!("Can't morph between meshes with different faces"));
+        }
+        Ok(())
+    }
+
+    /// Blend the positions of every vertex towards a mesh with the same
+    /// topology, such as the next key frame of an animation, blending the
+    /// vertex normals and colors too when both meshes have them.
+    ///
+    /// Parameters
+    /// ------------
+    /// other
+    ///   The mesh to morph towards, with the same faces.
+    /// t
+    ///   How far to morph, from zero for this mesh to one for `other`.
+    ///
+    /// Returns
+    /// ------------
+    /// morphed
+    ///   The blended mesh with the faces and every other attribute of
+    ///   this mesh, or an error if the meshes don't match.
+    pub fn morph(&self, other: &Trimesh, t: f64) -> Result<Trimesh> {
+        self.morph_with(other, t, &MorphOptions::default())
+    }
+
+    /// Blend the positions of every vertex towards a mesh with the same
+    /// topology like `morph`, choosing which attributes are blended.
+    ///
+    /// Parameters
+    /// ------------
+    /// other
+    ///   The mesh to morph towards, with the same faces.
+    /// t
+    ///   How far to morph, from zero for this mesh to one for `other`.
+    /// options
+    ///   Which vertex attributes to blend.
+    ///
+    /// Returns
+    /// ------------
+    /// morphed
+    ///   The blended mesh, or an error if the meshes don't match.
+    pub fn morph_with(&self, other: &Trimesh, t: f64, options: &MorphOptions) -> Result<Trimesh> {
+        self.check_morph_target(other)?;
+        if !t.is_finite() {
+            return Err(anyhow!("Morph amount must be finite: {t}"));
+        }
+        let vertices = self
+            .vertices
+            .iter()
+            .zip(other.vertices.iter())
+            .map(|(a, b)| a + (b - a) * t)
+            .collect();
+
+        let mut attributes_vertex = self.attributes_vertex.clone();
+        if options.normals {
+            for (mine, theirs) in attributes_vertex
+                .normals
+                .iter_mut()
+                .zip(other.attributes_vertex.normals.iter())
+                .filter(|(a, b)| a.len() == b.len())
+            {
+                for (a, b) in mine.iter_mut().zip(theirs.iter()) {
+                    *a = a.lerp(b, t).try_normalize(f64::EPSILON).unwrap_or(*a);
+                }
+            }
+        }
+        if options.colors {
+            for (mine, theirs) in attributes_vertex
+                .colors
+                .iter_mut()
+                .zip(other.attributes_vertex.colors.iter())
+                .filter(|(a, b)| a.len() == b.len())
+            {
+                for (a, b) in mine.iter_mut().zip(theirs.iter()) {
+                    *a = color_from_linear(&color_to_linear(a).lerp(&color_to_linear(b), t));
+                }
+            }
+        }
+
+        Ok(Trimesh {
+            vertices,
+            faces: self.faces.clone(),
+            attributes_vertex,
+            attributes_face: self.attributes_face.clone(),
+            materials: self.materials.clone(),
+            source: self.source.clone(),
+            ..Default::default()
+        })
+    }
+
+    /// Generate evenly spaced frames between this mesh and another in
+    /// parallel, for filling in an animation between two key frames.
+    ///
+    /// Parameters
+    /// ------------
+    /// other
+    ///   The mesh to morph towards, with the same faces.
+    /// count
+    ///   The number of frames between the two meshes, which doesn't
+    ///   include either of them.
+    /// options
+    ///   Which vertex attributes to blend.
+    ///
+    /// Returns
+    /// ------------
+    /// frames
+    ///   The frames in order from this mesh towards `other`, or an
+    ///   error if the meshes don't match.
+    pub fn morph_frames(
+        &self,
+        other: &Trimesh,
+        count: usize,
+        options: &MorphOptions,
+    ) -> Result<Vec<Trimesh>> {
+        self.check_morph_target(other)?;
+        (1..=count)
+            .into_par_iter()
+            .map(|i| self.morph_with(other, i as f64 / (count + 1) as f64, options))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+    use nalgebra::{Point3, Vector4};
+
+    #[test]
+    fn test_morph() {
+        let mut start = create_box(&[1.0, 1.0, 1.0]);
+        let mut end = create_box(&[3.0, 3.0, 3.0]);
+        let count = start.vertices.len();
+        start
+            .attributes_vertex
+            .colors
+            .push(vec![Vector4::new(0, 0, 0, 255); count]);
+        end.attributes_vertex
+            .colors
+            .push(vec![Vector4::new(255, 255, 255, 255); count]);
+
+        let half = start.morph(&end, 0.5).unwrap();
+        assert_relative_eq!(half.bounds().unwrap().1, Point3::new(1.0, 1.0, 1.0));
+        // half of the light is brighter than half of the sRGB value
+        assert!(half.attributes_vertex.colors[0][0].x > 128);
+        let plain = start
+            .morph_with(
+                &end,
+                0.5,
+                &MorphOptions {
+                    colors: false,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(plain.attributes_vertex.colors[0][0].x, 0);
+        assert_eq!(start.morph(&end, 0.0).unwrap().vertices, start.vertices);
+
+        let frames = start
+            .morph_frames(&end, 3, &MorphOptions::default())
+            .unwrap();
+        assert_eq!(frames.len(), 3);
+        for (i, frame) in frames.iter().enumerate() {
+            let size = 1.0 + 2.0 * (i + 1) as f64 / 4.0;
+            assert_relative_eq!(frame.area(), 6.0 * size * size, epsilon = 1e-10);
+        }
+
+        // the topology has to match
+        let mut other = end.clone();
+        other.faces_mut().swap(0, 1);
+        assert!(start.morph(&other, 0.5).is_err());
+        let fewer = Trimesh::from_slice(&[0.0; 9], &[0, 1, 2]).unwrap();
+        assert!(start.morph(&fewer, 0.5).is_err());
+        assert!(
+            start
+                .morph_frames(&fewer, 2, &MorphOptions::default())
+                .is_err()
+        );
+        assert!(start.morph(&end, f64::NAN).is_err());
+    }
+}
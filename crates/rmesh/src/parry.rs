@@ -0,0 +1,60 @@
+//! Convert meshes to and from the `TriMesh` collision shape of parry,
+//! using the double precision build so no precision is lost.
+
+pub use parry3d_f64 as parry3d;
+use parry3d_f64::shape::TriMesh;
+
+use crate::error::RmeshError;
+use crate::mesh::Trimesh;
+
+impl Trimesh {
+    /// Convert the mesh into a parry collision shape, moving the
+    /// vertices into it rather than copying them.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<TriMesh, RmeshError>
+    ///   The shape or an `InvalidGeometry` error if the mesh has no
+    ///   faces or too many vertices to index with a `u32`.
+    pub fn into_parry(self) -> Result<TriMesh, RmeshError> {
+        let indices = self.faces_u32()?;
+        TriMesh::new(self.vertices, indices).map_err(|e| RmeshError::InvalidGeometry(e.to_string()))
+    }
+
+    /// Convert a copy of the mesh into a parry collision shape,
+    /// as in `into_parry`.
+    pub fn to_parry(&self) -> Result<TriMesh, RmeshError> {
+        let indices = self.faces_u32()?;
+        TriMesh::new(self.vertices.clone(), indices)
+            .map_err(|e| RmeshError::InvalidGeometry(e.to_string()))
+    }
+
+    /// Create a mesh from the vertices and triangles of a parry shape.
+    pub fn from_parry(shape: &TriMesh) -> Result<Self, RmeshError> {
+        Trimesh::from_indexed(shape.vertices().to_vec(), shape.indices())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use parry3d_f64::query::PointQuery;
+
+    #[test]
+    fn test_parry() {
+        let mesh = create_box(&[2.0, 2.0, 2.0]);
+        let shape = mesh.to_parry().unwrap();
+        assert_eq!(shape.vertices(), mesh.vertices.as_slice());
+        assert_eq!(shape.indices().len(), mesh.faces.len());
+        let point = nalgebra::Point3::new(3.0, 0.0, 0.0);
+        let distance = shape.distance_to_point(&Default::default(), &point, true);
+        assert!((distance - 2.0).abs() < 1e-12);
+
+        let back = Trimesh::from_parry(&mesh.clone().into_parry().unwrap()).unwrap();
+        assert_eq!(back.vertices, mesh.vertices);
+        assert_eq!(back.faces, mesh.faces);
+        assert!(Trimesh::default().to_parry().is_err());
+    }
+}
@@ -0,0 +1,584 @@
+use std::f64::consts::TAU;
+
+use ahash::{AHashMap, AHashSet};
+use nalgebra::{Point2, Point3, Vector2};
+
+use crate::bvh::{Bounds, Bvh};
+use crate::path::{Curve, Path, nest_loops};
+use crate::tolerance::Tolerances;
+
+/// The number of points curves are sampled with and the number of
+/// sides of the circles which round the corners of an offset.
+const RESOLUTION: usize = 32;
+
+/// The box around two points in the XY plane grown by a distance.
+fn flat_bounds(a: &Point2<f64>, b: &Point2<f64>, pad: f64) -> Bounds {
+    let (lower, upper) = (a.inf(b), a.sup(b));
+    (
+        Point3::new(lower.x - pad, lower.y - pad, 0.0),
+        Point3::new(upper.x + pad, upper.y + pad, 0.0),
+    )
+}
+
+/// The edges of closed rings from each point to the next.
+fn ring_edges(rings: &[Vec<Point2<f64>>]) -> Vec<(Point2<f64>, Point2<f64>)> {
+    rings
+        .iter()
+        .flat_map(|ring| (0..ring.len()).map(move |i| (ring[i], ring[(i + 1) % ring.len()])))
+        .collect()
+}
+
+/// A closed region in the XY plane as rings in any winding, where
+/// a point is inside if it's inside an odd number of the rings.
+struct Region {
+    rings: Vec<Vec<Point2<f64>>>,
+    lower: Point2<f64>,
+    upper: Point2<f64>,
+    // the edges of every ring and a tree over their boxes
+    edges: Vec<(Point2<f64>, Point2<f64>)>,
+    tree: Bvh,
+}
+
+impl Region {
+    fn new(rings: Vec<Vec<Point2<f64>>>) -> Self {
+        let (mut lower, mut upper) = (
+            Point2::new(f64::INFINITY, f64::INFINITY),
+            Point2::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+        );
+        for point in rings.iter().flatten() {
+            lower = lower.inf(point);
+            upper = upper.sup(point);
+        }
+        let edges = ring_edges(&rings);
+        let tree = Bvh::new(
+            &edges
+                .iter()
+                .map(|(a, b)| flat_bounds(a, b, 0.0))
+                .collect::<Vec<_>>(),
+        );
+        Self {
+            rings,
+            lower,
+            upper,
+            edges,
+            tree,
+        }
+    }
+
+    /// The closed loops of a path.
+    fn from_path(path: &Path) -> Self {
+        Self::new(
+            path.to_polygons(RESOLUTION)
+                .into_iter()
+                .flat_map(|polygon| std::iter::once(polygon.exterior).chain(polygon.interiors))
+                .collect(),
+        )
+    }
+
+    /// Is a point inside, by the parity of the edges crossed by a ray
+    /// from it towards +X, where only edges near the ray are checked.
+    fn contains(&self, point: &Point2<f64>) -> bool {
+        if (0..2).any(|i| point[i] < self.lower[i] || point[i] > self.upper[i]) {
+            return false;
+        }
+        let ray = flat_bounds(point, &Point2::new(self.upper.x, point.y), 0.0);
+        let mut inside = false;
+        self.tree.query(&ray, |i| {
+            let (a, b) = &self.edges[i];
+            if (a.y > point.y) != (b.y > point.y)
+                && point.x < a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y)
+            {
+                inside = !inside;
+            }
+        });
+        inside
+    }
+}
+
+/// Merges points closer than a tolerance into one index by
+/// hashing them into a grid with cells of that size.
+struct Snap {
+    tolerance: f64,
+    points: Vec<Point2<f64>>,
+    grid: AHashMap<(i64, i64), Vec<usize>>,
+}
+
+impl Snap {
+    fn index(&mut self, point: Point2<f64>) -> usize {
+        let cell = (
+            (point.x / self.tolerance).floor() as i64,
+            (point.y / self.tolerance).floor() as i64,
+        );
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                if let Some(found) = self.grid.get(&(cell.0 + dx, cell.1 + dy))
+                    && let Some(index) = found
+                        .iter()
+                        .find(|i| (self.points[**i] - point).norm() <= self.tolerance)
+                {
+                    return *index;
+                }
+            }
+        }
+        self.points.push(point);
+        self.grid
+            .entry(cell)
+            .or_default()
+            .push(self.points.len() - 1);
+        self.points.len() - 1
+    }
+}
+
+/// The parameters along the segment from `a` to `b` where the segment
+/// from `c` to `d` crosses, touches or overlaps it.
+fn crossings(
+    a: &Point2<f64>,
+    b: &Point2<f64>,
+    c: &Point2<f64>,
+    d: &Point2<f64>,
+    tolerance: f64,
+) -> Vec<f64> {
+    let cross = |u: &Vector2<f64>, v: &Vector2<f64>| u.x * v.y - u.y * v.x;
+    let (r, s) = (b - a, d - c);
+    let length = r.norm();
+    if length == 0.0 {
+        return Vec::new();
+    }
+    let denominator = cross(&r, &s);
+    let along = |p: &Point2<f64>| (p - a).dot(&r) / (length * length);
+    let off = |p: &Point2<f64>| cross(&r, &(p - a)).abs() / length;
+
+    if denominator.abs() <= f64::EPSILON * length * s.norm() {
+        // parallel segments only meet if they're on the same line
+        return [c, d]
+            .into_iter()
+            .filter(|p| off(p) <= tolerance)
+            .map(along)
+            .filter(|t| *t > 0.0 && *t < 1.0)
+            .collect();
+    }
+    let t = cross(&(c - a), &s) / denominator;
+    let u = cross(&(c - a), &r) / denominator;
+    // allow for the tolerance at the ends of either segment
+    let (slack_t, slack_u) = (
+        tolerance / length,
+        tolerance / s.norm().max(f64::MIN_POSITIVE),
+    );
+    if t < -slack_t || t > 1.0 + slack_t || u < -slack_u || u > 1.0 + slack_u {
+        return Vec::new();
+    }
+    vec![t.clamp(0.0, 1.0)]
+}
+
+/// Combine regions into the boundary of the area where a predicate on
+/// which regions contain a point is true. Every edge is split where it
+/// meets another, and each piece is kept if the predicate differs on
+/// its two sides, oriented so the area is on its left. Testing both
+/// sides of every piece handles edges shared by several regions. The
+/// segments and regions are indexed by their boxes so each segment
+/// and point is only checked against the ones near it.
+fn overlay(regions: &[Region], keep: impl Fn(&[bool]) -> bool) -> Vec<Vec<Point2<f64>>> {
+    let segments: Vec<(Point2<f64>, Point2<f64>)> = regions
+        .iter()
+        .flat_map(|region| ring_edges(&region.rings))
+        .collect();
+    let Some(scale) = regions
+        .iter()
+        .filter(|r| !r.rings.is_empty())
+        .map(|r| (r.upper - r.lower).norm())
+        .reduce(f64::max)
+    else {
+        return Vec::new();
+    };
+    let tolerance = Tolerances::default().scaled(scale).merge;
+    // how far to either side of a piece to check the predicate
+    let probe = tolerance * 100.0;
+
+    let mut snap = Snap {
+        tolerance,
+        points: Vec::new(),
+        grid: AHashMap::new(),
+    };
+    let segment_tree = Bvh::new(
+        &segments
+            .iter()
+            .map(|(a, b)| flat_bounds(a, b, tolerance))
+            .collect::<Vec<_>>(),
+    );
+    let region_tree = Bvh::new(
+        &regions
+            .iter()
+            // an empty region's box is inverted so nothing overlaps it
+            .map(|r| {
+                (
+                    Point3::new(r.lower.x, r.lower.y, 0.0),
+                    Point3::new(r.upper.x, r.upper.y, 0.0),
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+    let mut edges: AHashSet<(usize, usize)> = AHashSet::new();
+    let members = |point: &Point2<f64>| -> Vec<bool> {
+        let mut inside = vec![false; regions.len()];
+        region_tree.query(&flat_bounds(point, point, 0.0), |r| {
+            inside[r] = regions[r].contains(point);
+        });
+        inside
+    };
+    for (i, (a, b)) in segments.iter().enumerate() {
+        let (lower, upper) = (a.inf(b), a.sup(b));
+        let mut split = vec![0.0, 1.0];
+        // the tree returns every segment in a leaf it reaches
+        segment_tree.query(&flat_bounds(a, b, 0.0), |j| {
+            let (c, d) = &segments[j];
+            let overlaps = (0..2).all(|k| {
+                c[k].min(d[k]) <= upper[k] + tolerance && c[k].max(d[k]) >= lower[k] - tolerance
+            });
+            if i != j && overlaps {
+                split.extend(crossings(a, b, c, d, tolerance));
+            }
+        });
+        split.sort_by(f64::total_cmp);
+        let indices: Vec<usize> = split.iter().map(|t| snap.index(a + (b - a) * *t)).collect();
+        for pair in indices.windows(2) {
+            let (u, v) = (pair[0], pair[1]);
+            if u == v {
+                continue;
+            }
+            let (p, q) = (snap.points[u], snap.points[v]);
+            let direction = (q - p).normalize();
+            let normal = Vector2::new(-direction.y, direction.x) * probe;
+            let middle = nalgebra::center(&p, &q);
+            let left = keep(&members(&(middle + normal)));
+            let right = keep(&members(&(middle - normal)));
+            if left != right {
+                edges.insert(if left { (u, v) } else { (v, u) });
+            }
+        }
+    }
+
+    // chain the edges into loops taking the sharpest right turn at any
+    // vertex with several ways out so touching loops stay separate
+    let mut outgoing: AHashMap<usize, Vec<usize>> = AHashMap::new();
+    let mut sorted: Vec<(usize, usize)> = edges.into_iter().collect();
+    sorted.sort_unstable();
+    for (u, v) in sorted.iter() {
+        outgoing.entry(*u).or_default().push(*v);
+    }
+    let points = &snap.points;
+    let mut loops = Vec::new();
+    for (start, first) in sorted.iter() {
+        let Some(index) = outgoing
+            .get(start)
+            .and_then(|n| n.iter().position(|v| v == first))
+        else {
+            continue;
+        };
+        outgoing.get_mut(start).unwrap().swap_remove(index);
+        let mut chain = vec![*start];
+        let (mut previous, mut current) = (*start, *first);
+        while current != *start {
+            chain.push(current);
+            let incoming = points[current] - points[previous];
+            let Some(next) = outgoing.get_mut(&current).and_then(|candidates| {
+                let turn = |v: &usize| {
+                    let out = points[*v] - points[current];
+                    (incoming.x * out.y - incoming.y * out.x).atan2(incoming.dot(&out))
+                };
+                let best = (0..candidates.len())
+                    .min_by(|a, b| turn(&candidates[*a]).total_cmp(&turn(&candidates[*b])))?;
+                Some(candidates.swap_remove(best))
+            }) else {
+                break;
+            };
+            (previous, current) = (current, next);
+        }
+        if current == *start && chain.len() > 2 {
+            loops.push(simplify_ring(
+                &chain.iter().map(|i| points[*i]).collect::<Vec<_>>(),
+                tolerance,
+            ));
+        }
+    }
+    loops.retain(|ring| ring.len() > 2);
+    loops
+}
+
+/// Remove the points of a ring which are on a straight
+/// line between the points on either side of them.
+fn simplify_ring(ring: &[Point2<f64>], tolerance: f64) -> Vec<Point2<f64>> {
+    let mut points = ring.to_vec();
+    let mut changed = true;
+    while changed && points.len() > 2 {
+        changed = false;
+        for i in 0..points.len() {
+            let count = points.len();
+            let (a, b, c) = (
+                points[(i + count - 1) % count],
+                points[i],
+                points[(i + 1) % count],
+            );
+            let base = c - a;
+            let height = (base.x * (b - a).y - base.y * (b - a).x).abs() / base.norm();
+            if base.norm() == 0.0 || height <= tolerance {
+                points.remove(i);
+                changed = true;
+                break;
+            }
+        }
+    }
+    points
+}
+
+/// A path of closed lines from the rings of polygons, ordered
+/// so each exterior is followed by the holes inside of it.
+fn to_path(loops: Vec<Vec<Point2<f64>>>) -> Path {
+    let mut vertices = Vec::new();
+    let mut entities = Vec::new();
+    for polygon in nest_loops(&loops) {
+        for ring in polygon {
+            let start = vertices.len();
+            vertices.extend(loops[ring].iter().map(|p| Point3::new(p.x, p.y, 0.0)));
+            let mut points: Vec<usize> = (start..vertices.len()).collect();
+            points.push(start);
+            entities.push(Curve::Line { points });
+        }
+    }
+    Path::new(vertices, entities)
+}
+
+/// The area inside either of two paths.
+///
+/// Parameters
+/// ------------
+/// a
+///   A path whose closed loops in the XY plane are the first area,
+///   where loops inside of another loop are holes.
+/// b
+///   The second area.
+///
+/// Returns
+/// ------------
+/// union
+///   The boundary of the area as closed lines, with exteriors wound
+///   counter-clockwise and holes clockwise. Curves are sampled into
+///   lines and open entities are ignored.
+pub fn union(a: &Path, b: &Path) -> Path {
+    let regions = [Region::from_path(a), Region::from_path(b)];
+    to_path(overlay(&regions, |inside| inside[0] || inside[1]))
+}
+
+/// The area inside of the first path and outside of the second.
+///
+/// Parameters
+/// ------------
+/// a
+///   The path to subtract from.
+/// b
+///   The path to subtract.
+///
+/// Returns
+/// ------------
+/// difference
+///   The boundary of the area as closed lines like `union`.
+pub fn difference(a: &Path, b: &Path) -> Path {
+    let regions = [Region::from_path(a), Region::from_path(b)];
+    to_path(overlay(&regions, |inside| inside[0] && !inside[1]))
+}
+
+/// The area inside of both paths.
+///
+/// Parameters
+/// ------------
+/// a
+///   The first path.
+/// b
+///   The second path.
+///
+/// Returns
+/// ------------
+/// intersection
+///   The boundary of the area as closed lines like `union`.
+pub fn intersection(a: &Path, b: &Path) -> Path {
+    let regions = [Region::from_path(a), Region::from_path(b)];
+    to_path(overlay(&regions, |inside| inside[0] && inside[1]))
+}
+
+/// Grow or shrink the area of a path by a distance, such as to find
+/// the wall of a pocket or the path of a cutter of some radius. Growing
+/// rounds the outside corners and shrinking rounds the inside ones, by
+/// adding or removing every point within the distance of the boundary.
+///
+/// Parameters
+/// ------------
+/// path
+///   The path whose closed loops in the XY plane are the area.
+/// distance
+///   How far to move the boundary outwards, or inwards if negative.
+///
+/// Returns
+/// ------------
+/// offset
+///   The boundary of the new area as closed lines like `union`,
+///   which is empty if shrinking removes everything.
+pub fn offset(path: &Path, distance: f64) -> Path {
+    let area = Region::from_path(path);
+    if distance == 0.0 || !distance.is_finite() {
+        return to_path(overlay(&[area], |inside| inside[0]));
+    }
+    let radius = distance.abs();
+    let circle: Vec<Vector2<f64>> = (0..RESOLUTION)
+        .map(|i| {
+            let (sin, cos) = (TAU * i as f64 / RESOLUTION as f64).sin_cos();
+            Vector2::new(cos, sin) * radius
+        })
+        .collect();
+
+    // every point within the distance of the boundary is inside the
+    // band around an edge or the circle around a corner
+    let mut regions = Vec::new();
+    for ring in area.rings.iter() {
+        for (i, a) in ring.iter().enumerate() {
+            let b = ring[(i + 1) % ring.len()];
+            regions.push(Region::new(vec![circle.iter().map(|c| a + c).collect()]));
+            let Some(direction) = (b - a).try_normalize(0.0) else {
+                continue;
+            };
+            let normal = Vector2::new(-direction.y, direction.x) * radius;
+            regions.push(Region::new(vec![vec![
+                a - normal,
+                b - normal,
+                b + normal,
+                a + normal,
+            ]]));
+        }
+    }
+    regions.insert(0, area);
+    let loops = if distance > 0.0 {
+        overlay(&regions, |inside| inside.iter().any(|i| *i))
+    } else {
+        overlay(&regions, |inside| {
+            inside[0] && !inside[1..].iter().any(|i| *i)
+        })
+    };
+    to_path(loops)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::path::rectangle;
+    use approx::assert_relative_eq;
+    use nalgebra::Vector3;
+    use std::f64::consts::PI;
+
+    /// The total area inside of the closed lines of a path written by
+    /// the operations here, where holes have a negative area.
+    fn area(path: &Path) -> f64 {
+        path.entities
+            .iter()
+            .map(|e| {
+                let points: Vec<Point2<f64>> = e.indices()[1..]
+                    .iter()
+                    .map(|i| path.vertices[*i].xy())
+                    .collect();
+                crate::path::signed_area(&points)
+            })
+            .sum()
+    }
+
+    /// A rectangle moved to be centered on a point.
+    fn square(size: f64, x: f64, y: f64) -> Path {
+        let mut path = rectangle(size, size);
+        for v in path.vertices.iter_mut() {
+            *v += Vector3::new(x, y, 0.0);
+        }
+        path
+    }
+
+    /// A regular polygon of some number of sides around the origin.
+    fn polygon(sides: usize, radius: f64) -> Path {
+        let vertices: Vec<Point3<f64>> = (0..sides)
+            .map(|i| {
+                let (sin, cos) = (TAU * i as f64 / sides as f64).sin_cos();
+                Point3::new(cos * radius, sin * radius, 0.0)
+            })
+            .collect();
+        let mut points: Vec<usize> = (0..sides).collect();
+        points.push(0);
+        Path::new(vertices, vec![Curve::Line { points }])
+    }
+
+    #[test]
+    fn test_boolean() {
+        let a = square(2.0, 0.0, 0.0);
+        let b = square(2.0, 1.0, 1.0);
+        let joined = union(&a, &b);
+        assert_eq!(joined.entities.len(), 1);
+        assert_eq!(joined.entities[0].indices().len(), 9);
+        assert_relative_eq!(area(&joined), 7.0, epsilon = 1e-10);
+        assert_relative_eq!(area(&intersection(&a, &b)), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(area(&difference(&a, &b)), 3.0, epsilon = 1e-10);
+        assert_relative_eq!(area(&difference(&b, &a)), 3.0, epsilon = 1e-10);
+
+        // squares sharing a side become one rectangle without extra corners
+        let beside = union(&a, &square(2.0, 2.0, 0.0));
+        assert_eq!(beside.entities.len(), 1);
+        assert_eq!(beside.entities[0].indices().len(), 5);
+        assert_relative_eq!(area(&beside), 8.0, epsilon = 1e-10);
+        assert!(intersection(&a, &square(2.0, 2.0, 0.0)).entities.is_empty());
+
+        // a square inside another cuts a hole which is wound clockwise
+        let ring = difference(&a, &square(1.0, 0.0, 0.0));
+        assert_eq!(ring.entities.len(), 2);
+        assert_relative_eq!(area(&ring), 3.0, epsilon = 1e-10);
+        let polygons = ring.to_polygons(8);
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].interiors.len(), 1);
+        // and the hole filled back in leaves the original square
+        assert_relative_eq!(area(&union(&ring, &a)), 4.0, epsilon = 1e-10);
+
+        // rings of thousands of segments only check their neighbors
+        let sides = 4000;
+        let ngon = |radius: f64| sides as f64 / 2.0 * radius * radius * (TAU / sides as f64).sin();
+        let annulus = difference(&polygon(sides, 2.0), &polygon(sides, 1.0));
+        assert_eq!(annulus.entities.len(), 2);
+        assert_relative_eq!(area(&annulus), ngon(2.0) - ngon(1.0), epsilon = 1e-8);
+
+        // disjoint areas stay separate
+        let apart = union(&a, &square(2.0, 5.0, 0.0));
+        assert_eq!(apart.entities.len(), 2);
+        assert!(
+            union(&Path::new(vec![], vec![]), &Path::new(vec![], vec![]))
+                .entities
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_offset() {
+        let a = square(2.0, 0.0, 0.0);
+        // growing rounds the corners with the inscribed polygon of a circle
+        let grown = offset(&a, 1.0);
+        assert_eq!(grown.entities.len(), 1);
+        let circle = RESOLUTION as f64 / 2.0 * (TAU / RESOLUTION as f64).sin();
+        assert_relative_eq!(area(&grown), 4.0 + 8.0 + circle, epsilon = 1e-8);
+        assert!(circle < PI && circle > 3.1);
+
+        // shrinking keeps the convex corners sharp
+        let shrunk = offset(&a, -0.5);
+        assert_eq!(shrunk.entities.len(), 1);
+        assert_eq!(shrunk.entities[0].indices().len(), 5);
+        assert_relative_eq!(area(&shrunk), 1.0, epsilon = 1e-10);
+        assert!(offset(&a, -1.5).entities.is_empty());
+        assert_relative_eq!(area(&offset(&a, 0.0)), 4.0, epsilon = 1e-10);
+
+        // shrinking a ring grows its hole
+        let ring = difference(&a, &square(1.0, 0.0, 0.0));
+        let thinner = offset(&ring, -0.1);
+        assert_eq!(thinner.entities.len(), 2);
+        let hole = 1.2 * 1.2 - (4.0 - PI) * 0.1 * 0.1;
+        assert_relative_eq!(area(&thinner), 1.8 * 1.8 - hole, epsilon = 1e-3);
+    }
+}
@@ -0,0 +1,389 @@
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector3};
+
+use std::f64::consts::TAU;
+
+use crate::exchange::ExportOptions;
+use crate::path::{Curve, Path};
+
+/// An entity of a DXF file as the group codes and values after
+/// the code 0 record naming its kind.
+struct Entity<'a> {
+    kind: &'a str,
+    values: Vec<(i32, &'a str)>,
+}
+
+impl Entity<'_> {
+    /// The first value with a group code as a number.
+    fn number(&self, code: i32) -> Option<Result<f64>> {
+        self.values.iter().find(|(c, _)| *c == code).map(|(_, v)| {
+            v.parse::<f64>()
+                .map_err(|_| anyhow!("Invalid number in {}: `{v}`", self.kind))
+        })
+    }
+
+    /// A number which the entity must have.
+    fn required(&self, code: i32) -> Result<f64> {
+        self.number(code)
+            .ok_or_else(|| anyhow!("{} is missing group code {code}", self.kind))?
+    }
+
+    /// The point from the group codes of its X, Y and optional Z.
+    fn point(&self, code: i32) -> Result<Point3<f64>> {
+        Ok(Point3::new(
+            self.required(code)?,
+            self.required(code + 10)?,
+            self.number(code + 20).transpose()?.unwrap_or(0.0),
+        ))
+    }
+}
+
+/// Split the text of an ASCII DXF file into the entities of its
+/// ENTITIES section.
+fn entities(text: &str) -> Result<Vec<Entity<'_>>> {
+    let lines: Vec<&str> = text.lines().map(|l| l.trim()).collect();
+    let mut pairs = Vec::with_capacity(lines.len() / 2);
+    for pair in lines.chunks_exact(2) {
+        let code = pair[0]
+            .parse::<i32>()
+            .map_err(|_| anyhow!("Invalid DXF group code: `{}`", pair[0]))?;
+        pairs.push((code, pair[1]));
+    }
+
+    let start = pairs
+        .windows(2)
+        .position(|w| w[0] == (0, "SECTION") && w[1] == (2, "ENTITIES"))
+        .ok_or_else(|| anyhow!("DXF has no ENTITIES section"))?;
+    let mut result: Vec<Entity> = Vec::new();
+    for (code, value) in pairs[start + 2..].iter().copied() {
+        if code == 0 {
+            if value == "ENDSEC" {
+                return Ok(result);
+            }
+            result.push(Entity {
+                kind: value,
+                values: Vec::new(),
+            });
+        } else if let Some(entity) = result.last_mut() {
+            entity.values.push((code, value));
+        }
+    }
+    Err(anyhow!("DXF ENTITIES section isn't terminated"))
+}
+
+/// The center of the arc between two points with a polyline bulge,
+/// which is the tangent of a quarter of the angle it sweeps through
+/// and is positive for counter-clockwise arcs.
+fn bulge_center(a: &Point3<f64>, b: &Point3<f64>, bulge: f64) -> Point3<f64> {
+    let chord = b - a;
+    let angle = 4.0 * bulge.atan();
+    // the distance from the middle of the chord to the center
+    let offset = 0.5 / (angle / 2.0).tan();
+    a + chord / 2.0 + Vector3::new(-chord.y, chord.x, 0.0) * offset
+}
+
+/// Load the LINE, ARC, CIRCLE and LWPOLYLINE entities of an ASCII
+/// DXF file into a path. Arcs are always counter-clockwise, so a
+/// polyline segment with a negative bulge becomes a clockwise arc
+/// and the straight segments of a polyline are one line. A SPLINE
+/// which is a single bezier segment, as `Path::export` writes them,
+/// is loaded as a bezier. Vertices aren't merged so entities which
+/// touch don't share an index.
+///
+/// Parameters
+/// ------------
+/// bytes
+///   The contents of the DXF file.
+///
+/// Returns
+/// ------------
+/// path
+///   The loaded path, or an error if the file isn't an ASCII DXF.
+///   Other kinds of entity are skipped.
+pub fn load_dxf(bytes: &[u8]) -> Result<Path> {
+    let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("DXF must be ASCII"))?;
+    let mut vertices: Vec<Point3<f64>> = Vec::new();
+    let mut curves: Vec<Curve> = Vec::new();
+    let mut add = |point: Point3<f64>| {
+        vertices.push(point);
+        vertices.len() - 1
+    };
+
+    for entity in entities(text)? {
+        match entity.kind {
+            "LINE" => curves.push(Curve::Line {
+                points: vec![add(entity.point(10)?), add(entity.point(11)?)],
+            }),
+            "CIRCLE" | "ARC" => {
+                let center = entity.point(10)?;
+                let radius = entity.required(40)?;
+                let on = |degrees: f64| {
+                    let (sin, cos) = degrees.to_radians().sin_cos();
+                    center + Vector3::new(cos, sin, 0.0) * radius
+                };
+                let closed = entity.kind == "CIRCLE";
+                // a full circle needs a second point a quarter turn on
+                let (start, end) = if closed {
+                    (0.0, 90.0)
+                } else {
+                    (entity.required(50)?, entity.required(51)?)
+                };
+                curves.push(Curve::Circle {
+                    start: add(on(start)),
+                    end: add(on(end)),
+                    center: add(center),
+                    closed,
+                    is_ccw: true,
+                });
+            }
+            "SPLINE" => {
+                let knots: Vec<&str> = entity
+                    .values
+                    .iter()
+                    .filter(|(c, _)| *c == 40)
+                    .map(|(_, v)| *v)
+                    .collect();
+                // only a single bezier segment has every knot at either end
+                let count = entity.values.iter().filter(|(c, _)| *c == 10).count();
+                let bezier = count > 1
+                    && knots.len() == count * 2
+                    && knots.iter().enumerate().all(|(i, k)| {
+                        k.parse::<f64>().ok() == Some(if i < count { 0.0 } else { 1.0 })
+                    });
+                if !bezier {
+                    continue;
+                }
+                let mut points = Vec::with_capacity(count);
+                let mut point: Option<[f64; 3]> = None;
+                for (code, value) in entity.values.iter() {
+                    let number = || {
+                        value
+                            .parse::<f64>()
+                            .map_err(|_| anyhow!("Invalid number in SPLINE: `{value}`"))
+                    };
+                    match code {
+                        10 => {
+                            if let Some([x, y, z]) = point.take() {
+                                points.push(add(Point3::new(x, y, z)));
+                            }
+                            point = Some([number()?, 0.0, 0.0]);
+                        }
+                        20 | 30 => {
+                            if let Some(p) = point.as_mut() {
+                                p[(*code as usize - 10) / 10] = number()?;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                if let Some([x, y, z]) = point {
+                    points.push(add(Point3::new(x, y, z)));
+                }
+                curves.push(Curve::Bezier { points });
+            }
+            "LWPOLYLINE" => {
+                let elevation = entity.number(38).transpose()?.unwrap_or(0.0);
+                // the vertices with the bulge of the segment after them
+                let mut corners: Vec<(Point3<f64>, f64)> = Vec::new();
+                let mut x = None;
+                for (code, value) in entity.values.iter() {
+                    let number = || {
+                        value
+                            .parse::<f64>()
+                            .map_err(|_| anyhow!("Invalid number in LWPOLYLINE: `{value}`"))
+                    };
+                    match code {
+                        10 => x = Some(number()?),
+                        20 => corners.push((
+                            Point3::new(
+                                x.take().ok_or_else(|| anyhow!("LWPOLYLINE Y without X"))?,
+                                number()?,
+                                elevation,
+                            ),
+                            0.0,
+                        )),
+                        42 => {
+                            if let Some(last) = corners.last_mut() {
+                                last.1 = number()?;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                if corners.len() < 2 {
+                    continue;
+                }
+                let flags = entity.number(70).transpose()?.unwrap_or(0.0) as u32;
+                let segments = if flags & 1 == 1 {
+                    corners.len()
+                } else {
+                    corners.len() - 1
+                };
+
+                let indices: Vec<usize> = corners.iter().map(|(p, _)| add(*p)).collect();
+                let mut line: Vec<usize> = Vec::new();
+                for i in 0..segments {
+                    let (a, b) = (i, (i + 1) % corners.len());
+                    let bulge = corners[a].1;
+                    if bulge == 0.0 {
+                        if line.is_empty() {
+                            line.push(indices[a]);
+                        }
+                        line.push(indices[b]);
+                        continue;
+                    }
+                    if line.len() > 1 {
+                        curves.push(Curve::Line {
+                            points: std::mem::take(&mut line),
+                        });
+                    }
+                    line.clear();
+                    curves.push(Curve::Circle {
+                        start: indices[a],
+                        end: indices[b],
+                        center: add(bulge_center(&corners[a].0, &corners[b].0, bulge)),
+                        closed: false,
+                        is_ccw: bulge > 0.0,
+                    });
+                }
+                if line.len() > 1 {
+                    curves.push(Curve::Line { points: line });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(Path::new(vertices, curves))
+}
+
+/// Write a group code and a number.
+fn write_number(out: &mut String, code: i32, value: f64) {
+    out.push_str(&format!("{code}\n"));
+    ExportOptions::default().write_float(value, out);
+    out.push('\n');
+}
+
+/// Write the group codes of the X and Y of a point.
+fn write_point(out: &mut String, code: i32, point: &Point3<f64>) {
+    write_number(out, code, point.x);
+    write_number(out, code + 10, point.y);
+}
+
+/// Write a path to the text of an ASCII DXF file. Lines become
+/// LWPOLYLINE entities which are closed if they end where they start,
+/// circles and arcs become CIRCLE and ARC entities and beziers become
+/// a SPLINE entity with the knots of a single bezier segment.
+pub fn export(path: &Path) -> String {
+    let vertices = &path.vertices;
+    let mut out = String::from("0\nSECTION\n2\nENTITIES\n");
+    for entity in path.entities.iter() {
+        match entity {
+            Curve::Line { points } => {
+                if points.len() < 2 {
+                    continue;
+                }
+                let closed = points.len() > 3 && points.first() == points.last();
+                let points = if closed {
+                    &points[..points.len() - 1]
+                } else {
+                    &points[..]
+                };
+                out.push_str("0\nLWPOLYLINE\n8\n0\n");
+                out.push_str(&format!("90\n{}\n70\n{}\n", points.len(), closed as u8));
+                write_number(&mut out, 38, vertices[points[0]].z);
+                for index in points {
+                    write_point(&mut out, 10, &vertices[*index]);
+                }
+            }
+            Curve::Circle { start, center, .. } => {
+                let radius = (vertices[*start] - vertices[*center]).norm();
+                let (from, sweep) = entity.sweep(vertices).unwrap_or_default();
+                if sweep.abs() >= TAU {
+                    out.push_str("0\nCIRCLE\n8\n0\n");
+                } else {
+                    out.push_str("0\nARC\n8\n0\n");
+                }
+                write_number(&mut out, 10, vertices[*center].x);
+                write_number(&mut out, 20, vertices[*center].y);
+                write_number(&mut out, 30, vertices[*center].z);
+                write_number(&mut out, 40, radius);
+                if sweep.abs() < TAU {
+                    // arcs are always counter-clockwise so a clockwise one is reversed
+                    let (a, b) = if sweep > 0.0 {
+                        (from, from + sweep)
+                    } else {
+                        (from + sweep, from)
+                    };
+                    write_number(&mut out, 50, a.to_degrees().rem_euclid(360.0));
+                    write_number(&mut out, 51, b.to_degrees().rem_euclid(360.0));
+                }
+            }
+            Curve::Bezier { points } => {
+                if points.len() < 2 {
+                    continue;
+                }
+                let degree = points.len() - 1;
+                out.push_str("0\nSPLINE\n8\n0\n70\n8\n");
+                out.push_str(&format!(
+                    "71\n{degree}\n72\n{}\n73\n{}\n",
+                    points.len() * 2,
+                    points.len()
+                ));
+                for knot in 0..points.len() * 2 {
+                    write_number(&mut out, 40, (knot >= points.len()) as u8 as f64);
+                }
+                for index in points {
+                    write_point(&mut out, 10, &vertices[*index]);
+                    write_number(&mut out, 30, vertices[*index].z);
+                }
+            }
+        }
+    }
+    out.push_str("0\nENDSEC\n0\nEOF\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use approx::assert_relative_eq;
+    use nalgebra::Point2;
+
+    #[test]
+    fn test_load_dxf() {
+        let text = "0\nSECTION\n2\nHEADER\n0\nENDSEC\n\
+                    0\nSECTION\n2\nENTITIES\n\
+                    0\nLINE\n8\n0\n10\n0.0\n20\n0.0\n30\n0.0\n11\n3.0\n21\n4.0\n31\n0.0\n\
+                    0\nCIRCLE\n10\n1.0\n20\n1.0\n40\n2.0\n\
+                    0\nARC\n10\n0.0\n20\n0.0\n40\n1.0\n50\n0.0\n51\n90.0\n\
+                    0\nLWPOLYLINE\n90\n4\n70\n1\n\
+                    10\n0.0\n20\n0.0\n10\n2.0\n20\n0.0\n42\n1.0\n10\n2.0\n20\n2.0\n10\n0.0\n20\n2.0\n\
+                    0\nSPLINE\n10\n0.0\n20\n0.0\n\
+                    0\nENDSEC\n0\nEOF\n";
+        let path = load_dxf(text.as_bytes()).unwrap();
+        let kinds: Vec<&str> = path.entities.iter().map(|e| e.kind()).collect();
+        assert_eq!(kinds, vec!["line", "circle", "arc", "line", "arc", "line"]);
+        assert_relative_eq!(path.entities[0].length(&path.vertices), 5.0);
+        assert_relative_eq!(
+            path.entities[1].length(&path.vertices),
+            4.0 * std::f64::consts::PI
+        );
+
+        // the bulge of 1 is a counter-clockwise half circle
+        if let Curve::Circle { center, .. } = &path.entities[4] {
+            assert_relative_eq!(path.vertices[*center], Point3::new(2.0, 1.0, 0.0));
+        } else {
+            panic!("Expected a circle");
+        }
+        // the closed polyline wraps back to its first vertex
+        assert_eq!(path.entities[5].indices().len(), 3);
+        let (lower, upper) = path.bounds().unwrap();
+        assert_relative_eq!(lower.xy(), Point2::new(-1.0, -1.0));
+        assert_relative_eq!(upper.xy(), Point2::new(3.0, 4.0));
+
+        assert!(load_dxf(b"0\nSECTION\n2\nHEADER\n0\nENDSEC\n").is_err());
+        assert!(load_dxf(b"0\nSECTION\n2\nENTITIES\n0\nLINE\n10\n0\n").is_err());
+        assert!(load_dxf(b"0\nSECTION\n2\nENTITIES\n0\nLINE\n10\n0\n0\nENDSEC\n").is_err());
+    }
+}
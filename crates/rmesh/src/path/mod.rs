@@ -0,0 +1,920 @@
+mod clipping;
+mod dxf;
+mod svg;
+
+use std::f64::consts::TAU;
+
+use anyhow::{Result, anyhow};
+use nalgebra::{Point2, Point3, Vector3};
+
+use crate::creation::Triangulator;
+use crate::mesh::Trimesh;
+use crate::tolerance::Tolerances;
+
+pub use crate::path::clipping::{difference, intersection, offset, union};
+pub use crate::path::dxf::load_dxf;
+pub use crate::path::svg::load_svg;
+
+// the number of points a bezier is sampled with to measure its length
+const BEZIER_LENGTH_RESOLUTION: usize = 256;
+
+pub enum Curve {
+    Line {
+        // indexes of points on a line.
+        points: Vec<usize>,
+    },
+    Circle {
+        // start is either the start point of a circular arc
+        // or any point on the full circle
+        start: usize,
+
+        // The end point of the circular are or any point
+        // on the full circle that isn't colinear with the
+        // center and start points as we need to know the
+        // direction of the normal this cicle is in
+        end: usize,
+
+        // center is the center of the circle
+        center: usize,
+
+        // we need to know if the circle is closed as start and end
+        // need to be different points in every case, even for a full circle
+        closed: bool,
+
+        is_ccw: bool, // is the circle counter-clockwise?
+    },
+    Bezier {
+        // indexes of control points for the bezier curve
+        points: Vec<usize>,
+    },
+}
+
+impl Curve {
+    /// Create a curve from a string kind and the indices of its vertices,
+    /// which is how curves are passed through the language bindings.
+    ///
+    /// Parameters
+    /// ------------
+    /// kind
+    ///   One of `line`, `bezier`, `circle`, `arc` or `arc_cw`.
+    /// indices
+    ///   The points of a line or bezier, or the start,
+    ///   end and center of a circle or arc.
+    ///
+    /// Returns
+    /// ------------
+    /// Result<Curve>
+    ///   The curve or an error for an unknown kind or wrong indices.
+    pub fn from_kind(kind: &str, indices: Vec<usize>) -> Result<Self> {
+        match (kind, indices.as_slice()) {
+            ("line", _) => Ok(Curve::Line { points: indices }),
+            ("bezier", _) => Ok(Curve::Bezier { points: indices }),
+            ("circle" | "arc" | "arc_cw", [start, end, center]) => Ok(Curve::Circle {
+                start: *start,
+                end: *end,
+                center: *center,
+                closed: kind == "circle",
+                is_ccw: kind != "arc_cw",
+            }),
+            ("circle" | "arc" | "arc_cw", _) => Err(anyhow!(
+                "`{kind}` needs exactly 3 indices for the start, end and center"
+            )),
+            _ => Err(anyhow!("Unknown curve kind: `{kind}`")),
+        }
+    }
+
+    /// The kind of curve as accepted by `Curve::from_kind`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Curve::Line { .. } => "line",
+            Curve::Bezier { .. } => "bezier",
+            Curve::Circle { closed: true, .. } => "circle",
+            Curve::Circle { is_ccw: true, .. } => "arc",
+            Curve::Circle { .. } => "arc_cw",
+        }
+    }
+
+    /// The indices of the vertices of the curve as accepted by `Curve::from_kind`.
+    pub fn indices(&self) -> Vec<usize> {
+        match self {
+            Curve::Line { points } | Curve::Bezier { points } => points.clone(),
+            Curve::Circle {
+                start, end, center, ..
+            } => vec![*start, *end, *center],
+        }
+    }
+
+    pub fn length(&self, vertices: &[Point3<f64>]) -> f64 {
+        match self {
+            Curve::Line { points } => {
+                if points.len() < 2 {
+                    return 0.0;
+                }
+                points
+                    .windows(2)
+                    .map(|w| {
+                        let a = vertices[w[0]];
+                        let b = vertices[w[1]];
+                        (b - a).norm()
+                    })
+                    .sum()
+            }
+            Curve::Circle { start, center, .. } => {
+                let radius = (vertices[*start] - vertices[*center]).norm();
+                let (_, sweep) = self.sweep(vertices).unwrap_or_default();
+                radius * sweep.abs()
+            }
+            Curve::Bezier { .. } => self
+                .discrete(vertices, BEZIER_LENGTH_RESOLUTION)
+                .windows(2)
+                .map(|w| (w[1] - w[0]).norm())
+                .sum(),
+        }
+    }
+
+    /// The angle of the start of a circle or arc around its center in the
+    /// XY plane and the signed angle it sweeps through, which is positive
+    /// counter-clockwise and a full turn for a closed circle.
+    fn sweep(&self, vertices: &[Point3<f64>]) -> Option<(f64, f64)> {
+        let Curve::Circle {
+            start,
+            end,
+            center,
+            closed,
+            is_ccw,
+        } = self
+        else {
+            return None;
+        };
+        let angle = |index: usize| {
+            let v = vertices[index] - vertices[*center];
+            v.y.atan2(v.x)
+        };
+        let (from, to) = (angle(*start), angle(*end));
+        let turn = match (closed, is_ccw) {
+            (true, _) => TAU,
+            (false, true) => (to - from).rem_euclid(TAU),
+            (false, false) => (from - to).rem_euclid(TAU),
+        };
+        // an arc ending where it starts is a full turn
+        let turn = if turn == 0.0 { TAU } else { turn };
+        Some((from, if *is_ccw { turn } else { -turn }))
+    }
+
+    /// Points along the curve, where circles and arcs are in the plane of
+    /// their center parallel to XY and the points of a closed circle end
+    /// on the start to close the loop.
+    ///
+    /// Parameters
+    /// ------------
+    /// vertices
+    ///   The vertices the curve indexes.
+    /// resolution
+    ///   The number of points to sample circles, arcs and beziers with.
+    ///
+    /// Returns
+    /// ------------
+    /// discrete
+    ///   The points along the curve in order.
+    pub fn discrete(&self, vertices: &[Point3<f64>], resolution: usize) -> Vec<Point3<f64>> {
+        match self {
+            Curve::Line { points } => {
+                // a discrete line is just the vertices indexed by the points
+                points.iter().map(|&i| vertices[i]).collect()
+            }
+            Curve::Circle {
+                start,
+                end,
+                center,
+                closed,
+                ..
+            } => {
+                let center_point = vertices[*center];
+                let radius = (vertices[*start] - center_point).norm();
+                let (angle, sweep) = self.sweep(vertices).unwrap_or_default();
+                let steps = resolution.max(2) - 1;
+                let mut points: Vec<Point3<f64>> = (0..=steps)
+                    .map(|i| {
+                        let (sin, cos) = (angle + sweep * i as f64 / steps as f64).sin_cos();
+                        center_point + Vector3::new(cos, sin, 0.0) * radius
+                    })
+                    .collect();
+                // end exactly on the vertices so the curve joins its neighbors
+                points[0] = vertices[*start];
+                points[steps] = if *closed {
+                    vertices[*start]
+                } else {
+                    vertices[*end]
+                };
+                points
+            }
+            Curve::Bezier { points } => {
+                if points.len() < 2 {
+                    return vec![];
+                }
+                // Collect control points
+                let control: Vec<Point3<f64>> = points.iter().map(|&i| vertices[i]).collect();
+                let n = control.len() - 1;
+
+                // Precompute binomial coefficients
+                fn binomial(n: usize, k: usize) -> f64 {
+                    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+                }
+                let binoms: Vec<f64> = (0..=n).map(|k| binomial(n, k)).collect();
+
+                // Sample points along the curve
+                (0..resolution)
+                    .map(|step| {
+                        let t = step as f64 / (resolution - 1) as f64;
+                        let one_minus_t = 1.0 - t;
+                        let mut pt = Point3::origin();
+                        for (i, p) in control.iter().enumerate() {
+                            let coeff =
+                                binoms[i] * one_minus_t.powi((n - i) as i32) * t.powi(i as i32);
+                            pt += p.coords * coeff;
+                        }
+                        Point3::from(pt)
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A 2D vector format a path can be exported to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathFormat {
+    // the DXF format is the ASCII interchange format of CAD drawings
+    DXF,
+    // the SVG format is the XML vector format of the web
+    SVG,
+}
+
+impl PathFormat {
+    /// Convert a string like `dxf` or `.SVG` to a PathFormat.
+    pub fn from_string(s: &str) -> Result<Self> {
+        match s
+            .trim()
+            .trim_start_matches('.')
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "dxf" => Ok(PathFormat::DXF),
+            "svg" => Ok(PathFormat::SVG),
+            other => Err(anyhow!("Unsupported path file type: `{other}`")),
+        }
+    }
+}
+
+/// A polygon in the XY plane with holes, where the rings don't repeat
+/// their first point at the end.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    // wound counter-clockwise
+    pub exterior: Vec<Point2<f64>>,
+    // wound clockwise
+    pub interiors: Vec<Vec<Point2<f64>>>,
+}
+
+impl Polygon {
+    /// The points of every ring in order and the indices of each ring
+    /// into them, as `Triangulator::trianglate_2d` takes a polygon with
+    /// the exterior first.
+    pub fn rings(&self) -> (Vec<Point2<f64>>, Vec<Vec<usize>>) {
+        let mut points = Vec::new();
+        let mut rings = Vec::with_capacity(self.interiors.len() + 1);
+        for ring in std::iter::once(&self.exterior).chain(self.interiors.iter()) {
+            rings.push((points.len()..points.len() + ring.len()).collect());
+            points.extend(ring.iter().copied());
+        }
+        (points, rings)
+    }
+
+    /// The area of the exterior minus the area of the holes.
+    pub fn area(&self) -> f64 {
+        signed_area(&self.exterior) + self.interiors.iter().map(|r| signed_area(r)).sum::<f64>()
+    }
+}
+
+pub struct Path {
+    pub entities: Vec<Curve>,
+    pub vertices: Vec<Point3<f64>>,
+}
+
+impl Path {
+    /// Create a new Path from a list of vertices and curves.
+    pub fn new(vertices: Vec<Point3<f64>>, entities: Vec<Curve>) -> Self {
+        Self { vertices, entities }
+    }
+
+    /// The summed length of every entity in the path.
+    pub fn length(&self) -> f64 {
+        self.entities.iter().map(|e| e.length(&self.vertices)).sum()
+    }
+
+    /// An axis aligned box containing the path including the full
+    /// extent of any circles, or `None` if the path has no vertices.
+    pub fn bounds(&self) -> Option<(Point3<f64>, Point3<f64>)> {
+        let first = *self.vertices.first()?;
+        let (mut lower, mut upper) = self
+            .vertices
+            .iter()
+            .fold((first, first), |(l, u), v| (l.inf(v), u.sup(v)));
+        for entity in self.entities.iter() {
+            if let Curve::Circle { start, center, .. } = entity {
+                let radius = (self.vertices[*start] - self.vertices[*center]).norm();
+                let extent = Vector3::repeat(radius);
+                lower = lower.inf(&(self.vertices[*center] - extent));
+                upper = upper.sup(&(self.vertices[*center] + extent));
+            }
+        }
+        Some((lower, upper))
+    }
+
+    /// Discretize every entity in the path into points.
+    ///
+    /// Parameters
+    /// ------------
+    /// resolution
+    ///   The number of points to sample curves with.
+    ///
+    /// Returns
+    /// ------------
+    /// discrete
+    ///   The points of each entity in the same order as `entities`.
+    pub fn discrete(&self, resolution: usize) -> Vec<Vec<Point3<f64>>> {
+        self.entities
+            .iter()
+            .map(|e| e.discrete(&self.vertices, resolution))
+            .collect()
+    }
+
+    /// Export the path to the text of a 2D vector format, where Z values
+    /// are dropped. Circles, arcs and beziers are written exactly except
+    /// beziers of a degree above three which are sampled into lines.
+    ///
+    /// Parameters
+    /// ------------
+    /// format
+    ///   The format to write.
+    ///
+    /// Returns
+    /// ------------
+    /// exported
+    ///   The text of the file.
+    pub fn export(&self, format: PathFormat) -> String {
+        match format {
+            PathFormat::DXF => dxf::export(self),
+            PathFormat::SVG => svg::export(self),
+        }
+    }
+
+    /// Convert the path to polygons in the XY plane. Every entity is
+    /// discretized and entities whose ends touch are joined into chains,
+    /// where the chains which close into loops become the rings of the
+    /// polygons and any open chains are dropped. Loops inside an odd
+    /// number of other loops are the holes of the loop around them.
+    ///
+    /// Parameters
+    /// ------------
+    /// resolution
+    ///   The number of points to sample curves with.
+    ///
+    /// Returns
+    /// ------------
+    /// polygons
+    ///   Every polygon with its exterior wound counter-clockwise
+    ///   and its holes clockwise, largest first.
+    pub fn to_polygons(&self, resolution: usize) -> Vec<Polygon> {
+        let scale = self
+            .bounds()
+            .map(|(lower, upper)| (upper - lower).norm())
+            .unwrap_or(1.0);
+        let tolerance = Tolerances::default().scaled(scale).merge;
+        let chains: Vec<Vec<Point2<f64>>> = self
+            .discrete(resolution)
+            .into_iter()
+            .map(|points| points.iter().map(|p| p.xy()).collect::<Vec<_>>())
+            .filter(|points| points.len() > 1)
+            .collect();
+
+        // join chains end to end until they close or nothing touches
+        let near = |a: &Point2<f64>, b: &Point2<f64>| (a - b).norm() <= tolerance;
+        let mut used = vec![false; chains.len()];
+        let mut loops: Vec<Vec<Point2<f64>>> = Vec::new();
+        for seed in 0..chains.len() {
+            if used[seed] {
+                continue;
+            }
+            used[seed] = true;
+            let mut points = chains[seed].clone();
+            while !(points.len() > 2 && near(&points[0], &points[points.len() - 1])) {
+                let end = points[points.len() - 1];
+                let Some((next, reverse)) = (0..chains.len()).find_map(|i| {
+                    if used[i] {
+                        None
+                    } else if near(&chains[i][0], &end) {
+                        Some((i, false))
+                    } else if near(&chains[i][chains[i].len() - 1], &end) {
+                        Some((i, true))
+                    } else {
+                        None
+                    }
+                }) else {
+                    break;
+                };
+                used[next] = true;
+                if reverse {
+                    points.extend(chains[next].iter().rev().skip(1));
+                } else {
+                    points.extend(chains[next].iter().skip(1));
+                }
+            }
+            if points.len() > 3 && near(&points[0], &points[points.len() - 1]) {
+                points.pop();
+                loops.push(points);
+            }
+        }
+
+        nest_loops(&loops)
+            .into_iter()
+            .map(|rings| {
+                // orient the exterior counter-clockwise and holes clockwise
+                let mut rings = rings.into_iter().enumerate().map(|(i, ring)| {
+                    let mut points = loops[ring].clone();
+                    if (signed_area(&points) > 0.0) != (i == 0) {
+                        points.reverse();
+                    }
+                    points
+                });
+                Polygon {
+                    exterior: rings.next().unwrap_or_default(),
+                    interiors: rings.collect(),
+                }
+            })
+            .collect()
+    }
+
+    /// Extrude the closed entities of the path along Z into a mesh, where
+    /// loops inside of another loop become holes. The path is expected to
+    /// be in the XY plane and any Z values are ignored.
+    ///
+    /// Parameters
+    /// ------------
+    /// height
+    ///   The distance to extrude along Z which may be negative.
+    /// resolution
+    ///   The number of points to sample curves with.
+    ///
+    /// Returns
+    /// ------------
+    /// extrusion
+    ///   A closed mesh or an error if the path has no closed loops.
+    pub fn extrude(&self, height: f64, resolution: usize) -> Result<Trimesh> {
+        if height == 0.0 || !height.is_finite() {
+            return Err(anyhow!("Extrusion height must be finite and non-zero"));
+        }
+        let polygons = self.to_polygons(resolution);
+        if polygons.is_empty() {
+            return Err(anyhow!("Path has no closed loops to extrude"));
+        }
+
+        let mut triangulator = Triangulator::new();
+        let mut vertices = Vec::new();
+        let mut faces = Vec::new();
+        for polygon in polygons {
+            let (points, rings) = polygon.rings();
+            let count = points.len();
+            let offset = vertices.len();
+
+            // the bottom vertices followed by the top vertices
+            vertices.extend(points.iter().map(|p| Point3::new(p.x, p.y, 0.0)));
+            vertices.extend(points.iter().map(|p| Point3::new(p.x, p.y, height)));
+
+            // the caps, wound to face down on the bottom and up on the top
+            for (a, b, c) in triangulator.trianglate_2d(&rings[0], &rings[1..], &points) {
+                let (a, b, c) = if (points[b] - points[a]).perp(&(points[c] - points[a])) > 0.0 {
+                    (a, b, c)
+                } else {
+                    (a, c, b)
+                };
+                faces.push((offset + a, offset + c, offset + b));
+                faces.push((offset + count + a, offset + count + b, offset + count + c));
+            }
+
+            // the walls between consecutive points of each ring
+            for ring in rings.iter() {
+                for (i, a) in ring.iter().enumerate() {
+                    let b = ring[(i + 1) % ring.len()];
+                    let (a, b) = (offset + a, offset + b);
+                    faces.push((a, b, b + count));
+                    faces.push((a, b + count, a + count));
+                }
+            }
+        }
+
+        // extruding downwards turns the mesh inside out
+        if height < 0.0 {
+            for face in faces.iter_mut() {
+                *face = (face.0, face.2, face.1);
+            }
+        }
+        Ok(Trimesh::new(vertices, faces, None, None)?)
+    }
+
+    /// Revolve every entity of the path around the Z axis, where the X
+    /// coordinate of the path is the radius and Y is the height. A
+    /// profile running from bottom to top at a positive radius will
+    /// produce faces with normals pointing away from the axis.
+    ///
+    /// Parameters
+    /// ------------
+    /// angle
+    ///   The angle to revolve in radians, where a full turn
+    ///   connects the last section back to the first.
+    /// sections
+    ///   The number of steps to divide the revolution into.
+    /// resolution
+    ///   The number of points to sample curves with.
+    ///
+    /// Returns
+    /// ------------
+    /// revolved
+    ///   The surface of revolution or an error for invalid parameters.
+    pub fn revolve(&self, angle: f64, sections: usize, resolution: usize) -> Result<Trimesh> {
+        if sections == 0 || angle == 0.0 || !angle.is_finite() {
+            return Err(anyhow!("Revolve needs a non-zero angle and sections"));
+        }
+        let closed = angle.abs() >= TAU - 1e-12;
+        // a full turn reuses the first section instead of the last
+        let rings = if closed { sections } else { sections + 1 };
+
+        let mut vertices = Vec::new();
+        let mut faces = Vec::new();
+        for profile in self.discrete(resolution) {
+            if profile.len() < 2 {
+                continue;
+            }
+            let offset = vertices.len();
+            for s in 0..rings {
+                let theta = angle * s as f64 / sections as f64;
+                let (sin, cos) = theta.sin_cos();
+                vertices.extend(
+                    profile
+                        .iter()
+                        .map(|p| Point3::new(p.x * cos, p.x * sin, p.y)),
+                );
+            }
+            let index = |s: usize, k: usize| offset + (s % rings) * profile.len() + k;
+            for s in 0..sections {
+                for k in 0..profile.len() - 1 {
+                    let (a, b) = (index(s, k), index(s, k + 1));
+                    let (c, d) = (index(s + 1, k + 1), index(s + 1, k));
+                    faces.push((a, d, c));
+                    faces.push((a, c, b));
+                }
+            }
+        }
+
+        // revolving clockwise turns the mesh inside out
+        if angle < 0.0 {
+            for face in faces.iter_mut() {
+                *face = (face.0, face.2, face.1);
+            }
+        }
+        Ok(Trimesh::new(vertices, faces, None, None)?)
+    }
+}
+
+/// Group closed loops into polygons with holes, where a loop inside an
+/// odd number of other loops is a hole in the smallest loop around it
+/// and a loop inside an even number is the exterior of a new polygon.
+///
+/// Parameters
+/// ------------
+/// loops
+///   Closed loops without the first point repeated in any winding.
+///
+/// Returns
+/// ------------
+/// polygons
+///   The indices of the loops of every polygon with the exterior
+///   first, in order of the area of their exterior from largest.
+pub(crate) fn nest_loops(loops: &[Vec<Point2<f64>>]) -> Vec<Vec<usize>> {
+    // check the largest loops first so holes find their exterior
+    let mut order: Vec<usize> = (0..loops.len()).collect();
+    let area: Vec<f64> = loops.iter().map(|l| signed_area(l).abs()).collect();
+    order.sort_by(|a, b| area[*b].total_cmp(&area[*a]));
+
+    let mut polygons: Vec<Vec<usize>> = Vec::new();
+    // the polygon of every checked loop and if it is a hole
+    let mut placed: Vec<(usize, bool)> = Vec::with_capacity(loops.len());
+    for (index, current) in order.iter().enumerate() {
+        // the smallest loop around this one is the last one found
+        let parent = (0..index)
+            .rev()
+            .find(|other| contains(&loops[order[*other]], &loops[*current][0]))
+            .map(|other| placed[other]);
+        match parent {
+            Some((polygon, false)) => {
+                polygons[polygon].push(*current);
+                placed.push((polygon, true));
+            }
+            _ => {
+                polygons.push(vec![*current]);
+                placed.push((polygons.len() - 1, false));
+            }
+        }
+    }
+    polygons
+}
+
+/// The signed area of a closed polygon which is positive
+/// if it is wound counter-clockwise.
+fn signed_area(points: &[Point2<f64>]) -> f64 {
+    let mut area = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area / 2.0
+}
+
+/// Is a point inside of a closed polygon using the even-odd rule.
+fn contains(polygon: &[Point2<f64>], point: &Point2<f64>) -> bool {
+    let mut inside = false;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Create a rectangle path (no rounded corners).
+pub fn rectangle(width: f64, height: f64) -> Path {
+    let w = width / 2.0;
+    let h = height / 2.0;
+
+    let vertices = vec![
+        Point3::new(-w, -h, 0.0),
+        Point3::new(w, -h, 0.0),
+        Point3::new(w, h, 0.0),
+        Point3::new(-w, h, 0.0),
+    ];
+
+    let entities = vec![Curve::Line {
+        points: vec![0, 1, 2, 3, 0],
+    }];
+
+    Path::new(vertices, entities)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use approx::assert_relative_eq;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_rectangle() {
+        let path = rectangle(10.0, 5.0);
+        assert_eq!(path.vertices.len(), 4);
+        assert_eq!(path.entities.len(), 1);
+
+        // Check vertices
+        assert_relative_eq!(path.vertices[0], Point3::new(-5.0, -2.5, 0.0));
+        assert_relative_eq!(path.vertices[1], Point3::new(5.0, -2.5, 0.0));
+        assert_relative_eq!(path.vertices[2], Point3::new(5.0, 2.5, 0.0));
+        assert_relative_eq!(path.vertices[3], Point3::new(-5.0, 2.5, 0.0));
+
+        // Check curves
+        if let Curve::Line { points } = &path.entities[0] {
+            assert_eq!(*points, vec![0, 1, 2, 3, 0]);
+        } else {
+            panic!("Expected Line curve");
+        }
+
+        assert_eq!(path.entities.len(), 1);
+        assert_relative_eq!(path.entities[0].length(&path.vertices), 30.0);
+
+        let (lower, upper) = path.bounds().unwrap();
+        assert_relative_eq!(lower, Point3::new(-5.0, -2.5, 0.0));
+        assert_relative_eq!(upper, Point3::new(5.0, 2.5, 0.0));
+        assert!(Path::new(vec![], vec![]).bounds().is_none());
+    }
+
+    #[test]
+    fn test_extrude() {
+        let path = rectangle(2.0, 4.0);
+        assert_eq!(path.discrete(8)[0].len(), 5);
+        assert_relative_eq!(path.length(), 12.0);
+
+        let mesh = path.extrude(3.0, 8).unwrap();
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.faces.len(), 12);
+        assert_relative_eq!(mesh.area(), 2.0 * 8.0 + 3.0 * 12.0, epsilon = 1e-10);
+        let (lower, upper) = mesh.bounds().unwrap();
+        assert_relative_eq!(lower, Point3::new(-1.0, -2.0, 0.0));
+        assert_relative_eq!(upper, Point3::new(1.0, 2.0, 3.0));
+
+        // every face should point out of the box
+        let center = Point3::new(0.0, 0.0, 1.5);
+        assert!(mesh.winding_number(&[center])[0] > 0.99);
+        let downward = path.extrude(-3.0, 8).unwrap();
+        assert!(downward.winding_number(&[Point3::new(0.0, 0.0, -1.5)])[0] > 0.99);
+
+        assert!(path.extrude(0.0, 8).is_err());
+        let open = Path::new(
+            path.vertices.clone(),
+            vec![Curve::Line {
+                points: vec![0, 1, 2],
+            }],
+        );
+        assert!(open.extrude(1.0, 8).is_err());
+    }
+
+    #[test]
+    fn test_revolve() {
+        // a vertical line at radius 1 revolves into an open cylinder
+        let path = Path::new(
+            vec![Point3::new(1.0, 0.0, 0.0), Point3::new(1.0, 2.0, 0.0)],
+            vec![Curve::Line { points: vec![0, 1] }],
+        );
+        let mesh = path.revolve(TAU, 64, 8).unwrap();
+        assert_eq!(mesh.vertices.len(), 128);
+        assert_eq!(mesh.faces.len(), 128);
+        // the area of the inscribed 64-gon prism
+        let expected = 2.0 * 64.0 * 2.0 * (std::f64::consts::PI / 64.0).sin();
+        assert_relative_eq!(mesh.area(), expected, epsilon = 1e-10);
+        // the normals should point away from the axis
+        for (face, normal) in mesh.faces.iter().zip(mesh.face_normals().iter()) {
+            let v = mesh.vertices[face.0];
+            assert!(normal.dot(&Vector3::new(v.x, v.y, 0.0)) > 0.0);
+        }
+
+        // a partial revolution has an extra section
+        let half = path.revolve(TAU / 2.0, 32, 8).unwrap();
+        assert_eq!(half.vertices.len(), 66);
+        assert_eq!(half.faces.len(), 64);
+
+        assert!(path.revolve(TAU, 0, 8).is_err());
+    }
+
+    #[test]
+    fn test_to_polygons() {
+        // a square of separate lines around a circle around a smaller
+        // square, next to a D shape of a clockwise arc and a line
+        let vertices = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(10.0, 0.0, 0.0),
+            Point3::new(10.0, 10.0, 0.0),
+            Point3::new(0.0, 10.0, 0.0),
+            Point3::new(5.0, 5.0, 0.0),
+            Point3::new(9.0, 5.0, 0.0),
+            Point3::new(5.0, 9.0, 0.0),
+            Point3::new(20.0, 0.0, 0.0),
+            Point3::new(20.0, 10.0, 0.0),
+            Point3::new(20.0, 5.0, 0.0),
+        ];
+        let mut entities: Vec<Curve> = [(0, 1), (2, 1), (2, 3), (3, 0)]
+            .iter()
+            .map(|(a, b)| Curve::Line {
+                points: vec![*a, *b],
+            })
+            .collect();
+        entities.push(Curve::from_kind("circle", vec![5, 6, 4]).unwrap());
+        entities.push(Curve::from_kind("arc_cw", vec![8, 7, 9]).unwrap());
+        entities.push(Curve::Line { points: vec![7, 8] });
+        // an open line which isn't part of any loop
+        entities.push(Curve::Line { points: vec![0, 4] });
+        let mut path = Path::new(vertices, entities);
+        let island = rectangle(2.0, 2.0);
+        let offset = path.vertices.len();
+        path.vertices.extend(
+            island
+                .vertices
+                .iter()
+                .map(|v| v + Vector3::new(5.0, 5.0, 0.0)),
+        );
+        path.entities.push(Curve::Line {
+            points: vec![offset, offset + 1, offset + 2, offset + 3, offset],
+        });
+
+        // the arc is a half circle and the circle a full one
+        assert_relative_eq!(path.entities[5].length(&path.vertices), 5.0 * PI);
+        assert_relative_eq!(path.entities[4].length(&path.vertices), 8.0 * PI);
+        let arc = path.entities[5].discrete(&path.vertices, 5);
+        assert_relative_eq!(arc[2], Point3::new(25.0, 5.0, 0.0), epsilon = 1e-12);
+
+        let polygons = path.to_polygons(256);
+        assert_eq!(polygons.len(), 3);
+        assert_eq!(polygons[0].interiors.len(), 1);
+        let circle = PI * 16.0;
+        assert_relative_eq!(polygons[0].area(), 100.0 - circle, epsilon = 0.01);
+        assert_relative_eq!(polygons[1].area(), 12.5 * PI, epsilon = 0.01);
+        // the square inside the hole is a polygon of its own
+        assert_relative_eq!(polygons[2].area(), 4.0, epsilon = 1e-10);
+        let (points, rings) = polygons[0].rings();
+        assert_eq!(rings.len(), 2);
+        assert_eq!(rings[0].len(), 4);
+        assert_eq!(points.len(), 4 + 255);
+
+        // a loop joined from separate entities can be extruded
+        let square = Path::new(
+            path.vertices[..4].to_vec(),
+            [(0, 1), (2, 1), (2, 3), (3, 0)]
+                .iter()
+                .map(|(a, b)| Curve::Line {
+                    points: vec![*a, *b],
+                })
+                .collect(),
+        );
+        let mesh = square.extrude(1.0, 64).unwrap();
+        assert_eq!(mesh.faces.len(), 12);
+        assert_relative_eq!(mesh.volume(), 100.0, epsilon = 1e-10);
+    }
+
+    #[test]
+    fn test_export() {
+        let mut path = rectangle(4.0, 2.0);
+        path.vertices.extend([
+            Point3::new(10.0, 0.0, 0.0),
+            Point3::new(11.0, 0.0, 0.0),
+            Point3::new(10.0, 1.0, 0.0),
+            Point3::new(12.0, 0.0, 0.0),
+            Point3::new(13.0, 3.0, 0.0),
+            Point3::new(14.0, -3.0, 0.0),
+            Point3::new(15.0, 0.0, 0.0),
+        ]);
+        path.entities.extend([
+            Curve::from_kind("circle", vec![5, 6, 4]).unwrap(),
+            Curve::from_kind("arc", vec![5, 6, 4]).unwrap(),
+            Curve::from_kind("arc_cw", vec![5, 6, 4]).unwrap(),
+            Curve::Bezier {
+                points: vec![7, 8, 9, 10],
+            },
+        ]);
+        let lengths = |path: &Path| -> Vec<f64> {
+            path.entities
+                .iter()
+                .filter(|e| e.kind() != "bezier")
+                .map(|e| e.length(&path.vertices))
+                .collect()
+        };
+        // every curve of the path survives a round trip through either format
+        for format in ["dxf", ".SVG"] {
+            let format = PathFormat::from_string(format).unwrap();
+            let text = path.export(format.clone());
+            let loaded = match format {
+                PathFormat::DXF => load_dxf(text.as_bytes()).unwrap(),
+                PathFormat::SVG => load_svg(text.as_bytes()).unwrap(),
+            };
+            let mut expected = lengths(&path);
+            let mut found = lengths(&loaded);
+            if format == PathFormat::SVG {
+                // a circle is written as two halves
+                expected.insert(1, expected[1] / 2.0);
+                expected[2] /= 2.0;
+            }
+            assert_eq!(found.len(), expected.len());
+            for (a, b) in found.iter_mut().zip(expected.iter()) {
+                assert_relative_eq!(*a, *b, epsilon = 1e-10);
+            }
+            let discrete = |path: &Path| {
+                let bezier = path.entities.last().unwrap();
+                assert_eq!(bezier.kind(), "bezier");
+                bezier.discrete(&path.vertices, 16)
+            };
+            for (a, b) in discrete(&path).iter().zip(discrete(&loaded).iter()) {
+                assert_relative_eq!(a, b, epsilon = 1e-10);
+            }
+            let (lower, upper) = path.bounds().unwrap();
+            let bounds = loaded.bounds().unwrap();
+            assert_relative_eq!(bounds.0, lower, epsilon = 1e-10);
+            assert_relative_eq!(bounds.1, upper, epsilon = 1e-10);
+        }
+        assert!(PathFormat::from_string("dwg").is_err());
+    }
+
+    #[test]
+    fn test_curve_kind() {
+        for (kind, indices) in [
+            ("line", vec![0, 1, 2]),
+            ("bezier", vec![0, 1, 2, 3]),
+            ("circle", vec![0, 1, 2]),
+            ("arc", vec![0, 1, 2]),
+            ("arc_cw", vec![2, 1, 0]),
+        ] {
+            let curve = Curve::from_kind(kind, indices.clone()).unwrap();
+            assert_eq!(curve.kind(), kind);
+            assert_eq!(curve.indices(), indices);
+        }
+        assert!(Curve::from_kind("arc", vec![0, 1]).is_err());
+        assert!(Curve::from_kind("spline", vec![0, 1]).is_err());
+    }
+}
@@ -0,0 +1,503 @@
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector3};
+use roxmltree::Document;
+
+use std::f64::consts::TAU;
+
+use crate::exchange::ExportOptions;
+use crate::path::{Curve, Path};
+
+/// The number of points to sample beziers above cubic with on export.
+const BEZIER_RESOLUTION: usize = 32;
+
+/// A command letter or a number in the `d` attribute of an SVG path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Command(char),
+    Number(f64),
+}
+
+/// Split path data into commands and numbers, where numbers may be
+/// separated by whitespace, commas, a sign or a second decimal point.
+fn tokenize(data: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = data.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() || c == ',' {
+            i += 1;
+        } else if c.is_ascii_alphabetic() {
+            tokens.push(Token::Command(c));
+            i += 1;
+        } else {
+            let start = i;
+            let (mut dot, mut exponent) = (false, false);
+            if c == '+' || c == '-' {
+                i += 1;
+            }
+            while i < chars.len() {
+                let c = chars[i];
+                if c.is_ascii_digit() {
+                    i += 1;
+                } else if c == '.' && !dot && !exponent {
+                    dot = true;
+                    i += 1;
+                } else if (c == 'e' || c == 'E') && !exponent {
+                    exponent = true;
+                    i += 1;
+                    if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                        i += 1;
+                    }
+                } else {
+                    break;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            tokens
+                .push(Token::Number(text.parse().map_err(|_| {
+                    anyhow!("Invalid number in SVG path: `{text}`")
+                })?));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Builds the curves of path data, keeping the straight segments
+/// since the last move or curve together as one line.
+struct Builder {
+    vertices: Vec<Point3<f64>>,
+    curves: Vec<Curve>,
+    line: Vec<usize>,
+    // the index of the current point and the start of the subpath
+    current: Option<usize>,
+    start: Option<usize>,
+}
+
+impl Builder {
+    fn add(&mut self, x: f64, y: f64) -> usize {
+        self.vertices.push(Point3::new(x, y, 0.0));
+        self.vertices.len() - 1
+    }
+
+    fn position(&self) -> Point3<f64> {
+        self.current
+            .map(|i| self.vertices[i])
+            .unwrap_or_else(Point3::origin)
+    }
+
+    /// End the current line so a curve or move can follow it.
+    fn flush(&mut self) {
+        if self.line.len() > 1 {
+            self.curves.push(Curve::Line {
+                points: std::mem::take(&mut self.line),
+            });
+        }
+        self.line.clear();
+    }
+
+    fn move_to(&mut self, x: f64, y: f64) {
+        self.flush();
+        let index = self.add(x, y);
+        self.current = Some(index);
+        self.start = Some(index);
+    }
+
+    fn line_to(&mut self, index: usize) {
+        if self.line.is_empty() {
+            let from = match self.current {
+                Some(current) => current,
+                None => self.add(0.0, 0.0),
+            };
+            self.line.push(from);
+        }
+        self.line.push(index);
+        self.current = Some(index);
+    }
+
+    fn cubic_to(&mut self, control: [(f64, f64); 3]) {
+        self.flush();
+        let from = match self.current {
+            Some(current) => current,
+            None => self.add(0.0, 0.0),
+        };
+        let mut points = vec![from];
+        points.extend(control.map(|(x, y)| self.add(x, y)));
+        self.current = points.last().copied();
+        self.curves.push(Curve::Bezier { points });
+    }
+
+    /// Add a circular arc from the current point with the parameters of
+    /// an SVG arc command, finding its center as in the appendix of the
+    /// SVG specification on elliptical arcs.
+    fn arc_to(&mut self, rx: f64, ry: f64, large: bool, sweep: bool, x: f64, y: f64) -> Result<()> {
+        let (rx, ry) = (rx.abs(), ry.abs());
+        if (rx - ry).abs() > 1e-9 * rx.max(ry) {
+            return Err(anyhow!(
+                "Elliptical SVG arcs aren't supported: {rx} by {ry}"
+            ));
+        }
+        let from = self.position();
+        let end = self.add(x, y);
+        let half = (from - self.vertices[end]) / 2.0;
+        let distance = half.norm();
+        // a zero radius or no distance is a straight line
+        if rx == 0.0 || distance == 0.0 {
+            self.line_to(end);
+            return Ok(());
+        }
+        // a radius too small to reach is scaled up to exactly reach
+        let radius = rx.max(distance);
+        let offset = ((radius * radius - distance * distance).max(0.0)).sqrt() / distance;
+        let sign = if large == sweep { -1.0 } else { 1.0 };
+        let middle = nalgebra::center(&from, &self.vertices[end]);
+        let center = middle + Vector3::new(half.y, -half.x, 0.0) * (sign * offset);
+
+        self.flush();
+        let start = match self.current {
+            Some(current) => current,
+            None => self.add(0.0, 0.0),
+        };
+        let center = self.add(center.x, center.y);
+        self.curves.push(Curve::Circle {
+            start,
+            end,
+            center,
+            closed: false,
+            // the positive angle direction with the coordinates as written
+            is_ccw: sweep,
+        });
+        self.current = Some(end);
+        Ok(())
+    }
+
+    fn close(&mut self) {
+        if let (Some(current), Some(start)) = (self.current, self.start) {
+            if self.vertices[current] != self.vertices[start] {
+                self.line_to(start);
+            } else if let Some(last) = self.line.last_mut() {
+                // end exactly on the start so the loop is closed
+                *last = start;
+            }
+        }
+        self.flush();
+        self.current = self.start;
+    }
+}
+
+/// Add the moves, lines and cubic beziers of the `d` attribute of an
+/// SVG path element to a builder.
+fn parse_data(data: &str, builder: &mut Builder) -> Result<()> {
+    let tokens = tokenize(data)?;
+    let mut i = 0;
+    let mut command = None;
+    // the second control point of the last cubic for smooth curves
+    let mut reflect: Option<Point3<f64>> = None;
+    while i < tokens.len() {
+        let name = match tokens[i] {
+            Token::Command(c) => {
+                i += 1;
+                c
+            }
+            // numbers after a command repeat it, with a move becoming a line
+            Token::Number(_) => match command {
+                Some('M') => 'L',
+                Some('m') => 'l',
+                Some(c) => c,
+                None => return Err(anyhow!("SVG path data must start with a command")),
+            },
+        };
+        command = Some(name);
+        let count = match name.to_ascii_uppercase() {
+            'M' | 'L' => 2,
+            'H' | 'V' => 1,
+            'C' => 6,
+            'A' => 7,
+            'S' => 4,
+            'Z' => 0,
+            other => return Err(anyhow!("Unsupported SVG path command: `{other}`")),
+        };
+        let mut values = [0.0; 7];
+        for value in values.iter_mut().take(count) {
+            match tokens.get(i) {
+                Some(Token::Number(n)) => *value = *n,
+                _ => return Err(anyhow!("SVG path command `{name}` needs {count} numbers")),
+            }
+            i += 1;
+        }
+
+        let origin = if name.is_ascii_lowercase() {
+            builder.position().coords
+        } else {
+            Vector3::zeros()
+        };
+        let point = |x: f64, y: f64| (x + origin.x, y + origin.y);
+        let mut next = None;
+        match name.to_ascii_uppercase() {
+            'M' => {
+                let (x, y) = point(values[0], values[1]);
+                builder.move_to(x, y);
+            }
+            'L' => {
+                let (x, y) = point(values[0], values[1]);
+                let index = builder.add(x, y);
+                builder.line_to(index);
+            }
+            'H' => {
+                let y = builder.position().y;
+                let index = builder.add(values[0] + origin.x, y);
+                builder.line_to(index);
+            }
+            'V' => {
+                let x = builder.position().x;
+                let index = builder.add(x, values[0] + origin.y);
+                builder.line_to(index);
+            }
+            'C' | 'S' => {
+                let (first, rest) = if name.eq_ignore_ascii_case(&'C') {
+                    (point(values[0], values[1]), 2)
+                } else {
+                    // reflect the last control point through the current one
+                    let current = builder.position();
+                    let first = reflect.map(|r| current + (current - r)).unwrap_or(current);
+                    ((first.x, first.y), 0)
+                };
+                let second = point(values[rest], values[rest + 1]);
+                let end = point(values[rest + 2], values[rest + 3]);
+                builder.cubic_to([first, second, end]);
+                next = Some(Point3::new(second.0, second.1, 0.0));
+            }
+            'A' => {
+                let (x, y) = point(values[5], values[6]);
+                builder.arc_to(
+                    values[0],
+                    values[1],
+                    values[3] != 0.0,
+                    values[4] != 0.0,
+                    x,
+                    y,
+                )?;
+            }
+            _ => builder.close(),
+        }
+        reflect = next;
+    }
+    builder.flush();
+    Ok(())
+}
+
+/// Load the `path` elements of an SVG document into a path, where
+/// the commands of their `d` attributes become lines, circular arcs and
+/// cubic bezier curves in the XY plane. Coordinates are kept as they are written
+/// so Y points down, and `transform` attributes aren't applied.
+///
+/// Parameters
+/// ------------
+/// bytes
+///   The contents of the SVG file.
+///
+/// Returns
+/// ------------
+/// path
+///   The loaded path, or an error if the document isn't valid or uses
+///   a path command other than a move, line, cubic, circular arc or close.
+pub fn load_svg(bytes: &[u8]) -> Result<Path> {
+    let text = std::str::from_utf8(bytes).map_err(|_| anyhow!("SVG must be UTF-8"))?;
+    let doc = Document::parse(text)?;
+    let mut builder = Builder {
+        vertices: Vec::new(),
+        curves: Vec::new(),
+        line: Vec::new(),
+        current: None,
+        start: None,
+    };
+    for node in doc.descendants().filter(|n| n.has_tag_name("path")) {
+        if let Some(data) = node.attribute("d") {
+            builder.current = None;
+            builder.start = None;
+            parse_data(data, &mut builder)?;
+        }
+    }
+    Ok(Path::new(builder.vertices, builder.curves))
+}
+
+/// Write the coordinates of a point for path data.
+fn write_point(out: &mut String, point: &Point3<f64>) {
+    let options = ExportOptions::default();
+    options.write_float(point.x, out);
+    out.push(' ');
+    options.write_float(point.y, out);
+}
+
+/// Write a path to the text of an SVG document with a `path` element
+/// for every entity. Lines close with `Z` if they end where they start,
+/// circles and arcs become arc commands with a full circle as two halves,
+/// beziers up to cubic become a cubic command and higher degree beziers
+/// are sampled into lines.
+pub fn export(path: &Path) -> String {
+    let vertices = &path.vertices;
+    let mut out = String::from(r#"<svg xmlns="http://www.w3.org/2000/svg""#);
+    if let Some((lower, upper)) = path.bounds() {
+        let options = ExportOptions::default();
+        out.push_str(r#" viewBox=""#);
+        for value in [lower.x, lower.y, upper.x - lower.x, upper.y - lower.y] {
+            options.write_float(value, &mut out);
+            out.push(' ');
+        }
+        out.pop();
+        out.push('"');
+    }
+    out.push_str(">\n");
+
+    for entity in path.entities.iter() {
+        let mut data = String::from("M ");
+        match entity {
+            Curve::Line { points } => {
+                if points.len() < 2 {
+                    continue;
+                }
+                let closed = points.len() > 3 && points.first() == points.last();
+                let end = if closed {
+                    points.len() - 1
+                } else {
+                    points.len()
+                };
+                write_point(&mut data, &vertices[points[0]]);
+                for index in points[1..end].iter() {
+                    data.push_str(" L ");
+                    write_point(&mut data, &vertices[*index]);
+                }
+                if closed {
+                    data.push_str(" Z");
+                }
+            }
+            Curve::Circle { start, center, .. } => {
+                let radius = (vertices[*start] - vertices[*center]).norm();
+                let (from, sweep) = entity.sweep(vertices).unwrap_or_default();
+                write_point(&mut data, &vertices[*start]);
+                // a single arc can't end where it starts so split full turns
+                let pieces = if sweep.abs() >= TAU { 2 } else { 1 };
+                for piece in 1..=pieces {
+                    let angle = from + sweep * piece as f64 / pieces as f64;
+                    let (sin, cos) = angle.sin_cos();
+                    let end = match (piece == pieces, pieces) {
+                        (true, 1) => vertices[entity.indices()[1]],
+                        (true, _) => vertices[*start],
+                        _ => vertices[*center] + Vector3::new(cos, sin, 0.0) * radius,
+                    };
+                    let large = (sweep.abs() / pieces as f64) > std::f64::consts::PI;
+                    data.push_str(" A ");
+                    write_point(&mut data, &Point3::new(radius, radius, 0.0));
+                    data.push_str(&format!(" 0 {} {} ", large as u8, (sweep > 0.0) as u8));
+                    write_point(&mut data, &end);
+                }
+            }
+            Curve::Bezier { points } => {
+                if points.len() < 2 {
+                    continue;
+                }
+                let control: Vec<Point3<f64>> = points.iter().map(|i| vertices[*i]).collect();
+                write_point(&mut data, &control[0]);
+                // raise the degree of lines and quadratics to a cubic
+                let cubic = match control.len() {
+                    2 => Some([
+                        control[0] + (control[1] - control[0]) / 3.0,
+                        control[1] + (control[0] - control[1]) / 3.0,
+                        control[1],
+                    ]),
+                    3 => Some([
+                        control[0] + (control[1] - control[0]) * (2.0 / 3.0),
+                        control[2] + (control[1] - control[2]) * (2.0 / 3.0),
+                        control[2],
+                    ]),
+                    4 => Some([control[1], control[2], control[3]]),
+                    _ => None,
+                };
+                match cubic {
+                    Some(cubic) => {
+                        data.push_str(" C");
+                        for point in cubic.iter() {
+                            data.push(' ');
+                            write_point(&mut data, point);
+                        }
+                    }
+                    None => {
+                        for point in entity.discrete(vertices, BEZIER_RESOLUTION)[1..].iter() {
+                            data.push_str(" L ");
+                            write_point(&mut data, point);
+                        }
+                    }
+                }
+            }
+        }
+        out.push_str(&format!(
+            "  <path d=\"{data}\" fill=\"none\" stroke=\"black\"/>\n"
+        ));
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_load_svg() {
+        assert_eq!(
+            tokenize("M1-2.5.5e1,3").unwrap(),
+            vec![
+                Token::Command('M'),
+                Token::Number(1.0),
+                Token::Number(-2.5),
+                Token::Number(5.0),
+                Token::Number(3.0)
+            ]
+        );
+
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg">
+              <g><path d="M 0 0 h 4 v 3 L 0 3 z"/></g>
+              <path d="M10,0 C10,5 20,5 20,0 s10,-5 10,0 m 5 0 l 1 1 2 2"/>
+            </svg>"#;
+        let path = load_svg(svg.as_bytes()).unwrap();
+        let kinds: Vec<&str> = path.entities.iter().map(|e| e.kind()).collect();
+        assert_eq!(kinds, vec!["line", "bezier", "bezier", "line"]);
+
+        // the rectangle is closed back onto its first vertex
+        let rectangle = path.entities[0].indices();
+        assert_eq!(rectangle.len(), 5);
+        assert_eq!(rectangle.first(), rectangle.last());
+        assert_relative_eq!(path.entities[0].length(&path.vertices), 14.0);
+
+        // the smooth curve reflects the last control point
+        let smooth = path.entities[2].indices();
+        assert_eq!(smooth[0], path.entities[1].indices()[3]);
+        assert_relative_eq!(path.vertices[smooth[1]], Point3::new(20.0, -5.0, 0.0));
+        assert_relative_eq!(path.vertices[smooth[3]], Point3::new(30.0, 0.0, 0.0));
+
+        // a relative move and repeated relative lines
+        let line = path.entities[3].indices();
+        assert_eq!(line.len(), 3);
+        assert_relative_eq!(path.vertices[line[2]], Point3::new(38.0, 3.0, 0.0));
+
+        // curves are measured along their length rather than their control points
+        let curves = load_svg(
+            br#"<svg><path d="M 0 0 C 1 0 2 0 3 0 M 1 0 C 1 0.5523 0.5523 1 0 1"/></svg>"#,
+        )
+        .unwrap();
+        assert_relative_eq!(curves.entities[0].length(&curves.vertices), 3.0);
+        assert_relative_eq!(
+            curves.entities[1].length(&curves.vertices),
+            std::f64::consts::FRAC_PI_2,
+            epsilon = 1e-3
+        );
+        assert!(path.length() > 14.0 + 20.0);
+
+        assert!(load_svg(br#"<svg><path d="M 0 0 A 1 2 0 0 1 2 2"/></svg>"#).is_err());
+        assert!(load_svg(br#"<svg><path d="M 0 0 Q 1 1 2 2"/></svg>"#).is_err());
+        assert!(load_svg(br#"<svg><path d="M 0"/></svg>"#).is_err());
+        assert!(load_svg(b"<svg>").is_err());
+    }
+}
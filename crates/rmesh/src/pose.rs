@@ -0,0 +1,225 @@
+use std::f64::consts::PI;
+
+use anyhow::{Result, anyhow};
+use nalgebra::{Matrix4, Point2, Rotation3, Translation3, Vector3};
+
+use crate::mesh::Trimesh;
+
+/// A stable orientation of a mesh resting on a flat surface.
+#[derive(Debug, Clone)]
+pub struct RestPose {
+    // places the mesh on the XY plane with its center of
+    // mass above the origin and the resting face on Z=0
+    pub transform: Matrix4<f64>,
+    // the outward normal of the resting face in the original frame
+    pub normal: Vector3<f64>,
+    // the distance from the center of mass projected onto the resting
+    // face to the nearest edge of the polygon the mesh rests on
+    pub margin: f64,
+    // the height of the center of mass above the resting face
+    pub height: f64,
+}
+
+impl RestPose {
+    /// The angle the mesh has to be tilted by before it tips over
+    /// the nearest edge, which compares poses independent of scale.
+    pub fn tipping_angle(&self) -> f64 {
+        self.margin.atan2(self.height)
+    }
+}
+
+/// The 2D convex hull of points with a monotone chain, counterclockwise.
+pub(crate) fn convex_hull_2d(mut points: Vec<Point2<f64>>) -> Vec<Point2<f64>> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: &Point2<f64>, a: &Point2<f64>, b: &Point2<f64>| (a - o).perp(&(b - o));
+    let mut hull: Vec<Point2<f64>> = Vec::with_capacity(points.len() * 2);
+    for pass in [points.clone(), points.into_iter().rev().collect()] {
+        let start = hull.len();
+        for p in pass {
+            while hull.len() >= start + 2
+                && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], &p) <= 0.0
+            {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // the last point of each chain is the first of the next
+        hull.pop();
+    }
+    hull
+}
+
+impl Trimesh {
+    /// Find the orientations a mesh can rest in on a flat surface without
+    /// tipping over. A mesh rests on a plane touching it from outside and
+    /// is stable if its center of mass is above the polygon it touches
+    /// the plane with.
+    ///
+    /// Parameters
+    /// ------------
+    /// candidates_from_hull
+    ///   Take the candidate planes from the faces of the convex hull, which
+    ///   finds every pose. Otherwise only the planar regions of the mesh
+    ///   itself are tried, which is cheaper and enough for convex meshes
+    ///   or to find poses where the mesh rests on a flat face.
+    ///
+    /// Returns
+    /// ------------
+    /// poses
+    ///   The stable poses with the most stable first by tipping angle, or
+    ///   an error if the mesh doesn't enclose a volume.
+    pub fn rest_pose(&self, candidates_from_hull: bool) -> Result<Vec<RestPose>> {
+        let center = self
+            .center_mass()
+            .ok_or(anyhow!("Mesh must enclose a volume to rest"))?;
+        let (lower, upper) = self.bounds().ok_or(anyhow!("Mesh is empty"))?;
+        let tolerance = (upper - lower).norm() * 1e-8;
+
+        let hull;
+        let source = if candidates_from_hull {
+            hull = self.convex_hull()?;
+            &hull
+        } else {
+            self
+        };
+
+        // merge the faces into distinct planes
+        let mut planes: Vec<(Vector3<f64>, f64)> = Vec::new();
+        for (face, normal) in source.faces.iter().zip(source.face_normals()) {
+            let Some(normal) = normal.try_normalize(f64::EPSILON) else {
+                continue;
+            };
+            let offset = normal.dot(&source.vertices[face.0].coords);
+            if !planes
+                .iter()
+                .any(|(n, o)| n.dot(&normal) > 1.0 - 1e-9 && (o - offset).abs() < tolerance)
+            {
+                planes.push((normal, offset));
+            }
+        }
+
+        let mut poses = Vec::new();
+        for (normal, offset) in planes {
+            // the plane has to touch the mesh without cutting into it
+            let distances: Vec<f64> = self
+                .vertices
+                .iter()
+                .map(|v| normal.dot(&v.coords) - offset)
+                .collect();
+            if distances.iter().any(|d| *d > tolerance) {
+                continue;
+            }
+
+            // put the mesh on the XY plane resting on this face
+            let rotation = Rotation3::rotation_between(&normal, &-Vector3::z())
+                .unwrap_or_else(|| Rotation3::from_axis_angle(&Vector3::x_axis(), PI));
+            let rotated = rotation * center;
+            let floor = -offset;
+            let height = rotated.z - floor;
+            let support = convex_hull_2d(
+                self.vertices
+                    .iter()
+                    .zip(distances.iter())
+                    .filter(|(_, d)| d.abs() <= tolerance)
+                    .map(|(v, _)| (rotation * v).xy())
+                    .collect(),
+            );
+            if support.len() < 3 {
+                continue;
+            }
+
+            // the signed distance to the nearest edge, positive inside
+            let projected = rotated.xy();
+            let margin = (0..support.len())
+                .map(|i| {
+                    let (a, b) = (support[i], support[(i + 1) % support.len()]);
+                    (b - a).perp(&(projected - a)) / (b - a).norm()
+                })
+                .fold(f64::INFINITY, f64::min);
+            if margin <= tolerance {
+                continue;
+            }
+
+            let translation = Translation3::new(-rotated.x, -rotated.y, -floor);
+            poses.push(RestPose {
+                transform: translation.to_homogeneous() * rotation.to_homogeneous(),
+                normal,
+                margin,
+                height,
+            });
+        }
+        poses.sort_by(|a, b| b.tipping_angle().total_cmp(&a.tipping_angle()));
+        Ok(poses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+    use nalgebra::Point3;
+
+    #[test]
+    fn test_rest_pose() {
+        let mesh = create_box(&[1.0, 2.0, 3.0])
+            .apply_translation(&Vector3::new(3.0, 2.0, 1.0))
+            .unwrap();
+        for from_hull in [true, false] {
+            let poses = mesh.rest_pose(from_hull).unwrap();
+            // a box rests on any of its sides
+            assert_eq!(poses.len(), 6);
+            // most stably on its largest side
+            let best = &poses[0];
+            assert_relative_eq!(best.normal.x.abs(), 1.0, epsilon = 1e-10);
+            assert_relative_eq!(best.height, 0.5, epsilon = 1e-10);
+            assert_relative_eq!(best.margin, 1.0, epsilon = 1e-10);
+            assert_relative_eq!(poses[5].height, 1.5, epsilon = 1e-10);
+
+            for pose in poses.iter() {
+                let placed = mesh.apply_transform(&pose.transform).unwrap();
+                let (lower, _) = placed.bounds().unwrap();
+                assert_relative_eq!(lower.z, 0.0, epsilon = 1e-10);
+                let center = placed.center_mass().unwrap();
+                assert_relative_eq!(center, Point3::new(0.0, 0.0, pose.height), epsilon = 1e-10);
+            }
+        }
+
+        // a heavy top on a narrow stem near its edge
+        let top = create_box(&[4.0, 4.0, 1.0])
+            .apply_translation(&Vector3::new(0.0, 0.0, 2.0))
+            .unwrap();
+        let stem = create_box(&[0.5, 0.5, 3.0])
+            .apply_translation(&Vector3::new(1.5, 0.0, 0.0))
+            .unwrap();
+        let mut vertices = top.vertices.clone();
+        vertices.extend(stem.vertices.iter());
+        let offset = top.vertices.len();
+        let mut faces = top.faces.clone();
+        faces.extend(
+            stem.faces
+                .iter()
+                .map(|f| (f.0 + offset, f.1 + offset, f.2 + offset)),
+        );
+        let mushroom = Trimesh::new(vertices, faces, None, None).unwrap();
+
+        let poses = mushroom.rest_pose(true).unwrap();
+        assert_relative_eq!(
+            poses[0].normal,
+            Vector3::new(0.0, 0.0, 1.0),
+            epsilon = 1e-10
+        );
+        // the center of mass isn't above the stem's foot so it tips over
+        assert!(poses.iter().all(|p| p.normal.z > -0.99));
+        // the hull finds poses leaning on the stem and the top together
+        let flat = mushroom.rest_pose(false).unwrap();
+        assert!(flat.len() < poses.len());
+
+        assert!(Trimesh::default().rest_pose(true).is_err());
+    }
+}
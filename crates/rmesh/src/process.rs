@@ -0,0 +1,452 @@
+use ahash::AHashSet;
+use anyhow::{Result, anyhow};
+
+use crate::attributes::Attributes;
+use crate::mesh::Trimesh;
+use crate::tolerance::Tolerances;
+
+/// The distance below which `Trimesh::process` considers vertices
+/// to be the same, which is well below the precision of most files.
+pub const MERGE_TOLERANCE: f64 = 1e-8;
+
+/// Round a value to the nearest multiple of a step. Dividing by the
+/// inverse when it's an integer means a step like 0.001 produces 0.3
+/// rather than 0.30000000000000004, and negative zero becomes zero so
+/// snapped values compare equal bit for bit.
+pub fn quantize(value: f64, step: f64) -> f64 {
+    let inverse = 1.0 / step;
+    let snapped = if (inverse - inverse.round()).abs() < 1e-9 * inverse {
+        (value * inverse).round() / inverse.round()
+    } else {
+        (value / step).round() * step
+    };
+    snapped + 0.0
+}
+
+/// Are the attributes of two vertices the same within a tolerance,
+/// checking only attributes with a value for every vertex.
+fn attributes_match(
+    attributes: &Attributes,
+    count: usize,
+    a: usize,
+    b: usize,
+    tolerance: f64,
+) -> bool {
+    attributes
+        .uv
+        .iter()
+        .filter(|v| v.len() == count)
+        .all(|v| (v[a] - v[b]).amax() <= tolerance)
+        && attributes
+            .normals
+            .iter()
+            .filter(|v| v.len() == count)
+            .all(|v| (v[a] - v[b]).amax() <= tolerance)
+        && attributes
+            .colors
+            .iter()
+            .filter(|v| v.len() == count)
+            .all(|v| v[a] == v[b])
+        && attributes
+            .groupings
+            .iter()
+            .filter(|g| g.indices.len() == count)
+            .all(|g| g.indices[a] == g.indices[b])
+}
+
+impl Trimesh {
+    /// Weld vertices which are within a distance of each other into a
+    /// single vertex, which turns a triangle soup like an STL into a
+    /// connected mesh. Vertices are only merged if their attributes like
+    /// normals and UV coordinates also match, so seams in a texture or
+    /// sharp edges in shading are preserved by keeping those vertices
+    /// split. Faces which collapse to reference the same vertex more than
+    /// once are removed along with their face attributes.
+    ///
+    /// Parameters
+    /// ------------
+    /// tolerance
+    ///   The maximum distance between merged vertices, which is also the
+    ///   maximum difference of each component of normals and UVs. Zero
+    ///   only merges vertices which are exactly equal.
+    ///
+    /// Returns
+    /// ------------
+    /// merged
+    ///   A new mesh or an error if the tolerance is negative.
+    pub fn merge_vertices(&self, tolerance: f64) -> Result<Self> {
+        if !(tolerance >= 0.0 && tolerance.is_finite()) {
+            return Err(anyhow!("Merge tolerance must be finite and positive"));
+        }
+        self.weld(tolerance, tolerance)
+    }
+
+    /// Merge vertices within a distance whose attributes match within a
+    /// separate tolerance, which is the implementation of `merge_vertices`.
+    fn weld(&self, tolerance: f64, attribute_tolerance: f64) -> Result<Self> {
+        let count = self.vertices.len();
+
+        // each vertex is merged into the earliest vertex within the
+        // tolerance which was kept and whose attributes match
+        let tree = self.vertex_tree();
+        // the index of the first occurrence of each unique vertex
+        let mut unique: Vec<usize> = Vec::new();
+        // the new index of every original vertex, or `usize::MAX`
+        // for vertices which haven't been reached yet
+        let mut inverse = vec![usize::MAX; count];
+        // which original vertices were kept
+        let mut kept = vec![false; count];
+
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            let found = tree
+                .radius(vertex, tolerance)
+                .into_iter()
+                .take_while(|other| *other < index)
+                .find(|other| {
+                    kept[*other]
+                        && attributes_match(
+                            &self.attributes_vertex,
+                            count,
+                            *other,
+                            index,
+                            attribute_tolerance,
+                        )
+                });
+            inverse[index] = match found {
+                Some(other) => inverse[other],
+                None => {
+                    kept[index] = true;
+                    unique.push(index);
+                    unique.len() - 1
+                }
+            };
+        }
+
+        // remap the faces and drop any that collapsed
+        let mut faces = Vec::with_capacity(self.faces.len());
+        let mut kept = Vec::with_capacity(self.faces.len());
+        for (index, face) in self.faces.iter().enumerate() {
+            let (a, b, c) = (inverse[face.0], inverse[face.1], inverse[face.2]);
+            if a != b && b != c && c != a {
+                faces.push((a, b, c));
+                kept.push(index);
+            }
+        }
+
+        let mut result = Trimesh::new(
+            unique.iter().map(|i| self.vertices[*i]).collect(),
+            faces,
+            Some(self.attributes_vertex.select(&unique, count)),
+            Some(self.attributes_face.select(&kept, self.faces.len())),
+        )?;
+        result.materials = self.materials.clone();
+        result.source = self.source.clone();
+        Ok(result)
+    }
+
+    /// Snap every vertex to the nearest point on a grid and then weld
+    /// vertices which landed on the same point, which makes dirty CAD
+    /// exports with nearly coincident vertices far more reliable for
+    /// booleans and repair. Vertices are only welded if their attributes
+    /// match within `MERGE_TOLERANCE` so texture seams and sharp shading
+    /// edges stay split, and faces which collapse are removed.
+    ///
+    /// Parameters
+    /// ------------
+    /// grid
+    ///   The spacing of the grid vertices are snapped to.
+    ///
+    /// Returns
+    /// ------------
+    /// snapped
+    ///   A new mesh or an error if the grid isn't positive and finite.
+    pub fn snap(&self, grid: f64) -> Result<Self> {
+        if !(grid > 0.0 && grid.is_finite()) {
+            return Err(anyhow!("Snap grid must be positive and finite: {grid}"));
+        }
+        let mut snapped = self.clone();
+        for vertex in snapped.vertices_mut().iter_mut() {
+            *vertex = vertex.map(|c| quantize(c, grid));
+        }
+        // snapped positions are either bit for bit equal or a grid apart
+        snapped.weld(0.0, MERGE_TOLERANCE)
+    }
+
+    /// Keep only some vertices, removing every face which references
+    /// a removed vertex and remapping the rest.
+    fn keep_vertices(&self, keep: &[bool]) -> Self {
+        let count = self.vertices.len();
+        let kept: Vec<usize> = (0..count).filter(|v| keep[*v]).collect();
+        let mut remap = vec![usize::MAX; count];
+        for (new, old) in kept.iter().enumerate() {
+            remap[*old] = new;
+        }
+        let faces: Vec<usize> = (0..self.faces.len())
+            .filter(|f| {
+                let (a, b, c) = self.faces[*f];
+                keep[a] && keep[b] && keep[c]
+            })
+            .collect();
+        Trimesh {
+            vertices: kept.iter().map(|v| self.vertices[*v]).collect(),
+            faces: faces
+                .iter()
+                .map(|f| {
+                    let (a, b, c) = self.faces[*f];
+                    (remap[a], remap[b], remap[c])
+                })
+                .collect(),
+            attributes_vertex: self.attributes_vertex.select(&kept, count),
+            attributes_face: self.attributes_face.select(&faces, self.faces.len()),
+            materials: self.materials.clone(),
+            source: self.source.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Remove vertices which aren't referenced by any face, compacting
+    /// the vertex attributes to match.
+    pub fn remove_unreferenced_vertices(&self) -> Self {
+        let mut referenced = vec![false; self.vertices.len()];
+        for f in self.faces.iter() {
+            referenced[f.0] = true;
+            referenced[f.1] = true;
+            referenced[f.2] = true;
+        }
+        self.keep_vertices(&referenced)
+    }
+
+    /// Remove vertices with a coordinate which is NaN or infinite along
+    /// with every face which references them.
+    pub fn remove_infinite_values(&self) -> Self {
+        let finite: Vec<bool> = self
+            .vertices
+            .iter()
+            .map(|v| v.iter().all(|c| c.is_finite()))
+            .collect();
+        self.keep_vertices(&finite)
+    }
+
+    /// Remove faces which reference the same vertices as an earlier face
+    /// in any order, keeping the first and its face attributes.
+    pub fn remove_duplicate_faces(&self) -> Self {
+        let mut seen = AHashSet::with_capacity(self.faces.len());
+        let kept: Vec<usize> = (0..self.faces.len())
+            .filter(|f| {
+                let (a, b, c) = self.faces[*f];
+                let mut key = [a, b, c];
+                key.sort_unstable();
+                seen.insert(key)
+            })
+            .collect();
+        self.with_faces(kept.iter().map(|f| self.faces[*f]).collect(), &kept)
+    }
+
+    /// Apply the cleanup most meshes need after loading, which removes
+    /// vertices that aren't finite and merges vertices closer than the
+    /// merge distance of `Trimesh::tolerances`, removing the faces that
+    /// collapse as a result.
+    ///
+    /// Parameters
+    /// ------------
+    /// validate
+    ///   Also remove degenerate and duplicate faces, make the winding
+    ///   consistent with normals pointing out, and remove any vertices
+    ///   left unreferenced, which is slower and changes more of the mesh.
+    ///
+    /// Returns
+    /// ------------
+    /// processed
+    ///   A new mesh.
+    pub fn process(&self, validate: bool) -> Result<Self> {
+        self.process_with(validate, &self.tolerances())
+    }
+
+    /// Process the mesh like `process` with explicit tolerances.
+    ///
+    /// Parameters
+    /// ------------
+    /// validate
+    ///   Also remove degenerate and duplicate faces and fix normals.
+    /// tolerances
+    ///   The distance to merge vertices and remove degenerate faces at.
+    ///
+    /// Returns
+    /// ------------
+    /// processed
+    ///   A new mesh.
+    pub fn process_with(&self, validate: bool, tolerances: &Tolerances) -> Result<Self> {
+        let mesh = self
+            .remove_infinite_values()
+            .merge_vertices(tolerances.merge)?;
+        if !validate {
+            return Ok(mesh);
+        }
+        Ok(mesh
+            .remove_degenerate_faces(tolerances.merge)
+            .remove_duplicate_faces()
+            .fix_normals()
+            .remove_unreferenced_vertices())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::exchange::{MeshFormat, load_mesh};
+    use approx::assert_relative_eq;
+    use nalgebra::{Point3, Vector2, Vector4};
+
+    #[test]
+    fn test_merge_vertices() {
+        let data = include_bytes!("../../../test/data/unit_cube.STL");
+        let soup = load_mesh(data, MeshFormat::STL).unwrap();
+        assert_eq!(soup.vertices.len(), 36);
+
+        let mesh = soup.process(false).unwrap();
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.faces.len(), 12);
+        assert_relative_eq!(mesh.area(), soup.area(), epsilon = 1e-10);
+        // every edge of a closed mesh is shared by two faces
+        assert_eq!(mesh.face_adjacency().len(), 18);
+
+        assert!(soup.merge_vertices(-1.0).is_err());
+        assert!(Trimesh::default().process(false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_merge_vertices_attributes() {
+        // two triangles sharing an edge with a tiny gap, plus a
+        // triangle that collapses when the gap is closed
+        let mut mesh = Trimesh::from_slice(
+            &[
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1e-9, 0.0, 1.0, 1.0, 0.0, 0.0,
+                1.0, 1e-9,
+            ],
+            &[0, 1, 2, 3, 4, 5, 1, 3, 4],
+        )
+        .unwrap();
+        mesh.attributes_face.colors.push(vec![
+            Vector4::new(255, 0, 0, 255),
+            Vector4::new(0, 255, 0, 255),
+            Vector4::new(0, 0, 255, 255),
+        ]);
+
+        let merged = mesh.merge_vertices(1e-8).unwrap();
+        assert_eq!(merged.vertices.len(), 4);
+        assert_eq!(merged.faces, vec![(0, 1, 2), (1, 3, 2)]);
+        // the color of the collapsed face should be removed
+        assert_eq!(
+            merged.attributes_face.colors[0],
+            mesh.attributes_face.colors[0][..2]
+        );
+
+        // a vertex on a texture seam should stay split
+        let mut seam = mesh.clone();
+        *seam.faces_mut() = vec![(0, 1, 2), (3, 4, 5)];
+        seam.attributes_vertex.uv.push(vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(1.0, 0.0),
+            Vector2::new(0.0, 1.0),
+            Vector2::new(0.5, 0.0),
+            Vector2::new(1.0, 1.0),
+            Vector2::new(0.0, 1.0),
+        ]);
+        let merged = seam.merge_vertices(1e-8).unwrap();
+        assert_eq!(merged.vertices.len(), 5);
+        assert_eq!(merged.uv().unwrap().len(), 5);
+        assert_eq!(merged.faces, vec![(0, 1, 2), (3, 4, 2)]);
+
+        // zero tolerance only merges exact duplicates
+        assert_eq!(seam.merge_vertices(0.0).unwrap().vertices.len(), 6);
+    }
+
+    #[test]
+    fn test_snap() {
+        assert_eq!(quantize(0.30004, 0.001), 0.3);
+        assert_eq!(quantize(0.3, 0.25), 0.25);
+        assert_eq!(quantize(-0.1, 1.0).to_bits(), 0.0_f64.to_bits());
+
+        // the same gap as above but wider than the merge tolerance
+        let mut mesh = Trimesh::from_slice(
+            &[
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1e-4, 0.0, 1.0, 1.0, 0.0, 0.0,
+                1.0, -1e-4,
+            ],
+            &[0, 1, 2, 3, 4, 5],
+        )
+        .unwrap();
+        assert_eq!(mesh.process(false).unwrap().vertices.len(), 6);
+        let snapped = mesh.snap(0.01).unwrap();
+        assert_eq!(snapped.vertices.len(), 4);
+        assert_eq!(snapped.faces, vec![(0, 1, 2), (1, 3, 2)]);
+        assert!(snapped.vertices.iter().all(|v| v.z == 0.0));
+
+        // but not across a texture seam
+        mesh.attributes_vertex.uv.push(vec![
+            Vector2::new(0.0, 0.0),
+            Vector2::new(1.0, 0.0),
+            Vector2::new(0.0, 1.0),
+            Vector2::new(0.5, 0.0),
+            Vector2::new(1.0, 1.0),
+            Vector2::new(0.0, 1.0),
+        ]);
+        let snapped = mesh.snap(0.01).unwrap();
+        assert_eq!(snapped.vertices.len(), 5);
+        assert_eq!(snapped.uv().unwrap().len(), 5);
+
+        assert!(mesh.snap(0.0).is_err());
+        assert!(mesh.snap(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_remove_geometry() {
+        use crate::creation::create_box;
+
+        let cube = create_box(&[1.0, 1.0, 1.0]);
+        let mut vertices = cube.vertices.clone();
+        // an unused vertex, a vertex that isn't finite and a face using it
+        vertices.push(Point3::new(5.0, 5.0, 5.0));
+        vertices.push(Point3::new(f64::NAN, 0.0, 0.0));
+        let mut faces = cube.faces.clone();
+        faces.push((0, 1, 9));
+        // the same face repeated with a different winding
+        faces.push((cube.faces[0].0, cube.faces[0].2, cube.faces[0].1));
+        let mut mesh = Trimesh::new(vertices, faces, None, None).unwrap();
+        mesh.attributes_vertex.uv.push(
+            (0..mesh.vertices.len())
+                .map(|i| Vector2::new(i as f64, 0.0))
+                .collect(),
+        );
+        mesh.attributes_face.colors.push(
+            (0..mesh.faces.len())
+                .map(|i| Vector4::new(i as u8, 0, 0, 255))
+                .collect(),
+        );
+
+        let finite = mesh.remove_infinite_values();
+        assert_eq!(finite.vertices.len(), 9);
+        assert_eq!(finite.faces.len(), 13);
+        assert_eq!(finite.attributes_vertex.uv[0].len(), 9);
+        assert_eq!(finite.attributes_face.colors[0][12].x, 13);
+
+        let unique = finite.remove_duplicate_faces();
+        assert_eq!(unique.faces, cube.faces);
+        assert_eq!(unique.attributes_face.colors[0][11].x, 11);
+
+        let referenced = unique.remove_unreferenced_vertices();
+        assert_eq!(referenced.vertices, cube.vertices);
+        assert_eq!(
+            referenced.attributes_vertex.uv[0],
+            mesh.attributes_vertex.uv[0][..8]
+        );
+
+        // processing only drops what isn't finite while validating cleans up the rest
+        assert_eq!(mesh.process(false).unwrap().faces.len(), 13);
+        let valid = mesh.process(true).unwrap();
+        assert_eq!(valid.faces.len(), 12);
+        assert_eq!(valid.vertices.len(), 8);
+        assert_relative_eq!(valid.volume(), 1.0, epsilon = 1e-10);
+    }
+}
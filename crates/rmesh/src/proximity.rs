@@ -0,0 +1,680 @@
+use nalgebra::{Point3, Vector3};
+use rayon::prelude::*;
+
+use crate::mesh::Trimesh;
+
+/// An enclosed region of empty space inside a mesh, such as a
+/// void in a casting or a resin trap in a 3D print.
+#[derive(Debug, Clone)]
+pub struct Pocket {
+    // the centers of the grid cells which make up the pocket
+    pub points: Vec<Point3<f64>>,
+
+    // the approximate volume of the pocket from the cell count
+    pub volume: f64,
+}
+
+impl Pocket {
+    /// The average position of the cells in the pocket.
+    pub fn centroid(&self) -> Point3<f64> {
+        let sum = self
+            .points
+            .iter()
+            .fold(Vector3::zeros(), |acc, p| acc + p.coords);
+        Point3::from(sum / self.points.len().max(1) as f64)
+    }
+}
+
+/// Find the closest point on a triangle to a query point using
+/// the Voronoi region method from "Real-Time Collision Detection".
+///
+/// Parameters
+/// ------------
+/// p
+///   The query point.
+/// a, b, c
+///   The vertices of the triangle.
+///
+/// Returns
+/// ------------
+/// closest
+///   The point on the triangle closest to `p`.
+pub fn closest_point_triangle(
+    p: &Point3<f64>,
+    a: &Point3<f64>,
+    b: &Point3<f64>,
+    c: &Point3<f64>,
+) -> Point3<f64> {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+
+    // vertex region outside `a`
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return *a;
+    }
+
+    // vertex region outside `b`
+    let bp = p - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return *b;
+    }
+
+    // edge region of `ab`
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        let v = d1 / (d1 - d3);
+        return a + ab * v;
+    }
+
+    // vertex region outside `c`
+    let cp = p - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return *c;
+    }
+
+    // edge region of `ac`
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        let w = d2 / (d2 - d6);
+        return a + ac * w;
+    }
+
+    // edge region of `bc`
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return b + (c - b) * w;
+    }
+
+    // inside the face region
+    let denom = 1.0 / (va + vb + vc);
+    let v = vb * denom;
+    let w = vc * denom;
+    a + ab * v + ac * w
+}
+
+/// The signed solid angle subtended by a triangle at the origin
+/// using the formula from Van Oosterom and Strackee (1983).
+fn solid_angle(a: &Vector3<f64>, b: &Vector3<f64>, c: &Vector3<f64>) -> f64 {
+    let (la, lb, lc) = (a.norm(), b.norm(), c.norm());
+    let numerator = a.dot(&b.cross(c));
+    let denominator = la * lb * lc + a.dot(b) * lc + a.dot(c) * lb + b.dot(c) * la;
+    2.0 * numerator.atan2(denominator)
+}
+
+/// How many times further than its radius a subtree of faces must be
+/// from a point for `winding_number` to use its dipole rather than the
+/// exact solid angle of every face, trading accuracy for speed.
+const WINDING_FAR: f64 = 4.0;
+
+/// The faces below a node of the face tree summarized as a dipole,
+/// whose solid angle approximates theirs at points far away.
+struct Dipole {
+    // the sum of the area weighted normals of the faces
+    normal: Vector3<f64>,
+    // the total area of the faces
+    area: f64,
+    // the area weighted centroid of the faces
+    center: Point3<f64>,
+}
+
+impl Dipole {
+    /// Combine the dipoles of two groups of faces.
+    fn combine(a: &Dipole, b: &Dipole) -> Dipole {
+        let area = a.area + b.area;
+        let center = if area > 0.0 {
+            Point3::from((a.center.coords * a.area + b.center.coords * b.area) / area)
+        } else {
+            nalgebra::center(&a.center, &b.center)
+        };
+        Dipole {
+            normal: a.normal + b.normal,
+            area,
+            center,
+        }
+    }
+}
+
+impl Trimesh {
+    /// The generalized winding number of each point with respect to the
+    /// mesh, which is close to 1.0 for points inside a closed, outward
+    /// wound mesh and close to 0.0 for points outside of it. Unlike ray
+    /// tests it degrades gracefully for meshes with small holes.
+    ///
+    /// Faces near a point contribute their exact solid angle while
+    /// distant groups of faces in the BVH are approximated by a dipole
+    /// as in "Fast Winding Numbers for Soups and Clouds" (Barill et al.
+    /// 2018), so each point costs roughly the log of the face count.
+    ///
+    /// Parameters
+    /// ------------
+    /// points
+    ///   The query points.
+    ///
+    /// Returns
+    /// ------------
+    /// winding
+    ///   The winding number for each point.
+    pub fn winding_number(&self, points: &[Point3<f64>]) -> Vec<f64> {
+        if self.is_empty() {
+            return vec![0.0; points.len()];
+        }
+        let bvh = self.bvh();
+        let triangle = |i: usize| {
+            let f = self.faces[i];
+            [self.vertices[f.0], self.vertices[f.1], self.vertices[f.2]]
+        };
+        let dipoles = bvh.fold_nodes(
+            |items| {
+                items
+                    .iter()
+                    .map(|i| {
+                        let [a, b, c] = triangle(*i);
+                        let normal = (b - a).cross(&(c - a)) / 2.0;
+                        Dipole {
+                            normal,
+                            area: normal.norm(),
+                            center: Point3::from((a.coords + b.coords + c.coords) / 3.0),
+                        }
+                    })
+                    .reduce(|a, b| Dipole::combine(&a, &b))
+                    .expect("leaves have items")
+            },
+            Dipole::combine,
+        );
+        points
+            .par_iter()
+            .map(|p| {
+                let mut total = 0.0;
+                bvh.descend(|node, bounds, items| {
+                    let dipole = &dipoles[node];
+                    let offset = dipole.center - p;
+                    let distance = offset.norm();
+                    // the furthest any face of the node can be from its center
+                    let radius = (bounds.0 - dipole.center)
+                        .abs()
+                        .sup(&(bounds.1 - dipole.center).abs())
+                        .norm();
+                    if distance > WINDING_FAR * radius {
+                        total += offset.dot(&dipole.normal) / distance.powi(3);
+                        return false;
+                    }
+                    if let Some(items) = items {
+                        for i in items {
+                            let [a, b, c] = triangle(*i);
+                            total += solid_angle(&(a - p), &(b - p), &(c - p));
+                        }
+                    }
+                    true
+                });
+                total / (4.0 * std::f64::consts::PI)
+            })
+            .collect()
+    }
+
+    /// Find the closest point on the surface of the mesh for each query point.
+    ///
+    /// Parameters
+    /// ------------
+    /// points
+    ///   The query points.
+    ///
+    /// Returns
+    /// ------------
+    /// closest
+    ///   The closest point on the surface, the distance to it and the index
+    ///   of the face it lies on, or `None` if the mesh is empty.
+    pub fn closest_point(&self, points: &[Point3<f64>]) -> Vec<Option<(Point3<f64>, f64, usize)>> {
+        if self.is_empty() {
+            return vec![None; points.len()];
+        }
+        let bvh = self.bvh();
+        points
+            .par_iter()
+            .map(|p| {
+                let mut best = None;
+                bvh.nearest(p, |i, limit| {
+                    let f = self.faces[i];
+                    let closest = closest_point_triangle(
+                        p,
+                        &self.vertices[f.0],
+                        &self.vertices[f.1],
+                        &self.vertices[f.2],
+                    );
+                    let distance = (closest - p).norm();
+                    if distance < *limit {
+                        *limit = distance;
+                        best = Some((closest, distance, i));
+                    }
+                });
+                best
+            })
+            .collect()
+    }
+
+    /// Points spread evenly over every face on a grid of barycentric
+    /// coordinates, including the vertices and points along each edge.
+    fn surface_grid(&self, resolution: usize) -> Vec<Point3<f64>> {
+        let n = resolution.max(1);
+        self.par_triangles()
+            .flat_map_iter(|[a, b, c]| {
+                (0..=n).flat_map(move |i| {
+                    (0..=n - i).map(move |j| {
+                        let (u, v) = (i as f64 / n as f64, j as f64 / n as f64);
+                        a + (b - a) * u + (c - a) * v
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// The Hausdorff distance between the surfaces of two meshes, which
+    /// is the furthest any point on one surface is from the other. It's
+    /// estimated from a grid of points on every face of both meshes so
+    /// it can only be an underestimate, which shrinks as the resolution
+    /// increases and is exact where the furthest point is a vertex.
+    ///
+    /// Parameters
+    /// ------------
+    /// other
+    ///   The mesh to compare against.
+    /// resolution
+    ///   The number of steps to divide the edges of every face into
+    ///   for the grid of points, where one is only the vertices.
+    ///
+    /// Returns
+    /// ------------
+    /// distance
+    ///   The largest distance found in either direction,
+    ///   or `None` if either mesh has no faces.
+    pub fn hausdorff(&self, other: &Trimesh, resolution: usize) -> Option<f64> {
+        if self.is_empty() || other.is_empty() {
+            return None;
+        }
+        let furthest = |from: &Trimesh, to: &Trimesh| {
+            to.closest_point(&from.surface_grid(resolution))
+                .into_iter()
+                .flatten()
+                .map(|(_, distance, _)| distance)
+                .fold(0.0, f64::max)
+        };
+        Some(furthest(self, other).max(furthest(other, self)))
+    }
+
+    /// The signed distance from each point to the surface of the mesh
+    /// using the same convention as `trimesh`: positive values are inside
+    /// the mesh and negative values are outside.
+    ///
+    /// Parameters
+    /// ------------
+    /// points
+    ///   The query points.
+    ///
+    /// Returns
+    /// ------------
+    /// distance
+    ///   The signed distance for each point, or negative infinity
+    ///   for every point if the mesh is empty.
+    pub fn signed_distance(&self, points: &[Point3<f64>]) -> Vec<f64> {
+        let winding = self.winding_number(points);
+        self.closest_point(points)
+            .into_iter()
+            .zip(winding)
+            .map(|(closest, w)| match closest {
+                Some((_, distance, _)) if w > 0.5 => distance,
+                Some((_, distance, _)) => -distance,
+                None => f64::NEG_INFINITY,
+            })
+            .collect()
+    }
+
+    /// Find the largest sphere that fits entirely inside the mesh by
+    /// maximizing the signed distance field: an initial grid search is
+    /// followed by a shrinking pattern search around the best sample.
+    ///
+    /// Parameters
+    /// ------------
+    /// resolution
+    ///   The number of grid samples along the longest axis of the mesh.
+    ///
+    /// Returns
+    /// ------------
+    /// sphere
+    ///   The center and radius of the sphere, or `None` if the mesh
+    ///   is empty or no sample was inside the mesh.
+    pub fn max_inscribed_sphere(&self, resolution: usize) -> Option<(Point3<f64>, f64)> {
+        let grid = SampleGrid::new(self, resolution, 0)?;
+        let points = grid.points();
+        let distance = self.signed_distance(&points);
+
+        let (index, radius) = distance
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, d)| (i, *d))?;
+        if radius <= 0.0 {
+            return None;
+        }
+
+        // refine the best grid sample by checking the neighbors along
+        // each axis and halving the step whenever none of them improve
+        let mut center = points[index];
+        let mut radius = radius;
+        let mut step = grid.pitch;
+        while step > grid.pitch * 1e-4 {
+            let candidates: Vec<Point3<f64>> = (0..3)
+                .flat_map(|axis| {
+                    let mut offset = Vector3::zeros();
+                    offset[axis] = step;
+                    [center + offset, center - offset]
+                })
+                .collect();
+            let best = self
+                .signed_distance(&candidates)
+                .into_iter()
+                .zip(candidates)
+                .max_by(|a, b| a.0.total_cmp(&b.0))?;
+            if best.0 > radius {
+                (radius, center) = best;
+            } else {
+                step /= 2.0;
+            }
+        }
+
+        Some((center, radius))
+    }
+
+    /// Find enclosed voids inside the mesh by evaluating the sign of the
+    /// distance field on a grid and then finding connected components of
+    /// the cells that are outside of the mesh. Any component that doesn't
+    /// touch the padded border of the grid is enclosed by the mesh.
+    ///
+    /// Parameters
+    /// ------------
+    /// resolution
+    ///   The number of grid cells along the longest axis of the mesh.
+    ///
+    /// Returns
+    /// ------------
+    /// pockets
+    ///   Every enclosed region found, in no particular order.
+    pub fn pockets(&self, resolution: usize) -> Vec<Pocket> {
+        // pad the grid by one cell so the exterior is always connected
+        let Some(grid) = SampleGrid::new(self, resolution, 1) else {
+            return Vec::new();
+        };
+        let points = grid.points();
+        let outside: Vec<bool> = self
+            .winding_number(&points)
+            .into_iter()
+            .map(|w| w < 0.5)
+            .collect();
+
+        let cell_volume = grid.pitch.powi(3);
+        let mut visited = vec![false; points.len()];
+        let mut pockets = Vec::new();
+
+        for start in 0..points.len() {
+            if visited[start] || !outside[start] {
+                continue;
+            }
+            // flood fill the connected outside cells from this seed
+            let mut component = Vec::new();
+            let mut border = false;
+            let mut queue = vec![start];
+            visited[start] = true;
+            while let Some(current) = queue.pop() {
+                component.push(current);
+                let ijk = grid.unravel(current);
+                border |= grid.on_border(ijk);
+                for neighbor in grid.neighbors(ijk) {
+                    if !visited[neighbor] && outside[neighbor] {
+                        visited[neighbor] = true;
+                        queue.push(neighbor);
+                    }
+                }
+            }
+
+            if !border {
+                pockets.push(Pocket {
+                    volume: component.len() as f64 * cell_volume,
+                    points: component.into_iter().map(|i| points[i]).collect(),
+                });
+            }
+        }
+
+        pockets
+    }
+}
+
+/// A regular grid of sample points over the bounds of a mesh.
+struct SampleGrid {
+    // the center of the first cell
+    origin: Point3<f64>,
+    // the edge length of a cell
+    pitch: f64,
+    // the number of cells along each axis
+    shape: [usize; 3],
+}
+
+impl SampleGrid {
+    /// Create a grid over the bounds of a mesh with `resolution` cells
+    /// along the longest axis and `pad` extra cells on every side.
+    fn new(mesh: &Trimesh, resolution: usize, pad: usize) -> Option<Self> {
+        if mesh.is_empty() || resolution == 0 {
+            return None;
+        }
+        let (lower, upper) = mesh.bounds()?;
+        let extents = upper - lower;
+        let pitch = extents.max() / resolution as f64;
+
+        let mut shape = [0; 3];
+        for (axis, count) in shape.iter_mut().enumerate() {
+            *count = ((extents[axis] / pitch).ceil() as usize).max(1) + 2 * pad;
+        }
+
+        // center the cells on the bounds of the mesh
+        let origin = lower
+            - Vector3::new(
+                (shape[0] as f64 * pitch - extents.x) / 2.0,
+                (shape[1] as f64 * pitch - extents.y) / 2.0,
+                (shape[2] as f64 * pitch - extents.z) / 2.0,
+            )
+            + Vector3::repeat(pitch / 2.0);
+
+        Some(Self {
+            origin,
+            pitch,
+            shape,
+        })
+    }
+
+    /// The center of every cell in the grid in row-major order.
+    fn points(&self) -> Vec<Point3<f64>> {
+        let count = self.shape.iter().product();
+        (0..count)
+            .map(|i| {
+                let [x, y, z] = self.unravel(i);
+                self.origin + Vector3::new(x as f64, y as f64, z as f64) * self.pitch
+            })
+            .collect()
+    }
+
+    fn unravel(&self, index: usize) -> [usize; 3] {
+        let [_, ny, nz] = self.shape;
+        [index / (ny * nz), (index / nz) % ny, index % nz]
+    }
+
+    fn ravel(&self, ijk: [usize; 3]) -> usize {
+        let [_, ny, nz] = self.shape;
+        ijk[0] * ny * nz + ijk[1] * nz + ijk[2]
+    }
+
+    fn on_border(&self, ijk: [usize; 3]) -> bool {
+        (0..3).any(|axis| ijk[axis] == 0 || ijk[axis] + 1 == self.shape[axis])
+    }
+
+    /// The face-connected neighbors of a cell.
+    fn neighbors(&self, ijk: [usize; 3]) -> Vec<usize> {
+        let mut result = Vec::with_capacity(6);
+        for axis in 0..3 {
+            if ijk[axis] > 0 {
+                let mut n = ijk;
+                n[axis] -= 1;
+                result.push(self.ravel(n));
+            }
+            if ijk[axis] + 1 < self.shape[axis] {
+                let mut n = ijk;
+                n[axis] += 1;
+                result.push(self.ravel(n));
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::{create_box, create_sphere};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_closest_point_triangle() {
+        let a = Point3::new(0.0, 0.0, 0.0);
+        let b = Point3::new(1.0, 0.0, 0.0);
+        let c = Point3::new(0.0, 1.0, 0.0);
+
+        // above the face
+        let p = closest_point_triangle(&Point3::new(0.2, 0.2, 1.0), &a, &b, &c);
+        assert_relative_eq!(p, Point3::new(0.2, 0.2, 0.0));
+        // past a vertex
+        let p = closest_point_triangle(&Point3::new(-1.0, -1.0, 0.0), &a, &b, &c);
+        assert_relative_eq!(p, a);
+        // past the hypotenuse
+        let p = closest_point_triangle(&Point3::new(1.0, 1.0, 0.0), &a, &b, &c);
+        assert_relative_eq!(p, Point3::new(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn test_signed_distance() {
+        let mesh = create_box(&[2.0, 2.0, 2.0]);
+        let points = vec![
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.5, 0.0, 0.0),
+            Point3::new(3.0, 0.0, 0.0),
+        ];
+
+        let winding = mesh.winding_number(&points);
+        assert_relative_eq!(winding[0], 1.0, epsilon = 1e-8);
+        assert_relative_eq!(winding[2], 0.0, epsilon = 1e-8);
+
+        let distance = mesh.signed_distance(&points);
+        assert_relative_eq!(distance[0], 1.0, epsilon = 1e-8);
+        assert_relative_eq!(distance[1], 0.5, epsilon = 1e-8);
+        assert_relative_eq!(distance[2], -2.0, epsilon = 1e-8);
+
+        assert!(Trimesh::default().signed_distance(&points)[0].is_infinite());
+    }
+
+    #[test]
+    fn test_winding_number() {
+        let mesh = create_sphere(1.0, [64, 64]).unwrap();
+        let points: Vec<_> = (0..200)
+            .map(|i| {
+                // a spiral of points moving from the center to well outside
+                let t = i as f64 / 200.0;
+                let angle = t * 40.0;
+                Point3::new(angle.cos(), angle.sin(), (angle * 0.7).cos()) * (t * 3.0)
+            })
+            .collect();
+        let winding = mesh.winding_number(&points);
+        for (p, w) in points.iter().zip(winding) {
+            // the exact sum over every face
+            let exact = mesh
+                .triangles()
+                .map(|[a, b, c]| solid_angle(&(a - p), &(b - p), &(c - p)))
+                .sum::<f64>()
+                / (4.0 * std::f64::consts::PI);
+            assert_relative_eq!(w, exact, epsilon = 1e-2);
+        }
+        assert!(
+            Trimesh::default()
+                .winding_number(&points)
+                .iter()
+                .all(|w| *w == 0.0)
+        );
+    }
+
+    #[test]
+    fn test_hausdorff() {
+        let mesh = create_box(&[2.0, 2.0, 2.0]);
+        assert_relative_eq!(mesh.hausdorff(&mesh, 1).unwrap(), 0.0);
+
+        // a box with one side pushed out is furthest from the
+        // original at the corners of the moved side
+        let taller = create_box(&[2.0, 2.0, 3.0])
+            .apply_translation(&Vector3::new(0.0, 0.0, 0.5))
+            .unwrap();
+        assert_relative_eq!(mesh.hausdorff(&taller, 1).unwrap(), 1.0, epsilon = 1e-12);
+        assert_relative_eq!(taller.hausdorff(&mesh, 4).unwrap(), 1.0, epsilon = 1e-12);
+
+        // the closest points agree with checking every face
+        let points = taller.surface_grid(3);
+        assert_eq!(points.len(), taller.faces.len() * 10);
+        for (p, closest) in points.iter().zip(mesh.closest_point(&points)) {
+            let (_, distance, _) = closest.unwrap();
+            let brute = mesh
+                .triangles()
+                .map(|[a, b, c]| (closest_point_triangle(p, &a, &b, &c) - p).norm())
+                .fold(f64::INFINITY, f64::min);
+            assert_relative_eq!(distance, brute, epsilon = 1e-12);
+        }
+        assert!(mesh.hausdorff(&Trimesh::default(), 1).is_none());
+    }
+
+    #[test]
+    fn test_max_inscribed_sphere() {
+        let mesh = create_box(&[2.0, 4.0, 6.0]);
+        let (center, radius) = mesh.max_inscribed_sphere(10).unwrap();
+        assert_relative_eq!(radius, 1.0, epsilon = 1e-6);
+        assert!(center.x.abs() < 1e-6);
+
+        assert!(Trimesh::default().max_inscribed_sphere(10).is_none());
+    }
+
+    #[test]
+    fn test_pockets() {
+        // a solid box has no pockets
+        let outer = create_box(&[4.0, 4.0, 4.0]);
+        assert!(outer.pockets(8).is_empty());
+
+        // add an inward-facing box inside to create a hollow shell
+        let inner = create_box(&[2.0, 2.0, 2.0]);
+        let offset = outer.vertices.len();
+        let mut vertices = outer.vertices.clone();
+        vertices.extend(inner.vertices.iter());
+        let mut faces = outer.faces.clone();
+        faces.extend(
+            inner
+                .faces
+                .iter()
+                .map(|f| (f.0 + offset, f.2 + offset, f.1 + offset)),
+        );
+        let shell = Trimesh::new(vertices, faces, None, None).unwrap();
+
+        let pockets = shell.pockets(8);
+        assert_eq!(pockets.len(), 1);
+        // the void is 2x2x2 which is 8 cells of size 0.5^3 per unit
+        assert_relative_eq!(pockets[0].volume, 8.0, epsilon = 1e-8);
+        assert_relative_eq!(pockets[0].centroid(), Point3::origin(), epsilon = 1e-8);
+    }
+}
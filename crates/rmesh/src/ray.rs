@@ -0,0 +1,604 @@
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector3};
+use rayon::prelude::*;
+use rmesh_macro::cache_access;
+
+use crate::bvh::Bvh;
+use crate::geometry::Geometry;
+use crate::mesh::Trimesh;
+use crate::scene::Scene;
+
+/// A ray with an origin and a direction which doesn't need to be normalized.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: Point3<f64>,
+    pub direction: Vector3<f64>,
+}
+
+impl Ray {
+    pub fn new(origin: Point3<f64>, direction: Vector3<f64>) -> Self {
+        Self { origin, direction }
+    }
+
+    /// The point at a parameter along the ray.
+    pub fn at(&self, t: f64) -> Point3<f64> {
+        self.origin + self.direction * t
+    }
+}
+
+/// The result of picking a mesh with a ray.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickResult {
+    // the index of the face that was hit
+    pub face: usize,
+
+    // the location of the hit in the frame of the mesh
+    pub point: Point3<f64>,
+
+    // the barycentric coordinates of the hit on the face
+    pub barycentric: Vector3<f64>,
+
+    // the distance from the ray origin to the hit
+    pub distance: f64,
+}
+
+impl PickResult {
+    /// The vertex of the face closest to the hit which
+    /// is what a viewer would usually highlight.
+    pub fn vertex(&self, mesh: &Trimesh) -> usize {
+        let face = mesh.faces[self.face];
+        let b = self.barycentric;
+        if b.x >= b.y && b.x >= b.z {
+            face.0
+        } else if b.y >= b.z {
+            face.1
+        } else {
+            face.2
+        }
+    }
+}
+
+/// The result of picking a scene with a ray.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenePickResult {
+    // the index of the node in the scene graph that was hit
+    pub node: usize,
+
+    // the index of the geometry in the scene that was hit
+    pub geometry: usize,
+
+    // the hit in the frame of the geometry, except for `distance`
+    // which is measured in the frame of the scene
+    pub pick: PickResult,
+
+    // the location of the hit in the frame of the scene
+    pub point: Point3<f64>,
+}
+
+/// Intersect a ray with a triangle using the Moller-Trumbore algorithm.
+///
+/// Parameters
+/// ------------
+/// ray
+///   The ray to intersect.
+/// a, b, c
+///   The vertices of the triangle.
+///
+/// Returns
+/// ------------
+/// hit
+///   The ray parameter and the barycentric coordinates of
+///   the hit, or `None` if the ray misses the triangle.
+pub fn intersect_triangle(
+    ray: &Ray,
+    a: &Point3<f64>,
+    b: &Point3<f64>,
+    c: &Point3<f64>,
+) -> Option<(f64, Vector3<f64>)> {
+    let ab = b - a;
+    let ac = c - a;
+    let p = ray.direction.cross(&ac);
+    let det = ab.dot(&p);
+    // the ray is parallel to the triangle or the triangle is degenerate
+    if det.abs() < f64::EPSILON {
+        return None;
+    }
+    let inv = 1.0 / det;
+
+    let s = ray.origin - a;
+    let u = s.dot(&p) * inv;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(&ab);
+    let v = ray.direction.dot(&q) * inv;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = ac.dot(&q) * inv;
+    if t < 0.0 {
+        return None;
+    }
+    Some((t, Vector3::new(1.0 - u - v, u, v)))
+}
+
+/// Pair up origins and directions into rays.
+fn zip_rays(origins: &[Point3<f64>], directions: &[Vector3<f64>]) -> Result<Vec<Ray>> {
+    if origins.len() != directions.len() {
+        return Err(anyhow!(
+            "Got {} ray origins but {} directions",
+            origins.len(),
+            directions.len()
+        ));
+    }
+    Ok(origins
+        .iter()
+        .zip(directions)
+        .map(|(o, d)| Ray::new(*o, *d))
+        .collect())
+}
+
+impl Trimesh {
+    /// A bounding volume hierarchy over the faces of the mesh which
+    /// is built on first use and accelerates every ray query.
+    #[cache_access]
+    pub fn bvh(&self) -> Arc<Bvh> {
+        let bounds: Vec<_> = self
+            .par_triangles()
+            .map(|[a, b, c]| (a.inf(&b).inf(&c), a.sup(&b).sup(&c)))
+            .collect();
+        Arc::new(Bvh::new(&bounds))
+    }
+
+    /// Intersect a ray with a face by index.
+    fn intersect_face(&self, ray: &Ray, face: usize) -> Option<(f64, Vector3<f64>)> {
+        let f = self.faces[face];
+        intersect_triangle(
+            ray,
+            &self.vertices[f.0],
+            &self.vertices[f.1],
+            &self.vertices[f.2],
+        )
+    }
+
+    /// Package a hit on a face as a pick result.
+    fn pick_result(ray: &Ray, face: usize, t: f64, barycentric: Vector3<f64>) -> PickResult {
+        PickResult {
+            face,
+            point: ray.at(t),
+            barycentric,
+            distance: t * ray.direction.norm(),
+        }
+    }
+
+    /// Find the closest hit of a ray using the tree, preferring the
+    /// lowest face index when hits are the same distance away.
+    fn closest_hit(&self, bvh: &Bvh, ray: &Ray) -> Option<PickResult> {
+        let mut best: Option<(usize, f64, Vector3<f64>)> = None;
+        bvh.traverse(ray, |face, limit| {
+            if let Some((t, barycentric)) = self.intersect_face(ray, face) {
+                let closer = match best {
+                    Some((other, closest, _)) => t < closest || (t == closest && face < other),
+                    None => true,
+                };
+                if closer {
+                    best = Some((face, t, barycentric));
+                    *limit = t;
+                }
+            }
+            false
+        });
+        best.map(|(face, t, barycentric)| Self::pick_result(ray, face, t, barycentric))
+    }
+
+    /// Find the first face hit by a ray, for example to implement
+    /// clicking on a mesh in a viewer.
+    ///
+    /// Parameters
+    /// ------------
+    /// ray
+    ///   The ray to cast in the frame of the mesh.
+    ///
+    /// Returns
+    /// ------------
+    /// pick
+    ///   The closest hit along the ray or `None` if it missed.
+    pub fn pick(&self, ray: &Ray) -> Option<PickResult> {
+        if self.is_empty() {
+            return None;
+        }
+        self.closest_hit(&self.bvh(), ray)
+    }
+
+    /// Check whether each ray hits the mesh at all, which is
+    /// faster than finding the hits as it stops at the first one.
+    ///
+    /// Parameters
+    /// ------------
+    /// origins
+    ///   The origin of each ray.
+    /// directions
+    ///   The direction of each ray.
+    ///
+    /// Returns
+    /// ------------
+    /// hit
+    ///   If each ray hit any face, or an error if the number of
+    ///   origins and directions don't match.
+    pub fn intersects_any(
+        &self,
+        origins: &[Point3<f64>],
+        directions: &[Vector3<f64>],
+    ) -> Result<Vec<bool>> {
+        let rays = zip_rays(origins, directions)?;
+        let bvh = self.bvh();
+        Ok(rays
+            .par_iter()
+            .map(|ray| {
+                let mut hit = false;
+                bvh.traverse(ray, |face, _| {
+                    hit = self.intersect_face(ray, face).is_some();
+                    hit
+                });
+                hit
+            })
+            .collect())
+    }
+
+    /// Find the index of the first face hit by each ray.
+    ///
+    /// Parameters
+    /// ------------
+    /// origins
+    ///   The origin of each ray.
+    /// directions
+    ///   The direction of each ray.
+    ///
+    /// Returns
+    /// ------------
+    /// faces
+    ///   The closest face hit by each ray or `None` if it missed, or an
+    ///   error if the number of origins and directions don't match.
+    pub fn intersects_id(
+        &self,
+        origins: &[Point3<f64>],
+        directions: &[Vector3<f64>],
+    ) -> Result<Vec<Option<usize>>> {
+        let rays = zip_rays(origins, directions)?;
+        let bvh = self.bvh();
+        Ok(rays
+            .par_iter()
+            .map(|ray| self.closest_hit(&bvh, ray).map(|hit| hit.face))
+            .collect())
+    }
+
+    /// Find every location where each ray hits the mesh, rather than
+    /// only the first one. A ray passing exactly through an edge or a
+    /// vertex will report a hit on each of the faces it touches.
+    ///
+    /// Parameters
+    /// ------------
+    /// origins
+    ///   The origin of each ray.
+    /// directions
+    ///   The direction of each ray.
+    ///
+    /// Returns
+    /// ------------
+    /// hits
+    ///   The index of the ray and the hit for every hit, ordered by
+    ///   ray and then distance along the ray, or an error if the number
+    ///   of origins and directions don't match.
+    pub fn intersects_location(
+        &self,
+        origins: &[Point3<f64>],
+        directions: &[Vector3<f64>],
+    ) -> Result<Vec<(usize, PickResult)>> {
+        let rays = zip_rays(origins, directions)?;
+        let bvh = self.bvh();
+        let hits: Vec<Vec<(usize, PickResult)>> = rays
+            .par_iter()
+            .enumerate()
+            .map(|(index, ray)| {
+                let mut hits = Vec::new();
+                bvh.traverse(ray, |face, _| {
+                    if let Some((t, barycentric)) = self.intersect_face(ray, face) {
+                        hits.push((index, Self::pick_result(ray, face, t, barycentric)));
+                    }
+                    false
+                });
+                hits.sort_by(|a, b| {
+                    a.1.distance
+                        .total_cmp(&b.1.distance)
+                        .then(a.1.face.cmp(&b.1.face))
+                });
+                hits
+            })
+            .collect();
+        Ok(hits.into_iter().flatten().collect())
+    }
+
+    /// Check whether each point is inside a watertight mesh by counting
+    /// how many times rays from it cross the surface, where an odd count
+    /// is inside. Three rays in unrelated directions vote so a ray which
+    /// grazes an edge or a vertex and is miscounted is outvoted.
+    ///
+    /// Parameters
+    /// ------------
+    /// points
+    ///   The points to check.
+    ///
+    /// Returns
+    /// ------------
+    /// contains
+    ///   If each point is inside the mesh, which is meaningless
+    ///   for meshes which aren't closed.
+    pub fn contains(&self, points: &[Point3<f64>]) -> Vec<bool> {
+        if self.is_empty() {
+            return vec![false; points.len()];
+        }
+        // directions unlikely to line up with the edges of real models
+        let directions = [
+            Vector3::new(0.4395064455, 0.617598629942, 0.652231566745),
+            Vector3::new(-0.724568, 0.115813, -0.679402),
+            Vector3::new(0.181732, -0.960284, 0.211766),
+        ];
+        let bvh = self.bvh();
+        points
+            .par_iter()
+            .map(|point| {
+                let inside = directions
+                    .iter()
+                    .filter(|direction| {
+                        let ray = Ray::new(*point, **direction);
+                        let mut crossings = 0;
+                        bvh.traverse(&ray, |face, _| {
+                            if self.intersect_face(&ray, face).is_some() {
+                                crossings += 1;
+                            }
+                            false
+                        });
+                        crossings % 2 == 1
+                    })
+                    .count();
+                inside >= 2
+            })
+            .collect()
+    }
+}
+
+impl Scene {
+    /// Find the first mesh instance hit by a ray, resolving which node
+    /// in the scene graph the hit geometry was placed by.
+    ///
+    /// Parameters
+    /// ------------
+    /// ray
+    ///   The ray to cast in the frame of the scene.
+    ///
+    /// Returns
+    /// ------------
+    /// pick
+    ///   The closest hit along the ray or `None` if it missed.
+    pub fn pick(&self, ray: &Ray) -> Option<ScenePickResult> {
+        let bvh = self.bvh();
+        let scale = ray.direction.norm();
+        let mut best: Option<(usize, ScenePickResult)> = None;
+        bvh.tree.traverse(ray, |index, limit| {
+            let (geometry, instance) = &bvh.instances[index];
+            let Geometry::Mesh(mesh) = &self.geometry[*geometry] else {
+                return false;
+            };
+            // move the ray into the frame of the mesh
+            let Some(inverse) = instance.transform.try_inverse() else {
+                return false;
+            };
+            let local = Ray::new(
+                inverse.transform_point(&ray.origin),
+                inverse.transform_vector(&ray.direction),
+            );
+            let Some(mut pick) = mesh.pick(&local) else {
+                return false;
+            };
+            let point = instance.transform.transform_point(&pick.point);
+            pick.distance = (point - ray.origin).norm();
+            // ties go to the instance first in traversal order
+            let closer = match &best {
+                Some((other, hit)) => {
+                    pick.distance < hit.pick.distance
+                        || (pick.distance == hit.pick.distance && index < *other)
+                }
+                None => true,
+            };
+            if closer {
+                *limit = pick.distance / scale;
+                best = Some((
+                    index,
+                    ScenePickResult {
+                        node: instance.node,
+                        geometry: *geometry,
+                        pick,
+                        point,
+                    },
+                ));
+            }
+            false
+        });
+        best.map(|(_, hit)| hit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::{create_box, create_sphere};
+    use crate::scene::{SceneNode, SceneNodeKind};
+    use approx::assert_relative_eq;
+    use nalgebra::Matrix4;
+
+    #[test]
+    fn test_intersect_triangle() {
+        let a = Point3::new(0.0, 0.0, 0.0);
+        let b = Point3::new(1.0, 0.0, 0.0);
+        let c = Point3::new(0.0, 1.0, 0.0);
+
+        let ray = Ray::new(Point3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, -2.0));
+        let (t, bary) = intersect_triangle(&ray, &a, &b, &c).unwrap();
+        assert_relative_eq!(t, 0.5);
+        assert_relative_eq!(bary, Vector3::new(0.5, 0.25, 0.25));
+
+        // pointing away from the triangle
+        let ray = Ray::new(Point3::new(0.25, 0.25, 1.0), Vector3::new(0.0, 0.0, 1.0));
+        assert!(intersect_triangle(&ray, &a, &b, &c).is_none());
+        // outside of the triangle
+        let ray = Ray::new(Point3::new(1.0, 1.0, 1.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(intersect_triangle(&ray, &a, &b, &c).is_none());
+    }
+
+    #[test]
+    fn test_mesh_pick() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        let ray = Ray::new(Point3::new(0.1, 0.2, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        let pick = mesh.pick(&ray).unwrap();
+
+        // should hit the top of the box rather than the bottom
+        assert_relative_eq!(pick.point, Point3::new(0.1, 0.2, 0.5), epsilon = 1e-10);
+        assert_relative_eq!(pick.distance, 4.5, epsilon = 1e-10);
+        assert_relative_eq!(mesh.face_normals()[pick.face].z, 1.0, epsilon = 1e-10);
+        assert_relative_eq!(pick.barycentric.sum(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(mesh.vertices[pick.vertex(&mesh)].z, 0.5);
+
+        let miss = Ray::new(Point3::new(5.0, 5.0, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert!(mesh.pick(&miss).is_none());
+        assert!(Trimesh::default().pick(&ray).is_none());
+    }
+
+    #[test]
+    fn test_mesh_intersects() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        let origins = [
+            Point3::new(0.1, 0.2, 5.0),
+            Point3::new(5.0, 5.0, 5.0),
+            Point3::new(0.0, 0.1, 0.2),
+        ];
+        let directions = [
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+        ];
+
+        let any = mesh.intersects_any(&origins, &directions).unwrap();
+        assert_eq!(any, vec![true, false, true]);
+
+        let ids = mesh.intersects_id(&origins, &directions).unwrap();
+        assert!(ids[1].is_none());
+        let normals = mesh.face_normals();
+        assert_relative_eq!(normals[ids[0].unwrap()].z, 1.0, epsilon = 1e-10);
+        // the ray from inside hits the face it points at
+        assert_relative_eq!(normals[ids[2].unwrap()].x, 1.0, epsilon = 1e-10);
+
+        let hits = mesh.intersects_location(&origins, &directions).unwrap();
+        // the first ray goes through the top and bottom
+        let first: Vec<_> = hits.iter().filter(|h| h.0 == 0).collect();
+        assert_eq!(first.len(), 2);
+        assert_relative_eq!(
+            first[0].1.point,
+            Point3::new(0.1, 0.2, 0.5),
+            epsilon = 1e-10
+        );
+        assert_relative_eq!(
+            first[1].1.point,
+            Point3::new(0.1, 0.2, -0.5),
+            epsilon = 1e-10
+        );
+        assert_eq!(hits.iter().filter(|h| h.0 == 2).count(), 1);
+
+        assert!(mesh.intersects_any(&origins, &directions[..1]).is_err());
+
+        // the tree should agree with checking every face on a larger mesh
+        let data = include_bytes!("../../../test/data/fuze.obj");
+        let mesh = crate::exchange::load_mesh(data, crate::exchange::MeshFormat::OBJ).unwrap();
+        let (lower, upper) = mesh.bounds().unwrap();
+        let center = nalgebra::center(&lower, &upper);
+        let origins: Vec<_> = (0..200)
+            .map(|i| {
+                let angle = i as f64 * 0.1;
+                center + Vector3::new(angle.cos(), angle.sin(), (angle * 0.7).cos()) * 10.0
+            })
+            .collect();
+        let directions: Vec<_> = origins
+            .iter()
+            .enumerate()
+            .map(|(i, o)| center - o + Vector3::new(0.0, 0.0, (i as f64 * 0.37).sin() * 0.05))
+            .collect();
+        let ids = mesh.intersects_id(&origins, &directions).unwrap();
+        assert!(ids.iter().filter(|id| id.is_some()).count() > 100);
+        for ((origin, direction), id) in origins.iter().zip(&directions).zip(ids) {
+            let ray = Ray::new(*origin, *direction);
+            let brute = mesh
+                .faces
+                .iter()
+                .enumerate()
+                .filter_map(|(i, _)| mesh.intersect_face(&ray, i).map(|hit| (i, hit.0)))
+                .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+                .map(|hit| hit.0);
+            assert_eq!(id, brute);
+        }
+    }
+
+    #[test]
+    fn test_contains() {
+        let mesh = create_box(&[2.0, 2.0, 2.0]);
+        let points = [
+            Point3::new(0.0, 0.0, 0.0),
+            Point3::new(0.9, -0.9, 0.5),
+            // level with a vertex and an edge
+            Point3::new(0.0, 0.0, 1.0 - 1e-9),
+            Point3::new(1.5, 0.0, 0.0),
+            Point3::new(0.0, 0.0, -3.0),
+        ];
+        assert_eq!(mesh.contains(&points), vec![true, true, true, false, false]);
+        let sphere = create_sphere(1.0, [32, 16]).unwrap();
+        let inside = sphere.contains(&[Point3::new(0.0, 0.0, 0.9), Point3::new(0.7, 0.7, 0.0)]);
+        assert_eq!(inside, vec![true, true]);
+        assert_eq!(Trimesh::default().contains(&points[..1]), vec![false]);
+    }
+
+    #[test]
+    fn test_scene_pick() {
+        let mut scene = Scene::new();
+        let geometry = scene.add_geometry(Geometry::Mesh(Box::new(create_box(&[1.0, 1.0, 1.0]))));
+        let near = scene.graph.add_node(SceneNode {
+            name: "near".to_string(),
+            index: vec![geometry],
+            transform: Some(Matrix4::new_translation(&Vector3::new(0.0, 0.0, 2.0))),
+            ..Default::default()
+        });
+        let far = scene.graph.add_node(SceneNode {
+            name: "far".to_string(),
+            index: vec![geometry],
+            ..Default::default()
+        });
+        let root = scene.graph.add_node(SceneNode {
+            name: "root".to_string(),
+            kind: SceneNodeKind::CUSTOM,
+            children: vec![far, near],
+            ..Default::default()
+        });
+        scene.graph.set_root(root);
+
+        let ray = Ray::new(Point3::new(0.0, 0.0, 10.0), Vector3::new(0.0, 0.0, -1.0));
+        let pick = scene.pick(&ray).unwrap();
+        assert_eq!(pick.node, near);
+        assert_eq!(pick.geometry, geometry);
+        assert_relative_eq!(pick.point, Point3::new(0.0, 0.0, 2.5), epsilon = 1e-10);
+        assert_relative_eq!(pick.pick.point, Point3::new(0.0, 0.0, 0.5), epsilon = 1e-10);
+        assert_relative_eq!(pick.pick.distance, 7.5, epsilon = 1e-10);
+
+        assert!(Scene::new().pick(&ray).is_none());
+    }
+}
@@ -0,0 +1,335 @@
+use anyhow::{Result, anyhow};
+use nalgebra::{Matrix3, Matrix4, Matrix6, Point3, Rotation3, Vector3, Vector6};
+use rayon::prelude::*;
+
+use crate::mesh::Trimesh;
+use crate::transform::transform_points;
+
+/// How the distance between a moved source vertex and the
+/// surface of the target is measured by `icp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IcpMethod {
+    // the distance to the closest target vertex
+    #[default]
+    PointToPoint,
+    // the distance to the tangent plane of the closest target vertex,
+    // which converges in far fewer iterations on smooth surfaces
+    PointToPlane,
+}
+
+impl IcpMethod {
+    /// Parse a method from a name like `point_to_plane`.
+    pub fn from_string(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "point_to_point" | "point" => Ok(IcpMethod::PointToPoint),
+            "point_to_plane" | "plane" => Ok(IcpMethod::PointToPlane),
+            _ => Err(anyhow!("Unknown ICP method: {name}")),
+        }
+    }
+}
+
+/// Find the transform moving points onto corresponding points with the
+/// least squared distance between them, from the SVD of the covariance
+/// of the centered points as in the Kabsch and Umeyama algorithms.
+///
+/// Parameters
+/// ------------
+/// points_a
+///   The points to move.
+/// points_b
+///   The point each of `points_a` should be moved onto.
+/// reflection
+///   Allow the transform to mirror the points, otherwise the closest
+///   proper rotation is used.
+/// scale
+///   Allow the transform to uniformly scale the points.
+///
+/// Returns
+/// ------------
+/// procrustes
+///   The transform from `points_a` onto `points_b` and the mean distance
+///   between the moved points and their correspondences, or an error if
+///   there are no points or the lists have different lengths.
+pub fn procrustes(
+    points_a: &[Point3<f64>],
+    points_b: &[Point3<f64>],
+    reflection: bool,
+    scale: bool,
+) -> Result<(Matrix4<f64>, f64)> {
+    if points_a.len() != points_b.len() {
+        return Err(anyhow!(
+            "Procrustes requires corresponding points: {} != {}",
+            points_a.len(),
+            points_b.len()
+        ));
+    }
+    if points_a.is_empty() {
+        return Err(anyhow!("Procrustes requires at least one point"));
+    }
+    let count = points_a.len() as f64;
+    let center_a = points_a.iter().map(|p| p.coords).sum::<Vector3<f64>>() / count;
+    let center_b = points_b.iter().map(|p| p.coords).sum::<Vector3<f64>>() / count;
+    let covariance: Matrix3<f64> = points_a
+        .iter()
+        .zip(points_b.iter())
+        .map(|(p, q)| (p.coords - center_a) * (q.coords - center_b).transpose())
+        .sum();
+    let svd = covariance.svd(true, true);
+    let (Some(u), Some(v_t)) = (svd.u, svd.v_t) else {
+        return Err(anyhow!("Procrustes failed to decompose the covariance"));
+    };
+    // flip the least significant axis rather than return a reflection
+    let mut signs = Vector3::new(1.0, 1.0, 1.0);
+    if !reflection && (v_t.transpose() * u.transpose()).determinant() < 0.0 {
+        signs.z = -1.0;
+    }
+    let rotation = v_t.transpose() * Matrix3::from_diagonal(&signs) * u.transpose();
+    let factor = if scale {
+        let spread: f64 = points_a
+            .iter()
+            .map(|p| (p.coords - center_a).norm_squared())
+            .sum();
+        if spread > 0.0 {
+            svd.singular_values.dot(&signs) / spread
+        } else {
+            1.0
+        }
+    } else {
+        1.0
+    };
+
+    let linear = rotation * factor;
+    let mut transform = linear.to_homogeneous();
+    transform
+        .fixed_view_mut::<3, 1>(0, 3)
+        .copy_from(&(center_b - linear * center_a));
+    let cost = points_a
+        .iter()
+        .zip(points_b.iter())
+        .map(|(p, q)| (transform.transform_point(p) - q).norm())
+        .sum::<f64>()
+        / count;
+    Ok((transform, cost))
+}
+
+/// The small rigid motion reducing the distance of points to the
+/// tangent planes of their corresponding points the most, from the
+/// linearization of the rotation about its axis.
+fn fit_plane(
+    a: &[Point3<f64>],
+    b: &[Point3<f64>],
+    normals: &[Vector3<f64>],
+) -> Option<Matrix4<f64>> {
+    let mut lhs = Matrix6::zeros();
+    let mut rhs = Vector6::zeros();
+    for ((p, q), n) in a.iter().zip(b.iter()).zip(normals.iter()) {
+        let row = Vector6::from_iterator(p.coords.cross(n).iter().chain(n.iter()).copied());
+        lhs += row * row.transpose();
+        rhs -= row * (p - q).dot(n);
+    }
+    let solution = lhs.cholesky()?.solve(&rhs);
+    let rotation = Rotation3::new(solution.fixed_rows::<3>(0).into_owned());
+    let mut transform = rotation.to_homogeneous();
+    transform
+        .fixed_view_mut::<3, 1>(0, 3)
+        .copy_from(&solution.fixed_rows::<3>(3));
+    Some(transform)
+}
+
+/// Align a source mesh to a target mesh with the iterative closest point
+/// algorithm. Every iteration pairs each moved source vertex with the
+/// closest target vertex using the target's `vertex_tree` and moves the
+/// source to reduce the distance between the pairs, which converges to
+/// the nearest local minimum so the initial transform should already be
+/// roughly right.
+///
+/// Parameters
+/// ------------
+/// source
+///   The mesh to move.
+/// target
+///   The mesh to move the source onto.
+/// initial
+///   The transform to start from.
+/// max_iterations
+///   The most times the pairs are updated and the source moved.
+/// tolerance
+///   Stop when the residual changes less than this between iterations.
+/// method
+///   Whether to minimize the distance to the paired points or to
+///   their tangent planes from the target's vertex normals.
+///
+/// Returns
+/// ------------
+/// registration
+///   The transform from the source onto the target with the lowest
+///   residual found, and the residual which is the mean distance of the
+///   moved source vertices as measured by the method, or an error if
+///   either mesh has no vertices.
+pub fn icp(
+    source: &Trimesh,
+    target: &Trimesh,
+    initial: &Matrix4<f64>,
+    max_iterations: usize,
+    tolerance: f64,
+    method: IcpMethod,
+) -> Result<(Matrix4<f64>, f64)> {
+    if source.vertices.is_empty() || target.vertices.is_empty() {
+        return Err(anyhow!("ICP requires both meshes to have vertices"));
+    }
+    let tree = target.vertex_tree();
+    let normals = match method {
+        IcpMethod::PointToPoint => Vec::new(),
+        IcpMethod::PointToPlane => target.vertex_normals(),
+    };
+
+    let rigid = |a: &[Point3<f64>], b: &[Point3<f64>]| {
+        procrustes(a, b, false, false).ok().map(|(step, _)| step)
+    };
+    let mut transform = *initial;
+    let mut best = (transform, f64::INFINITY);
+    let mut previous = f64::INFINITY;
+    for iteration in 0..=max_iterations {
+        let moved = transform_points(&source.vertices, &transform);
+        let pairs: Vec<usize> = moved.par_iter().map(|p| tree.nearest(p, 1)[0].0).collect();
+        let paired: Vec<Point3<f64>> = pairs.iter().map(|i| target.vertices[*i]).collect();
+        let residual = match method {
+            IcpMethod::PointToPoint => moved
+                .iter()
+                .zip(paired.iter())
+                .map(|(p, q)| (p - q).norm())
+                .sum::<f64>(),
+            IcpMethod::PointToPlane => moved
+                .iter()
+                .zip(pairs.iter())
+                .map(|(p, i)| (p - target.vertices[*i]).dot(&normals[*i]).abs())
+                .sum::<f64>(),
+        } / moved.len() as f64;
+        if residual < best.1 {
+            best = (transform, residual);
+        }
+        if iteration == max_iterations || (previous - residual).abs() <= tolerance {
+            break;
+        }
+        previous = residual;
+
+        let step = match method {
+            IcpMethod::PointToPoint => rigid(&moved, &paired),
+            IcpMethod::PointToPlane => {
+                let paired_normals: Vec<Vector3<f64>> = pairs.iter().map(|i| normals[*i]).collect();
+                // too few distinct planes to pin down the motion
+                fit_plane(&moved, &paired, &paired_normals).or_else(|| rigid(&moved, &paired))
+            }
+        };
+        let Some(step) = step else {
+            break;
+        };
+        transform = step * transform;
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_sphere;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_procrustes() {
+        let points: Vec<Point3<f64>> = (0..20)
+            .map(|i| {
+                let i = i as f64;
+                Point3::new(i.sin(), (i * 1.7).cos() * 2.0, i * 0.1)
+            })
+            .collect();
+        let mut similar = Rotation3::from_euler_angles(0.4, 1.2, -0.3).to_homogeneous() * 2.5;
+        similar[(3, 3)] = 1.0;
+        similar
+            .fixed_view_mut::<3, 1>(0, 3)
+            .copy_from(&Vector3::new(1.0, 2.0, -3.0));
+        let moved = transform_points(&points, &similar);
+
+        let (transform, cost) = procrustes(&points, &moved, false, true).unwrap();
+        assert_relative_eq!(transform, similar, epsilon = 1e-9);
+        assert!(cost < 1e-9);
+        // without scale the best rotation leaves a residual
+        let (transform, cost) = procrustes(&points, &moved, false, false).unwrap();
+        assert_relative_eq!(
+            transform.fixed_view::<3, 3>(0, 0).determinant(),
+            1.0,
+            epsilon = 1e-9
+        );
+        assert!(cost > 0.1);
+
+        // a mirror image needs a reflection to match exactly
+        let mut mirror = Matrix4::identity();
+        mirror[(0, 0)] = -1.0;
+        let mirrored = transform_points(&points, &mirror);
+        let (transform, cost) = procrustes(&points, &mirrored, true, false).unwrap();
+        assert_relative_eq!(transform, mirror, epsilon = 1e-9);
+        assert!(cost < 1e-9);
+        let (transform, cost) = procrustes(&points, &mirrored, false, false).unwrap();
+        assert!(transform.fixed_view::<3, 3>(0, 0).determinant() > 0.0);
+        assert!(cost > 0.1);
+
+        assert!(procrustes(&points, &moved[1..], false, false).is_err());
+        assert!(procrustes(&[], &[], true, true).is_err());
+    }
+
+    #[test]
+    fn test_icp() {
+        // an ellipsoid has no symmetry to slide along
+        let mut target = create_sphere(1.0, [32, 16]).unwrap();
+        target.vertices = target
+            .vertices
+            .iter()
+            .map(|v| Point3::new(v.x, v.y * 2.0, v.z * 3.0))
+            .collect();
+        let target = Trimesh::new(target.vertices, target.faces, None, None).unwrap();
+        let mut moved = Rotation3::from_euler_angles(0.1, -0.05, 0.08).to_homogeneous();
+        moved
+            .fixed_view_mut::<3, 1>(0, 3)
+            .copy_from(&Vector3::new(0.05, -0.1, 0.02));
+        let source = target.apply_transform(&moved).unwrap();
+        let expected = moved.try_inverse().unwrap();
+
+        for method in [IcpMethod::PointToPoint, IcpMethod::PointToPlane] {
+            let (transform, residual) =
+                icp(&source, &target, &Matrix4::identity(), 100, 1e-12, method).unwrap();
+            assert_relative_eq!(transform, expected, epsilon = 1e-6);
+            assert!(residual < 1e-6);
+        }
+
+        // starting at the answer stays there
+        let (transform, residual) = icp(
+            &source,
+            &target,
+            &expected,
+            10,
+            1e-12,
+            IcpMethod::PointToPoint,
+        )
+        .unwrap();
+        assert_relative_eq!(transform, expected, epsilon = 1e-9);
+        assert!(residual < 1e-9);
+
+        assert_eq!(
+            IcpMethod::from_string("Point_To_Plane").unwrap(),
+            IcpMethod::PointToPlane
+        );
+        assert!(IcpMethod::from_string("nope").is_err());
+        assert!(
+            icp(
+                &Trimesh::default(),
+                &target,
+                &expected,
+                10,
+                0.0,
+                IcpMethod::PointToPoint
+            )
+            .is_err()
+        );
+    }
+}
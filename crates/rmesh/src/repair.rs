@@ -0,0 +1,296 @@
+use std::collections::VecDeque;
+
+use ahash::AHashMap;
+use anyhow::{Result, anyhow};
+use nalgebra::Vector3;
+
+use crate::creation::{Plane, Triangulator, triangulate_fan};
+use crate::facets::boundary_loops;
+use crate::mesh::Trimesh;
+
+/// Does a face traverse the edge from `a` to `b` in that direction.
+fn has_edge(face: &(usize, usize, usize), a: usize, b: usize) -> bool {
+    [(face.0, face.1), (face.1, face.2), (face.2, face.0)].contains(&(a, b))
+}
+
+impl Trimesh {
+    /// The faces which share an edge with every face.
+    fn face_neighbors(&self) -> Vec<Vec<usize>> {
+        let mut neighbors: Vec<Vec<usize>> = vec![Vec::new(); self.faces.len()];
+        for (a, b) in self.face_adjacency() {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+        neighbors
+    }
+
+    /// The faces of each connected component, where faces are
+    /// connected if they share an edge, as a component index per face.
+    fn face_components(&self) -> (Vec<usize>, usize) {
+        let neighbors = self.face_neighbors();
+        let mut component = vec![usize::MAX; self.faces.len()];
+        let mut count = 0;
+        for seed in 0..self.faces.len() {
+            if component[seed] != usize::MAX {
+                continue;
+            }
+            component[seed] = count;
+            let mut queue = VecDeque::from([seed]);
+            while let Some(face) = queue.pop_front() {
+                for other in neighbors[face].iter() {
+                    if component[*other] == usize::MAX {
+                        component[*other] = count;
+                        queue.push_back(*other);
+                    }
+                }
+            }
+            count += 1;
+        }
+        (component, count)
+    }
+
+    /// Remove faces which are too thin to have a meaningful normal,
+    /// including faces which reference the same vertex more than once.
+    ///
+    /// Parameters
+    /// ------------
+    /// epsilon
+    ///   Faces whose smallest height is at most this are removed.
+    ///
+    /// Returns
+    /// ------------
+    /// repaired
+    ///   A new mesh with the face attributes of the kept faces.
+    pub fn remove_degenerate_faces(&self, epsilon: f64) -> Self {
+        let kept: Vec<usize> = self
+            .faces
+            .iter()
+            .zip(self.faces_cross())
+            .enumerate()
+            .filter(|(_, (f, cross))| {
+                let (a, b, c) = (self.vertices[f.0], self.vertices[f.1], self.vertices[f.2]);
+                let longest = (b - a).norm().max((c - b).norm()).max((a - c).norm());
+                // twice the area over the longest edge is the smallest height
+                f.0 != f.1 && f.1 != f.2 && f.2 != f.0 && cross.norm() > epsilon * longest
+            })
+            .map(|(i, _)| i)
+            .collect();
+        self.with_faces(kept.iter().map(|i| self.faces[*i]).collect(), &kept)
+    }
+
+    /// Flip faces so every pair of faces sharing an edge traverses it in
+    /// opposite directions, which is what makes normals consistent. Each
+    /// connected component keeps the winding of its first face.
+    ///
+    /// Returns
+    /// ------------
+    /// repaired
+    ///   A new mesh with consistently wound faces.
+    pub fn fix_winding(&self) -> Self {
+        let neighbors = self.face_neighbors();
+
+        let mut faces = self.faces.clone();
+        let mut visited = vec![false; faces.len()];
+        for seed in 0..faces.len() {
+            if visited[seed] {
+                continue;
+            }
+            visited[seed] = true;
+            let mut queue = VecDeque::from([seed]);
+            while let Some(current) = queue.pop_front() {
+                let face = faces[current];
+                for other in neighbors[current].iter().copied() {
+                    if visited[other] {
+                        continue;
+                    }
+                    visited[other] = true;
+                    // the neighbor should run the shared edge backwards
+                    let shared = [(face.0, face.1), (face.1, face.2), (face.2, face.0)]
+                        .into_iter()
+                        .find(|(a, b)| {
+                            has_edge(&faces[other], *a, *b) || has_edge(&faces[other], *b, *a)
+                        });
+                    if let Some((a, b)) = shared
+                        && has_edge(&faces[other], a, b)
+                    {
+                        let f = faces[other];
+                        faces[other] = (f.0, f.2, f.1);
+                    }
+                    queue.push_back(other);
+                }
+            }
+        }
+        let source: Vec<usize> = (0..faces.len()).collect();
+        self.with_faces(faces, &source)
+    }
+
+    /// Make the winding of faces consistent and then flip any connected
+    /// component which encloses a negative volume so normals point out.
+    /// For an open component the volume is taken to its centroid which
+    /// usually but not always points the normals away from it.
+    ///
+    /// Returns
+    /// ------------
+    /// repaired
+    ///   A new mesh with consistent outward facing normals.
+    pub fn fix_normals(&self) -> Self {
+        let mesh = self.fix_winding();
+        let (component, count) = mesh.face_components();
+
+        // the signed volume of each component to its centroid
+        let mut centroid = vec![Vector3::zeros(); count];
+        let mut total = vec![0.0; count];
+        for (face, c) in mesh.faces.iter().zip(component.iter()) {
+            let (a, b, d) = (
+                mesh.vertices[face.0],
+                mesh.vertices[face.1],
+                mesh.vertices[face.2],
+            );
+            centroid[*c] += (a.coords + b.coords + d.coords) / 3.0;
+            total[*c] += 1.0;
+        }
+        let mut volume = vec![0.0; count];
+        for (face, c) in mesh.faces.iter().zip(component.iter()) {
+            let center = centroid[*c] / total[*c];
+            let a = mesh.vertices[face.0].coords - center;
+            let b = mesh.vertices[face.1].coords - center;
+            let d = mesh.vertices[face.2].coords - center;
+            volume[*c] += a.dot(&b.cross(&d));
+        }
+        let faces = mesh
+            .faces
+            .iter()
+            .zip(component.iter())
+            .map(|(f, c)| {
+                if volume[*c] < 0.0 {
+                    (f.0, f.2, f.1)
+                } else {
+                    *f
+                }
+            })
+            .collect();
+        let source: Vec<usize> = (0..mesh.faces.len()).collect();
+        mesh.with_faces(faces, &source)
+    }
+
+    /// Fill holes in the mesh by triangulating the loops of boundary edges
+    /// around them, where each new face takes its face attributes from the
+    /// face next to the hole. The winding should be consistent first,
+    /// which `fix_winding` can do.
+    ///
+    /// Parameters
+    /// ------------
+    /// max_edges
+    ///   Only fill holes with at most this many edges, which leaves the
+    ///   intended openings of a mesh alone.
+    ///
+    /// Returns
+    /// ------------
+    /// repaired
+    ///   A new mesh or an error if the boundary isn't a set of simple loops.
+    pub fn fill_holes(&self, max_edges: usize) -> Result<Self> {
+        let loops = boundary_loops(&self.faces)
+            .ok_or(anyhow!("Mesh boundary isn't a set of simple loops"))?;
+        let mut owner: AHashMap<(usize, usize), usize> = AHashMap::new();
+        for (index, f) in self.faces.iter().enumerate() {
+            for edge in [(f.0, f.1), (f.1, f.2), (f.2, f.0)] {
+                owner.insert(edge, index);
+            }
+        }
+
+        let mut faces = self.faces.clone();
+        let mut source: Vec<usize> = (0..faces.len()).collect();
+        let mut triangulator = Triangulator::new();
+        for chain in loops.iter().filter(|c| c.len() <= max_edges) {
+            let points: Vec<_> = chain.iter().map(|i| self.vertices[*i]).collect();
+            let local: Vec<usize> = (0..chain.len()).collect();
+            // holes in scans aren't flat so fit the plane by least squares
+            let mut triangles = match Plane::from_points(&points, false) {
+                Ok(plane) => triangulator.trianglate_2d(&local, &[], &plane.to_2d(&points)),
+                Err(_) => Vec::new(),
+            };
+            if triangles.len() != chain.len() - 2 {
+                triangles = triangulate_fan(&local);
+            }
+            let mut triangles: Vec<(usize, usize, usize)> = triangles
+                .into_iter()
+                .map(|(a, b, c)| (chain[a], chain[b], chain[c]))
+                .collect();
+            // the new faces have to run each boundary edge backwards
+            if triangles.iter().any(|t| has_edge(t, chain[0], chain[1])) {
+                triangles = triangles.into_iter().map(|(a, b, c)| (a, c, b)).collect();
+            }
+            let neighbor = owner[&(chain[0], chain[1])];
+            source.extend(std::iter::repeat_n(neighbor, triangles.len()));
+            faces.extend(triangles);
+        }
+        Ok(self.with_faces(faces, &source))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+    use nalgebra::{Point3, Vector4};
+
+    #[test]
+    fn test_repair() {
+        let mesh = create_box(&[1.0, 2.0, 3.0]);
+        let count = mesh.faces.len();
+        let mut broken = Trimesh {
+            vertices: mesh.vertices.clone(),
+            // remove one side and flip some of the others
+            faces: mesh.faces[2..]
+                .iter()
+                .enumerate()
+                .map(|(i, f)| if i % 3 == 0 { (f.0, f.2, f.1) } else { *f })
+                .collect(),
+            ..Default::default()
+        };
+        broken.attributes_face.colors.push(
+            (0..count - 2)
+                .map(|i| Vector4::new(i as u8, 0, 0, 255))
+                .collect(),
+        );
+
+        let fixed = broken.fix_normals();
+        // every edge is used once in each direction except the hole
+        let boundary = boundary_loops(&fixed.faces).unwrap();
+        assert_eq!(boundary.len(), 1);
+        assert_eq!(boundary[0].len(), 4);
+
+        // a hole larger than the limit is left alone
+        assert_eq!(fixed.fill_holes(3).unwrap().faces.len(), count - 2);
+        let filled = fixed.fill_holes(4).unwrap();
+        assert_eq!(filled.faces.len(), count);
+        assert!(boundary_loops(&filled.faces).unwrap().is_empty());
+        assert_relative_eq!(filled.volume(), 6.0, epsilon = 1e-10);
+        assert_relative_eq!(filled.area(), mesh.area(), epsilon = 1e-10);
+        // the new faces take the colors of a face next to the hole
+        let colors = &filled.attributes_face.colors[0];
+        assert_eq!(colors.len(), count);
+        assert_eq!(colors[..count - 2], broken.attributes_face.colors[0][..]);
+
+        // the whole mesh turned inside out is flipped back
+        let inverted = filled.with_faces(
+            filled.faces.iter().map(|f| (f.0, f.2, f.1)).collect(),
+            &(0..count).collect::<Vec<_>>(),
+        );
+        assert_relative_eq!(inverted.fix_normals().volume(), 6.0, epsilon = 1e-10);
+
+        // a sliver and a collapsed face are removed
+        let mut vertices = mesh.vertices.clone();
+        vertices.push(Point3::new(10.0, 0.0, 0.0));
+        vertices.push(Point3::new(20.0, 1e-12, 0.0));
+        vertices.push(Point3::new(30.0, 0.0, 0.0));
+        let mut faces = mesh.faces.clone();
+        faces.push((8, 9, 10));
+        faces.push((1, 1, 2));
+        let dirty = Trimesh::new(vertices, faces, None, None).unwrap();
+        let clean = dirty.remove_degenerate_faces(1e-8);
+        assert_eq!(clean.faces, mesh.faces);
+    }
+}
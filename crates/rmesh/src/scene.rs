@@ -1,6 +1,77 @@
-use nalgebra::Matrix4;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 
+use anyhow::{Result, anyhow};
+use nalgebra::{Matrix4, Point3, Rotation3, Vector3};
+
+use crate::bvh::{Bounds, Bvh};
 use crate::geometry::Geometry;
+use crate::mesh::Trimesh;
+use crate::transform::transform_points;
+use crate::util::concatenate;
+
+/// A unit of length the coordinates of a scene are in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Micron,
+    Millimeter,
+    Centimeter,
+    Meter,
+    Inch,
+    Foot,
+}
+
+impl Unit {
+    /// The length of one of this unit in meters.
+    pub fn meters(&self) -> f64 {
+        match self {
+            Unit::Micron => 1e-6,
+            Unit::Millimeter => 1e-3,
+            Unit::Centimeter => 1e-2,
+            Unit::Meter => 1.0,
+            Unit::Inch => 0.0254,
+            Unit::Foot => 0.3048,
+        }
+    }
+
+    /// Parse the name of a unit as file formats write it, such
+    /// as the `unit` attribute of a 3MF model.
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "micron" | "micrometer" | "um" => Ok(Unit::Micron),
+            "millimeter" | "millimetre" | "mm" => Ok(Unit::Millimeter),
+            "centimeter" | "centimetre" | "cm" => Ok(Unit::Centimeter),
+            "meter" | "metre" | "m" => Ok(Unit::Meter),
+            "inch" | "in" => Ok(Unit::Inch),
+            "foot" | "feet" | "ft" => Ok(Unit::Foot),
+            _ => Err(anyhow!("Unknown unit: `{name}`")),
+        }
+    }
+}
+
+/// The axis of a scene which points up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Axis {
+    X,
+    Y,
+    #[default]
+    Z,
+}
+
+impl Axis {
+    /// The unit vector along the axis.
+    pub fn vector(&self) -> Vector3<f64> {
+        match self {
+            Axis::X => Vector3::x(),
+            Axis::Y => Vector3::y(),
+            Axis::Z => Vector3::z(),
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct Light {
@@ -15,6 +86,9 @@ pub enum SceneNodeKind {
     CAMERA = 2,
     LIGHT = 3,
     CUSTOM = 4,
+    // a named coordinate system such as a datum from a CAD
+    // file, which has a transform but nothing to render
+    FRAME = 5,
 }
 
 #[derive(Default)]
@@ -37,13 +111,85 @@ pub struct SceneNode {
     pub index: Vec<usize>,
 }
 
-#[derive(Default)]
 pub struct SceneGraph {
-    // The root node index in the nodes vector
-    pub root: usize,
+    // The root node index in the nodes vector, set with `set_root`
+    root: usize,
 
-    // A flat list of nodes in the scene.
-    pub nodes: Vec<SceneNode>,
+    // A flat list of nodes in the scene, edited with `nodes_mut`
+    nodes: Vec<SceneNode>,
+
+    // values derived from the nodes which are cleared by every method
+    // that edits the graph
+    cache: RwLock<GraphCache>,
+
+    // changed every time the cache is cleared, so values derived
+    // from the graph elsewhere can tell it has changed
+    generation: u64,
+}
+
+/// Every graph draws its generations from here, so a graph replacing
+/// another one never starts at a generation the old one has used.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// A generation no graph has had before.
+fn next_generation() -> u64 {
+    GENERATION.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+impl Default for SceneGraph {
+    fn default() -> Self {
+        SceneGraph {
+            root: 0,
+            nodes: Vec::new(),
+            cache: RwLock::default(),
+            generation: next_generation(),
+        }
+    }
+}
+
+/// Mutable access to the nodes of a graph which clears the values
+/// derived from them once it is dropped.
+pub struct NodesMut<'a> {
+    graph: &'a mut SceneGraph,
+}
+
+impl Deref for NodesMut<'_> {
+    type Target = Vec<SceneNode>;
+
+    fn deref(&self) -> &Vec<SceneNode> {
+        &self.graph.nodes
+    }
+}
+
+impl DerefMut for NodesMut<'_> {
+    fn deref_mut(&mut self) -> &mut Vec<SceneNode> {
+        &mut self.graph.nodes
+    }
+}
+
+impl Drop for NodesMut<'_> {
+    fn drop(&mut self) {
+        self.graph.clear_cache();
+    }
+}
+
+/// Values derived from the nodes of a `SceneGraph`.
+#[derive(Default)]
+struct GraphCache {
+    // the transform from the root to the first placement of each node
+    world: Option<Vec<Option<Matrix4<f64>>>>,
+    // the nodes which list each node as a child
+    parents: Option<Vec<Vec<usize>>>,
+}
+
+/// A single placement of a node found while traversing the graph.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    // the index of the node in the SceneGraph's nodes vector
+    pub node: usize,
+
+    // the transform from the root of the graph to this node
+    pub transform: Matrix4<f64>,
 }
 
 impl SceneGraph {
@@ -54,8 +200,233 @@ impl SceneGraph {
     pub fn add_node(&mut self, node: SceneNode) -> usize {
         let index = self.nodes.len();
         self.nodes.push(node);
+        self.clear_cache();
+        index
+    }
+
+    /// The index of the root node in `nodes`.
+    pub fn root(&self) -> usize {
+        self.root
+    }
+
+    /// Make another node the root of the graph.
+    pub fn set_root(&mut self, root: usize) {
+        self.root = root;
+        self.clear_cache();
+    }
+
+    /// A flat list of every node in the graph.
+    pub fn nodes(&self) -> &[SceneNode] {
+        &self.nodes
+    }
+
+    /// Edit the nodes in place, discarding the world transforms and
+    /// parents computed from them once the returned guard is dropped.
+    pub fn nodes_mut(&mut self) -> NodesMut<'_> {
+        NodesMut { graph: self }
+    }
+
+    /// Forget the world transforms and parents computed for the graph.
+    fn clear_cache(&mut self) {
+        *self.cache.get_mut().unwrap() = GraphCache::default();
+        self.generation = next_generation();
+    }
+
+    /// Make one node a child of another, refusing edges which would
+    /// make a node its own ancestor.
+    ///
+    /// Parameters
+    /// ------------
+    /// parent
+    ///   The index of the node to add the child to.
+    /// child
+    ///   The index of the node to place under the parent.
+    ///
+    /// Returns
+    /// ------------
+    /// added
+    ///   An error if either node doesn't exist or the edge would
+    ///   create a cycle.
+    pub fn add_edge(&mut self, parent: usize, child: usize) -> Result<()> {
+        if parent >= self.nodes.len() || child >= self.nodes.len() {
+            return Err(anyhow!(
+                "Edge {parent} -> {child} is out of range for {} nodes",
+                self.nodes.len()
+            ));
+        }
+        // the parent can't be reachable from the child
+        let mut stack = vec![child];
+        let mut visited = vec![false; self.nodes.len()];
+        while let Some(node) = stack.pop() {
+            if node == parent {
+                return Err(anyhow!("Edge {parent} -> {child} would create a cycle"));
+            }
+            if !std::mem::replace(&mut visited[node], true) {
+                stack.extend(
+                    self.nodes[node]
+                        .children
+                        .iter()
+                        .filter(|c| **c < self.nodes.len()),
+                );
+            }
+        }
+        self.nodes[parent].children.push(child);
+        self.clear_cache();
+        Ok(())
+    }
+
+    /// Replace the transform from a node's parent to the node.
+    pub fn set_transform(&mut self, node: usize, transform: Option<Matrix4<f64>>) {
+        if let Some(node) = self.nodes.get_mut(node) {
+            node.transform = transform;
+        }
+        self.clear_cache();
+    }
+
+    /// The index of the first node with a name.
+    pub fn node_by_name(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|n| n.name == name)
+    }
+
+    /// The nodes which list a node as a child, which is more than
+    /// one node for geometry instanced in several places.
+    pub fn parents(&self, node: usize) -> Vec<usize> {
+        if let Some(parents) = &self.cache.read().unwrap().parents {
+            return parents.get(node).cloned().unwrap_or_default();
+        }
+        let mut parents = vec![Vec::new(); self.nodes.len()];
+        for (index, n) in self.nodes.iter().enumerate() {
+            for child in n.children.iter().filter(|c| **c < self.nodes.len()) {
+                if !parents[*child].contains(&index) {
+                    parents[*child].push(index);
+                }
+            }
+        }
+        let result = parents.get(node).cloned().unwrap_or_default();
+        self.cache.write().unwrap().parents = Some(parents);
+        result
+    }
+
+    /// The transform from the root to a node, computed for every node in
+    /// one traversal and cached until the graph is edited. A node placed
+    /// in several places takes its first placement in traversal order.
+    ///
+    /// Parameters
+    /// ------------
+    /// node
+    ///   The index of the node.
+    ///
+    /// Returns
+    /// ------------
+    /// transform
+    ///   The transform from the root or `None` if the node
+    ///   isn't reachable from the root.
+    pub fn world_transform(&self, node: usize) -> Option<Matrix4<f64>> {
+        if let Some(world) = &self.cache.read().unwrap().world {
+            return world.get(node).copied().flatten();
+        }
+        let mut world = vec![None; self.nodes.len()];
+        for instance in self.traverse() {
+            world[instance.node].get_or_insert(instance.transform);
+        }
+        let result = world.get(node).copied().flatten();
+        self.cache.write().unwrap().world = Some(world);
+        result
+    }
+
+    /// Add a named coordinate frame as a child of another node.
+    ///
+    /// Parameters
+    /// ------------
+    /// parent
+    ///   The index of the node the frame is relative to.
+    /// name
+    ///   The name other code will use to look the frame up.
+    /// transform
+    ///   The transform from the parent to the frame.
+    ///
+    /// Returns
+    /// ------------
+    /// index
+    ///   The index of the new node.
+    pub fn add_frame(&mut self, parent: usize, name: &str, transform: Matrix4<f64>) -> usize {
+        let index = self.add_node(SceneNode {
+            name: name.to_string(),
+            transform: Some(transform),
+            kind: SceneNodeKind::FRAME,
+            ..Default::default()
+        });
+        if let Some(parent) = self.nodes.get_mut(parent) {
+            parent.children.push(index);
+        }
+        self.clear_cache();
         index
     }
+
+    /// Every placement of a frame node in traversal order.
+    pub fn frames(&self) -> Vec<Instance> {
+        self.traverse()
+            .into_iter()
+            .filter(|i| matches!(self.nodes[i.node].kind, SceneNodeKind::FRAME))
+            .collect()
+    }
+
+    /// The transform from the root to the first frame with a name.
+    pub fn frame(&self, name: &str) -> Option<Matrix4<f64>> {
+        self.frames()
+            .into_iter()
+            .find(|i| self.nodes[i.node].name == name)
+            .map(|i| i.transform)
+    }
+
+    /// Traverse the graph depth-first from the root node, producing an
+    /// instance for every path to a node. Children are visited sorted by
+    /// name and then by index so the result is the same regardless of
+    /// the order nodes were inserted into the graph. A node referenced by
+    /// multiple parents is instanced once per parent and edges which
+    /// would create a cycle are skipped.
+    ///
+    /// Returns
+    /// ------------
+    /// instances
+    ///   Every reachable node with its transform from the root.
+    pub fn traverse(&self) -> Vec<Instance> {
+        let mut instances = Vec::new();
+        if self.root >= self.nodes.len() {
+            return instances;
+        }
+
+        // the stack holds the node, its parent transform and
+        // the nodes on the path to it to detect cycles
+        let mut stack = vec![(self.root, Matrix4::identity(), vec![])];
+        while let Some((index, parent, path)) = stack.pop() {
+            let node = &self.nodes[index];
+            let transform = match &node.transform {
+                Some(local) => parent * local,
+                None => parent,
+            };
+            instances.push(Instance {
+                node: index,
+                transform,
+            });
+
+            let mut path = path;
+            path.push(index);
+            let mut children: Vec<usize> = node
+                .children
+                .iter()
+                .copied()
+                .filter(|c| *c < self.nodes.len() && !path.contains(c))
+                .collect();
+            children.sort_by(|a, b| self.nodes[*a].name.cmp(&self.nodes[*b].name).then(a.cmp(b)));
+            // push in reverse so the first sorted child is visited first
+            for child in children.into_iter().rev() {
+                stack.push((child, transform, path.clone()));
+            }
+        }
+
+        instances
+    }
 }
 
 #[derive(Default)]
@@ -70,6 +441,89 @@ pub struct Scene {
 
     // The node index of the camera.
     pub camera: usize,
+
+    // the unit of the coordinates if the source recorded one
+    pub units: Option<Unit>,
+
+    // the axis which points up
+    pub up: Axis,
+
+    // every transform `normalized` has applied to the scene
+    pub normalization: Option<Matrix4<f64>>,
+
+    // values derived from the geometry and the graph which are cleared
+    // by every method that edits the geometry, or by `clear_cache`
+    cache: RwLock<SceneCache>,
+}
+
+/// Values derived from the geometry and graph of a `Scene`.
+#[derive(Default)]
+struct SceneCache {
+    // the box of each geometry in its own frame, or None if it's empty
+    bounds: Option<Vec<Option<Bounds>>>,
+    // the last tree built, which is kept after the scene changes
+    // so it can be refit if the same geometry is still placed
+    bvh: Option<Arc<SceneBvh>>,
+    // the generation of the graph `bvh` is up to date with, or
+    // None if the geometry has changed since it was built
+    current: Option<u64>,
+}
+
+/// A bounding volume hierarchy over every placement of geometry
+/// in a scene, for picking, culling and collision queries.
+pub struct SceneBvh {
+    // the geometry index and transform of every placement of
+    // non-empty geometry in traversal order
+    pub instances: Vec<(usize, Instance)>,
+
+    // the box of each instance in the frame of the scene
+    pub bounds: Vec<Bounds>,
+
+    // the tree referencing instances by their index
+    pub tree: Bvh,
+}
+
+/// Do two boxes overlap or touch.
+fn overlaps(a: &Bounds, b: &Bounds) -> bool {
+    (0..3).all(|i| a.0[i] <= b.1[i] && b.0[i] <= a.1[i])
+}
+
+/// The box containing a box after it has been transformed.
+fn transform_bounds(bounds: &Bounds, transform: &Matrix4<f64>) -> Bounds {
+    let (lower, upper) = bounds;
+    let corners: [Point3<f64>; 8] = std::array::from_fn(|i| {
+        let corner = Point3::new(
+            if i & 1 == 0 { lower.x } else { upper.x },
+            if i & 2 == 0 { lower.y } else { upper.y },
+            if i & 4 == 0 { lower.z } else { upper.z },
+        );
+        transform.transform_point(&corner)
+    });
+    corners
+        .iter()
+        .fold((corners[0], corners[0]), |(l, u), c| (l.inf(c), u.sup(c)))
+}
+
+/// A hash of the vertices and faces of a mesh, which is the same for
+/// meshes that compare equal so it can bucket candidate duplicates.
+fn mesh_key(mesh: &Trimesh) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    // adding zero turns negative zero positive so it hashes like zero
+    for vertex in mesh.vertices.iter() {
+        vertex.map(|c| (c + 0.0).to_bits()).hash(&mut hasher);
+    }
+    mesh.faces.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Are two meshes the same geometry with the same attributes and
+/// materials, so one can be placed in place of the other.
+fn same_mesh(a: &Trimesh, b: &Trimesh) -> bool {
+    a.vertices == b.vertices
+        && a.faces == b.faces
+        && a.attributes_vertex == b.attributes_vertex
+        && a.attributes_face == b.attributes_face
+        && a.materials == b.materials
 }
 
 impl Scene {
@@ -80,8 +534,383 @@ impl Scene {
     pub fn add_geometry(&mut self, geom: Geometry) -> usize {
         let index = self.geometry.len();
         self.geometry.push(geom);
+        self.clear_cache();
         index
     }
+
+    /// Forget the bounds of the geometry and mark the tree over it as
+    /// out of date, which is needed after editing `geometry` directly.
+    /// Edits to the graph are picked up without it.
+    pub fn clear_cache(&mut self) {
+        let cache = self.cache.get_mut().unwrap();
+        cache.bounds = None;
+        cache.current = None;
+    }
+
+    /// The box of each geometry in its own frame, which is computed
+    /// once and kept until the geometry is edited.
+    fn geometry_bounds(&self) -> Vec<Option<Bounds>> {
+        if let Some(bounds) = &self.cache.read().unwrap().bounds {
+            return bounds.clone();
+        }
+        let bounds: Vec<Option<Bounds>> = self.geometry.iter().map(|g| g.bounds()).collect();
+        self.cache.write().unwrap().bounds = Some(bounds.clone());
+        bounds
+    }
+
+    /// Flatten the scene graph into a deterministic list of geometry
+    /// instances, referencing geometry by index rather than copying it.
+    ///
+    /// Returns
+    /// ------------
+    /// instances
+    ///   The geometry index and transform from the root for every
+    ///   placement of geometry in the scene, in traversal order.
+    pub fn flatten(&self) -> Vec<(usize, Instance)> {
+        self.graph
+            .traverse()
+            .into_iter()
+            .filter(|instance| {
+                matches!(
+                    self.graph.nodes[instance.node].kind,
+                    SceneNodeKind::GEOMETRY
+                )
+            })
+            .flat_map(|instance| {
+                self.graph.nodes[instance.node]
+                    .index
+                    .iter()
+                    .filter(|g| **g < self.geometry.len())
+                    .map(|g| (*g, instance.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Flatten the scene graph like `flatten` but only keep instances
+    /// whose geometry overlaps a region, so consumers of huge scenes
+    /// don't bake geometry they will immediately discard. Each geometry
+    /// is tested by the box containing its transformed local bounds,
+    /// which may keep instances that are near but outside the region.
+    ///
+    /// Parameters
+    /// ------------
+    /// bounds
+    ///   The lower and upper corners of the region in the root frame.
+    ///
+    /// Returns
+    /// ------------
+    /// instances
+    ///   The geometry index and transform from the root for every
+    ///   placement of geometry which might overlap the region.
+    pub fn flatten_region(&self, bounds: &Bounds) -> Vec<(usize, Instance)> {
+        let bvh = self.bvh();
+        let mut found = Vec::new();
+        bvh.tree.query(bounds, |i| {
+            if overlaps(bounds, &bvh.bounds[i]) {
+                found.push(i);
+            }
+        });
+        // keep the traversal order of `flatten`
+        found.sort_unstable();
+        found
+            .into_iter()
+            .map(|i| bvh.instances[i].clone())
+            .collect()
+    }
+
+    /// A tree over the box of every placement of geometry, which is
+    /// built on first use and returned as it is until the graph or the
+    /// geometry is edited. After an edit the graph is traversed again
+    /// and if only transforms or geometry have changed the tree is refit
+    /// rather than rebuilt, so moving parts of a large assembly stays fast.
+    ///
+    /// Returns
+    /// ------------
+    /// bvh
+    ///   The tree over the current placements of the geometry.
+    pub fn bvh(&self) -> Arc<SceneBvh> {
+        let generation = self.graph.generation;
+        let cached = {
+            let cache = self.cache.read().unwrap();
+            if let Some(bvh) = cache
+                .bvh
+                .as_ref()
+                .filter(|_| cache.current == Some(generation))
+            {
+                return bvh.clone();
+            }
+            cache.bvh.clone()
+        };
+
+        let local = self.geometry_bounds();
+        let instances: Vec<(usize, Instance)> = self
+            .flatten()
+            .into_iter()
+            .filter(|(g, _)| local[*g].is_some())
+            .collect();
+        let bounds: Vec<Bounds> = instances
+            .iter()
+            .filter_map(|(g, i)| local[*g].map(|b| transform_bounds(&b, &i.transform)))
+            .collect();
+
+        let tree = match cached {
+            Some(cached)
+                if cached.instances.len() == instances.len()
+                    && cached
+                        .instances
+                        .iter()
+                        .zip(instances.iter())
+                        .all(|(a, b)| a.0 == b.0 && a.1.node == b.1.node) =>
+            {
+                if cached.bounds == bounds {
+                    self.cache.write().unwrap().current = Some(generation);
+                    return cached;
+                }
+                let mut tree = cached.tree.clone();
+                tree.refit(&bounds);
+                tree
+            }
+            _ => Bvh::new(&bounds),
+        };
+        let bvh = Arc::new(SceneBvh {
+            instances,
+            bounds,
+            tree,
+        });
+        let mut cache = self.cache.write().unwrap();
+        cache.bvh = Some(bvh.clone());
+        cache.current = Some(generation);
+        bvh
+    }
+
+    /// Find the pairs of placed geometry whose boxes overlap, which
+    /// are the candidates to check for a collision between parts.
+    ///
+    /// Returns
+    /// ------------
+    /// pairs
+    ///   The indices of each pair into `bvh().instances` with the
+    ///   lower index first, sorted.
+    pub fn overlapping(&self) -> Vec<(usize, usize)> {
+        let bvh = self.bvh();
+        let mut pairs = Vec::new();
+        for (index, bounds) in bvh.bounds.iter().enumerate() {
+            bvh.tree.query(bounds, |other| {
+                if other > index && overlaps(bounds, &bvh.bounds[other]) {
+                    pairs.push((index, other));
+                }
+            });
+        }
+        pairs.sort_unstable();
+        pairs
+    }
+
+    /// Every placement of a mesh in the scene, which is what a renderer
+    /// needs to draw it without copying any geometry.
+    ///
+    /// Returns
+    /// ------------
+    /// meshes
+    ///   The transform from the root and the mesh for every placement
+    ///   of a mesh in traversal order, skipping any other geometry.
+    pub fn dump(&self) -> Vec<(Matrix4<f64>, &Trimesh)> {
+        self.flatten()
+            .into_iter()
+            .filter_map(|(g, instance)| match &self.geometry[g] {
+                Geometry::Mesh(mesh) => Some((instance.transform, mesh.as_ref())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Collapse every placement of a mesh in the scene into a single mesh
+    /// in the frame of the root, joining their attributes and materials
+    /// with `util::concatenate`.
+    ///
+    /// Returns
+    /// ------------
+    /// mesh
+    ///   The combined mesh, or an error if a transform in the
+    ///   scene can't be applied to its mesh.
+    pub fn flatten_mesh(&self) -> Result<Trimesh> {
+        self.flatten_mesh_with(|mesh| Ok(Cow::Borrowed(mesh)))
+    }
+
+    /// `flatten_mesh` after replacing every placed mesh once, such as
+    /// with a copy with its textures baked, before it is transformed.
+    ///
+    /// Parameters
+    /// ------------
+    /// prepare
+    ///   The mesh to place in the scene for each mesh geometry.
+    ///
+    /// Returns
+    /// ------------
+    /// mesh
+    ///   The combined mesh, or an error from `prepare` or
+    ///   from applying a transform.
+    pub(crate) fn flatten_mesh_with<'a>(
+        &'a self,
+        prepare: impl Fn(&'a Trimesh) -> Result<Cow<'a, Trimesh>>,
+    ) -> Result<Trimesh> {
+        // geometry placed several times is only prepared once
+        let mut prepared: Vec<Option<Cow<'a, Trimesh>>> = Vec::new();
+        prepared.resize_with(self.geometry.len(), || None);
+        let mut placed = Vec::new();
+        for (index, instance) in self.flatten() {
+            let Geometry::Mesh(mesh) = &self.geometry[index] else {
+                continue;
+            };
+            let mesh = match &mut prepared[index] {
+                Some(mesh) => mesh,
+                slot => slot.insert(prepare(mesh)?),
+            };
+            placed.push(mesh.apply_transform(&instance.transform)?);
+        }
+        Ok(concatenate(&placed.iter().collect::<Vec<_>>()))
+    }
+
+    /// Convert the scene to a unit and up axis so scenes from different
+    /// sources can be combined. The geometry is scaled and rotated and
+    /// every node transform is conjugated by the same transform, so
+    /// each node stays rigid and its translation is converted, and
+    /// the transform is accumulated into `normalization`.
+    ///
+    /// Parameters
+    /// ------------
+    /// units
+    ///   The unit to convert the scene to.
+    /// up
+    ///   The axis which should point up.
+    ///
+    /// Returns
+    /// ------------
+    /// normalized
+    ///   The converted scene, or an error if the scene's units are unknown.
+    pub fn normalized(mut self, units: Unit, up: Axis) -> Result<Self> {
+        let current = self
+            .units
+            .ok_or_else(|| anyhow!("Scene units are unknown so it can't be rescaled"))?;
+        // basis vectors are never opposite so there is always a rotation
+        let rotation = Rotation3::rotation_between(&self.up.vector(), &up.vector())
+            .unwrap_or_else(Rotation3::identity);
+        let matrix =
+            rotation.to_homogeneous() * Matrix4::new_scaling(current.meters() / units.meters());
+        let inverse = matrix
+            .try_inverse()
+            .ok_or_else(|| anyhow!("Normalization isn't invertible: {matrix}"))?;
+
+        for geometry in self.geometry.iter_mut() {
+            match geometry {
+                Geometry::Mesh(mesh) => {
+                    **mesh = mesh.apply_transform(&matrix)?;
+                    mesh.source.units = Some(units);
+                }
+                Geometry::Path(path) => path.vertices = transform_points(&path.vertices, &matrix),
+            }
+        }
+        for node in self.graph.nodes_mut().iter_mut() {
+            if let Some(transform) = node.transform.as_mut() {
+                *transform = matrix * *transform * inverse;
+            }
+        }
+        self.clear_cache();
+
+        self.units = Some(units);
+        self.up = up;
+        self.normalization = Some(matrix * self.normalization.unwrap_or_else(Matrix4::identity));
+        Ok(self)
+    }
+
+    /// The order geometry should be written by an exporter so output is
+    /// reproducible: every geometry index in the order it is first
+    /// referenced by `flatten`, followed by any unreferenced geometry.
+    ///
+    /// Returns
+    /// ------------
+    /// order
+    ///   Each index of `self.geometry` exactly once.
+    pub fn geometry_order(&self) -> Vec<usize> {
+        let mut seen = vec![false; self.geometry.len()];
+        let mut order = Vec::with_capacity(self.geometry.len());
+        for (g, _) in self.flatten() {
+            if !seen[g] {
+                seen[g] = true;
+                order.push(g);
+            }
+        }
+        order.extend((0..self.geometry.len()).filter(|g| !seen[*g]));
+        order
+    }
+
+    /// Every placement of each geometry, so an exporter for a format
+    /// with instancing can write each geometry once and reference it
+    /// from every placement rather than writing a copy for each.
+    ///
+    /// Returns
+    /// ------------
+    /// instances
+    ///   The placements of each geometry in `geometry_order` with
+    ///   the placements in traversal order, which are empty for
+    ///   geometry no node references.
+    pub fn instances(&self) -> Vec<(usize, Vec<Instance>)> {
+        let mut placed = vec![Vec::new(); self.geometry.len()];
+        for (g, instance) in self.flatten() {
+            placed[g].push(instance);
+        }
+        self.geometry_order()
+            .into_iter()
+            .map(|g| (g, std::mem::take(&mut placed[g])))
+            .collect()
+    }
+
+    /// Merge meshes which are exactly the same, like the copies of a part
+    /// made when a file without instancing is loaded, into one geometry
+    /// referenced by every node which referenced any of the copies. Meshes
+    /// match if their vertices, faces, attributes and materials are equal,
+    /// and paths are left as they are.
+    ///
+    /// Returns
+    /// ------------
+    /// removed
+    ///   The number of geometries merged into an earlier copy, with
+    ///   the remaining geometry keeping its order.
+    pub fn deduplicate_geometry(&mut self) -> usize {
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut remap = Vec::with_capacity(self.geometry.len());
+        let mut kept: Vec<Geometry> = Vec::with_capacity(self.geometry.len());
+        for geometry in std::mem::take(&mut self.geometry) {
+            if let Geometry::Mesh(mesh) = &geometry {
+                let bucket = buckets.entry(mesh_key(mesh)).or_default();
+                let existing = bucket.iter().find(|k| match &kept[**k] {
+                    Geometry::Mesh(other) => same_mesh(mesh, other),
+                    _ => false,
+                });
+                if let Some(existing) = existing {
+                    remap.push(*existing);
+                    continue;
+                }
+                bucket.push(kept.len());
+            }
+            remap.push(kept.len());
+            kept.push(geometry);
+        }
+        let removed = remap.len() - kept.len();
+        self.geometry = kept;
+
+        for node in self.graph.nodes_mut().iter_mut() {
+            if matches!(node.kind, SceneNodeKind::GEOMETRY) {
+                for index in node.index.iter_mut() {
+                    if let Some(new) = remap.get(*index) {
+                        *index = *new;
+                    }
+                }
+            }
+        }
+        self.clear_cache();
+        removed
+    }
 }
 
 #[cfg(test)]
@@ -89,6 +918,8 @@ mod tests {
 
     use super::*;
     use crate::creation;
+    use crate::ray::Ray;
+    use nalgebra::Vector3;
 
     #[test]
     fn test_scene_basic() {
@@ -106,12 +937,410 @@ mod tests {
         };
 
         let root_index = scene.graph.add_node(root_node);
-        scene.graph.root = root_index;
+        scene.graph.set_root(root_index);
 
         assert_eq!(scene.geometry.len(), 1);
-        assert_eq!(scene.graph.nodes.len(), 1);
-        assert_eq!(scene.graph.root, 0);
-        assert_eq!(scene.graph.nodes[0].name, "root");
-        assert_eq!(scene.graph.nodes[0].index.len(), 1);
+        assert_eq!(scene.graph.nodes().len(), 1);
+        assert_eq!(scene.graph.root(), 0);
+        assert_eq!(scene.graph.nodes()[0].name, "root");
+        assert_eq!(scene.graph.nodes()[0].index.len(), 1);
+    }
+
+    #[test]
+    fn test_scene_flatten() {
+        let mut scene = Scene::new();
+        let small = scene.add_geometry(Geometry::Mesh(Box::new(creation::create_box(&[
+            1.0, 1.0, 1.0,
+        ]))));
+        let large = scene.add_geometry(Geometry::Mesh(Box::new(creation::create_box(&[
+            2.0, 2.0, 2.0,
+        ]))));
+        // never referenced by a node
+        let orphan = scene.add_geometry(Geometry::Mesh(Box::new(creation::create_box(&[
+            3.0, 3.0, 3.0,
+        ]))));
+
+        let root = scene.graph.add_node(SceneNode {
+            name: "root".to_string(),
+            kind: SceneNodeKind::CUSTOM,
+            ..Default::default()
+        });
+        // insert the children out of name order
+        let b = scene.graph.add_node(SceneNode {
+            name: "b".to_string(),
+            index: vec![small],
+            transform: Some(Matrix4::new_translation(&Vector3::new(1.0, 0.0, 0.0))),
+            ..Default::default()
+        });
+        let a = scene.graph.add_node(SceneNode {
+            name: "a".to_string(),
+            index: vec![large],
+            // "a" also instances "b" as a child and has a cycle back to root
+            children: vec![b, root],
+            transform: Some(Matrix4::new_translation(&Vector3::new(0.0, 10.0, 0.0))),
+            ..Default::default()
+        });
+        scene.graph.nodes_mut()[root].children = vec![b, a];
+
+        let order: Vec<usize> = scene.graph.traverse().iter().map(|i| i.node).collect();
+        assert_eq!(order, vec![root, a, b, b]);
+
+        let flat = scene.flatten();
+        assert_eq!(flat.len(), 3);
+        assert_eq!(flat[0].0, large);
+        // "b" instanced under "a" picks up both translations
+        assert_eq!(
+            flat[1].1.transform.column(3).xyz(),
+            Vector3::new(1.0, 10.0, 0.0)
+        );
+        assert_eq!(
+            flat[2].1.transform.column(3).xyz(),
+            Vector3::new(1.0, 0.0, 0.0)
+        );
+
+        assert_eq!(scene.geometry_order(), vec![large, small, orphan]);
+
+        // only the instance of "b" directly under the root is near the origin
+        let region = (Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+        let near = scene.flatten_region(&region);
+        assert_eq!(near.len(), 1);
+        assert_eq!(near[0].0, small);
+        assert_eq!(near[0].1.transform, flat[2].1.transform);
+        // a region around the instances under "a"
+        let region = (Point3::new(0.0, 9.0, -1.0), Point3::new(2.0, 11.0, 1.0));
+        assert_eq!(scene.flatten_region(&region).len(), 2);
+        let region = (Point3::new(50.0, 50.0, 50.0), Point3::new(60.0, 60.0, 60.0));
+        assert!(scene.flatten_region(&region).is_empty());
+
+        // an empty scene should not panic
+        assert!(Scene::new().flatten().is_empty());
+    }
+
+    #[test]
+    fn test_scene_flatten_mesh() {
+        use crate::attributes::{Grouping, GroupingKind, Material, SimpleMaterial};
+
+        let mut scene = Scene::new();
+        let mut red = creation::create_box(&[1.0, 1.0, 1.0]);
+        red.materials
+            .push(Material::Simple(SimpleMaterial::new("red")));
+        red.attributes_face.groupings.push(Grouping {
+            name: "material".to_string(),
+            kind: GroupingKind::MaterialIndex,
+            indices: vec![0; 12],
+            labels: Vec::new(),
+        });
+        let mut blue = creation::create_box(&[2.0, 2.0, 2.0]);
+        blue.materials
+            .push(Material::Simple(SimpleMaterial::new("blue")));
+        blue.attributes_face.groupings.push(Grouping {
+            name: "material".to_string(),
+            kind: GroupingKind::MaterialIndex,
+            indices: vec![0; 12],
+            labels: Vec::new(),
+        });
+        let red = scene.add_geometry(Geometry::Mesh(Box::new(red)));
+        let blue = scene.add_geometry(Geometry::Mesh(Box::new(blue)));
+
+        let root = scene.graph.add_node(SceneNode {
+            name: "root".to_string(),
+            index: vec![blue],
+            ..Default::default()
+        });
+        let child = scene.graph.add_node(SceneNode {
+            name: "child".to_string(),
+            index: vec![red],
+            transform: Some(Matrix4::new_translation(&Vector3::new(5.0, 0.0, 0.0))),
+            ..Default::default()
+        });
+        // the red box is placed twice, once further out
+        let grandchild = scene.graph.add_node(SceneNode {
+            name: "grandchild".to_string(),
+            index: vec![red],
+            transform: Some(Matrix4::new_translation(&Vector3::new(0.0, 5.0, 0.0))),
+            ..Default::default()
+        });
+        scene.graph.nodes_mut()[root].children = vec![child];
+        scene.graph.nodes_mut()[child].children = vec![grandchild];
+
+        let dump = scene.dump();
+        assert_eq!(dump.len(), 3);
+        assert_eq!(dump[2].0.column(3).xyz(), Vector3::new(5.0, 5.0, 0.0));
+
+        let mesh = scene.flatten_mesh().unwrap();
+        assert_eq!(mesh.vertices.len(), 24);
+        assert_eq!(mesh.faces.len(), 36);
+        assert!((mesh.volume() - 10.0).abs() < 1e-10);
+        let (lower, upper) = mesh.bounds().unwrap();
+        assert_eq!(lower, Point3::new(-1.0, -1.0, -1.0));
+        assert_eq!(upper, Point3::new(5.5, 5.5, 1.0));
+        // every instance brings its materials along
+        assert_eq!(mesh.materials.len(), 3);
+        let indices = &mesh.attributes_face.groupings[0].indices;
+        assert_eq!(indices[..12], [0; 12]);
+        assert_eq!(indices[12..24], [1; 12]);
+        assert_eq!(indices[24..], [2; 12]);
+        assert!(mesh.primitive.is_none());
+
+        assert!(Scene::new().flatten_mesh().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scene_graph_queries() {
+        let mut graph = SceneGraph::new();
+        let root = graph.add_node(SceneNode {
+            name: "world".to_string(),
+            ..Default::default()
+        });
+        let arm = graph.add_node(SceneNode {
+            name: "arm".to_string(),
+            transform: Some(Matrix4::new_translation(&Vector3::new(1.0, 0.0, 0.0))),
+            ..Default::default()
+        });
+        let hand = graph.add_node(SceneNode {
+            name: "hand".to_string(),
+            transform: Some(Matrix4::new_translation(&Vector3::new(0.0, 2.0, 0.0))),
+            ..Default::default()
+        });
+        let loose = graph.add_node(SceneNode::default());
+        graph.add_edge(root, arm).unwrap();
+        graph.add_edge(arm, hand).unwrap();
+
+        assert_eq!(graph.node_by_name("hand"), Some(hand));
+        assert!(graph.node_by_name("foot").is_none());
+        assert_eq!(graph.parents(hand), vec![arm]);
+        assert!(graph.parents(root).is_empty());
+        assert_eq!(
+            graph.world_transform(hand).unwrap(),
+            Matrix4::new_translation(&Vector3::new(1.0, 2.0, 0.0))
+        );
+        assert_eq!(graph.world_transform(root).unwrap(), Matrix4::identity());
+        assert!(graph.world_transform(loose).is_none());
+        assert!(graph.world_transform(100).is_none());
+
+        // editing the graph clears the cached transforms
+        graph.set_transform(arm, None);
+        assert_eq!(
+            graph.world_transform(hand).unwrap(),
+            Matrix4::new_translation(&Vector3::new(0.0, 2.0, 0.0))
+        );
+        graph.add_edge(root, loose).unwrap();
+        assert_eq!(graph.world_transform(loose).unwrap(), Matrix4::identity());
+
+        // edges which would make a node its own ancestor are refused
+        assert!(graph.add_edge(hand, root).is_err());
+        assert!(graph.add_edge(hand, hand).is_err());
+        assert!(graph.add_edge(hand, 100).is_err());
+        assert_eq!(graph.nodes()[hand].children.len(), 0);
+        // but a node can be instanced under several parents
+        graph.add_edge(loose, hand).unwrap();
+        assert_eq!(graph.parents(hand), vec![arm, loose]);
+    }
+
+    #[test]
+    fn test_scene_bvh() {
+        // a row of a thousand instances of one box
+        let mut scene = Scene::new();
+        let cube = scene.add_geometry(Geometry::Mesh(Box::new(creation::create_box(&[
+            1.0, 1.0, 1.0,
+        ]))));
+        let root = scene.graph.add_node(SceneNode::default());
+        scene.graph.set_root(root);
+        for i in 0..1000 {
+            let node = scene.graph.add_node(SceneNode {
+                name: format!("{i:04}"),
+                transform: Some(Matrix4::new_translation(&Vector3::new(
+                    i as f64 * 2.0,
+                    0.0,
+                    0.0,
+                ))),
+                kind: SceneNodeKind::GEOMETRY,
+                index: vec![cube],
+                ..Default::default()
+            });
+            scene.graph.add_edge(0, node).unwrap();
+        }
+        let bvh = scene.bvh();
+        assert_eq!(bvh.instances.len(), 1000);
+        assert!(Arc::ptr_eq(&bvh, &scene.bvh()));
+        assert!(scene.overlapping().is_empty());
+
+        // move the last box onto the first and the tree is refit
+        let last = scene.graph.node_by_name("0999").unwrap();
+        scene.graph.set_transform(
+            last,
+            Some(Matrix4::new_translation(&Vector3::new(0.5, 0.0, 0.0))),
+        );
+        let refit = scene.bvh();
+        assert!(!Arc::ptr_eq(&bvh, &refit));
+        assert_eq!(refit.bounds[999].0, Point3::new(0.0, -0.5, -0.5));
+        assert_eq!(scene.overlapping(), vec![(0, 999)]);
+        let region = (Point3::new(0.25, 0.0, 0.0), Point3::new(0.3, 0.0, 0.0));
+        let found: Vec<usize> = scene
+            .flatten_region(&region)
+            .iter()
+            .map(|(_, i)| i.node)
+            .collect();
+        assert_eq!(found, vec![scene.graph.node_by_name("0000").unwrap(), last]);
+
+        // a ray from the end of the row hits the moved box first
+        let ray = Ray::new(Point3::new(3000.0, 0.1, 0.1), Vector3::new(-1.0, 0.0, 0.0));
+        let pick = scene.pick(&ray).unwrap();
+        assert_eq!(pick.node, scene.graph.node_by_name("0998").unwrap());
+        let ray = Ray::new(Point3::new(0.75, 0.1, 5.0), Vector3::new(0.0, 0.0, -1.0));
+        assert_eq!(scene.pick(&ray).unwrap().node, last);
+
+        // the tree is kept until the scene is edited
+        assert!(Arc::ptr_eq(&refit, &scene.bvh()));
+        scene.geometry[cube] = Geometry::Mesh(Box::new(creation::create_box(&[3.0, 1.0, 1.0])));
+        assert!(Arc::ptr_eq(&refit, &scene.bvh()));
+        scene.clear_cache();
+        let grown = scene.bvh();
+        assert!(!Arc::ptr_eq(&refit, &grown));
+        assert_eq!(grown.bounds[0].0, Point3::new(-1.5, -0.5, -0.5));
+        assert_eq!(scene.overlapping().len(), 1000);
+
+        // editing the nodes in place is picked up once the guard drops
+        scene.graph.nodes_mut()[last].transform = None;
+        let edited = scene.bvh();
+        assert!(!Arc::ptr_eq(&grown, &edited));
+        assert_eq!(edited.bounds[999].0, Point3::new(-1.5, -0.5, -0.5));
+
+        // a replaced graph never shares a generation with the old one
+        let mut graph = SceneGraph::new();
+        let root = graph.add_node(SceneNode::default());
+        let node = graph.add_node(SceneNode {
+            index: vec![cube],
+            ..Default::default()
+        });
+        graph.add_edge(root, node).unwrap();
+        scene.graph = graph;
+        assert_eq!(scene.bvh().instances.len(), 1);
+    }
+
+    #[test]
+    fn test_scene_normalized() {
+        // a millimeter box 10mm up along Y
+        let mut scene = Scene::new();
+        let index = scene.add_geometry(Geometry::Mesh(Box::new(creation::create_box(&[
+            2.0, 4.0, 6.0,
+        ]))));
+        let root = scene.graph.add_node(SceneNode::default());
+        let part = scene.graph.add_node(SceneNode {
+            name: "part".to_string(),
+            index: vec![index],
+            transform: Some(Matrix4::new_translation(&Vector3::new(0.0, 10.0, 0.0))),
+            ..Default::default()
+        });
+        scene.graph.add_edge(root, part).unwrap();
+        scene.up = Axis::Y;
+        assert!(Scene::new().normalized(Unit::Meter, Axis::Z).is_err());
+        scene.units = Some(Unit::Millimeter);
+        let before = scene.flatten_mesh().unwrap();
+
+        let scene = scene.normalized(Unit::Meter, Axis::Z).unwrap();
+        assert_eq!(scene.units, Some(Unit::Meter));
+        // the node is still a pure translation, now up along Z in meters
+        let world = scene.graph.world_transform(part).unwrap();
+        assert!((world - Matrix4::new_translation(&Vector3::new(0.0, 0.0, 0.01))).amax() < 1e-12);
+        let (lower, upper) = scene.flatten_mesh().unwrap().bounds().unwrap();
+        assert!((lower - Point3::new(-0.001, -0.003, 0.008)).amax() < 1e-12);
+        assert!((upper - Point3::new(0.001, 0.003, 0.012)).amax() < 1e-12);
+
+        // the recorded transform maps the original scene onto the new one
+        let recorded = scene.normalization.unwrap();
+        let moved = before.apply_transform(&recorded).unwrap();
+        assert!((moved.bounds().unwrap().0 - lower).amax() < 1e-12);
+
+        // normalizing again accumulates
+        let scene = scene.normalized(Unit::Millimeter, Axis::Z).unwrap();
+        assert!(
+            (scene.normalization.unwrap() - recorded * 1000.0)
+                .fixed_view::<3, 4>(0, 0)
+                .amax()
+                < 1e-9
+        );
+        assert_eq!(Unit::from_name(" MM ").unwrap(), Unit::Millimeter);
+        assert!(Unit::from_name("furlong").is_err());
+    }
+
+    #[test]
+    fn test_scene_frames() {
+        let mut scene = Scene::new();
+        let root = scene.graph.add_node(SceneNode {
+            name: "world".to_string(),
+            kind: SceneNodeKind::CUSTOM,
+            ..Default::default()
+        });
+        let part = scene.graph.add_node(SceneNode {
+            name: "part".to_string(),
+            kind: SceneNodeKind::CUSTOM,
+            transform: Some(Matrix4::new_translation(&Vector3::new(1.0, 0.0, 0.0))),
+            ..Default::default()
+        });
+        scene.graph.nodes_mut()[root].children.push(part);
+        scene.graph.add_frame(
+            part,
+            "datum",
+            Matrix4::new_translation(&Vector3::new(0.0, 2.0, 0.0)),
+        );
+
+        let frames = scene.graph.frames();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(
+            scene.graph.frame("datum").unwrap(),
+            Matrix4::new_translation(&Vector3::new(1.0, 2.0, 0.0))
+        );
+        assert!(scene.graph.frame("missing").is_none());
+        // frames have no geometry to flatten
+        assert!(scene.flatten().is_empty());
+    }
+
+    #[test]
+    fn test_scene_deduplicate() {
+        let mut scene = Scene::new();
+        let root = scene.graph.add_node(SceneNode {
+            name: "world".to_string(),
+            kind: SceneNodeKind::CUSTOM,
+            ..Default::default()
+        });
+        // three copies of one box as a loader without instancing makes
+        // them, and a different box
+        for (i, size) in [1.0, 1.0, 2.0, 1.0].into_iter().enumerate() {
+            let g = scene.add_geometry(Geometry::Mesh(Box::new(creation::create_box(&[
+                size, size, size,
+            ]))));
+            let node = scene.graph.add_node(SceneNode {
+                name: format!("part {i}"),
+                kind: SceneNodeKind::GEOMETRY,
+                transform: Some(Matrix4::new_translation(&Vector3::new(
+                    i as f64 * 3.0,
+                    0.0,
+                    0.0,
+                ))),
+                index: vec![g],
+                ..Default::default()
+            });
+            scene.graph.add_edge(root, node).unwrap();
+        }
+        let before = scene.flatten_mesh().unwrap();
+        assert_eq!(scene.bvh().instances.len(), 4);
+
+        assert_eq!(scene.deduplicate_geometry(), 2);
+        assert_eq!(scene.geometry.len(), 2);
+        assert_eq!(scene.deduplicate_geometry(), 0);
+        let instances = scene.instances();
+        assert_eq!(instances.len(), 2);
+        assert_eq!((instances[0].0, instances[0].1.len()), (0, 3));
+        assert_eq!((instances[1].0, instances[1].1.len()), (1, 1));
+        assert_eq!(
+            instances[0].1[2].node,
+            scene.graph.node_by_name("part 3").unwrap()
+        );
+
+        // the placed geometry is unchanged
+        let after = scene.flatten_mesh().unwrap();
+        assert_eq!(after.vertices, before.vertices);
+        assert_eq!(scene.bvh().instances.len(), 4);
     }
 }
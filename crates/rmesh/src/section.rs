@@ -0,0 +1,350 @@
+use ahash::AHashMap;
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector3};
+use rayon::prelude::*;
+
+use crate::creation::Plane;
+use crate::facets::boundary_loops;
+use crate::mesh::Trimesh;
+use crate::path::{Curve, Path};
+
+/// Where a cross section crosses the mesh, which is either exactly at a
+/// vertex on the plane or somewhere along an edge between two vertices.
+/// Keying points this way lets segments from adjacent faces be stitched
+/// together without comparing floating point positions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Crossing {
+    Vertex(usize),
+    Edge(usize, usize),
+}
+
+impl Trimesh {
+    /// Stitch the segments where faces cross the zero level of a signed
+    /// distance per vertex into loops, oriented counter-clockwise around
+    /// `normal` for the outside of a closed mesh.
+    fn section_distances(&self, distance: &[f64], normal: &Vector3<f64>) -> Option<Path> {
+        let face_normals = self.face_normals();
+        let mut vertices: Vec<Point3<f64>> = Vec::new();
+        let mut index: AHashMap<Crossing, usize> = AHashMap::new();
+        // the outgoing segments from every point
+        let mut next: AHashMap<usize, Vec<usize>> = AHashMap::new();
+        let mut incoming: AHashMap<usize, usize> = AHashMap::new();
+
+        for (f, face_normal) in self.faces.iter().zip(face_normals.iter()) {
+            let corners = [f.0, f.1, f.2];
+            // vertices exactly on the plane count as above it
+            let above = corners.map(|v| distance[v] >= 0.0);
+            if above.iter().all(|a| *a) || above.iter().all(|a| !*a) {
+                continue;
+            }
+
+            let mut crossings = Vec::with_capacity(2);
+            for i in 0..3 {
+                let (a, b) = (corners[i], corners[(i + 1) % 3]);
+                if above[i] == above[(i + 1) % 3] {
+                    continue;
+                }
+                let (top, bottom) = if above[i] { (a, b) } else { (b, a) };
+                let (crossing, point) = if distance[top] == 0.0 {
+                    (Crossing::Vertex(top), self.vertices[top])
+                } else {
+                    let t = distance[top] / (distance[top] - distance[bottom]);
+                    let point =
+                        self.vertices[top] + (self.vertices[bottom] - self.vertices[top]) * t;
+                    (Crossing::Edge(top.min(bottom), top.max(bottom)), point)
+                };
+                let point = *index.entry(crossing).or_insert_with(|| {
+                    vertices.push(point);
+                    vertices.len() - 1
+                });
+                crossings.push(point);
+            }
+            // a face touching the plane at a single vertex
+            let [mut start, mut end] = [crossings[0], crossings[1]];
+            if start == end {
+                continue;
+            }
+            // the outside of a solid is on the left of the segment
+            if (vertices[end] - vertices[start]).dot(&normal.cross(face_normal)) < 0.0 {
+                (start, end) = (end, start);
+            }
+            next.entry(start).or_default().push(end);
+            *incoming.entry(end).or_default() += 1;
+        }
+        if next.is_empty() {
+            return None;
+        }
+
+        // start with open chains from points nothing leads to, then
+        // walk the remaining closed loops in a deterministic order
+        let mut starts: Vec<usize> = next.keys().copied().collect();
+        starts.sort_unstable();
+        starts.sort_by_key(|s| incoming.contains_key(s));
+
+        let mut entities = Vec::new();
+        for start in starts {
+            while next.get(&start).is_some_and(|n| !n.is_empty()) {
+                let mut points = vec![start];
+                let mut current = start;
+                while let Some(following) = next.get_mut(&current).and_then(|n| n.pop()) {
+                    points.push(following);
+                    current = following;
+                    if current == start {
+                        break;
+                    }
+                }
+                entities.push(Curve::Line { points });
+            }
+        }
+        Some(Path::new(vertices, entities))
+    }
+
+    /// Find the cross section of the mesh with a plane as loops of
+    /// line segments. For a closed mesh every loop is closed with the
+    /// first point repeated at the end, and is counter-clockwise around
+    /// the plane normal for outer boundaries and clockwise for holes.
+    ///
+    /// Parameters
+    /// ------------
+    /// plane
+    ///   The plane to intersect the mesh with.
+    ///
+    /// Returns
+    /// ------------
+    /// section
+    ///   The cross section in 3D on the plane, or `None` if the plane
+    ///   doesn't cross the mesh.
+    pub fn section(&self, plane: &Plane) -> Option<Path> {
+        let normal = plane.normal.try_normalize(0.0)?;
+        let distance: Vec<f64> = self
+            .vertices
+            .par_iter()
+            .map(|v| normal.dot(&(v - plane.origin)))
+            .collect();
+        self.section_distances(&distance, &normal)
+    }
+
+    /// The boundary of the mesh or of a selection of its faces as closed
+    /// loops of line segments, which are the edges whose reverse isn't
+    /// an edge of another of the faces. The loops are chained with
+    /// `facets::boundary_loops` so they follow the winding of the faces
+    /// next to them. A closed mesh has no outline.
+    ///
+    /// Parameters
+    /// ------------
+    /// face_indices
+    ///   The faces to find the outline of, or `None` for every face.
+    ///
+    /// Returns
+    /// ------------
+    /// outline
+    ///   The loops with the first point repeated at the end, or an error
+    ///   if a face index is out of range or the boundary isn't a set of
+    ///   simple loops, for example if two loops touch at a vertex.
+    pub fn outline(&self, face_indices: Option<&[usize]>) -> Result<Path> {
+        let faces: Vec<(usize, usize, usize)> = match face_indices {
+            Some(indices) => {
+                if let Some(bad) = indices.iter().find(|f| **f >= self.faces.len()) {
+                    return Err(anyhow!(
+                        "Face index {bad} is out of range for {} faces",
+                        self.faces.len()
+                    ));
+                }
+                indices.iter().map(|f| self.faces[*f]).collect()
+            }
+            None => self.faces.clone(),
+        };
+        let loops = boundary_loops(&faces).ok_or(anyhow!("Outline isn't a set of simple loops"))?;
+
+        let mut vertices = Vec::new();
+        let mut entities = Vec::new();
+        for chain in loops {
+            let offset = vertices.len();
+            vertices.extend(chain.iter().map(|v| self.vertices[*v]));
+            let mut points: Vec<usize> = (offset..vertices.len()).collect();
+            points.push(offset);
+            entities.push(Curve::Line { points });
+        }
+        Ok(Path::new(vertices, entities))
+    }
+
+    /// Find cross sections of the mesh with many parallel planes
+    /// like the layers of a slicer, computing each layer in parallel.
+    ///
+    /// Parameters
+    /// ------------
+    /// origin
+    ///   A point on the plane at a height of zero.
+    /// normal
+    ///   The normal of every plane.
+    /// heights
+    ///   The offset of each plane from the origin along the normal.
+    ///
+    /// Returns
+    /// ------------
+    /// sections
+    ///   The cross section for each height in 3D on its plane, or `None`
+    ///   for planes that don't cross the mesh.
+    pub fn section_multiplane(
+        &self,
+        origin: &Point3<f64>,
+        normal: &Vector3<f64>,
+        heights: &[f64],
+    ) -> Vec<Option<Path>> {
+        let Some(normal) = normal.try_normalize(0.0) else {
+            return heights.iter().map(|_| None).collect();
+        };
+        // the distance to the origin plane is shared by every layer
+        let base: Vec<f64> = self
+            .vertices
+            .par_iter()
+            .map(|v| normal.dot(&(v - origin)))
+            .collect();
+        heights
+            .par_iter()
+            .map(|height| {
+                let distance: Vec<f64> = base.iter().map(|d| d - height).collect();
+                self.section_distances(&distance, &normal)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use crate::exchange::{MeshFormat, load_mesh};
+    use approx::assert_relative_eq;
+
+    /// The signed area of a closed loop in the XY plane.
+    fn area_xy(path: &Path, curve: &Curve) -> f64 {
+        let points = curve.indices();
+        points
+            .windows(2)
+            .map(|w| {
+                let (a, b) = (path.vertices[w[0]], path.vertices[w[1]]);
+                a.x * b.y - b.x * a.y
+            })
+            .sum::<f64>()
+            / 2.0
+    }
+
+    #[test]
+    fn test_section() {
+        let mesh = create_box(&[2.0, 2.0, 2.0]);
+
+        // crossing the middle of every side
+        let plane = Plane::new(Vector3::z(), Point3::origin());
+        let path = mesh.section(&plane).unwrap();
+        assert_eq!(path.entities.len(), 1);
+        let points = path.entities[0].indices();
+        assert_eq!(points.first(), points.last());
+        assert_relative_eq!(path.length(), 8.0, epsilon = 1e-10);
+        assert_relative_eq!(area_xy(&path, &path.entities[0]), 4.0, epsilon = 1e-10);
+        assert!(path.vertices.iter().all(|v| v.z.abs() < 1e-12));
+
+        // flipping the plane reverses the loop
+        let flipped = mesh
+            .section(&Plane::new(-Vector3::z(), Point3::origin()))
+            .unwrap();
+        assert_relative_eq!(
+            area_xy(&flipped, &flipped.entities[0]),
+            -4.0,
+            epsilon = 1e-10
+        );
+
+        // a plane through the top face lands exactly on vertices
+        let top = mesh
+            .section(&Plane::new(Vector3::z(), Point3::new(0.0, 0.0, 1.0)))
+            .unwrap();
+        assert_eq!(top.entities.len(), 1);
+        assert_relative_eq!(top.length(), 8.0, epsilon = 1e-10);
+
+        assert!(
+            mesh.section(&Plane::new(Vector3::z(), Point3::new(0.0, 0.0, 5.0)))
+                .is_none()
+        );
+
+        // the outline of the box cut in half is the loop around the cut
+        let half = mesh.slice_plane(&plane, false).unwrap();
+        let outline = half.outline(None).unwrap();
+        assert_eq!(outline.entities.len(), 1);
+        assert_relative_eq!(outline.length(), 8.0, epsilon = 1e-10);
+        assert!(outline.vertices.iter().all(|v| v.z.abs() < 1e-12));
+        assert!(mesh.outline(None).unwrap().entities.is_empty());
+
+        // the faces of the box on the top are a unit square
+        let top: Vec<usize> = (0..mesh.faces.len())
+            .filter(|f| mesh.face_normals()[*f].z > 0.5)
+            .collect();
+        let square = mesh.outline(Some(&top)).unwrap();
+        assert_eq!(square.entities.len(), 1);
+        assert_relative_eq!(square.length(), 8.0, epsilon = 1e-10);
+        // it winds counter-clockwise around the normal of the faces
+        let v = &square.vertices;
+        let area: f64 = (0..v.len())
+            .map(|i| v[i].coords.cross(&v[(i + 1) % v.len()].coords).z)
+            .sum();
+        assert_relative_eq!(area / 2.0, 4.0, epsilon = 1e-10);
+
+        // two squares touching at a corner aren't simple loops
+        let grid = Trimesh::from_slice(
+            &[
+                0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0, 2.0, 1.0, 0.0, 2.0,
+                2.0, 0.0, 1.0, 2.0, 0.0,
+            ],
+            &[0, 1, 2, 0, 2, 3, 2, 4, 5, 2, 5, 6],
+        )
+        .unwrap();
+        assert!(grid.outline(None).is_err());
+        // but each square on its own is
+        let first = grid.outline(Some(&[0, 1])).unwrap();
+        assert_eq!(first.entities.len(), 1);
+        assert_relative_eq!(first.length(), 4.0, epsilon = 1e-10);
+        assert!(grid.outline(Some(&[])).unwrap().entities.is_empty());
+        assert!(grid.outline(Some(&[100])).is_err());
+    }
+
+    #[test]
+    fn test_section_multiplane() {
+        let mut mesh = load_mesh(
+            include_bytes!("../../../test/data/fuze.obj"),
+            MeshFormat::OBJ,
+        )
+        .unwrap();
+        // drop the UVs so the seams are welded into a closed mesh
+        mesh.attributes_vertex = Default::default();
+        let mesh = mesh.merge_vertices(1e-10).unwrap();
+        let (lower, upper) = mesh.bounds().unwrap();
+        let heights: Vec<f64> = (1..10)
+            .map(|i| (upper.z - lower.z) * i as f64 / 10.0)
+            .collect();
+        let sections = mesh.section_multiplane(&lower, &Vector3::z(), &heights);
+        assert_eq!(sections.len(), heights.len());
+
+        for (section, height) in sections.iter().zip(heights.iter()) {
+            let section = section.as_ref().unwrap();
+            // every loop of a closed mesh is closed and on the plane
+            for curve in section.entities.iter() {
+                let points = curve.indices();
+                assert_eq!(points.first(), points.last());
+            }
+            for v in section.vertices.iter() {
+                assert_relative_eq!(v.z, lower.z + height, epsilon = 1e-10);
+            }
+            // matches sectioning with a single plane
+            let single = mesh
+                .section(&Plane::new(
+                    Vector3::z(),
+                    Point3::new(0.0, 0.0, lower.z + height),
+                ))
+                .unwrap();
+            assert_relative_eq!(single.length(), section.length(), epsilon = 1e-10);
+        }
+
+        let outside = mesh.section_multiplane(&lower, &Vector3::z(), &[-1.0]);
+        assert!(outside[0].is_none());
+    }
+}
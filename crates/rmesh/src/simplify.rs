@@ -3,6 +3,8 @@
 use nalgebra::{Point3, Vector3};
 use std::ops::{Add, AddAssign};
 
+use crate::tolerance::Tolerances;
+
 // Type aliases for clarity
 type Point = Point3<f64>;
 type Vector = Vector3<f64>;
@@ -94,8 +96,8 @@ struct Triangle {
     err: [f64; 4], // Edge errors [0-1, 1-2, 2-0], min error
     deleted: bool,
     dirty: bool,
-    n: Vector, // Normal vector
-               // UVs and material omitted as not requested in signature
+    n: Vector,     // Normal vector
+    source: usize, // Index of the input face
 }
 
 #[derive(Debug, Clone)]
@@ -105,6 +107,7 @@ struct Vertex {
     tcount: usize,      // Number of refs entries
     q: SymmetricMatrix, // Quadric error matrix
     border: bool,       // Is vertex on a boundary edge?
+    source: usize,      // Index of the nearest input vertex
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -119,29 +122,42 @@ struct Simplifier {
     vertices: Vec<Vertex>,
     triangles: Vec<Triangle>,
     refs: Vec<Ref>,
+    values: Vec<f64>, // Per vertex values interpolated through collapses
+    stride: usize,    // Number of values per vertex
+    tolerances: Tolerances,
 }
 
 impl Simplifier {
-    fn new(input_vertices: &[Point], input_faces: &[(usize, usize, usize)]) -> Self {
+    fn new(
+        input_vertices: &[Point],
+        input_faces: &[(usize, usize, usize)],
+        values: &[f64],
+        stride: usize,
+        tolerances: &Tolerances,
+    ) -> Self {
         let vertices = input_vertices
             .iter()
-            .map(|&p| Vertex {
+            .enumerate()
+            .map(|(source, &p)| Vertex {
                 p,
                 tstart: 0,
                 tcount: 0,
                 q: SymmetricMatrix::new(0.0),
                 border: false,
+                source,
             })
             .collect();
 
         let triangles = input_faces
             .iter()
-            .map(|&(v0, v1, v2)| Triangle {
+            .enumerate()
+            .map(|(source, &(v0, v1, v2))| Triangle {
                 v: [v0, v1, v2],
                 err: [0.0; 4],
                 deleted: false,
                 dirty: false,
                 n: Vector::zeros(), // Will be calculated later
+                source,
             })
             .collect();
 
@@ -149,6 +165,31 @@ impl Simplifier {
             vertices,
             triangles,
             refs: Vec::new(),
+            values: values.to_vec(),
+            stride,
+            tolerances: *tolerances,
+        }
+    }
+
+    // Interpolate the values of i0 and i1 at the position they collapse to,
+    // by projecting it onto the edge between them
+    fn merge_values(&mut self, i0: usize, i1: usize, p: Point) {
+        let p0 = self.vertices[i0].p;
+        let edge = self.vertices[i1].p - p0;
+        let length = edge.norm_squared();
+        let t = if length > 0.0 {
+            ((p - p0).dot(&edge) / length).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        if t > 0.5 {
+            self.vertices[i0].source = self.vertices[i1].source;
+        }
+        let stride = self.stride;
+        for k in 0..stride {
+            let a = self.values[i0 * stride + k];
+            let b = self.values[i1 * stride + k];
+            self.values[i0 * stride + k] = a + (b - a) * t;
         }
     }
 
@@ -235,19 +276,24 @@ impl Simplifier {
             let p1 = self.vertices[id1].p;
             let p2 = self.vertices[id2].p;
 
-            // Check for degenerate triangles (collinear vertices) after collapse
-            let d1 = (p1 - p).normalize();
-            let d2 = (p2 - p).normalize();
-            if d1.dot(&d2).abs() > 0.999 {
+            // Check for degenerate triangles (collinear vertices) after collapse,
+            // including collapsing onto one of the other vertices
+            let (Some(d1), Some(d2)) = (
+                (p1 - p).try_normalize(f64::EPSILON),
+                (p2 - p).try_normalize(f64::EPSILON),
+            ) else {
+                return true;
+            };
+            if d1.dot(&d2).abs() > self.tolerances.parallel {
                 return true;
             } // Nearly collinear
 
             // Check if normal flips significantly
             let n = d1.cross(&d2).normalize();
             deleted_flags[k] = false; // Not deleted by this edge collapse
-            if n.dot(&t.n) < 0.2 {
+            if n.dot(&t.n) < self.tolerances.flip {
                 return true;
-            } // Normal flipped too much (original code used 0.2)
+            } // Normal flipped too much
         }
         false
     }
@@ -437,35 +483,34 @@ impl Simplifier {
             }
 
             // --- Initialize Edge Errors ---
-            for t in self.triangles.iter_mut() {
-                if t.deleted {
-                    continue;
-                }
-                for j in 0..3 {
-                    let v0 = t.v[j];
-                    let v1 = t.v[(j + 1) % 3];
-                    let err = {
-                        let vertices = &self.vertices;
-                        let q_v0 = vertices[v0].q;
-                        let q_v1 = vertices[v1].q;
-                        let border = vertices[v0].border && vertices[v1].border;
-                        let det = (q_v0 + q_v1).det(0, 1, 2, 1, 4, 5, 2, 5, 7);
-
-                        if det.abs() > 1e-15 && !border {
-                            0.0 // Replace with actual error calculation logic if needed
-                        } else {
-                            f64::MAX // Replace with fallback error logic if needed
-                        }
-                    };
-                    t.err[j] = err;
-                }
-                t.err[3] = t.err[0].min(t.err[1].min(t.err[2]));
+            let errors: Vec<[f64; 4]> = self
+                .triangles
+                .iter()
+                .map(|t| {
+                    if t.deleted {
+                        return t.err;
+                    }
+                    let [e0, e1, e2] =
+                        [0, 1, 2].map(|j| self.calculate_error(t.v[j], t.v[(j + 1) % 3]).0);
+                    [e0, e1, e2, e0.min(e1.min(e2))]
+                })
+                .collect();
+            for (t, err) in self.triangles.iter_mut().zip(errors) {
+                t.err = err;
             }
         }
     }
 
-    // Perform the main simplification loop
-    fn simplify(&mut self, target_count: usize, aggressiveness: f64, verbose: bool) {
+    // Perform the main simplification loop, collapsing edges until there
+    // are at most target_count triangles or no edge with an error of at
+    // most max_error is left
+    fn simplify(
+        &mut self,
+        target_count: usize,
+        max_error: f64,
+        aggressiveness: f64,
+        verbose: bool,
+    ) {
         let initial_triangle_count = self.triangles.len();
         let mut deleted_triangles = 0;
 
@@ -475,14 +520,25 @@ impl Simplifier {
         let mut deleted0: Vec<bool> = vec![false; max_tcount];
         let mut deleted1: Vec<bool> = vec![false; max_tcount];
 
-        for iteration in 0..100 {
+        let mut iteration = 0;
+        loop {
             let current_triangle_count = initial_triangle_count - deleted_triangles;
             if current_triangle_count <= target_count {
                 break;
             }
 
-            // Update mesh structure (refs, etc.) periodically or if first iteration
-            if iteration == 0 || iteration % 5 == 0 {
+            // Threshold calculation, which once it reaches the maximum error
+            // stays there until an iteration can't collapse anything
+            let threshold = 0.000000001 * (iteration as f64 + 3.0).powf(aggressiveness);
+            let capped = threshold >= max_error;
+            let threshold = threshold.min(max_error);
+            if iteration >= if capped { 10000 } else { 100 } {
+                break;
+            }
+
+            // Update mesh structure (refs, etc.) periodically or if first
+            // iteration, and every iteration once the threshold is capped
+            if iteration == 0 || iteration % 5 == 0 || capped {
                 self.update_mesh(iteration);
                 // Resize temp vectors if max tcount changed after update_mesh
                 let current_max_tcount = self.vertices.iter().map(|v| v.tcount).max().unwrap_or(0);
@@ -497,9 +553,6 @@ impl Simplifier {
                 t.dirty = false;
             }
 
-            // Threshold calculation
-            let threshold = 0.000000001 * (iteration as f64 + 3.0).powf(aggressiveness);
-
             if verbose && iteration % 5 == 0 {
                 println!(
                     "Iteration {iteration} - Triangles: {current_triangle_count} Threshold: {threshold:.1e}"
@@ -507,6 +560,7 @@ impl Simplifier {
             }
 
             // --- Edge Collapse Loop ---
+            let deleted_before = deleted_triangles;
             for tid in 0..self.triangles.len() {
                 // Check triangle status
                 if self.triangles[tid].err[3] > threshold
@@ -560,6 +614,7 @@ impl Simplifier {
 
                         // --- Collapse the edge ---
                         // Update vertex i0
+                        self.merge_values(i0, i1, p_result);
                         self.vertices[i0].p = p_result;
                         let (v0, v1) = if i0 < i1 {
                             let (left, right) = self.vertices.split_at_mut(i1);
@@ -606,6 +661,11 @@ impl Simplifier {
                     break;
                 } // Check target again
             } // End triangle loop (tid)
+
+            if capped && deleted_triangles == deleted_before {
+                break;
+            }
+            iteration += 1;
         } // End iteration loop
 
         // --- Final Cleanup ---
@@ -635,11 +695,15 @@ impl Simplifier {
 
         // 3. Create the new vertex list and populate the remap table
         let mut new_vertices = Vec::with_capacity(new_vertex_count);
+        let mut new_values = Vec::with_capacity(new_vertex_count * self.stride);
         let mut current_new_idx = 0;
         for (old_idx, used) in vertex_used.iter().enumerate() {
             if *used && old_idx < self.vertices.len() {
                 // Bounds check
                 new_vertices.push(self.vertices[old_idx].clone()); // Clone the used vertex data
+                new_values.extend_from_slice(
+                    &self.values[old_idx * self.stride..(old_idx + 1) * self.stride],
+                );
                 vertex_remap[old_idx] = current_new_idx;
                 current_new_idx += 1;
             }
@@ -668,6 +732,7 @@ impl Simplifier {
 
         // 5. Replace old vertices with the compacted list
         self.vertices = new_vertices;
+        self.values = new_values;
         // Refs are implicitly invalid now and would need rebuilding if used further,
         // but compact_mesh is the last step before returning results.
         self.refs.clear();
@@ -685,6 +750,34 @@ impl Simplifier {
     }
 }
 
+/// The result of simplifying a mesh along with where each
+/// part of it came from in the input.
+#[derive(Debug, Clone, Default)]
+pub struct Simplified {
+    pub vertices: Vec<Point3<f64>>,
+    pub faces: Vec<(usize, usize, usize)>,
+    // `stride` values per vertex interpolated through each collapse
+    pub values: Vec<f64>,
+    // the input vertex nearest to each vertex, for values like
+    // groupings which can't be interpolated
+    pub vertex_source: Vec<usize>,
+    // the input face each face came from
+    pub face_source: Vec<usize>,
+}
+
+impl Simplified {
+    /// The input returned unchanged.
+    fn identity(vertices: &[Point3<f64>], faces: &[(usize, usize, usize)], values: &[f64]) -> Self {
+        Simplified {
+            vertices: vertices.to_vec(),
+            faces: faces.to_vec(),
+            values: values.to_vec(),
+            vertex_source: (0..vertices.len()).collect(),
+            face_source: (0..faces.len()).collect(),
+        }
+    }
+}
+
 /// Simplifies a mesh using the Fast Quadric Mesh Simplification algorithm.
 ///
 /// # Arguments
@@ -706,7 +799,61 @@ pub fn simplify_mesh(
     aggressiveness: f64,
     verbose: bool, // Added verbose flag
 ) -> SimplifiedMesh {
+    let result = simplify_mesh_values(
+        input_vertices,
+        input_faces,
+        &[],
+        0,
+        target_count,
+        f64::INFINITY,
+        aggressiveness,
+        verbose,
+        &Tolerances::default(),
+    );
+    (result.vertices, result.faces)
+}
+
+/// Simplifies a mesh like `simplify_mesh` while carrying per vertex values
+/// such as UV coordinates through every edge collapse, interpolating the
+/// values of the two vertices at the position they collapse to.
+///
+/// # Arguments
+///
+/// * `input_vertices` - Slice of vertex positions.
+/// * `input_faces` - Slice of triangle faces, represented as tuples of vertex indices.
+/// * `values` - `stride` values for every vertex, or empty with a `stride` of zero.
+/// * `stride` - The number of values per vertex.
+/// * `target_count` - The desired number of faces in the simplified mesh.
+/// * `max_error` - Stop once every remaining edge collapse would have a quadric
+///   error above this, or infinity to only stop at `target_count`.
+/// * `aggressiveness` - Controls how aggressively to collapse edges.
+/// * `verbose` - Print progress information during simplification.
+/// * `tolerances` - When a collapse would make a face degenerate or flip it.
+///
+/// # Returns
+///
+/// The simplified mesh with its interpolated values and the source of every
+/// vertex and face. Returns the original mesh if target_count is >= current
+/// face count or the input is invalid.
+#[allow(clippy::too_many_arguments)]
+pub fn simplify_mesh_values(
+    input_vertices: &[Point3<f64>],
+    input_faces: &[(usize, usize, usize)],
+    values: &[f64],
+    stride: usize,
+    target_count: usize,
+    max_error: f64,
+    aggressiveness: f64,
+    verbose: bool,
+    tolerances: &Tolerances,
+) -> Simplified {
     // Basic checks
+    if values.len() != input_vertices.len() * stride {
+        if verbose {
+            println!("Values don't match the vertex count, returning original.");
+        }
+        return Simplified::identity(input_vertices, input_faces, values);
+    }
     if target_count >= input_faces.len() {
         if verbose {
             println!(
@@ -715,19 +862,19 @@ pub fn simplify_mesh(
                 input_faces.len()
             );
         }
-        return (input_vertices.to_vec(), input_faces.to_vec());
+        return Simplified::identity(input_vertices, input_faces, values);
     }
     if input_faces.is_empty() || input_vertices.len() < 3 {
         if verbose {
             println!("Input mesh is empty or too small, returning original.");
         }
-        return (input_vertices.to_vec(), input_faces.to_vec());
+        return Simplified::identity(input_vertices, input_faces, values);
     }
-    if target_count == 0 {
+    if target_count == 0 && max_error == f64::INFINITY {
         if verbose {
             println!("Target count is 0, returning empty mesh.");
         }
-        return (Vec::new(), Vec::new());
+        return Simplified::default();
     }
 
     if verbose {
@@ -735,21 +882,26 @@ pub fn simplify_mesh(
         println!("  Input vertices: {}", input_vertices.len());
         println!("  Input faces: {}", input_faces.len());
         println!("  Target faces: {target_count}");
+        println!("  Max error: {max_error:.1e}");
         println!("  Aggressiveness: {aggressiveness}");
     }
 
-    let mut simplifier = Simplifier::new(input_vertices, input_faces);
+    let mut simplifier = Simplifier::new(input_vertices, input_faces, values, stride, tolerances);
 
-    simplifier.simplify(target_count, aggressiveness, verbose);
+    simplifier.simplify(target_count, max_error, aggressiveness, verbose);
 
+    let (vertices, faces) = simplifier.get_result();
     if verbose {
-        let (final_verts, final_faces) = simplifier.get_result();
         println!("Simplification finished:");
-        println!("  Output vertices: {}", final_verts.len());
-        println!("  Output faces: {}", final_faces.len());
-        (final_verts, final_faces)
-    } else {
-        simplifier.get_result()
+        println!("  Output vertices: {}", vertices.len());
+        println!("  Output faces: {}", faces.len());
+    }
+    Simplified {
+        vertices,
+        faces,
+        vertex_source: simplifier.vertices.iter().map(|v| v.source).collect(),
+        face_source: simplifier.triangles.iter().map(|t| t.source).collect(),
+        values: simplifier.values,
     }
 }
 
@@ -802,4 +954,112 @@ mod tests {
         println!("Simplified Vertices: {}", simplified_vertices.len());
         println!("Simplified Faces: {}", simplified_faces.len());
     }
+
+    #[test]
+    fn test_simplify_attributes() {
+        use crate::attributes::{Grouping, GroupingKind};
+        use crate::creation::create_box;
+        use approx::assert_relative_eq;
+        use nalgebra::{Vector2, Vector4};
+
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]).subdivide_to_size(0.2).unwrap();
+        let count = mesh.vertices.len();
+        // values which are a linear function of position so collapses
+        // onto an edge should interpolate them exactly
+        mesh.attributes_vertex.uv = vec![
+            mesh.vertices
+                .iter()
+                .map(|v| Vector2::new(v.x, v.y))
+                .collect(),
+        ];
+        mesh.attributes_vertex.colors = vec![
+            mesh.vertices
+                .iter()
+                .map(|v| Vector4::new(((v.z + 0.5) * 200.0).round() as u8, 0, 0, 255))
+                .collect(),
+        ];
+        mesh.attributes_face.groupings.push(Grouping {
+            name: "face".to_string(),
+            kind: GroupingKind::GroupingIndex,
+            indices: (0..mesh.faces.len()).collect(),
+            labels: Vec::new(),
+        });
+
+        let target = mesh.faces.len() / 4;
+        let simplified = mesh.simplify(target, 7.0);
+        assert!(simplified.faces.len() < mesh.faces.len());
+        assert!(simplified.vertices.len() < count);
+
+        let uv = &simplified.attributes_vertex.uv[0];
+        let colors = &simplified.attributes_vertex.colors[0];
+        assert_eq!(uv.len(), simplified.vertices.len());
+        for ((v, uv), c) in simplified.vertices.iter().zip(uv).zip(colors) {
+            assert_relative_eq!(uv.x, v.x, epsilon = 1e-10);
+            assert_relative_eq!(uv.y, v.y, epsilon = 1e-10);
+            assert!((c.x as f64 - (v.z + 0.5) * 200.0).abs() <= 1.0);
+        }
+
+        // every face keeps the grouping of the face it came from
+        let groups = &simplified.attributes_face.groupings[0].indices;
+        assert_eq!(groups.len(), simplified.faces.len());
+        assert!(groups.iter().all(|g| *g < mesh.faces.len()));
+    }
+
+    #[test]
+    fn test_simplify_stopping() {
+        use crate::creation::create_box;
+        use crate::exchange::{MeshFormat, load_mesh};
+        use approx::assert_relative_eq;
+
+        let mesh = create_box(&[1.0, 2.0, 3.0]).subdivide_to_size(0.2).unwrap();
+        let count = mesh.faces.len();
+
+        // every collapse within a flat side of the box has zero error
+        let lossless = mesh.simplify_lossless();
+        assert!(lossless.faces.len() < count / 10);
+        assert_relative_eq!(lossless.area(), mesh.area(), epsilon = 1e-8);
+        let (lower, upper) = lossless.bounds().unwrap();
+        let (expected_lower, expected_upper) = mesh.bounds().unwrap();
+        assert_relative_eq!(lower, expected_lower, epsilon = 1e-10);
+        assert_relative_eq!(upper, expected_upper, epsilon = 1e-10);
+        // which is judged relative to the size of the mesh so a tiny
+        // curved mesh isn't flattened
+        let sphere = crate::creation::create_sphere(1.0, [32, 16]).unwrap();
+        let tiny = sphere
+            .apply_transform(&nalgebra::Matrix4::new_scaling(1e-7))
+            .unwrap();
+        assert_eq!(
+            tiny.simplify_lossless().faces.len(),
+            sphere.simplify_lossless().faces.len()
+        );
+
+        let half = mesh.simplify_to_ratio(0.5, 7.0).unwrap();
+        assert!(half.faces.len() <= count / 2 && half.faces.len() > count / 4);
+        assert!(mesh.simplify_to_ratio(1.5, 7.0).is_err());
+
+        // a larger error allows more collapses
+        let sphere = load_mesh(
+            include_bytes!("../../../test/data/fuze.obj"),
+            MeshFormat::OBJ,
+        )
+        .unwrap();
+        let fine = sphere.simplify_to_error(1e-7, 7.0).unwrap();
+        let coarse = sphere.simplify_to_error(1e-4, 7.0).unwrap();
+        assert!(fine.faces.len() < sphere.faces.len());
+        assert!(coarse.faces.len() < fine.faces.len());
+        assert!(sphere.simplify_to_error(-1.0, 7.0).is_err());
+
+        // the certified deviation grows as more faces are removed
+        let (_, zero) = lossless.simplify_certified(lossless.faces.len(), 7.0, 4);
+        assert!(zero < 1e-12);
+        let (_, flat) = mesh.simplify_certified(lossless.faces.len(), 7.0, 4);
+        assert!(flat < 1e-8);
+        let (small, low) = sphere.simplify_certified(sphere.faces.len() / 2, 7.0, 1);
+        let (tiny, high) = sphere.simplify_certified(sphere.faces.len() / 10, 7.0, 1);
+        assert!(tiny.faces.len() < small.faces.len());
+        assert!(low > 0.0 && low < high);
+        // a finer grid can only find a larger distance
+        assert!(high <= tiny.hausdorff(&sphere, 2).unwrap() + 1e-12);
+        assert!(sphere.simplify_to_error(f64::NAN, 7.0).is_err());
+    }
 }
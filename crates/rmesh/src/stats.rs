@@ -0,0 +1,255 @@
+use anyhow::{Result, anyhow};
+use rayon::prelude::*;
+
+use crate::mesh::Trimesh;
+
+/// A per-element measure of mesh quality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    // the length of every unique edge
+    EdgeLength,
+    // the area of every face
+    FaceArea,
+    // the angle in radians between the normals of adjacent faces
+    DihedralAngle,
+    // the aspect ratio of every face where equilateral is 1.0
+    AspectRatio,
+}
+
+impl Metric {
+    /// Parse a metric from a name like `edge_length`.
+    pub fn from_string(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "edge_length" | "edge_lengths" => Ok(Metric::EdgeLength),
+            "face_area" | "face_areas" | "area" => Ok(Metric::FaceArea),
+            "dihedral_angle" | "dihedral_angles" | "dihedral" => Ok(Metric::DihedralAngle),
+            "aspect_ratio" | "aspect_ratios" => Ok(Metric::AspectRatio),
+            _ => Err(anyhow!("Unknown metric: {name}")),
+        }
+    }
+}
+
+/// The number of values that fall into each of a set of equal width bins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    // the number of values in each bin
+    pub counts: Vec<usize>,
+    // the edges of the bins, which is one longer than `counts`
+    pub edges: Vec<f64>,
+}
+
+/// Count values into equal width bins spanning their range, matching
+/// `numpy.histogram` where the last bin includes its upper edge.
+/// Values which aren't finite like the aspect ratio of a degenerate
+/// face are ignored.
+///
+/// Parameters
+/// ------------
+/// values
+///   The values to count.
+/// bins
+///   The number of bins.
+///
+/// Returns
+/// ------------
+/// histogram
+///   The counts and bin edges, or an error if `bins` is zero.
+pub fn histogram(values: &[f64], bins: usize) -> Result<Histogram> {
+    if bins == 0 {
+        return Err(anyhow!("Histogram must have at least one bin"));
+    }
+    let finite = values.iter().filter(|v| v.is_finite());
+    let (mut lower, mut upper) = finite
+        .clone()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(l, u), v| {
+            (l.min(*v), u.max(*v))
+        });
+    if lower > upper {
+        // no finite values so use the same unit range as numpy
+        (lower, upper) = (0.0, 1.0);
+    } else if lower == upper {
+        (lower, upper) = (lower - 0.5, upper + 0.5);
+    }
+
+    let width = (upper - lower) / bins as f64;
+    let mut counts = vec![0; bins];
+    for value in finite {
+        let bin = ((value - lower) / width) as usize;
+        counts[bin.min(bins - 1)] += 1;
+    }
+    let edges = (0..=bins)
+        .map(|i| {
+            if i == bins {
+                upper
+            } else {
+                lower + width * i as f64
+            }
+        })
+        .collect();
+    Ok(Histogram { counts, edges })
+}
+
+/// Compute percentiles of values using linear interpolation between
+/// the closest ranks, matching the default of `numpy.percentile`.
+/// Values which aren't finite are ignored.
+///
+/// Parameters
+/// ------------
+/// values
+///   The values to compute percentiles of.
+/// percents
+///   The percentiles to compute between 0.0 and 100.0.
+///
+/// Returns
+/// ------------
+/// percentiles
+///   The value at each percentile, or an error if there are no finite
+///   values or a percentile is out of range.
+pub fn percentiles(values: &[f64], percents: &[f64]) -> Result<Vec<f64>> {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if sorted.is_empty() {
+        return Err(anyhow!("No finite values to compute percentiles of"));
+    }
+    sorted.sort_unstable_by(f64::total_cmp);
+
+    percents
+        .iter()
+        .map(|p| {
+            if !(0.0..=100.0).contains(p) {
+                return Err(anyhow!("Percentile {p} is not between 0 and 100"));
+            }
+            let rank = p / 100.0 * (sorted.len() - 1) as f64;
+            let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+            let fraction = rank - below as f64;
+            Ok(sorted[below] + (sorted[above] - sorted[below]) * fraction)
+        })
+        .collect()
+}
+
+impl Trimesh {
+    /// The length of every unique edge in the mesh.
+    pub fn edge_lengths(&self) -> Vec<f64> {
+        let mut unique: Vec<[usize; 2]> = self
+            .edges()
+            .into_iter()
+            .map(|[a, b]| [a.min(b), a.max(b)])
+            .collect();
+        unique.sort_unstable();
+        unique.dedup();
+        unique
+            .par_iter()
+            .map(|[a, b]| (self.vertices[*a] - self.vertices[*b]).norm())
+            .collect()
+    }
+
+    /// The aspect ratio of every face, which is the longest edge divided
+    /// by the shortest altitude scaled so an equilateral triangle is 1.0
+    /// and slivers are large. Degenerate faces are infinite.
+    pub fn aspect_ratios(&self) -> Vec<f64> {
+        let scale = 3.0_f64.sqrt() / 2.0;
+        self.par_triangles()
+            .zip(self.faces_area().par_iter())
+            .map(|([a, b, c], area)| {
+                let longest = (b - a).norm().max((c - b).norm()).max((a - c).norm());
+                if *area <= 0.0 {
+                    return f64::INFINITY;
+                }
+                // the shortest altitude is the one onto the longest edge
+                scale * longest / (2.0 * area / longest)
+            })
+            .collect()
+    }
+
+    /// Compute a quality metric for every element of the mesh.
+    ///
+    /// Parameters
+    /// ------------
+    /// metric
+    ///   Which metric to compute.
+    ///
+    /// Returns
+    /// ------------
+    /// values
+    ///   The value of the metric for every edge, face or adjacent face pair.
+    pub fn metric(&self, metric: Metric) -> Vec<f64> {
+        match metric {
+            Metric::EdgeLength => self.edge_lengths(),
+            Metric::FaceArea => self.faces_area(),
+            Metric::DihedralAngle => self.face_adjacency_angles(),
+            Metric::AspectRatio => self.aspect_ratios(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_histogram() {
+        let values = [0.0, 1.0, 1.0, 2.0, 3.0, 4.0, f64::NAN];
+        let h = histogram(&values, 4).unwrap();
+        assert_eq!(h.counts, vec![1, 2, 1, 2]);
+        assert_eq!(h.edges, vec![0.0, 1.0, 2.0, 3.0, 4.0]);
+
+        // a single value is centered in a unit range
+        let h = histogram(&[2.0, 2.0], 2).unwrap();
+        assert_eq!(h.counts, vec![0, 2]);
+        assert_eq!(h.edges, vec![1.5, 2.0, 2.5]);
+
+        assert_eq!(histogram(&[], 3).unwrap().counts, vec![0, 0, 0]);
+        assert!(histogram(&values, 0).is_err());
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let values = [4.0, 1.0, 3.0, 2.0, f64::INFINITY];
+        assert_eq!(
+            percentiles(&values, &[0.0, 50.0, 100.0, 25.0]).unwrap(),
+            vec![1.0, 2.5, 4.0, 1.75]
+        );
+        assert!(percentiles(&values, &[101.0]).is_err());
+        assert!(percentiles(&[], &[50.0]).is_err());
+    }
+
+    #[test]
+    fn test_metrics() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+
+        // 12 sides plus a diagonal across each of the 6 faces
+        let lengths = mesh.metric(Metric::EdgeLength);
+        assert_eq!(lengths.len(), 18);
+        let h = histogram(&lengths, 2).unwrap();
+        assert_eq!(h.counts, vec![12, 6]);
+        assert_relative_eq!(h.edges[2], 2.0_f64.sqrt());
+
+        let areas = mesh.metric(Metric::FaceArea);
+        assert!(areas.iter().all(|a| (a - 0.5).abs() < 1e-10));
+
+        // coplanar pairs across diagonals and right angles across sides
+        let angles = mesh.metric(Metric::DihedralAngle);
+        assert_eq!(angles.len(), 18);
+        assert_eq!(angles.iter().filter(|a| a.abs() < 1e-10).count(), 6);
+
+        // a right isosceles triangle has a longest edge of sqrt(2)
+        // and an altitude onto it of sqrt(2) / 2
+        for ratio in mesh.metric(Metric::AspectRatio) {
+            assert_relative_eq!(ratio, 3.0_f64.sqrt(), epsilon = 1e-10);
+        }
+        let equilateral = Trimesh::from_slice(
+            &[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.5, 3.0_f64.sqrt() / 2.0, 0.0],
+            &[0, 1, 2],
+        )
+        .unwrap();
+        assert_relative_eq!(equilateral.aspect_ratios()[0], 1.0, epsilon = 1e-10);
+
+        assert_eq!(
+            Metric::from_string("Aspect_Ratio").unwrap(),
+            Metric::AspectRatio
+        );
+        assert!(Metric::from_string("volume").is_err());
+    }
+}
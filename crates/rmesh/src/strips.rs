@@ -0,0 +1,155 @@
+use ahash::AHashMap;
+
+use crate::mesh::Trimesh;
+
+/// Convert triangle strips back into triangles, flipping every other
+/// triangle so they all keep the winding of the first triangle.
+///
+/// Parameters
+/// ------------
+/// strips
+///   Sequences of vertex indices where every three consecutive
+///   indices are a triangle.
+///
+/// Returns
+/// ------------
+/// faces
+///   The triangles of every strip in order.
+pub fn strips_to_faces(strips: &[Vec<usize>]) -> Vec<(usize, usize, usize)> {
+    strips
+        .iter()
+        .flat_map(|strip| {
+            strip.windows(3).enumerate().map(|(i, w)| {
+                if i % 2 == 0 {
+                    (w[0], w[1], w[2])
+                } else {
+                    (w[1], w[0], w[2])
+                }
+            })
+        })
+        .collect()
+}
+
+impl Trimesh {
+    /// Convert the faces into triangle strips for renderers which draw
+    /// strips faster than lists. Strips are grown greedily across the
+    /// face adjacency from each face that isn't in a strip yet, trying
+    /// each edge of the starting face and keeping the longest strip.
+    /// Every face appears in exactly one strip with its winding
+    /// preserved, and faces with no usable neighbor are a strip of one.
+    ///
+    /// Returns
+    /// ------------
+    /// strips
+    ///   Sequences of vertex indices which can be converted back
+    ///   to faces with `strips_to_faces`.
+    pub fn triangle_strips(&self) -> Vec<Vec<usize>> {
+        // the faces which contain each directed edge
+        let mut edges: AHashMap<(usize, usize), Vec<usize>> =
+            AHashMap::with_capacity(self.faces.len() * 3);
+        for (index, f) in self.faces.iter().enumerate() {
+            for edge in [(f.0, f.1), (f.1, f.2), (f.2, f.0)] {
+                edges.entry(edge).or_default().push(index);
+            }
+        }
+
+        let mut used = vec![false; self.faces.len()];
+
+        // grow a strip from a starting triangle without marking faces as used
+        let grow = |start: [usize; 3], used: &[bool], first: usize| {
+            let mut strip = start.to_vec();
+            let mut faces = vec![first];
+            loop {
+                let n = strip.len();
+                // odd triangles in a strip are wound backwards
+                let edge = if n % 2 == 1 {
+                    (strip[n - 1], strip[n - 2])
+                } else {
+                    (strip[n - 2], strip[n - 1])
+                };
+                let next = edges.get(&edge).and_then(|candidates| {
+                    candidates
+                        .iter()
+                        .find(|c| !used[**c] && !faces.contains(*c))
+                });
+                let Some(&next) = next else {
+                    break;
+                };
+                // the vertex of the next face that isn't on the shared edge
+                let f = self.faces[next];
+                let vertex = [f.0, f.1, f.2]
+                    .into_iter()
+                    .find(|v| *v != edge.0 && *v != edge.1)
+                    .unwrap_or(f.0);
+                strip.push(vertex);
+                faces.push(next);
+            }
+            (strip, faces)
+        };
+
+        let mut strips = Vec::new();
+        for (index, f) in self.faces.iter().enumerate() {
+            if used[index] {
+                continue;
+            }
+            // start with each rotation of the face and keep the longest
+            let (strip, faces) = [[f.0, f.1, f.2], [f.1, f.2, f.0], [f.2, f.0, f.1]]
+                .into_iter()
+                .map(|start| grow(start, &used, index))
+                .max_by_key(|(_, faces)| faces.len())
+                .unwrap();
+            for face in faces {
+                used[face] = true;
+            }
+            strips.push(strip);
+        }
+        strips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use crate::exchange::{MeshFormat, load_mesh};
+
+    /// Check that strips contain every face exactly once with the same winding.
+    fn check_strips(mesh: &Trimesh, strips: &[Vec<usize>]) {
+        let rotate = |f: (usize, usize, usize)| {
+            // rotate so the smallest index is first, keeping winding
+            let min = f.0.min(f.1).min(f.2);
+            if f.0 == min {
+                f
+            } else if f.1 == min {
+                (f.1, f.2, f.0)
+            } else {
+                (f.2, f.0, f.1)
+            }
+        };
+        let mut expected: Vec<_> = mesh.faces.iter().map(|f| rotate(*f)).collect();
+        let mut decoded: Vec<_> = strips_to_faces(strips).into_iter().map(rotate).collect();
+        expected.sort_unstable();
+        decoded.sort_unstable();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_triangle_strips() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        let strips = mesh.triangle_strips();
+        check_strips(&mesh, &strips);
+        // at least some faces should have been joined
+        assert!(strips.len() < mesh.faces.len());
+
+        let data = include_bytes!("../../../test/data/fuze.obj");
+        let mesh = load_mesh(data, MeshFormat::OBJ).unwrap();
+        let strips = mesh.triangle_strips();
+        check_strips(&mesh, &strips);
+        // strips should be much shorter than a list of triangles
+        let length: usize = strips.iter().map(|s| s.len()).sum();
+        assert!(length < mesh.faces.len() * 2);
+
+        assert!(Trimesh::default().triangle_strips().is_empty());
+    }
+}
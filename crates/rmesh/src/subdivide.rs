@@ -0,0 +1,163 @@
+use ahash::AHashMap;
+use anyhow::{Result, anyhow};
+
+use crate::mesh::Trimesh;
+
+/// The most passes `subdivide_to_size` will make, which halves the
+/// longest edge of a face each pass so is only reached by huge ratios.
+const MAX_PASSES: usize = 64;
+
+impl Trimesh {
+    /// Split every marked edge at its midpoint and retriangulate the
+    /// faces around it, so the result has no T-junctions.
+    fn split_edges(&self, edges: &[(usize, usize)]) -> Result<Self> {
+        let mut midpoints: Vec<(usize, usize)> =
+            edges.iter().map(|(a, b)| (*a.min(b), *a.max(b))).collect();
+        midpoints.sort_unstable();
+        midpoints.dedup();
+        let index: AHashMap<(usize, usize), usize> = midpoints
+            .iter()
+            .enumerate()
+            .map(|(i, edge)| (*edge, self.vertices.len() + i))
+            .collect();
+        let midpoint = |a: usize, b: usize| index.get(&(a.min(b), a.max(b))).copied();
+
+        let mut vertices = self.vertices.clone();
+        vertices.extend(
+            midpoints
+                .iter()
+                .map(|(a, b)| nalgebra::center(&self.vertices[*a], &self.vertices[*b])),
+        );
+
+        let mut faces = Vec::with_capacity(self.faces.len() * 2);
+        // the face each new face was split from
+        let mut parents = Vec::with_capacity(self.faces.len() * 2);
+        for (parent, f) in self.faces.iter().enumerate() {
+            let corners = [f.0, f.1, f.2];
+            let mids = [0, 1, 2].map(|i| midpoint(corners[i], corners[(i + 1) % 3]));
+            let split: Vec<(usize, usize, usize)> = match mids {
+                [None, None, None] => vec![*f],
+                [Some(ab), Some(bc), Some(ca)] => {
+                    vec![(f.0, ab, ca), (ab, f.1, bc), (ca, bc, f.2), (ab, bc, ca)]
+                }
+                _ => {
+                    // rotate so the first edge is split and the last isn't,
+                    // which covers both one and two split edges
+                    let start = (0..3)
+                        .find(|i| mids[*i].is_some() && mids[(i + 2) % 3].is_none())
+                        .unwrap_or(0);
+                    let [a, b, c] = [0, 1, 2].map(|i| corners[(start + i) % 3]);
+                    let ab = mids[start].unwrap_or(a);
+                    match mids[(start + 1) % 3] {
+                        Some(bc) => vec![(ab, b, bc), (a, ab, bc), (a, bc, c)],
+                        None => vec![(a, ab, c), (ab, b, c)],
+                    }
+                }
+            };
+            parents.extend(std::iter::repeat_n(parent, split.len()));
+            faces.extend(split);
+        }
+
+        let mut result = Trimesh::new(
+            vertices,
+            faces,
+            Some(
+                self.attributes_vertex.interpolate(
+                    self.vertices.len(),
+                    &midpoints
+                        .iter()
+                        .map(|(a, b)| (*a, *b, 0.5))
+                        .collect::<Vec<_>>(),
+                ),
+            ),
+            Some(self.attributes_face.select(&parents, self.faces.len())),
+        )?;
+        result.materials = self.materials.clone();
+        result.source = self.source.clone();
+        Ok(result)
+    }
+
+    /// Subdivide faces until no edge is longer than a length, splitting
+    /// the longest edge of each face that is too long at its midpoint.
+    /// Faces sharing a split edge are split with it so the mesh stays
+    /// watertight, and vertex attributes like UV coordinates are
+    /// interpolated onto the new vertices.
+    ///
+    /// Parameters
+    /// ------------
+    /// max_edge
+    ///   The longest allowed edge length.
+    ///
+    /// Returns
+    /// ------------
+    /// subdivided
+    ///   A new mesh or an error if `max_edge` isn't positive and finite.
+    pub fn subdivide_to_size(&self, max_edge: f64) -> Result<Self> {
+        if max_edge <= 0.0 || !max_edge.is_finite() {
+            return Err(anyhow!(
+                "Edge length must be positive and finite: {max_edge}"
+            ));
+        }
+        let mut mesh = self.clone();
+
+        for _ in 0..MAX_PASSES {
+            let length = |a: usize, b: usize| (mesh.vertices[a] - mesh.vertices[b]).norm();
+            let marked: Vec<(usize, usize)> = mesh
+                .faces
+                .iter()
+                .filter_map(|f| {
+                    [(f.0, f.1), (f.1, f.2), (f.2, f.0)]
+                        .into_iter()
+                        .max_by(|x, y| length(x.0, x.1).total_cmp(&length(y.0, y.1)))
+                        .filter(|(a, b)| length(*a, *b) > max_edge)
+                })
+                .collect();
+            if marked.is_empty() {
+                break;
+            }
+            mesh = mesh.split_edges(&marked)?;
+        }
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_subdivide_to_size() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        let subdivided = mesh.subdivide_to_size(0.3).unwrap();
+        assert!(subdivided.edge_lengths().iter().all(|l| *l <= 0.3));
+        assert_relative_eq!(subdivided.area(), mesh.area(), epsilon = 1e-10);
+        assert_eq!(subdivided.bounds(), mesh.bounds());
+
+        // every edge is shared by exactly two faces
+        let mut edges: Vec<[usize; 2]> = subdivided.edges();
+        let total = edges.len();
+        edges.iter_mut().for_each(|e| e.sort());
+        edges.sort();
+        edges.dedup();
+        assert_eq!(edges.len() * 2, total);
+
+        // UV coordinates are interpolated onto new vertices
+        let mut mesh = mesh;
+        let uv = mesh.vertices.iter().map(|v| v.xy().coords).collect();
+        mesh.attributes_vertex.uv.push(uv);
+        let subdivided = mesh.subdivide_to_size(0.5).unwrap();
+        for (v, uv) in subdivided
+            .vertices
+            .iter()
+            .zip(subdivided.attributes_vertex.uv[0].iter())
+        {
+            assert_relative_eq!(v.xy().coords, uv);
+        }
+
+        // a mesh already small enough is unchanged
+        assert_eq!(mesh.subdivide_to_size(10.0).unwrap().faces, mesh.faces);
+        assert!(mesh.subdivide_to_size(0.0).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::Result;
+use image::imageops::sample_bilinear;
+use nalgebra::{Vector2, Vector4};
+use rayon::prelude::*;
+
+use crate::attributes::{Color, DEFAULT_COLOR, GroupingKind, Material};
+use crate::mesh::Trimesh;
+
+/// The color of a material at a UV coordinate, which is the texture
+/// if it has one or the flat diffuse color otherwise. UV coordinates
+/// outside of the unit square wrap around like a repeating texture.
+fn material_color(material: Option<&Material>, uv: &Vector2<f64>) -> Vector4<u8> {
+    let Some(Material::Simple(material)) = material else {
+        return DEFAULT_COLOR;
+    };
+    if let Some(image) = &material.image {
+        // wrap without moving the edges of the unit square onto each other
+        let wrap = |c: f64| {
+            if (0.0..=1.0).contains(&c) {
+                c
+            } else {
+                c.rem_euclid(1.0)
+            }
+        };
+        // images start at the top left while UV starts at the bottom left
+        let (u, v) = (wrap(uv.x), 1.0 - wrap(uv.y));
+        if let Some(pixel) = sample_bilinear(image, u as f32, v as f32) {
+            return Vector4::from(pixel.0);
+        }
+    }
+    let alpha = material
+        .alpha
+        .map_or(255, |a| (a.clamp(0.0, 1.0) * 255.0).round() as u8);
+    match material.diffuse {
+        Some(d) => {
+            let d = d.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+            Vector4::new(d.x, d.y, d.z, alpha)
+        }
+        None => DEFAULT_COLOR,
+    }
+}
+
+impl Trimesh {
+    /// Does any material of the mesh have a texture image.
+    pub fn has_textures(&self) -> bool {
+        self.materials
+            .iter()
+            .any(|m| matches!(m, Material::Simple(s) if s.image.is_some()))
+    }
+
+    /// Sample the material of every vertex at its UV coordinate into a
+    /// color, using the material of the first face that references it.
+    ///
+    /// Returns
+    /// ------------
+    /// colors
+    ///   A color for every vertex or `None` if the mesh has no UV
+    ///   coordinates for every vertex or no textures to sample.
+    pub fn texture_colors(&self) -> Option<Color> {
+        let uv = self
+            .attributes_vertex
+            .uv
+            .first()
+            .filter(|uv| uv.len() == self.vertices.len())?;
+        if !self.has_textures() {
+            return None;
+        }
+
+        // without material groups every face uses the first material
+        let groups = self
+            .attributes_face
+            .groupings
+            .iter()
+            .find(|g| {
+                matches!(g.kind, GroupingKind::MaterialIndex) && g.indices.len() == self.faces.len()
+            })
+            .map(|g| g.indices.as_slice());
+        let mut material = vec![0; self.vertices.len()];
+        let mut seen = vec![false; self.vertices.len()];
+        for (index, f) in self.faces.iter().enumerate() {
+            for v in [f.0, f.1, f.2] {
+                if !seen[v] {
+                    seen[v] = true;
+                    material[v] = groups.map_or(0, |g| g[index]);
+                }
+            }
+        }
+
+        Some(
+            uv.par_iter()
+                .zip(material.par_iter())
+                .map(|(uv, m)| material_color(self.materials.get(*m), uv))
+                .collect(),
+        )
+    }
+
+    /// Bake the textures of the mesh into vertex colors for formats
+    /// which can't store textures, first subdividing so no edge is
+    /// longer than a length so the colors can capture texture detail.
+    ///
+    /// Parameters
+    /// ------------
+    /// max_edge
+    ///   The longest edge before sampling colors, or infinity to sample
+    ///   the existing vertices without subdividing.
+    ///
+    /// Returns
+    /// ------------
+    /// baked
+    ///   A new mesh with the baked colors as its first vertex colors,
+    ///   which is unchanged if there are no textures to bake.
+    pub fn bake_textures(&self, max_edge: f64) -> Result<Self> {
+        let mut mesh = if max_edge.is_finite() && self.has_textures() {
+            self.subdivide_to_size(max_edge)?
+        } else {
+            self.clone()
+        };
+        if let Some(colors) = mesh.texture_colors() {
+            mesh.attributes_vertex.colors.insert(0, colors);
+        }
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::attributes::SimpleMaterial;
+    use crate::creation::create_box;
+    use crate::exchange::{ExportOptions, MeshFormat, export_mesh};
+    use image::{DynamicImage, Rgba, RgbaImage};
+
+    #[test]
+    fn test_bake_textures() {
+        // a texture which is red on the left and blue on the right
+        let image = RgbaImage::from_fn(4, 4, |x, _| {
+            if x < 2 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                Rgba([0, 0, 255, 255])
+            }
+        });
+        let mut material = SimpleMaterial::new("checker");
+        material.image = Some(DynamicImage::ImageRgba8(image));
+
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        assert!(mesh.texture_colors().is_none());
+        // map X from -0.5..0.5 to U from 0.0..1.0
+        let uv = mesh
+            .vertices
+            .iter()
+            .map(|v| Vector2::new(v.x + 0.5, 0.5))
+            .collect();
+        mesh.attributes_vertex.uv.push(uv);
+        mesh.materials.push(Material::Simple(material));
+        assert!(mesh.has_textures());
+
+        let colors = mesh.texture_colors().unwrap();
+        for (v, c) in mesh.vertices.iter().zip(colors.iter()) {
+            if v.x < 0.0 {
+                assert_eq!(*c, Vector4::new(255, 0, 0, 255));
+            } else {
+                assert_eq!(*c, Vector4::new(0, 0, 255, 255));
+            }
+        }
+
+        // subdividing adds vertices which sample the middle of the texture
+        let baked = mesh.bake_textures(0.5).unwrap();
+        assert!(baked.vertices.len() > mesh.vertices.len());
+        assert_eq!(
+            baked.attributes_vertex.colors[0].len(),
+            baked.vertices.len()
+        );
+        assert!(
+            baked.attributes_vertex.colors[0]
+                .iter()
+                .any(|c| c.x > 0 && c.z > 0)
+        );
+        let baked = mesh.bake_textures(f64::INFINITY).unwrap();
+        assert_eq!(baked.vertices.len(), mesh.vertices.len());
+        assert_eq!(baked.attributes_vertex.colors[0], colors);
+
+        // exporting to a format without textures bakes them when asked
+        let options = ExportOptions {
+            bake_textures: Some(f64::INFINITY),
+            ..Default::default()
+        };
+        let ply = export_mesh(&mesh, MeshFormat::PLY, &options).unwrap();
+        assert!(ply.contains("property uchar red"));
+        assert!(ply.contains(" 255 0 0 255\n"));
+        let ply = export_mesh(&mesh, MeshFormat::PLY, &ExportOptions::default()).unwrap();
+        assert!(!ply.contains("property uchar red"));
+    }
+}
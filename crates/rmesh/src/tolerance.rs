@@ -0,0 +1,103 @@
+use crate::mesh::Trimesh;
+use crate::process::MERGE_TOLERANCE;
+
+/// The tolerances algorithms use to decide when floating point values
+/// are close enough to be treated as equal. The defaults suit a model
+/// around one unit across, and `scaled` or `Trimesh::tolerances` adjust
+/// the lengths for much smaller or larger models.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerances {
+    // vertices closer than this distance are the same vertex
+    pub merge: f64,
+    // a cross product with a smaller norm, i.e. twice the area of a
+    // triangle, has no meaningful direction when fitting a plane
+    pub zero: f64,
+    // the sine of the angle below which three points on the
+    // boundary of a facet are on a straight line
+    pub collinear: f64,
+    // the cosine of the angle above which two edges out of a vertex
+    // are parallel and a simplification collapse would be degenerate
+    pub parallel: f64,
+    // the smallest cosine of the angle a face normal may turn
+    // through during a simplification collapse
+    pub flip: f64,
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Self {
+            merge: MERGE_TOLERANCE,
+            zero: 1e-10,
+            collinear: 1e-10,
+            parallel: 0.999,
+            flip: 0.2,
+        }
+    }
+}
+
+impl Tolerances {
+    /// The tolerances for a model of a size, scaling the lengths and
+    /// areas and leaving the angles alone.
+    ///
+    /// Parameters
+    /// ------------
+    /// scale
+    ///   The size of the model relative to one unit.
+    ///
+    /// Returns
+    /// ------------
+    /// scaled
+    ///   The scaled tolerances, or these ones if the scale
+    ///   isn't positive and finite.
+    pub fn scaled(&self, scale: f64) -> Self {
+        if !(scale > 0.0 && scale.is_finite()) {
+            return *self;
+        }
+        Self {
+            merge: self.merge * scale,
+            zero: self.zero * scale * scale,
+            ..*self
+        }
+    }
+}
+
+impl Trimesh {
+    /// The default tolerances scaled by the diagonal of the bounding
+    /// box, which is what algorithms on this mesh use unless they are
+    /// passed tolerances explicitly.
+    pub fn tolerances(&self) -> Tolerances {
+        let scale = self
+            .bounds()
+            .map(|(lower, upper)| (upper - lower).norm())
+            .unwrap_or(1.0);
+        Tolerances::default().scaled(scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+
+    #[test]
+    fn test_tolerances() {
+        let default = Tolerances::default();
+        let scaled = default.scaled(1000.0);
+        assert_eq!(scaled.merge, default.merge * 1000.0);
+        assert_eq!(scaled.zero, default.zero * 1e6);
+        assert_eq!(scaled.parallel, default.parallel);
+        assert_eq!(default.scaled(0.0), default);
+        assert_eq!(default.scaled(f64::NAN), default);
+
+        // a tiny model gets a merge distance below its features
+        let tiny = create_box(&[3e-9, 4e-9, 12e-9]);
+        let tolerances = tiny.tolerances();
+        assert!((tolerances.merge - 1.3e-16).abs() < 1e-30);
+        // so processing doesn't collapse it the way the default would
+        assert_eq!(tiny.process(false).unwrap().vertices.len(), 8);
+        let merged = tiny.process_with(false, &default).unwrap();
+        assert!(merged.vertices.len() < 8);
+        assert_eq!(Trimesh::default().tolerances(), default);
+    }
+}
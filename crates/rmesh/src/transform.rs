@@ -0,0 +1,466 @@
+use anyhow::{Result, anyhow};
+use nalgebra::{Matrix3, Matrix4, Point3, Vector3};
+use rayon::prelude::*;
+
+use crate::attributes::Attributes;
+use crate::creation::Primitive;
+use crate::mesh::{InnerCache, Trimesh};
+use crate::scene::Unit;
+
+/// A scale factor which is either the same along every
+/// axis or different for each axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scale {
+    Uniform(f64),
+    Axis(Vector3<f64>),
+}
+
+impl Scale {
+    /// The scale factor along each axis.
+    pub fn factors(&self) -> Vector3<f64> {
+        match self {
+            Scale::Uniform(s) => Vector3::repeat(*s),
+            Scale::Axis(v) => *v,
+        }
+    }
+}
+
+impl From<f64> for Scale {
+    fn from(value: f64) -> Self {
+        Scale::Uniform(value)
+    }
+}
+
+impl From<Vector3<f64>> for Scale {
+    fn from(value: Vector3<f64>) -> Self {
+        Scale::Axis(value)
+    }
+}
+
+impl From<[f64; 3]> for Scale {
+    fn from(value: [f64; 3]) -> Self {
+        Scale::Axis(Vector3::from(value))
+    }
+}
+
+/// Transform direction vectors such as normals by a matrix and
+/// renormalize them, leaving zero-length vectors as zero.
+fn transform_normals(normals: &[Vector3<f64>], matrix: &Matrix3<f64>) -> Vec<Vector3<f64>> {
+    normals
+        .par_iter()
+        .map(|n| (matrix * n).try_normalize(f64::EPSILON).unwrap_or_default())
+        .collect()
+}
+
+/// Apply a linear transform to the normals in a set of attributes.
+fn transform_attributes(attributes: &Attributes, normal_matrix: &Matrix3<f64>) -> Attributes {
+    let mut result = attributes.clone();
+    result.normals = attributes
+        .normals
+        .iter()
+        .map(|n| transform_normals(n, normal_matrix))
+        .collect();
+    result
+}
+
+/// Carry over the cached values which are still valid after a mesh is
+/// transformed, so for example a moved mesh keeps its adjacency and
+/// areas and a rotated mesh rotates its normals rather than dropping
+/// them. Anything depending on absolute positions like the BVH is
+/// always recomputed.
+fn transform_cache(
+    cache: &InnerCache,
+    matrix: &Matrix4<f64>,
+    normal_matrix: &Matrix3<f64>,
+    flip: bool,
+    loaded_normals: bool,
+) -> InnerCache {
+    // topology doesn't depend on the vertex positions
+    let mut result = InnerCache {
+        face_adjacency: cache.face_adjacency.clone(),
+        edges: cache.edges.clone().filter(|_| !flip),
+        half_edges: cache.half_edges.clone().filter(|_| !flip),
+        faces_flat: cache.faces_flat.clone().filter(|_| !flip),
+        ..Default::default()
+    };
+    if matrix.fixed_view::<1, 4>(3, 0) != Matrix4::identity().fixed_view::<1, 4>(3, 0) {
+        return result;
+    }
+
+    // the normals of an affine transform are exactly the inverse
+    // transpose of the original normals, and the face winding is
+    // flipped with a negative determinant to keep them outward
+    let linear: Matrix3<f64> = matrix.fixed_view::<3, 3>(0, 0).into();
+    let determinant = linear.determinant().abs();
+    result.face_normals = cache
+        .face_normals
+        .as_ref()
+        .map(|n| transform_normals(n, normal_matrix));
+    result.faces_cross = cache.faces_cross.as_ref().map(|c| {
+        c.par_iter()
+            .map(|c| normal_matrix * c * determinant)
+            .collect()
+    });
+
+    // a rotation, reflection or uniform scale keeps every angle
+    let gram = linear.transpose() * linear;
+    let scale = gram.trace() / 3.0;
+    let similar = (gram - Matrix3::identity() * scale).amax() <= 1e-12 * scale;
+    if similar || loaded_normals {
+        result.vertex_normals = cache
+            .vertex_normals
+            .as_ref()
+            .map(|n| transform_normals(n, normal_matrix));
+    }
+    if similar {
+        result.faces_area = cache
+            .faces_area
+            .as_ref()
+            .map(|a| a.iter().map(|a| a * scale).collect());
+        result.area = cache.area.map(|a| a * scale);
+        result.face_adjacency_angles = cache.face_adjacency_angles.clone();
+        result.vertex_defects = cache.vertex_defects.clone();
+        // the corners of a flipped face are in a different order
+        result.face_angles = cache.face_angles.as_ref().map(|angles| {
+            if flip {
+                angles.iter().map(|a| Vector3::new(a.x, a.z, a.y)).collect()
+            } else {
+                angles.clone()
+            }
+        });
+    }
+    result
+}
+
+impl Trimesh {
+    /// Scale the mesh around the origin by a uniform or per-axis scale,
+    /// producing a new mesh. Negative scales mirror the mesh, so if the
+    /// scale has a negative determinant the faces are rewound to keep
+    /// the mesh from turning inside out. Normal attributes are transformed
+    /// by the inverse transpose of the scale and renormalized.
+    ///
+    /// Parameters
+    /// ------------
+    /// scale
+    ///   A scalar like `2.0` or a per-axis scale like `[1.0, 2.0, -1.0]`.
+    ///
+    /// Returns
+    /// ------------
+    /// scaled
+    ///   The scaled mesh or an error if any scale factor is zero or not finite.
+    pub fn apply_scale(&self, scale: impl Into<Scale>) -> Result<Self> {
+        let factors = scale.into().factors();
+        if factors.iter().any(|f| *f == 0.0 || !f.is_finite()) {
+            return Err(anyhow!("Scale must be finite and non-zero: {factors:?}"));
+        }
+        self.apply_transform(&Matrix4::new_nonuniform_scaling(&factors))
+    }
+
+    /// Apply a homogeneous transform to the mesh, producing a new mesh.
+    /// Transforms with a negative determinant mirror the mesh so the
+    /// faces are rewound to keep the mesh from turning inside out.
+    /// Normal attributes are transformed by the inverse transpose of
+    /// the upper 3x3 of the matrix and renormalized.
+    ///
+    /// Parameters
+    /// ------------
+    /// matrix
+    ///   A (4, 4) homogeneous transform.
+    ///
+    /// Returns
+    /// ------------
+    /// transformed
+    ///   The transformed mesh or an error if the matrix isn't finite
+    ///   or would collapse the mesh.
+    pub fn apply_transform(&self, matrix: &Matrix4<f64>) -> Result<Self> {
+        let linear: Matrix3<f64> = matrix.fixed_view::<3, 3>(0, 0).into();
+        let normal_matrix = linear
+            .try_inverse()
+            .filter(|_| matrix.iter().all(|v| v.is_finite()))
+            .ok_or_else(|| anyhow!("Transform must be finite and invertible: {matrix}"))?
+            .transpose();
+
+        // a mirrored mesh needs to have its winding reversed
+        let flip = linear.determinant() < 0.0;
+        let faces = if flip {
+            self.faces.iter().map(|f| (f.0, f.2, f.1)).collect()
+        } else {
+            self.faces.clone()
+        };
+
+        let mut result = Trimesh::new(
+            transform_points(&self.vertices, matrix),
+            faces,
+            Some(transform_attributes(
+                &self.attributes_vertex,
+                &normal_matrix,
+            )),
+            Some(transform_attributes(&self.attributes_face, &normal_matrix)),
+        )?;
+        result.materials = self.materials.clone();
+        result.source = self.source.clone();
+        // a projective transform doesn't keep the shape of a primitive
+        if matrix.fixed_view::<1, 4>(3, 0) == Matrix4::identity().fixed_view::<1, 4>(3, 0) {
+            result.primitive = self.primitive.as_ref().map(|p| Primitive {
+                kind: p.kind.clone(),
+                transform: matrix * p.transform,
+            });
+        }
+        let loaded_normals = self
+            .attributes_vertex
+            .normals
+            .first()
+            .is_some_and(|n| n.len() == self.vertices.len());
+        *result._cache.get_mut().unwrap() = transform_cache(
+            &self._cache.read().unwrap(),
+            matrix,
+            &normal_matrix,
+            flip,
+            loaded_normals,
+        );
+        Ok(result)
+    }
+
+    /// Scale the mesh from the units it was loaded in to another unit,
+    /// recording the new unit in `source.units` so it's only converted
+    /// once even if this is called again.
+    ///
+    /// Parameters
+    /// ------------
+    /// target
+    ///   The unit to convert the coordinates to.
+    ///
+    /// Returns
+    /// ------------
+    /// converted
+    ///   The scaled mesh, or the mesh unchanged if it's already in the
+    ///   target unit, or an error if the units of the mesh are unknown.
+    pub fn convert_units(self, target: Unit) -> Result<Self> {
+        let current = self
+            .source
+            .units
+            .ok_or_else(|| anyhow!("Mesh units are unknown so it can't be converted"))?;
+        if current == target {
+            return Ok(self);
+        }
+        let mut result = self.apply_scale(current.meters() / target.meters())?;
+        result.source.units = Some(target);
+        Ok(result)
+    }
+
+    /// Move the mesh by a vector, producing a new mesh which keeps
+    /// every cached value that doesn't depend on absolute position.
+    pub fn apply_translation(&self, translation: &Vector3<f64>) -> Result<Self> {
+        self.apply_transform(&Matrix4::new_translation(translation))
+    }
+}
+
+/// Apply a homogeneous transform to points in parallel, dividing
+/// through by the homogeneous coordinate for projective transforms.
+///
+/// Parameters
+/// ------------
+/// points
+///   The points to transform.
+/// matrix
+///   A (4, 4) homogeneous transform.
+///
+/// Returns
+/// ------------
+/// transformed
+///   The transformed points in the same order.
+pub fn transform_points(points: &[Point3<f64>], matrix: &Matrix4<f64>) -> Vec<Point3<f64>> {
+    // skip the division for the common affine case
+    if matrix.fixed_view::<1, 4>(3, 0) == Matrix4::identity().fixed_view::<1, 4>(3, 0) {
+        let linear = matrix.fixed_view::<3, 3>(0, 0);
+        let translation = matrix.fixed_view::<3, 1>(0, 3);
+        points
+            .par_iter()
+            .map(|p| Point3::from(linear * p.coords + translation))
+            .collect()
+    } else {
+        points
+            .par_iter()
+            .map(|p| matrix.transform_point(p))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use crate::exchange::{MeshFormat, load_mesh};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_apply_scale() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        assert!(mesh.winding_number(&[Point3::origin()])[0] > 0.99);
+
+        let scaled = mesh.apply_scale(2.0).unwrap();
+        let (lower, upper) = scaled.bounds().unwrap();
+        assert_relative_eq!(lower, Point3::new(-1.0, -1.0, -1.0));
+        assert_relative_eq!(upper, Point3::new(1.0, 1.0, 1.0));
+        assert_relative_eq!(scaled.area(), 24.0, epsilon = 1e-10);
+
+        let scaled = mesh.apply_scale([1.0, 2.0, 3.0]).unwrap();
+        assert_relative_eq!(scaled.bounds().unwrap().1, Point3::new(0.5, 1.0, 1.5));
+
+        // mirroring should rewind the faces so the mesh isn't inside out
+        for scale in [[-1.0, 1.0, 1.0], [1.0, -2.0, 1.0], [-1.0, -1.0, -1.0]] {
+            let mirrored = mesh.apply_scale(scale).unwrap();
+            assert!(mirrored.winding_number(&[Point3::origin()])[0] > 0.99);
+            // every face normal should point away from the center
+            let normals = mirrored.face_normals();
+            for (face, normal) in mirrored.faces.iter().zip(normals.iter()) {
+                assert!(mirrored.vertices[face.0].coords.dot(normal) > 0.0);
+            }
+        }
+
+        assert!(mesh.apply_scale(0.0).is_err());
+        assert!(mesh.apply_scale(f64::NAN).is_err());
+        assert!(Trimesh::default().apply_scale(2.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_convert_units() {
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        assert!(mesh.clone().convert_units(Unit::Millimeter).is_err());
+
+        mesh.source.units = Some(Unit::Inch);
+        let converted = mesh.clone().convert_units(Unit::Millimeter).unwrap();
+        assert_eq!(converted.source.units, Some(Unit::Millimeter));
+        assert_relative_eq!(converted.bounds().unwrap().1, Point3::new(12.7, 12.7, 12.7));
+        // converting to the same unit again changes nothing
+        let vertices = converted.vertices.clone();
+        let again = converted.convert_units(Unit::Millimeter).unwrap();
+        assert_eq!(again.vertices, vertices);
+        let back = again.convert_units(Unit::Inch).unwrap();
+        assert_relative_eq!(back.area(), mesh.area(), epsilon = 1e-12);
+
+        // the attributes and source of a loaded mesh are kept either way
+        let loaded = load_mesh(
+            include_bytes!("../../../test/data/multi_object.3mf"),
+            MeshFormat::THREEMF,
+        )
+        .unwrap();
+        assert!(!loaded.attributes_face.colors.is_empty());
+        let units = loaded.source.units.unwrap();
+        for target in [units, Unit::Inch] {
+            let converted = loaded.clone().convert_units(target).unwrap();
+            assert_eq!(converted.attributes_face, loaded.attributes_face);
+            assert_eq!(converted.source.format, Some(MeshFormat::THREEMF));
+            assert_eq!(converted.source.units, Some(target));
+        }
+    }
+
+    #[test]
+    fn test_apply_scale_normals() {
+        let mut mesh =
+            Trimesh::from_slice(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0], &[0, 1, 2])
+                .unwrap();
+        let diagonal = Vector3::new(1.0, 1.0, 0.0).normalize();
+        mesh.attributes_vertex.normals.push(vec![diagonal; 3]);
+
+        // stretching along X should tilt the normal towards Y
+        let scaled = mesh.apply_scale([2.0, 1.0, 1.0]).unwrap();
+        let normal = scaled.attributes_vertex.normals[0][0];
+        assert_relative_eq!(normal.norm(), 1.0, epsilon = 1e-10);
+        assert_relative_eq!(
+            normal,
+            Vector3::new(0.5, 1.0, 0.0).normalize(),
+            epsilon = 1e-10
+        );
+
+        // mirroring should flip the normal and the face
+        let mirrored = mesh.apply_scale([1.0, 1.0, -1.0]).unwrap();
+        assert_eq!(mirrored.faces[0], (0, 2, 1));
+        assert_relative_eq!(mirrored.face_normals()[0], Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_apply_transform() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+
+        let matrix = Matrix4::new_translation(&Vector3::new(1.0, 2.0, 3.0))
+            * Matrix4::from_euler_angles(0.0, 0.0, std::f64::consts::FRAC_PI_2);
+        let moved = mesh.apply_transform(&matrix).unwrap();
+        let (lower, upper) = moved.bounds().unwrap();
+        assert_relative_eq!(lower, Point3::new(0.5, 1.5, 2.5), epsilon = 1e-10);
+        assert_relative_eq!(upper, Point3::new(1.5, 2.5, 3.5), epsilon = 1e-10);
+        assert_eq!(moved.faces, mesh.faces);
+        assert!(moved.winding_number(&[Point3::new(1.0, 2.0, 3.0)])[0] > 0.99);
+
+        // a reflection should rewind the faces
+        let mirror = Matrix4::new_nonuniform_scaling(&Vector3::new(-1.0, 1.0, 1.0));
+        let mirrored = mesh.apply_transform(&mirror).unwrap();
+        assert!(mirrored.winding_number(&[Point3::origin()])[0] > 0.99);
+
+        assert!(mesh.apply_transform(&Matrix4::zeros()).is_err());
+        assert!(
+            mesh.apply_transform(&(Matrix4::identity() * f64::NAN))
+                .is_err()
+        );
+
+        // projective transforms divide by the homogeneous coordinate
+        let mut projective = Matrix4::identity();
+        projective[(3, 3)] = 2.0;
+        let points = transform_points(&[Point3::new(2.0, 4.0, 6.0)], &projective);
+        assert_relative_eq!(points[0], Point3::new(1.0, 2.0, 3.0));
+    }
+
+    #[test]
+    fn test_apply_transform_cache() {
+        let mesh = create_box(&[1.0, 2.0, 3.0]);
+        // fill the cache
+        let area = mesh.area();
+        mesh.face_adjacency_angles();
+        mesh.vertex_normals();
+        mesh.face_angles();
+        mesh.edges();
+
+        let moved = mesh
+            .apply_translation(&Vector3::new(1.0, 2.0, 3.0))
+            .unwrap();
+        {
+            let cache = moved._cache.read().unwrap();
+            assert_eq!(cache.area, Some(area));
+            assert!(cache.face_normals.is_some() && cache.edges.is_some());
+        }
+
+        // cached values should match what would be computed from scratch
+        let rotation = Matrix4::from_euler_angles(0.3, 0.5, 0.7);
+        let stretch = Matrix4::new_nonuniform_scaling(&Vector3::new(1.0, 2.0, 0.5));
+        let mirror = Matrix4::new_nonuniform_scaling(&Vector3::new(-2.0, 2.0, 2.0));
+        for matrix in [rotation, stretch, mirror * rotation] {
+            let cached = mesh.apply_transform(&matrix).unwrap();
+            let fresh =
+                Trimesh::new(cached.vertices.clone(), cached.faces.clone(), None, None).unwrap();
+            for (a, b) in cached.face_normals().iter().zip(fresh.face_normals()) {
+                assert_relative_eq!(*a, b, epsilon = 1e-10);
+            }
+            for (a, b) in cached.faces_cross().iter().zip(fresh.faces_cross()) {
+                assert_relative_eq!(*a, b, epsilon = 1e-10);
+            }
+            for (a, b) in cached.vertex_normals().iter().zip(fresh.vertex_normals()) {
+                assert_relative_eq!(*a, b, epsilon = 1e-10);
+            }
+            for (a, b) in cached.face_angles().iter().zip(fresh.face_angles()) {
+                assert_relative_eq!(*a, b, epsilon = 1e-10);
+            }
+            assert_relative_eq!(cached.area(), fresh.area(), epsilon = 1e-10);
+            assert_eq!(cached.edges(), fresh.edges());
+        }
+        assert!(
+            mesh.apply_transform(&stretch)
+                .unwrap()
+                ._cache
+                .read()
+                .unwrap()
+                .area
+                .is_none()
+        );
+    }
+}
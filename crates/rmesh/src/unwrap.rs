@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+
+use ahash::AHashMap;
+use anyhow::{Result, anyhow};
+use nalgebra::{Vector2, Vector3};
+
+use crate::attributes::{Grouping, GroupingKind};
+use crate::creation::perpendicular;
+use crate::mesh::Trimesh;
+
+/// The name of the face grouping holding the chart of every face
+/// in the atlas made by `Trimesh::unwrap`.
+pub const CHART_GROUPING: &str = "uv_chart";
+
+/// The largest angle in radians between the normal of a face and the
+/// normal of the chart it's projected along, which keeps every face
+/// the right way around and stretched by at most a factor of two.
+const CHART_ANGLE: f64 = std::f64::consts::FRAC_PI_3;
+
+/// The gap in texels left around every chart in the atlas so
+/// filtering a texture doesn't bleed between charts.
+const PADDING: f64 = 2.0;
+
+/// Place rectangles in rows on a roughly square sheet, tallest first,
+/// with a gap before each rectangle and above each row.
+///
+/// Parameters
+/// ------------
+/// sizes
+///   The width and height of each rectangle.
+/// gap
+///   The space to leave between rectangles and around the sheet.
+///
+/// Returns
+/// ------------
+/// offsets
+///   The position of the lower left corner of each rectangle.
+/// extent
+///   The length of the sides of the square sheet holding all of them.
+fn pack_rows(sizes: &[Vector2<f64>], gap: f64) -> (Vec<Vector2<f64>>, f64) {
+    let area: f64 = sizes.iter().map(|s| (s.x + gap) * (s.y + gap)).sum();
+    let widest = sizes.iter().map(|s| s.x + gap).fold(0.0, f64::max);
+    let width = area.sqrt().max(widest);
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| sizes[*b].y.total_cmp(&sizes[*a].y));
+
+    let mut offsets = vec![Vector2::zeros(); sizes.len()];
+    let (mut x, mut y, mut row) = (gap, gap, 0.0_f64);
+    let mut right = 0.0_f64;
+    for index in order {
+        let size = sizes[index];
+        if x > gap && x + size.x > width {
+            x = gap;
+            y += row + gap;
+            row = 0.0;
+        }
+        offsets[index] = Vector2::new(x, y);
+        x += size.x + gap;
+        right = right.max(x);
+        row = row.max(size.y);
+    }
+    (offsets, right.max(y + row + gap))
+}
+
+impl Trimesh {
+    /// Generate a texture atlas for the mesh by splitting it into charts
+    /// of connected faces pointing in about the same direction, projecting
+    /// each chart flat along its first face's normal and packing the charts
+    /// into the unit square. Vertices on the seams between charts are
+    /// split so each chart has its own copy.
+    ///
+    /// Charts are flattened without solving for a conformal map, so the
+    /// area of a face in the atlas is within a factor of two of its
+    /// share of the surface, and a chart which folds over itself like a
+    /// spiral ramp may overlap itself in the atlas.
+    ///
+    /// Parameters
+    /// ------------
+    /// resolution
+    ///   The width in texels of the square texture the atlas is for,
+    ///   which sets the gap of a couple of texels left between charts.
+    ///
+    /// Returns
+    /// ------------
+    /// unwrapped
+    ///   A new mesh with the atlas as its first UV coordinates and the
+    ///   chart of every face in the `CHART_GROUPING` face grouping.
+    pub fn unwrap(&self, resolution: u32) -> Result<Self> {
+        if resolution == 0 {
+            return Err(anyhow!("The resolution of an atlas must be positive"));
+        }
+        let normals = self.face_normals();
+        let degenerate = |face: usize| normals[face].norm_squared() < 0.5;
+        let mut neighbors = vec![Vec::new(); self.faces.len()];
+        for (a, b) in self.face_adjacency() {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+
+        // grow charts from faces with a normal before the degenerate ones,
+        // which can join any chart as they don't face any direction
+        let limit = CHART_ANGLE.cos();
+        let mut seeds: Vec<usize> = (0..self.faces.len()).collect();
+        seeds.sort_by_key(|f| degenerate(*f));
+        let mut chart = vec![usize::MAX; self.faces.len()];
+        let mut axes: Vec<Vector3<f64>> = Vec::new();
+        for seed in seeds {
+            if chart[seed] != usize::MAX {
+                continue;
+            }
+            let axis = if degenerate(seed) {
+                Vector3::z()
+            } else {
+                normals[seed]
+            };
+            chart[seed] = axes.len();
+            let mut queue = VecDeque::from([seed]);
+            while let Some(face) = queue.pop_front() {
+                for other in neighbors[face].iter() {
+                    if chart[*other] == usize::MAX
+                        && (degenerate(*other) || normals[*other].dot(&axis) >= limit)
+                    {
+                        chart[*other] = axes.len();
+                        queue.push_back(*other);
+                    }
+                }
+            }
+            axes.push(axis);
+        }
+
+        // a vertex for every chart it's used in, projected onto the chart
+        let bases: Vec<[Vector3<f64>; 2]> = axes
+            .iter()
+            .map(|axis| {
+                let u = perpendicular(axis);
+                [u, axis.cross(&u)]
+            })
+            .collect();
+        let mut lookup: AHashMap<(usize, usize), usize> = AHashMap::new();
+        let mut index: Vec<usize> = Vec::new();
+        let mut owner: Vec<usize> = Vec::new();
+        let mut flat: Vec<Vector2<f64>> = Vec::new();
+        let faces = self
+            .faces
+            .iter()
+            .enumerate()
+            .map(|(face, f)| {
+                let c = chart[face];
+                let [a, b, d] = [f.0, f.1, f.2].map(|vertex| {
+                    *lookup.entry((vertex, c)).or_insert_with(|| {
+                        let v = self.vertices[vertex].coords;
+                        index.push(vertex);
+                        owner.push(c);
+                        flat.push(Vector2::new(v.dot(&bases[c][0]), v.dot(&bases[c][1])));
+                        index.len() - 1
+                    })
+                });
+                (a, b, d)
+            })
+            .collect();
+
+        let mut lower = vec![Vector2::repeat(f64::INFINITY); axes.len()];
+        let mut upper = vec![Vector2::repeat(f64::NEG_INFINITY); axes.len()];
+        for (point, c) in flat.iter().zip(owner.iter()) {
+            lower[*c] = lower[*c].inf(point);
+            upper[*c] = upper[*c].sup(point);
+        }
+        let sizes: Vec<Vector2<f64>> = lower.iter().zip(upper.iter()).map(|(l, u)| u - l).collect();
+
+        // the gap depends on the size of the sheet so settle it by repacking
+        let mut extent: f64 = sizes.iter().map(|s| s.x * s.y).sum::<f64>().sqrt();
+        let mut packed = (vec![Vector2::zeros(); sizes.len()], extent);
+        for _ in 0..3 {
+            packed = pack_rows(&sizes, PADDING * extent / resolution as f64);
+            extent = packed.1;
+        }
+        let (offsets, extent) = packed;
+        let scale = if extent > 0.0 { 1.0 / extent } else { 0.0 };
+        let uv = flat
+            .iter()
+            .zip(owner.iter())
+            .map(|(point, c)| (point - lower[*c] + offsets[*c]) * scale)
+            .collect();
+
+        let mut attributes_vertex = self.attributes_vertex.select(&index, self.vertices.len());
+        attributes_vertex.uv.insert(0, uv);
+        let mut attributes_face = self.attributes_face.clone();
+        attributes_face
+            .groupings
+            .retain(|g| g.name != CHART_GROUPING);
+        attributes_face.groupings.push(Grouping {
+            name: CHART_GROUPING.to_string(),
+            kind: GroupingKind::Unspecified,
+            indices: chart,
+            labels: Vec::new(),
+        });
+
+        Ok(Trimesh {
+            vertices: index.iter().map(|i| self.vertices[*i]).collect(),
+            faces,
+            attributes_vertex,
+            attributes_face,
+            materials: self.materials.clone(),
+            source: self.source.clone(),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::{create_box, create_sphere};
+    use approx::assert_relative_eq;
+
+    /// The signed area of every face in the first UV coordinates.
+    fn uv_areas(mesh: &Trimesh) -> Vec<f64> {
+        let uv = mesh.uv().unwrap();
+        mesh.faces
+            .iter()
+            .map(|f| (uv[f.1] - uv[f.0]).perp(&(uv[f.2] - uv[f.0])) / 2.0)
+            .collect()
+    }
+
+    #[test]
+    fn test_unwrap() {
+        let mesh = create_box(&[1.0, 2.0, 3.0]);
+        let unwrapped = mesh.unwrap(256).unwrap();
+        assert_eq!(unwrapped.faces.len(), mesh.faces.len());
+        // every side of the box is its own chart with its own corners
+        let charts = unwrapped.attributes_face.grouping(CHART_GROUPING).unwrap();
+        assert_eq!(charts.indices.iter().max(), Some(&5));
+        assert_eq!(unwrapped.vertices.len(), 24);
+        assert_relative_eq!(unwrapped.area(), mesh.area(), epsilon = 1e-10);
+
+        let uv = unwrapped.uv().unwrap();
+        assert!(
+            uv.iter()
+                .all(|c| (0.0..=1.0).contains(&c.x) && (0.0..=1.0).contains(&c.y))
+        );
+        // flat charts keep the proportions of the faces
+        let areas = uv_areas(&unwrapped);
+        assert!(areas.iter().all(|a| *a > 0.0));
+        let ratio = areas[0] / unwrapped.faces_area()[0];
+        for (a, b) in areas.iter().zip(unwrapped.faces_area().iter()) {
+            assert_relative_eq!(a / b, ratio, epsilon = 1e-9);
+        }
+        // the charts don't overlap so they cover less than the square
+        assert!(areas.iter().sum::<f64>() < 1.0);
+
+        let sphere = create_sphere(1.0, [32, 16]).unwrap();
+        let unwrapped = sphere.unwrap(1024).unwrap();
+        assert!(uv_areas(&unwrapped).iter().all(|a| *a > 0.0));
+        assert!(unwrapped.vertices.len() > sphere.vertices.len());
+        assert_relative_eq!(unwrapped.volume(), sphere.volume(), epsilon = 1e-10);
+
+        assert!(mesh.unwrap(0).is_err());
+        assert!(Trimesh::default().unwrap(16).unwrap().is_empty());
+    }
+}
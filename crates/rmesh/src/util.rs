@@ -0,0 +1,331 @@
+use std::collections::HashSet;
+use std::ops::Add;
+
+use nalgebra::Vector2;
+
+use crate::attributes::{
+    AttributeArray, Attributes, DEFAULT_COLOR, EmptyMaterial, Grouping, GroupingKind, Material,
+    Normal,
+};
+use crate::mesh::Trimesh;
+
+/// Join one kind of attribute layer by layer, where the layers are
+/// matched by their position and a mesh without a layer contributes
+/// a default value for each of its elements.
+fn join_layers<T: Clone>(
+    layers: &[(&[Vec<T>], usize)],
+    default: impl Fn(usize) -> Vec<T>,
+) -> Vec<Vec<T>> {
+    let count = layers
+        .iter()
+        .map(|(values, n)| values.iter().filter(|v| v.len() == *n).count())
+        .max()
+        .unwrap_or(0);
+    (0..count)
+        .map(|layer| {
+            layers
+                .iter()
+                .enumerate()
+                .flat_map(|(mesh, (values, n))| {
+                    values
+                        .iter()
+                        .filter(|v| v.len() == *n)
+                        .nth(layer)
+                        .cloned()
+                        .unwrap_or_else(|| default(mesh))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Join the UV coordinates, normals, colors and named attributes of
+/// many sets of elements, with groupings left to the caller.
+pub(crate) fn join_attributes(
+    attributes: &[(&Attributes, usize)],
+    normals: impl Fn(usize) -> Normal,
+) -> Attributes {
+    let uv: Vec<_> = attributes.iter().map(|(a, n)| (&a.uv[..], *n)).collect();
+    let normal: Vec<_> = attributes
+        .iter()
+        .map(|(a, n)| (&a.normals[..], *n))
+        .collect();
+    let colors: Vec<_> = attributes
+        .iter()
+        .map(|(a, n)| (&a.colors[..], *n))
+        .collect();
+    let names: HashSet<&String> = attributes
+        .iter()
+        .flat_map(|(a, n)| a.named.iter().filter(|(_, v)| v.len() == *n))
+        .map(|(name, _)| name)
+        .collect();
+    Attributes {
+        uv: join_layers(&uv, |m| vec![Vector2::zeros(); attributes[m].1]),
+        normals: join_layers(&normal, normals),
+        colors: join_layers(&colors, |m| vec![DEFAULT_COLOR; attributes[m].1]),
+        groupings: Vec::new(),
+        // attributes holding different types on different meshes are dropped
+        named: names
+            .into_iter()
+            .filter_map(|name| {
+                let parts: Vec<_> = attributes
+                    .iter()
+                    .map(|(a, n)| (a.named.get(name).filter(|v| v.len() == *n), *n))
+                    .collect();
+                Some((name.clone(), AttributeArray::join(&parts)?))
+            })
+            .collect(),
+    }
+}
+
+/// Join groupings by name, where a mesh without a grouping
+/// contributes the value from `default` for each of its elements.
+/// If the grouping has labels that value is labeled with an empty
+/// name, so those elements don't join the group with that index.
+pub(crate) fn join_groupings(
+    groupings: &[(&[Grouping], usize)],
+    default: impl Fn(usize, &Grouping) -> usize,
+) -> Vec<Grouping> {
+    let mut names: Vec<&Grouping> = Vec::new();
+    for (values, n) in groupings.iter() {
+        for g in values.iter().filter(|g| g.indices.len() == *n) {
+            if !names.iter().any(|e| e.name == g.name) {
+                names.push(g);
+            }
+        }
+    }
+    names
+        .into_iter()
+        .map(|first| {
+            let found: Vec<Option<&Grouping>> = groupings
+                .iter()
+                .map(|(values, n)| {
+                    values
+                        .iter()
+                        .find(|g| g.name == first.name && g.indices.len() == *n)
+                })
+                .collect();
+            let labeled = found.iter().flatten().any(|g| !g.labels.is_empty());
+            // the values of meshes without the grouping and their labels
+            let filled: Vec<(Vec<usize>, Vec<String>)> = groupings
+                .iter()
+                .enumerate()
+                .map(|(mesh, (_, n))| {
+                    let value = default(mesh, first);
+                    let labels = if labeled {
+                        vec![String::new(); value + 1]
+                    } else {
+                        Vec::new()
+                    };
+                    (vec![value; *n], labels)
+                })
+                .collect();
+            let parts: Vec<(&[usize], &[String])> = found
+                .iter()
+                .zip(filled.iter())
+                .map(|(found, (values, labels))| match found {
+                    Some(g) => (&g.indices[..], &g.labels[..]),
+                    None => (&values[..], &labels[..]),
+                })
+                .collect();
+            let (indices, labels) = Grouping::join(&parts);
+            Grouping {
+                name: first.name.clone(),
+                kind: first.kind.clone(),
+                indices,
+                labels,
+            }
+        })
+        .collect()
+}
+
+/// Combine many meshes into a single mesh without merging any vertices.
+/// Vertex and face attributes are kept if any mesh has them: UV
+/// coordinates, normals and colors are matched by their position among
+/// attributes of their kind and groupings by name. A mesh without an
+/// attribute the others have fills in zero UV coordinates, its computed
+/// normals, the default color, group zero or zeros for a named
+/// attribute. The materials of each mesh follow the ones before it with
+/// its material indices offset to match, and a mesh without material
+/// indices uses its first material or an empty one.
+///
+/// Parameters
+/// ------------
+/// meshes
+///   The meshes to combine in order.
+///
+/// Returns
+/// ------------
+/// combined
+///   A mesh with the vertices and faces of every mesh.
+pub fn concatenate(meshes: &[&Trimesh]) -> Trimesh {
+    let mut vertices = Vec::with_capacity(meshes.iter().map(|m| m.vertices.len()).sum());
+    let mut faces = Vec::with_capacity(meshes.iter().map(|m| m.faces.len()).sum());
+    let mut materials: Vec<Material> = Vec::new();
+    // the offset of the materials of each mesh
+    let mut material_offset = Vec::with_capacity(meshes.len());
+    let material_grouping = |m: &Trimesh| {
+        m.attributes_face
+            .groupings
+            .iter()
+            .any(|g| matches!(g.kind, GroupingKind::MaterialIndex))
+    };
+    let any_material = meshes.iter().any(|m| material_grouping(m));
+    for mesh in meshes {
+        let offset = vertices.len();
+        vertices.extend(mesh.vertices.iter().copied());
+        faces.extend(
+            mesh.faces
+                .iter()
+                .map(|f| (f.0 + offset, f.1 + offset, f.2 + offset)),
+        );
+        material_offset.push(materials.len());
+        materials.extend(mesh.materials.iter().cloned());
+        if any_material && mesh.materials.is_empty() && !material_grouping(mesh) {
+            materials.push(Material::Empty(EmptyMaterial {}));
+        }
+    }
+
+    let vertex: Vec<(&Attributes, usize)> = meshes
+        .iter()
+        .map(|m| (&m.attributes_vertex, m.vertices.len()))
+        .collect();
+    let face: Vec<(&Attributes, usize)> = meshes
+        .iter()
+        .map(|m| (&m.attributes_face, m.faces.len()))
+        .collect();
+    let mut attributes_vertex = join_attributes(&vertex, |m| meshes[m].vertex_normals());
+    attributes_vertex.groupings = join_groupings(
+        &vertex
+            .iter()
+            .map(|(a, n)| (&a.groupings[..], *n))
+            .collect::<Vec<_>>(),
+        |_, _| 0,
+    );
+    let mut attributes_face = join_attributes(&face, |m| meshes[m].face_normals());
+    // material indices are offset to the materials of their mesh
+    let offset_faces: Vec<Vec<Grouping>> = meshes
+        .iter()
+        .zip(material_offset.iter())
+        .map(|(m, offset)| {
+            let mut groupings = m.attributes_face.groupings.clone();
+            for g in groupings.iter_mut() {
+                if matches!(g.kind, GroupingKind::MaterialIndex) {
+                    g.indices.iter_mut().for_each(|i| *i += offset);
+                }
+            }
+            groupings
+        })
+        .collect();
+    attributes_face.groupings = join_groupings(
+        &offset_faces
+            .iter()
+            .zip(meshes.iter())
+            .map(|(g, m)| (&g[..], m.faces.len()))
+            .collect::<Vec<_>>(),
+        |mesh, first| match first.kind {
+            // the first material of the mesh or the empty one added for it
+            GroupingKind::MaterialIndex => material_offset[mesh],
+            _ => 0,
+        },
+    );
+
+    Trimesh {
+        vertices,
+        faces,
+        attributes_vertex,
+        attributes_face,
+        materials,
+        ..Default::default()
+    }
+}
+
+impl Add for &Trimesh {
+    type Output = Trimesh;
+
+    /// Combine two meshes with `concatenate`.
+    fn add(self, other: &Trimesh) -> Trimesh {
+        concatenate(&[self, other])
+    }
+}
+
+impl Add for Trimesh {
+    type Output = Trimesh;
+
+    /// Combine two meshes with `concatenate`.
+    fn add(self, other: Trimesh) -> Trimesh {
+        concatenate(&[&self, &other])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::attributes::SimpleMaterial;
+    use crate::creation::create_box;
+    use nalgebra::{Vector3, Vector4};
+
+    #[test]
+    fn test_concatenate() {
+        let mut a = create_box(&[1.0, 1.0, 1.0]);
+        a.attributes_vertex
+            .colors
+            .push(vec![Vector4::new(255, 0, 0, 255); 8]);
+        a.materials
+            .push(Material::Simple(SimpleMaterial::new("red")));
+        a.attributes_face.groupings.push(Grouping {
+            name: "material".to_string(),
+            kind: GroupingKind::MaterialIndex,
+            indices: vec![0; 12],
+            labels: Vec::new(),
+        });
+        let b = create_box(&[2.0, 2.0, 2.0])
+            .apply_translation(&Vector3::new(5.0, 0.0, 0.0))
+            .unwrap();
+        let mut c = create_box(&[1.0, 1.0, 1.0]);
+        c.materials
+            .push(Material::Simple(SimpleMaterial::new("blue")));
+        c.attributes_face.groupings.push(Grouping {
+            name: "material".to_string(),
+            kind: GroupingKind::MaterialIndex,
+            indices: vec![0; 12],
+            labels: Vec::new(),
+        });
+        c.attributes_face.groupings.push(Grouping {
+            name: "part".to_string(),
+            kind: GroupingKind::GroupingIndex,
+            indices: vec![3; 12],
+            labels: Vec::new(),
+        });
+
+        let mesh = concatenate(&[&a, &b, &c]);
+        assert_eq!(mesh.vertices.len(), 24);
+        assert_eq!(mesh.faces.len(), 36);
+        assert_eq!(mesh.faces[12], (8, 10, 9));
+        assert!((mesh.volume() - 10.0).abs() < 1e-10);
+
+        // the boxes without colors get the default
+        let colors = &mesh.attributes_vertex.colors[0];
+        assert_eq!(colors.len(), 24);
+        assert_eq!(colors[0], Vector4::new(255, 0, 0, 255));
+        assert_eq!(colors[8], DEFAULT_COLOR);
+
+        // the box without materials gets an empty one
+        assert_eq!(mesh.materials.len(), 3);
+        assert!(matches!(mesh.materials[1], Material::Empty(_)));
+        let material = mesh.attributes_face.grouping("material").unwrap();
+        assert_eq!(material.indices[0], 0);
+        assert_eq!(material.indices[12], 1);
+        assert_eq!(material.indices[24], 2);
+        let part = mesh.attributes_face.grouping("part").unwrap();
+        assert_eq!(part.indices[..24], [0; 24]);
+        assert_eq!(part.indices[24], 3);
+
+        // adding is the same as concatenating
+        let sum = &a + &b;
+        assert_eq!(sum.faces, concatenate(&[&a, &b]).faces);
+        assert_eq!((a + b).vertices.len(), 16);
+        assert!(concatenate(&[]).is_empty());
+    }
+}
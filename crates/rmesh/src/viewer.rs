@@ -0,0 +1,286 @@
+//! Show meshes and scenes in a browser with a local three.js page.
+//!
+//! The repository has no GLB exporter, so scenes are flattened into one
+//! mesh with their textures baked into vertex colors and served as a PLY
+//! rather than as a glTF scene with its graph and materials intact.
+
+use std::borrow::Cow;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+
+use anyhow::{Result, anyhow};
+
+use crate::exchange::{ExportOptions, MeshFormat, export_mesh};
+use crate::mesh::Trimesh;
+use crate::scene::Scene;
+
+/// The page served by the viewer, which loads three.js from a CDN and
+/// fetches the mesh as a PLY with vertex colors from the same server.
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>rmesh</title>
+<style>body { margin: 0; overflow: hidden; }</style>
+<script type="importmap">
+{ "imports": {
+  "three": "https://unpkg.com/three@0.160.0/build/three.module.js",
+  "three/addons/": "https://unpkg.com/three@0.160.0/examples/jsm/"
+} }
+</script>
+</head>
+<body>
+<script type="module">
+import * as THREE from "three";
+import { OrbitControls } from "three/addons/controls/OrbitControls.js";
+import { PLYLoader } from "three/addons/loaders/PLYLoader.js";
+
+const renderer = new THREE.WebGLRenderer({ antialias: true });
+renderer.setPixelRatio(window.devicePixelRatio);
+renderer.setSize(window.innerWidth, window.innerHeight);
+document.body.appendChild(renderer.domElement);
+
+const scene = new THREE.Scene();
+scene.background = new THREE.Color(0xffffff);
+scene.add(new THREE.HemisphereLight(0xffffff, 0x444444, 2.0));
+const camera = new THREE.PerspectiveCamera(45, window.innerWidth / window.innerHeight, 0.01, 1000);
+scene.add(camera);
+camera.add(new THREE.DirectionalLight(0xffffff, 1.5));
+const controls = new OrbitControls(camera, renderer.domElement);
+
+new PLYLoader().load("mesh.ply", (geometry) => {
+  geometry.computeVertexNormals();
+  const material = new THREE.MeshStandardMaterial({
+    color: geometry.hasAttribute("color") ? 0xffffff : 0x999999,
+    vertexColors: geometry.hasAttribute("color"),
+    side: THREE.DoubleSide,
+  });
+  scene.add(new THREE.Mesh(geometry, material));
+
+  // fit the camera to the bounding sphere of the mesh
+  geometry.computeBoundingSphere();
+  const { center, radius } = geometry.boundingSphere;
+  const scale = radius > 0 ? radius : 1.0;
+  camera.near = scale / 100.0;
+  camera.far = scale * 100.0;
+  camera.position.copy(center).add(new THREE.Vector3(1, 1, 1).multiplyScalar(scale * 1.8));
+  camera.updateProjectionMatrix();
+  controls.target.copy(center);
+  controls.update();
+});
+
+window.addEventListener("resize", () => {
+  camera.aspect = window.innerWidth / window.innerHeight;
+  camera.updateProjectionMatrix();
+  renderer.setSize(window.innerWidth, window.innerHeight);
+});
+// stop the server once the page is closed
+window.addEventListener("pagehide", () => navigator.sendBeacon("close"));
+
+renderer.setAnimationLoop(() => renderer.render(scene, camera));
+</script>
+</body>
+</html>
+"#;
+
+/// A local HTTP server showing a mesh in a three.js page.
+pub struct Viewer {
+    listener: TcpListener,
+    // the mesh exported as a PLY for the page to load
+    ply: String,
+}
+
+impl Viewer {
+    /// Bind a server to a free port on the loopback interface which
+    /// will serve a mesh, with any textures baked into vertex colors.
+    ///
+    /// Parameters
+    /// ------------
+    /// mesh
+    ///   The mesh to show.
+    ///
+    /// Returns
+    /// ------------
+    /// viewer
+    ///   A viewer which isn't serving until `serve` is called, or an
+    ///   error if the mesh couldn't be exported or no port was free.
+    pub fn new(mesh: &Trimesh) -> Result<Self> {
+        let options = ExportOptions {
+            bake_textures: Some(f64::INFINITY),
+            ..Default::default()
+        };
+        Ok(Viewer {
+            listener: TcpListener::bind(("127.0.0.1", 0))?,
+            ply: export_mesh(mesh, MeshFormat::PLY, &options)?,
+        })
+    }
+
+    /// The address of the page to open in a browser.
+    pub fn url(&self) -> Result<String> {
+        Ok(format!("http://{}/", self.listener.local_addr()?))
+    }
+
+    /// Serve the page and the mesh until the page is closed, which
+    /// blocks the calling thread.
+    pub fn serve(&self) -> Result<()> {
+        for stream in self.listener.incoming() {
+            // a single broken connection shouldn't stop the viewer
+            let Ok(stream) = stream else { continue };
+            if let Ok(false) = self.respond(stream) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answer a single request, returning whether to keep serving.
+    fn respond(&self, mut stream: TcpStream) -> Result<bool> {
+        let mut reader = BufReader::new(&stream);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        // skip the headers and any body as every route is fixed
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+
+        let path = request.split_whitespace().nth(1).unwrap_or("/");
+        let (status, kind, body) = match path {
+            "/" | "/index.html" => ("200 OK", "text/html; charset=utf-8", PAGE),
+            "/mesh.ply" => ("200 OK", "text/plain", self.ply.as_str()),
+            "/close" => ("200 OK", "text/plain", ""),
+            _ => ("404 Not Found", "text/plain", ""),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {status}\r\nContent-Type: {kind}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        stream.flush()?;
+        Ok(path != "/close")
+    }
+}
+
+/// Try to open a page in the default browser, which is allowed to
+/// fail as the address is also printed for opening by hand.
+fn open_browser(url: &str) {
+    let command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open").arg(url).spawn()
+    } else if cfg!(target_os = "windows") {
+        std::process::Command::new("cmd")
+            .args(["/C", "start", "", url])
+            .spawn()
+    } else {
+        std::process::Command::new("xdg-open").arg(url).spawn()
+    };
+    if command.is_err() {
+        eprintln!("Couldn't open a browser, open {url} to view the mesh");
+    }
+}
+
+/// Show a mesh in a browser until the page is closed.
+fn show_mesh(mesh: &Trimesh) -> Result<()> {
+    let viewer = Viewer::new(mesh)?;
+    let url = viewer.url()?;
+    eprintln!("Viewing mesh at {url}");
+    open_browser(&url);
+    viewer.serve()
+}
+
+impl Trimesh {
+    /// Show the mesh in a three.js viewer in the default browser,
+    /// blocking until the page is closed like `trimesh.show()`.
+    pub fn show(&self) -> Result<()> {
+        show_mesh(self)
+    }
+}
+
+impl Scene {
+    /// Combine every mesh placed in the scene into one mesh in the
+    /// root frame with `flatten_mesh`. Textures are baked into vertex
+    /// colors for each geometry first, as baking the combined mesh
+    /// would replace the vertex colors of meshes without textures.
+    fn to_mesh(&self) -> Result<Trimesh> {
+        let combined =
+            self.flatten_mesh_with(|mesh| Ok(Cow::Owned(mesh.bake_textures(f64::INFINITY)?)))?;
+        if combined.faces.is_empty() {
+            return Err(anyhow!("Scene has no meshes to show"));
+        }
+        Ok(combined)
+    }
+
+    /// Show every mesh in the scene in a three.js viewer in the
+    /// default browser, blocking until the page is closed.
+    pub fn show(&self) -> Result<()> {
+        show_mesh(&self.to_mesh()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use crate::geometry::Geometry;
+    use crate::scene::SceneNode;
+    use std::io::Read;
+
+    /// Make a request to the viewer and return the body of the response.
+    fn get(url: &str, path: &str) -> String {
+        let address = url.trim_start_matches("http://").trim_end_matches('/');
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: {address}\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.split_once("\r\n\r\n").unwrap().1.to_string()
+    }
+
+    #[test]
+    fn test_viewer() {
+        let mesh = create_box(&[1.0, 1.0, 1.0]);
+        let viewer = Viewer::new(&mesh).unwrap();
+        let url = viewer.url().unwrap();
+        let server = std::thread::spawn(move || viewer.serve());
+
+        assert!(get(&url, "/").contains("PLYLoader"));
+        let ply = get(&url, "/mesh.ply");
+        assert!(ply.starts_with("ply\n"));
+        assert!(ply.contains("element face 12\n"));
+        assert!(get(&url, "/missing").is_empty());
+
+        // closing the page stops the server
+        get(&url, "/close");
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_scene_to_mesh() {
+        let mut scene = Scene::new();
+        assert!(scene.to_mesh().is_err());
+
+        // one box placed twice, with vertex colors which are kept
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        let red = nalgebra::Vector4::new(255, 0, 0, 255);
+        mesh.attributes_vertex.colors.push(vec![red; 8]);
+        let geometry = scene.add_geometry(Geometry::Mesh(Box::new(mesh)));
+        let root = scene.graph.add_node(SceneNode::default());
+        for x in [0.0, 2.0] {
+            let node = scene.graph.add_node(SceneNode {
+                transform: Some(nalgebra::Matrix4::new_translation(&nalgebra::Vector3::new(
+                    x, 0.0, 0.0,
+                ))),
+                index: vec![geometry],
+                ..Default::default()
+            });
+            scene.graph.add_edge(root, node).unwrap();
+        }
+        let combined = scene.to_mesh().unwrap();
+        assert_eq!(combined.faces.len(), 24);
+        assert_eq!(combined.bounds().unwrap().1.x, 2.5);
+        assert!(
+            combined.attributes_vertex.colors[0]
+                .iter()
+                .all(|c| *c == red)
+        );
+    }
+}
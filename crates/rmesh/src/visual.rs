@@ -0,0 +1,256 @@
+use nalgebra::{Vector3, Vector4};
+
+use crate::attributes::{Color, DEFAULT_COLOR};
+use crate::mesh::Trimesh;
+
+/// The colors of viridis at evenly spaced stops from zero to one,
+/// sampled from the matplotlib colormap.
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+/// A map from a value between zero and one to a color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Colormap {
+    // perceptually uniform from dark purple to yellow
+    #[default]
+    Viridis,
+    // the rainbow from blue through green to red
+    Jet,
+    // black to white
+    Grayscale,
+}
+
+impl Colormap {
+    /// The opaque color of a value, which is clamped between zero and one.
+    pub fn color(&self, value: f64) -> Vector4<u8> {
+        let t = if value.is_nan() {
+            0.0
+        } else {
+            value.clamp(0.0, 1.0)
+        };
+        let rgb: Vector3<f64> = match self {
+            Colormap::Viridis => {
+                let position = t * (VIRIDIS.len() - 1) as f64;
+                let index = (position.floor() as usize).min(VIRIDIS.len() - 2);
+                let [a, b] = [VIRIDIS[index], VIRIDIS[index + 1]]
+                    .map(|c| Vector3::new(c[0] as f64, c[1] as f64, c[2] as f64) / 255.0);
+                a.lerp(&b, position - index as f64)
+            }
+            Colormap::Jet => {
+                let ramp = |offset: f64| (1.5 - (4.0 * t - offset).abs()).clamp(0.0, 1.0);
+                Vector3::new(ramp(3.0), ramp(2.0), ramp(1.0))
+            }
+            Colormap::Grayscale => Vector3::repeat(t),
+        };
+        let rgb = rgb.map(|c| (c * 255.0).round() as u8);
+        Vector4::new(rgb.x, rgb.y, rgb.z, 255)
+    }
+}
+
+/// Color values like the curvature of every vertex or the thickness of
+/// every face, stretching the colormap from the smallest value to the
+/// largest. Values which aren't finite get the default color.
+///
+/// Parameters
+/// ------------
+/// values
+///   The value of every vertex or face.
+/// colormap
+///   The colors to map the values to.
+///
+/// Returns
+/// ------------
+/// colors
+///   The color of every value, to store as a vertex or face color.
+pub fn colors_from_scalar(values: &[f64], colormap: Colormap) -> Color {
+    let (lower, upper) = values
+        .iter()
+        .filter(|v| v.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(l, u), v| {
+            (l.min(*v), u.max(*v))
+        });
+    let span = upper - lower;
+    values
+        .iter()
+        .map(|v| {
+            if !v.is_finite() {
+                DEFAULT_COLOR
+            } else if span > 0.0 {
+                colormap.color((v - lower) / span)
+            } else {
+                colormap.color(0.0)
+            }
+        })
+        .collect()
+}
+
+/// Convert a color channel from sRGB, which is how 8 bit colors are
+/// stored, to linear light where colors can be averaged and blended.
+pub fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a color channel from linear light back to sRGB.
+pub fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Convert an 8 bit sRGB color to linear light between zero and
+/// one, with alpha which is already linear only scaled.
+pub fn color_to_linear(color: &Vector4<u8>) -> Vector4<f64> {
+    let c = color.map(|c| c as f64 / 255.0);
+    Vector4::new(
+        srgb_to_linear(c.x),
+        srgb_to_linear(c.y),
+        srgb_to_linear(c.z),
+        c.w,
+    )
+}
+
+/// Convert a color in linear light back to 8 bit sRGB.
+pub fn color_from_linear(color: &Vector4<f64>) -> Vector4<u8> {
+    let c = Vector4::new(
+        linear_to_srgb(color.x),
+        linear_to_srgb(color.y),
+        linear_to_srgb(color.z),
+        color.w,
+    );
+    c.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/// The average of several colors, which is done in linear light so
+/// mixing red and green gives a bright yellow rather than a dark one.
+fn mix(colors: impl Iterator<Item = Vector4<u8>>) -> Option<Vector4<u8>> {
+    let (sum, count) = colors.fold((Vector4::zeros(), 0), |(sum, count), c| {
+        (sum + color_to_linear(&c), count + 1)
+    });
+    (count > 0).then(|| color_from_linear(&(sum / count as f64)))
+}
+
+impl Trimesh {
+    /// Spread the first face colors onto the vertices, with every vertex
+    /// taking the average color of the faces around it. Vertices which
+    /// aren't used by any face get the default color.
+    ///
+    /// Returns
+    /// ------------
+    /// colors
+    ///   The color of every vertex, or `None` if there are no face colors.
+    pub fn face_colors_to_vertex_colors(&self) -> Option<Color> {
+        let colors = self.attributes_face.colors.first()?;
+        let mut around: Vec<Vec<Vector4<u8>>> = vec![Vec::new(); self.vertices.len()];
+        for (face, color) in self.faces.iter().zip(colors.iter()) {
+            for vertex in [face.0, face.1, face.2] {
+                around[vertex].push(*color);
+            }
+        }
+        Some(
+            around
+                .into_iter()
+                .map(|c| mix(c.into_iter()).unwrap_or(DEFAULT_COLOR))
+                .collect(),
+        )
+    }
+
+    /// Collapse the first vertex colors onto the faces, with every
+    /// face taking the average color of its corners.
+    ///
+    /// Returns
+    /// ------------
+    /// colors
+    ///   The color of every face, or `None` if there are no vertex colors.
+    pub fn vertex_colors_to_face_colors(&self) -> Option<Color> {
+        let colors = self.attributes_vertex.colors.first()?;
+        Some(
+            self.faces
+                .iter()
+                .map(|f| {
+                    mix([f.0, f.1, f.2].into_iter().map(|v| colors[v])).unwrap_or(DEFAULT_COLOR)
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_colors() {
+        assert_eq!(Colormap::Viridis.color(0.0), Vector4::new(68, 1, 84, 255));
+        assert_eq!(
+            Colormap::Viridis.color(2.0),
+            Vector4::new(253, 231, 37, 255)
+        );
+        assert_eq!(Colormap::Jet.color(0.0), Vector4::new(0, 0, 128, 255));
+        assert_eq!(Colormap::Jet.color(0.5), Vector4::new(128, 255, 128, 255));
+        assert_eq!(Colormap::Jet.color(1.0), Vector4::new(128, 0, 0, 255));
+
+        let colors = colors_from_scalar(&[1.0, 3.0, 2.0, f64::NAN], Colormap::Grayscale);
+        assert_eq!(colors[0], Vector4::new(0, 0, 0, 255));
+        assert_eq!(colors[1], Vector4::new(255, 255, 255, 255));
+        assert_eq!(colors[2], Vector4::new(128, 128, 128, 255));
+        assert_eq!(colors[3], DEFAULT_COLOR);
+
+        for value in [0.0, 0.002, 0.2, 0.5, 1.0] {
+            assert_relative_eq!(
+                linear_to_srgb(srgb_to_linear(value)),
+                value,
+                epsilon = 1e-12
+            );
+        }
+        // middle gray in sRGB is about a fifth of the light
+        assert_relative_eq!(srgb_to_linear(0.5), 0.214, epsilon = 1e-3);
+        let color = Vector4::new(12, 100, 250, 40);
+        assert_eq!(color_from_linear(&color_to_linear(&color)), color);
+
+        let mut mesh = create_box(&[1.0, 1.0, 1.0]);
+        assert!(mesh.face_colors_to_vertex_colors().is_none());
+        let red = Vector4::new(255, 0, 0, 255);
+        mesh.attributes_face
+            .colors
+            .push(vec![red; mesh.faces.len()]);
+        let vertex = mesh.face_colors_to_vertex_colors().unwrap();
+        assert!(vertex.iter().all(|c| *c == red));
+
+        // red and green mix to a bright yellow in linear light
+        mesh.attributes_vertex.colors.push(
+            mesh.vertices
+                .iter()
+                .map(|v| {
+                    if v.x > 0.0 {
+                        red
+                    } else {
+                        Vector4::new(0, 255, 0, 255)
+                    }
+                })
+                .collect(),
+        );
+        let face = mesh.vertex_colors_to_face_colors().unwrap();
+        assert_eq!(face.len(), mesh.faces.len());
+        // a third green is brighter than the third of 255 it would be in sRGB
+        let mixed = face.iter().find(|c| c.x > 0 && c.y > 0).unwrap();
+        assert!(mixed.x.min(mixed.y) > 150);
+    }
+}
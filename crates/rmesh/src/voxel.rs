@@ -0,0 +1,319 @@
+use std::collections::VecDeque;
+
+use anyhow::{Result, anyhow};
+use nalgebra::{Point3, Vector3};
+use rayon::prelude::*;
+
+use crate::mesh::Trimesh;
+
+/// The most voxels a grid may have, to fail on a tiny pitch
+/// rather than trying to allocate an enormous grid.
+const MAX_VOXELS: usize = 1 << 28;
+
+/// How the inside of a mesh is filled when voxelizing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VoxelFill {
+    // only the voxels the surface passes through
+    Surface,
+    // cast rays along every axis and count surface crossings, with a
+    // voxel inside if the majority of axes agree, so a hole only
+    // misleads the rays which pass through it
+    #[default]
+    Parity,
+    // flood the empty space from outside of the mesh and fill every
+    // voxel it can't reach, which handles nested and intersecting
+    // shells but leaks through any hole larger than a voxel
+    FloodFill,
+}
+
+/// Options for voxelizing a mesh.
+#[derive(Debug, Clone, Default)]
+pub struct VoxelizeOptions {
+    pub fill: VoxelFill,
+}
+
+/// A regular grid of voxels which are either filled or empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxelGrid {
+    // the lower corner of the first voxel
+    pub origin: Point3<f64>,
+    // the edge length of every voxel
+    pub pitch: f64,
+    // the number of voxels along each axis
+    pub shape: [usize; 3],
+    // whether each voxel is filled, with the last axis varying fastest
+    pub filled: Vec<bool>,
+}
+
+impl VoxelGrid {
+    /// The flat index of a voxel.
+    fn index(&self, i: usize, j: usize, k: usize) -> usize {
+        (i * self.shape[1] + j) * self.shape[2] + k
+    }
+
+    /// Is a voxel filled, which is false outside of the grid.
+    pub fn get(&self, i: usize, j: usize, k: usize) -> bool {
+        i < self.shape[0]
+            && j < self.shape[1]
+            && k < self.shape[2]
+            && self.filled[self.index(i, j, k)]
+    }
+
+    /// The number of filled voxels.
+    pub fn count(&self) -> usize {
+        self.filled.iter().filter(|f| **f).count()
+    }
+
+    /// The volume of the filled voxels.
+    pub fn volume(&self) -> f64 {
+        self.count() as f64 * self.pitch.powi(3)
+    }
+
+    /// The center of a voxel.
+    pub fn center(&self, i: usize, j: usize, k: usize) -> Point3<f64> {
+        self.origin + Vector3::new(i as f64 + 0.5, j as f64 + 0.5, k as f64 + 0.5) * self.pitch
+    }
+
+    /// The center of every filled voxel.
+    pub fn points(&self) -> Vec<Point3<f64>> {
+        let [_, ny, nz] = self.shape;
+        self.filled
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| **f)
+            .map(|(index, _)| self.center(index / (ny * nz), (index / nz) % ny, index % nz))
+            .collect()
+    }
+}
+
+impl Trimesh {
+    /// Mark every voxel the surface passes through by sampling each
+    /// face more densely than the voxels.
+    fn voxelize_surface(&self, grid: &mut VoxelGrid) {
+        let spacing = grid.pitch / 2.0;
+        let cell = |p: Point3<f64>| {
+            let local = (p - grid.origin) / grid.pitch;
+            // points on the upper bound belong to the last voxel
+            [0, 1, 2].map(|a| (local[a].max(0.0) as usize).min(grid.shape[a] - 1))
+        };
+        let cells: Vec<[usize; 3]> = self
+            .par_triangles()
+            .flat_map_iter(|[a, b, c]| {
+                let longest = (b - a).norm().max((c - b).norm()).max((a - c).norm());
+                let steps = ((longest / spacing).ceil() as usize).max(1);
+                (0..=steps).flat_map(move |u| {
+                    (0..=steps - u).map(move |v| {
+                        let (u, v) = (u as f64 / steps as f64, v as f64 / steps as f64);
+                        cell(a + (b - a) * u + (c - a) * v)
+                    })
+                })
+            })
+            .collect();
+        for [i, j, k] in cells {
+            let index = grid.index(i, j, k);
+            grid.filled[index] = true;
+        }
+    }
+
+    /// Is the center of every voxel inside the mesh according to the
+    /// parity of surface crossings of rays along one axis.
+    fn voxelize_parity(&self, grid: &VoxelGrid, axis: usize) -> Result<Vec<bool>> {
+        let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut direction = Vector3::zeros();
+        direction[axis] = 1.0;
+
+        // one ray along every column of voxels starting outside the grid
+        let mut origins = Vec::with_capacity(grid.shape[u] * grid.shape[v]);
+        for a in 0..grid.shape[u] {
+            for b in 0..grid.shape[v] {
+                let mut origin = grid.origin;
+                origin[u] += (a as f64 + 0.5) * grid.pitch;
+                origin[v] += (b as f64 + 0.5) * grid.pitch;
+                origin[axis] -= grid.pitch;
+                origins.push(origin);
+            }
+        }
+        let directions = vec![direction; origins.len()];
+        let mut crossings = vec![Vec::new(); origins.len()];
+        for (ray, hit) in self.intersects_location(&origins, &directions)? {
+            crossings[ray].push(hit.distance - grid.pitch);
+        }
+
+        let mut inside = vec![false; grid.filled.len()];
+        let tolerance = grid.pitch * 1e-9;
+        for (column, mut hits) in crossings.into_iter().enumerate() {
+            // a ray through an edge or vertex hits several faces at once
+            hits.sort_by(f64::total_cmp);
+            hits.dedup_by(|a, b| (*a - *b).abs() < tolerance);
+            let (a, b) = (column / grid.shape[v], column % grid.shape[v]);
+            for c in 0..grid.shape[axis] {
+                let center = (c as f64 + 0.5) * grid.pitch;
+                if hits.iter().filter(|h| **h < center).count() % 2 == 1 {
+                    let mut cell = [0; 3];
+                    (cell[axis], cell[u], cell[v]) = (c, a, b);
+                    inside[grid.index(cell[0], cell[1], cell[2])] = true;
+                }
+            }
+        }
+        Ok(inside)
+    }
+
+    /// Voxelize the mesh into a regular grid covering its bounds.
+    ///
+    /// Parameters
+    /// ------------
+    /// pitch
+    ///   The edge length of every voxel.
+    /// options
+    ///   How to fill the inside of the mesh.
+    ///
+    /// Returns
+    /// ------------
+    /// grid
+    ///   The voxels of the mesh or an error if the mesh is empty, the
+    ///   pitch isn't positive and finite or the grid would be too large.
+    pub fn voxelize(&self, pitch: f64, options: &VoxelizeOptions) -> Result<VoxelGrid> {
+        if pitch <= 0.0 || !pitch.is_finite() {
+            return Err(anyhow!("Voxel pitch must be positive and finite: {pitch}"));
+        }
+        let (lower, upper) = self
+            .bounds()
+            .filter(|_| !self.is_empty())
+            .ok_or(anyhow!("Can't voxelize an empty mesh"))?;
+        let shape = [0, 1, 2].map(|a| (((upper[a] - lower[a]) / pitch).ceil() as usize).max(1));
+        let total = shape
+            .iter()
+            .try_fold(1usize, |total, s| total.checked_mul(*s))
+            .filter(|t| *t <= MAX_VOXELS)
+            .ok_or(anyhow!("Voxel grid of {shape:?} is too large"))?;
+
+        let mut grid = VoxelGrid {
+            origin: lower,
+            pitch,
+            shape,
+            filled: vec![false; total],
+        };
+        self.voxelize_surface(&mut grid);
+
+        match options.fill {
+            VoxelFill::Surface => {}
+            VoxelFill::Parity => {
+                let votes = (0..3)
+                    .map(|axis| self.voxelize_parity(&grid, axis))
+                    .collect::<Result<Vec<_>>>()?;
+                for (index, filled) in grid.filled.iter_mut().enumerate() {
+                    *filled |= votes.iter().filter(|v| v[index]).count() >= 2;
+                }
+            }
+            VoxelFill::FloodFill => {
+                // pad by one voxel so the outside is connected around the mesh
+                let padded = shape.map(|s| s + 2);
+                let flat = |c: [usize; 3]| (c[0] * padded[1] + c[1]) * padded[2] + c[2];
+                let blocked = |c: [usize; 3]| {
+                    (0..3).all(|a| c[a] >= 1 && c[a] <= shape[a])
+                        && grid.get(c[0] - 1, c[1] - 1, c[2] - 1)
+                };
+                let mut outside = vec![false; padded.iter().product()];
+                let mut queue = VecDeque::from([[0usize; 3]]);
+                outside[0] = true;
+                while let Some(cell) = queue.pop_front() {
+                    for axis in 0..3 {
+                        for step in [-1isize, 1] {
+                            let Some(next) = cell[axis]
+                                .checked_add_signed(step)
+                                .filter(|n| *n < padded[axis])
+                            else {
+                                continue;
+                            };
+                            let mut neighbor = cell;
+                            neighbor[axis] = next;
+                            if !outside[flat(neighbor)] && !blocked(neighbor) {
+                                outside[flat(neighbor)] = true;
+                                queue.push_back(neighbor);
+                            }
+                        }
+                    }
+                }
+                for i in 0..shape[0] {
+                    for j in 0..shape[1] {
+                        for k in 0..shape[2] {
+                            let index = grid.index(i, j, k);
+                            grid.filled[index] = !outside[flat([i + 1, j + 1, k + 1])];
+                        }
+                    }
+                }
+            }
+        }
+        Ok(grid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::creation::create_box;
+
+    #[test]
+    fn test_voxelize() {
+        let mesh = create_box(&[2.0, 2.0, 2.0]);
+        let surface = mesh
+            .voxelize(
+                0.3,
+                &VoxelizeOptions {
+                    fill: VoxelFill::Surface,
+                },
+            )
+            .unwrap();
+        assert_eq!(surface.shape, [7, 7, 7]);
+        // a shell of voxels with an empty inside
+        assert!(surface.get(0, 3, 3) && surface.get(6, 3, 3));
+        assert!(!surface.get(3, 3, 3));
+        assert_eq!(surface.count(), 7 * 7 * 7 - 5 * 5 * 5);
+
+        // a closed mesh is filled completely either way
+        let parity = mesh.voxelize(0.3, &VoxelizeOptions::default()).unwrap();
+        assert_eq!(parity.count(), 7 * 7 * 7);
+        let flood = mesh
+            .voxelize(
+                0.3,
+                &VoxelizeOptions {
+                    fill: VoxelFill::FloodFill,
+                },
+            )
+            .unwrap();
+        assert_eq!(flood.filled, parity.filled);
+        assert_eq!(parity.points().len(), parity.count());
+
+        // remove the top of the box so it leaks
+        let normals = mesh.face_normals();
+        let faces = mesh
+            .faces
+            .iter()
+            .zip(normals.iter())
+            .filter(|(_, n)| n.z < 0.5)
+            .map(|(f, _)| *f)
+            .collect();
+        let open = Trimesh::new(mesh.vertices.clone(), faces, None, None).unwrap();
+        // voting outvotes the axis whose rays pass through the hole
+        let parity = open.voxelize(0.3, &VoxelizeOptions::default()).unwrap();
+        assert_eq!(parity.count(), 7 * 7 * 7);
+        // while flooding leaks inside and leaves only the walls
+        let flood = open
+            .voxelize(
+                0.3,
+                &VoxelizeOptions {
+                    fill: VoxelFill::FloodFill,
+                },
+            )
+            .unwrap();
+        assert!(!flood.get(3, 3, 3));
+
+        assert!(mesh.voxelize(0.0, &VoxelizeOptions::default()).is_err());
+        assert!(
+            Trimesh::default()
+                .voxelize(1.0, &VoxelizeOptions::default())
+                .is_err()
+        );
+    }
+}
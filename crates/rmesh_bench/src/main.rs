@@ -0,0 +1,94 @@
+//! Load a corpus of mesh files in parallel and report how long each
+//! one took, for comparing loader performance across changes.
+//!
+//! Usage: `rmesh-bench <file or directory>...`
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, anyhow};
+
+use rmesh::exchange::{MeshFormat, load_many_timed};
+use rmesh::stats::percentiles;
+
+/// Collect every file under a path with the extension of a supported format.
+fn collect(path: &Path, files: &mut Vec<(PathBuf, MeshFormat)>) -> Result<()> {
+    if path.is_dir() {
+        let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<_, _>>()?;
+        entries.sort();
+        for entry in entries {
+            collect(&entry, files)?;
+        }
+    } else if let Some(format) = path
+        .extension()
+        .and_then(|e| MeshFormat::from_string(&e.to_string_lossy()).ok())
+    {
+        files.push((path.to_path_buf(), format));
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let roots: Vec<PathBuf> = std::env::args().skip(1).map(PathBuf::from).collect();
+    if roots.is_empty() {
+        return Err(anyhow!("Usage: rmesh-bench <file or directory>..."));
+    }
+    let mut files = Vec::new();
+    for root in roots.iter() {
+        collect(root, &mut files)?;
+    }
+
+    // read everything up front so only the parsing is timed
+    let items = files
+        .iter()
+        .map(|(path, format)| Ok((fs::read(path)?, format.clone())))
+        .collect::<Result<Vec<_>>>()?;
+
+    let start = Instant::now();
+    let loaded = load_many_timed(&items);
+    let wall = start.elapsed();
+
+    let mut failed = 0;
+    for ((path, _), (result, stats)) in files.iter().zip(loaded.iter()) {
+        match result {
+            Ok(_) => println!(
+                "{:>10.3} ms {:>9.1} MB/s {:>10} faces  {}",
+                stats.duration.as_secs_f64() * 1e3,
+                stats.throughput(),
+                stats.faces,
+                path.display()
+            ),
+            Err(error) => {
+                failed += 1;
+                println!("{:>13} {}: {error}", "failed", path.display());
+            }
+        }
+    }
+
+    let times: Vec<f64> = loaded
+        .iter()
+        .map(|(_, stats)| stats.duration.as_secs_f64() * 1e3)
+        .collect();
+    let busy: Duration = loaded.iter().map(|(_, stats)| stats.duration).sum();
+    let bytes: usize = loaded.iter().map(|(_, stats)| stats.bytes).sum();
+    let faces: usize = loaded.iter().map(|(_, stats)| stats.faces).sum();
+    println!(
+        "\nloaded {} of {} files with {faces} faces in {:.3} s wall, {:.3} s in loaders, {:.1} MB/s",
+        files.len() - failed,
+        files.len(),
+        wall.as_secs_f64(),
+        busy.as_secs_f64(),
+        bytes as f64 / 1e6 / wall.as_secs_f64().max(f64::EPSILON)
+    );
+    if !times.is_empty() {
+        let p = percentiles(&times, &[50.0, 95.0, 100.0])?;
+        println!(
+            "per file: median {:.3} ms, p95 {:.3} ms, max {:.3} ms",
+            p[0], p[1], p[2]
+        );
+    }
+    Ok(())
+}
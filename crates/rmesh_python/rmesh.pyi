@@ -0,0 +1,386 @@
+"""
+Type stubs for `rmesh` generated by `typegen.py`, do not edit by hand.
+"""
+
+from numpy import bool_, float64, int64
+from numpy.typing import NDArray
+
+
+def detect_format(file_data: bytes) -> str:
+    """Detect the format of a file from its contents, returning the file extension."""
+
+def icp(source: Trimesh, target: Trimesh, initial: NDArray[float64] | None = None, max_iterations: int = 50, tolerance: float = 1e-8, method: str = "point_to_point") -> tuple[NDArray[float64], float]:
+    """
+    Align a source mesh onto a target mesh with iterative closest
+    point, starting from an optional (4, 4) transform and using either the
+    `point_to_point` or `point_to_plane` method, returning the (4, 4)
+    transform from the source onto the target and its mean residual.
+    """
+
+def load(file: bytes | str, file_type: str | None = None) -> Trimesh | Scene:
+    """
+    Load a file from its bytes or a path, returning a Trimesh if it
+    contains a single mesh and a Scene otherwise. The format is `file_type`,
+    or the extension of a path, or detected from the contents.
+    """
+
+def load_mesh(file_data: bytes, file_type: str) -> Trimesh:
+    """Load a mesh from a file, doing no initial processing."""
+
+def load_mesh_auto(file_data: bytes) -> Trimesh:
+    """Load a mesh from a file, detecting the format from the file contents."""
+
+def load_scene(file_data: bytes, file_type: str) -> Scene:
+    """Load a file into a scene, preserving multiple objects and their transforms."""
+
+def procrustes(points_a: NDArray[float64], points_b: NDArray[float64], reflection: bool = True, scale: bool = True) -> tuple[NDArray[float64], float]:
+    """
+    Find the (4, 4) transform moving an (n, 3) array of points onto
+    corresponding points with the least squared error, optionally allowing
+    reflection and uniform scale, returning it and the mean residual.
+    """
+
+def rectangle(width: float, height: float) -> Path:
+    """Create a rectangular path centered at the origin."""
+
+def transform_points(points: NDArray[float64], matrix: NDArray[float64]) -> NDArray[float64]:
+    """Apply a (4, 4) homogeneous transform to an (n, 3) array of points."""
+
+class RmeshError(Exception):
+    """The base class of every error raised by rmesh."""
+
+class ParseError(RmeshError):
+    """The contents of a file don't match its format."""
+
+class UnsupportedFormatError(RmeshError):
+    """The format of a file isn't supported or couldn't be detected."""
+
+class InvalidGeometryError(RmeshError):
+    """The geometry is inconsistent, like a face referencing a missing vertex."""
+
+class Path:
+    def __init__(self, vertices: NDArray[float64], entities: list[tuple[str, list[int]]]) -> None:
+        """
+        Create a new Path from (n, 3) vertices and a list of entities
+        as `(kind, indices)` where kind is `line`, `bezier`, `circle`, `arc`
+        or `arc_cw` and indices reference the vertices.
+        """
+    def __repr__(self) -> str:
+        ...
+    def discrete(self, resolution: int) -> list[NDArray[float64]]:
+        """Discretize every entity into an (n, 3) array of points."""
+    @property
+    def entities(self) -> list[tuple[str, list[int]]]:
+        """Every entity as its kind and the indices of its vertices."""
+    def extrude(self, height: float, resolution: int = 32) -> Trimesh:
+        """Extrude the closed entities of the path along Z into a mesh."""
+    @property
+    def length(self) -> float:
+        """The total length of every entity."""
+    def revolve(self, angle: float, sections: int = 32, resolution: int = 32) -> Trimesh:
+        """Revolve the path around the Z axis using X as the radius and Y as the height."""
+    @property
+    def vertices(self) -> NDArray[float64]:
+        """The (n, 3) float64 positions of the vertices."""
+
+class Scene:
+    def __init__(self) -> None:
+        """Create an empty scene."""
+    def __repr__(self) -> str:
+        ...
+    def add_geometry(self, mesh: Trimesh, node_name: str | None = None, transform: NDArray[float64] | None = None, parent_node_name: str | None = None) -> str:
+        """
+        Add a mesh to the scene under a new node, returning the name of
+        the node. The node is placed under the root or a named parent
+        with an optional (4, 4) transform from the parent, and a root
+        named `world` is created for the first geometry of an empty scene.
+        """
+    def dump(self) -> list[tuple[NDArray[float64], Trimesh]]:
+        """
+        Every placement of a mesh as the (4, 4) transform from
+        the root and a copy of the mesh in its own frame.
+        """
+    def export(self, path: str, file_type: str | None = None, digits: int | None = None) -> None:
+        """
+        Export every placement of a mesh combined into a single mesh to a
+        file in an ASCII format, which is the extension of the path unless
+        `file_type` is passed, optionally with a fixed number of digits.
+        """
+    def flatten(self) -> Trimesh:
+        """
+        Every placement of a mesh combined into a single mesh in the
+        frame of the root, which is what trimesh calls `dump(concatenate=True)`.
+        """
+    @property
+    def geometry(self) -> list[Trimesh | None]:
+        """
+        The meshes in the scene in the frame they were defined in,
+        or `None` for geometry which isn't a mesh.
+        """
+    @property
+    def graph(self) -> SceneGraph:
+        """The scene graph, which edits the nodes of this scene."""
+    def instances(self) -> list[tuple[str, int, NDArray[float64]]]:
+        """
+        Every placement of geometry in the scene as the name of the node,
+        the index of the geometry and the (4, 4) transform from the root.
+        """
+    @property
+    def node_names(self) -> list[str]:
+        """The name of every node in the scene graph."""
+    def show(self) -> None:
+        """
+        Show every mesh in the scene in a three.js viewer in the
+        default browser, blocking until the page is closed.
+        """
+
+class SceneGraph:
+    def __getitem__(self, name: str) -> tuple[NDArray[float64], int | None]:
+        ...
+    def __repr__(self) -> str:
+        ...
+    def get(self, name: str) -> tuple[NDArray[float64], int | None]:
+        """
+        The (4, 4) transform from the root to the first placement of a
+        node and the first geometry index it places, like `graph[name]`
+        in trimesh.
+        """
+    def node(self, name: str) -> SceneNode:
+        """A copy of the node with a name."""
+    @property
+    def nodes(self) -> list[str]:
+        """The name of every node."""
+    @property
+    def root(self) -> str | None:
+        """The name of the root node, or `None` for an empty scene."""
+    def set_transform(self, name: str, matrix: NDArray[float64]) -> None:
+        """Replace the (4, 4) transform from the parent of a node to the node."""
+
+class SceneNode:
+    def __repr__(self) -> str:
+        ...
+    @property
+    def children(self) -> list[str]:
+        """The names of the child nodes."""
+    @property
+    def geometry(self) -> list[int]:
+        """The indices into `Scene.geometry` placed by the node."""
+    @property
+    def kind(self) -> str:
+        """What the node is: `geometry`, `camera`, `light`, `custom` or `frame`."""
+    @property
+    def name(self) -> str:
+        """The name of the node."""
+    @property
+    def transform(self) -> NDArray[float64]:
+        """The (4, 4) transform from the parent to this node."""
+
+class Trimesh:
+    def __init__(self, vertices: NDArray[float64], faces: NDArray[int64]) -> None:
+        """Create a new Trimesh from vertices and faces."""
+    def __repr__(self) -> str:
+        ...
+    def apply_transform(self, matrix: NDArray[float64]) -> Trimesh:
+        """Apply a (4, 4) homogeneous transform to the mesh, returning a new mesh."""
+    def apply_translation(self, translation: list[float]) -> Trimesh:
+        """Move the mesh by a translation vector, returning a new mesh."""
+    @property
+    def area(self) -> float:
+        """The summed area of every face."""
+    def bounding_box_oriented(self) -> tuple[list[float], NDArray[float64]] | None:
+        """
+        An oriented box containing the mesh with close to the smallest
+        volume as its extents and the (4, 4) transform placing its center
+        and axes, or `None` for a mesh without vertices.
+        """
+    @property
+    def bounds(self) -> NDArray[float64] | None:
+        """
+        The (2, 3) float64 lower and upper corners of the axis aligned
+        bounding box of the vertices, or `None` if there are no vertices.
+        """
+    @property
+    def center_mass(self) -> NDArray[float64] | None:
+        """
+        The (3,) float64 center of mass of the volume enclosed by the mesh,
+        or `None` if it doesn't enclose any volume.
+        """
+    def closest_point(self, points: NDArray[float64]) -> tuple[NDArray[float64], NDArray[float64], NDArray[int64]]:
+        """
+        Find the closest point on the surface to each point of an (n, 3)
+        array, returning the (n, 3) closest points, the (n,) distances and
+        the (n,) int64 index of the face each closest point is on.
+        """
+    def contains(self, points: NDArray[float64]) -> NDArray[bool_]:
+        """
+        Check whether each point of an (n, 3) array is inside the
+        mesh, returning an (n,) bool array.
+        """
+    def convex_hull(self) -> Trimesh:
+        """The convex hull of the vertices, returning a new mesh."""
+    def difference(self, other: Trimesh) -> Trimesh:
+        """The volume inside this closed mesh but not another, returning a new mesh."""
+    @property
+    def edges(self) -> NDArray[int64]:
+        """
+        The (m * 3, 2) int64 vertex indices of the edges of every face,
+        with the edges of face `i` in rows `3 * i` to `3 * i + 2`.
+        """
+    def export(self, file_type: str, digits: int | None = None, quantize: float | None = None, bake_textures: float | None = None, path: str | None = None) -> str:
+        """
+        Export the mesh to the text of an ASCII format, optionally with a
+        fixed number of digits, with vertices snapped to a grid or with
+        textures baked into vertex colors after subdividing to an edge length.
+        The text is also written to `path` if one is passed.
+        """
+    @property
+    def face_adjacency(self) -> NDArray[int64]:
+        """The (k, 2) int64 indices of every pair of faces sharing an edge."""
+    @property
+    def face_adjacency_angles(self) -> NDArray[float64]:
+        """
+        The (k,) float64 angle in radians between the normals of
+        every pair of faces in `face_adjacency`.
+        """
+    @property
+    def face_normals(self) -> NDArray[float64]:
+        """
+        The (m, 3) float64 unit normal of every face, which
+        is zero for degenerate faces without any area.
+        """
+    @property
+    def faces(self) -> NDArray[int64]:
+        """The (m, 3) int64 indices of the vertices of each triangle."""
+    @property
+    def faces_area(self) -> NDArray[float64]:
+        """The (m,) float64 area of every face."""
+    def histogram(self, name: str, bins: int = 10) -> tuple[NDArray[int64], NDArray[float64]]:
+        """
+        Count a quality metric into equal width bins, returning
+        the counts and the bin edges like `numpy.histogram`.
+        """
+    def intersection(self, other: Trimesh) -> Trimesh:
+        """The volume inside both this closed mesh and another, returning a new mesh."""
+    def intersects_any(self, origins: NDArray[float64], directions: NDArray[float64]) -> NDArray[bool_]:
+        """
+        Check whether each ray in (n, 3) arrays of origins
+        and directions hits the mesh, returning an (n,) bool array.
+        """
+    def intersects_id(self, origins: NDArray[float64], directions: NDArray[float64]) -> NDArray[int64]:
+        """
+        Find the first face hit by each ray in (n, 3) arrays of origins and
+        directions, returning an (n,) int64 array with -1 for rays that missed.
+        """
+    def intersects_location(self, origins: NDArray[float64], directions: NDArray[float64]) -> tuple[NDArray[float64], NDArray[int64], NDArray[int64]]:
+        """
+        Find every hit of each ray in (n, 3) arrays of origins and directions,
+        returning the (m, 3) locations, the (m,) index of the ray for each hit
+        and the (m,) index of the face for each hit.
+        """
+    @property
+    def is_watertight(self) -> bool:
+        """Is every edge shared by exactly two faces."""
+    @property
+    def is_winding_consistent(self) -> bool:
+        """Is every edge shared by two faces traversed in opposite directions."""
+    def merge_vertices(self, tolerance: float) -> Trimesh:
+        """Weld vertices closer than a tolerance, returning a new mesh."""
+    def metric(self, name: str) -> NDArray[float64]:
+        """
+        Compute a quality metric like `edge_length`, `face_area`,
+        `dihedral_angle` or `aspect_ratio` for every element.
+        """
+    @property
+    def moment_inertia(self) -> NDArray[float64] | None:
+        """
+        The (3, 3) float64 moment of inertia tensor about the center of mass
+        with a density of one, or `None` if the mesh doesn't enclose any volume.
+        """
+    def nearest_vertex(self, points: NDArray[float64], k: int = 1) -> tuple[NDArray[float64], NDArray[int64]]:
+        """
+        Find the `k` vertices closest to each point of an (n, 3) array,
+        returning the (n, k) distances and (n, k) int64 vertex indices from
+        closest to farthest, padded with infinity and -1 past the vertex count.
+        """
+    def percentiles(self, name: str, percents: list[float]) -> NDArray[float64]:
+        """Compute percentiles between 0 and 100 of a quality metric."""
+    def pick(self, origin: list[float], direction: list[float]) -> tuple[int, list[float], list[float], float] | None:
+        """
+        Find the first face hit by a ray, returning the face index, the
+        hit location, its barycentric coordinates on the face and the
+        distance along the ray.
+        """
+    def principal_inertia_transform(self) -> NDArray[float64] | None:
+        """
+        The (4, 4) transform moving the center of mass to the origin and
+        the principal axes of inertia onto X, Y and Z, or `None` if the
+        mesh doesn't enclose any volume.
+        """
+    def process(self, validate: bool = False) -> Trimesh:
+        """
+        Apply the default cleanup to the mesh, also removing degenerate
+        and duplicate faces and fixing normals if validating, returning a new mesh.
+        """
+    def py_check(self) -> int:
+        ...
+    def show(self) -> None:
+        """
+        Show the mesh in a three.js viewer in the default browser,
+        blocking until the page is closed.
+        """
+    def simplify(self, target_count: int, aggressiveness: float = 7.0) -> Trimesh:
+        """
+        Collapse edges until the mesh has about a target number
+        of faces, returning a new mesh.
+        """
+    def smooth_shaded(self, threshold: float) -> Trimesh:
+        """
+        Split vertices along edges sharper than an angle in radians,
+        returning a new mesh with smooth vertex normals.
+        """
+    def snap(self, grid: float) -> Trimesh:
+        """Snap vertices to a grid and weld any that coincide, returning a new mesh."""
+    def union(self, other: Trimesh) -> Trimesh:
+        """The volume inside either this closed mesh or another, returning a new mesh."""
+    @property
+    def uv(self) -> NDArray[float64] | None:
+        """The (n, 2) float64 texture coordinates of the vertices if loaded."""
+    @property
+    def uv_view(self) -> NDArray[float64] | None:
+        """
+        A read-only (n, 2) float64 view of the texture coordinates if loaded,
+        which shares memory with the mesh rather than copying it like `uv`.
+        """
+    @property
+    def vertex_defects(self) -> NDArray[float64]:
+        """
+        The (n,) float64 angle defect of every vertex in radians,
+        which is the discrete Gaussian curvature.
+        """
+    @property
+    def vertex_degree(self) -> NDArray[int64]:
+        """The (n,) int64 number of vertices sharing an edge with each vertex."""
+    @property
+    def vertex_normals(self) -> NDArray[float64]:
+        """
+        The (n, 3) float64 angle weighted normal of every vertex,
+        or the normals loaded from the file if it had them.
+        """
+    @property
+    def vertices(self) -> NDArray[float64]:
+        """The (n, 3) float64 positions of the vertices."""
+    @property
+    def vertices_view(self) -> NDArray[float64]:
+        """
+        A read-only (n, 3) float64 view of the vertices which shares memory
+        with the mesh rather than copying it like `vertices`, and keeps the
+        mesh alive for as long as the view exists.
+        """
+    def vertices_within(self, point: list[float], radius: float) -> NDArray[int64]:
+        """The int64 indices of every vertex within a radius of a point."""
+    @property
+    def volume(self) -> float:
+        """
+        The volume enclosed by the mesh, which is only meaningful if it's
+        watertight and is negative if the faces are wound inward.
+        """
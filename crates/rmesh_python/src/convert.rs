@@ -0,0 +1,153 @@
+use anyhow::Result;
+use nalgebra::{Matrix3, Matrix4, Point3, Vector2, Vector3};
+use numpy::ndarray::{Array2, ArrayView2};
+use numpy::{Element, PyArray1, PyArray2, PyArrayMethods};
+use pyo3::prelude::*;
+
+/// Convert mesh data into a numpy array owned by Python, with points and
+/// vectors as rows of a 2D float64 array and indices as int64 so they
+/// can be used directly to index other numpy arrays.
+pub(crate) trait ToNumPy {
+    type Array;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array>;
+}
+
+/// Stack fixed length rows into an (n, N) array.
+fn from_rows<T: Element, const N: usize>(py: Python<'_>, rows: Vec<[T; N]>) -> Py<PyArray2<T>> {
+    PyArray2::from_owned_array(py, Array2::from(rows)).into()
+}
+
+impl ToNumPy for [Point3<f64>] {
+    type Array = PyArray2<f64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        from_rows(py, self.iter().map(|p| [p.x, p.y, p.z]).collect())
+    }
+}
+
+impl ToNumPy for [Vector3<f64>] {
+    type Array = PyArray2<f64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        from_rows(py, self.iter().map(|v| [v.x, v.y, v.z]).collect())
+    }
+}
+
+impl ToNumPy for [Vector2<f64>] {
+    type Array = PyArray2<f64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        from_rows(py, self.iter().map(|v| [v.x, v.y]).collect())
+    }
+}
+
+impl ToNumPy for [(usize, usize, usize)] {
+    type Array = PyArray2<i64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        from_rows(
+            py,
+            self.iter()
+                .map(|&(a, b, c)| [a as i64, b as i64, c as i64])
+                .collect(),
+        )
+    }
+}
+
+impl ToNumPy for [(usize, usize)] {
+    type Array = PyArray2<i64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        from_rows(
+            py,
+            self.iter().map(|&(a, b)| [a as i64, b as i64]).collect(),
+        )
+    }
+}
+
+impl ToNumPy for [[usize; 2]] {
+    type Array = PyArray2<i64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        from_rows(
+            py,
+            self.iter().map(|[a, b]| [*a as i64, *b as i64]).collect(),
+        )
+    }
+}
+
+impl ToNumPy for [f64] {
+    type Array = PyArray1<f64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        PyArray1::from_slice(py, self).into()
+    }
+}
+
+impl ToNumPy for [usize] {
+    type Array = PyArray1<i64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        PyArray1::from_iter(py, self.iter().map(|i| *i as i64)).into()
+    }
+}
+
+impl ToNumPy for [bool] {
+    type Array = PyArray1<bool>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        PyArray1::from_slice(py, self).into()
+    }
+}
+
+impl ToNumPy for Point3<f64> {
+    type Array = PyArray1<f64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        PyArray1::from_slice(py, self.coords.as_slice()).into()
+    }
+}
+
+impl ToNumPy for Matrix3<f64> {
+    type Array = PyArray2<f64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        // nalgebra is column major so read it out by rows
+        from_rows(
+            py,
+            (0..3).map(|r| [0, 1, 2].map(|c| self[(r, c)])).collect(),
+        )
+    }
+}
+
+impl ToNumPy for Matrix4<f64> {
+    type Array = PyArray2<f64>;
+
+    fn to_numpy(&self, py: Python<'_>) -> Py<Self::Array> {
+        from_rows(
+            py,
+            (0..4).map(|r| [0, 1, 2, 3].map(|c| self[(r, c)])).collect(),
+        )
+    }
+}
+
+/// Borrow rows of packed floats like points as a read-only (n, N) numpy
+/// array without copying them, with `owner` set as the base of the array
+/// so the memory stays alive for as long as the array does.
+///
+/// # Safety
+///
+/// The rows must be owned by `owner` and must not be modified or
+/// reallocated for as long as `owner` is alive.
+pub(crate) unsafe fn borrow_rows<'py, T: bytemuck::Pod, const N: usize>(
+    rows: &[T],
+    owner: Bound<'py, PyAny>,
+) -> Result<Bound<'py, PyArray2<f64>>> {
+    let flat: &[f64] = bytemuck::cast_slice(rows);
+    let view = ArrayView2::from_shape((flat.len() / N, N), flat)?;
+    // SAFETY: the caller guarantees the rows outlive `owner`
+    let array = unsafe { PyArray2::borrow_from_array(&view, owner) };
+    array.readwrite().make_nonwriteable();
+    Ok(array)
+}
@@ -0,0 +1,51 @@
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyIOError};
+use pyo3::prelude::*;
+
+create_exception!(
+    rmesh,
+    RmeshError,
+    PyException,
+    "The base class of every error raised by rmesh."
+);
+create_exception!(
+    rmesh,
+    ParseError,
+    RmeshError,
+    "The contents of a file don't match its format."
+);
+create_exception!(
+    rmesh,
+    UnsupportedFormatError,
+    RmeshError,
+    "The format of a file isn't supported or couldn't be detected."
+);
+create_exception!(
+    rmesh,
+    InvalidGeometryError,
+    RmeshError,
+    "The geometry is inconsistent, like a face referencing a missing vertex."
+);
+
+/// Raise the Python exception matching the kind of an error,
+/// with I/O errors raised as the builtin `OSError`.
+pub(crate) fn to_py_err(error: rmesh::error::RmeshError) -> PyErr {
+    use rmesh::error::RmeshError as Kind;
+    let message = error.to_string();
+    match error {
+        Kind::Io(_) => PyIOError::new_err(message),
+        Kind::Parse { .. } => ParseError::new_err(message),
+        Kind::UnsupportedFormat(_) => UnsupportedFormatError::new_err(message),
+        Kind::InvalidGeometry(_) => InvalidGeometryError::new_err(message),
+        Kind::Other(_) => RmeshError::new_err(message),
+    }
+}
+
+/// Raise an error which may have come from rmesh as the matching
+/// Python exception, and anything else as the base `RmeshError`.
+pub(crate) fn anyhow_to_py_err(error: anyhow::Error) -> PyErr {
+    match error.downcast::<rmesh::error::RmeshError>() {
+        Ok(error) => to_py_err(error),
+        Err(error) => RmeshError::new_err(format!("{error:#}")),
+    }
+}
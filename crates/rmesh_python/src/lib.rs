@@ -1,6 +1,16 @@
+mod convert;
+mod error;
 mod mesh;
+mod path;
+mod scene;
 
-pub use mesh::{PyTrimesh, py_load_mesh};
+pub use error::{InvalidGeometryError, ParseError, RmeshError, UnsupportedFormatError};
+pub use mesh::{
+    PyTrimesh, py_detect_format, py_icp, py_load_mesh, py_load_mesh_auto, py_procrustes,
+    py_transform_points,
+};
+pub use path::{PyPath, py_rectangle};
+pub use scene::{PyScene, PySceneGraph, PySceneNode, py_load, py_load_scene};
 
 use pyo3::prelude::*;
 
@@ -8,6 +18,29 @@ use pyo3::prelude::*;
 #[pymodule]
 fn rmesh(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(py_load_mesh, m)?)?;
+    m.add_function(wrap_pyfunction!(py_load_mesh_auto, m)?)?;
+    m.add_function(wrap_pyfunction!(py_detect_format, m)?)?;
+    m.add_function(wrap_pyfunction!(py_rectangle, m)?)?;
+    m.add_function(wrap_pyfunction!(py_load_scene, m)?)?;
+    m.add_function(wrap_pyfunction!(py_load, m)?)?;
+    m.add_function(wrap_pyfunction!(py_transform_points, m)?)?;
+    m.add_function(wrap_pyfunction!(py_icp, m)?)?;
+    m.add_function(wrap_pyfunction!(py_procrustes, m)?)?;
     m.add_class::<PyTrimesh>()?;
+    m.add_class::<PyPath>()?;
+    m.add_class::<PyScene>()?;
+    m.add_class::<PySceneGraph>()?;
+    m.add_class::<PySceneNode>()?;
+    let py = m.py();
+    m.add("RmeshError", py.get_type::<RmeshError>())?;
+    m.add("ParseError", py.get_type::<ParseError>())?;
+    m.add(
+        "UnsupportedFormatError",
+        py.get_type::<UnsupportedFormatError>(),
+    )?;
+    m.add(
+        "InvalidGeometryError",
+        py.get_type::<InvalidGeometryError>(),
+    )?;
     Ok(())
 }
@@ -1,19 +1,78 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
-use nalgebra::Point3;
+use anyhow::anyhow;
+use nalgebra::{Matrix4, Point3, Vector3};
 use numpy::ndarray::Array2;
 use pyo3::prelude::*;
 
-use numpy::{PyArray2, PyReadonlyArray2};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray2};
 
-use rmesh::exchange::{MeshFormat, load_mesh};
+use rmesh::boolean;
+use rmesh::creation::PrimitiveKind;
+use rmesh::exchange::{ExportOptions, MeshFormat, export_mesh, load_mesh, load_mesh_auto};
 use rmesh::mesh::Trimesh;
+use rmesh::ray::Ray;
+use rmesh::registration::{IcpMethod, icp, procrustes};
+use rmesh::stats::{Metric, histogram, percentiles};
+use rmesh::transform::transform_points;
+
+use crate::convert::{ToNumPy, borrow_rows};
+use crate::error::to_py_err;
 
 //use crate::rmesh::mesh::{load_mesh, MeshFormat, Trimesh};
 
-#[pyclass(name = "Trimesh")]
+/// Convert (n, 3) arrays of ray origins and directions to points and vectors.
+fn rays_from_arrays(
+    origins: PyReadonlyArray2<'_, f64>,
+    directions: PyReadonlyArray2<'_, f64>,
+) -> (Vec<Point3<f64>>, Vec<Vector3<f64>>) {
+    let origins = origins
+        .as_array()
+        .rows()
+        .into_iter()
+        .map(|x| Point3::new(x[0], x[1], x[2]))
+        .collect();
+    let directions = directions
+        .as_array()
+        .rows()
+        .into_iter()
+        .map(|x| Vector3::new(x[0], x[1], x[2]))
+        .collect();
+    (origins, directions)
+}
+
+/// Convert an (n, 3) array to points.
+fn points_from_array(points: PyReadonlyArray2<'_, f64>) -> Result<Vec<Point3<f64>>> {
+    let points = points.as_array();
+    if points.ncols() != 3 {
+        return Err(anyhow!("Points must be (n, 3), got {:?}", points.shape()));
+    }
+    Ok(points
+        .rows()
+        .into_iter()
+        .map(|x| Point3::new(x[0], x[1], x[2]))
+        .collect())
+}
+
+/// Convert a (4, 4) array to a homogeneous transform.
+pub(crate) fn matrix_from_array(matrix: PyReadonlyArray2<'_, f64>) -> Result<Matrix4<f64>> {
+    let matrix = matrix.as_array();
+    if matrix.shape() != [4, 4] {
+        return Err(anyhow!(
+            "Transform must be (4, 4), got {:?}",
+            matrix.shape()
+        ));
+    }
+    Ok(Matrix4::from_fn(|row, column| matrix[[row, column]]))
+}
+
+// frozen so the mesh data is never modified in place,
+// which lets numpy views borrow its buffers safely
+#[pyclass(name = "Trimesh", frozen)]
 #[derive(Clone)]
 pub struct PyTrimesh {
-    data: Trimesh,
+    pub(crate) data: Trimesh,
 }
 
 #[pymethods]
@@ -23,7 +82,7 @@ impl PyTrimesh {
     pub fn new<'py>(
         vertices: PyReadonlyArray2<'py, f64>,
         faces: PyReadonlyArray2<'py, i64>,
-    ) -> Result<Self> {
+    ) -> PyResult<Self> {
         let vertices: Vec<Point3<f64>> = vertices
             .as_array()
             .rows()
@@ -39,58 +98,496 @@ impl PyTrimesh {
             .collect::<Vec<_>>();
 
         Ok(PyTrimesh {
-            data: Trimesh::new(vertices, faces, None, None)?,
+            data: Trimesh::new(vertices, faces, None, None).map_err(to_py_err)?,
         })
     }
 
+    /// The (n, 3) float64 positions of the vertices.
     #[getter]
     pub fn get_vertices<'py>(&self, py: Python<'py>) -> Py<PyArray2<f64>> {
-        // todo : is this the best way to do these conversions from Vec<Point3<f64>> to ndarray?
-        // todo : the output array should be read-only
         // todo : should we cache this numpy conversion?
-        let vertices = &self.data.vertices;
-        let shape = (vertices.len(), 3);
-
-        let arr = Array2::from_shape_vec(
-            shape,
-            vertices
-                .iter()
-                .flat_map(|p| p.coords.iter().cloned().collect::<Vec<_>>())
-                .collect(),
-        )
-        .unwrap();
+        self.data.vertices().to_numpy(py)
+    }
 
-        PyArray2::from_array(py, &arr).to_owned().into()
+    /// A read-only (n, 3) float64 view of the vertices which shares memory
+    /// with the mesh rather than copying it like `vertices`, and keeps the
+    /// mesh alive for as long as the view exists.
+    #[getter]
+    pub fn get_vertices_view<'py>(slf: Bound<'py, Self>) -> Result<Bound<'py, PyArray2<f64>>> {
+        let vertices = slf.get().data.vertices();
+        // SAFETY: the class is frozen so the vertices are never reallocated
+        unsafe { borrow_rows::<_, 3>(vertices, slf.clone().into_any()) }
     }
 
+    /// The (m, 3) int64 indices of the vertices of each triangle.
     #[getter]
     pub fn get_faces<'py>(&self, py: Python<'py>) -> Py<PyArray2<i64>> {
-        let faces = &self.data.faces;
-        let shape = (faces.len(), 3);
-
-        let arr = Array2::from_shape_vec(
-            shape,
-            faces
-                .iter()
-                .flat_map(|&(a, b, c)| vec![a as i64, b as i64, c as i64])
+        self.data.faces().to_numpy(py)
+    }
+
+    /// The (n, 2) float64 texture coordinates of the vertices if loaded.
+    #[getter]
+    pub fn get_uv<'py>(&self, py: Python<'py>) -> Option<Py<PyArray2<f64>>> {
+        self.data.uv().map(|uv| uv.to_numpy(py))
+    }
+
+    /// A read-only (n, 2) float64 view of the texture coordinates if loaded,
+    /// which shares memory with the mesh rather than copying it like `uv`.
+    #[getter]
+    pub fn get_uv_view<'py>(slf: Bound<'py, Self>) -> Result<Option<Bound<'py, PyArray2<f64>>>> {
+        let Some(uv) = slf.get().data.uv() else {
+            return Ok(None);
+        };
+        // SAFETY: the class is frozen so the coordinates are never reallocated
+        unsafe { borrow_rows::<_, 2>(uv, slf.clone().into_any()) }.map(Some)
+    }
+
+    /// The (n, 3) float64 angle weighted normal of every vertex,
+    /// or the normals loaded from the file if it had them.
+    #[getter]
+    pub fn get_vertex_normals<'py>(&self, py: Python<'py>) -> Py<PyArray2<f64>> {
+        self.data.vertex_normals().to_numpy(py)
+    }
+
+    /// The (m, 3) float64 unit normal of every face, which
+    /// is zero for degenerate faces without any area.
+    #[getter]
+    pub fn get_face_normals<'py>(&self, py: Python<'py>) -> Py<PyArray2<f64>> {
+        self.data.face_normals().to_numpy(py)
+    }
+
+    /// The (m,) float64 area of every face.
+    #[getter]
+    pub fn get_faces_area<'py>(&self, py: Python<'py>) -> Py<PyArray1<f64>> {
+        self.data.faces_area().to_numpy(py)
+    }
+
+    /// The summed area of every face.
+    #[getter]
+    pub fn get_area(&self) -> f64 {
+        self.data.area()
+    }
+
+    /// The volume enclosed by the mesh, which is only meaningful if it's
+    /// watertight and is negative if the faces are wound inward.
+    #[getter]
+    pub fn get_volume(&self) -> f64 {
+        self.data.volume()
+    }
+
+    /// The (3,) float64 center of mass of the volume enclosed by the mesh,
+    /// or `None` if it doesn't enclose any volume.
+    #[getter]
+    pub fn get_center_mass<'py>(&self, py: Python<'py>) -> Option<Py<PyArray1<f64>>> {
+        self.data.center_mass().map(|c| c.to_numpy(py))
+    }
+
+    /// The (3, 3) float64 moment of inertia tensor about the center of mass
+    /// with a density of one, or `None` if the mesh doesn't enclose any volume.
+    #[getter]
+    pub fn get_moment_inertia<'py>(&self, py: Python<'py>) -> Option<Py<PyArray2<f64>>> {
+        self.data.moment_inertia().map(|m| m.to_numpy(py))
+    }
+
+    /// The (2, 3) float64 lower and upper corners of the axis aligned
+    /// bounding box of the vertices, or `None` if there are no vertices.
+    #[getter]
+    pub fn get_bounds<'py>(&self, py: Python<'py>) -> Option<Py<PyArray2<f64>>> {
+        self.data
+            .bounds()
+            .map(|(lower, upper)| [lower, upper].to_numpy(py))
+    }
+
+    /// The (m * 3, 2) int64 vertex indices of the edges of every face,
+    /// with the edges of face `i` in rows `3 * i` to `3 * i + 2`.
+    #[getter]
+    pub fn get_edges<'py>(&self, py: Python<'py>) -> Py<PyArray2<i64>> {
+        self.data.edges().to_numpy(py)
+    }
+
+    /// The (k, 2) int64 indices of every pair of faces sharing an edge.
+    #[getter]
+    pub fn get_face_adjacency<'py>(&self, py: Python<'py>) -> Py<PyArray2<i64>> {
+        self.data.face_adjacency().to_numpy(py)
+    }
+
+    /// The (k,) float64 angle in radians between the normals of
+    /// every pair of faces in `face_adjacency`.
+    #[getter]
+    pub fn get_face_adjacency_angles<'py>(&self, py: Python<'py>) -> Py<PyArray1<f64>> {
+        self.data.face_adjacency_angles().to_numpy(py)
+    }
+
+    /// The (n,) float64 angle defect of every vertex in radians,
+    /// which is the discrete Gaussian curvature.
+    #[getter]
+    pub fn get_vertex_defects<'py>(&self, py: Python<'py>) -> Py<PyArray1<f64>> {
+        self.data.vertex_defects().to_numpy(py)
+    }
+
+    /// The (n,) int64 number of vertices sharing an edge with each vertex.
+    #[getter]
+    pub fn get_vertex_degree<'py>(&self, py: Python<'py>) -> Py<PyArray1<i64>> {
+        self.data.vertex_degree().to_numpy(py)
+    }
+
+    /// Is every edge shared by exactly two faces.
+    #[getter]
+    pub fn get_is_watertight(&self) -> bool {
+        self.data.is_watertight()
+    }
+
+    /// Is every edge shared by two faces traversed in opposite directions.
+    #[getter]
+    pub fn get_is_winding_consistent(&self) -> bool {
+        self.data.is_winding_consistent()
+    }
+
+    /// Collapse edges until the mesh has about a target number
+    /// of faces, returning a new mesh.
+    #[pyo3(signature = (target_count, aggressiveness=7.0))]
+    pub fn simplify(&self, py: Python<'_>, target_count: usize, aggressiveness: f64) -> Self {
+        let data = py.allow_threads(|| self.data.simplify(target_count, aggressiveness));
+        PyTrimesh { data }
+    }
+
+    /// The convex hull of the vertices, returning a new mesh.
+    pub fn convex_hull(&self, py: Python<'_>) -> Result<Self> {
+        let data = py.allow_threads(|| self.data.convex_hull())?;
+        Ok(PyTrimesh { data })
+    }
+
+    /// Check whether each point of an (n, 3) array is inside the
+    /// mesh, returning an (n,) bool array.
+    pub fn contains<'py>(
+        &self,
+        py: Python<'py>,
+        points: PyReadonlyArray2<'py, f64>,
+    ) -> Result<Py<PyArray1<bool>>> {
+        let points = points_from_array(points)?;
+        let inside = py.allow_threads(|| self.data.contains(&points));
+        Ok(inside.to_numpy(py))
+    }
+
+    /// Find the closest point on the surface to each point of an (n, 3)
+    /// array, returning the (n, 3) closest points, the (n,) distances and
+    /// the (n,) int64 index of the face each closest point is on.
+    #[allow(clippy::type_complexity)]
+    pub fn closest_point<'py>(
+        &self,
+        py: Python<'py>,
+        points: PyReadonlyArray2<'py, f64>,
+    ) -> Result<(Py<PyArray2<f64>>, Py<PyArray1<f64>>, Py<PyArray1<i64>>)> {
+        let points = points_from_array(points)?;
+        let closest = py.allow_threads(|| self.data.closest_point(&points));
+        let closest: Vec<(Point3<f64>, f64, usize)> = closest
+            .into_iter()
+            .collect::<Option<_>>()
+            .ok_or_else(|| anyhow!("Closest point requires a mesh with faces"))?;
+        let locations: Vec<Point3<f64>> = closest.iter().map(|c| c.0).collect();
+        let distances: Vec<f64> = closest.iter().map(|c| c.1).collect();
+        let faces: Vec<usize> = closest.iter().map(|c| c.2).collect();
+        Ok((
+            locations.to_numpy(py),
+            distances.to_numpy(py),
+            faces.to_numpy(py),
+        ))
+    }
+
+    /// Split vertices along edges sharper than an angle in radians,
+    /// returning a new mesh with smooth vertex normals.
+    pub fn smooth_shaded(&self, threshold: f64) -> Self {
+        PyTrimesh {
+            data: self.data.smooth_shaded(threshold),
+        }
+    }
+
+    /// Find the first face hit by a ray, returning the face index, the
+    /// hit location, its barycentric coordinates on the face and the
+    /// distance along the ray.
+    pub fn pick(
+        &self,
+        origin: [f64; 3],
+        direction: [f64; 3],
+    ) -> Option<(usize, [f64; 3], [f64; 3], f64)> {
+        let ray = Ray::new(Point3::from(origin), Vector3::from(direction));
+        self.data.pick(&ray).map(|hit| {
+            (
+                hit.face,
+                hit.point.coords.into(),
+                hit.barycentric.into(),
+                hit.distance,
+            )
+        })
+    }
+
+    /// Check whether each ray in (n, 3) arrays of origins
+    /// and directions hits the mesh, returning an (n,) bool array.
+    pub fn intersects_any<'py>(
+        &self,
+        py: Python<'py>,
+        origins: PyReadonlyArray2<'py, f64>,
+        directions: PyReadonlyArray2<'py, f64>,
+    ) -> Result<Py<PyArray1<bool>>> {
+        let (origins, directions) = rays_from_arrays(origins, directions);
+        let hits = py.allow_threads(|| self.data.intersects_any(&origins, &directions))?;
+        Ok(hits.to_numpy(py))
+    }
+
+    /// Find the first face hit by each ray in (n, 3) arrays of origins and
+    /// directions, returning an (n,) int64 array with -1 for rays that missed.
+    pub fn intersects_id<'py>(
+        &self,
+        py: Python<'py>,
+        origins: PyReadonlyArray2<'py, f64>,
+        directions: PyReadonlyArray2<'py, f64>,
+    ) -> Result<Py<PyArray1<i64>>> {
+        let (origins, directions) = rays_from_arrays(origins, directions);
+        let ids = py.allow_threads(|| self.data.intersects_id(&origins, &directions))?;
+        Ok(PyArray1::from_vec(
+            py,
+            ids.into_iter()
+                .map(|id| id.map_or(-1, |id| id as i64))
                 .collect(),
         )
-        .unwrap();
+        .into())
+    }
+
+    /// Find every hit of each ray in (n, 3) arrays of origins and directions,
+    /// returning the (m, 3) locations, the (m,) index of the ray for each hit
+    /// and the (m,) index of the face for each hit.
+    #[allow(clippy::type_complexity)]
+    pub fn intersects_location<'py>(
+        &self,
+        py: Python<'py>,
+        origins: PyReadonlyArray2<'py, f64>,
+        directions: PyReadonlyArray2<'py, f64>,
+    ) -> Result<(Py<PyArray2<f64>>, Py<PyArray1<i64>>, Py<PyArray1<i64>>)> {
+        let (origins, directions) = rays_from_arrays(origins, directions);
+        let hits = py.allow_threads(|| self.data.intersects_location(&origins, &directions))?;
 
-        PyArray2::from_array(py, &arr).to_owned().into()
+        let locations: Vec<Point3<f64>> = hits.iter().map(|(_, hit)| hit.point).collect();
+        let index_ray: Vec<usize> = hits.iter().map(|(ray, _)| *ray).collect();
+        let index_tri: Vec<usize> = hits.iter().map(|(_, hit)| hit.face).collect();
+        Ok((
+            locations.to_numpy(py),
+            index_ray.to_numpy(py),
+            index_tri.to_numpy(py),
+        ))
     }
 
-    #[getter]
-    pub fn get_uv<'py>(&self, py: Python<'py>) -> Option<Py<PyArray2<f64>>> {
-        self.data.uv().as_ref().map(|uvs| {
-            let shape = (uvs.len(), 2);
-            let arr =
-                Array2::from_shape_vec(shape, uvs.iter().flat_map(|p| vec![p.x, p.y]).collect())
-                    .unwrap();
-            PyArray2::from_array(py, &arr).to_owned().into()
+    /// Compute a quality metric like `edge_length`, `face_area`,
+    /// `dihedral_angle` or `aspect_ratio` for every element.
+    pub fn metric<'py>(&self, py: Python<'py>, name: String) -> Result<Py<PyArray1<f64>>> {
+        Ok(self.data.metric(Metric::from_string(&name)?).to_numpy(py))
+    }
+
+    /// Count a quality metric into equal width bins, returning
+    /// the counts and the bin edges like `numpy.histogram`.
+    #[pyo3(signature = (name, bins=10))]
+    #[allow(clippy::type_complexity)]
+    pub fn histogram<'py>(
+        &self,
+        py: Python<'py>,
+        name: String,
+        bins: usize,
+    ) -> Result<(Py<PyArray1<i64>>, Py<PyArray1<f64>>)> {
+        let values = self.data.metric(Metric::from_string(&name)?);
+        let h = histogram(&values, bins)?;
+        Ok((
+            PyArray1::from_vec(py, h.counts.into_iter().map(|c| c as i64).collect()).into(),
+            PyArray1::from_vec(py, h.edges).into(),
+        ))
+    }
+
+    /// Compute percentiles between 0 and 100 of a quality metric.
+    pub fn percentiles<'py>(
+        &self,
+        py: Python<'py>,
+        name: String,
+        percents: Vec<f64>,
+    ) -> Result<Py<PyArray1<f64>>> {
+        let values = self.data.metric(Metric::from_string(&name)?);
+        Ok(percentiles(&values, &percents)?.to_numpy(py))
+    }
+
+    /// An oriented box containing the mesh with close to the smallest
+    /// volume as its extents and the (4, 4) transform placing its center
+    /// and axes, or `None` for a mesh without vertices.
+    #[allow(clippy::type_complexity)]
+    pub fn bounding_box_oriented<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> Option<([f64; 3], Py<PyArray2<f64>>)> {
+        let primitive = py.allow_threads(|| self.data.bounding_box_oriented())?;
+        match primitive.kind {
+            PrimitiveKind::Box { extents } => Some((extents, primitive.transform.to_numpy(py))),
+            _ => None,
+        }
+    }
+
+    /// The (4, 4) transform moving the center of mass to the origin and
+    /// the principal axes of inertia onto X, Y and Z, or `None` if the
+    /// mesh doesn't enclose any volume.
+    pub fn principal_inertia_transform<'py>(&self, py: Python<'py>) -> Option<Py<PyArray2<f64>>> {
+        let transform = py.allow_threads(|| self.data.principal_inertia_transform())?;
+        Some(transform.to_numpy(py))
+    }
+
+    /// Find the `k` vertices closest to each point of an (n, 3) array,
+    /// returning the (n, k) distances and (n, k) int64 vertex indices from
+    /// closest to farthest, padded with infinity and -1 past the vertex count.
+    #[pyo3(signature = (points, k=1))]
+    #[allow(clippy::type_complexity)]
+    pub fn nearest_vertex<'py>(
+        &self,
+        py: Python<'py>,
+        points: PyReadonlyArray2<'py, f64>,
+        k: usize,
+    ) -> Result<(Py<PyArray2<f64>>, Py<PyArray2<i64>>)> {
+        let points = points_from_array(points)?;
+        let nearest: Vec<Vec<(usize, f64)>> = py.allow_threads(|| {
+            let tree = self.data.vertex_tree();
+            points.iter().map(|p| tree.nearest(p, k)).collect()
+        });
+        let mut distances = Array2::from_elem((points.len(), k), f64::INFINITY);
+        let mut indices = Array2::from_elem((points.len(), k), -1_i64);
+        for (row, found) in nearest.into_iter().enumerate() {
+            for (column, (index, distance)) in found.into_iter().enumerate() {
+                distances[[row, column]] = distance;
+                indices[[row, column]] = index as i64;
+            }
+        }
+        Ok((
+            PyArray2::from_array(py, &distances).into(),
+            PyArray2::from_array(py, &indices).into(),
+        ))
+    }
+
+    /// The int64 indices of every vertex within a radius of a point.
+    pub fn vertices_within<'py>(
+        &self,
+        py: Python<'py>,
+        point: [f64; 3],
+        radius: f64,
+    ) -> Py<PyArray1<i64>> {
+        let found =
+            py.allow_threads(|| self.data.vertex_tree().radius(&Point3::from(point), radius));
+        found.to_numpy(py)
+    }
+
+    /// Weld vertices closer than a tolerance, returning a new mesh.
+    pub fn merge_vertices(&self, tolerance: f64) -> Result<Self> {
+        Ok(PyTrimesh {
+            data: self.data.merge_vertices(tolerance)?,
+        })
+    }
+
+    /// Snap vertices to a grid and weld any that coincide, returning a new mesh.
+    pub fn snap(&self, grid: f64) -> Result<Self> {
+        Ok(PyTrimesh {
+            data: self.data.snap(grid)?,
+        })
+    }
+
+    /// Apply the default cleanup to the mesh, also removing degenerate
+    /// and duplicate faces and fixing normals if validating, returning a new mesh.
+    #[pyo3(signature = (validate=false))]
+    pub fn process(&self, validate: bool) -> Result<Self> {
+        Ok(PyTrimesh {
+            data: self.data.process(validate)?,
+        })
+    }
+
+    /// The volume inside either this closed mesh or another, returning a new mesh.
+    pub fn union(&self, py: Python<'_>, other: &PyTrimesh) -> Result<Self> {
+        let data = py.allow_threads(|| boolean::union(&self.data, &other.data))?;
+        Ok(PyTrimesh { data })
+    }
+
+    /// The volume inside both this closed mesh and another, returning a new mesh.
+    pub fn intersection(&self, py: Python<'_>, other: &PyTrimesh) -> Result<Self> {
+        let data = py.allow_threads(|| boolean::intersection(&self.data, &other.data))?;
+        Ok(PyTrimesh { data })
+    }
+
+    /// The volume inside this closed mesh but not another, returning a new mesh.
+    pub fn difference(&self, py: Python<'_>, other: &PyTrimesh) -> Result<Self> {
+        let data = py.allow_threads(|| boolean::difference(&self.data, &other.data))?;
+        Ok(PyTrimesh { data })
+    }
+
+    /// Apply a (4, 4) homogeneous transform to the mesh, returning a new mesh.
+    pub fn apply_transform(
+        &self,
+        py: Python<'_>,
+        matrix: PyReadonlyArray2<'_, f64>,
+    ) -> Result<Self> {
+        let matrix = matrix_from_array(matrix)?;
+        let data = py.allow_threads(|| self.data.apply_transform(&matrix))?;
+        Ok(PyTrimesh { data })
+    }
+
+    /// Show the mesh in a three.js viewer in the default browser,
+    /// blocking until the page is closed.
+    pub fn show(&self, py: Python<'_>) -> Result<()> {
+        py.allow_threads(|| self.data.show())
+    }
+
+    /// Move the mesh by a translation vector, returning a new mesh.
+    pub fn apply_translation(&self, translation: [f64; 3]) -> Result<Self> {
+        let data = self.data.apply_translation(&Vector3::from(translation))?;
+        Ok(PyTrimesh { data })
+    }
+
+    /// Export the mesh to the text of an ASCII format, optionally with a
+    /// fixed number of digits, with vertices snapped to a grid or with
+    /// textures baked into vertex colors after subdividing to an edge length.
+    /// The text is also written to `path` if one is passed.
+    #[pyo3(signature = (file_type, digits=None, quantize=None, bake_textures=None, path=None))]
+    pub fn export(
+        &self,
+        py: Python<'_>,
+        file_type: String,
+        digits: Option<usize>,
+        quantize: Option<f64>,
+        bake_textures: Option<f64>,
+        path: Option<PathBuf>,
+    ) -> Result<String> {
+        let format = MeshFormat::from_string(&file_type)?;
+        py.allow_threads(|| {
+            let text = export_mesh(
+                &self.data,
+                format,
+                &ExportOptions {
+                    digits,
+                    quantize,
+                    bake_textures,
+                },
+            )?;
+            if let Some(path) = path {
+                std::fs::write(path, &text)?;
+            }
+            Ok(text)
         })
     }
 
+    pub fn __repr__(&self) -> String {
+        let mut repr = format!(
+            "<rmesh.Trimesh(vertices.shape=({}, 3), faces.shape=({}, 3)",
+            self.data.vertices().len(),
+            self.data.faces().len()
+        );
+        if let Some(format) = &self.data.source.format {
+            repr.push_str(&format!(", source={}", format.extension()));
+        }
+        if !self.data.materials.is_empty() {
+            repr.push_str(&format!(", materials={}", self.data.materials.len()));
+        }
+        repr.push_str(")>");
+        repr
+    }
+
     pub fn py_check(&self) -> usize {
         10
     }
@@ -98,12 +595,94 @@ impl PyTrimesh {
 
 /// (pyfunc) Load a mesh from a file, doing no initial processing.
 #[pyfunction(name = "load_mesh")]
-pub fn py_load_mesh(file_data: &[u8], file_type: String) -> Result<PyTrimesh> {
-    let data = load_mesh(file_data, MeshFormat::from_string(&file_type)?)?;
+pub fn py_load_mesh(file_data: &[u8], file_type: String) -> PyResult<PyTrimesh> {
+    let format = MeshFormat::from_string(&file_type).map_err(to_py_err)?;
+    let data = load_mesh(file_data, format).map_err(to_py_err)?;
 
     Ok(PyTrimesh { data })
 }
 
+/// (pyfunc) Load a mesh from a file, detecting the format from the file contents.
+#[pyfunction(name = "load_mesh_auto")]
+pub fn py_load_mesh_auto(file_data: &[u8]) -> PyResult<PyTrimesh> {
+    let data = load_mesh_auto(file_data).map_err(to_py_err)?;
+
+    Ok(PyTrimesh { data })
+}
+
+/// (pyfunc) Detect the format of a file from its contents, returning the file extension.
+#[pyfunction(name = "detect_format")]
+pub fn py_detect_format(file_data: &[u8]) -> PyResult<String> {
+    let format = MeshFormat::detect(file_data).map_err(to_py_err)?;
+    Ok(format.extension().to_string())
+}
+
+/// (pyfunc) Apply a (4, 4) homogeneous transform to an (n, 3) array of points.
+#[pyfunction(name = "transform_points")]
+pub fn py_transform_points<'py>(
+    py: Python<'py>,
+    points: PyReadonlyArray2<'py, f64>,
+    matrix: PyReadonlyArray2<'py, f64>,
+) -> Result<Py<PyArray2<f64>>> {
+    let matrix = matrix_from_array(matrix)?;
+    let points = points_from_array(points)?;
+
+    let transformed = py.allow_threads(|| transform_points(&points, &matrix));
+    Ok(transformed.to_numpy(py))
+}
+
+/// (pyfunc) Align a source mesh onto a target mesh with iterative closest
+/// point, starting from an optional (4, 4) transform and using either the
+/// `point_to_point` or `point_to_plane` method, returning the (4, 4)
+/// transform from the source onto the target and its mean residual.
+#[pyfunction(name = "icp")]
+#[pyo3(signature = (source, target, initial=None, max_iterations=50, tolerance=1e-8, method="point_to_point"))]
+pub fn py_icp<'py>(
+    py: Python<'py>,
+    source: &PyTrimesh,
+    target: &PyTrimesh,
+    initial: Option<PyReadonlyArray2<'py, f64>>,
+    max_iterations: usize,
+    tolerance: f64,
+    method: &str,
+) -> Result<(Py<PyArray2<f64>>, f64)> {
+    let initial = match initial {
+        Some(matrix) => matrix_from_array(matrix)?,
+        None => Matrix4::identity(),
+    };
+    let method = IcpMethod::from_string(method)?;
+    let (transform, residual) = py.allow_threads(|| {
+        icp(
+            &source.data,
+            &target.data,
+            &initial,
+            max_iterations,
+            tolerance,
+            method,
+        )
+    })?;
+    Ok((transform.to_numpy(py), residual))
+}
+
+/// (pyfunc) Find the (4, 4) transform moving an (n, 3) array of points onto
+/// corresponding points with the least squared error, optionally allowing
+/// reflection and uniform scale, returning it and the mean residual.
+#[pyfunction(name = "procrustes")]
+#[pyo3(signature = (points_a, points_b, reflection=true, scale=true))]
+pub fn py_procrustes<'py>(
+    py: Python<'py>,
+    points_a: PyReadonlyArray2<'py, f64>,
+    points_b: PyReadonlyArray2<'py, f64>,
+    reflection: bool,
+    scale: bool,
+) -> Result<(Py<PyArray2<f64>>, f64)> {
+    let points_a = points_from_array(points_a)?;
+    let points_b = points_from_array(points_b)?;
+    let (transform, cost) =
+        py.allow_threads(|| procrustes(&points_a, &points_b, reflection, scale))?;
+    Ok((transform.to_numpy(py), cost))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -118,5 +697,22 @@ mod tests {
         let m = PyTrimesh { data };
 
         assert_eq!(m.py_check(), 10);
+        assert_eq!(
+            m.__repr__(),
+            "<rmesh.Trimesh(vertices.shape=(8, 3), faces.shape=(12, 3))>"
+        );
+
+        let (face, point, barycentric, distance) =
+            m.pick([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]).unwrap();
+        assert!(face < m.data.faces().len());
+        assert_eq!(point, [0.0, 0.0, 0.5]);
+        assert_eq!(distance, 4.5);
+        // the coordinates rebuild the point from the corners of the face
+        let corners = m.data.faces()[face];
+        let vertices = m.data.vertices();
+        let rebuilt = vertices[corners.0].coords * barycentric[0]
+            + vertices[corners.1].coords * barycentric[1]
+            + vertices[corners.2].coords * barycentric[2];
+        assert!((rebuilt - Vector3::from(point)).norm() < 1e-12);
     }
 }
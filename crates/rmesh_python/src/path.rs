@@ -0,0 +1,156 @@
+use anyhow::Result;
+use nalgebra::Point3;
+use pyo3::prelude::*;
+
+use numpy::{PyArray2, PyReadonlyArray2};
+
+use rmesh::path::{Curve, Path, rectangle};
+
+use crate::convert::ToNumPy;
+use crate::mesh::PyTrimesh;
+
+#[pyclass(name = "Path")]
+pub struct PyPath {
+    data: Path,
+}
+
+#[pymethods]
+impl PyPath {
+    #[new]
+    /// (pyfunc) Create a new Path from (n, 3) vertices and a list of entities
+    /// as `(kind, indices)` where kind is `line`, `bezier`, `circle`, `arc`
+    /// or `arc_cw` and indices reference the vertices.
+    pub fn new<'py>(
+        vertices: PyReadonlyArray2<'py, f64>,
+        entities: Vec<(String, Vec<usize>)>,
+    ) -> Result<Self> {
+        let vertices: Vec<Point3<f64>> = vertices
+            .as_array()
+            .rows()
+            .into_iter()
+            .map(|x| Point3::new(x[0], x[1], x[2]))
+            .collect();
+        let entities = entities
+            .into_iter()
+            .map(|(kind, indices)| Curve::from_kind(&kind, indices))
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(index) = entities
+            .iter()
+            .flat_map(|e| e.indices())
+            .find(|i| *i >= vertices.len())
+        {
+            return Err(anyhow::anyhow!(
+                "Entity references vertex {index} but there are only {} vertices",
+                vertices.len()
+            ));
+        }
+
+        Ok(PyPath {
+            data: Path::new(vertices, entities),
+        })
+    }
+
+    /// The (n, 3) float64 positions of the vertices.
+    #[getter]
+    pub fn get_vertices<'py>(&self, py: Python<'py>) -> Py<PyArray2<f64>> {
+        self.data.vertices.to_numpy(py)
+    }
+
+    /// Every entity as its kind and the indices of its vertices.
+    #[getter]
+    pub fn get_entities(&self) -> Vec<(String, Vec<usize>)> {
+        self.data
+            .entities
+            .iter()
+            .map(|e| (e.kind().to_string(), e.indices()))
+            .collect()
+    }
+
+    /// The total length of every entity.
+    #[getter]
+    pub fn get_length(&self) -> f64 {
+        self.data.length()
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "<rmesh.Path(vertices.shape=({}, 3), entities={})>",
+            self.data.vertices.len(),
+            self.data.entities.len()
+        )
+    }
+
+    /// Discretize every entity into an (n, 3) array of points.
+    pub fn discrete<'py>(&self, py: Python<'py>, resolution: usize) -> Vec<Py<PyArray2<f64>>> {
+        self.data
+            .discrete(resolution)
+            .iter()
+            .map(|points| points.to_numpy(py))
+            .collect()
+    }
+
+    /// Extrude the closed entities of the path along Z into a mesh.
+    #[pyo3(signature = (height, resolution=32))]
+    pub fn extrude(&self, height: f64, resolution: usize) -> Result<PyTrimesh> {
+        Ok(PyTrimesh {
+            data: self.data.extrude(height, resolution)?,
+        })
+    }
+
+    /// Revolve the path around the Z axis using X as the radius and Y as the height.
+    #[pyo3(signature = (angle, sections=32, resolution=32))]
+    pub fn revolve(&self, angle: f64, sections: usize, resolution: usize) -> Result<PyTrimesh> {
+        Ok(PyTrimesh {
+            data: self.data.revolve(angle, sections, resolution)?,
+        })
+    }
+}
+
+/// (pyfunc) Create a rectangular path centered at the origin.
+#[pyfunction(name = "rectangle")]
+pub fn py_rectangle(width: f64, height: f64) -> PyPath {
+    PyPath {
+        data: rectangle(width, height),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_path_python() {
+        let path = py_rectangle(2.0, 4.0);
+        assert_eq!(
+            path.get_entities(),
+            vec![("line".to_string(), vec![0, 1, 2, 3, 0])]
+        );
+        assert_eq!(path.get_length(), 12.0);
+        assert_eq!(
+            path.__repr__(),
+            "<rmesh.Path(vertices.shape=(4, 3), entities=1)>"
+        );
+
+        let mesh = path.extrude(3.0, 32).unwrap();
+        assert_eq!(mesh.data.faces().len(), 12);
+        assert!(path.revolve(std::f64::consts::TAU, 16, 32).is_ok());
+
+        // a cubic bezier approximating a quarter circle
+        let curve = PyPath {
+            data: Path::new(
+                vec![
+                    Point3::new(1.0, 0.0, 0.0),
+                    Point3::new(1.0, 0.5523, 0.0),
+                    Point3::new(0.5523, 1.0, 0.0),
+                    Point3::new(0.0, 1.0, 0.0),
+                ],
+                vec![Curve::Bezier {
+                    points: vec![0, 1, 2, 3],
+                }],
+            ),
+        };
+        assert!((curve.get_length() - std::f64::consts::FRAC_PI_2).abs() < 1e-3);
+    }
+}
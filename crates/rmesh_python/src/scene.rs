@@ -0,0 +1,567 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use nalgebra::Matrix4;
+use numpy::{PyArray2, PyReadonlyArray2};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use rmesh::exchange::{ExportOptions, MeshFormat, export_mesh, load_mesh, load_scene};
+use rmesh::geometry::Geometry;
+use rmesh::mesh::Trimesh;
+use rmesh::scene::{Scene, SceneNode, SceneNodeKind};
+
+use crate::convert::ToNumPy;
+use crate::error::anyhow_to_py_err;
+use crate::mesh::{PyTrimesh, matrix_from_array};
+
+#[pyclass(name = "Scene")]
+#[derive(Default)]
+pub struct PyScene {
+    pub(crate) data: Scene,
+}
+
+impl PyScene {
+    /// The index of the node with a name.
+    fn node_index(&self, name: &str) -> Result<usize> {
+        self.data
+            .graph
+            .node_by_name(name)
+            .ok_or_else(|| anyhow!("Scene has no node named `{name}`"))
+    }
+
+    /// Add a mesh as new geometry under a new node, creating a
+    /// root named `world` if the scene doesn't have any nodes.
+    fn add_mesh(
+        &mut self,
+        mesh: Trimesh,
+        node_name: Option<String>,
+        transform: Option<Matrix4<f64>>,
+        parent_node_name: Option<String>,
+    ) -> Result<String> {
+        let parent = match parent_node_name {
+            Some(parent) => self.node_index(&parent)?,
+            None if self.data.graph.nodes().is_empty() => {
+                let root = self.data.graph.add_node(SceneNode {
+                    name: "world".to_string(),
+                    kind: SceneNodeKind::CUSTOM,
+                    ..Default::default()
+                });
+                self.data.graph.set_root(root);
+                root
+            }
+            None => self.data.graph.root(),
+        };
+        let name = node_name.unwrap_or_else(|| format!("geometry_{}", self.data.geometry.len()));
+        if self.data.graph.node_by_name(&name).is_some() {
+            return Err(anyhow!("Scene already has a node named `{name}`"));
+        }
+        let index = self.data.add_geometry(Geometry::Mesh(Box::new(mesh)));
+        let node = self.data.graph.add_node(SceneNode {
+            name: name.clone(),
+            transform,
+            kind: SceneNodeKind::GEOMETRY,
+            index: vec![index],
+            ..Default::default()
+        });
+        self.data.graph.add_edge(parent, node)?;
+        Ok(name)
+    }
+
+    /// A copy of a node with the names of its children.
+    fn node_snapshot(&self, node: usize) -> PySceneNode {
+        let nodes = &self.data.graph.nodes();
+        let data = &nodes[node];
+        PySceneNode {
+            name: data.name.clone(),
+            kind: match data.kind {
+                SceneNodeKind::GEOMETRY => "geometry",
+                SceneNodeKind::CAMERA => "camera",
+                SceneNodeKind::LIGHT => "light",
+                SceneNodeKind::CUSTOM => "custom",
+                SceneNodeKind::FRAME => "frame",
+            }
+            .to_string(),
+            children: data
+                .children
+                .iter()
+                .filter_map(|c| nodes.get(*c).map(|c| c.name.clone()))
+                .collect(),
+            geometry: data.index.clone(),
+            transform: data.transform.unwrap_or_else(Matrix4::identity),
+        }
+    }
+}
+
+#[pymethods]
+impl PyScene {
+    #[new]
+    /// (pyfunc) Create an empty scene.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a mesh to the scene under a new node, returning the name of
+    /// the node. The node is placed under the root or a named parent
+    /// with an optional (4, 4) transform from the parent, and a root
+    /// named `world` is created for the first geometry of an empty scene.
+    #[pyo3(signature = (mesh, node_name=None, transform=None, parent_node_name=None))]
+    pub fn add_geometry(
+        &mut self,
+        mesh: &PyTrimesh,
+        node_name: Option<String>,
+        transform: Option<PyReadonlyArray2<'_, f64>>,
+        parent_node_name: Option<String>,
+    ) -> Result<String> {
+        let transform = transform.map(matrix_from_array).transpose()?;
+        self.add_mesh(mesh.data.clone(), node_name, transform, parent_node_name)
+    }
+
+    /// The scene graph, which edits the nodes of this scene.
+    #[getter]
+    pub fn get_graph(slf: Py<Self>) -> PySceneGraph {
+        PySceneGraph { scene: slf }
+    }
+
+    /// Every placement of a mesh combined into a single mesh in the
+    /// frame of the root, which is what trimesh calls `dump(concatenate=True)`.
+    pub fn flatten(&self, py: Python<'_>) -> Result<PyTrimesh> {
+        let data = py.allow_threads(|| self.data.flatten_mesh())?;
+        Ok(PyTrimesh { data })
+    }
+
+    /// Export every placement of a mesh combined into a single mesh to a
+    /// file in an ASCII format, which is the extension of the path unless
+    /// `file_type` is passed, optionally with a fixed number of digits.
+    #[pyo3(signature = (path, file_type=None, digits=None))]
+    pub fn export(
+        &self,
+        py: Python<'_>,
+        path: PathBuf,
+        file_type: Option<String>,
+        digits: Option<usize>,
+    ) -> Result<()> {
+        let format = format_from(file_type.as_deref(), Some(&path))?
+            .ok_or_else(|| anyhow!("Can't tell the format to export from `{}`", path.display()))?;
+        py.allow_threads(|| {
+            let text = export_mesh(
+                &self.data.flatten_mesh()?,
+                format,
+                &ExportOptions {
+                    digits,
+                    ..Default::default()
+                },
+            )?;
+            Ok(std::fs::write(&path, text)?)
+        })
+    }
+
+    /// Every placement of a mesh as the (4, 4) transform from
+    /// the root and a copy of the mesh in its own frame.
+    pub fn dump(&self, py: Python<'_>) -> Vec<(Py<PyArray2<f64>>, PyTrimesh)> {
+        self.data
+            .dump()
+            .into_iter()
+            .map(|(transform, mesh)| (transform.to_numpy(py), PyTrimesh { data: mesh.clone() }))
+            .collect()
+    }
+
+    /// The meshes in the scene in the frame they were defined in,
+    /// or `None` for geometry which isn't a mesh.
+    #[getter]
+    pub fn get_geometry(&self) -> Vec<Option<PyTrimesh>> {
+        self.data
+            .geometry
+            .iter()
+            .map(|g| match g {
+                Geometry::Mesh(mesh) => Some(PyTrimesh {
+                    data: mesh.as_ref().clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The name of every node in the scene graph.
+    #[getter]
+    pub fn get_node_names(&self) -> Vec<String> {
+        self.data
+            .graph
+            .nodes()
+            .iter()
+            .map(|n| n.name.clone())
+            .collect()
+    }
+
+    /// Every placement of geometry in the scene as the name of the node,
+    /// the index of the geometry and the (4, 4) transform from the root.
+    pub fn instances<'py>(&self, py: Python<'py>) -> Vec<(String, usize, Py<PyArray2<f64>>)> {
+        self.data
+            .flatten()
+            .into_iter()
+            .map(|(geometry, instance)| {
+                (
+                    self.data.graph.nodes()[instance.node].name.clone(),
+                    geometry,
+                    instance.transform.to_numpy(py),
+                )
+            })
+            .collect()
+    }
+
+    /// Show every mesh in the scene in a three.js viewer in the
+    /// default browser, blocking until the page is closed.
+    pub fn show(&self, py: Python<'_>) -> Result<()> {
+        py.allow_threads(|| self.data.show())
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "<rmesh.Scene(geometry={}, nodes={}, instances={})>",
+            self.data.geometry.len(),
+            self.data.graph.nodes().len(),
+            self.data.flatten().len()
+        )
+    }
+}
+
+/// A node of a scene graph, copied out of the scene.
+#[pyclass(name = "SceneNode", frozen)]
+pub struct PySceneNode {
+    name: String,
+    kind: String,
+    children: Vec<String>,
+    geometry: Vec<usize>,
+    transform: Matrix4<f64>,
+}
+
+#[pymethods]
+impl PySceneNode {
+    /// The name of the node.
+    #[getter]
+    pub fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// What the node is: `geometry`, `camera`, `light`, `custom` or `frame`.
+    #[getter]
+    pub fn get_kind(&self) -> String {
+        self.kind.clone()
+    }
+
+    /// The names of the child nodes.
+    #[getter]
+    pub fn get_children(&self) -> Vec<String> {
+        self.children.clone()
+    }
+
+    /// The indices into `Scene.geometry` placed by the node.
+    #[getter]
+    pub fn get_geometry(&self) -> Vec<usize> {
+        self.geometry.clone()
+    }
+
+    /// The (4, 4) transform from the parent to this node.
+    #[getter]
+    pub fn get_transform(&self, py: Python<'_>) -> Py<PyArray2<f64>> {
+        self.transform.to_numpy(py)
+    }
+
+    pub fn __repr__(&self) -> String {
+        format!(
+            "<rmesh.SceneNode(name={:?}, kind={}, children={})>",
+            self.name,
+            self.kind,
+            self.children.len()
+        )
+    }
+}
+
+/// The nodes of a scene and the transforms between them, which
+/// reads and edits the scene it came from rather than a copy.
+#[pyclass(name = "SceneGraph")]
+pub struct PySceneGraph {
+    scene: Py<PyScene>,
+}
+
+#[pymethods]
+impl PySceneGraph {
+    /// The name of every node.
+    #[getter]
+    pub fn get_nodes(&self, py: Python<'_>) -> Vec<String> {
+        self.scene.borrow(py).get_node_names()
+    }
+
+    /// The name of the root node, or `None` for an empty scene.
+    #[getter]
+    pub fn get_root(&self, py: Python<'_>) -> Option<String> {
+        let scene = self.scene.borrow(py);
+        let graph = &scene.data.graph;
+        graph.nodes().get(graph.root()).map(|n| n.name.clone())
+    }
+
+    /// A copy of the node with a name.
+    pub fn node(&self, py: Python<'_>, name: String) -> Result<PySceneNode> {
+        let scene = self.scene.borrow(py);
+        Ok(scene.node_snapshot(scene.node_index(&name)?))
+    }
+
+    /// The (4, 4) transform from the root to the first placement of a
+    /// node and the first geometry index it places, like `graph[name]`
+    /// in trimesh.
+    pub fn get(&self, py: Python<'_>, name: String) -> Result<(Py<PyArray2<f64>>, Option<usize>)> {
+        let scene = self.scene.borrow(py);
+        let node = scene.node_index(&name)?;
+        let transform = scene
+            .data
+            .graph
+            .world_transform(node)
+            .ok_or_else(|| anyhow!("Node `{name}` isn't reachable from the root"))?;
+        Ok((
+            transform.to_numpy(py),
+            scene.data.graph.nodes()[node].index.first().copied(),
+        ))
+    }
+
+    pub fn __getitem__(
+        &self,
+        py: Python<'_>,
+        name: String,
+    ) -> Result<(Py<PyArray2<f64>>, Option<usize>)> {
+        self.get(py, name)
+    }
+
+    /// Replace the (4, 4) transform from the parent of a node to the node.
+    pub fn set_transform(
+        &self,
+        py: Python<'_>,
+        name: String,
+        matrix: PyReadonlyArray2<'_, f64>,
+    ) -> Result<()> {
+        let matrix = matrix_from_array(matrix)?;
+        let mut scene = self.scene.borrow_mut(py);
+        let node = scene.node_index(&name)?;
+        scene.data.graph.set_transform(node, Some(matrix));
+        Ok(())
+    }
+
+    pub fn __repr__(&self, py: Python<'_>) -> String {
+        format!(
+            "<rmesh.SceneGraph(nodes={})>",
+            self.scene.borrow(py).data.graph.nodes().len()
+        )
+    }
+}
+
+/// (pyfunc) Load a file into a scene, preserving multiple objects and their transforms.
+#[pyfunction(name = "load_scene")]
+pub fn py_load_scene(file_data: &[u8], file_type: String) -> Result<PyScene> {
+    Ok(PyScene {
+        data: load_scene(file_data, MeshFormat::from_string(&file_type)?)?,
+    })
+}
+
+/// The contents of a file, or the path to read them from.
+#[derive(FromPyObject)]
+pub enum FileData<'py> {
+    Bytes(Bound<'py, PyBytes>),
+    Path(PathBuf),
+}
+
+/// What `load` returns, which is a mesh for files containing one.
+/// It's converted to Python as soon as it's returned so its size
+/// doesn't matter.
+#[derive(IntoPyObject)]
+#[allow(clippy::large_enum_variant)]
+pub enum Loaded {
+    Mesh(PyTrimesh),
+    Scene(PyScene),
+}
+
+/// The format named by a file type, or by the extension of a path if it's
+/// a known one, or `None` to detect the format from the contents.
+fn format_from(file_type: Option<&str>, path: Option<&Path>) -> Result<Option<MeshFormat>> {
+    if let Some(file_type) = file_type {
+        return Ok(Some(MeshFormat::from_string(file_type)?));
+    }
+    Ok(path
+        .and_then(|p| p.extension())
+        .and_then(|e| MeshFormat::from_string(&e.to_string_lossy()).ok()))
+}
+
+/// Load a file as a mesh if it contains a single mesh placed once,
+/// and as a scene otherwise, like `trimesh.load`.
+fn load_any(file_data: &[u8], format: Option<MeshFormat>) -> Result<Loaded> {
+    let format = match format {
+        Some(format) => format,
+        None => MeshFormat::detect(file_data)?,
+    };
+    let data = match format {
+        // formats which only ever contain a single mesh
+        MeshFormat::PLY | MeshFormat::OFF | MeshFormat::XYZ => {
+            return Ok(Loaded::Mesh(PyTrimesh {
+                data: load_mesh(file_data, format)?,
+            }));
+        }
+        _ => load_scene(file_data, format)?,
+    };
+    if let [(transform, mesh)] = data.dump().as_slice()
+        && data.geometry.len() == 1
+    {
+        let data = if transform.is_identity(0.0) {
+            (*mesh).clone()
+        } else {
+            mesh.apply_transform(transform)?
+        };
+        return Ok(Loaded::Mesh(PyTrimesh { data }));
+    }
+    Ok(Loaded::Scene(PyScene { data }))
+}
+
+/// (pyfunc) Load a file from its bytes or a path, returning a Trimesh if it
+/// contains a single mesh and a Scene otherwise. The format is `file_type`,
+/// or the extension of a path, or detected from the contents.
+#[pyfunction(name = "load")]
+#[pyo3(signature = (file, file_type=None))]
+pub fn py_load(py: Python<'_>, file: FileData<'_>, file_type: Option<String>) -> PyResult<Loaded> {
+    match file {
+        FileData::Bytes(bytes) => {
+            let format = format_from(file_type.as_deref(), None).map_err(anyhow_to_py_err)?;
+            let file_data = bytes.as_bytes();
+            py.allow_threads(|| load_any(file_data, format))
+        }
+        FileData::Path(path) => py.allow_threads(|| {
+            let format = format_from(file_type.as_deref(), Some(&path))?;
+            let file_data = std::fs::read(&path).map_err(rmesh::error::RmeshError::from)?;
+            load_any(&file_data, format)
+        }),
+    }
+    .map_err(anyhow_to_py_err)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_scene_python() {
+        let data = include_bytes!("../../../test/data/unit_cube.STL");
+        let scene = py_load_scene(data, "stl".to_string()).unwrap();
+        assert_eq!(
+            scene.__repr__(),
+            "<rmesh.Scene(geometry=1, nodes=1, instances=1)>"
+        );
+        assert_eq!(scene.get_node_names(), vec!["world".to_string()]);
+        let geometry = scene.get_geometry();
+        assert_eq!(geometry[0].as_ref().unwrap().data.faces().len(), 12);
+
+        // the geometry keeps the face colors and source of the file
+        let data = include_bytes!("../../../test/data/multi_object.3mf");
+        let scene = py_load_scene(data, "3mf".to_string()).unwrap();
+        for (copy, geometry) in scene.get_geometry().iter().zip(scene.data.geometry.iter()) {
+            let Geometry::Mesh(mesh) = geometry else {
+                panic!("a 3MF only has meshes");
+            };
+            let copy = &copy.as_ref().unwrap().data;
+            assert!(!copy.attributes_face.colors.is_empty());
+            assert_eq!(copy.attributes_face, mesh.attributes_face);
+            assert_eq!(copy.source.format, Some(MeshFormat::THREEMF));
+        }
+    }
+
+    #[test]
+    fn test_load_any() {
+        let stl = include_bytes!("../../../test/data/unit_cube.STL");
+        let Loaded::Mesh(mesh) = load_any(stl, None).unwrap() else {
+            panic!("a single STL should load as a mesh");
+        };
+        assert_eq!(mesh.data.faces().len(), 12);
+
+        let multi = include_bytes!("../../../test/data/two_objects_mixed_case_names.stl");
+        let Loaded::Scene(scene) = load_any(multi, Some(MeshFormat::STL)).unwrap() else {
+            panic!("two solids should load as a scene");
+        };
+        assert_eq!(scene.data.geometry.len(), 2);
+
+        assert!(load_any(b"glTF", None).is_err());
+        assert_eq!(
+            format_from(None, Some(Path::new("a/b.OBJ"))).unwrap(),
+            Some(MeshFormat::OBJ)
+        );
+        assert_eq!(format_from(None, Some(Path::new("a/b.bin"))).unwrap(), None);
+        assert_eq!(
+            format_from(Some("ply"), Some(Path::new("a.obj"))).unwrap(),
+            Some(MeshFormat::PLY)
+        );
+        assert!(format_from(Some("glb"), None).is_err());
+    }
+
+    #[test]
+    fn test_scene_add_geometry() {
+        let mut scene = PyScene::new();
+        let mesh = rmesh::creation::create_box(&[1.0, 1.0, 1.0]);
+        let shift = Matrix4::new_translation(&nalgebra::Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(
+            scene.add_mesh(mesh.clone(), None, None, None).unwrap(),
+            "geometry_0"
+        );
+        scene
+            .add_mesh(mesh.clone(), Some("moved".into()), Some(shift), None)
+            .unwrap();
+        scene
+            .add_mesh(
+                mesh.clone(),
+                Some("child".into()),
+                Some(shift),
+                Some("moved".into()),
+            )
+            .unwrap();
+        // names are unique and parents have to exist
+        assert!(
+            scene
+                .add_mesh(mesh.clone(), Some("moved".into()), None, None)
+                .is_err()
+        );
+        assert!(
+            scene
+                .add_mesh(mesh, None, None, Some("missing".into()))
+                .is_err()
+        );
+
+        assert_eq!(
+            scene.get_node_names(),
+            vec!["world", "geometry_0", "moved", "child"]
+        );
+        let root = scene.node_snapshot(scene.node_index("world").unwrap());
+        assert_eq!(root.kind, "custom");
+        assert_eq!(root.children, vec!["geometry_0", "moved"]);
+        let child = scene.node_snapshot(scene.node_index("child").unwrap());
+        assert_eq!((child.geometry.clone(), child.transform), (vec![2], shift));
+
+        let placed = scene.data.flatten_mesh().unwrap();
+        assert_eq!(placed.faces().len(), 36);
+        assert_eq!(placed.bounds().unwrap().1.x, 4.5);
+
+        // the attributes of a mesh survive a round trip through the scene
+        let mut colored = rmesh::creation::create_box(&[1.0, 1.0, 1.0]);
+        colored
+            .attributes_face
+            .colors
+            .push(vec![nalgebra::Vector4::new(255, 0, 0, 255); 12]);
+        let mut scene = PyScene::new();
+        scene
+            .add_geometry(
+                &PyTrimesh {
+                    data: colored.clone(),
+                },
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        let dumped = scene.data.dump();
+        assert_eq!(dumped[0].1.attributes_face, colored.attributes_face);
+        assert!(dumped[0].1.primitive.is_some());
+    }
+}
@@ -1,6 +1,9 @@
 import os
+import subprocess
+import sys
 
 import numpy as np
+import pytest
 import rmesh
 
 # current directory this test is in
@@ -26,6 +29,428 @@ def test_load_stl():
     # make sure this unit cube produces the correct shape of triangle soup
     assert m.vertices[m.faces].shape == (12, 3, 3)
 
+    # welding the soup should leave only the corners of the cube
+    p = m.process()
+    assert p.vertices.shape == (8, 3)
+    assert np.allclose(p.vertices[p.faces], m.vertices[m.faces])
+
+    # export to ASCII and make sure it loads back the same
+    for file_type in ["stl", "obj"]:
+        text = p.export(file_type)
+        r = rmesh.load_mesh(text.encode(), file_type)
+        assert np.allclose(r.vertices[r.faces], m.vertices[m.faces])
+    assert len(p.export("obj", digits=2)) <= len(p.export("obj"))
+
+
+def test_load_auto():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        data = f.read()
+
+    assert rmesh.detect_format(data) == "stl"
+    m = rmesh.load_mesh_auto(data)
+    assert m.vertices[m.faces].shape == (12, 3, 3)
+
+
+def test_errors():
+    # every error is a subclass of the base so callers can catch them all
+    with pytest.raises(rmesh.UnsupportedFormatError):
+        rmesh.load_mesh(b"", "step")
+    with pytest.raises(rmesh.UnsupportedFormatError):
+        rmesh.detect_format(b"glTF")
+    with pytest.raises(rmesh.ParseError):
+        rmesh.load_mesh(b"OFF\n3 1 0\n0 0 0\n", "off")
+    with pytest.raises(rmesh.InvalidGeometryError):
+        rmesh.Trimesh(np.zeros((1, 3)), np.array([[0, 1, 2]]))
+    with pytest.raises(rmesh.RmeshError):
+        rmesh.load_mesh(b"OFF\n3 1 0\n0 0 0\n", "off")
+    assert issubclass(rmesh.ParseError, rmesh.RmeshError)
+
+
+def test_pick():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        m = rmesh.load_mesh(f.read(), "stl")
+
+    # shoot a ray down through the center of the cube
+    center = m.vertices.mean(axis=0)
+    origin = [center[0], center[1], 10.0]
+    face, point, barycentric, distance = m.pick(origin, [0.0, 0.0, -1.0])
+
+    assert 0 <= face < len(m.faces)
+    assert np.isclose(point[2], m.vertices[:, 2].max())
+    assert np.isclose(distance, 10.0 - point[2])
+    # the barycentric coordinates rebuild the point from the face corners
+    corners = m.vertices[m.faces[face]]
+    assert np.isclose(sum(barycentric), 1.0)
+    assert np.allclose(np.dot(barycentric, corners), point)
+
+    # a ray pointing away should miss
+    assert m.pick(origin, [0.0, 0.0, 1.0]) is None
+
+
+def test_intersects():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        m = rmesh.load_mesh(f.read(), "stl").process()
+
+    # a grid of rays pointing down, half of which are off the cube
+    center = m.vertices.mean(axis=0)
+    x, y = np.meshgrid(np.linspace(-1, 1, 10), np.linspace(-1, 1, 10))
+    origins = np.column_stack((x.ravel(), y.ravel(), np.full(x.size, 10.0)))
+    origins[:, :2] += center[:2]
+    directions = np.tile([0.0, 0.0, -1.0], (len(origins), 1))
+
+    hit = m.intersects_any(origins, directions)
+    assert hit.dtype == bool
+    assert hit.shape == (100,)
+
+    ids = m.intersects_id(origins, directions)
+    assert ids.shape == (100,)
+    assert ((ids >= 0) == hit).all()
+    # the first hit should be on the top of the cube
+    assert (m.vertices[m.faces[ids[hit]]][:, :, 2] == m.vertices[:, 2].max()).all()
+
+    locations, index_ray, index_tri = m.intersects_location(origins, directions)
+    assert locations.shape == (len(index_ray), 3)
+    assert len(index_ray) == len(index_tri)
+    # every ray that hit goes through both the top and the bottom
+    assert set(index_ray) == set(np.nonzero(hit)[0])
+    assert len(index_ray) >= 2 * hit.sum()
+
+
+def test_stats():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        m = rmesh.load_mesh(f.read(), "stl").process()
+
+    lengths = m.metric("edge_length")
+    assert lengths.shape == (18,)
+
+    counts, edges = m.histogram("edge_length", bins=2)
+    assert counts.tolist() == [12, 6]
+    assert np.allclose(edges, [1.0, (1.0 + np.sqrt(2)) / 2, np.sqrt(2)])
+
+    # every face of the cube is a right triangle with the same shape
+    assert np.allclose(m.percentiles("aspect_ratio", [0, 50, 100]), np.sqrt(3))
+    assert np.allclose(m.metric("face_area").sum(), 6.0)
+
+
+def test_boolean():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        a = rmesh.load_mesh(f.read(), "stl").process()
+
+    # move a copy so it overlaps the first cube by a quarter
+    b = rmesh.Trimesh(vertices=a.vertices + [0.5, 0.5, 0.0], faces=a.faces)
+
+    def volume(m):
+        t = m.vertices[m.faces]
+        return np.einsum("ij,ij->i", t[:, 0], np.cross(t[:, 1], t[:, 2])).sum() / 6.0
+
+    assert np.isclose(volume(a.union(b)), 1.75)
+    assert np.isclose(volume(a.intersection(b)), 0.25)
+    assert np.isclose(volume(a.difference(b)), 0.75)
+
+
+def test_transform():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        m = rmesh.load_mesh(f.read(), "stl").process()
+
+    # rotate 90 degrees around Z and translate
+    matrix = np.array(
+        [
+            [0.0, -1.0, 0.0, 1.0],
+            [1.0, 0.0, 0.0, 2.0],
+            [0.0, 0.0, 1.0, 3.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]
+    )
+    points = np.random.random((100, 3))
+    expected = points @ matrix[:3, :3].T + matrix[:3, 3]
+    assert np.allclose(rmesh.transform_points(points, matrix), expected)
+
+    moved = m.apply_transform(matrix)
+    assert moved.faces.shape == m.faces.shape
+    assert np.allclose(moved.vertices, rmesh.transform_points(m.vertices, matrix))
+
+    with pytest.raises(BaseException):
+        m.apply_transform(np.eye(3))
+
+
+def test_bounding():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        m = rmesh.load_mesh(f.read(), "stl").process()
+
+    # a tilted cube still has a unit oriented box
+    angle = np.radians(30)
+    matrix = np.eye(4)
+    matrix[:2, :2] = [[np.cos(angle), -np.sin(angle)], [np.sin(angle), np.cos(angle)]]
+    tilted = m.apply_transform(matrix)
+    extents, transform = tilted.bounding_box_oriented()
+    assert np.allclose(extents, 1.0)
+    assert transform.shape == (4, 4)
+
+    # and aligning it moves its center of mass to the origin
+    aligned = tilted.apply_transform(tilted.principal_inertia_transform())
+    assert np.allclose(aligned.vertices.mean(axis=0), 0.0)
+
+
+def test_nearest_vertex():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        m = rmesh.load_mesh(f.read(), "stl").process()
+
+    # each corner is its own nearest vertex
+    distances, indices = m.nearest_vertex(m.vertices)
+    assert distances.shape == (8, 1)
+    assert np.allclose(distances, 0.0)
+    assert (indices[:, 0] == np.arange(8)).all()
+
+    # asking for more neighbors than vertices pads the result
+    distances, indices = m.nearest_vertex(m.vertices[:1], k=10)
+    assert np.isinf(distances[0, 8:]).all()
+    assert (indices[0, 8:] == -1).all()
+
+    # every corner is within the diagonal of the center
+    center = m.vertices.mean(axis=0)
+    assert len(m.vertices_within(center, np.sqrt(3) / 2 + 1e-9)) == 8
+
+
+def test_analysis():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        m = rmesh.load_mesh(f.read(), "stl").process()
+
+    assert m.is_watertight
+    assert m.is_winding_consistent
+    assert np.isclose(m.area, 6.0)
+    assert np.isclose(m.volume, 1.0)
+    assert m.faces_area.shape == (12,)
+    assert np.isclose(m.faces_area.sum(), m.area)
+    assert m.face_normals.shape == (12, 3)
+    assert np.allclose(np.linalg.norm(m.face_normals, axis=1), 1.0)
+    assert m.bounds.shape == (2, 3)
+    assert np.allclose(m.bounds[1] - m.bounds[0], 1.0)
+    assert np.allclose(m.center_mass, m.bounds.mean(axis=0))
+    assert m.moment_inertia.shape == (3, 3)
+
+    # indices are int64 so they can index other arrays
+    assert m.edges.shape == (36, 2)
+    assert m.edges.dtype == np.int64
+    assert m.face_adjacency.shape == (18, 2)
+    assert np.allclose(m.vertex_defects, np.pi / 2)
+
+    # points are accepted as numpy arrays
+    inside = m.contains(np.array([m.center_mass, m.center_mass + 5.0]))
+    assert inside.tolist() == [True, False]
+    closest, distance, face = m.closest_point(np.array([m.center_mass]))
+    assert np.isclose(distance[0], 0.5)
+    assert face.dtype == np.int64
+
+    assert len(m.convex_hull().faces) == 12
+    assert len(m.simplify(10).faces) <= 12
+
+
+def test_views():
+    with open(os.path.join(_models, "fuze.obj"), "rb") as f:
+        m = rmesh.load_mesh(f.read(), "obj")
+
+    view = m.vertices_view
+    assert np.array_equal(view, m.vertices)
+    assert view.dtype == np.float64
+    # the view aliases the mesh so it can't be written to
+    assert not view.flags.writeable
+    assert not view.flags.owndata
+    with pytest.raises(ValueError):
+        view[0, 0] = 1.0
+    assert np.array_equal(m.uv_view, m.uv)
+
+    # the view keeps the mesh alive after every other reference is gone
+    expected = m.vertices
+    del m
+    assert np.array_equal(view, expected)
+
+
+def test_icp():
+    with open(os.path.join(_models, "fuze.obj"), "rb") as f:
+        target = rmesh.load_mesh(f.read(), "obj")
+
+    # a small rotation and translation is undone
+    angle = np.radians(3)
+    matrix = np.eye(4)
+    matrix[:2, :2] = [[np.cos(angle), -np.sin(angle)], [np.sin(angle), np.cos(angle)]]
+    matrix[:3, 3] = [0.1, -0.05, 0.0]
+    source = target.apply_transform(matrix)
+    transform, residual = rmesh.icp(source, target, max_iterations=100)
+    assert transform.shape == (4, 4)
+    assert np.allclose(transform, np.linalg.inv(matrix), atol=1e-4)
+    assert residual < 1e-4
+
+    with pytest.raises(BaseException):
+        rmesh.icp(source, target, method="nope")
+
+
+def test_procrustes():
+    points = np.random.default_rng(7).random((50, 3))
+    matrix = np.eye(4)
+    matrix[:3, :3] = np.diag([-2.0, 2.0, 2.0])
+    matrix[:3, 3] = [1.0, 2.0, 3.0]
+    moved = rmesh.transform_points(points, matrix)
+
+    # a scaled mirror image is matched exactly when both are allowed
+    transform, cost = rmesh.procrustes(points, moved)
+    assert np.allclose(transform, matrix)
+    assert cost < 1e-9
+
+    # but not with a proper rotation
+    transform, cost = rmesh.procrustes(points, moved, reflection=False, scale=False)
+    assert np.linalg.det(transform[:3, :3]) > 0.0
+    assert cost > 0.1
+
+
+def test_vertex_normals():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        m = rmesh.load_mesh(f.read(), "stl").process()
+
+    # the corners of a cube point diagonally away from the center
+    normals = m.vertex_normals
+    assert normals.shape == (8, 3)
+    assert np.allclose(np.linalg.norm(normals, axis=1), 1.0)
+    center = m.vertices.mean(axis=0)
+    assert (np.einsum("ij,ij->i", normals, m.vertices - center) > 0).all()
+
+    # every edge of the cube is sharp at 10 degrees
+    s = m.smooth_shaded(np.radians(10))
+    assert s.vertices.shape == (24, 3)
+    assert s.vertex_normals.shape == (24, 3)
+
+
+def test_repr():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        data = f.read()
+
+    m = rmesh.load_mesh(data, "stl")
+    assert repr(m).startswith("<rmesh.Trimesh(vertices.shape=(36, 3), faces.shape=(12, 3)")
+
+    scene = rmesh.load_scene(data, "stl")
+    assert repr(scene) == "<rmesh.Scene(geometry=1, nodes=1, instances=1)>"
+    assert scene.node_names == ["world"]
+    name, index, transform = scene.instances()[0]
+    assert index == 0
+    assert np.allclose(transform, np.eye(4))
+    assert repr(rmesh.rectangle(1.0, 1.0)).startswith("<rmesh.Path(")
+
+
+def test_stubs():
+    # the committed stubs should match the current source
+    typegen = os.path.join(_cwd, "..", "typegen.py")
+    subprocess.check_call([sys.executable, typegen, "--check"])
+
+    # everything exported by the module should be in the stubs
+    with open(os.path.join(_cwd, "..", "rmesh.pyi"), encoding="utf8") as f:
+        stub = f.read()
+    for name in dir(rmesh):
+        if not name.startswith("_") and name != "rmesh":
+            assert any(
+                f"{prefix} {name}{suffix}" in stub
+                for prefix, suffix in [("def", "("), ("class", ":"), ("class", "(")]
+            ), name
+
+
+def test_path():
+    path = rmesh.rectangle(2.0, 4.0)
+    assert path.vertices.shape == (4, 3)
+    assert path.entities == [("line", [0, 1, 2, 3, 0])]
+    assert np.isclose(path.length, 12.0)
+
+    discrete = path.discrete(16)
+    assert len(discrete) == 1
+    assert discrete[0].shape == (5, 3)
+
+    # extrude into a closed box
+    m = path.extrude(3.0)
+    assert m.faces.shape == (12, 3)
+    assert np.allclose(m.vertices.max(axis=0), [1.0, 2.0, 3.0])
+
+    # revolve a vertical line into a cylinder
+    line = rmesh.Path(np.array([[1.0, 0.0, 0.0], [1.0, 2.0, 0.0]]), [("line", [0, 1])])
+    m = line.revolve(np.pi * 2, sections=16)
+    assert m.faces.shape == (32, 3)
+    assert np.allclose(np.linalg.norm(m.vertices[:, :2], axis=1), 1.0)
+
+    # a cubic bezier approximating a quarter circle
+    k = 0.5523
+    curve = rmesh.Path(
+        np.array([[1.0, 0.0, 0.0], [1.0, k, 0.0], [k, 1.0, 0.0], [0.0, 1.0, 0.0]]),
+        [("bezier", [0, 1, 2, 3])],
+    )
+    assert np.isclose(curve.length, np.pi / 2, atol=1e-3)
+
+
+def test_scene_graph():
+    with open(os.path.join(_models, "unit_cube.STL"), "rb") as f:
+        m = rmesh.load_mesh(f.read(), "stl")
+
+    scene = rmesh.Scene()
+    shift = np.eye(4)
+    shift[:3, 3] = [2.0, 0.0, 0.0]
+    assert scene.add_geometry(m) == "geometry_0"
+    assert scene.add_geometry(m, node_name="moved", transform=shift) == "moved"
+    scene.add_geometry(m, node_name="child", transform=shift, parent_node_name="moved")
+    with pytest.raises(BaseException):
+        scene.add_geometry(m, node_name="moved")
+
+    graph = scene.graph
+    assert graph.root == "world"
+    assert graph.nodes == ["world", "geometry_0", "moved", "child"]
+    node = graph.node("child")
+    assert node.kind == "geometry"
+    assert node.geometry == [2]
+    assert np.allclose(node.transform, shift)
+    transform, index = graph["child"]
+    assert index == 2
+    assert np.allclose(transform[:3, 3], [4.0, 0.0, 0.0])
+
+    # the graph edits the scene it came from
+    graph.set_transform("child", np.eye(4))
+    assert np.allclose(graph["child"][0][:3, 3], [2.0, 0.0, 0.0])
+
+    dumped = scene.dump()
+    assert len(dumped) == 3
+    assert np.allclose(dumped[1][0], shift)
+    flat = scene.flatten()
+    assert flat.faces.shape == (36, 3)
+    assert np.allclose(flat.vertices.max(axis=0), [2.5, 0.5, 0.5])
+
+
+def test_load(tmp_path):
+    path = os.path.join(_models, "unit_cube.STL")
+    with open(path, "rb") as f:
+        data = f.read()
+
+    # a single mesh loads as a Trimesh from bytes or a path
+    assert isinstance(rmesh.load(data), rmesh.Trimesh)
+    m = rmesh.load(path)
+    assert m.faces.shape == (12, 3)
+    scene = rmesh.load(os.path.join(_models, "two_objects_mixed_case_names.stl"))
+    assert isinstance(scene, rmesh.Scene)
+    assert len(scene.geometry) == 2
+    with pytest.raises(rmesh.UnsupportedFormatError):
+        rmesh.load(b"glTF")
+
+    # exports round trip through files
+    out = str(tmp_path / "cube.obj")
+    text = m.export("obj", path=out)
+    with open(out, encoding="utf8") as f:
+        assert f.read() == text
+    assert rmesh.load(out).faces.shape == (12, 3)
+    out = str(tmp_path / "scene.ply")
+    scene.export(out)
+    assert rmesh.load(out).faces.shape == (24, 3)
+
 
 if __name__ == "__main__":
     test_load_stl()
+    test_load_auto()
+    test_pick()
+    test_intersects()
+    test_stats()
+    test_vertex_normals()
+    test_repr()
+    test_stubs()
+    test_path()
+    test_scene_graph()
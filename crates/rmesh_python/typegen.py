@@ -1,34 +1,24 @@
 """
-Generate a `.pyi` type stub from the rustdoc JSON output.
+Generate a `.pyi` type stub by reading the pyo3 macros in the Rust source.
+
+Reading the source rather than the rustdoc output means the attributes
+consumed by the pyo3 macros like `#[getter]` and `#[pyo3(signature)]`
+are still available, and no nightly toolchain is required.
+
+Run `python typegen.py` to write `rmesh.pyi` next to `Cargo.toml` where
+maturin will include it in the wheel, or `python typegen.py --check` to
+exit with an error if the committed stub is out of date.
 """
 
-import json
 import os
-from collections import defaultdict
-from dataclasses import dataclass
-from typing import Any
+import re
+import sys
+from dataclasses import dataclass, field
 
 # absolute location of current directory and PYI output
-
 cwd = os.path.abspath(os.path.expanduser(os.path.dirname(__file__)))
-root = os.path.abspath(os.path.join(cwd, "..", ".."))
-target = os.path.join(root, "target")
-pyi = os.path.join(cwd, "__init__.pyi")
-
-
-def run_json_docgen():
-    os.system("cargo rustdoc -- --output-format json -Z unstable-options")
-
-
-def run_formatter():
-    os.system(f"ruff format {pyi} && ruff check --fix {pyi} && ruff format {pyi}")
-
-
-def get_typings():
-    run_json_docgen()
-    json_path = os.path.join(target, "doc", "rmesh.json")
-    with open(json_path, encoding="utf8") as src:
-        return json.load(src)["index"]
+src = os.path.join(cwd, "src")
+pyi = os.path.join(cwd, "rmesh.pyi")
 
 
 PRIMITIVES = {
@@ -42,215 +32,410 @@ PRIMITIVES = {
     "u16": "int",
     "u32": "int",
     "u64": "int",
-    "bool": "bool",
     "usize": "int",
+    "bool": "bool",
+    "String": "str",
+    "str": "str",
+    "PathBuf": "str",
+    "PyBytes": "bytes",
 }
 
-REPLACEMENTS = {"String": "str", "Cow": "bytes"}
-
-TypeInfo = dict[str, Any]
-FuncArg = tuple[str, TypeInfo]
-
-
-def clean_type(typename: str) -> str:
-    if typename in REPLACEMENTS:
-        return REPLACEMENTS[typename]
-    else:
-        return typename
-
-
-def get_inner_type(info: TypeInfo):
-    try:
-        args = info["resolved_path"]["args"]["angle_bracketed"]["args"]
-    except KeyError:
-        return None
-
-    return next((a["type"] for a in args if "type" in a), None)
-
-
-def clean_name(name: str) -> str:
-    return name.replace("crate::", "")
+# the numpy scalar type for the element type of an array
+DTYPES = {
+    "f32": "float32",
+    "f64": "float64",
+    "i64": "int64",
+    "u32": "uint32",
+    "bool": "bool_",
+}
 
+# builtin exceptions which pyo3 names differently
+EXCEPTIONS = {
+    "PyException": "Exception",
+    "PyIOError": "OSError",
+    "PyValueError": "ValueError",
+    "PyRuntimeError": "RuntimeError",
+}
 
-def format_type(info: TypeInfo, classname: str | None = None) -> str:
-    if classname is not None:
-        classname = clean_name(classname)
+# default values which are spelled differently in Rust
+PY_LITERALS = {
+    "true": "True",
+    "false": "False",
+}
 
-    if "tuple" in info:
-        innertypes = ",".join(format_type(t) for t in info["tuple"])
-        return f"tuple[{innertypes}]"
-    elif "primitive" in info:
-        return PRIMITIVES[info["primitive"]]
-    elif "generic" in info:
-        if info["generic"] == "Self" and classname is not None:
-            return classname  # '"' + classname + '"'
-        else:
-            return info["generic"]
-    elif "resolved_path" in info:
-        resolved_name = info["resolved_path"].get(
-            "name", info["resolved_path"].get("path")
-        )
-        if resolved_name == "Option":
-            return f"{format_type(get_inner_type(info), classname)} | None"
-        elif resolved_name == "Result" or resolved_name == "anyhow::Result":
-            return format_type(get_inner_type(info), classname)
-        elif resolved_name == "Vec":
-            return f"list[{format_type(get_inner_type(info), classname)}]"
-        elif resolved_name.startswith("PyReadonlyArray"):
-            prim = get_inner_type(info)["primitive"]
-            if prim == "f32":
-                return "NDArray[float32]"
-            elif prim == "f64":
-                return "NDArray[float64]"
-            elif prim == "i64":
-                return "NDArray[int64]"
-            else:
-                raise ValueError(info)
-                return "NDArray"
+PREAMBLE = '''"""
+Type stubs for `rmesh` generated by `typegen.py`, do not edit by hand.
+"""
+'''
+
+
+def split_top(text: str, sep: str = ",") -> list[str]:
+    """
+    Split text on a separator outside of any brackets.
+    """
+    parts, depth, current = [], 0, ""
+    for char in text:
+        if char in "<([":
+            depth += 1
+        elif char in ">)]":
+            depth -= 1
+        if char == sep and depth == 0:
+            parts.append(current)
+            current = ""
         else:
-            return clean_name(clean_type(resolved_name))  # '"' + resolved_name + '"'
-    elif "borrowed_ref" in info:
-        reftype = info["borrowed_ref"]["type"]
-        return format_type(reftype, classname)
-    elif "slice" in info:
-        stype = info["slice"]
-        assert stype["primitive"] == "u8", f"Weird slice: {info}"
-        return "bytes"
-    elif "array" in info:
-        array_type = info["array"]["type"]
-        # TODO: consider if there's any way to nicely type fixed-size arrays?
-        return f"list[{format_type(array_type, classname)}]"
-    else:
-        return "Any"
-        # raise ValueError(f"Unknown type: {info}")
-
-
-def format_arg(name: str, info: TypeInfo, classname: str | None = None) -> str:
-    if name == "self":
-        return "self"
-    return f"{name}: {format_type(info, classname)}"
-
-
-def remove_prefix(s: str, prefix: str) -> str:
-    if s.startswith(prefix):
-        return s[len(prefix) :]
-    else:
-        return s
+            current += char
+    if current.strip():
+        parts.append(current)
+    return [p.strip() for p in parts]
+
+
+def generic(text: str) -> tuple[str, list[str]]:
+    """
+    Split a type like `Vec<Option<f64>>` into `Vec` and its arguments,
+    dropping any lifetimes.
+    """
+    if "<" not in text:
+        return text, []
+    name, inner = text.split("<", 1)
+    args = [a for a in split_top(inner[: inner.rindex(">")]) if not a.startswith("'")]
+    return name.strip(), args
+
+
+def format_type(rust: str, classes: dict[str, str], owner: str | None) -> str:
+    """
+    Convert a Rust type from a signature to a Python annotation.
+    """
+    rust = re.sub(r"^&(mut\s+)?('\w+\s+)?", "", rust.strip())
+
+    # the unit type is returned to Python as None
+    if rust == "()":
+        return "None"
+    if rust.startswith("(") and rust.endswith(")"):
+        inner = ", ".join(format_type(t, classes, owner) for t in split_top(rust[1:-1]))
+        return f"tuple[{inner}]"
+    if rust.startswith("["):
+        inner = rust[1:-1]
+        if ";" in inner:
+            return f"list[{format_type(inner.split(';')[0], classes, owner)}]"
+        return "bytes" if inner.strip() == "u8" else f"list[{format_type(inner, classes, owner)}]"
+
+    name, args = generic(rust)
+    name = name.split("::")[-1]
+    if name in ("Result", "PyResult", "Py", "Bound"):
+        return format_type(args[0], classes, owner)
+    if name == "Option":
+        return f"{format_type(args[0], classes, owner)} | None"
+    if name == "Vec":
+        return f"list[{format_type(args[0], classes, owner)}]"
+    if re.match(r"^(PyReadonly|Py)Array(\d|Dyn)$", name):
+        return f"NDArray[{DTYPES[args[-1]]}]"
+    if name == "Self" and owner is not None:
+        return owner
+    if name in classes:
+        return classes[name]
+    if name in PRIMITIVES:
+        return PRIMITIVES[name]
+    return "object"
 
 
 @dataclass
-class FuncInfo:
+class Function:
     name: str
-    args: list[FuncArg]
-    ret: TypeInfo | None
-    doc: str | None
-
-    def decl(self, classname: str | None = None) -> list[str]:
-        ret = []
-        arglist = ", ".join(format_arg(arg[0], arg[1], classname) for arg in self.args)
-        fname = self.name
-        if fname == "new":
-            fname = "__init__"
-            arglist = "self, " + arglist
-        elif fname.startswith("py_get_"):
-            ret.append("@property")
-            fname = remove_prefix(fname, "py_get_")
-        elif fname.startswith("py_"):
-            fname = remove_prefix(fname, "py_")
-
-        res = f"{fname}({arglist})"
-        if self.ret is not None and fname != "__init__":
-            res += f" -> {format_type(self.ret, classname)}"
-        ret.append(f"def {res}:")
-        if self.doc is not None:
-            doclines = remove_prefix(self.doc, "(pyfunc)").strip().split("\n")
-            ret.append('    """')
-            ret.extend("    " + line.rstrip() for line in doclines)
-            ret.append('    """')
+    args: list[tuple[str, str]]
+    ret: str | None
+    doc: list[str]
+    attrs: list[str]
+    has_self: bool = False
+
+    def signature_defaults(self) -> dict[str, str]:
+        """
+        Default values from a `#[pyo3(signature = (a, b=1))]` attribute.
+        """
+        for attr in self.attrs:
+            match = re.search(r"signature\s*=\s*\(", attr)
+            if match is None:
+                continue
+            # take the arguments up to the matching parenthesis
+            inner = attr[match.end() :]
+            depth = 1
+            for position, char in enumerate(inner):
+                depth += {"(": 1, ")": -1}.get(char, 0)
+                if depth == 0:
+                    inner = inner[:position]
+                    break
+            return dict(
+                [p.strip() for p in arg.split("=", 1)] for arg in split_top(inner) if "=" in arg
+            )
+        return {}
+
+    def python_name(self) -> str:
+        for attr in self.attrs:
+            match = re.search(r'name\s*=\s*"(\w+)"', attr)
+            if match and "pyfunction" in attr:
+                return match.group(1)
+        if self.is_getter():
+            return self.name.removeprefix("get_")
+        if self.is_new():
+            return "__init__"
+        return self.name
+
+    def is_getter(self) -> bool:
+        return any(a.startswith("#[getter") for a in self.attrs)
+
+    def is_new(self) -> bool:
+        return any(a.startswith("#[new") for a in self.attrs)
+
+    def decl(self, classes: dict[str, str], owner: str | None) -> list[str]:
+        defaults = self.signature_defaults()
+        args = ["self"] if self.has_self or self.is_new() else []
+        for name, rust in self.args:
+            arg = f"{name}: {format_type(rust, classes, owner)}"
+            if name in defaults:
+                arg += f" = {PY_LITERALS.get(defaults[name], defaults[name])}"
+            args.append(arg)
+
+        ret = "None" if self.is_new() or self.ret is None else format_type(self.ret, classes, owner)
+        lines = []
+        if self.is_getter():
+            lines.append("@property")
+        elif owner is not None and not self.has_self and not self.is_new():
+            lines.append("@staticmethod")
+        lines.append(f"def {self.python_name()}({', '.join(args)}) -> {ret}:")
+
+        doc = "\n".join(self.doc).removeprefix("(pyfunc)").strip().splitlines()
+        if len(doc) == 1:
+            lines.append(f'    """{doc[0]}"""')
+        elif doc:
+            lines.append('    """')
+            lines.extend(f"    {line}".rstrip() for line in doc)
+            lines.append('    """')
         else:
-            ret.append('    """ Undocumented function """')
-        return ret
-
-
-def extract_func(item: dict[str, Any]) -> FuncInfo:
-    fname = item["name"]
-    funcinfo = item["inner"]["function"]
-    if "decl" in funcinfo:
-        decl = funcinfo["decl"]
-    elif "sig" in funcinfo:
-        decl = funcinfo["sig"]
-    else:
-        raise RuntimeError(f"Function {fname} missing 'sig' or 'decl'!")
-    args = decl["inputs"]
-    retval = None
-    if "output" in decl and decl["output"] is not None:
-        retval = decl["output"]
-    doc = None
-    if "docs" in item:
-        doc = item["docs"]
-    return FuncInfo(fname, args, retval, doc)
-
-
-def find_methods(typings: dict[str, TypeInfo]) -> dict[str, list[FuncInfo]]:
-    res = defaultdict(list)
-    for v in typings.values():
-        if ("docs" in v) and isinstance(v["docs"], str):
-            docstr = v["docs"]
-            if docstr.startswith("(pymethods)"):
-                impl = v["inner"]["impl"]
-                resolve = impl["for"]["resolved_path"]
-                classname = resolve.get("name", resolve.get("path"))
-                for item in impl["items"]:
-                    res[classname].append(extract_func(typings[str(item)]))
-            elif docstr.startswith("(pyfunc)"):
-                res["__global"].append(extract_func(v))
-            else:
-                print(f"Unknown docstr: {docstr}")
-
-    return res
+            lines.append("    ...")
+        return lines
 
 
-def emit_methods(methods: list[FuncInfo], classname: str | None) -> str:
-    indent = ""
-    res = ""
-    if (classname is not None) and (classname != "__global"):
-        res = f"class {classname}:\n"
-        indent = "    "
-
-    bodies = []
-    for meth in methods:
-        body = "\n".join([indent + line for line in meth.decl(classname)]) + "\n"
-        bodies.append(body)
-    return res + "\n".join(sorted(bodies))
+@dataclass
+class Class:
+    name: str
+    methods: list[Function] = field(default_factory=list)
 
 
-# ruff postprocessing will remove anything that wasn't used
-PREAMBLE = "\n".join(
-    [
-        "from typing import Any",
-        "from numpy.typing import NDArray",
-        "from numpy import float64, float32, int64, uint32",
-    ]
-)
+@dataclass
+class Exception_:
+    name: str
+    base: str
+    doc: str
+
+    def decl(self) -> str:
+        base = EXCEPTIONS.get(self.base, self.base)
+        return f'class {self.name}({base}):\n    """{self.doc}"""'
+
+
+def strip_strings(line: str) -> str:
+    """
+    Remove string and char literals so braces inside them aren't counted.
+    """
+    line = re.sub(r'"(?:\\.|[^"\\])*"', '""', line)
+    return re.sub(r"'(?:\\.|[^'\\])'", "''", line)
+
+
+def parse_function(lines: list[str], index: int) -> tuple[str, int]:
+    """
+    Collect the signature of a function starting at a line until its body.
+    """
+    signature = ""
+    while True:
+        line = strip_strings(lines[index])
+        depth = 0
+        for position, char in enumerate(line):
+            if char in "([<":
+                depth += 1
+            elif char in ")]>" and not line[position - 1 : position + 1] == "->":
+                depth -= 1
+            elif char in "{;" and depth <= 0 and signature.count("(") + line[:position].count("(") > 0:
+                return signature + line[:position], index
+        signature += line + " "
+        index += 1
+
+
+def parse_signature(signature: str) -> tuple[str, list[tuple[str, str]], str | None, bool]:
+    name = re.search(r"fn\s+(\w+)", signature).group(1)
+    params = signature[signature.index("(", signature.index(name)) + 1 :]
+    # find the closing parenthesis of the parameters
+    depth = 1
+    for position, char in enumerate(params):
+        depth += {"(": 1, ")": -1}.get(char, 0)
+        if depth == 0:
+            rest = params[position + 1 :]
+            params = params[:position]
+            break
+
+    ret = rest.split("->", 1)[1].strip() if "->" in rest else None
+    args, has_self = [], False
+    for param in split_top(params):
+        if param.endswith("self"):
+            has_self = True
+            continue
+        arg, rust = [p.strip() for p in param.split(":", 1)]
+        # a receiver spelled as a smart pointer to the class is still self
+        if re.match(r"^(Bound|Py|PyRef)<.*\bSelf>$", rust):
+            has_self = True
+            continue
+        # the interpreter token isn't a Python argument
+        if rust.startswith("Python"):
+            continue
+        args.append((arg, rust))
+    return name, args, ret, has_self
+
+
+def parse_file(
+    path: str,
+) -> tuple[dict[str, str], list[Class], list[Function], list[Exception_], dict[str, list[str]]]:
+    """
+    Find the classes, methods, functions and exceptions exposed by pyo3 in a
+    file, and the enums converted to or from Python as one of their variants.
+    """
+    with open(path, encoding="utf8") as f:
+        lines = f.read().splitlines()
+
+    classes, methods, functions, exceptions, unions = {}, [], [], [], {}
+    doc, attrs = [], []
+    current = None
+    depth = 0
+    index = 0
+    while index < len(lines):
+        line = lines[index].strip()
+        if line.startswith("#[cfg(test)]"):
+            break
+        if line.startswith("create_exception!"):
+            # the macro call may be split over several lines
+            call = line
+            while not call.endswith(";"):
+                index += 1
+                call += " " + lines[index].strip()
+            inner = call[call.index("(") + 1 : call.rindex(")")]
+            # the docstring may itself contain commas
+            _, name, base, *rest = [p.strip() for p in inner.split(",", 3)]
+            exceptions.append(Exception_(name, base, rest[0].strip('"') if rest else ""))
+        elif line.startswith("///"):
+            doc.append(line[3:].strip())
+        elif line.startswith("#["):
+            # attributes may span lines
+            attr = line
+            while attr.count("[") > attr.count("]"):
+                index += 1
+                attr += " " + lines[index].strip()
+            attrs.append(attr)
+        elif re.match(r"(pub(\(crate\))?\s+)?struct\s+\w+", line) and any(
+            a.startswith("#[pyclass") for a in attrs
+        ):
+            rust = re.search(r"struct\s+(\w+)", line).group(1)
+            match = re.search(r'name\s*=\s*"(\w+)"', " ".join(attrs))
+            classes[rust] = match.group(1) if match else rust
+        elif re.match(r"(pub(\(crate\))?\s+)?enum\s+\w+", line) and any(
+            re.search(r"\b(From|Into)PyObject\b", a) for a in attrs
+        ):
+            # each variant wraps a single type and the enum is any of them
+            rust = re.search(r"enum\s+(\w+)", line).group(1)
+            unions[rust] = []
+            while not lines[index].strip().startswith("}"):
+                index += 1
+                variant = re.match(r"\w+\((.*)\),?$", lines[index].strip())
+                if variant:
+                    unions[rust].append(variant.group(1))
+            doc, attrs = [], []
+        elif line.startswith("impl ") and "#[pymethods]" in attrs:
+            current = Class(re.search(r"impl\s+(\w+)", line).group(1))
+            methods.append(current)
+            depth = 0
+        elif re.match(r"(pub(\(crate\))?\s+)?fn\s+", line) and (
+            current is not None or any(a.startswith("#[pyfunction") for a in attrs)
+        ):
+            signature, index = parse_function(lines, index)
+            name, args, ret, has_self = parse_signature(signature)
+            function = Function(name, args, ret, doc, attrs, has_self)
+            if current is not None:
+                # only public methods are exposed
+                if signature.strip().startswith("pub"):
+                    current.methods.append(function)
+            else:
+                functions.append(function)
+            doc, attrs = [], []
+            # skip the body of the function which starts on the last line
+            # of the signature, as signatures themselves contain no braces
+            depth_body, opened = 0, False
+            while True:
+                code = strip_strings(lines[index])
+                opened = opened or "{" in code
+                depth_body += code.count("{") - code.count("}")
+                if (opened and depth_body <= 0) or (not opened and code.rstrip().endswith(";")):
+                    break
+                index += 1
+            index += 1
+            continue
+        else:
+            doc, attrs = [], []
+
+        if current is not None:
+            code = strip_strings(lines[index])
+            depth += code.count("{") - code.count("}")
+            if depth <= 0 and "}" in code:
+                current = None
+        index += 1
+
+    return classes, methods, functions, exceptions, unions
+
+
+def generate() -> str:
+    classes, methods, functions, exceptions, unions = {}, [], [], [], {}
+    for name in sorted(os.listdir(src)):
+        if name.endswith(".rs"):
+            c, m, f, e, u = parse_file(os.path.join(src, name))
+            classes.update(c)
+            methods.extend(m)
+            functions.extend(f)
+            exceptions.extend(e)
+            unions.update(u)
+    # enums are annotated as the union of their variants
+    types = dict(classes)
+    for rust, variants in unions.items():
+        types[rust] = " | ".join(format_type(v, classes, None) for v in variants)
+
+    blocks = []
+    for function in sorted(functions, key=lambda f: f.python_name()):
+        blocks.append("\n".join(function.decl(types, None)))
+
+    # exceptions stay in source order so base classes come first
+    for exception in exceptions:
+        blocks.append(exception.decl())
+
+    for rust, python in sorted(classes.items(), key=lambda c: c[1]):
+        body = []
+        for impl in methods:
+            if impl.name != rust:
+                continue
+            for method in sorted(impl.methods, key=lambda m: (m.python_name() != "__init__", m.python_name())):
+                body.extend("    " + line for line in method.decl(types, python))
+        if not body:
+            body = ["    ..."]
+        blocks.append(f"class {python}:\n" + "\n".join(body))
+
+    body = "\n\n".join(blocks)
+    # only import the numpy types which are used
+    dtypes = sorted(d for d in set(DTYPES.values()) if re.search(rf"\[{d}\]", body))
+    imports = f"from numpy import {', '.join(dtypes)}\nfrom numpy.typing import NDArray\n"
+    return PREAMBLE + "\n" + imports + "\n\n" + body + "\n"
 
 
 if __name__ == "__main__":
-    run_json_docgen()
-    print("Emitted JSON")
-    typings = get_typings()
-    meths = find_methods(typings)
-
-    print(meths)
-
-    items = [emit_methods(v, k) for k, v in meths.items()]
-    print(typings)
-    items = sorted(items)
-
-    with open(pyi, "w", encoding="utf8") as dest:
-        dest.write(PREAMBLE)
-        dest.write("\n\n")
-        dest.write("\n".join(items))
-
-    print("Wrote stub file")
-    run_formatter()
-    print("Formatted.")
+    stub = generate()
+    if "--check" in sys.argv:
+        with open(pyi, encoding="utf8") as f:
+            if f.read() != stub:
+                print(f"{pyi} is out of date, run `python typegen.py`")
+                sys.exit(1)
+        print(f"{pyi} is up to date")
+    else:
+        with open(pyi, "w", encoding="utf8") as f:
+            f.write(stub)
+        print(f"Wrote {pyi}")
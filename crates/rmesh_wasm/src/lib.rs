@@ -1,8 +1,19 @@
 mod utils;
 
+use js_sys::{Float32Array, Float64Array, Function, Promise, Uint32Array};
 use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
 
-use rmesh::exchange::{MeshFormat, load_mesh};
+use rmesh::error::RmeshError;
+use rmesh::exchange::{
+    Compression, MeshFormat, MeshStream, PackOptions, load_mesh, load_mesh_auto, load_scene,
+    pack_mesh, unpack_mesh as core_unpack_mesh,
+};
+use rmesh::geometry::Geometry;
+use rmesh::mesh::Trimesh;
+use rmesh::path::{Curve, Path as CorePath, rectangle as core_rectangle};
+use rmesh::ray::Ray;
+use rmesh::scene::Scene;
 
 #[wasm_bindgen]
 extern "C" {
@@ -14,10 +25,555 @@ pub fn greet() {
     alert("Hello, rmesh-wasm!");
 }
 
+/// Errors can only cross into JavaScript as strings, so prefix the
+/// message with the kind of error like `ParseError: ...` which
+/// callers can switch on.
+fn typed_error(error: RmeshError) -> String {
+    format!("{}: {error}", error.kind())
+}
+
+/// Convert transforms to single precision one after another as
+/// the column major values `Matrix4.fromArray` in three.js expects.
+fn transforms_array<'a>(
+    transforms: impl Iterator<Item = &'a nalgebra::Matrix4<f64>>,
+) -> Float32Array {
+    let values: Vec<f32> = transforms
+        .flat_map(|m| m.iter().map(|v| *v as f32))
+        .collect();
+    Float32Array::from(values.as_slice())
+}
+
 #[wasm_bindgen]
 pub fn load_mesh_ex(file_data: &[u8], file_type: &str) -> Result<String, String> {
-    let mesh_format = MeshFormat::from_string(file_type).map_err(|e| e.to_string())?;
-    let mesh = load_mesh(file_data, mesh_format).map_err(|e| e.to_string())?;
+    let mesh_format = MeshFormat::from_string(file_type).map_err(typed_error)?;
+    let mesh = load_mesh(file_data, mesh_format).map_err(typed_error)?;
     // just print the debug info
     Ok(format!("{mesh:?}"))
 }
+
+#[wasm_bindgen]
+pub fn load_mesh_auto_ex(file_data: &[u8]) -> Result<String, String> {
+    let mesh = load_mesh_auto(file_data).map_err(typed_error)?;
+    Ok(format!("{mesh:?}"))
+}
+
+#[wasm_bindgen]
+pub fn detect_format(file_data: &[u8]) -> Result<String, String> {
+    MeshFormat::detect(file_data)
+        .map(|f| f.extension().to_string())
+        .map_err(typed_error)
+}
+
+/// How many bytes `load_mesh_async` parses between progress reports,
+/// which is small enough to keep a page responsive while loading.
+const ASYNC_CHUNK: usize = 1 << 20;
+
+/// Let the event loop run, such as to repaint the page or handle input,
+/// by waiting on a `setTimeout` of zero which exists in windows and workers.
+async fn yield_now() {
+    let promise = Promise::new(&mut |resolve, _| {
+        let global = js_sys::global();
+        let timeout = js_sys::Reflect::get(&global, &JsValue::from_str("setTimeout"))
+            .ok()
+            .and_then(|f| f.dyn_into::<Function>().ok());
+        let _ = match timeout {
+            Some(timeout) => timeout.call2(&global, &resolve, &JsValue::from(0)),
+            None => resolve.call0(&JsValue::NULL),
+        };
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+/// Load a mesh without blocking the page, parsing a chunk of the file at a
+/// time and yielding to the event loop in between. `on_progress` is called
+/// with the percent of the file parsed so far and with 100 once it's done,
+/// and an exception thrown from it stops the load.
+///
+/// OBJ and binary STL files are parsed as the chunks go, while other
+/// formats are parsed all at once at the end like `MeshLoader`.
+#[wasm_bindgen]
+pub async fn load_mesh_async(
+    file_data: Vec<u8>,
+    file_type: String,
+    on_progress: Function,
+) -> Result<Mesh, String> {
+    let report = |percent: f64| {
+        on_progress
+            .call1(&JsValue::NULL, &JsValue::from(percent))
+            .map(|_| ())
+            .map_err(|e| e.as_string().unwrap_or_else(|| format!("{e:?}")))
+    };
+    let mut stream = MeshStream::new(MeshFormat::from_string(&file_type).map_err(typed_error)?);
+    let total = file_data.len().max(1) as f64;
+    for chunk in file_data.chunks(ASYNC_CHUNK) {
+        report(100.0 * stream.received() as f64 / total)?;
+        stream.push(chunk).map_err(typed_error)?;
+        yield_now().await;
+    }
+    let data = stream.finish().map_err(typed_error)?;
+    report(100.0)?;
+    Ok(Mesh { data })
+}
+
+/// Load a mesh from chunks of a file pushed as they arrive, such as from
+/// the reader of a `fetch` response body, so the file is parsed while
+/// it's still downloading rather than all at once at the end.
+///
+/// OBJ, STL and PLY files are parsed as their lines, triangles and rows
+/// arrive, while other formats are held until `finish`.
+#[wasm_bindgen]
+pub struct MeshLoader {
+    stream: MeshStream,
+    // the size of the whole file if it's known, for reporting progress
+    total: Option<usize>,
+}
+
+#[wasm_bindgen]
+impl MeshLoader {
+    /// Start loading a file of a format, optionally with its size in
+    /// bytes like the `Content-Length` of a response to report progress.
+    #[wasm_bindgen(constructor)]
+    pub fn new(file_type: &str, total: Option<usize>) -> Result<MeshLoader, String> {
+        Ok(MeshLoader {
+            stream: MeshStream::new(MeshFormat::from_string(file_type).map_err(typed_error)?),
+            total,
+        })
+    }
+
+    /// Parse the next chunk of the file as far as possible.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), String> {
+        self.stream.push(chunk).map_err(typed_error)
+    }
+
+    /// How many bytes have been pushed so far.
+    pub fn received(&self) -> usize {
+        self.stream.received()
+    }
+
+    /// The percent of the file pushed so far, or `undefined`
+    /// if the size of the file wasn't passed.
+    pub fn progress(&self) -> Option<f64> {
+        self.total
+            .map(|total| (100.0 * self.stream.received() as f64 / total.max(1) as f64).min(100.0))
+    }
+
+    /// Parse the rest of the file once every chunk has been pushed,
+    /// which frees the loader.
+    pub fn finish(self) -> Result<Mesh, String> {
+        self.stream
+            .finish()
+            .map(|data| Mesh { data })
+            .map_err(typed_error)
+    }
+}
+
+/// A triangle mesh with flat arrays for passing to a renderer.
+#[wasm_bindgen]
+pub struct Mesh {
+    data: Trimesh,
+}
+
+#[wasm_bindgen]
+impl Mesh {
+    /// The vertices as a flat array of XYZ values.
+    pub fn vertices(&self) -> Vec<f64> {
+        self.data.vertices_flat().to_vec()
+    }
+
+    /// The faces as a flat array of vertex indices.
+    pub fn faces(&self) -> Vec<u32> {
+        // a wasm32 `usize` is a `u32` so every index fits
+        self.data
+            .faces_flat()
+            .map(|f| f.to_vec())
+            .unwrap_or_default()
+    }
+
+    /// The first face colors as a flat array of RGBA values, or
+    /// `undefined` if the mesh doesn't have a color for every face.
+    pub fn face_colors(&self) -> Option<Vec<u8>> {
+        let colors = self.data.attributes_face.colors.first()?;
+        (colors.len() == self.data.faces().len())
+            .then(|| colors.iter().flat_map(|c| [c.x, c.y, c.z, c.w]).collect())
+    }
+
+    /// The vertex normals as a flat array of XYZ values.
+    pub fn vertex_normals(&self) -> Vec<f64> {
+        self.data
+            .vertex_normals()
+            .iter()
+            .flat_map(|n| [n.x, n.y, n.z])
+            .collect()
+    }
+
+    /// A view of the vertices as a flat array of XYZ values directly over
+    /// wasm memory rather than a copy like `vertices`.
+    ///
+    /// The view is only valid until the mesh is freed or the wasm memory
+    /// grows, which any call allocating in rmesh may do, so copy it with
+    /// `slice()` or upload it before calling anything else.
+    pub fn vertices_view(&self) -> Float64Array {
+        // SAFETY: the caller is told not to keep the view past any allocation
+        unsafe { Float64Array::view(bytemuck::cast_slice(self.data.vertices())) }
+    }
+
+    /// Convert the mesh to the single precision buffers a renderer
+    /// uploads, which can then be viewed without copying.
+    pub fn render_buffers(&self) -> RenderBuffers {
+        RenderBuffers::from_mesh(&self.data)
+    }
+
+    /// Split vertices along edges sharper than an angle in radians
+    /// so the vertex normals shade smoothly without blurring creases.
+    pub fn smooth_shaded(&self, threshold: f64) -> Mesh {
+        Mesh {
+            data: self.data.smooth_shaded(threshold),
+        }
+    }
+
+    /// Find the first face hit by a ray from an XYZ origin along an XYZ
+    /// direction, or `undefined` if the ray misses the mesh.
+    pub fn pick(&self, origin: &[f64], direction: &[f64]) -> Result<Option<Pick>, String> {
+        let (&[ox, oy, oz], &[dx, dy, dz]) = (origin, direction) else {
+            return Err("The origin and direction need three values each".to_string());
+        };
+        let ray = Ray::new(
+            nalgebra::Point3::new(ox, oy, oz),
+            nalgebra::Vector3::new(dx, dy, dz),
+        );
+        Ok(self.data.pick(&ray).map(|hit| Pick {
+            face: hit.face,
+            point: hit.point.coords.into(),
+            barycentric: hit.barycentric.into(),
+            distance: hit.distance,
+        }))
+    }
+
+    /// Pack the mesh into compact bytes whose buffer can be transferred
+    /// to another worker rather than copied, optionally snapping vertices
+    /// to a grid and compressing with `lz4`, `zstd` or `none`.
+    pub fn pack(&self, quantize: Option<f64>, compression: &str) -> Result<Vec<u8>, String> {
+        let options = PackOptions {
+            quantize,
+            compression: Compression::from_string(compression).map_err(|e| e.to_string())?,
+        };
+        pack_mesh(&self.data, &options).map_err(|e| e.to_string())
+    }
+}
+
+/// Where a ray first hit a mesh, from `Mesh.pick`.
+#[wasm_bindgen]
+pub struct Pick {
+    face: usize,
+    point: [f64; 3],
+    barycentric: [f64; 3],
+    distance: f64,
+}
+
+#[wasm_bindgen]
+impl Pick {
+    /// The index of the face that was hit.
+    pub fn face(&self) -> usize {
+        self.face
+    }
+
+    /// The location of the hit as XYZ values.
+    pub fn point(&self) -> Vec<f64> {
+        self.point.to_vec()
+    }
+
+    /// The barycentric coordinates of the hit on the corners of the face.
+    pub fn barycentric(&self) -> Vec<f64> {
+        self.barycentric.to_vec()
+    }
+
+    /// The distance from the origin of the ray to the hit.
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+}
+
+/// The flat arrays of a mesh in the layout of a WebGL or WebGPU vertex
+/// and index buffer, owned by wasm memory so they can be viewed from
+/// JavaScript without copying.
+///
+/// Every view is only valid until the buffers are released with `free()`
+/// or the wasm memory grows, which any call allocating in rmesh may do,
+/// so upload or `slice()` the views right away and call `free()` once
+/// they're no longer needed rather than waiting on the garbage collector.
+#[wasm_bindgen]
+pub struct RenderBuffers {
+    positions: Vec<f32>,
+    normals: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+impl RenderBuffers {
+    /// The buffers of a mesh with its vertex normals.
+    fn from_mesh(mesh: &Trimesh) -> Self {
+        RenderBuffers {
+            positions: mesh
+                .vertices()
+                .iter()
+                .flat_map(|v| [v.x as f32, v.y as f32, v.z as f32])
+                .collect(),
+            normals: mesh
+                .vertex_normals()
+                .iter()
+                .flat_map(|n| [n.x as f32, n.y as f32, n.z as f32])
+                .collect(),
+            indices: mesh.faces_flat().map(|f| f.to_vec()).unwrap_or_default(),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RenderBuffers {
+    /// A view of the vertex positions as flat XYZ values.
+    pub fn positions(&self) -> Float32Array {
+        // SAFETY: the buffers are never modified and the caller
+        // is told not to keep the view past any allocation
+        unsafe { Float32Array::view(&self.positions) }
+    }
+
+    /// A view of the vertex normals as flat XYZ values.
+    pub fn normals(&self) -> Float32Array {
+        // SAFETY: as for `positions`
+        unsafe { Float32Array::view(&self.normals) }
+    }
+
+    /// A view of the vertex indices of every triangle.
+    pub fn indices(&self) -> Uint32Array {
+        // SAFETY: as for `positions`
+        unsafe { Uint32Array::view(&self.indices) }
+    }
+
+    /// The number of vertices in the buffers.
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len() / 3
+    }
+
+    /// The number of indices in the buffers, which is three per triangle.
+    pub fn index_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// A copy of the positions and normals interleaved as the XYZ position
+    /// then the XYZ normal of every vertex, for a single vertex buffer with
+    /// a stride of six floats like an `InterleavedBuffer` in three.js.
+    pub fn interleaved(&self) -> Vec<f32> {
+        self.positions
+            .chunks_exact(3)
+            .zip(self.normals.chunks_exact(3))
+            .flat_map(|(p, n)| [p[0], p[1], p[2], n[0], n[1], n[2]])
+            .collect()
+    }
+}
+
+/// A scene of meshes placed by a graph of nodes, for rendering every
+/// mesh once and placing it with the transforms of its instances.
+#[wasm_bindgen]
+pub struct WasmScene {
+    data: Scene,
+}
+
+#[wasm_bindgen]
+impl WasmScene {
+    /// Load a file into a scene, detecting the format from the contents
+    /// if `file_type` isn't passed. Every object of an OBJ, solid of an
+    /// STL and object of a 3MF is its own geometry, while GLB files
+    /// aren't supported yet and are an `UnsupportedFormat` error.
+    pub fn load(file_data: &[u8], file_type: Option<String>) -> Result<WasmScene, String> {
+        let format = match file_type {
+            Some(file_type) => MeshFormat::from_string(&file_type),
+            None => MeshFormat::detect(file_data),
+        }
+        .map_err(typed_error)?;
+        load_scene(file_data, format)
+            .map(|data| WasmScene { data })
+            .map_err(typed_error)
+    }
+
+    /// The number of geometries, including any which aren't meshes.
+    pub fn geometry_count(&self) -> usize {
+        self.data.geometry.len()
+    }
+
+    /// The name of every node, in the order of their indices.
+    pub fn node_names(&self) -> Vec<String> {
+        self.data
+            .graph
+            .nodes()
+            .iter()
+            .map(|n| n.name.clone())
+            .collect()
+    }
+
+    /// The indices of the geometry a node places.
+    pub fn node_geometry(&self, node: usize) -> Vec<u32> {
+        self.data
+            .graph
+            .nodes()
+            .get(node)
+            .map(|n| n.index.iter().map(|i| *i as u32).collect())
+            .unwrap_or_default()
+    }
+
+    /// The transform from the root to a node as 16 column major values,
+    /// or `undefined` if the node doesn't exist or isn't under the root.
+    pub fn world_transform(&self, node: usize) -> Option<Float32Array> {
+        let transform = self.data.graph.world_transform(node)?;
+        Some(transforms_array(std::iter::once(&transform)))
+    }
+
+    /// The transform from the root to every placement of a geometry as
+    /// 16 column major values each, which is the layout of the
+    /// `instanceMatrix` of an `InstancedMesh` in three.js.
+    pub fn instance_transforms(&self, geometry: usize) -> Float32Array {
+        let instances: Vec<_> = self
+            .data
+            .flatten()
+            .into_iter()
+            .filter(|(g, _)| *g == geometry)
+            .map(|(_, instance)| instance.transform)
+            .collect();
+        transforms_array(instances.iter())
+    }
+
+    /// The indices of the node of every placement of a geometry in
+    /// the same order as `instance_transforms`.
+    pub fn instance_nodes(&self, geometry: usize) -> Vec<u32> {
+        self.data
+            .flatten()
+            .into_iter()
+            .filter(|(g, _)| *g == geometry)
+            .map(|(_, instance)| instance.node as u32)
+            .collect()
+    }
+
+    /// A copy of a geometry in its own frame, or `undefined`
+    /// if it doesn't exist or isn't a mesh.
+    pub fn mesh(&self, geometry: usize) -> Option<Mesh> {
+        match self.data.geometry.get(geometry)? {
+            Geometry::Mesh(mesh) => Some(Mesh {
+                data: mesh.as_ref().clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The render buffers of a geometry in its own frame, which are
+    /// placed with `instance_transforms`, or `undefined` if it doesn't
+    /// exist or isn't a mesh.
+    pub fn render_buffers(&self, geometry: usize) -> Option<RenderBuffers> {
+        match self.data.geometry.get(geometry)? {
+            Geometry::Mesh(mesh) => Some(RenderBuffers::from_mesh(mesh)),
+            _ => None,
+        }
+    }
+}
+
+/// Unpack a mesh from the bytes of `Mesh.pack`.
+#[wasm_bindgen]
+pub fn unpack_mesh(data: &[u8]) -> Result<Mesh, String> {
+    core_unpack_mesh(data)
+        .map(|data| Mesh { data })
+        .map_err(|e| e.to_string())
+}
+
+/// A 2D path made of lines, arcs and curves.
+#[wasm_bindgen]
+pub struct Path {
+    data: CorePath,
+}
+
+#[wasm_bindgen]
+impl Path {
+    /// Create a path with no entities from a flat array of XYZ vertices.
+    #[wasm_bindgen(constructor)]
+    pub fn new(vertices: &[f64]) -> Path {
+        let vertices = vertices
+            .chunks_exact(3)
+            .map(|c| nalgebra::Point3::new(c[0], c[1], c[2]))
+            .collect();
+        Path {
+            data: CorePath::new(vertices, vec![]),
+        }
+    }
+
+    /// Add an entity of kind `line`, `bezier`, `circle`, `arc` or `arc_cw`.
+    pub fn add_entity(&mut self, kind: &str, indices: &[u32]) -> Result<(), String> {
+        let indices: Vec<usize> = indices.iter().map(|i| *i as usize).collect();
+        if let Some(index) = indices.iter().find(|i| **i >= self.data.vertices.len()) {
+            return Err(format!("Entity references missing vertex {index}"));
+        }
+        let curve = Curve::from_kind(kind, indices).map_err(|e| e.to_string())?;
+        self.data.entities.push(curve);
+        Ok(())
+    }
+
+    /// The vertices as a flat array of XYZ values.
+    pub fn vertices(&self) -> Vec<f64> {
+        self.data
+            .vertices
+            .iter()
+            .flat_map(|v| [v.x, v.y, v.z])
+            .collect()
+    }
+
+    /// The kind of every entity.
+    pub fn entity_kinds(&self) -> Vec<String> {
+        self.data
+            .entities
+            .iter()
+            .map(|e| e.kind().to_string())
+            .collect()
+    }
+
+    /// The vertex indices of an entity.
+    pub fn entity_indices(&self, index: usize) -> Option<Vec<u32>> {
+        self.data
+            .entities
+            .get(index)
+            .map(|e| e.indices().iter().map(|i| *i as u32).collect())
+    }
+
+    /// The total length of every entity.
+    pub fn length(&self) -> f64 {
+        self.data.length()
+    }
+
+    /// Discretize an entity into a flat array of XYZ values.
+    pub fn discrete(&self, index: usize, resolution: usize) -> Option<Vec<f64>> {
+        self.data.entities.get(index).map(|e| {
+            e.discrete(&self.data.vertices, resolution)
+                .iter()
+                .flat_map(|v| [v.x, v.y, v.z])
+                .collect()
+        })
+    }
+
+    /// Extrude the closed entities of the path along Z into a mesh.
+    pub fn extrude(&self, height: f64, resolution: usize) -> Result<Mesh, String> {
+        self.data
+            .extrude(height, resolution)
+            .map(|data| Mesh { data })
+            .map_err(|e| e.to_string())
+    }
+
+    /// Revolve the path around the Z axis using X as the radius and Y as the height.
+    pub fn revolve(&self, angle: f64, sections: usize, resolution: usize) -> Result<Mesh, String> {
+        self.data
+            .revolve(angle, sections, resolution)
+            .map(|data| Mesh { data })
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Create a rectangular path centered at the origin.
+#[wasm_bindgen]
+pub fn rectangle(width: f64, height: f64) -> Path {
+    Path {
+        data: core_rectangle(width, height),
+    }
+}
@@ -17,3 +17,189 @@ fn load_mesh() {
 
     assert!(mesh.contains("Trimesh"));
 }
+
+#[wasm_bindgen_test]
+fn load_mesh_auto() {
+    let stl_data = include_bytes!("../../../test/data/unit_cube.STL");
+    assert_eq!(rmesh_wasm::detect_format(stl_data).unwrap(), "stl");
+    let mesh = rmesh_wasm::load_mesh_auto_ex(stl_data).unwrap();
+
+    assert!(mesh.contains("Trimesh"));
+}
+
+#[wasm_bindgen_test]
+fn typed_errors() {
+    let error = rmesh_wasm::load_mesh_ex(b"", "step").unwrap_err();
+    assert!(error.starts_with("UnsupportedFormat: "));
+    let error = rmesh_wasm::load_mesh_ex(b"OFF\n3 1 0\n0 0 0\n", "off").unwrap_err();
+    assert!(error.starts_with("ParseError: "));
+}
+
+#[wasm_bindgen_test]
+fn path_extrude() {
+    let path = rmesh_wasm::rectangle(2.0, 4.0);
+    assert_eq!(path.entity_kinds(), vec!["line".to_string()]);
+    assert_eq!(path.entity_indices(0).unwrap(), vec![0, 1, 2, 3, 0]);
+    assert_eq!(path.discrete(0, 16).unwrap().len(), 15);
+
+    let mesh = path.extrude(3.0, 16).unwrap();
+    assert_eq!(mesh.faces().len(), 36);
+
+    // every edge of the box is sharp so each side gets its own vertices
+    let shaded = mesh.smooth_shaded(0.1);
+    assert_eq!(shaded.vertices().len(), 24 * 3);
+    assert_eq!(shaded.vertex_normals().len(), 24 * 3);
+
+    let mut line = rmesh_wasm::Path::new(&[1.0, 0.0, 0.0, 1.0, 2.0, 0.0]);
+    line.add_entity("line", &[0, 1]).unwrap();
+    assert!(line.add_entity("line", &[0, 7]).is_err());
+    let mesh = line.revolve(std::f64::consts::TAU, 16, 16).unwrap();
+    assert_eq!(mesh.faces().len(), 32 * 3);
+
+    // a cubic bezier approximating a quarter circle
+    let mut curve = rmesh_wasm::Path::new(&[
+        1.0, 0.0, 0.0, 1.0, 0.5523, 0.0, 0.5523, 1.0, 0.0, 0.0, 1.0, 0.0,
+    ]);
+    curve.add_entity("bezier", &[0, 1, 2, 3]).unwrap();
+    assert!((curve.length() - std::f64::consts::FRAC_PI_2).abs() < 1e-3);
+}
+
+#[wasm_bindgen_test]
+fn pack_mesh() {
+    let mesh = rmesh_wasm::rectangle(2.0, 4.0).extrude(3.0, 16).unwrap();
+    for compression in ["none", "lz4", "zstd"] {
+        let packed = mesh.pack(None, compression).unwrap();
+        let unpacked = rmesh_wasm::unpack_mesh(&packed).unwrap();
+        assert_eq!(unpacked.faces(), mesh.faces());
+        assert_eq!(unpacked.vertices(), mesh.vertices());
+    }
+    assert!(mesh.pack(None, "gzip").is_err());
+}
+
+#[wasm_bindgen_test]
+fn pick() {
+    let mesh = rmesh_wasm::rectangle(2.0, 4.0).extrude(3.0, 16).unwrap();
+    let hit = mesh
+        .pick(&[0.5, 0.5, 10.0], &[0.0, 0.0, -1.0])
+        .unwrap()
+        .unwrap();
+    assert!(hit.face() < mesh.faces().len() / 3);
+    assert_eq!(hit.point(), vec![0.5, 0.5, 3.0]);
+    assert_eq!(hit.distance(), 7.0);
+    // the coordinates rebuild the point from the corners of the face
+    let (faces, vertices) = (mesh.faces(), mesh.vertices());
+    let barycentric = hit.barycentric();
+    for axis in 0..3 {
+        let rebuilt: f64 = (0..3)
+            .map(|i| vertices[faces[hit.face() * 3 + i] as usize * 3 + axis] * barycentric[i])
+            .sum();
+        assert!((rebuilt - hit.point()[axis]).abs() < 1e-12);
+    }
+
+    assert!(
+        mesh.pick(&[0.5, 0.5, 10.0], &[0.0, 0.0, 1.0])
+            .unwrap()
+            .is_none()
+    );
+    assert!(mesh.pick(&[0.5, 0.5], &[0.0, 0.0, -1.0]).is_err());
+}
+
+#[wasm_bindgen_test]
+fn render_buffers() {
+    let mesh = rmesh_wasm::rectangle(2.0, 4.0).extrude(3.0, 16).unwrap();
+    let view = mesh.vertices_view();
+    assert_eq!(view.to_vec(), mesh.vertices());
+
+    let buffers = mesh.render_buffers();
+    assert_eq!(buffers.vertex_count(), 8);
+    assert_eq!(buffers.index_count(), 36);
+    assert_eq!(buffers.indices().to_vec(), mesh.faces());
+    let positions: Vec<f64> = buffers
+        .positions()
+        .to_vec()
+        .iter()
+        .map(|v| *v as f64)
+        .collect();
+    assert_eq!(positions, mesh.vertices());
+    assert_eq!(buffers.normals().length(), 24);
+}
+
+#[wasm_bindgen_test]
+fn mesh_loader() {
+    let stl_data = include_bytes!("../../../test/data/unit_cube.STL");
+    let mut loader = rmesh_wasm::MeshLoader::new("stl", Some(stl_data.len())).unwrap();
+    for chunk in stl_data.chunks(100) {
+        loader.push(chunk).unwrap();
+    }
+    assert_eq!(loader.received(), stl_data.len());
+    assert_eq!(loader.progress(), Some(100.0));
+    assert_eq!(loader.finish().unwrap().faces().len(), 36);
+
+    // a file which stops early is a parse error
+    let mut loader = rmesh_wasm::MeshLoader::new("stl", None).unwrap();
+    loader.push(&stl_data[..stl_data.len() - 10]).unwrap();
+    assert_eq!(loader.progress(), None);
+    assert!(loader.finish().unwrap_err().starts_with("ParseError: "));
+    assert!(rmesh_wasm::MeshLoader::new("step", None).is_err());
+}
+
+#[wasm_bindgen_test]
+async fn load_mesh_async() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use wasm_bindgen::prelude::*;
+
+    let reports = Rc::new(RefCell::new(Vec::new()));
+    let recorded = reports.clone();
+    let callback = Closure::<dyn FnMut(f64)>::new(move |p: f64| recorded.borrow_mut().push(p));
+    let stl_data = include_bytes!("../../../test/data/unit_cube.STL");
+    let mesh = rmesh_wasm::load_mesh_async(
+        stl_data.to_vec(),
+        "stl".to_string(),
+        callback
+            .as_ref()
+            .unchecked_ref::<js_sys::Function>()
+            .clone(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(mesh.faces().len(), 36);
+    assert_eq!(*reports.borrow(), vec![0.0, 100.0]);
+}
+
+#[wasm_bindgen_test]
+fn scene_buffers() {
+    let stl_data = include_bytes!("../../../test/data/two_objects_mixed_case_names.stl");
+    let scene = rmesh_wasm::WasmScene::load(stl_data, None).unwrap();
+    assert_eq!(scene.geometry_count(), 2);
+    let names = scene.node_names();
+    assert_eq!(names[0], "world");
+    assert_eq!(scene.node_geometry(1), vec![0]);
+    let identity = scene.world_transform(1).unwrap().to_vec();
+    assert_eq!(identity.len(), 16);
+    assert_eq!(identity[0], 1.0);
+    assert_eq!(identity[12], 0.0);
+    assert!(scene.world_transform(99).is_none());
+
+    assert_eq!(scene.instance_transforms(0).length(), 16);
+    assert_eq!(scene.instance_nodes(0), vec![1]);
+    let buffers = scene.render_buffers(0).unwrap();
+    let interleaved = buffers.interleaved();
+    assert_eq!(interleaved.len(), buffers.vertex_count() * 6);
+    assert_eq!(interleaved[..3], buffers.positions().to_vec()[..3]);
+    assert_eq!(interleaved[3..6], buffers.normals().to_vec()[..3]);
+    assert_eq!(scene.mesh(0).unwrap().faces().len(), buffers.index_count());
+    assert!(scene.render_buffers(2).is_none());
+
+    let error = rmesh_wasm::WasmScene::load(b"glTF", None).err().unwrap();
+    assert!(error.starts_with("UnsupportedFormat: "));
+
+    // a copy of a geometry keeps its face colors
+    let threemf_data = include_bytes!("../../../test/data/multi_object.3mf");
+    let scene = rmesh_wasm::WasmScene::load(threemf_data, None).unwrap();
+    let mesh = scene.mesh(0).unwrap();
+    assert_eq!(
+        mesh.face_colors().unwrap().len(),
+        mesh.faces().len() / 3 * 4
+    );
+}